@@ -2,8 +2,21 @@
 // For anime/manga metadata
 
 use anyhow::Result;
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 
+use crate::api::cache::{Fetched, RateLimit, TtlCache, LOOKUP_TTL, SEARCH_TTL};
+
+static RATE_LIMIT: RateLimit = RateLimit::new("AniList");
+
+/// Keyed by (media type, lowercased query), holds the whole first page
+pub(super) static SEARCH_CACHE: Lazy<TtlCache<(&'static str, String), Vec<AniListMedia>>> =
+    Lazy::new(|| TtlCache::new("AniList search", 500, SEARCH_TTL));
+
+/// Keyed by (media type, id), also filled from search results
+pub(super) static MEDIA_CACHE: Lazy<TtlCache<(&'static str, i32), Option<AniListMedia>>> =
+    Lazy::new(|| TtlCache::new("AniList media", 2000, LOOKUP_TTL));
+
 /// Media type for AniList
 #[derive(Debug, Clone, Copy)]
 pub enum MediaType {
@@ -29,6 +42,15 @@ pub struct AniListMedia {
     pub title_romaji: Option<String>,
     pub image: Option<String>,
     pub url: String,
+    /// Release status such as "RELEASING" or "FINISHED"
+    pub status: Option<String>,
+}
+
+impl AniListMedia {
+    /// Still airing (or still being published)
+    pub fn is_releasing(&self) -> bool {
+        self.status.as_deref() == Some("RELEASING")
+    }
 }
 
 /// Search for media on AniList
@@ -38,6 +60,19 @@ pub async fn search_media(
     media_type: MediaType,
     limit: usize,
 ) -> Result<Vec<AniListMedia>> {
+    let key = (media_type.as_str(), query.trim().to_lowercase());
+    let results = SEARCH_CACHE
+        .get_or_fetch(key, &RATE_LIMIT, || fetch_search(client, query, media_type))
+        .await?
+        .unwrap_or_default();
+    Ok(results.into_iter().take(limit).collect())
+}
+
+async fn fetch_search(
+    client: &reqwest::Client,
+    query: &str,
+    media_type: MediaType,
+) -> Result<Fetched<Vec<AniListMedia>>> {
     let graphql_query = r#"
         query ($search: String, $type: MediaType) {
             Page(perPage: 25) {
@@ -52,6 +87,7 @@ pub async fn search_media(
                         large
                     }
                     siteUrl
+                    status
                 }
             }
         }
@@ -71,21 +107,24 @@ pub async fn search_media(
         .send()
         .await?;
 
+    if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        RATE_LIMIT.trip(response.headers());
+        return Ok(Fetched::Unavailable);
+    }
     if !response.status().is_success() {
         let status = response.status();
         let body = response.text().await.unwrap_or_default();
         eprintln!("ERROR: AniList API error: status={}, body={}", status, body);
-        return Ok(vec![]);
+        return Ok(Fetched::Unavailable);
     }
 
     let data: AniListResponse = response.json().await?;
 
-    let results = data
+    let results: Vec<AniListMedia> = data
         .data
         .page
         .media
         .into_iter()
-        .take(limit)
         .map(|m| AniListMedia {
             id: m.id,
             title: m
@@ -97,10 +136,18 @@ pub async fn search_media(
             title_romaji: m.title.romaji,
             image: m.cover_image.map(|c| c.large),
             url: m.site_url,
+            status: m.status,
         })
         .collect();
 
-    Ok(results)
+    // Picking a search result then logs it by id, which needs no second request
+    for media in &results {
+        MEDIA_CACHE
+            .insert((media_type.as_str(), media.id), Some(media.clone()))
+            .await;
+    }
+
+    Ok(Fetched::Value(results))
 }
 
 /// Get media info by ID
@@ -109,6 +156,19 @@ pub async fn get_media_by_id(
     id: i32,
     media_type: MediaType,
 ) -> Result<Option<AniListMedia>> {
+    let media = MEDIA_CACHE
+        .get_or_fetch((media_type.as_str(), id), &RATE_LIMIT, || {
+            fetch_media(client, id, media_type)
+        })
+        .await?;
+    Ok(media.flatten())
+}
+
+async fn fetch_media(
+    client: &reqwest::Client,
+    id: i32,
+    media_type: MediaType,
+) -> Result<Fetched<Option<AniListMedia>>> {
     let graphql_query = r#"
         query ($id: Int, $type: MediaType) {
             Media(id: $id, type: $type) {
@@ -122,6 +182,7 @@ pub async fn get_media_by_id(
                     large
                 }
                 siteUrl
+                status
             }
         }
     "#;
@@ -140,14 +201,18 @@ pub async fn get_media_by_id(
         .send()
         .await?;
 
+    if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        RATE_LIMIT.trip(response.headers());
+        return Ok(Fetched::Unavailable);
+    }
     if !response.status().is_success() {
-        return Ok(None);
+        return Ok(Fetched::Unavailable);
     }
 
     let data: AniListSingleResponse = response.json().await?;
 
     if let Some(m) = data.data.media {
-        Ok(Some(AniListMedia {
+        Ok(Fetched::Value(Some(AniListMedia {
             id: m.id,
             title: m
                 .title
@@ -158,9 +223,10 @@ pub async fn get_media_by_id(
             title_romaji: m.title.romaji,
             image: m.cover_image.map(|c| c.large),
             url: m.site_url,
-        }))
+            status: m.status,
+        })))
     } else {
-        Ok(None)
+        Ok(Fetched::Value(None))
     }
 }
 
@@ -206,6 +272,8 @@ struct AniListMediaItem {
     cover_image: Option<AniListCoverImage>,
     #[serde(rename = "siteUrl")]
     site_url: String,
+    #[serde(default)]
+    status: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -219,3 +287,30 @@ struct AniListTitle {
 struct AniListCoverImage {
     large: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_media_item_status_is_optional() {
+        let with_status: AniListMediaItem = serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "title": { "romaji": "Frieren" },
+            "coverImage": { "large": "https://example.com/a.png" },
+            "siteUrl": "https://anilist.co/anime/1",
+            "status": "RELEASING"
+        }))
+        .unwrap();
+        assert_eq!(with_status.status.as_deref(), Some("RELEASING"));
+
+        let without: AniListMediaItem = serde_json::from_value(serde_json::json!({
+            "id": 2,
+            "title": { "romaji": "Older" },
+            "coverImage": null,
+            "siteUrl": "https://anilist.co/anime/2"
+        }))
+        .unwrap();
+        assert!(without.status.is_none());
+    }
+}
@@ -0,0 +1,386 @@
+// In-process cache for metadata API responses
+// Autocomplete fires a search per keystroke, so AniList and VNDB answers are kept for a while
+// and served stale while an API is rate limiting us
+
+use lru::LruCache;
+use std::future::Future;
+use std::hash::Hash;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+/// How long search results stay fresh
+pub const SEARCH_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// How long id lookups stay fresh
+pub const LOOKUP_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Backoff when a 429 comes without a usable Retry-After header
+const DEFAULT_BACKOFF: Duration = Duration::from_secs(60);
+
+const STATS_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Result of a cache read
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lookup<V> {
+    Fresh(V),
+    /// Past its TTL, only worth serving when the API can't be asked
+    Stale(V),
+    Missing,
+}
+
+/// What an API call produced
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fetched<V> {
+    Value(V),
+    /// Rate limited or an error status, nothing worth caching
+    Unavailable,
+}
+
+struct Entry<V> {
+    value: V,
+    stored_at: Instant,
+}
+
+/// LRU cache whose entries go stale after a TTL, with hit/miss counters
+pub struct TtlCache<K: Hash + Eq, V: Clone> {
+    name: &'static str,
+    ttl: Duration,
+    entries: Mutex<LruCache<K, Entry<V>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    stale_served: AtomicU64,
+}
+
+impl<K: Hash + Eq, V: Clone> TtlCache<K, V> {
+    pub fn new(name: &'static str, capacity: usize, ttl: Duration) -> Self {
+        Self {
+            name,
+            ttl,
+            entries: Mutex::new(LruCache::new(
+                NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN),
+            )),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            stale_served: AtomicU64::new(0),
+        }
+    }
+
+    pub async fn get(&self, key: &K) -> Lookup<V> {
+        self.get_at(key, Instant::now()).await
+    }
+
+    async fn get_at(&self, key: &K, now: Instant) -> Lookup<V> {
+        let lookup = match self.entries.lock().await.get(key) {
+            Some(entry) if now.duration_since(entry.stored_at) < self.ttl => {
+                Lookup::Fresh(entry.value.clone())
+            }
+            Some(entry) => Lookup::Stale(entry.value.clone()),
+            None => Lookup::Missing,
+        };
+        let counter = match lookup {
+            Lookup::Fresh(_) => &self.hits,
+            _ => &self.misses,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        lookup
+    }
+
+    pub async fn insert(&self, key: K, value: V) {
+        self.insert_at(key, value, Instant::now()).await
+    }
+
+    async fn insert_at(&self, key: K, value: V, now: Instant) {
+        self.entries.lock().await.put(
+            key,
+            Entry {
+                value,
+                stored_at: now,
+            },
+        );
+    }
+
+    /// Cached value when fresh, otherwise `fetch` unless the API is backing off.
+    /// A stale value covers for an unavailable or failing API; `None` when there is nothing.
+    pub async fn get_or_fetch<Fut>(
+        &self,
+        key: K,
+        rate_limit: &RateLimit,
+        fetch: impl FnOnce() -> Fut,
+    ) -> anyhow::Result<Option<V>>
+    where
+        Fut: Future<Output = anyhow::Result<Fetched<V>>>,
+    {
+        let stale = match self.get(&key).await {
+            Lookup::Fresh(value) => return Ok(Some(value)),
+            Lookup::Stale(value) => Some(value),
+            Lookup::Missing => None,
+        };
+
+        let fetched = if rate_limit.is_limited() {
+            debug!("{} is backing off, not calling it", rate_limit.name);
+            Ok(Fetched::Unavailable)
+        } else {
+            fetch().await
+        };
+
+        match fetched {
+            Ok(Fetched::Value(value)) => {
+                self.insert(key, value.clone()).await;
+                Ok(Some(value))
+            }
+            Ok(Fetched::Unavailable) => Ok(self.serve_stale(stale)),
+            Err(e) if stale.is_some() => {
+                debug!("{} request failed, serving stale cache: {:?}", self.name, e);
+                Ok(self.serve_stale(stale))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn serve_stale(&self, stale: Option<V>) -> Option<V> {
+        if stale.is_some() {
+            self.stale_served.fetch_add(1, Ordering::Relaxed);
+        }
+        stale
+    }
+}
+
+/// A cache whose counters go into the hourly cache log
+pub trait CacheCounters: Sync {
+    /// Counters since the last call, reset afterwards
+    fn take_stats(&self) -> CacheStats;
+}
+
+impl<K: Hash + Eq + Send, V: Clone + Send> CacheCounters for TtlCache<K, V> {
+    fn take_stats(&self) -> CacheStats {
+        CacheStats {
+            name: self.name,
+            hits: self.hits.swap(0, Ordering::Relaxed),
+            misses: self.misses.swap(0, Ordering::Relaxed),
+            stale_served: self.stale_served.swap(0, Ordering::Relaxed),
+        }
+    }
+}
+
+/// Counters of one cache over a stats interval
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheStats {
+    pub name: &'static str,
+    pub hits: u64,
+    pub misses: u64,
+    pub stale_served: u64,
+}
+
+impl std::fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} cache: {} hits, {} misses, {} stale served",
+            self.name, self.hits, self.misses, self.stale_served
+        )
+    }
+}
+
+/// Remembers a 429 so requests stop until the API is ready again
+pub struct RateLimit {
+    name: &'static str,
+    until: std::sync::Mutex<Option<Instant>>,
+}
+
+impl RateLimit {
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            until: std::sync::Mutex::new(None),
+        }
+    }
+
+    /// Whether we are still backing off
+    pub fn is_limited(&self) -> bool {
+        self.is_limited_at(Instant::now())
+    }
+
+    fn is_limited_at(&self, now: Instant) -> bool {
+        let until = self.until.lock().unwrap_or_else(|e| e.into_inner());
+        until.is_some_and(|until| now < until)
+    }
+
+    /// Start backing off after a 429 response
+    pub fn trip(&self, headers: &reqwest::header::HeaderMap) {
+        self.trip_at(retry_after(headers), Instant::now());
+    }
+
+    fn trip_at(&self, backoff: Duration, now: Instant) {
+        let mut until = self.until.lock().unwrap_or_else(|e| e.into_inner());
+        // Only the first 429 of a burst is worth a warning
+        if !until.is_some_and(|until| now < until) {
+            warn!(
+                "{} rate limited us, backing off for {}s",
+                self.name,
+                backoff.as_secs()
+            );
+        }
+        *until = Some(now + backoff);
+    }
+}
+
+/// Seconds from the Retry-After header, `DEFAULT_BACKOFF` when missing or not a number
+fn retry_after(headers: &reqwest::header::HeaderMap) -> Duration {
+    headers
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_BACKOFF)
+}
+
+/// Log the metadata cache counters every hour
+pub fn spawn_stats_logger() {
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(STATS_INTERVAL);
+        // The first tick completes immediately
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let caches: [&dyn CacheCounters; 7] = [
+                &*super::anilist::SEARCH_CACHE,
+                &*super::anilist::MEDIA_CACHE,
+                &*super::vndb::SEARCH_CACHE,
+                &*super::vndb::VN_CACHE,
+                &*super::jisho::SEARCH_CACHE,
+                &*super::jpdb::KNOWN_WORDS_CACHE,
+                &*super::tatoeba::SENTENCE_CACHE,
+            ];
+            for cache in caches {
+                debug!("{}", cache.take_stats());
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+
+    #[tokio::test]
+    async fn test_entries_go_stale_after_ttl() {
+        let cache: TtlCache<&str, u32> = TtlCache::new("test", 2, Duration::from_secs(60));
+        let start = Instant::now();
+
+        assert_eq!(cache.get_at(&"a", start).await, Lookup::Missing);
+        cache.insert_at("a", 1, start).await;
+        assert_eq!(
+            cache.get_at(&"a", start + Duration::from_secs(59)).await,
+            Lookup::Fresh(1)
+        );
+        assert_eq!(
+            cache.get_at(&"a", start + Duration::from_secs(60)).await,
+            Lookup::Stale(1)
+        );
+
+        let stats = cache.take_stats();
+        assert_eq!((stats.hits, stats.misses, stats.stale_served), (1, 2, 0));
+        assert_eq!(
+            stats.to_string(),
+            "test cache: 1 hits, 2 misses, 0 stale served"
+        );
+        // Counters restart for the next interval
+        assert_eq!(cache.take_stats().hits, 0);
+    }
+
+    #[tokio::test]
+    async fn test_get_or_fetch_serves_stale_when_unavailable() {
+        // Zero TTL: everything cached is immediately stale
+        let cache: TtlCache<&str, u32> = TtlCache::new("test", 10, Duration::ZERO);
+        let limit = RateLimit::new("test");
+
+        let fetched = cache
+            .get_or_fetch("a", &limit, || async { Ok(Fetched::Value(1)) })
+            .await
+            .unwrap();
+        assert_eq!(fetched, Some(1));
+
+        let unavailable = cache
+            .get_or_fetch("a", &limit, || async { Ok(Fetched::Unavailable) })
+            .await
+            .unwrap();
+        assert_eq!(unavailable, Some(1));
+
+        let failed = cache
+            .get_or_fetch("a", &limit, || async { Err(anyhow::anyhow!("timeout")) })
+            .await
+            .unwrap();
+        assert_eq!(failed, Some(1));
+        assert_eq!(cache.take_stats().stale_served, 2);
+
+        // Nothing cached: unavailable is empty, errors surface
+        let missing = cache
+            .get_or_fetch("b", &limit, || async { Ok(Fetched::Unavailable) })
+            .await
+            .unwrap();
+        assert_eq!(missing, None);
+        assert!(cache
+            .get_or_fetch("b", &limit, || async { Err(anyhow::anyhow!("timeout")) })
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_backing_off_skips_the_api() {
+        let cache: TtlCache<&str, u32> = TtlCache::new("test", 10, Duration::from_secs(60));
+        let limit = RateLimit::new("test");
+        limit.trip_at(Duration::from_secs(60), Instant::now());
+
+        let result = cache
+            .get_or_fetch("a", &limit, || async {
+                panic!("the API must not be called while backing off")
+            })
+            .await
+            .unwrap();
+        assert_eq!(result, None);
+    }
+
+    #[tokio::test]
+    async fn test_least_recently_used_entry_is_evicted() {
+        let cache: TtlCache<&str, u32> = TtlCache::new("test", 2, Duration::from_secs(60));
+        let now = Instant::now();
+        cache.insert_at("a", 1, now).await;
+        cache.insert_at("b", 2, now).await;
+        cache.get_at(&"a", now).await;
+        cache.insert_at("c", 3, now).await;
+
+        assert_eq!(cache.get_at(&"a", now).await, Lookup::Fresh(1));
+        assert_eq!(cache.get_at(&"b", now).await, Lookup::Missing);
+    }
+
+    #[test]
+    fn test_rate_limit_backoff() {
+        let limit = RateLimit::new("test");
+        let now = Instant::now();
+        assert!(!limit.is_limited_at(now));
+
+        limit.trip_at(Duration::from_secs(30), now);
+        assert!(limit.is_limited_at(now + Duration::from_secs(29)));
+        assert!(!limit.is_limited_at(now + Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_retry_after_header() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), DEFAULT_BACKOFF);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("12"));
+        assert_eq!(retry_after(&headers), Duration::from_secs(12));
+
+        // HTTP dates aren't worth parsing here
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(retry_after(&headers), DEFAULT_BACKOFF);
+    }
+}
@@ -2,14 +2,20 @@
 // Using service account JWT authentication
 
 use anyhow::{anyhow, Result};
+use futures::future::BoxFuture;
 use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::future::Future;
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
-use tokio::sync::RwLock;
-use tracing::{debug, error};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{Mutex, OwnedMutexGuard, RwLock};
+use tracing::{debug, error, warn};
+
+use super::storage::Storage;
+use crate::models::user::UserDoc;
+use crate::utils::metrics::{self, Kind};
 
 /// Firebase service account credentials
 #[derive(Debug, Clone, Deserialize)]
@@ -36,6 +42,10 @@ struct CachedToken {
     expires_at: u64,
 }
 
+/// Field path that orders a query by document ID. As a cursor value it takes
+/// `{ "referenceValue": "<document id>" }`, the full document name is filled in.
+pub const DOCUMENT_ID: &str = "__name__";
+
 /// Filter for structured queries
 #[derive(Debug, Clone)]
 pub struct QueryFilter {
@@ -58,6 +68,24 @@ impl QueryFilter {
         }
     }
 
+    /// Create a >= filter with a string value (e.g. "YYYY-MM-DD" dates)
+    pub fn string_gte(field: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            op: "GREATER_THAN_OR_EQUAL".to_string(),
+            value: json!({ "stringValue": value.into() }),
+        }
+    }
+
+    /// Create a < filter with a string value
+    pub fn string_lt(field: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            op: "LESS_THAN".to_string(),
+            value: json!({ "stringValue": value.into() }),
+        }
+    }
+
     /// Create a >= filter with a timestamp value (RFC3339 string)
     pub fn timestamp_gte(field: impl Into<String>, rfc3339: impl Into<String>) -> Self {
         Self {
@@ -78,13 +106,194 @@ pub enum TransactionWrite {
         document_path: String,
         fields: Value,
     },
+    /// Update only the given field paths (dotted, see `field_path`), leaving the
+    /// other fields of the same maps untouched
+    UpdatePaths {
+        document_path: String,
+        fields: Value,
+        field_paths: Vec<String>,
+    },
+    /// Create a document, failing with ALREADY_EXISTS if it is already there
+    /// (precondition `currentDocument.exists: false`)
+    Create {
+        document_path: String,
+        fields: Value,
+    },
+    /// `UpdatePaths`, then add to number fields on the server (`increment` transforms),
+    /// so writers that read the same document don't overwrite each other's counts.
+    /// A missing field counts as 0. The incremented paths must not be in `field_paths`.
+    UpdateWithTransforms {
+        document_path: String,
+        fields: Value,
+        field_paths: Vec<String>,
+        /// Dotted field path and the number added to it
+        increments: Vec<(String, Value)>,
+    },
+}
+
+/// Returned by `commit_transaction` when Firestore aborted the commit because of contention.
+/// The whole transaction (read + commit) can be retried.
+#[derive(Debug, thiserror::Error)]
+#[error("Firebase transaction conflict: {0}")]
+pub struct TransactionConflict(pub reqwest::StatusCode);
+
+/// Returned by `commit_transaction` when a `TransactionWrite::Create` target already exists
+#[derive(Debug, thiserror::Error)]
+#[error("Firebase document already exists")]
+pub struct DocumentAlreadyExists;
+
+/// Whether an error is a retryable transaction conflict
+pub fn is_transaction_conflict(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<TransactionConflict>().is_some()
+}
+
+/// Whether a create precondition failed because the document exists
+pub fn is_already_exists(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<DocumentAlreadyExists>().is_some()
+}
+
+/// Whether a request failed in a way where it may still have been applied server-side
+pub fn is_timeout(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<reqwest::Error>()
+        .is_some_and(|e| e.is_timeout())
+}
+
+/// Whether an immersion log document is in the trash
+pub fn is_soft_deleted(doc: &Value) -> bool {
+    doc.get("deleted").and_then(|v| v.as_bool()) == Some(true)
+}
+
+/// Attempts per request, the first one included
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Per-request timeout; the shared reqwest client has none
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Backoff before the first retry, doubled for each one after
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(250);
+
+/// Documents per page when listing a collection, the most Firestore hands out at once
+const LIST_PAGE_SIZE: usize = 300;
+
+/// When a failed request may be sent again
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Retry {
+    /// Idempotent requests: on 429, 5xx and transport errors
+    Always,
+    /// Requests that must not run twice: only when the connection failed before sending
+    IfNotSent,
+}
+
+impl Retry {
+    fn on_status(self, status: StatusCode) -> bool {
+        self == Retry::Always
+            && (status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error())
+    }
+
+    fn on_error(self, e: &reqwest::Error) -> bool {
+        match self {
+            Retry::Always => !e.is_builder(),
+            Retry::IfNotSent => e.is_connect(),
+        }
+    }
+}
+
+/// Exponential backoff for the retry after `attempt`, `jitter` in [0, 1) adds up to half again
+fn retry_delay(attempt: u32, jitter: f64) -> Duration {
+    let delay = RETRY_BASE_DELAY * 2u32.pow(attempt.saturating_sub(1));
+    delay + delay.mul_f64(jitter.clamp(0.0, 1.0) / 2.0)
+}
+
+/// Generate a Firestore-style auto id (20 alphanumeric characters)
+pub fn generate_document_id() -> String {
+    use rand::distr::{Alphanumeric, SampleString};
+    Alphanumeric.sample_string(&mut rand::rng(), 20)
+}
+
+/// Google access tokens live an hour
+const TOKEN_LIFETIME_SECS: u64 = 3600;
+
+/// Tokens this close to expiring aren't handed out anymore
+const TOKEN_EXPIRY_BUFFER_SECS: u64 = 60;
+
+/// Tokens this close to expiring are refreshed in the background
+const TOKEN_EARLY_REFRESH_SECS: u64 = 5 * 60;
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// The access token shared by all requests. Only one task refreshes it at a time,
+/// the others wait for that refresh instead of running their own.
+#[derive(Default)]
+struct TokenCache {
+    token: RwLock<Option<CachedToken>>,
+    refresh: Arc<Mutex<()>>,
+}
+
+impl TokenCache {
+    async fn valid(&self, now: u64) -> Option<String> {
+        self.token
+            .read()
+            .await
+            .as_ref()
+            .filter(|cached| cached.expires_at > now + TOKEN_EXPIRY_BUFFER_SECS)
+            .map(|cached| cached.token.clone())
+    }
+
+    async fn store(&self, token: String, now: u64) {
+        *self.token.write().await = Some(CachedToken {
+            token,
+            expires_at: now + TOKEN_LIFETIME_SECS,
+        });
+    }
+
+    /// Cached token, or one from `fetch` shared with every caller waiting meanwhile
+    async fn get_or_refresh<Fut>(&self, now: u64, fetch: impl FnOnce() -> Fut) -> Result<String>
+    where
+        Fut: Future<Output = Result<String>>,
+    {
+        if let Some(token) = self.valid(now).await {
+            return Ok(token);
+        }
+
+        let _refreshing = self.refresh.lock().await;
+        // Whoever held the lock before us may have refreshed it already
+        if let Some(token) = self.valid(now).await {
+            return Ok(token);
+        }
+        let token = fetch().await?;
+        self.store(token.clone(), now).await;
+        Ok(token)
+    }
+
+    async fn expires_at(&self) -> Option<u64> {
+        self.token
+            .read()
+            .await
+            .as_ref()
+            .map(|cached| cached.expires_at)
+    }
+
+    /// The refresh lock, when the token is still usable but about to expire
+    /// and nobody is refreshing it yet
+    async fn claim_early_refresh(&self, now: u64) -> Option<OwnedMutexGuard<()>> {
+        let expires_at = self.token.read().await.as_ref()?.expires_at;
+        if expires_at > now + TOKEN_EARLY_REFRESH_SECS {
+            return None;
+        }
+        Arc::clone(&self.refresh).try_lock_owned().ok()
+    }
 }
 
 /// Firebase REST API client
 pub struct FirebaseClient {
     client: Client,
     service_account: ServiceAccount,
-    token_cache: Arc<RwLock<Option<CachedToken>>>,
+    tokens: Arc<TokenCache>,
 }
 
 impl FirebaseClient {
@@ -96,104 +305,37 @@ impl FirebaseClient {
         Ok(Self {
             client,
             service_account,
-            token_cache: Arc::new(RwLock::new(None)),
+            tokens: Arc::default(),
         })
     }
 
     /// Get access token (with caching)
     async fn get_access_token(&self) -> Result<String> {
-        // Check cache first
-        {
-            let cache = self.token_cache.read().await;
-            if let Some(cached) = cache.as_ref() {
-                let now = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs();
-                // Return cached token if still valid (with 60s buffer)
-                if cached.expires_at > now + 60 {
-                    return Ok(cached.token.clone());
-                }
-            }
-        }
-
-        // Generate new token
-        let token = self.generate_access_token().await?;
+        let now = unix_now();
+        let token = self
+            .tokens
+            .get_or_refresh(now, || {
+                generate_access_token(&self.client, &self.service_account)
+            })
+            .await?;
 
-        // Cache it
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        {
-            let mut cache = self.token_cache.write().await;
-            *cache = Some(CachedToken {
-                token: token.clone(),
-                expires_at: now + 3600, // 1 hour
+        // Refresh ahead of expiry so commands rarely wait for the exchange
+        if let Some(refreshing) = self.tokens.claim_early_refresh(now).await {
+            let client = self.client.clone();
+            let account = self.service_account.clone();
+            let tokens = Arc::clone(&self.tokens);
+            tokio::spawn(async move {
+                let _refreshing = refreshing;
+                match generate_access_token(&client, &account).await {
+                    Ok(token) => tokens.store(token, unix_now()).await,
+                    Err(e) => warn!("Background Firebase token refresh failed: {:?}", e),
+                }
             });
         }
 
         Ok(token)
     }
 
-    /// Generate a new access token using JWT
-    async fn generate_access_token(&self) -> Result<String> {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-
-        let claims = Claims {
-            iss: self.service_account.client_email.clone(),
-            sub: self.service_account.client_email.clone(),
-            aud: "https://oauth2.googleapis.com/token".to_string(),
-            iat: now,
-            exp: now + 3600,
-            scope: "https://www.googleapis.com/auth/datastore".to_string(),
-        };
-
-        // Encode JWT
-        // Support both normal PEM newlines and escaped "\\n" format from some deploy setups.
-        let key = match EncodingKey::from_rsa_pem(self.service_account.private_key.as_bytes()) {
-            Ok(key) => key,
-            Err(original_err) => {
-                let normalized_private_key = self
-                    .service_account
-                    .private_key
-                    .replace("\\r\\n", "\n")
-                    .replace("\\n", "\n");
-
-                EncodingKey::from_rsa_pem(normalized_private_key.as_bytes())
-                    .map_err(|_| anyhow!("Invalid Firebase private_key format: {}", original_err))?
-            }
-        };
-        let jwt = encode(&Header::new(Algorithm::RS256), &claims, &key)?;
-
-        // Exchange JWT for access token
-        let response = self
-            .client
-            .post("https://oauth2.googleapis.com/token")
-            .form(&[
-                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
-                ("assertion", &jwt),
-            ])
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            let body = response.text().await?;
-            error!("Failed to get access token: {}", body);
-            return Err(anyhow!("Failed to get access token"));
-        }
-
-        let data: Value = response.json().await?;
-        let token = data["access_token"]
-            .as_str()
-            .ok_or_else(|| anyhow!("No access_token in response"))?;
-
-        Ok(token.to_string())
-    }
-
     /// Base URL for Firestore REST API
     fn base_url(&self) -> String {
         format!(
@@ -202,12 +344,42 @@ impl FirebaseClient {
         )
     }
 
+    /// Send a request, timing it under `operation` and its final status
+    async fn send(
+        &self,
+        operation: &'static str,
+        retry: Retry,
+        request: impl Fn() -> RequestBuilder,
+    ) -> Result<Response> {
+        let started = Instant::now();
+        let result = send_with_retry(retry, request).await;
+        let (status, ok) = match &result {
+            Ok(response) => (
+                response.status().as_u16().to_string(),
+                // A missing document is an answer, not a failure
+                response.status().is_success() || response.status() == StatusCode::NOT_FOUND,
+            ),
+            Err(_) => ("error".to_string(), false),
+        };
+        metrics::record(
+            Kind::Firestore,
+            &format!("{} {}", operation, status),
+            started.elapsed(),
+            ok,
+        );
+        result
+    }
+
     /// Get a document by path
-    pub async fn get_document(&self, collection: &str, doc_id: &str) -> Result<Option<Value>> {
+    async fn get_document(&self, collection: &str, doc_id: &str) -> Result<Option<Value>> {
         let token = self.get_access_token().await?;
         let url = format!("{}/{}/{}", self.base_url(), collection, doc_id);
 
-        let response = self.client.get(&url).bearer_auth(&token).send().await?;
+        let response = self
+            .send("get_document", Retry::Always, || {
+                self.client.get(&url).bearer_auth(&token)
+            })
+            .await?;
 
         if response.status() == 404 {
             return Ok(None);
@@ -225,36 +397,59 @@ impl FirebaseClient {
     }
 
     /// Set/update a document (merge)
-    pub async fn set_document(&self, collection: &str, doc_id: &str, data: &Value) -> Result<()> {
-        let token = self.get_access_token().await?;
-
-        // Build updateMask from top-level field names
-        let field_paths: String = data
+    /// Top-level fields are replaced whole, nested maps included
+    async fn set_document(&self, collection: &str, doc_id: &str, data: &Value) -> Result<()> {
+        let field_paths: Vec<String> = data
             .as_object()
-            .map(|obj| {
-                obj.keys()
-                    .map(|k| format!("updateMask.fieldPaths={}", k))
-                    .collect::<Vec<_>>()
-                    .join("&")
-            })
+            .map(|obj| obj.keys().map(|k| field_path(&[k])).collect())
             .unwrap_or_default();
+        self.patch_document(collection, doc_id, data, &field_paths)
+            .await
+    }
+
+    /// Update only the given dotted field paths (e.g. `stats.anime.total`), taking
+    /// their values from `data`. A path missing from `data` deletes that field.
+    async fn set_document_merge_paths(
+        &self,
+        collection: &str,
+        doc_id: &str,
+        data: &Value,
+        field_paths: &[&str],
+    ) -> Result<()> {
+        let field_paths: Vec<String> = field_paths
+            .iter()
+            .map(|p| normalize_field_path(p))
+            .collect();
+        self.patch_document(collection, doc_id, data, &field_paths)
+            .await
+    }
+
+    async fn patch_document(
+        &self,
+        collection: &str,
+        doc_id: &str,
+        data: &Value,
+        field_paths: &[String],
+    ) -> Result<()> {
+        let token = self.get_access_token().await?;
 
         let url = format!(
             "{}/{}/{}?{}",
             self.base_url(),
             collection,
             doc_id,
-            field_paths
+            update_mask_query(field_paths)
         );
 
         let firestore_doc = to_firestore_document(data);
 
         let response = self
-            .client
-            .patch(&url)
-            .bearer_auth(&token)
-            .json(&firestore_doc)
-            .send()
+            .send("patch_document", Retry::Always, || {
+                self.client
+                    .patch(&url)
+                    .bearer_auth(&token)
+                    .json(&firestore_doc)
+            })
             .await?;
 
         if !response.status().is_success() {
@@ -268,7 +463,7 @@ impl FirebaseClient {
     }
 
     /// Add a document to a subcollection
-    pub async fn add_to_subcollection(
+    async fn add_to_subcollection(
         &self,
         collection: &str,
         doc_id: &str,
@@ -287,11 +482,12 @@ impl FirebaseClient {
         let firestore_doc = to_firestore_document(data);
 
         let response = self
-            .client
-            .post(&url)
-            .bearer_auth(&token)
-            .json(&firestore_doc)
-            .send()
+            .send("add_to_subcollection", Retry::IfNotSent, || {
+                self.client
+                    .post(&url)
+                    .bearer_auth(&token)
+                    .json(&firestore_doc)
+            })
             .await?;
 
         if !response.status().is_success() {
@@ -303,95 +499,89 @@ impl FirebaseClient {
 
         let result: Value = response.json().await?;
         let name = result["name"].as_str().unwrap_or("");
-        let id = name.split('/').last().unwrap_or("");
+        let id = name.split('/').next_back().unwrap_or("");
         Ok(id.to_string())
     }
 
-    /// Query a subcollection - returns just the data
-    pub async fn query_subcollection(
-        &self,
-        collection: &str,
-        doc_id: &str,
-        subcollection: &str,
-    ) -> Result<Vec<Value>> {
-        let docs = self
-            .query_subcollection_with_ids(collection, doc_id, subcollection)
-            .await?;
-        Ok(docs.into_iter().map(|(_, v)| v).collect())
-    }
-
     /// Query a subcollection with filters - returns (id, data) tuples
     /// Handles pagination to fetch ALL documents
-    pub async fn query_subcollection_with_ids(
+    async fn query_subcollection_with_ids(
         &self,
         collection: &str,
         doc_id: &str,
         subcollection: &str,
     ) -> Result<Vec<(String, Value)>> {
-        let token = self.get_access_token().await?;
-        let base_url = format!(
+        let url = format!(
             "{}/{}/{}/{}",
             self.base_url(),
             collection,
             doc_id,
             subcollection
         );
+        self.list_documents("query_subcollection", &url, None).await
+    }
 
-        let mut all_docs = Vec::new();
-        let mut page_token: Option<String> = None;
-
-        loop {
-            let mut url = format!("{}?pageSize=300", base_url);
-            if let Some(ref t) = page_token {
-                url.push_str(&format!("&pageToken={}", t));
-            }
+    /// Every document of a top-level collection as (id, data), following pages like
+    /// `query_subcollection_with_ids`. `fields` limits each document to those field paths.
+    async fn list_collection(
+        &self,
+        collection: &str,
+        fields: Option<&[&str]>,
+    ) -> Result<Vec<(String, Value)>> {
+        let url = format!("{}/{}", self.base_url(), collection);
+        self.list_documents("list_collection", &url, fields).await
+    }
 
-            let response = self.client.get(&url).bearer_auth(&token).send().await?;
+    /// All pages of a collection listing at `base_url`
+    async fn list_documents(
+        &self,
+        operation: &'static str,
+        base_url: &str,
+        fields: Option<&[&str]>,
+    ) -> Result<Vec<(String, Value)>> {
+        let token = self.get_access_token().await?;
+        let mask = fields.map(|fields| {
+            mask_query(
+                &fields
+                    .iter()
+                    .map(|f| normalize_field_path(f))
+                    .collect::<Vec<_>>(),
+            )
+        });
 
-            if !response.status().is_success() {
-                let status = response.status();
-                let body = response.text().await?;
-                debug!("Firebase error: {}", body);
-                return Err(anyhow!("Firebase error: {}", status));
-            }
+        collect_pages(|page_token| {
+            let url = list_page_url(base_url, mask.as_deref(), page_token.as_deref());
+            let token = &token;
+            async move {
+                let response = self
+                    .send(operation, Retry::Always, || {
+                        self.client.get(&url).bearer_auth(token)
+                    })
+                    .await?;
 
-            let result: Value = response.json().await?;
-
-            if let Some(arr) = result["documents"].as_array() {
-                for doc in arr {
-                    if let Some(id) = doc["name"]
-                        .as_str()
-                        .and_then(|name| name.split('/').last())
-                        .map(|s| s.to_string())
-                    {
-                        let data = from_firestore_document(doc);
-                        all_docs.push((id, data));
-                    }
+                if !response.status().is_success() {
+                    let status = response.status();
+                    let body = response.text().await?;
+                    debug!("Firebase error: {}", body);
+                    return Err(anyhow!("Firebase error: {}", status));
                 }
-            }
 
-            // Check for next page
-            match result.get("nextPageToken") {
-                Some(t) => {
-                    if let Some(t_str) = t.as_str() {
-                        page_token = Some(t_str.to_string());
-                    } else {
-                        break;
-                    }
-                }
-                None => break,
+                Ok(response.json().await?)
             }
-        }
-
-        Ok(all_docs)
+        })
+        .await
     }
 
     /// Delete a document
-    pub async fn delete_document(&self, collection: &str, doc_id: &str) -> Result<()> {
+    async fn delete_document(&self, collection: &str, doc_id: &str) -> Result<()> {
         let token = self.get_access_token().await?;
         let url = format!("{}/{}/{}", self.base_url(), collection, doc_id);
 
-        let response = self.client.delete(&url).bearer_auth(&token).send().await?;
+        let response = self
+            .send("delete_document", Retry::Always, || {
+                self.client.delete(&url).bearer_auth(&token)
+            })
+            .await?;
 
         if !response.status().is_success() && response.status() != 404 {
             let status = response.status();
@@ -403,64 +593,19 @@ impl FirebaseClient {
         Ok(())
     }
 
-    /// Get all users collection
-    pub async fn get_all_users(&self) -> Result<Vec<Value>> {
-        let token = self.get_access_token().await?;
-        let url = format!("{}/users", self.base_url());
-
-        let response = self.client.get(&url).bearer_auth(&token).send().await?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await?;
-            debug!("Firebase error: {}", body);
-            return Err(anyhow!("Firebase error: {}", status));
-        }
-
-        let result: Value = response.json().await?;
-        let docs = result["documents"]
-            .as_array()
-            .map(|arr| {
-                arr.iter()
-                    .map(|doc| {
-                        let mut parsed = from_firestore_document(doc);
-                        // Extract user ID from document name
-                        if let Some(name) = doc["name"].as_str() {
-                            if let Some(id) = name.split('/').last() {
-                                parsed["_id"] = json!(id);
-                            }
-                        }
-                        parsed
-                    })
-                    .collect()
-            })
-            .unwrap_or_default();
-
-        Ok(docs)
-    }
-
     // ============ Structured Queries ============
 
-    /// Run a structured query on a subcollection with server-side filtering.
-    /// Returns Vec<(doc_id, data)>.
-    ///
-    /// # Arguments
-    /// * `parent_collection` - e.g., "users"
-    /// * `parent_doc_id` - e.g., user ID
-    /// * `subcollection` - e.g., "immersion_logs"
-    /// * `filters` - List of (field_path, op, value) tuples
-    /// * `order_by` - Optional (field_path, direction) where direction is "ASCENDING" or "DESCENDING"
-    /// * `limit` - Max documents to return
-    /// * `start_after` - Optional cursor (document values to start after)
-    pub async fn run_query(
+    /// `run_query` ordered by several fields, in priority order
+    #[allow(clippy::too_many_arguments)]
+    async fn run_query_ordered(
         &self,
         parent_collection: &str,
         parent_doc_id: &str,
         subcollection: &str,
         filters: Vec<QueryFilter>,
-        order_by: Option<(&str, &str)>,
+        order_by: &[(&str, &str)],
         limit: usize,
-        start_after: Option<&Value>,
+        start_after: &[Value],
     ) -> Result<Vec<(String, Value)>> {
         let token = self.get_access_token().await?;
 
@@ -471,70 +616,35 @@ impl FirebaseClient {
         );
         let url = format!("https://firestore.googleapis.com/v1/{}:runQuery", parent);
 
-        // Build structuredQuery
-        let mut query = json!({
-            "from": [{ "collectionId": subcollection }],
-            "limit": limit
-        });
-
-        // Add filters
-        if !filters.is_empty() {
-            let filter_clauses: Vec<Value> = filters
-                .iter()
-                .map(|f| {
-                    json!({
-                        "fieldFilter": {
-                            "field": { "fieldPath": &f.field },
-                            "op": &f.op,
-                            "value": f.value.clone()
-                        }
-                    })
-                })
-                .collect();
-
-            if filter_clauses.len() == 1 {
-                query["where"] = filter_clauses.into_iter().next().unwrap();
-            } else {
-                query["where"] = json!({
-                    "compositeFilter": {
-                        "op": "AND",
-                        "filters": filter_clauses
-                    }
-                });
-            }
-        }
-
-        // Add orderBy
-        if let Some((field, direction)) = order_by {
-            query["orderBy"] = json!([{
-                "field": { "fieldPath": field },
-                "direction": direction
-            }]);
-        }
-
-        // Add startAfter cursor
-        if let Some(cursor_values) = start_after {
-            query["startAt"] = json!({
-                "values": [cursor_values.clone()],
-                "before": false
-            });
-        }
-
+        // Document ID cursors name the document in full
+        let start_after: Vec<Value> = start_after
+            .iter()
+            .map(
+                |value| match value.get("referenceValue").and_then(Value::as_str) {
+                    Some(id) if !id.contains('/') => json!({
+                        "referenceValue": format!("{}/{}/{}", parent, subcollection, id)
+                    }),
+                    _ => value.clone(),
+                },
+            )
+            .collect();
+        let query = structured_query(subcollection, &filters, order_by, limit, &start_after);
         let body = json!({ "structuredQuery": query });
 
         let response = self
-            .client
-            .post(&url)
-            .bearer_auth(&token)
-            .json(&body)
-            .send()
+            .send("run_query", Retry::Always, || {
+                self.client.post(&url).bearer_auth(&token).json(&body)
+            })
             .await?;
 
         if !response.status().is_success() {
             let status = response.status();
             let body = response.text().await?;
-            debug!("Firebase query error: {}", body);
-            return Err(anyhow!("Firebase query error: {}", status));
+            return Err(anyhow!(
+                "Firebase query error: {}: {}",
+                status,
+                error_message(&body)
+            ));
         }
 
         // Response is an array of { document: {...} } or { readTime: ... }
@@ -544,7 +654,7 @@ impl FirebaseClient {
         for item in results {
             if let Some(doc) = item.get("document") {
                 if let Some(name) = doc["name"].as_str() {
-                    let id = name.split('/').last().unwrap_or("").to_string();
+                    let id = name.split('/').next_back().unwrap_or("").to_string();
                     let data = from_firestore_document(doc);
                     docs.push((id, data));
                 }
@@ -557,7 +667,7 @@ impl FirebaseClient {
     // ============ Transactions ============
 
     /// Begin a new Firestore transaction. Returns the transaction ID.
-    pub async fn begin_transaction(&self) -> Result<String> {
+    async fn begin_transaction(&self) -> Result<String> {
         let token = self.get_access_token().await?;
         let url = format!(
             "https://firestore.googleapis.com/v1/projects/{}/databases/(default)/documents:beginTransaction",
@@ -565,11 +675,9 @@ impl FirebaseClient {
         );
 
         let response = self
-            .client
-            .post(&url)
-            .bearer_auth(&token)
-            .json(&json!({}))
-            .send()
+            .send("begin_transaction", Retry::Always, || {
+                self.client.post(&url).bearer_auth(&token).json(&json!({}))
+            })
             .await?;
 
         if !response.status().is_success() {
@@ -587,12 +695,54 @@ impl FirebaseClient {
         Ok(tx_id.to_string())
     }
 
+    /// Roll back a transaction that won't be committed, releasing the locks its reads took
+    async fn rollback_transaction(&self, transaction_id: &str) -> Result<()> {
+        let token = self.get_access_token().await?;
+        let url = format!(
+            "https://firestore.googleapis.com/v1/projects/{}/databases/(default)/documents:rollback",
+            self.service_account.project_id
+        );
+
+        let response = self
+            .send("rollback_transaction", Retry::Always, || {
+                self.client
+                    .post(&url)
+                    .bearer_auth(&token)
+                    .json(&json!({ "transaction": transaction_id }))
+            })
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await?;
+            debug!("Firebase rollback error: {}", body);
+            return Err(anyhow!("Firebase rollback error: {}", status));
+        }
+        Ok(())
+    }
+
     /// Commit a transaction with a list of writes.
     /// All writes are applied atomically.
-    pub async fn commit_transaction(
+    async fn commit_transaction(
         &self,
         transaction_id: &str,
         writes: Vec<TransactionWrite>,
+    ) -> Result<()> {
+        self.commit(Some(transaction_id), writes).await
+    }
+
+    /// Apply writes atomically outside a transaction, e.g. `UpdateWithTransforms`
+    /// increments that need no read first
+    async fn update_with_transforms(&self, writes: Vec<TransactionWrite>) -> Result<()> {
+        self.commit(None, writes).await
+    }
+
+    /// `documents:commit`. Without a transaction a replayed commit would apply twice, so
+    /// it's only retried when the request never went out.
+    async fn commit(
+        &self,
+        transaction_id: Option<&str>,
+        writes: Vec<TransactionWrite>,
     ) -> Result<()> {
         let token = self.get_access_token().await?;
         let url = format!(
@@ -601,57 +751,40 @@ impl FirebaseClient {
         );
 
         let write_objects: Vec<Value> = writes
-            .into_iter()
-            .map(|w| match w {
-                TransactionWrite::Delete { document_path } => {
-                    let full_path = format!(
-                        "projects/{}/databases/(default)/documents/{}",
-                        self.service_account.project_id, document_path
-                    );
-                    json!({ "delete": full_path })
-                }
-                TransactionWrite::Update {
-                    document_path,
-                    fields,
-                } => {
-                    let full_path = format!(
-                        "projects/{}/databases/(default)/documents/{}",
-                        self.service_account.project_id, document_path
-                    );
-                    let field_paths: Vec<String> = fields
-                        .as_object()
-                        .map(|obj| obj.keys().cloned().collect())
-                        .unwrap_or_default();
-                    json!({
-                        "update": {
-                            "name": full_path,
-                            "fields": to_firestore_fields(&fields)
-                        },
-                        "updateMask": {
-                            "fieldPaths": field_paths
-                        }
-                    })
-                }
-            })
+            .iter()
+            .map(|w| to_write_object(&self.service_account.project_id, w))
             .collect();
 
-        let body = json!({
-            "transaction": transaction_id,
-            "writes": write_objects
-        });
+        let mut body = json!({ "writes": write_objects });
+        if let Some(transaction_id) = transaction_id {
+            body["transaction"] = json!(transaction_id);
+        }
+        let retry = if transaction_id.is_some() {
+            Retry::Always
+        } else {
+            Retry::IfNotSent
+        };
 
         let response = self
-            .client
-            .post(&url)
-            .bearer_auth(&token)
-            .json(&body)
-            .send()
+            .send("commit", retry, || {
+                self.client.post(&url).bearer_auth(&token).json(&body)
+            })
             .await?;
 
         if !response.status().is_success() {
             let status = response.status();
             let body = response.text().await?;
             debug!("Firebase commit error: {}", body);
+            // Both of these come back as 409, the status string tells them apart
+            let error_status = serde_json::from_str::<Value>(&body)
+                .ok()
+                .and_then(|v| v["error"]["status"].as_str().map(|s| s.to_string()))
+                .unwrap_or_default();
+            match error_status.as_str() {
+                "ALREADY_EXISTS" => return Err(DocumentAlreadyExists.into()),
+                "ABORTED" => return Err(TransactionConflict(status).into()),
+                _ => {}
+            }
             return Err(anyhow!("Firebase commit error: {}", status));
         }
 
@@ -659,7 +792,7 @@ impl FirebaseClient {
     }
 
     /// Read a document within a transaction context.
-    pub async fn get_document_in_transaction(
+    async fn get_document_in_transaction(
         &self,
         transaction_id: &str,
         collection: &str,
@@ -674,7 +807,11 @@ impl FirebaseClient {
             transaction_id
         );
 
-        let response = self.client.get(&url).bearer_auth(&token).send().await?;
+        let response = self
+            .send("get_in_transaction", Retry::Always, || {
+                self.client.get(&url).bearer_auth(&token)
+            })
+            .await?;
 
         if response.status() == 404 {
             return Ok(None);
@@ -692,24 +829,585 @@ impl FirebaseClient {
     }
 }
 
-/// Convert Firestore document to regular JSON
-fn from_firestore_document(doc: &Value) -> Value {
-    if let Some(fields) = doc.get("fields") {
-        from_firestore_value(&json!({ "mapValue": { "fields": fields } }))
-    } else {
-        Value::Null
+impl Storage for FirebaseClient {
+    fn get_document<'a>(
+        &'a self,
+        collection: &'a str,
+        doc_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<Value>>> {
+        Box::pin(FirebaseClient::get_document(self, collection, doc_id))
     }
-}
 
-/// Convert Firestore value to regular JSON value
-fn from_firestore_value(value: &Value) -> Value {
-    if let Some(s) = value.get("stringValue") {
-        return s.clone();
+    fn set_document<'a>(
+        &'a self,
+        collection: &'a str,
+        doc_id: &'a str,
+        data: &'a Value,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(FirebaseClient::set_document(self, collection, doc_id, data))
     }
-    if let Some(n) = value.get("integerValue") {
-        if let Some(s) = n.as_str() {
-            return Value::Number(s.parse().unwrap_or(0.into()));
-        }
+
+    fn set_document_merge_paths<'a>(
+        &'a self,
+        collection: &'a str,
+        doc_id: &'a str,
+        data: &'a Value,
+        field_paths: &'a [&'a str],
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(FirebaseClient::set_document_merge_paths(
+            self,
+            collection,
+            doc_id,
+            data,
+            field_paths,
+        ))
+    }
+
+    fn add_to_subcollection<'a>(
+        &'a self,
+        collection: &'a str,
+        doc_id: &'a str,
+        subcollection: &'a str,
+        data: &'a Value,
+    ) -> BoxFuture<'a, Result<String>> {
+        Box::pin(FirebaseClient::add_to_subcollection(
+            self,
+            collection,
+            doc_id,
+            subcollection,
+            data,
+        ))
+    }
+
+    fn query_subcollection_with_ids<'a>(
+        &'a self,
+        collection: &'a str,
+        doc_id: &'a str,
+        subcollection: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<(String, Value)>>> {
+        Box::pin(FirebaseClient::query_subcollection_with_ids(
+            self,
+            collection,
+            doc_id,
+            subcollection,
+        ))
+    }
+
+    fn delete_document<'a>(
+        &'a self,
+        collection: &'a str,
+        doc_id: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(FirebaseClient::delete_document(self, collection, doc_id))
+    }
+
+    fn list_collection<'a>(
+        &'a self,
+        collection: &'a str,
+        fields: Option<&'a [&'a str]>,
+    ) -> BoxFuture<'a, Result<Vec<(String, Value)>>> {
+        Box::pin(FirebaseClient::list_collection(self, collection, fields))
+    }
+
+    fn run_query_ordered<'a>(
+        &'a self,
+        parent_collection: &'a str,
+        parent_doc_id: &'a str,
+        subcollection: &'a str,
+        filters: Vec<QueryFilter>,
+        order_by: &'a [(&'a str, &'a str)],
+        limit: usize,
+        start_after: &'a [Value],
+    ) -> BoxFuture<'a, Result<Vec<(String, Value)>>> {
+        Box::pin(FirebaseClient::run_query_ordered(
+            self,
+            parent_collection,
+            parent_doc_id,
+            subcollection,
+            filters,
+            order_by,
+            limit,
+            start_after,
+        ))
+    }
+
+    fn begin_transaction(&self) -> BoxFuture<'_, Result<String>> {
+        Box::pin(FirebaseClient::begin_transaction(self))
+    }
+
+    fn commit_transaction<'a>(
+        &'a self,
+        transaction_id: &'a str,
+        writes: Vec<TransactionWrite>,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(FirebaseClient::commit_transaction(
+            self,
+            transaction_id,
+            writes,
+        ))
+    }
+
+    fn commit_writes(&self, writes: Vec<TransactionWrite>) -> BoxFuture<'_, Result<()>> {
+        Box::pin(FirebaseClient::update_with_transforms(self, writes))
+    }
+
+    fn rollback_transaction<'a>(&'a self, transaction_id: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(FirebaseClient::rollback_transaction(self, transaction_id))
+    }
+
+    fn token_expires_at(&self) -> BoxFuture<'_, Option<u64>> {
+        Box::pin(self.tokens.expires_at())
+    }
+
+    fn get_document_in_transaction<'a>(
+        &'a self,
+        transaction_id: &'a str,
+        collection: &'a str,
+        doc_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<Value>>> {
+        Box::pin(FirebaseClient::get_document_in_transaction(
+            self,
+            transaction_id,
+            collection,
+            doc_id,
+        ))
+    }
+}
+
+/// Generate a new access token using JWT
+async fn generate_access_token(client: &Client, account: &ServiceAccount) -> Result<String> {
+    let now = unix_now();
+
+    let claims = Claims {
+        iss: account.client_email.clone(),
+        sub: account.client_email.clone(),
+        aud: "https://oauth2.googleapis.com/token".to_string(),
+        iat: now,
+        exp: now + TOKEN_LIFETIME_SECS,
+        scope: "https://www.googleapis.com/auth/datastore".to_string(),
+    };
+
+    // Encode JWT
+    // Support both normal PEM newlines and escaped "\\n" format from some deploy setups.
+    let key = match EncodingKey::from_rsa_pem(account.private_key.as_bytes()) {
+        Ok(key) => key,
+        Err(original_err) => {
+            let normalized_private_key = account
+                .private_key
+                .replace("\\r\\n", "\n")
+                .replace("\\n", "\n");
+
+            EncodingKey::from_rsa_pem(normalized_private_key.as_bytes())
+                .map_err(|_| anyhow!("Invalid Firebase private_key format: {}", original_err))?
+        }
+    };
+    let jwt = encode(&Header::new(Algorithm::RS256), &claims, &key)?;
+
+    // Exchange JWT for access token
+    let response = send_with_retry(Retry::Always, || {
+        client.post("https://oauth2.googleapis.com/token").form(&[
+            ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+            ("assertion", &jwt),
+        ])
+    })
+    .await?;
+
+    if !response.status().is_success() {
+        let body = response.text().await?;
+        error!("Failed to get access token: {}", body);
+        return Err(anyhow!("Failed to get access token"));
+    }
+
+    let data: Value = response.json().await?;
+    let token = data["access_token"]
+        .as_str()
+        .ok_or_else(|| anyhow!("No access_token in response"))?;
+
+    Ok(token.to_string())
+}
+
+/// Send a request with a timeout, retrying transient failures as `retry` allows.
+/// The last response comes back whatever its status, for the caller to check.
+async fn send_with_retry(retry: Retry, request: impl Fn() -> RequestBuilder) -> Result<Response> {
+    let mut attempt = 1;
+    loop {
+        let result = request().timeout(REQUEST_TIMEOUT).send().await;
+        let reason = match &result {
+            Ok(response) if retry.on_status(response.status()) => response.status().to_string(),
+            Err(e) if retry.on_error(e) => e.to_string(),
+            _ => return Ok(result?),
+        };
+        if attempt >= MAX_ATTEMPTS {
+            return Ok(result?);
+        }
+
+        let delay = retry_delay(attempt, rand::random::<f64>());
+        warn!(
+            "Firebase request failed ({}), retrying in {}ms (attempt {}/{})",
+            reason,
+            delay.as_millis(),
+            attempt + 1,
+            MAX_ATTEMPTS
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// Build the REST `Write` object for a transaction write
+fn to_write_object(project_id: &str, write: &TransactionWrite) -> Value {
+    match write {
+        TransactionWrite::Delete { document_path } => {
+            let full_path = format!(
+                "projects/{}/databases/(default)/documents/{}",
+                project_id, document_path
+            );
+            json!({ "delete": full_path })
+        }
+        TransactionWrite::Update {
+            document_path,
+            fields,
+        } => {
+            let full_path = format!(
+                "projects/{}/databases/(default)/documents/{}",
+                project_id, document_path
+            );
+            let field_paths: Vec<String> = fields
+                .as_object()
+                .map(|obj| obj.keys().map(|k| field_path(&[k])).collect())
+                .unwrap_or_default();
+            json!({
+                "update": {
+                    "name": full_path,
+                    "fields": to_firestore_fields(fields)
+                },
+                "updateMask": {
+                    "fieldPaths": field_paths
+                }
+            })
+        }
+        TransactionWrite::UpdatePaths {
+            document_path,
+            fields,
+            field_paths,
+        } => {
+            let full_path = format!(
+                "projects/{}/databases/(default)/documents/{}",
+                project_id, document_path
+            );
+            let field_paths: Vec<String> = field_paths
+                .iter()
+                .map(|p| normalize_field_path(p))
+                .collect();
+            json!({
+                "update": {
+                    "name": full_path,
+                    "fields": to_firestore_fields(fields)
+                },
+                "updateMask": {
+                    "fieldPaths": field_paths
+                }
+            })
+        }
+        TransactionWrite::Create {
+            document_path,
+            fields,
+        } => {
+            let full_path = format!(
+                "projects/{}/databases/(default)/documents/{}",
+                project_id, document_path
+            );
+            json!({
+                "update": {
+                    "name": full_path,
+                    "fields": to_firestore_fields(fields)
+                },
+                "currentDocument": {
+                    "exists": false
+                }
+            })
+        }
+        TransactionWrite::UpdateWithTransforms {
+            document_path,
+            fields,
+            field_paths,
+            increments,
+        } => {
+            let mut write = to_write_object(
+                project_id,
+                &TransactionWrite::UpdatePaths {
+                    document_path: document_path.clone(),
+                    fields: fields.clone(),
+                    field_paths: field_paths.clone(),
+                },
+            );
+            write["updateTransforms"] = field_transforms(increments);
+            write
+        }
+    }
+}
+
+/// The `fieldTransforms` array of a write, one `increment` per field
+fn field_transforms(increments: &[(String, Value)]) -> Value {
+    increments
+        .iter()
+        .map(|(path, amount)| {
+            json!({
+                "fieldPath": normalize_field_path(path),
+                "increment": to_firestore_value(amount)
+            })
+        })
+        .collect()
+}
+
+/// Operators Firestore treats as inequalities, which constrain the first orderBy
+const INEQUALITY_OPS: [&str; 6] = [
+    "LESS_THAN",
+    "LESS_THAN_OR_EQUAL",
+    "GREATER_THAN",
+    "GREATER_THAN_OR_EQUAL",
+    "NOT_EQUAL",
+    "NOT_IN",
+];
+
+/// The orderBy of a query. Firestore rejects an inequality filter unless the first
+/// orderBy is on the same field, so that field is moved (or added, ascending) to the
+/// front of `order_by`.
+pub(crate) fn query_order<'a>(
+    filters: &'a [QueryFilter],
+    order_by: &[(&'a str, &'a str)],
+) -> Vec<(&'a str, &'a str)> {
+    let mut order: Vec<(&str, &str)> = order_by.to_vec();
+    if let Some(inequality) = filters
+        .iter()
+        .find(|f| INEQUALITY_OPS.contains(&f.op.as_str()))
+    {
+        let field = inequality.field.as_str();
+        if order.first().map(|(f, _)| *f) != Some(field) {
+            let direction = order
+                .iter()
+                .find(|(f, _)| *f == field)
+                .map(|(_, d)| *d)
+                .unwrap_or("ASCENDING");
+            order.retain(|(f, _)| *f != field);
+            order.insert(0, (field, direction));
+        }
+    }
+    order
+}
+
+/// The `structuredQuery` of a `runQuery` request, ordered by `query_order`.
+/// A `start_after` cursor holds the values of the resulting orderBy fields, in order.
+fn structured_query(
+    subcollection: &str,
+    filters: &[QueryFilter],
+    order_by: &[(&str, &str)],
+    limit: usize,
+    start_after: &[Value],
+) -> Value {
+    let mut query = json!({
+        "from": [{ "collectionId": subcollection }],
+        "limit": limit
+    });
+
+    let filter_clauses: Vec<Value> = filters
+        .iter()
+        .map(|f| {
+            json!({
+                "fieldFilter": {
+                    "field": { "fieldPath": &f.field },
+                    "op": &f.op,
+                    "value": f.value.clone()
+                }
+            })
+        })
+        .collect();
+    match filter_clauses.len() {
+        0 => {}
+        1 => query["where"] = filter_clauses.into_iter().next().unwrap(),
+        _ => {
+            query["where"] = json!({
+                "compositeFilter": {
+                    "op": "AND",
+                    "filters": filter_clauses
+                }
+            })
+        }
+    }
+
+    let order = query_order(filters, order_by);
+    if !order.is_empty() {
+        query["orderBy"] = order
+            .iter()
+            .map(|(field, direction)| {
+                json!({
+                    "field": { "fieldPath": field },
+                    "direction": direction
+                })
+            })
+            .collect();
+    }
+
+    // startAt with before: false starts right after the cursor
+    if !start_after.is_empty() {
+        query["startAt"] = json!({
+            "values": start_after,
+            "before": false
+        });
+    }
+
+    query
+}
+
+/// Firestore's own message from an error response body, else the body itself
+fn error_message(body: &str) -> String {
+    serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|v| {
+            // runQuery wraps errors in an array
+            let error = v.get("error").or_else(|| v.get(0)?.get("error"))?;
+            error.get("message")?.as_str().map(str::to_string)
+        })
+        .unwrap_or_else(|| body.trim().to_string())
+}
+
+/// Firestore field path of nested field names. Names other than letters, digits and
+/// underscores (or starting with a digit) are backtick-quoted.
+pub fn field_path(names: &[&str]) -> String {
+    names
+        .iter()
+        .map(|name| {
+            let mut chars = name.chars();
+            let simple = chars
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if simple {
+                name.to_string()
+            } else {
+                format!("`{}`", name.replace('\\', "\\\\").replace('`', "\\`"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Field names of a dotted path; backtick-quoted names may contain dots
+pub(crate) fn split_field_path(path: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut chars = path.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '`' => quoted = !quoted,
+            '\\' if quoted => current.extend(chars.next()),
+            '.' if !quoted => names.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    names.push(current);
+    names
+}
+
+/// A dotted path with every name quoted the way Firestore expects
+fn normalize_field_path(path: &str) -> String {
+    let names = split_field_path(path);
+    field_path(&names.iter().map(String::as_str).collect::<Vec<_>>())
+}
+
+/// `updateMask.fieldPaths` query string for normalized field paths
+fn update_mask_query(field_paths: &[String]) -> String {
+    field_paths
+        .iter()
+        .map(|p| format!("updateMask.fieldPaths={}", urlencoding::encode(p)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// `mask.fieldPaths` query string for normalized field paths
+fn mask_query(field_paths: &[String]) -> String {
+    field_paths
+        .iter()
+        .map(|p| format!("mask.fieldPaths={}", urlencoding::encode(p)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// URL of one page of a collection listing
+fn list_page_url(base_url: &str, mask: Option<&str>, page_token: Option<&str>) -> String {
+    let mut url = format!("{}?pageSize={}", base_url, LIST_PAGE_SIZE);
+    if let Some(mask) = mask {
+        url.push('&');
+        url.push_str(mask);
+    }
+    if let Some(token) = page_token {
+        url.push_str(&format!("&pageToken={}", urlencoding::encode(token)));
+    }
+    url
+}
+
+/// (id, data) of each document on a listing page, and the token of the next page
+fn parse_list_page(page: &Value) -> (Vec<(String, Value)>, Option<String>) {
+    let docs = page["documents"]
+        .as_array()
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|doc| {
+                    let id = doc["name"].as_str()?.split('/').next_back()?;
+                    Some((id.to_string(), from_firestore_document(doc)))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let next = page["nextPageToken"]
+        .as_str()
+        .filter(|t| !t.is_empty())
+        .map(str::to_string);
+    (docs, next)
+}
+
+/// Fetch listing pages until one has no next page token
+async fn collect_pages<F, Fut>(mut fetch: F) -> Result<Vec<(String, Value)>>
+where
+    F: FnMut(Option<String>) -> Fut,
+    Fut: Future<Output = Result<Value>>,
+{
+    let mut all_docs = Vec::new();
+    let mut page_token = None;
+    loop {
+        let (docs, next) = parse_list_page(&fetch(page_token).await?);
+        all_docs.extend(docs);
+        match next {
+            Some(token) => page_token = Some(token),
+            None => return Ok(all_docs),
+        }
+    }
+}
+
+/// Typed user document from converted Firestore JSON
+pub fn parse_user_doc(doc: Value) -> Result<UserDoc> {
+    serde_json::from_value(doc).map_err(|e| anyhow!("Invalid user document: {}", e))
+}
+
+/// Convert Firestore document to regular JSON
+fn from_firestore_document(doc: &Value) -> Value {
+    if let Some(fields) = doc.get("fields") {
+        from_firestore_value(&json!({ "mapValue": { "fields": fields } }))
+    } else {
+        Value::Null
+    }
+}
+
+/// Convert Firestore value to regular JSON value
+pub(crate) fn from_firestore_value(value: &Value) -> Value {
+    if let Some(s) = value.get("stringValue") {
+        return s.clone();
+    }
+    if let Some(n) = value.get("integerValue") {
+        if let Some(s) = n.as_str() {
+            return Value::Number(s.parse().unwrap_or(0.into()));
+        }
         return n.clone();
     }
     if let Some(n) = value.get("doubleValue") {
@@ -721,6 +1419,9 @@ fn from_firestore_value(value: &Value) -> Value {
     if let Some(ts) = value.get("timestampValue") {
         return ts.clone();
     }
+    if let Some(name) = value.get("referenceValue") {
+        return name.clone();
+    }
     if value.get("nullValue").is_some() {
         return Value::Null;
     }
@@ -791,3 +1492,634 @@ fn to_firestore_value(value: &Value) -> Value {
         Value::Null => json!({ "nullValue": null }),
     }
 }
+
+impl TransactionWrite {
+    /// Path of the document the write targets
+    pub fn document_path(&self) -> &str {
+        match self {
+            TransactionWrite::Delete { document_path }
+            | TransactionWrite::Update { document_path, .. }
+            | TransactionWrite::UpdatePaths { document_path, .. }
+            | TransactionWrite::Create { document_path, .. }
+            | TransactionWrite::UpdateWithTransforms { document_path, .. } => document_path,
+        }
+    }
+}
+
+/// The document after `write`, `None` once deleted, for backends without a server.
+/// Checking the `Create` precondition is left to the caller.
+pub(crate) fn apply_write(current: Option<Value>, write: &TransactionWrite) -> Option<Value> {
+    match write {
+        TransactionWrite::Delete { .. } => None,
+        TransactionWrite::Create { fields, .. } => Some(fields.clone()),
+        TransactionWrite::Update { fields, .. } => {
+            let mut doc = current.unwrap_or_else(|| json!({}));
+            if let (Some(doc), Some(fields)) = (doc.as_object_mut(), fields.as_object()) {
+                for (k, v) in fields {
+                    doc.insert(k.clone(), v.clone());
+                }
+            }
+            Some(doc)
+        }
+        TransactionWrite::UpdatePaths {
+            fields,
+            field_paths,
+            ..
+        } => {
+            let mut doc = current.unwrap_or_else(|| json!({}));
+            for path in field_paths {
+                set_path(&mut doc, &split_field_path(path), fields);
+            }
+            Some(doc)
+        }
+        TransactionWrite::UpdateWithTransforms {
+            document_path,
+            fields,
+            field_paths,
+            increments,
+        } => {
+            let mut doc = apply_write(
+                current,
+                &TransactionWrite::UpdatePaths {
+                    document_path: document_path.clone(),
+                    fields: fields.clone(),
+                    field_paths: field_paths.clone(),
+                },
+            )?;
+            for (path, amount) in increments {
+                increment_path(&mut doc, &split_field_path(path), amount);
+            }
+            Some(doc)
+        }
+    }
+}
+
+/// Only the `fields` paths of `doc`, like a listing with `mask.fieldPaths`, for backends
+/// without a server
+pub(crate) fn mask_document(doc: &Value, fields: &[&str]) -> Value {
+    let mut masked = json!({});
+    for path in fields {
+        set_path(&mut masked, &split_field_path(path), doc);
+    }
+    masked
+}
+
+/// Add `amount` to the number at `names`, a missing or non-number field counting as 0.
+/// Integers stay integers, like Firestore's `increment`.
+fn increment_path(doc: &mut Value, names: &[String], amount: &Value) {
+    let current = names.iter().try_fold(&*doc, |v, name| v.get(name));
+    let sum = match (current.and_then(Value::as_i64), amount.as_i64()) {
+        (Some(a), Some(b)) => json!(a + b),
+        (None, Some(b)) if current.and_then(Value::as_f64).is_none() => json!(b),
+        _ => json!(current.and_then(Value::as_f64).unwrap_or(0.0) + amount.as_f64().unwrap_or(0.0)),
+    };
+    set_path(doc, names, &nest(names, sum));
+}
+
+/// `value` wrapped in one map per name, so `set_path` finds it at `names`
+fn nest(names: &[String], value: Value) -> Value {
+    names.iter().rev().fold(value, |inner, name| {
+        Value::Object(serde_json::Map::from_iter([(name.clone(), inner)]))
+    })
+}
+
+/// Copy the field at `names` from `fields` into `doc`, removing it when absent
+fn set_path(doc: &mut Value, names: &[String], fields: &Value) {
+    let source = names.iter().try_fold(fields, |v, name| v.get(name));
+    let Some((leaf, parents)) = names.split_last() else {
+        return;
+    };
+    let mut target = doc;
+    for name in parents {
+        let Some(obj) = target.as_object_mut() else {
+            return;
+        };
+        target = obj.entry(name.clone()).or_insert_with(|| json!({}));
+    }
+    if let Some(obj) = target.as_object_mut() {
+        match source {
+            Some(v) => obj.insert(leaf.clone(), v.clone()),
+            None => obj.remove(leaf),
+        };
+    }
+}
+
+/// In-memory stand-in for Firestore commits, applying writes with the same
+/// all-or-nothing and precondition semantics
+#[cfg(test)]
+pub(crate) mod mock {
+    use super::{apply_write, DocumentAlreadyExists, TransactionWrite};
+    use serde_json::Value;
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    pub struct MockFirestore {
+        pub docs: HashMap<String, Value>,
+    }
+
+    impl MockFirestore {
+        pub fn get(&self, path: &str) -> Option<Value> {
+            self.docs.get(path).cloned()
+        }
+
+        pub fn commit(&mut self, writes: &[TransactionWrite]) -> anyhow::Result<()> {
+            // Preconditions are checked before anything is applied
+            for w in writes {
+                if let TransactionWrite::Create { document_path, .. } = w {
+                    if self.docs.contains_key(document_path) {
+                        return Err(DocumentAlreadyExists.into());
+                    }
+                }
+            }
+
+            for w in writes {
+                let path = w.document_path();
+                match apply_write(self.docs.remove(path), w) {
+                    Some(doc) => self.docs.insert(path.to_string(), doc),
+                    None => None,
+                };
+            }
+
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_write_object_precondition() {
+        let write = TransactionWrite::Create {
+            document_path: "users/123/immersion_logs/abc".to_string(),
+            fields: json!({ "activity": { "amount": 1 } }),
+        };
+        let obj = to_write_object("demo", &write);
+
+        assert_eq!(obj["currentDocument"], json!({ "exists": false }));
+        assert!(obj.get("updateMask").is_none());
+    }
+
+    #[test]
+    fn test_generate_document_id() {
+        let id = generate_document_id();
+        assert_eq!(id.len(), 20);
+        assert!(id.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_ne!(id, generate_document_id());
+    }
+
+    #[test]
+    fn test_delete_write_object_path() {
+        let write = TransactionWrite::Delete {
+            document_path: "users/123/immersion_logs/abc".to_string(),
+        };
+        assert_eq!(
+            to_write_object("demo", &write),
+            json!({ "delete": "projects/demo/databases/(default)/documents/users/123/immersion_logs/abc" })
+        );
+    }
+
+    #[test]
+    fn test_update_write_object_mask() {
+        let write = TransactionWrite::Update {
+            document_path: "users/123".to_string(),
+            fields: json!({
+                "stats": { "anime": { "total": 3 } },
+                "timestamps": { "updated": "2025-01-15T10:00:00Z" }
+            }),
+        };
+        let obj = to_write_object("demo", &write);
+
+        assert_eq!(
+            obj["update"]["name"],
+            "projects/demo/databases/(default)/documents/users/123"
+        );
+        assert_eq!(
+            obj["updateMask"]["fieldPaths"],
+            json!(["stats", "timestamps"])
+        );
+        assert_eq!(
+            obj["update"]["fields"]["stats"]["mapValue"]["fields"]["anime"]["mapValue"]["fields"]
+                ["total"],
+            json!({ "integerValue": "3" })
+        );
+    }
+
+    #[test]
+    fn test_user_doc_survives_firestore_conversion() {
+        let raw = json!({
+            "profile": { "id": "123", "username": "yuki", "nickname": "yu" },
+            "stats": {
+                "anime": { "total": 12, "sessions": 2, "bestStreak": 3 },
+                "reading": { "total": 1500.5, "sessions": 1 }
+            },
+            "summary": { "totalSessions": 3, "activeTypes": ["anime", "reading"] },
+            "preferences": { "hideAiringCovers": true },
+            "goals": { "anime": { "amount": 20.0, "month": "2025-01" } }
+        });
+
+        // What get_user sees: integers come back as integerValue, floats as doubleValue
+        let stored = from_firestore_document(&to_firestore_document(&raw));
+        let user = parse_user_doc(stored).unwrap();
+        assert_eq!(user.stats.total("anime"), 12.0);
+        assert_eq!(user.stats.total("reading"), 1500.5);
+        assert_eq!(user.stats.get("anime").unwrap().best_streak, 3);
+        assert!(user.preference("hideAiringCovers"));
+        assert_eq!(user.goals()["anime"].month, "2025-01");
+
+        // What set_user writes back, then reads again
+        let written = to_firestore_document(&serde_json::to_value(&user).unwrap());
+        assert_eq!(
+            written["fields"]["profile"]["mapValue"]["fields"]["nickname"],
+            json!({ "stringValue": "yu" })
+        );
+        assert_eq!(
+            written["fields"]["stats"]["mapValue"]["fields"]["anime"]["mapValue"]["fields"]
+                ["sessions"],
+            json!({ "integerValue": "2" })
+        );
+        let reread = parse_user_doc(from_firestore_document(&written)).unwrap();
+        assert_eq!(reread, user);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_token_requests_refresh_once() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let tokens = TokenCache::default();
+        let fetches = AtomicUsize::new(0);
+        let fetch = || async {
+            fetches.fetch_add(1, Ordering::SeqCst);
+            // Long enough for every caller to pile up behind the refresh
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok("token-1".to_string())
+        };
+
+        let results =
+            futures::future::join_all((0..10).map(|_| tokens.get_or_refresh(1_000, fetch))).await;
+        assert!(results.iter().all(|r| r.as_deref().ok() == Some("token-1")));
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        // Past the expiry buffer it is fetched again
+        let later = 1_000 + TOKEN_LIFETIME_SECS - TOKEN_EXPIRY_BUFFER_SECS;
+        tokens.get_or_refresh(later, fetch).await.unwrap();
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_early_refresh_is_claimed_once() {
+        let tokens = TokenCache::default();
+        assert!(tokens.claim_early_refresh(0).await.is_none());
+
+        tokens.store("token-1".to_string(), 0).await;
+        assert!(tokens.claim_early_refresh(0).await.is_none());
+
+        let soon = TOKEN_LIFETIME_SECS - TOKEN_EARLY_REFRESH_SECS;
+        let claimed = tokens.claim_early_refresh(soon).await;
+        assert!(claimed.is_some());
+        assert!(tokens.claim_early_refresh(soon).await.is_none());
+        // Still handed out while the refresh runs
+        assert_eq!(tokens.valid(soon).await.as_deref(), Some("token-1"));
+
+        tokens.store("token-2".to_string(), soon).await;
+        drop(claimed);
+        assert!(tokens.claim_early_refresh(soon).await.is_none());
+    }
+
+    #[test]
+    fn test_retry_policy() {
+        for status in [429, 500, 502, 503] {
+            let status = StatusCode::from_u16(status).unwrap();
+            assert!(Retry::Always.on_status(status), "{}", status);
+            assert!(!Retry::IfNotSent.on_status(status), "{}", status);
+        }
+        for status in [400, 401, 403, 404, 409] {
+            assert!(!Retry::Always.on_status(StatusCode::from_u16(status).unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_retry_delay_backoff() {
+        assert_eq!(retry_delay(1, 0.0), Duration::from_millis(250));
+        assert_eq!(retry_delay(2, 0.0), Duration::from_millis(500));
+        assert_eq!(retry_delay(3, 0.0), Duration::from_millis(1000));
+        assert_eq!(retry_delay(2, 1.0), Duration::from_millis(750));
+        assert!(retry_delay(2, 0.5) < retry_delay(3, 0.0));
+    }
+
+    #[test]
+    fn test_field_path_quoting() {
+        assert_eq!(
+            field_path(&["stats", "anime", "total"]),
+            "stats.anime.total"
+        );
+        assert_eq!(
+            field_path(&["stats", "light-novel", "2024"]),
+            "stats.`light-novel`.`2024`"
+        );
+        assert_eq!(field_path(&["a`b\\c"]), r"`a\`b\\c`");
+        assert_eq!(field_path(&["goals.v2"]), "`goals.v2`");
+
+        // Quoted names come back whole, dots and escapes included
+        assert_eq!(
+            split_field_path(r"stats.`goals.v2`.`a\`b`"),
+            vec!["stats", "goals.v2", "a`b"]
+        );
+        assert_eq!(
+            normalize_field_path("stats.light-novel.sessions"),
+            "stats.`light-novel`.sessions"
+        );
+        assert_eq!(normalize_field_path("stats.`goals.v2`"), "stats.`goals.v2`");
+    }
+
+    #[test]
+    fn test_update_mask_query_encoding() {
+        let paths = vec![
+            normalize_field_path("stats.anime.total"),
+            normalize_field_path("stats.light novel.sessions"),
+        ];
+        assert_eq!(
+            update_mask_query(&paths),
+            "updateMask.fieldPaths=stats.anime.total\
+             &updateMask.fieldPaths=stats.%60light%20novel%60.sessions"
+        );
+    }
+
+    #[test]
+    fn test_update_paths_write_object_mask() {
+        let write = TransactionWrite::UpdatePaths {
+            document_path: "users/123".to_string(),
+            fields: json!({ "stats": { "anime": { "total": 3, "sessions": 1 } } }),
+            field_paths: vec![
+                "stats.anime.total".to_string(),
+                "stats.anime.sessions".to_string(),
+                "stats.my-type.total".to_string(),
+            ],
+        };
+        let obj = to_write_object("demo", &write);
+
+        assert_eq!(
+            obj["updateMask"]["fieldPaths"],
+            json!([
+                "stats.anime.total",
+                "stats.anime.sessions",
+                "stats.`my-type`.total"
+            ])
+        );
+        assert_eq!(
+            obj["update"]["fields"]["stats"]["mapValue"]["fields"]["anime"]["mapValue"]["fields"]
+                ["total"],
+            json!({ "integerValue": "3" })
+        );
+    }
+
+    #[test]
+    fn test_mock_update_paths_keeps_siblings() {
+        let mut store = mock::MockFirestore::default();
+        store.docs.insert(
+            "users/1".to_string(),
+            json!({ "stats": { "anime": { "total": 1, "bestStreak": 9 }, "manga": { "total": 4 } } }),
+        );
+        store
+            .commit(&[TransactionWrite::UpdatePaths {
+                document_path: "users/1".to_string(),
+                fields: json!({ "stats": { "anime": { "total": 2 } } }),
+                field_paths: vec!["stats.anime.total".to_string()],
+            }])
+            .unwrap();
+
+        assert_eq!(
+            store.get("users/1").unwrap(),
+            json!({ "stats": { "anime": { "total": 2, "bestStreak": 9 }, "manga": { "total": 4 } } })
+        );
+    }
+
+    #[test]
+    fn test_parse_user_doc_rejects_wrong_shapes() {
+        assert!(parse_user_doc(json!({ "stats": { "anime": { "total": "lots" } } })).is_err());
+        assert_eq!(parse_user_doc(json!({})).unwrap(), UserDoc::default());
+    }
+
+    #[test]
+    fn test_query_with_single_filter() {
+        let query = structured_query(
+            "immersion_logs",
+            &[QueryFilter::string_eq("timestamps.date", "2025-01-15")],
+            &[],
+            500,
+            &[],
+        );
+        assert_eq!(
+            query,
+            json!({
+                "from": [{ "collectionId": "immersion_logs" }],
+                "limit": 500,
+                "where": {
+                    "fieldFilter": {
+                        "field": { "fieldPath": "timestamps.date" },
+                        "op": "EQUAL",
+                        "value": { "stringValue": "2025-01-15" }
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_query_with_composite_filter() {
+        let query = structured_query(
+            "immersion_logs",
+            &[
+                QueryFilter::string_eq("activity.type", "anime"),
+                QueryFilter::string_eq("timestamps.date", "2025-01-15"),
+            ],
+            &[("timestamps.created", "DESCENDING")],
+            10,
+            &[],
+        );
+        assert_eq!(
+            query["where"],
+            json!({
+                "compositeFilter": {
+                    "op": "AND",
+                    "filters": [
+                        {
+                            "fieldFilter": {
+                                "field": { "fieldPath": "activity.type" },
+                                "op": "EQUAL",
+                                "value": { "stringValue": "anime" }
+                            }
+                        },
+                        {
+                            "fieldFilter": {
+                                "field": { "fieldPath": "timestamps.date" },
+                                "op": "EQUAL",
+                                "value": { "stringValue": "2025-01-15" }
+                            }
+                        }
+                    ]
+                }
+            })
+        );
+        // Equality filters leave the order alone
+        assert_eq!(
+            query["orderBy"],
+            json!([{ "field": { "fieldPath": "timestamps.created" }, "direction": "DESCENDING" }])
+        );
+    }
+
+    #[test]
+    fn test_inequality_field_is_ordered_first() {
+        let filters = [
+            QueryFilter::string_eq("activity.type", "anime"),
+            QueryFilter::timestamp_gte("timestamps.created", "2025-01-01T00:00:00Z"),
+        ];
+        let order = |query: Value| query["orderBy"].clone();
+
+        // No order given
+        assert_eq!(
+            order(structured_query("logs", &filters, &[], 10, &[])),
+            json!([{ "field": { "fieldPath": "timestamps.created" }, "direction": "ASCENDING" }])
+        );
+        // Ordered on another field
+        assert_eq!(
+            order(structured_query(
+                "logs",
+                &filters,
+                &[("activity.amount", "DESCENDING")],
+                10,
+                &[]
+            )),
+            json!([
+                { "field": { "fieldPath": "timestamps.created" }, "direction": "ASCENDING" },
+                { "field": { "fieldPath": "activity.amount" }, "direction": "DESCENDING" }
+            ])
+        );
+        // Already ordered on it later on: moved up, keeping its direction
+        assert_eq!(
+            order(structured_query(
+                "logs",
+                &filters,
+                &[
+                    ("activity.amount", "DESCENDING"),
+                    ("timestamps.created", "DESCENDING")
+                ],
+                10,
+                &[]
+            )),
+            json!([
+                { "field": { "fieldPath": "timestamps.created" }, "direction": "DESCENDING" },
+                { "field": { "fieldPath": "activity.amount" }, "direction": "DESCENDING" }
+            ])
+        );
+        // Already first: unchanged
+        assert_eq!(
+            order(structured_query(
+                "logs",
+                &filters,
+                &[("timestamps.created", "DESCENDING")],
+                10,
+                &[]
+            )),
+            json!([{ "field": { "fieldPath": "timestamps.created" }, "direction": "DESCENDING" }])
+        );
+    }
+
+    #[test]
+    fn test_query_with_cursor() {
+        let cursor = [
+            json!({ "timestampValue": "2025-01-15T10:00:00Z" }),
+            json!({ "referenceValue": "abc" }),
+        ];
+        let query = structured_query(
+            "immersion_logs",
+            &[],
+            &[
+                ("timestamps.created", "DESCENDING"),
+                (DOCUMENT_ID, "DESCENDING"),
+            ],
+            50,
+            &cursor,
+        );
+        assert_eq!(
+            query["startAt"],
+            json!({
+                "values": [
+                    { "timestampValue": "2025-01-15T10:00:00Z" },
+                    { "referenceValue": "abc" }
+                ],
+                "before": false
+            })
+        );
+        assert!(query.get("where").is_none());
+    }
+
+    #[test]
+    fn test_error_message() {
+        let body = r#"[{"error": {"code": 400, "message": "inequality filter property and first sort order must be the same", "status": "INVALID_ARGUMENT"}}]"#;
+        assert_eq!(
+            error_message(body),
+            "inequality filter property and first sort order must be the same"
+        );
+        assert_eq!(
+            error_message(r#"{"error": {"code": 403, "message": "Missing permissions"}}"#),
+            "Missing permissions"
+        );
+        assert_eq!(error_message("Bad Gateway\n"), "Bad Gateway");
+    }
+
+    #[tokio::test]
+    async fn test_list_pagination() {
+        let doc = |name: &str, total: i64| {
+            json!({
+                "name": format!("projects/demo/databases/(default)/documents/users/{}", name),
+                "fields": { "stats": { "mapValue": { "fields": {
+                    "total": { "integerValue": total.to_string() }
+                } } } }
+            })
+        };
+        let pages = [
+            json!({ "documents": [doc("1", 10), doc("2", 20)], "nextPageToken": "page+2" }),
+            json!({ "documents": [doc("3", 30)] }),
+        ];
+
+        let mut tokens = Vec::new();
+        let docs = collect_pages(|token| {
+            let page = pages[tokens.len()].clone();
+            tokens.push(token);
+            async move { Ok(page) }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(tokens, vec![None, Some("page+2".to_string())]);
+        assert_eq!(
+            docs,
+            vec![
+                ("1".to_string(), json!({ "stats": { "total": 10 } })),
+                ("2".to_string(), json!({ "stats": { "total": 20 } })),
+                ("3".to_string(), json!({ "stats": { "total": 30 } })),
+            ]
+        );
+
+        // An empty collection comes back without `documents`
+        assert_eq!(parse_list_page(&json!({})), (vec![], None));
+    }
+
+    #[test]
+    fn test_list_page_url() {
+        let mask = mask_query(&[
+            normalize_field_path("profile"),
+            normalize_field_path("stats"),
+        ]);
+        assert_eq!(
+            list_page_url("https://x/users", Some(&mask), None),
+            "https://x/users?pageSize=300&mask.fieldPaths=profile&mask.fieldPaths=stats"
+        );
+        assert_eq!(
+            list_page_url("https://x/users", None, Some("a+b/c=")),
+            "https://x/users?pageSize=300&pageToken=a%2Bb%2Fc%3D"
+        );
+    }
+}
@@ -15,6 +15,15 @@ pub struct JimakuEntry {
     pub english_name: Option<String>,
     pub japanese_name: Option<String>,
     pub anilist_id: Option<i32>,
+    #[serde(default)]
+    pub flags: JimakuFlags,
+}
+
+/// What kind of entry it is, as tagged on Jimaku
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct JimakuFlags {
+    #[serde(default)]
+    pub movie: bool,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -33,6 +42,68 @@ pub struct JimakuFile {
     pub last_modified: String,
 }
 
+/// Subtitle format to keep, by file extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, poise::ChoiceParameter)]
+pub enum SubtitleFormat {
+    #[name = "ASS"]
+    Ass,
+    #[name = "SRT"]
+    Srt,
+    #[default]
+    #[name = "Any"]
+    Any,
+}
+
+impl SubtitleFormat {
+    fn matches(self, file_name: &str) -> bool {
+        let extension = file_name
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_ascii_lowercase())
+            .unwrap_or_default();
+        match self {
+            // SSA is the older version of the same format
+            SubtitleFormat::Ass => extension == "ass" || extension == "ssa",
+            SubtitleFormat::Srt => extension == "srt",
+            SubtitleFormat::Any => true,
+        }
+    }
+}
+
+/// Which subtitle languages to keep
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, poise::ChoiceParameter)]
+pub enum LanguagePreference {
+    #[name = "Japanese only"]
+    JapaneseOnly,
+    #[default]
+    #[name = "Any"]
+    Any,
+}
+
+/// Whether a file name is tagged as English, e.g. "[EN] Show 01.ass" or "Show.01.eng.srt".
+/// Only whole words count, so titles that merely contain "en" are kept.
+fn has_english_marker(file_name: &str) -> bool {
+    file_name
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| matches!(word.to_lowercase().as_str(), "en" | "eng" | "english"))
+}
+
+/// Keep the files matching the format and language, in order. Also returns how many
+/// were left out.
+pub fn filter_files(
+    files: Vec<JimakuFile>,
+    format: SubtitleFormat,
+    language: LanguagePreference,
+) -> (Vec<JimakuFile>, usize) {
+    let total = files.len();
+    let kept: Vec<JimakuFile> = files
+        .into_iter()
+        .filter(|file| format.matches(&file.name))
+        .filter(|file| language == LanguagePreference::Any || !has_english_marker(&file.name))
+        .collect();
+    let excluded = total - kept.len();
+    (kept, excluded)
+}
+
 pub async fn search_anime(
     client: &reqwest::Client,
     api_key: &str,
@@ -105,3 +176,68 @@ pub async fn download_file(client: &reqwest::Client, url: &str) -> Result<Vec<u8
     let bytes = response.bytes().await?;
     Ok(bytes.to_vec())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(names: &[&str]) -> Vec<JimakuFile> {
+        names
+            .iter()
+            .map(|name| serde_json::from_value(serde_json::json!({ "name": name })).unwrap())
+            .collect()
+    }
+
+    fn names(files: &[JimakuFile]) -> Vec<&str> {
+        files.iter().map(|f| f.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_filter_by_format() {
+        let all = files(&[
+            "Show.S01E01.ja.srt",
+            "[EN] something.ass",
+            "Show 02.SSA",
+            "Show S01.zip",
+            "no extension",
+        ]);
+
+        let (kept, excluded) =
+            filter_files(all.clone(), SubtitleFormat::Ass, LanguagePreference::Any);
+        assert_eq!(names(&kept), vec!["[EN] something.ass", "Show 02.SSA"]);
+        assert_eq!(excluded, 3);
+
+        let (kept, excluded) =
+            filter_files(all.clone(), SubtitleFormat::Srt, LanguagePreference::Any);
+        assert_eq!(names(&kept), vec!["Show.S01E01.ja.srt"]);
+        assert_eq!(excluded, 4);
+
+        let (kept, excluded) = filter_files(all, SubtitleFormat::Any, LanguagePreference::Any);
+        assert_eq!(kept.len(), 5);
+        assert_eq!(excluded, 0);
+    }
+
+    #[test]
+    fn test_filter_japanese_only() {
+        let all = files(&[
+            "Show.S01E01.ja.srt",
+            "[EN] something.ass",
+            "Show.S01E01.eng.srt",
+            "Show - 01 (English).ass",
+            "Ren'ai Flops - 01.ass",
+            "Kenja no Mago 01.srt",
+            "Show_01_EN.srt",
+        ]);
+        let (kept, excluded) =
+            filter_files(all, SubtitleFormat::Any, LanguagePreference::JapaneseOnly);
+        assert_eq!(
+            names(&kept),
+            vec![
+                "Show.S01E01.ja.srt",
+                "Ren'ai Flops - 01.ass",
+                "Kenja no Mago 01.srt"
+            ]
+        );
+        assert_eq!(excluded, 4);
+    }
+}
@@ -0,0 +1,210 @@
+// Jisho.org API client
+// For dictionary lookups (JMdict entries with JLPT levels)
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+
+use crate::api::cache::{Fetched, RateLimit, TtlCache, LOOKUP_TTL};
+
+const JISHO_SEARCH_URL: &str = "https://jisho.org/api/v1/search/words";
+
+static RATE_LIMIT: RateLimit = RateLimit::new("Jisho");
+
+/// Keyed by the query as sent; dictionary entries barely change, so they keep for a day
+pub(super) static SEARCH_CACHE: Lazy<TtlCache<String, Vec<JishoEntry>>> =
+    Lazy::new(|| TtlCache::new("Jisho search", 1000, LOOKUP_TTL));
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct JishoEntry {
+    #[serde(default)]
+    pub slug: String,
+    #[serde(default)]
+    pub is_common: bool,
+    /// Tags like "jlpt-n5"
+    #[serde(default)]
+    pub jlpt: Vec<String>,
+    #[serde(default)]
+    pub japanese: Vec<JishoJapanese>,
+    #[serde(default)]
+    pub senses: Vec<JishoSense>,
+}
+
+/// One written form; kana-only words have no `word`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct JishoJapanese {
+    pub word: Option<String>,
+    pub reading: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct JishoSense {
+    #[serde(default)]
+    pub english_definitions: Vec<String>,
+    #[serde(default)]
+    pub parts_of_speech: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub info: Vec<String>,
+}
+
+impl JishoEntry {
+    /// Headword: the first written form, or the reading for kana-only words
+    pub fn headword(&self) -> &str {
+        self.japanese
+            .first()
+            .and_then(|j| j.word.as_deref().or(j.reading.as_deref()))
+            .unwrap_or(&self.slug)
+    }
+
+    /// Easiest JLPT level listed, e.g. "N5"
+    pub fn jlpt_level(&self) -> Option<String> {
+        self.jlpt
+            .iter()
+            .filter_map(|tag| tag.strip_prefix("jlpt-n"))
+            .filter_map(|n| n.parse::<u8>().ok())
+            .max()
+            .map(|n| format!("N{}", n))
+    }
+
+    pub fn has_word(&self, word: &str) -> bool {
+        self.slug == word
+            || self
+                .japanese
+                .iter()
+                .any(|j| j.word.as_deref() == Some(word))
+    }
+
+    pub fn has_reading(&self, reading: &str) -> bool {
+        self.japanese
+            .iter()
+            .any(|j| j.reading.as_deref() == Some(reading))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct JishoResponse {
+    #[serde(default)]
+    data: Vec<JishoEntry>,
+}
+
+/// Search Jisho for words matching `query` (kanji, kana, romaji or English)
+pub async fn search_words(client: &reqwest::Client, query: &str) -> Result<Vec<JishoEntry>> {
+    let query = query.trim();
+    let entries = SEARCH_CACHE
+        .get_or_fetch(query.to_lowercase(), &RATE_LIMIT, || {
+            fetch_search(client, query)
+        })
+        .await?
+        .unwrap_or_default();
+    Ok(entries)
+}
+
+async fn fetch_search(client: &reqwest::Client, query: &str) -> Result<Fetched<Vec<JishoEntry>>> {
+    let response = client
+        .get(JISHO_SEARCH_URL)
+        .query(&[("keyword", query)])
+        .send()
+        .await?;
+
+    if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        RATE_LIMIT.trip(response.headers());
+        return Ok(Fetched::Unavailable);
+    }
+    if !response.status().is_success() {
+        return Ok(Fetched::Unavailable);
+    }
+
+    let data: JishoResponse = response.json().await?;
+    Ok(Fetched::Value(data.data))
+}
+
+/// Up to `limit` entries for `query`, best match first. Exact written forms
+/// come first, then exact readings (common words before rare ones), then the
+/// rest in Jisho's order. `reading` is the kana for a romaji query.
+pub fn best_candidates(
+    entries: Vec<JishoEntry>,
+    query: &str,
+    reading: Option<&str>,
+    limit: usize,
+) -> Vec<JishoEntry> {
+    let query = query.trim();
+    let reading = reading.unwrap_or(query);
+    let rank = |entry: &JishoEntry| {
+        if entry.has_word(query) {
+            0
+        } else if entry.has_reading(reading) && entry.is_common {
+            1
+        } else if entry.has_reading(reading) {
+            2
+        } else {
+            3
+        }
+    };
+
+    let mut ranked: Vec<(usize, JishoEntry)> = entries.into_iter().map(|e| (rank(&e), e)).collect();
+    // Stable, so ties keep Jisho's order
+    ranked.sort_by_key(|(rank, _)| *rank);
+    ranked.into_iter().take(limit).map(|(_, e)| e).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entries() -> Vec<JishoEntry> {
+        serde_json::from_value(json!([
+            {
+                "slug": "田部",
+                "japanese": [{ "word": "田部", "reading": "たべ" }],
+                "senses": [{ "english_definitions": ["Tabe"], "parts_of_speech": ["Place"] }]
+            },
+            {
+                "slug": "食べる",
+                "is_common": true,
+                "jlpt": ["jlpt-n5", "jlpt-n4"],
+                "japanese": [
+                    { "word": "食べる", "reading": "たべる" },
+                    { "word": "喰べる", "reading": "たべる" }
+                ],
+                "senses": [{
+                    "english_definitions": ["to eat"],
+                    "parts_of_speech": ["Ichidan verb", "Transitive verb"]
+                }]
+            },
+            {
+                "slug": "たべる-1",
+                "japanese": [{ "reading": "たべる" }],
+                "senses": [{ "english_definitions": ["rare homophone"] }]
+            }
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_entry() {
+        let entries = entries();
+        let taberu = &entries[1];
+        assert_eq!(taberu.headword(), "食べる");
+        assert_eq!(taberu.jlpt_level().as_deref(), Some("N5"));
+        assert!(taberu.has_word("喰べる"));
+        assert_eq!(entries[2].headword(), "たべる");
+        assert_eq!(entries[0].jlpt_level(), None);
+    }
+
+    #[test]
+    fn test_kanji_kana_and_romaji_land_on_the_same_entry() {
+        for (query, reading) in [
+            ("食べる", None),
+            ("たべる", None),
+            ("taberu", Some("たべる")),
+        ] {
+            let best = best_candidates(entries(), query, reading, 3);
+            assert_eq!(best[0].slug, "食べる", "query {}", query);
+            assert_eq!(best.len(), 3);
+        }
+        assert_eq!(best_candidates(entries(), "たべる", None, 2).len(), 2);
+    }
+}
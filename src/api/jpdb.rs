@@ -0,0 +1,150 @@
+// jpdb.io API client
+// For the known-word count of members who linked their jpdb account with /link jpdb.
+// The API key is the member's own and is only ever sent to jpdb.
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::api::cache::{Fetched, RateLimit, TtlCache};
+use crate::utils::formatters::format_number;
+
+const JPDB_API_URL: &str = "https://jpdb.io/api/v1";
+
+/// Per-request timeout, so a slow jpdb doesn't hold up /stat or /card
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Known words move slowly, an hour old is close enough
+const KNOWN_WORDS_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Fields asked of `list-user-decks`, in the order jpdb returns them
+const DECK_FIELDS: [&str; 2] = ["vocabulary_count", "vocabulary_known_coverage"];
+
+static RATE_LIMIT: RateLimit = RateLimit::new("jpdb");
+
+/// Keyed by Discord user ID, never by the API key
+pub(super) static KNOWN_WORDS_CACHE: Lazy<TtlCache<String, u64>> =
+    Lazy::new(|| TtlCache::new("jpdb known words", 500, KNOWN_WORDS_TTL));
+
+#[derive(Debug, Deserialize)]
+struct DecksResponse {
+    /// One row per deck, values in `DECK_FIELDS` order
+    #[serde(default)]
+    decks: Vec<Vec<serde_json::Value>>,
+}
+
+/// Whether jpdb accepts `api_key`. `Err` when jpdb couldn't be asked.
+pub async fn validate_key(client: &reqwest::Client, api_key: &str) -> Result<bool> {
+    let response = client
+        .post(format!("{}/ping", JPDB_API_URL))
+        .bearer_auth(api_key)
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await?;
+
+    match response.status() {
+        status if status.is_success() => Ok(true),
+        reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => Ok(false),
+        reqwest::StatusCode::TOO_MANY_REQUESTS => {
+            RATE_LIMIT.trip(response.headers());
+            anyhow::bail!("jpdb is rate limiting us")
+        }
+        status => anyhow::bail!("jpdb ping returned {}", status),
+    }
+}
+
+/// Known words of a linked member, `None` when jpdb is unavailable
+pub async fn known_words(
+    client: &reqwest::Client,
+    user_id: &str,
+    api_key: &str,
+) -> Result<Option<u64>> {
+    KNOWN_WORDS_CACHE
+        .get_or_fetch(user_id.to_string(), &RATE_LIMIT, || {
+            fetch_known_words(client, api_key)
+        })
+        .await
+}
+
+/// Fetch the count past the cache, for a key that was just linked
+pub async fn refresh_known_words(
+    client: &reqwest::Client,
+    user_id: &str,
+    api_key: &str,
+) -> Result<Option<u64>> {
+    match fetch_known_words(client, api_key).await? {
+        Fetched::Value(count) => {
+            KNOWN_WORDS_CACHE.insert(user_id.to_string(), count).await;
+            Ok(Some(count))
+        }
+        Fetched::Unavailable => Ok(None),
+    }
+}
+
+async fn fetch_known_words(client: &reqwest::Client, api_key: &str) -> Result<Fetched<u64>> {
+    let response = client
+        .post(format!("{}/list-user-decks", JPDB_API_URL))
+        .bearer_auth(api_key)
+        .json(&serde_json::json!({ "fields": DECK_FIELDS }))
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await?;
+
+    if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        RATE_LIMIT.trip(response.headers());
+        return Ok(Fetched::Unavailable);
+    }
+    if !response.status().is_success() {
+        return Ok(Fetched::Unavailable);
+    }
+
+    let data: DecksResponse = response.json().await?;
+    Ok(Fetched::Value(sum_known_words(&data.decks)))
+}
+
+/// Words known across the decks: each deck's size times its known coverage (a percentage).
+/// A word in two decks counts twice, jpdb has no call for a deduplicated total.
+fn sum_known_words(decks: &[Vec<serde_json::Value>]) -> u64 {
+    decks
+        .iter()
+        .filter_map(|row| {
+            let count = row.first()?.as_f64()?;
+            let coverage = row.get(1)?.as_f64()?;
+            Some(count * coverage.clamp(0.0, 100.0) / 100.0)
+        })
+        .sum::<f64>()
+        .round() as u64
+}
+
+/// "1,234 known words", or "unavailable" when jpdb couldn't be reached
+pub fn known_words_text(count: Option<u64>) -> String {
+    match count {
+        Some(count) => format!("{} known words", format_number(count as i64)),
+        None => "unavailable".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sum_known_words() {
+        let data: DecksResponse = serde_json::from_value(json!({
+            "decks": [
+                [1000, 50.0],
+                [300, 100.0],
+                // Malformed rows are skipped
+                [null, 20.0],
+                [200]
+            ]
+        }))
+        .unwrap();
+        assert_eq!(sum_known_words(&data.decks), 800);
+        assert_eq!(sum_known_words(&[]), 0);
+        assert_eq!(known_words_text(Some(12345)), "12,345 known words");
+        assert_eq!(known_words_text(None), "unavailable");
+    }
+}
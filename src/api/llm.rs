@@ -1,6 +1,8 @@
+use crate::utils::metrics::{self, Kind};
 use crate::Data;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::time::Instant;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatMessage {
@@ -12,6 +14,24 @@ pub struct ChatMessage {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OpenAIResponse {
     pub choices: Vec<OpenAIChoice>,
+    #[serde(default)]
+    pub usage: Option<OpenAIUsage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenAIUsage {
+    #[serde(default)]
+    pub prompt_tokens: u64,
+    #[serde(default)]
+    pub completion_tokens: u64,
+}
+
+impl OpenAIResponse {
+    fn record_tokens(&self, model: &str) {
+        if let Some(usage) = &self.usage {
+            metrics::record_tokens(model, usage.prompt_tokens, usage.completion_tokens);
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,6 +48,28 @@ type OpenRouterChoice = OpenAIChoice;
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GeminiResponse {
     pub candidates: Option<Vec<GeminiCandidate>>,
+    #[serde(default, rename = "usageMetadata")]
+    pub usage_metadata: Option<GeminiUsage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeminiUsage {
+    #[serde(default, rename = "promptTokenCount")]
+    pub prompt_token_count: u64,
+    #[serde(default, rename = "candidatesTokenCount")]
+    pub candidates_token_count: u64,
+}
+
+impl GeminiResponse {
+    fn record_tokens(&self, model: &str) {
+        if let Some(usage) = &self.usage_metadata {
+            metrics::record_tokens(
+                model,
+                usage.prompt_token_count,
+                usage.candidates_token_count,
+            );
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -69,23 +111,27 @@ pub async fn completion_openrouter(
         "temperature": 0.5, // Adjusted to match typical chatbot settings
     });
 
-    // Note: OpenRouter API URL
-    let res = data
-        .http_client
-        .post("https://openrouter.ai/api/v1/chat/completions")
-        .header("Authorization", format!("Bearer {}", api_key))
-        .header("HTTP-Referer", "https://discord.com") // Required by OpenRouter
-        .header("X-Title", "Ayumi Bot")
-        .json(&body)
-        .send()
-        .await?;
-
-    if !res.status().is_success() {
-        let error_text = res.text().await?;
-        anyhow::bail!("OpenRouter API error: {}", error_text);
-    }
+    let response: OpenAIResponse = metrics::timed(Kind::Llm, model, async {
+        // Note: OpenRouter API URL
+        let res = data
+            .http_client
+            .post("https://openrouter.ai/api/v1/chat/completions")
+            .header("Authorization", format!("Bearer {}", api_key))
+            .header("HTTP-Referer", "https://discord.com") // Required by OpenRouter
+            .header("X-Title", "Ayumi Bot")
+            .json(&body)
+            .send()
+            .await?;
+
+        if !res.status().is_success() {
+            let error_text = res.text().await?;
+            anyhow::bail!("OpenRouter API error: {}", error_text);
+        }
 
-    let response: OpenAIResponse = res.json().await?;
+        Ok(res.json().await?)
+    })
+    .await?;
+    response.record_tokens(model);
 
     response
         .choices
@@ -135,30 +181,35 @@ pub async fn completion_chat_with_fallback(
     }];
     all_messages.extend(messages);
 
+    let model = "openrouter/free";
     let body = json!({
-        "model": "openrouter/free",
+        "model": model,
         "messages": all_messages,
         "max_tokens": 2048,
         "temperature": 0.5,
     });
 
-    let res = data
-        .http_client
-        .post("https://openrouter.ai/api/v1/chat/completions")
-        .header("Authorization", format!("Bearer {}", openrouter_key))
-        .header("Content-Type", "application/json")
-        .header("HTTP-Referer", "https://discord.com")
-        .header("X-Title", "Ayumi Bot")
-        .json(&body)
-        .send()
-        .await?;
-
-    if !res.status().is_success() {
-        let error_text = res.text().await?;
-        anyhow::bail!("OpenRouter fallback error: {}", error_text);
-    }
+    let response: OpenAIResponse = metrics::timed(Kind::Llm, model, async {
+        let res = data
+            .http_client
+            .post("https://openrouter.ai/api/v1/chat/completions")
+            .header("Authorization", format!("Bearer {}", openrouter_key))
+            .header("Content-Type", "application/json")
+            .header("HTTP-Referer", "https://discord.com")
+            .header("X-Title", "Ayumi Bot")
+            .json(&body)
+            .send()
+            .await?;
+
+        if !res.status().is_success() {
+            let error_text = res.text().await?;
+            anyhow::bail!("OpenRouter fallback error: {}", error_text);
+        }
 
-    let response: OpenAIResponse = res.json().await?;
+        Ok(res.json().await?)
+    })
+    .await?;
+    response.record_tokens(model);
     response
         .choices
         .first()
@@ -173,10 +224,10 @@ pub async fn completion_gemini_chat(
     messages: Vec<ChatMessage>,
 ) -> anyhow::Result<String> {
     let api_key = std::env::var("GEMINI_API_KEY")?;
-    // Using gemini-flash-latest
+    let model = "gemini-flash-latest";
     let url = format!(
-        "https://generativelanguage.googleapis.com/v1beta/models/gemini-flash-latest:generateContent?key={}",
-        api_key
+        "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
+        model, api_key
     );
 
     let mut contents = Vec::new();
@@ -225,14 +276,18 @@ pub async fn completion_gemini_chat(
         }
     });
 
-    let res = data.http_client.post(&url).json(&body).send().await?;
+    let response: GeminiResponse = metrics::timed(Kind::Llm, model, async {
+        let res = data.http_client.post(&url).json(&body).send().await?;
 
-    if !res.status().is_success() {
-        let error_text = res.text().await?;
-        anyhow::bail!("Gemini Chat API error: {}", error_text);
-    }
+        if !res.status().is_success() {
+            let error_text = res.text().await?;
+            anyhow::bail!("Gemini Chat API error: {}", error_text);
+        }
 
-    let response: GeminiResponse = res.json().await?;
+        Ok(res.json().await?)
+    })
+    .await?;
+    response.record_tokens(model);
 
     response
         .candidates
@@ -246,9 +301,10 @@ pub async fn completion_gemini_chat(
 /// Send a request to Gemini for multimodal tasks (Translate, etc.) (Placeholder for now)
 pub async fn completion_gemini(data: &Data, prompt: &str) -> anyhow::Result<String> {
     let api_key = std::env::var("GEMINI_API_KEY")?;
+    let model = "gemini-2.0-flash";
     let url = format!(
-        "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.0-flash:generateContent?key={}",
-        api_key
+        "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
+        model, api_key
     );
 
     let body = json!({
@@ -259,14 +315,18 @@ pub async fn completion_gemini(data: &Data, prompt: &str) -> anyhow::Result<Stri
         }]
     });
 
-    let res = data.http_client.post(&url).json(&body).send().await?;
+    let response: GeminiResponse = metrics::timed(Kind::Llm, model, async {
+        let res = data.http_client.post(&url).json(&body).send().await?;
 
-    if !res.status().is_success() {
-        let error_text = res.text().await?;
-        anyhow::bail!("Gemini API error: {}", error_text);
-    }
+        if !res.status().is_success() {
+            let error_text = res.text().await?;
+            anyhow::bail!("Gemini API error: {}", error_text);
+        }
 
-    let response: GeminiResponse = res.json().await?;
+        Ok(res.json().await?)
+    })
+    .await?;
+    response.record_tokens(model);
 
     response
         .candidates
@@ -290,9 +350,10 @@ pub async fn completion_gemini_vision(
 
     // --- Primary: Gemini 2.0 Flash ---
     if let Ok(api_key) = std::env::var("GEMINI_API_KEY") {
+        let model = "gemini-2.0-flash";
         let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.0-flash:generateContent?key={}",
-            api_key
+            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
+            model, api_key
         );
 
         let body = json!({
@@ -309,9 +370,14 @@ pub async fn completion_gemini_vision(
             }]
         });
 
-        match data.http_client.post(&url).json(&body).send().await {
+        let started = Instant::now();
+        let result = data.http_client.post(&url).json(&body).send().await;
+        let ok = matches!(&result, Ok(res) if res.status().is_success());
+        metrics::record(Kind::Llm, model, started.elapsed(), ok);
+        match result {
             Ok(res) if res.status().is_success() => {
                 let response: GeminiResponse = res.json().await?;
+                response.record_tokens(model);
                 if let Some(text) = response
                     .candidates
                     .as_ref()
@@ -346,8 +412,9 @@ pub async fn completion_gemini_vision(
 
     let data_uri = format!("data:{};base64,{}", mime_type, base64_image);
 
+    let model = "openrouter/free";
     let body = json!({
-        "model": "openrouter/free",
+        "model": model,
         "messages": [{
             "role": "user",
             "content": [
@@ -358,23 +425,27 @@ pub async fn completion_gemini_vision(
         "max_tokens": 2048,
     });
 
-    let res = data
-        .http_client
-        .post("https://openrouter.ai/api/v1/chat/completions")
-        .header("Authorization", format!("Bearer {}", openrouter_key))
-        .header("Content-Type", "application/json")
-        .header("HTTP-Referer", "https://discord.com")
-        .header("X-Title", "Ayumi Bot")
-        .json(&body)
-        .send()
-        .await?;
-
-    if !res.status().is_success() {
-        let error_text = res.text().await?;
-        anyhow::bail!("OpenRouter Vision fallback error: {}", error_text);
-    }
+    let response: OpenAIResponse = metrics::timed(Kind::Llm, model, async {
+        let res = data
+            .http_client
+            .post("https://openrouter.ai/api/v1/chat/completions")
+            .header("Authorization", format!("Bearer {}", openrouter_key))
+            .header("Content-Type", "application/json")
+            .header("HTTP-Referer", "https://discord.com")
+            .header("X-Title", "Ayumi Bot")
+            .json(&body)
+            .send()
+            .await?;
+
+        if !res.status().is_success() {
+            let error_text = res.text().await?;
+            anyhow::bail!("OpenRouter Vision fallback error: {}", error_text);
+        }
 
-    let response: OpenAIResponse = res.json().await?;
+        Ok(res.json().await?)
+    })
+    .await?;
+    response.record_tokens(model);
     response
         .choices
         .first()
@@ -431,10 +502,10 @@ pub struct ImageGenerationResult {
 pub async fn generate_image(data: &Data, prompt: &str) -> anyhow::Result<ImageGenerationResult> {
     let api_key = std::env::var("GEMINI_API_KEY")?;
 
-    // Using gemini-2.0-flash-preview-image-generation model
+    let model = "gemini-2.0-flash-preview-image-generation";
     let url = format!(
-        "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.0-flash-preview-image-generation:generateContent?key={}",
-        api_key
+        "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
+        model, api_key
     );
 
     // Clean up the prompt
@@ -467,14 +538,17 @@ pub async fn generate_image(data: &Data, prompt: &str) -> anyhow::Result<ImageGe
         }
     });
 
-    let res = data.http_client.post(&url).json(&body).send().await?;
+    let response: ImageGenResponse = metrics::timed(Kind::Llm, model, async {
+        let res = data.http_client.post(&url).json(&body).send().await?;
 
-    if !res.status().is_success() {
-        let error_text = res.text().await?;
-        anyhow::bail!("Gemini Image Generation API error: {}", error_text);
-    }
+        if !res.status().is_success() {
+            let error_text = res.text().await?;
+            anyhow::bail!("Gemini Image Generation API error: {}", error_text);
+        }
 
-    let response: ImageGenResponse = res.json().await?;
+        Ok(res.json().await?)
+    })
+    .await?;
 
     // Find image part in response
     let candidates = response
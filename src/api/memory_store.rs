@@ -0,0 +1,549 @@
+// In-memory storage
+// Stands in for Firestore when the bot runs without credentials, so commands can be
+// worked on locally. Nothing survives a restart.
+//
+// Documents are kept by their full path ("users/123", "users/123/immersion_logs/abc").
+// Queries follow Firestore where the bot relies on it: documents missing an order field
+// are left out, and a cursor continues after the values of the leading order fields.
+
+use anyhow::Result;
+use dashmap::DashMap;
+use futures::future::BoxFuture;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::sync::Mutex;
+
+use super::firebase::{
+    apply_write, from_firestore_value, generate_document_id, mask_document, query_order,
+    split_field_path, DocumentAlreadyExists, QueryFilter, TransactionWrite, DOCUMENT_ID,
+};
+use super::storage::Storage;
+
+#[derive(Default)]
+pub struct MemoryStore {
+    docs: DashMap<String, Value>,
+    /// Held while a commit checks its preconditions and applies its writes
+    commit_lock: Mutex<()>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn write(&self, write: &TransactionWrite) {
+        let path = write.document_path();
+        let current = self.docs.remove(path).map(|(_, doc)| doc);
+        if let Some(doc) = apply_write(current, write) {
+            self.docs.insert(path.to_string(), doc);
+        }
+    }
+
+    /// Apply writes all or nothing, checking `Create` preconditions first
+    fn commit(&self, writes: &[TransactionWrite]) -> Result<()> {
+        let _committing = self.commit_lock.lock().unwrap_or_else(|e| e.into_inner());
+        for w in writes {
+            if let TransactionWrite::Create { document_path, .. } = w {
+                if self.docs.contains_key(document_path) {
+                    return Err(DocumentAlreadyExists.into());
+                }
+            }
+        }
+        for w in writes {
+            self.write(w);
+        }
+        Ok(())
+    }
+
+    /// Documents directly under a collection path, in ID order like Firestore lists them
+    fn children(&self, collection_path: &str) -> Vec<(String, Value)> {
+        let prefix = format!("{}/", collection_path);
+        let mut docs: Vec<(String, Value)> = self
+            .docs
+            .iter()
+            .filter_map(|entry| {
+                let id = entry.key().strip_prefix(&prefix)?;
+                (!id.contains('/')).then(|| (id.to_string(), entry.value().clone()))
+            })
+            .collect();
+        docs.sort_by(|a, b| a.0.cmp(&b.0));
+        docs
+    }
+}
+
+fn field<'a>(doc: &'a Value, path: &str) -> Option<&'a Value> {
+    split_field_path(path)
+        .iter()
+        .try_fold(doc, |value, name| value.get(name))
+}
+
+/// Value a query orders a document by, its ID for `__name__`
+fn order_value(id: &str, doc: &Value, path: &str) -> Option<Value> {
+    if path == DOCUMENT_ID {
+        return Some(Value::String(id.to_string()));
+    }
+    field(doc, path).cloned()
+}
+
+/// Order of two field values; values of different types don't compare
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        _ => (a == b).then_some(Ordering::Equal),
+    }
+}
+
+fn matches(doc: &Value, filter: &QueryFilter) -> bool {
+    let Some(value) = field(doc, &filter.field) else {
+        return false;
+    };
+    let ordering = compare(value, &from_firestore_value(&filter.value));
+    match filter.op.as_str() {
+        "EQUAL" => ordering == Some(Ordering::Equal),
+        "NOT_EQUAL" => ordering != Some(Ordering::Equal),
+        "LESS_THAN" => ordering == Some(Ordering::Less),
+        "LESS_THAN_OR_EQUAL" => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+        "GREATER_THAN" => ordering == Some(Ordering::Greater),
+        "GREATER_THAN_OR_EQUAL" => {
+            matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+        }
+        _ => false,
+    }
+}
+
+/// Filter, order, skip past the cursor and limit, the way `runQuery` does
+fn run_query(
+    mut docs: Vec<(String, Value)>,
+    filters: &[QueryFilter],
+    order_by: &[(&str, &str)],
+    limit: usize,
+    start_after: &[Value],
+) -> Vec<(String, Value)> {
+    let order = query_order(filters, order_by);
+    docs.retain(|(id, doc)| {
+        filters.iter().all(|f| matches(doc, f))
+            && order
+                .iter()
+                .all(|(path, _)| order_value(id, doc, path).is_some())
+    });
+
+    let descending = |direction: &str| direction == "DESCENDING";
+    // Position of a document against the given values of the leading order fields
+    let position = |id: &str, doc: &Value, values: &[Value]| {
+        order
+            .iter()
+            .zip(values)
+            .map(|((path, direction), value)| {
+                let ordering = order_value(id, doc, path)
+                    .and_then(|own| compare(&own, value))
+                    .unwrap_or(Ordering::Equal);
+                if descending(direction) {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    };
+    docs.sort_by(|(a_id, a), (b_id, b)| {
+        let b_values: Vec<Value> = order
+            .iter()
+            .map(|(path, _)| order_value(b_id, b, path).unwrap_or(Value::Null))
+            .collect();
+        position(a_id, a, &b_values)
+    });
+
+    if !start_after.is_empty() {
+        let cursor: Vec<Value> = start_after.iter().map(from_firestore_value).collect();
+        docs.retain(|(id, doc)| position(id, doc, &cursor) == Ordering::Greater);
+    }
+
+    docs.truncate(limit);
+    docs
+}
+
+impl Storage for MemoryStore {
+    fn get_document<'a>(
+        &'a self,
+        collection: &'a str,
+        doc_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<Value>>> {
+        let path = format!("{}/{}", collection, doc_id);
+        Box::pin(async move { Ok(self.docs.get(&path).map(|doc| doc.clone())) })
+    }
+
+    fn set_document<'a>(
+        &'a self,
+        collection: &'a str,
+        doc_id: &'a str,
+        data: &'a Value,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            self.write(&TransactionWrite::Update {
+                document_path: format!("{}/{}", collection, doc_id),
+                fields: data.clone(),
+            });
+            Ok(())
+        })
+    }
+
+    fn set_document_merge_paths<'a>(
+        &'a self,
+        collection: &'a str,
+        doc_id: &'a str,
+        data: &'a Value,
+        field_paths: &'a [&'a str],
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            self.write(&TransactionWrite::UpdatePaths {
+                document_path: format!("{}/{}", collection, doc_id),
+                fields: data.clone(),
+                field_paths: field_paths.iter().map(|p| p.to_string()).collect(),
+            });
+            Ok(())
+        })
+    }
+
+    fn add_to_subcollection<'a>(
+        &'a self,
+        collection: &'a str,
+        doc_id: &'a str,
+        subcollection: &'a str,
+        data: &'a Value,
+    ) -> BoxFuture<'a, Result<String>> {
+        Box::pin(async move {
+            let id = generate_document_id();
+            self.docs.insert(
+                format!("{}/{}/{}/{}", collection, doc_id, subcollection, id),
+                data.clone(),
+            );
+            Ok(id)
+        })
+    }
+
+    fn query_subcollection_with_ids<'a>(
+        &'a self,
+        collection: &'a str,
+        doc_id: &'a str,
+        subcollection: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<(String, Value)>>> {
+        let path = format!("{}/{}/{}", collection, doc_id, subcollection);
+        Box::pin(async move { Ok(self.children(&path)) })
+    }
+
+    fn delete_document<'a>(
+        &'a self,
+        collection: &'a str,
+        doc_id: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        let path = format!("{}/{}", collection, doc_id);
+        Box::pin(async move {
+            self.docs.remove(&path);
+            Ok(())
+        })
+    }
+
+    fn list_collection<'a>(
+        &'a self,
+        collection: &'a str,
+        fields: Option<&'a [&'a str]>,
+    ) -> BoxFuture<'a, Result<Vec<(String, Value)>>> {
+        Box::pin(async move {
+            let docs = self.children(collection);
+            Ok(match fields {
+                Some(fields) => docs
+                    .into_iter()
+                    .map(|(id, doc)| (id, mask_document(&doc, fields)))
+                    .collect(),
+                None => docs,
+            })
+        })
+    }
+
+    fn run_query_ordered<'a>(
+        &'a self,
+        parent_collection: &'a str,
+        parent_doc_id: &'a str,
+        subcollection: &'a str,
+        filters: Vec<QueryFilter>,
+        order_by: &'a [(&'a str, &'a str)],
+        limit: usize,
+        start_after: &'a [Value],
+    ) -> BoxFuture<'a, Result<Vec<(String, Value)>>> {
+        let path = format!("{}/{}/{}", parent_collection, parent_doc_id, subcollection);
+        Box::pin(async move {
+            Ok(run_query(
+                self.children(&path),
+                &filters,
+                order_by,
+                limit,
+                start_after,
+            ))
+        })
+    }
+
+    fn begin_transaction(&self) -> BoxFuture<'_, Result<String>> {
+        Box::pin(async { Ok(generate_document_id()) })
+    }
+
+    fn commit_transaction<'a>(
+        &'a self,
+        _transaction_id: &'a str,
+        writes: Vec<TransactionWrite>,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move { self.commit(&writes) })
+    }
+
+    fn commit_writes(&self, writes: Vec<TransactionWrite>) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move { self.commit(&writes) })
+    }
+
+    fn rollback_transaction<'a>(&'a self, _transaction_id: &'a str) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn get_document_in_transaction<'a>(
+        &'a self,
+        _transaction_id: &'a str,
+        collection: &'a str,
+        doc_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<Value>>> {
+        self.get_document(collection, doc_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::firebase::is_already_exists;
+    use crate::utils::test_support::log;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_log_queries() {
+        let store = MemoryStore::new();
+        let storage: &dyn Storage = &store;
+        for (created, date, deleted) in [
+            ("2025-01-01T10:00:00+00:00", "2025-01-01", false),
+            ("2025-01-02T10:00:00+00:00", "2025-01-02", false),
+            ("2025-01-02T12:00:00+00:00", "2025-01-02", true),
+            ("2025-01-03T10:00:00+00:00", "2025-01-03", false),
+        ] {
+            storage
+                .add_to_subcollection(
+                    "users",
+                    "1",
+                    "immersion_logs",
+                    &log("anime", 1.0)
+                        .created(created)
+                        .date(date)
+                        .deleted(deleted)
+                        .build(),
+                )
+                .await
+                .unwrap();
+        }
+        // Another user's logs stay out of the queries
+        storage
+            .add_to_subcollection(
+                "users",
+                "2",
+                "immersion_logs",
+                &log("anime", 1.0)
+                    .created("2025-01-02T11:00:00+00:00")
+                    .date("2025-01-02")
+                    .build(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(storage.get_user_logs("1").await.unwrap().len(), 3);
+        assert_eq!(storage.get_deleted_user_logs("1").await.unwrap().len(), 1);
+        assert_eq!(
+            storage
+                .get_user_logs_on("1", "2025-01-02")
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let latest = storage.get_latest_user_log("1").await.unwrap().unwrap();
+        assert_eq!(latest["timestamps"]["date"], "2025-01-03");
+
+        // Pages of two, oldest first, the deleted log counted against the page
+        let (page, cursor) = storage.get_user_logs_page("1", None, 2).await.unwrap();
+        assert_eq!(page.len(), 2);
+        let (page, cursor) = storage
+            .get_user_logs_page("1", cursor.as_ref(), 2)
+            .await
+            .unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0]["timestamps"]["date"], "2025-01-03");
+        assert_eq!(cursor.unwrap().created, "2025-01-03T10:00:00+00:00");
+
+        let (between, _) = storage
+            .get_user_logs_created_between(
+                "1",
+                "2025-01-02T00:00:00+00:00",
+                Some("2025-01-03T00:00:00+00:00"),
+                10,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(between.len(), 1);
+
+        let mut reacted = log("anime", 1.0)
+            .created("2025-01-04T10:00:00+00:00")
+            .date("2025-01-04")
+            .build();
+        reacted["metadata"] = json!({ "source": "reaction", "messageId": "9" });
+        storage
+            .add_to_subcollection("users", "1", "immersion_logs", &reacted)
+            .await
+            .unwrap();
+        let from_message = storage.get_user_logs_for_message("1", "9").await.unwrap();
+        assert_eq!(from_message, vec![reacted]);
+        assert!(storage
+            .get_user_logs_for_message("1", "10")
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_log_pages_split_logs_created_together() {
+        let store = MemoryStore::new();
+        let storage: &dyn Storage = &store;
+        for _ in 0..3 {
+            storage
+                .add_to_subcollection(
+                    "users",
+                    "1",
+                    "immersion_logs",
+                    &log("anime", 1.0)
+                        .created("2025-01-02T10:00:00+00:00")
+                        .date("2025-01-02")
+                        .build(),
+                )
+                .await
+                .unwrap();
+        }
+
+        // Pages of one: the document ID tells the three apart
+        let mut seen = Vec::new();
+        let mut cursor = None;
+        loop {
+            let (page, next) = storage
+                .get_user_logs_page("1", cursor.as_ref(), 1)
+                .await
+                .unwrap();
+            seen.extend(page);
+            cursor = match next {
+                Some(next) => Some(next),
+                None => break,
+            };
+        }
+        assert_eq!(seen.len(), 3);
+
+        let mut ids = Vec::new();
+        let mut cursor = None;
+        loop {
+            let (page, next) = storage
+                .get_user_logs_created_between(
+                    "1",
+                    "2025-01-01T00:00:00+00:00",
+                    None,
+                    1,
+                    cursor.as_ref(),
+                )
+                .await
+                .unwrap();
+            ids.extend(page.into_iter().map(|(id, _)| id));
+            cursor = match next {
+                Some(next) => Some(next),
+                None => break,
+            };
+        }
+        ids.dedup();
+        assert_eq!(ids.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_documents_and_transactions() {
+        let store = MemoryStore::new();
+        let storage: &dyn Storage = &store;
+
+        storage
+            .set_document("afk", "7", &json!({ "reason": "tidur", "expiresAt": 5 }))
+            .await
+            .unwrap();
+        storage
+            .set_document_merge_paths(
+                "afk",
+                "7",
+                &json!({ "reason": "makan" }),
+                &["reason", "expiresAt"],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.get_document("afk", "7").await.unwrap(),
+            Some(json!({ "reason": "makan" }))
+        );
+        assert_eq!(
+            storage.get_all_documents("afk").await.unwrap(),
+            vec![json!({ "reason": "makan", "_id": "7" })]
+        );
+
+        storage
+            .set_document(
+                "users",
+                "7",
+                &json!({
+                    "profile": { "displayName": "Yuki" },
+                    "stats": { "anime": { "total": 3 } },
+                    "reminders": { "streak": { "enabled": true } }
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            storage
+                .get_all_users(Some(&["profile.displayName", "stats", "missing"]))
+                .await
+                .unwrap(),
+            vec![json!({
+                "profile": { "displayName": "Yuki" },
+                "stats": { "anime": { "total": 3 } },
+                "_id": "7"
+            })]
+        );
+
+        let tx = storage.begin_transaction().await.unwrap();
+        let create = TransactionWrite::Create {
+            document_path: "afk/7".to_string(),
+            fields: json!({}),
+        };
+        let err = storage
+            .commit_transaction(
+                &tx,
+                vec![
+                    TransactionWrite::Delete {
+                        document_path: "afk/8".to_string(),
+                    },
+                    create,
+                ],
+            )
+            .await
+            .unwrap_err();
+        assert!(is_already_exists(&err));
+
+        storage.delete_document("afk", "7").await.unwrap();
+        assert_eq!(storage.get_document("afk", "7").await.unwrap(), None);
+    }
+}
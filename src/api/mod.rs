@@ -1,9 +1,15 @@
 // API integrations module
 pub mod anilist;
 pub mod ayumu;
+pub mod cache;
 pub mod firebase;
 pub mod jimaku;
+pub mod jisho;
+pub mod jpdb;
 pub mod llm;
+pub mod memory_store;
 pub mod ocr;
+pub mod storage;
+pub mod tatoeba;
 pub mod vndb;
 pub mod youtube;
@@ -0,0 +1,542 @@
+// Storage backend
+// Everything the bot keeps goes through `Storage`: Firestore in production, or the
+// in-memory store when running without Firebase credentials (see `memory_store`).
+//
+// The trait only holds the Firestore primitives. Typed helpers (users, AFK, immersion
+// logs) are written once on `dyn Storage` so both backends share them.
+
+use anyhow::Result;
+use futures::future::BoxFuture;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use super::firebase::{
+    is_soft_deleted, parse_user_doc, FirebaseClient, QueryFilter, TransactionWrite, DOCUMENT_ID,
+};
+use super::memory_store::MemoryStore;
+use crate::models::afk::AfkData;
+use crate::models::user::UserDoc;
+
+/// Where a page of logs ordered by creation ended: the last log's `timestamps.created`,
+/// and its document ID for logs created the same instant
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogCursor {
+    pub created: String,
+    pub id: String,
+}
+
+impl LogCursor {
+    /// Cursor after the last of `docs`, `None` when the page wasn't full and so was the last
+    fn after(docs: &[(String, Value)], page_size: usize) -> Option<Self> {
+        if docs.len() < page_size {
+            return None;
+        }
+        let (id, doc) = docs.last()?;
+        Some(Self {
+            created: doc["timestamps"]["created"].as_str()?.to_string(),
+            id: id.clone(),
+        })
+    }
+
+    fn values(&self) -> [Value; 2] {
+        [
+            json!({ "stringValue": self.created }),
+            json!({ "referenceValue": self.id }),
+        ]
+    }
+}
+
+pub trait Storage: Send + Sync {
+    /// Get a document by path
+    fn get_document<'a>(
+        &'a self,
+        collection: &'a str,
+        doc_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<Value>>>;
+
+    /// Set/update a document (merge)
+    /// Top-level fields are replaced whole, nested maps included
+    fn set_document<'a>(
+        &'a self,
+        collection: &'a str,
+        doc_id: &'a str,
+        data: &'a Value,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    /// Update only the given dotted field paths (e.g. `stats.anime.total`), taking
+    /// their values from `data`. A path missing from `data` deletes that field.
+    fn set_document_merge_paths<'a>(
+        &'a self,
+        collection: &'a str,
+        doc_id: &'a str,
+        data: &'a Value,
+        field_paths: &'a [&'a str],
+    ) -> BoxFuture<'a, Result<()>>;
+
+    /// Add a document to a subcollection, returning its generated ID
+    fn add_to_subcollection<'a>(
+        &'a self,
+        collection: &'a str,
+        doc_id: &'a str,
+        subcollection: &'a str,
+        data: &'a Value,
+    ) -> BoxFuture<'a, Result<String>>;
+
+    /// Every document of a subcollection - returns (id, data) tuples
+    fn query_subcollection_with_ids<'a>(
+        &'a self,
+        collection: &'a str,
+        doc_id: &'a str,
+        subcollection: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<(String, Value)>>>;
+
+    /// Delete a document; deleting a missing one is not an error
+    fn delete_document<'a>(
+        &'a self,
+        collection: &'a str,
+        doc_id: &'a str,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    /// Every document of a top-level collection as (id, data), across all pages. `fields`
+    /// limits each document to those field paths.
+    fn list_collection<'a>(
+        &'a self,
+        collection: &'a str,
+        fields: Option<&'a [&'a str]>,
+    ) -> BoxFuture<'a, Result<Vec<(String, Value)>>>;
+
+    /// Structured query on a subcollection, ordered by several fields in priority order.
+    /// Returns Vec<(doc_id, data)>.
+    ///
+    /// # Arguments
+    /// * `parent_collection` - e.g., "users"
+    /// * `parent_doc_id` - e.g., user ID
+    /// * `subcollection` - e.g., "immersion_logs"
+    /// * `filters` - Filters that must all match
+    /// * `order_by` - (field_path, direction) pairs, direction "ASCENDING" or "DESCENDING"
+    /// * `limit` - Max documents to return
+    /// * `start_after` - Cursor to start after: values of the leading order fields, in
+    ///   order, empty from the start
+    #[allow(clippy::too_many_arguments)]
+    fn run_query_ordered<'a>(
+        &'a self,
+        parent_collection: &'a str,
+        parent_doc_id: &'a str,
+        subcollection: &'a str,
+        filters: Vec<QueryFilter>,
+        order_by: &'a [(&'a str, &'a str)],
+        limit: usize,
+        start_after: &'a [Value],
+    ) -> BoxFuture<'a, Result<Vec<(String, Value)>>>;
+
+    /// Begin a new transaction. Returns the transaction ID.
+    fn begin_transaction(&self) -> BoxFuture<'_, Result<String>>;
+
+    /// Commit a transaction with a list of writes.
+    /// All writes are applied atomically.
+    fn commit_transaction<'a>(
+        &'a self,
+        transaction_id: &'a str,
+        writes: Vec<TransactionWrite>,
+    ) -> BoxFuture<'a, Result<()>>;
+
+    /// Apply writes atomically outside a transaction. Nothing is read first, so there is
+    /// nothing to conflict with; `UpdateWithTransforms` increments apply to the documents
+    /// as they are at commit time.
+    fn commit_writes(&self, writes: Vec<TransactionWrite>) -> BoxFuture<'_, Result<()>>;
+
+    /// Abandon a transaction without committing it
+    fn rollback_transaction<'a>(&'a self, transaction_id: &'a str) -> BoxFuture<'a, Result<()>>;
+
+    /// Read a document within a transaction context.
+    fn get_document_in_transaction<'a>(
+        &'a self,
+        transaction_id: &'a str,
+        collection: &'a str,
+        doc_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<Value>>>;
+
+    /// When the cached access token expires (unix seconds), `None` for backends
+    /// without one or before the first request
+    fn token_expires_at(&self) -> BoxFuture<'_, Option<u64>> {
+        Box::pin(async { None })
+    }
+}
+
+/// Service account credentials of the Firestore backend
+const FIREBASE_KEY_PATH: &str = "firebase-key.json";
+
+/// Pick the storage backend. `YUYUKO_NO_FIREBASE=1`, or no credentials file, runs the
+/// bot on the in-memory store; otherwise Firestore, panicking on unreadable credentials.
+pub fn storage_from_env(client: reqwest::Client) -> Arc<dyn Storage> {
+    let disabled = std::env::var("YUYUKO_NO_FIREBASE").as_deref() == Ok("1");
+    if disabled || !Path::new(FIREBASE_KEY_PATH).exists() {
+        warn!("==============================================================");
+        warn!("Running WITHOUT Firebase: data is kept in memory and lost on exit.");
+        warn!(
+            "Unset YUYUKO_NO_FIREBASE and provide {} to use Firestore.",
+            FIREBASE_KEY_PATH
+        );
+        warn!("==============================================================");
+        return Arc::new(MemoryStore::new());
+    }
+
+    let firebase = FirebaseClient::from_file(client, FIREBASE_KEY_PATH)
+        .expect("Failed to load Firebase credentials");
+    info!("Firebase client initialized");
+    Arc::new(firebase)
+}
+
+impl dyn Storage + '_ {
+    /// Roll back a transaction that is given up on before commit. A failed rollback only
+    /// leaves the transaction to expire on its own, so it's logged and not returned.
+    pub async fn abandon_transaction(&self, transaction_id: &str) {
+        if let Err(e) = self.rollback_transaction(transaction_id).await {
+            warn!(
+                "Failed to roll back transaction {}: {:?}",
+                transaction_id, e
+            );
+        }
+    }
+
+    /// Query a subcollection - returns just the data
+    pub async fn query_subcollection(
+        &self,
+        collection: &str,
+        doc_id: &str,
+        subcollection: &str,
+    ) -> Result<Vec<Value>> {
+        let docs = self
+            .query_subcollection_with_ids(collection, doc_id, subcollection)
+            .await?;
+        Ok(docs.into_iter().map(|(_, v)| v).collect())
+    }
+
+    // ============ Users ============
+
+    /// A user's document, `None` if they have never logged anything
+    pub async fn get_user(&self, user_id: &str) -> Result<Option<UserDoc>> {
+        self.get_document("users", user_id)
+            .await?
+            .map(parse_user_doc)
+            .transpose()
+    }
+
+    /// Write a user's document; fields other clients added are kept through `extra`
+    pub async fn set_user(&self, user_id: &str, user: &UserDoc) -> Result<()> {
+        self.set_document("users", user_id, &serde_json::to_value(user)?)
+            .await
+    }
+
+    // ============ AFK ============
+
+    /// A user's AFK status, `None` when they aren't AFK
+    pub async fn get_afk(&self, user_id: u64) -> Result<Option<AfkData>> {
+        self.get_document("afk", &user_id.to_string())
+            .await?
+            .map(|doc| serde_json::from_value(doc).map_err(Into::into))
+            .transpose()
+    }
+
+    /// Replace a user's AFK status; an unset expiry or guild deletes the old one
+    pub async fn set_afk(&self, user_id: u64, afk: &AfkData) -> Result<()> {
+        self.set_document_merge_paths(
+            "afk",
+            &user_id.to_string(),
+            &serde_json::to_value(afk)?,
+            &[
+                "username",
+                "reason",
+                "since",
+                "avatarUrl",
+                "expiresAt",
+                "guildId",
+            ],
+        )
+        .await
+    }
+
+    pub async fn delete_afk(&self, user_id: u64) -> Result<()> {
+        self.delete_document("afk", &user_id.to_string()).await
+    }
+
+    /// Every stored AFK status; unreadable documents are skipped
+    pub async fn get_all_afk(&self) -> Result<Vec<(u64, AfkData)>> {
+        let docs = self.get_all_documents("afk").await?;
+        Ok(docs
+            .into_iter()
+            .filter_map(|doc| {
+                let user_id = doc["_id"].as_str()?.parse().ok()?;
+                Some((user_id, serde_json::from_value(doc).ok()?))
+            })
+            .collect())
+    }
+
+    // ============ Immersion Logs ============
+    // Every reader goes through these so soft-deleted logs never show up in stats
+
+    /// A user's immersion logs, excluding soft-deleted ones
+    pub async fn get_user_logs(&self, user_id: &str) -> Result<Vec<Value>> {
+        let docs = self
+            .query_subcollection("users", user_id, "immersion_logs")
+            .await?;
+        Ok(docs.into_iter().filter(|d| !is_soft_deleted(d)).collect())
+    }
+
+    /// At most `limit` of a user's logs in no particular order, excluding soft-deleted ones.
+    /// The flag is set when the limit was reached, so some logs may be missing.
+    pub async fn get_user_logs_capped(
+        &self,
+        user_id: &str,
+        limit: usize,
+    ) -> Result<(Vec<Value>, bool)> {
+        let docs = self
+            .run_query(
+                "users",
+                user_id,
+                "immersion_logs",
+                vec![],
+                None,
+                limit,
+                None,
+            )
+            .await?;
+        let truncated = docs.len() >= limit;
+        Ok((
+            docs.into_iter()
+                .map(|(_, d)| d)
+                .filter(|d| !is_soft_deleted(d))
+                .collect(),
+            truncated,
+        ))
+    }
+
+    /// A user's logs dated on one day (YYYY-MM-DD), excluding soft-deleted ones
+    pub async fn get_user_logs_on(&self, user_id: &str, date: &str) -> Result<Vec<Value>> {
+        let docs = self.get_user_logs_on_with_ids(user_id, date).await?;
+        Ok(docs.into_iter().map(|(_, d)| d).collect())
+    }
+
+    /// Same as `get_user_logs_on`, with document IDs
+    pub async fn get_user_logs_on_with_ids(
+        &self,
+        user_id: &str,
+        date: &str,
+    ) -> Result<Vec<(String, Value)>> {
+        let docs = self
+            .run_query(
+                "users",
+                user_id,
+                "immersion_logs",
+                vec![QueryFilter::string_eq("timestamps.date", date)],
+                None,
+                500,
+                None,
+            )
+            .await?;
+        Ok(docs
+            .into_iter()
+            .filter(|(_, d)| !is_soft_deleted(d))
+            .collect())
+    }
+
+    /// A user's logs made from one Discord message (`metadata.messageId`), excluding
+    /// soft-deleted ones
+    pub async fn get_user_logs_for_message(
+        &self,
+        user_id: &str,
+        message_id: &str,
+    ) -> Result<Vec<Value>> {
+        let docs = self
+            .run_query(
+                "users",
+                user_id,
+                "immersion_logs",
+                vec![QueryFilter::string_eq("metadata.messageId", message_id)],
+                None,
+                50,
+                None,
+            )
+            .await?;
+        Ok(docs
+            .into_iter()
+            .map(|(_, d)| d)
+            .filter(|d| !is_soft_deleted(d))
+            .collect())
+    }
+
+    /// One page of a user's logs, oldest first, excluding soft-deleted ones.
+    /// Pass the returned cursor back in for the next page; it is `None` after the last page.
+    /// Logs without a `timestamps.created` can't be ordered and are left out.
+    pub async fn get_user_logs_page(
+        &self,
+        user_id: &str,
+        after: Option<&LogCursor>,
+        page_size: usize,
+    ) -> Result<(Vec<Value>, Option<LogCursor>)> {
+        let cursor = after.map(LogCursor::values);
+        let docs = self
+            .run_query_ordered(
+                "users",
+                user_id,
+                "immersion_logs",
+                vec![],
+                &[
+                    ("timestamps.created", "ASCENDING"),
+                    (DOCUMENT_ID, "ASCENDING"),
+                ],
+                page_size,
+                cursor.as_ref().map_or(&[], |c| c.as_slice()),
+            )
+            .await?;
+        let next = LogCursor::after(&docs, page_size);
+        Ok((
+            docs.into_iter()
+                .map(|(_, d)| d)
+                .filter(|d| !is_soft_deleted(d))
+                .collect(),
+            next,
+        ))
+    }
+
+    /// A user's most recently created log, skipping soft-deleted ones
+    pub async fn get_latest_user_log(&self, user_id: &str) -> Result<Option<Value>> {
+        // A few extra in case the newest ones sit in the trash
+        let docs = self
+            .run_query(
+                "users",
+                user_id,
+                "immersion_logs",
+                vec![],
+                Some(("timestamps.created", "DESCENDING")),
+                5,
+                None,
+            )
+            .await?;
+        Ok(docs
+            .into_iter()
+            .map(|(_, d)| d)
+            .find(|d| !is_soft_deleted(d)))
+    }
+
+    /// Per-day totals under `users/{id}/daily_aggregates`, empty when nothing maintains them
+    pub async fn get_daily_aggregates(&self, user_id: &str) -> Result<Vec<(String, Value)>> {
+        self.query_subcollection_with_ids("users", user_id, "daily_aggregates")
+            .await
+    }
+
+    /// Same as `get_user_logs`, with document IDs
+    pub async fn get_user_logs_with_ids(&self, user_id: &str) -> Result<Vec<(String, Value)>> {
+        let docs = self
+            .query_subcollection_with_ids("users", user_id, "immersion_logs")
+            .await?;
+        Ok(docs
+            .into_iter()
+            .filter(|(_, d)| !is_soft_deleted(d))
+            .collect())
+    }
+
+    /// One page of a user's logs created in `start..end`, newest first, with document IDs
+    /// and excluding soft-deleted ones. `created` is stored as an RFC3339 string, so the
+    /// bounds are compared as strings and should be formatted the same way.
+    /// Pass the returned cursor back in for the next page; it is `None` after the last page.
+    pub async fn get_user_logs_created_between(
+        &self,
+        user_id: &str,
+        start: &str,
+        end: Option<&str>,
+        page_size: usize,
+        before: Option<&LogCursor>,
+    ) -> Result<(Vec<(String, Value)>, Option<LogCursor>)> {
+        let mut filters = vec![QueryFilter::string_gte("timestamps.created", start)];
+        if let Some(end) = end {
+            filters.push(QueryFilter::string_lt("timestamps.created", end));
+        }
+        let cursor = before.map(LogCursor::values);
+        let docs = self
+            .run_query_ordered(
+                "users",
+                user_id,
+                "immersion_logs",
+                filters,
+                &[
+                    ("timestamps.created", "DESCENDING"),
+                    (DOCUMENT_ID, "DESCENDING"),
+                ],
+                page_size,
+                cursor.as_ref().map_or(&[], |c| c.as_slice()),
+            )
+            .await?;
+        let next = LogCursor::after(&docs, page_size);
+        Ok((
+            docs.into_iter()
+                .filter(|(_, d)| !is_soft_deleted(d))
+                .collect(),
+            next,
+        ))
+    }
+
+    /// Only the soft-deleted logs (the trash), with document IDs
+    pub async fn get_deleted_user_logs(&self, user_id: &str) -> Result<Vec<(String, Value)>> {
+        let docs = self
+            .query_subcollection_with_ids("users", user_id, "immersion_logs")
+            .await?;
+        Ok(docs
+            .into_iter()
+            .filter(|(_, d)| is_soft_deleted(d))
+            .collect())
+    }
+
+    /// Get every document in a top-level collection, with the document ID in `_id`
+    pub async fn get_all_documents(&self, collection: &str) -> Result<Vec<Value>> {
+        self.get_all_documents_with(collection, None).await
+    }
+
+    /// `get_all_documents` keeping only the `fields` paths of each document
+    pub async fn get_all_documents_with(
+        &self,
+        collection: &str,
+        fields: Option<&[&str]>,
+    ) -> Result<Vec<Value>> {
+        Ok(self
+            .list_collection(collection, fields)
+            .await?
+            .into_iter()
+            .map(|(id, mut doc)| {
+                doc["_id"] = json!(id);
+                doc
+            })
+            .collect())
+    }
+
+    /// Get all users collection, `fields` limiting what's read of each
+    pub async fn get_all_users(&self, fields: Option<&[&str]>) -> Result<Vec<Value>> {
+        self.get_all_documents_with("users", fields).await
+    }
+
+    /// `run_query_ordered` with at most one order field
+    #[allow(clippy::too_many_arguments)]
+    pub async fn run_query(
+        &self,
+        parent_collection: &str,
+        parent_doc_id: &str,
+        subcollection: &str,
+        filters: Vec<QueryFilter>,
+        order_by: Option<(&str, &str)>,
+        limit: usize,
+        start_after: Option<&Value>,
+    ) -> Result<Vec<(String, Value)>> {
+        self.run_query_ordered(
+            parent_collection,
+            parent_doc_id,
+            subcollection,
+            filters,
+            order_by.as_slice(),
+            limit,
+            start_after.map_or(&[], std::slice::from_ref),
+        )
+        .await
+    }
+}
@@ -0,0 +1,133 @@
+// Tatoeba API client
+// For example sentences (Jisho has no sentence API, its examples come from Tatoeba)
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+
+use crate::api::cache::{Fetched, RateLimit, TtlCache, LOOKUP_TTL};
+
+const TATOEBA_SEARCH_URL: &str = "https://tatoeba.org/en/api_v0/search";
+
+static RATE_LIMIT: RateLimit = RateLimit::new("Tatoeba");
+
+/// Keyed by the word searched
+pub(super) static SENTENCE_CACHE: Lazy<TtlCache<String, Vec<ExampleSentence>>> =
+    Lazy::new(|| TtlCache::new("Tatoeba sentences", 500, LOOKUP_TTL));
+
+/// A Japanese sentence with its English translation, when there is one
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExampleSentence {
+    pub japanese: String,
+    pub english: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    results: Vec<Sentence>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Sentence {
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    lang: Option<String>,
+    /// Direct translations first, then translations of translations
+    #[serde(default)]
+    translations: Vec<Vec<Sentence>>,
+}
+
+/// Japanese example sentences containing `word`, with English translations
+pub async fn example_sentences(
+    client: &reqwest::Client,
+    word: &str,
+) -> Result<Vec<ExampleSentence>> {
+    let sentences = SENTENCE_CACHE
+        .get_or_fetch(word.to_string(), &RATE_LIMIT, || {
+            fetch_sentences(client, word)
+        })
+        .await?
+        .unwrap_or_default();
+    Ok(sentences)
+}
+
+async fn fetch_sentences(
+    client: &reqwest::Client,
+    word: &str,
+) -> Result<Fetched<Vec<ExampleSentence>>> {
+    let response = client
+        .get(TATOEBA_SEARCH_URL)
+        .query(&[("from", "jpn"), ("to", "eng"), ("query", word)])
+        .send()
+        .await?;
+
+    if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        RATE_LIMIT.trip(response.headers());
+        return Ok(Fetched::Unavailable);
+    }
+    if !response.status().is_success() {
+        return Ok(Fetched::Unavailable);
+    }
+
+    let data: SearchResponse = response.json().await?;
+    Ok(Fetched::Value(parse_sentences(data)))
+}
+
+fn parse_sentences(data: SearchResponse) -> Vec<ExampleSentence> {
+    data.results
+        .into_iter()
+        .filter(|s| !s.text.is_empty())
+        .map(|s| ExampleSentence {
+            english: s
+                .translations
+                .iter()
+                .flatten()
+                .find(|t| t.lang.as_deref() == Some("eng"))
+                .map(|t| t.text.clone()),
+            japanese: s.text,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_sentences() {
+        let data: SearchResponse = serde_json::from_value(json!({
+            "paging": {},
+            "results": [
+                {
+                    "text": "パンを食べる。",
+                    "lang": "jpn",
+                    "translations": [
+                        [],
+                        [
+                            { "text": "Je mange du pain.", "lang": "fra" },
+                            { "text": "I eat bread.", "lang": "eng" }
+                        ]
+                    ]
+                },
+                { "text": "何を食べたい？", "lang": "jpn" }
+            ]
+        }))
+        .unwrap();
+        assert_eq!(
+            parse_sentences(data),
+            vec![
+                ExampleSentence {
+                    japanese: "パンを食べる。".to_string(),
+                    english: Some("I eat bread.".to_string()),
+                },
+                ExampleSentence {
+                    japanese: "何を食べたい？".to_string(),
+                    english: None,
+                },
+            ]
+        );
+    }
+}
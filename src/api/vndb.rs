@@ -2,8 +2,44 @@
 // For visual novel metadata
 
 use anyhow::Result;
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 
+use crate::api::cache::{Fetched, RateLimit, TtlCache, LOOKUP_TTL, SEARCH_TTL};
+
+/// Most results a search asks for, smaller limits are served from the same entry
+const MAX_SEARCH_RESULTS: usize = 25;
+
+static RATE_LIMIT: RateLimit = RateLimit::new("VNDB");
+
+/// Keyed by lowercased query
+pub(super) static SEARCH_CACHE: Lazy<TtlCache<String, Vec<VnInfo>>> =
+    Lazy::new(|| TtlCache::new("VNDB search", 500, SEARCH_TTL));
+
+/// Keyed by VN id; search results lack the description, so they don't go here
+pub(super) static VN_CACHE: Lazy<TtlCache<String, Option<VnInfo>>> =
+    Lazy::new(|| TtlCache::new("VNDB vn", 2000, LOOKUP_TTL));
+
+/// Reading speed the length estimates assume, VNDB lengths are those of a fluent reader
+const CHARACTERS_PER_HOUR: f64 = 15_000.0;
+
+/// Hours of each VNDB length bucket (1 very short .. 5 very long), the top of its range
+/// so an estimate doesn't run past 100% halfway through
+const LENGTH_HOURS: [(i32, f64); 5] = [(1, 2.0), (2, 10.0), (3, 30.0), (4, 50.0), (5, 80.0)];
+
+/// Rough reading time of a VNDB length bucket
+pub fn estimated_hours(length: i32) -> Option<f64> {
+    LENGTH_HOURS
+        .iter()
+        .find(|(bucket, _)| *bucket == length)
+        .map(|(_, hours)| *hours)
+}
+
+/// Rough character count of a VNDB length bucket
+pub fn estimated_characters(length: i32) -> Option<f64> {
+    estimated_hours(length).map(|hours| hours * CHARACTERS_PER_HOUR)
+}
+
 /// VNDB visual novel info
 #[derive(Debug, Clone)]
 pub struct VnInfo {
@@ -23,10 +59,20 @@ pub async fn search_vns(
     query: &str,
     limit: usize,
 ) -> Result<Vec<VnInfo>> {
+    let results = SEARCH_CACHE
+        .get_or_fetch(query.trim().to_lowercase(), &RATE_LIMIT, || {
+            fetch_search(client, query)
+        })
+        .await?
+        .unwrap_or_default();
+    Ok(results.into_iter().take(limit).collect())
+}
+
+async fn fetch_search(client: &reqwest::Client, query: &str) -> Result<Fetched<Vec<VnInfo>>> {
     let request = VndbRequest {
         filters: vec!["search".to_string(), "=".to_string(), query.to_string()],
         fields: "id, title, image.url, released, length, developers.name".to_string(),
-        results: limit.min(25) as i32,
+        results: MAX_SEARCH_RESULTS as i32,
     };
 
     let response = client
@@ -35,8 +81,12 @@ pub async fn search_vns(
         .send()
         .await?;
 
+    if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        RATE_LIMIT.trip(response.headers());
+        return Ok(Fetched::Unavailable);
+    }
     if !response.status().is_success() {
-        return Ok(vec![]);
+        return Ok(Fetched::Unavailable);
     }
 
     let data: VndbResponse = response.json().await?;
@@ -56,11 +106,18 @@ pub async fn search_vns(
         })
         .collect();
 
-    Ok(results)
+    Ok(Fetched::Value(results))
 }
 
 /// Get visual novel info by ID
 pub async fn get_vn_by_id(client: &reqwest::Client, id: &str) -> Result<Option<VnInfo>> {
+    let vn = VN_CACHE
+        .get_or_fetch(id.to_lowercase(), &RATE_LIMIT, || fetch_vn(client, id))
+        .await?;
+    Ok(vn.flatten())
+}
+
+async fn fetch_vn(client: &reqwest::Client, id: &str) -> Result<Fetched<Option<VnInfo>>> {
     let request = VndbRequest {
         filters: vec!["id".to_string(), "=".to_string(), id.to_string()],
         fields: "id, title, image.url, released, length, developers.name, description".to_string(),
@@ -73,14 +130,18 @@ pub async fn get_vn_by_id(client: &reqwest::Client, id: &str) -> Result<Option<V
         .send()
         .await?;
 
+    if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        RATE_LIMIT.trip(response.headers());
+        return Ok(Fetched::Unavailable);
+    }
     if !response.status().is_success() {
-        return Ok(None);
+        return Ok(Fetched::Unavailable);
     }
 
     let data: VndbResponse = response.json().await?;
 
     if let Some(v) = data.results.first() {
-        Ok(Some(VnInfo {
+        Ok(Fetched::Value(Some(VnInfo {
             id: v.id.clone(),
             title: v.title.clone(),
             image: v.image.as_ref().map(|i| i.url.clone()),
@@ -89,9 +150,9 @@ pub async fn get_vn_by_id(client: &reqwest::Client, id: &str) -> Result<Option<V
             released: v.released.clone(),
             length: v.length,
             description: v.description.clone(),
-        }))
+        })))
     } else {
-        Ok(None)
+        Ok(Fetched::Value(None))
     }
 }
 
@@ -128,3 +189,17 @@ struct VndbImage {
 struct VndbDeveloper {
     name: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_length_estimates() {
+        assert_eq!(estimated_hours(3), Some(30.0));
+        assert_eq!(estimated_characters(1), Some(30_000.0));
+        assert_eq!(estimated_characters(5), Some(1_200_000.0));
+        assert_eq!(estimated_hours(0), None);
+        assert_eq!(estimated_characters(6), None);
+    }
+}
@@ -3,6 +3,7 @@
 
 use anyhow::Result;
 use serde::Deserialize;
+use std::collections::HashMap;
 
 /// YouTube video information
 #[derive(Debug, Clone)]
@@ -29,8 +30,8 @@ pub fn extract_video_id(input: &str) -> Option<String> {
     if input.contains("youtube.com/watch") {
         if let Some(v_param) = input.split("v=").nth(1) {
             let id = v_param.split('&').next().unwrap_or(v_param);
-            if id.len() >= 11 {
-                return Some(id[..11].to_string());
+            if let Some(id) = id.get(..11) {
+                return Some(id.to_string());
             }
         }
     }
@@ -39,8 +40,8 @@ pub fn extract_video_id(input: &str) -> Option<String> {
     if input.contains("youtu.be/") {
         if let Some(path) = input.split("youtu.be/").nth(1) {
             let id = path.split(['?', '&', '/'].as_ref()).next().unwrap_or(path);
-            if id.len() >= 11 {
-                return Some(id[..11].to_string());
+            if let Some(id) = id.get(..11) {
+                return Some(id.to_string());
             }
         }
     }
@@ -48,49 +49,168 @@ pub fn extract_video_id(input: &str) -> Option<String> {
     None
 }
 
+/// Extract playlist ID from a youtube.com/playlist URL
+pub fn extract_playlist_id(input: &str) -> Option<String> {
+    if !input.contains("youtube.com/playlist") {
+        return None;
+    }
+    let list = input.split("list=").nth(1)?;
+    let id = list.split(['&', '#', '/'].as_ref()).next().unwrap_or(list);
+    let valid = !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    valid.then(|| id.to_string())
+}
+
+/// Most videos one listening log can cover
+pub const MAX_VIDEOS: usize = 50;
+
+/// What a pasted listening message points at
+#[derive(Debug, Clone, PartialEq)]
+pub enum ListeningInput {
+    Playlist(String),
+    /// Video IDs in the order given, without repeats
+    Videos(Vec<String>),
+}
+
+/// Read a playlist URL or space/newline separated video URLs.
+/// A playlist wins over videos in the same message.
+pub fn parse_listening_input(text: &str) -> Option<ListeningInput> {
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+    if let Some(id) = tokens.iter().find_map(|t| extract_playlist_id(t)) {
+        return Some(ListeningInput::Playlist(id));
+    }
+
+    // A bare ID only counts on its own, any 11-letter word would pass for one
+    let single = tokens.len() == 1;
+    let mut ids: Vec<String> = Vec::new();
+    for id in tokens
+        .iter()
+        .filter(|t| single || t.contains("youtu"))
+        .filter_map(|t| extract_video_id(t))
+    {
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    (!ids.is_empty()).then_some(ListeningInput::Videos(ids))
+}
+
 /// Normalize YouTube URL to standard format
 pub fn normalize_url(video_id: &str) -> String {
     format!("https://youtube.com/watch?v={}", video_id)
 }
 
-/// Fetch video info from YouTube API
-pub async fn get_video_info(
+pub fn playlist_url(playlist_id: &str) -> String {
+    format!("https://youtube.com/playlist?list={}", playlist_id)
+}
+
+/// Fetch info of up to 50 videos in one request, keyed by video ID.
+/// Missing or private videos are simply absent.
+pub async fn get_videos_info(
     client: &reqwest::Client,
     api_key: &str,
-    video_id: &str,
-) -> Result<Option<VideoInfo>> {
+    video_ids: &[String],
+) -> Result<HashMap<String, VideoInfo>> {
+    if video_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let ids = video_ids[..video_ids.len().min(MAX_VIDEOS)].join(",");
     let url = format!(
         "https://www.googleapis.com/youtube/v3/videos?part=snippet,contentDetails&id={}&key={}",
-        video_id, api_key
+        ids, api_key
     );
 
     let response = client.get(&url).send().await?;
 
     if !response.status().is_success() {
-        return Ok(None);
+        return Ok(HashMap::new());
     }
 
     let data: YouTubeResponse = response.json().await?;
 
-    if data.items.is_empty() {
+    Ok(data
+        .items
+        .into_iter()
+        .map(|item| {
+            let info = VideoInfo {
+                title: item.snippet.title,
+                duration_seconds: parse_iso8601_duration(&item.content_details.duration),
+                thumbnail: item
+                    .snippet
+                    .thumbnails
+                    .get("high")
+                    .or_else(|| item.snippet.thumbnails.get("medium"))
+                    .or_else(|| item.snippet.thumbnails.get("default"))
+                    .map(|t| t.url.clone()),
+                channel: item.snippet.channel_title,
+            };
+            (item.id, info)
+        })
+        .collect())
+}
+
+/// Title of a playlist, `None` when it doesn't exist or is private
+pub async fn get_playlist_title(
+    client: &reqwest::Client,
+    api_key: &str,
+    playlist_id: &str,
+) -> Result<Option<String>> {
+    let url = format!(
+        "https://www.googleapis.com/youtube/v3/playlists?part=snippet&id={}&key={}",
+        playlist_id, api_key
+    );
+
+    let response = client.get(&url).send().await?;
+
+    if !response.status().is_success() {
         return Ok(None);
     }
 
-    let item = &data.items[0];
-    let duration = parse_iso8601_duration(&item.content_details.duration);
+    let data: PlaylistResponse = response.json().await?;
+    Ok(data.items.into_iter().next().map(|item| item.snippet.title))
+}
 
-    Ok(Some(VideoInfo {
-        title: item.snippet.title.clone(),
-        duration_seconds: duration,
-        thumbnail: item
-            .snippet
-            .thumbnails
-            .get("high")
-            .or_else(|| item.snippet.thumbnails.get("medium"))
-            .or_else(|| item.snippet.thumbnails.get("default"))
-            .map(|t| t.url.clone()),
-        channel: item.snippet.channel_title.clone(),
-    }))
+/// Video IDs of a playlist in playlist order, following pages until `limit` are collected
+pub async fn get_playlist_items(
+    client: &reqwest::Client,
+    api_key: &str,
+    playlist_id: &str,
+    limit: usize,
+) -> Result<Vec<String>> {
+    let mut video_ids = Vec::new();
+    let mut page_token: Option<String> = None;
+
+    while video_ids.len() < limit {
+        let mut url = format!(
+            "https://www.googleapis.com/youtube/v3/playlistItems?part=contentDetails&maxResults=50&playlistId={}&key={}",
+            playlist_id, api_key
+        );
+        if let Some(token) = &page_token {
+            url.push_str(&format!("&pageToken={}", token));
+        }
+
+        let response = client.get(&url).send().await?;
+        if !response.status().is_success() {
+            anyhow::bail!("YouTube playlistItems returned {}", response.status());
+        }
+
+        let page: PlaylistItemsResponse = response.json().await?;
+        video_ids.extend(
+            page.items
+                .into_iter()
+                .map(|item| item.content_details.video_id),
+        );
+
+        match page.next_page_token {
+            Some(token) => page_token = Some(token),
+            None => break,
+        }
+    }
+
+    video_ids.truncate(limit);
+    Ok(video_ids)
 }
 
 /// Parse ISO 8601 duration (PT1H30M45S) to seconds
@@ -125,6 +245,7 @@ struct YouTubeResponse {
 
 #[derive(Debug, Deserialize)]
 struct YouTubeVideoItem {
+    id: String,
     snippet: YouTubeSnippet,
     #[serde(rename = "contentDetails")]
     content_details: YouTubeContentDetails,
@@ -148,6 +269,41 @@ struct YouTubeThumbnail {
     url: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct PlaylistResponse {
+    items: Vec<PlaylistItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaylistItem {
+    snippet: PlaylistSnippet,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaylistSnippet {
+    title: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaylistItemsResponse {
+    #[serde(default)]
+    items: Vec<PlaylistVideo>,
+    #[serde(rename = "nextPageToken")]
+    next_page_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaylistVideo {
+    #[serde(rename = "contentDetails")]
+    content_details: PlaylistVideoDetails,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaylistVideoDetails {
+    #[serde(rename = "videoId")]
+    video_id: String,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -174,4 +330,48 @@ mod tests {
         assert_eq!(parse_iso8601_duration("PT10M"), 600);
         assert_eq!(parse_iso8601_duration("PT45S"), 45);
     }
+
+    #[test]
+    fn test_parse_listening_input() {
+        assert_eq!(
+            parse_listening_input(
+                "https://youtu.be/dQw4w9WgXcQ\nhttps://www.youtube.com/watch?v=9bZkp7q19f0 https://youtu.be/dQw4w9WgXcQ"
+            ),
+            Some(ListeningInput::Videos(vec![
+                "dQw4w9WgXcQ".to_string(),
+                "9bZkp7q19f0".to_string()
+            ]))
+        );
+        assert_eq!(
+            parse_listening_input(
+                "https://youtu.be/dQw4w9WgXcQ https://www.youtube.com/playlist?list=PLx0sYbCqOb8TBPRdmBHs5Iftvv9TPboYG&si=abc"
+            ),
+            Some(ListeningInput::Playlist(
+                "PLx0sYbCqOb8TBPRdmBHs5Iftvv9TPboYG".to_string()
+            ))
+        );
+        assert_eq!(parse_listening_input("nonton anime tadi"), None);
+        assert_eq!(
+            parse_listening_input("dQw4w9WgXcQ"),
+            Some(ListeningInput::Videos(vec!["dQw4w9WgXcQ".to_string()]))
+        );
+        assert_eq!(parse_listening_input("listening immersion12"), None);
+    }
+
+    #[test]
+    fn test_playlist_items_page() {
+        let page: PlaylistItemsResponse = serde_json::from_value(serde_json::json!({
+            "items": [
+                { "contentDetails": { "videoId": "dQw4w9WgXcQ" } },
+                { "contentDetails": { "videoId": "9bZkp7q19f0" } }
+            ],
+            "nextPageToken": "CAUQAA"
+        }))
+        .unwrap();
+        assert_eq!(page.items.len(), 2);
+        assert_eq!(page.next_page_token.as_deref(), Some("CAUQAA"));
+
+        let last: PlaylistItemsResponse = serde_json::from_value(serde_json::json!({})).unwrap();
+        assert!(last.items.is_empty() && last.next_page_token.is_none());
+    }
 }
@@ -0,0 +1,243 @@
+// Admin command - maintenance and internal state for the bot owner
+// Things that otherwise need a restart or a debugger: reloading the novel list,
+// the guilds the bot is in and what the process is holding on to
+
+use poise::serenity_prelude as serenity;
+use std::collections::HashSet;
+
+use crate::features::{ayumi, novel_recommender};
+use crate::utils::config::colors;
+use crate::utils::formatters::{
+    fit_description, format_number, EMBED_DESCRIPTION_LIMIT, EMBED_FIELD_LIMIT,
+};
+use crate::utils::metrics::{self, Kind, TimingRow};
+use crate::{Context, Error};
+
+/// Busiest names shown per metrics field
+const METRICS_SHOWN: usize = 8;
+
+/// Bot maintenance (owner only)
+#[poise::command(
+    slash_command,
+    hide_in_help,
+    owners_only = true,
+    subcommands("reload_novels", "guilds", "usage")
+)]
+pub async fn admin(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Re-read novelList.json without restarting
+#[poise::command(
+    slash_command,
+    hide_in_help,
+    owners_only = true,
+    rename = "reload-novels"
+)]
+pub async fn reload_novels(ctx: Context<'_>) -> Result<(), Error> {
+    let before = novel_recommender::get_novels().novels.len();
+    let content = match novel_recommender::reload_novels() {
+        Ok(count) => format!(
+            "Reloaded the novel list: {} novels (was {}).",
+            format_number(count as i64),
+            format_number(before as i64)
+        ),
+        Err(e) => format!("{}, kept the current {} novels.", e, before),
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .content(content)
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// List the guilds the bot is in
+#[poise::command(slash_command, hide_in_help, owners_only = true)]
+pub async fn guilds(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let configured: HashSet<String> = ctx
+        .data()
+        .firebase
+        .get_all_documents("guilds")
+        .await?
+        .iter()
+        .filter_map(|doc| doc.get("_id").and_then(|id| id.as_str()))
+        .map(str::to_string)
+        .collect();
+
+    let mut guilds: Vec<(serenity::GuildId, String, u64)> = ctx
+        .cache()
+        .guilds()
+        .into_iter()
+        .map(|id| match ctx.cache().guild(id) {
+            Some(guild) => (id, guild.name.clone(), guild.member_count),
+            None => (id, "(not cached)".to_string(), 0),
+        })
+        .collect();
+    guilds.sort_by(|a, b| b.2.cmp(&a.2).then(a.1.cmp(&b.1)));
+
+    let lines: Vec<String> = guilds
+        .iter()
+        .map(|(id, name, members)| {
+            format!(
+                "{} **{}** `{}` - {} members",
+                if configured.contains(&id.to_string()) {
+                    "⚙️"
+                } else {
+                    "▫️"
+                },
+                name,
+                id,
+                format_number(*members as i64)
+            )
+        })
+        .collect();
+
+    let embed = serenity::CreateEmbed::new()
+        .title(format!("Guilds ({})", guilds.len()))
+        .description(fit_description(lines, EMBED_DESCRIPTION_LIMIT).0)
+        .footer(serenity::CreateEmbedFooter::new("⚙️ has a config document"))
+        .color(colors::INFO);
+    ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Show uptime, caches and sessions
+#[poise::command(slash_command, hide_in_help, owners_only = true)]
+pub async fn usage(ctx: Context<'_>) -> Result<(), Error> {
+    let data = ctx.data();
+    let (conversations, messages) = ayumi::history_cache_size().await;
+    let token = match data.firebase.token_expires_at().await {
+        Some(expires_at) => format!("expires <t:{}:R>", expires_at),
+        None => "none cached".to_string(),
+    };
+
+    let snapshot = metrics::snapshot();
+    let tokens: Vec<String> = snapshot
+        .tokens
+        .iter()
+        .map(|row| {
+            format!(
+                "`{}` {} in, {} out",
+                row.model,
+                format_number(row.prompt as i64),
+                format_number(row.completion as i64)
+            )
+        })
+        .collect();
+
+    let embed = serenity::CreateEmbed::new()
+        .title("Usage")
+        .field(
+            "Uptime",
+            uptime_text(data.started_at.elapsed().as_secs()),
+            true,
+        )
+        .field(
+            "Guild configs cached",
+            data.guild_configs.len().to_string(),
+            true,
+        )
+        .field(
+            "Active quiz sessions",
+            data.role_rank_sessions.len().to_string(),
+            true,
+        )
+        .field(
+            "Ayumi history",
+            format!("{} users, {} messages", conversations, messages),
+            true,
+        )
+        .field(
+            "Local novels",
+            format_number(novel_recommender::get_novels().novels.len() as i64),
+            true,
+        )
+        .field("Firestore token", token, true)
+        .field("Commands", timing_lines(snapshot.of(Kind::Command)), false)
+        .field(
+            "Firestore requests",
+            timing_lines(snapshot.of(Kind::Firestore)),
+            false,
+        )
+        .field("LLM calls", timing_lines(snapshot.of(Kind::Llm)), false)
+        .field(
+            "LLM tokens",
+            if tokens.is_empty() {
+                "none yet".to_string()
+            } else {
+                fit_description(tokens, EMBED_FIELD_LIMIT).0
+            },
+            false,
+        )
+        .color(colors::INFO);
+    ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// "3d 4h 12m", minutes only below an hour
+fn uptime_text(secs: u64) -> String {
+    let (days, hours, minutes) = (secs / 86400, secs % 86400 / 3600, secs % 3600 / 60);
+    match (days, hours) {
+        (0, 0) => format!("{}m", minutes),
+        (0, _) => format!("{}h {}m", hours, minutes),
+        _ => format!("{}d {}h {}m", days, hours, minutes),
+    }
+}
+
+/// One line per name, busiest first: calls, failures, average and p95 latency
+fn timing_lines<'a>(rows: impl Iterator<Item = &'a TimingRow>) -> String {
+    let lines: Vec<String> = rows
+        .take(METRICS_SHOWN)
+        .map(|row| {
+            format!(
+                "`{}` {}× ({} failed), avg {}ms, p95 {}ms",
+                row.name,
+                format_number(row.count as i64),
+                row.errors,
+                row.avg_ms,
+                row.p95_ms
+            )
+        })
+        .collect();
+    if lines.is_empty() {
+        return "none yet".to_string();
+    }
+    fit_description(lines, EMBED_FIELD_LIMIT).0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uptime_text() {
+        assert_eq!(uptime_text(59), "0m");
+        assert_eq!(uptime_text(3 * 60), "3m");
+        assert_eq!(uptime_text(2 * 3600 + 5 * 60), "2h 5m");
+        assert_eq!(uptime_text(3 * 86400 + 4 * 3600 + 12 * 60), "3d 4h 12m");
+    }
+
+    #[test]
+    fn test_timing_lines() {
+        assert_eq!(timing_lines(std::iter::empty()), "none yet");
+        let row = TimingRow {
+            kind: Kind::Command,
+            name: "stat".to_string(),
+            count: 1200,
+            errors: 3,
+            avg_ms: 180,
+            p95_ms: 500,
+            max_ms: 2100,
+        };
+        assert_eq!(
+            timing_lines([row].iter()),
+            "`stat` 1,200× (3 failed), avg 180ms, p95 500ms"
+        );
+    }
+}
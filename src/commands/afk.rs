@@ -1,56 +1,114 @@
 // AFK command - set AFK status
 // Ported from commands/afk.js
 
-use once_cell::sync::Lazy;
 use poise::serenity_prelude as serenity;
-use std::collections::HashMap;
-use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
-use tokio::sync::RwLock;
+use tracing::error;
 
-use crate::utils::config::colors;
+use crate::models::afk::AfkData;
+use crate::utils::config::{colors, get_guild_config};
+use crate::utils::i18n::{t, tf, Msg};
 use crate::{Context, Error};
 
-/// AFK user data
-#[derive(Debug, Clone)]
-pub struct AfkData {
-    pub username: String,
-    pub reason: String,
-    pub timestamp: u64,
-    pub avatar_url: String,
+/// Longest AFK duration accepted
+const MAX_DURATION_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// Seconds in a duration like "30m", "2h", "1d" or "1h30m", at most 7 days
+pub fn parse_duration(input: &str) -> Option<u64> {
+    let input = input.trim().to_lowercase();
+    let mut total: u64 = 0;
+    let mut number = String::new();
+    for c in input.chars().filter(|c| !c.is_whitespace()) {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            _ => return None,
+        };
+        let amount: u64 = number.parse().ok()?;
+        total = total.checked_add(amount.checked_mul(unit)?)?;
+        number.clear();
+    }
+    // A trailing number without a unit
+    if !number.is_empty() || total == 0 || total > MAX_DURATION_SECS {
+        return None;
+    }
+    Some(total)
 }
 
-/// Global AFK users map (User ID -> AFK Data)
-pub static AFK_USERS: Lazy<Arc<RwLock<HashMap<u64, AfkData>>>> =
-    Lazy::new(|| Arc::new(RwLock::new(HashMap::new())));
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
 
 /// Set your AFK status
 #[poise::command(slash_command, prefix_command)]
 pub async fn afk(
     ctx: Context<'_>,
     #[description = "Alasan AFK (opsional)"] reason: Option<String>,
+    #[description = "Otomatis kembali setelah, mis. 30m, 2h, 1d (maks 7 hari)"] duration: Option<
+        String,
+    >,
 ) -> Result<(), Error> {
+    let config = match ctx.guild_id() {
+        Some(guild_id) => get_guild_config(ctx.data(), &guild_id.to_string()).await,
+        None => None,
+    };
+    let config = config.as_ref();
+
+    let timestamp = unix_now();
+    let expires_at = match duration.as_deref().map(parse_duration) {
+        None => None,
+        Some(Some(secs)) => Some(timestamp + secs),
+        Some(None) => {
+            ctx.send(
+                poise::CreateReply::default()
+                    .content(t(config, Msg::AfkInvalidDuration))
+                    .ephemeral(true),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
     let reason = reason.unwrap_or_else(|| "AFK".to_string());
     let user = ctx.author();
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
 
     // Store AFK data
-    {
-        let mut afk_users = AFK_USERS.write().await;
-        afk_users.insert(
-            user.id.get(),
-            AfkData {
-                username: user.name.clone(),
-                reason: reason.clone(),
-                timestamp,
-                avatar_url: user
-                    .avatar_url()
-                    .unwrap_or_else(|| user.default_avatar_url()),
-            },
-        );
+    let data = AfkData {
+        username: user.name.clone(),
+        reason: reason.clone(),
+        timestamp,
+        avatar_url: user
+            .avatar_url()
+            .unwrap_or_else(|| user.default_avatar_url()),
+        expires_at,
+        guild_id: ctx.guild_id().map(|id| id.to_string()),
+    };
+    if let Err(e) = ctx.data().afk.set(user.id.get(), data).await {
+        error!("Failed to save AFK status of {}: {:?}", user.id, e);
+        ctx.send(
+            poise::CreateReply::default()
+                .content(t(config, Msg::AfkSaveFailed))
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    let mut description = format!(
+        "{}\n{}",
+        t(config, Msg::AfkSet),
+        tf(config, Msg::AfkReason, &[&reason])
+    );
+    if let Some(at) = expires_at {
+        description.push('\n');
+        description.push_str(&tf(config, Msg::AfkBack, &[&at]));
     }
 
     let embed = serenity::CreateEmbed::new()
@@ -62,13 +120,11 @@ pub async fn afk(
             ),
         )
         .title("AFK")
-        .description(format!(
-            "User lain akan diberitahu kalau kamu sedang AFK.\n**Alasan:** {}",
-            reason
-        ))
-        .footer(serenity::CreateEmbedFooter::new(
-            "Kirim pesan lagi untuk menghapus status AFK",
-        ))
+        .description(description)
+        .footer(serenity::CreateEmbedFooter::new(t(
+            config,
+            Msg::AfkSetFooter,
+        )))
         .timestamp(serenity::Timestamp::now());
 
     ctx.send(poise::CreateReply::default().embed(embed)).await?;
@@ -76,20 +132,33 @@ pub async fn afk(
     Ok(())
 }
 
-/// Check if user is AFK and return their data
-pub async fn get_afk_data(user_id: u64) -> Option<AfkData> {
-    let afk_users = AFK_USERS.read().await;
-    afk_users.get(&user_id).cloned()
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-/// Remove user from AFK
-pub async fn remove_afk(user_id: u64) -> Option<AfkData> {
-    let mut afk_users = AFK_USERS.write().await;
-    afk_users.remove(&user_id)
-}
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30m"), Some(30 * 60));
+        assert_eq!(parse_duration("2h"), Some(2 * 60 * 60));
+        assert_eq!(parse_duration("1D"), Some(24 * 60 * 60));
+        assert_eq!(parse_duration(" 1h 30m "), Some(90 * 60));
+        assert_eq!(parse_duration("7d"), Some(MAX_DURATION_SECS));
+    }
 
-/// Check if user is AFK
-pub async fn is_afk(user_id: u64) -> bool {
-    let afk_users = AFK_USERS.read().await;
-    afk_users.contains_key(&user_id)
+    #[test]
+    fn test_parse_duration_rejects_invalid() {
+        for input in [
+            "",
+            "30",
+            "m",
+            "2 hours",
+            "0m",
+            "8d",
+            "1w",
+            "-1h",
+            "99999999999999999999d",
+        ] {
+            assert_eq!(parse_duration(input), None, "{:?}", input);
+        }
+    }
 }
@@ -0,0 +1,77 @@
+use tracing::{error, info};
+
+use crate::commands::config::load_config_for_update;
+use crate::{Context, Error};
+
+/// Control whether Ayumi replies to you
+#[poise::command(slash_command, guild_only, subcommands("mute", "unmute"))]
+pub async fn ayumi(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Stop Ayumi from replying to your messages in her channel
+#[poise::command(slash_command, guild_only)]
+pub async fn mute(ctx: Context<'_>) -> Result<(), Error> {
+    set_muted(ctx, true).await
+}
+
+/// Let Ayumi reply to your messages in her channel again
+#[poise::command(slash_command, guild_only)]
+pub async fn unmute(ctx: Context<'_>) -> Result<(), Error> {
+    set_muted(ctx, false).await
+}
+
+async fn set_muted(ctx: Context<'_>, muted: bool) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    let guild_id = guild_id.to_string();
+    let user_id = ctx.author().id.to_string();
+    let data = ctx.data();
+
+    let mut config = match load_config_for_update(data, &guild_id).await {
+        Ok(c) => c,
+        Err(e) => {
+            error!("Failed to fetch guild config: {:?}", e);
+            ctx.say("Failed to fetch configuration.").await?;
+            return Ok(());
+        }
+    };
+
+    let already = config.ayumi_muted_user_ids.contains(&user_id) == muted;
+    if !already {
+        if muted {
+            config.ayumi_muted_user_ids.push(user_id.clone());
+        } else {
+            config.ayumi_muted_user_ids.retain(|id| *id != user_id);
+        }
+
+        let json_val = serde_json::to_value(&config)?;
+        if let Err(e) = data
+            .firebase
+            .set_document("guilds", &guild_id, &json_val)
+            .await
+        {
+            error!("Failed to save guild config: {:?}", e);
+            ctx.say("Failed to save your setting.").await?;
+            return Ok(());
+        }
+        info!(
+            "User {} {} Ayumi in guild {}",
+            user_id,
+            if muted { "muted" } else { "unmuted" },
+            guild_id
+        );
+        data.guild_configs.insert(guild_id, config);
+    }
+
+    let reply = match (muted, already) {
+        (true, false) => "Ayumi won't reply to your messages in her channel anymore. Use `/ayumi unmute` to undo.",
+        (true, true) => "Ayumi is already muted for you.",
+        (false, false) => "Ayumi will reply to your messages in her channel again.",
+        (false, true) => "Ayumi isn't muted for you.",
+    };
+    ctx.say(reply).await?;
+    Ok(())
+}
@@ -1,12 +1,21 @@
 use poise::serenity_prelude as serenity;
+use poise::ChoiceParameter;
+use std::collections::HashMap;
 use tracing::{error, info};
 
-use crate::models::guild::GuildConfig;
-use crate::utils::config::colors;
+use crate::commands::immersion::MediaType;
+use crate::commands::role_rank::DEFAULT_SELECTOR_TITLE;
+use crate::features::reaction_log::parse_emoji;
+use crate::features::role_rank::{configured_quiz_bots, parse_bot_id, QUIZZES};
+use crate::models::guild::{GuildConfig, ReactionLogConfig};
+use crate::utils::config::{colors, get_media_label, get_unit};
+use crate::utils::i18n::Language;
+use crate::utils::points::{effective_multiplier, points_multipliers};
+use crate::utils::validation::validate_amount;
 use crate::{Context, Error};
 
 /// Configuration options
-#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
 pub enum ConfigKey {
     #[name = "Ayumi Channel"]
     AyumiChannel,
@@ -14,10 +23,95 @@ pub enum ConfigKey {
     QuizChannel,
     #[name = "Quiz Category"]
     QuizCategory,
+    #[name = "Welcome Channel"]
+    WelcomeChannel,
     #[name = "Immersion Channel"]
     ImmersionChannel,
     #[name = "Role Rank Announcement"]
     RoleRankAnnouncement,
+    #[name = "Weekly Recap Channel"]
+    RecapChannel,
+    #[name = "Mod Log Channel"]
+    ModLogChannel,
+    #[name = "Activity Feed Channel"]
+    ActivityFeedChannel,
+}
+
+impl ConfigKey {
+    /// Every key, in the order `/config view` lists them
+    pub const ALL: [ConfigKey; 9] = [
+        ConfigKey::AyumiChannel,
+        ConfigKey::QuizChannel,
+        ConfigKey::QuizCategory,
+        ConfigKey::WelcomeChannel,
+        ConfigKey::ImmersionChannel,
+        ConfigKey::RoleRankAnnouncement,
+        ConfigKey::RecapChannel,
+        ConfigKey::ModLogChannel,
+        ConfigKey::ActivityFeedChannel,
+    ];
+
+    /// Whether the key takes a category rather than a text channel
+    fn is_category(self) -> bool {
+        self == ConfigKey::QuizCategory
+    }
+
+    /// The GuildConfig field the key is stored in
+    fn slot(self, config: &mut GuildConfig) -> &mut Option<String> {
+        match self {
+            ConfigKey::AyumiChannel => &mut config.ayumi_channel_id,
+            ConfigKey::QuizChannel => &mut config.quiz_channel_id,
+            ConfigKey::QuizCategory => &mut config.quiz_category_id,
+            ConfigKey::WelcomeChannel => &mut config.welcome_channel_id,
+            ConfigKey::ImmersionChannel => &mut config.immersion_channel_id,
+            ConfigKey::RoleRankAnnouncement => &mut config.role_rank_announcement_channel_id,
+            ConfigKey::RecapChannel => &mut config.recap_channel_id,
+            ConfigKey::ModLogChannel => &mut config.mod_log_channel_id,
+            ConfigKey::ActivityFeedChannel => &mut config.activity_feed_channel_id,
+        }
+    }
+
+    fn value(self, config: &GuildConfig) -> Option<&str> {
+        match self {
+            ConfigKey::AyumiChannel => config.ayumi_channel_id.as_deref(),
+            ConfigKey::QuizChannel => config.quiz_channel_id.as_deref(),
+            ConfigKey::QuizCategory => config.quiz_category_id.as_deref(),
+            ConfigKey::WelcomeChannel => config.welcome_channel_id.as_deref(),
+            ConfigKey::ImmersionChannel => config.immersion_channel_id.as_deref(),
+            ConfigKey::RoleRankAnnouncement => config.role_rank_announcement_channel_id.as_deref(),
+            ConfigKey::RecapChannel => config.recap_channel_id.as_deref(),
+            ConfigKey::ModLogChannel => config.mod_log_channel_id.as_deref(),
+            ConfigKey::ActivityFeedChannel => config.activity_feed_channel_id.as_deref(),
+        }
+    }
+}
+
+/// Why `kind` can't be used for `key`, `None` when it fits
+fn channel_mismatch(key: ConfigKey, kind: serenity::ChannelType) -> Option<String> {
+    use serenity::ChannelType;
+
+    let fits = if key.is_category() {
+        kind == ChannelType::Category
+    } else {
+        matches!(kind, ChannelType::Text | ChannelType::News)
+    };
+    if fits {
+        return None;
+    }
+    Some(if key.is_category() {
+        format!("**{}** needs a category, not a channel.", key.name())
+    } else {
+        format!("**{}** needs a text channel.", key.name())
+    })
+}
+
+/// Toggleable guild features
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum GuildFeature {
+    #[name = "Unfurl Learning Links"]
+    UnfurlLearningLinks,
+    #[name = "Stat Lookup of Other Members"]
+    StatLookup,
 }
 
 /// Manage bot configuration
@@ -25,18 +119,66 @@ pub enum ConfigKey {
     slash_command,
     prefix_command,
     // required_permissions = "MANAGE_GUILD", // Removed for manual check
-    subcommands("set", "get")
+    subcommands(
+        "set",
+        "clear",
+        "view",
+        "get",
+        "feature",
+        "points",
+        "quiz_bot",
+        "quiz_role",
+        "quiz_selector",
+        "reaction_log"
+    )
 )]
 pub async fn config(_ctx: Context<'_>) -> Result<(), Error> {
     Ok(())
 }
 
 /// Set a configuration value
-#[poise::command(slash_command)]
-pub async fn set(
+#[poise::command(slash_command, subcommands("set_channel", "set_language"))]
+pub async fn set(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Set the channel (or category) of a setting
+#[poise::command(slash_command, rename = "channel")]
+pub async fn set_channel(
     ctx: Context<'_>,
     #[description = "Setting to configure"] key: ConfigKey,
-    #[description = "Channel to use"] channel: serenity::Channel,
+    #[description = "Channel (or category, for Quiz Category) to use"]
+    #[channel_types("Text", "News", "Category")]
+    channel: serenity::Channel,
+) -> Result<(), Error> {
+    let kind = match &channel {
+        serenity::Channel::Guild(c) => c.kind,
+        _ => {
+            ctx.say("Pick a channel from this server.").await?;
+            return Ok(());
+        }
+    };
+    if let Some(message) = channel_mismatch(key, kind) {
+        ctx.say(message).await?;
+        return Ok(());
+    }
+    update_channel(ctx, key, Some(channel.id())).await
+}
+
+/// Unset a configuration value
+#[poise::command(slash_command)]
+pub async fn clear(
+    ctx: Context<'_>,
+    #[description = "Setting to unset"] key: ConfigKey,
+) -> Result<(), Error> {
+    update_channel(ctx, key, None).await
+}
+
+/// Store (or with `None`, remove) the channel of a key
+async fn update_channel(
+    ctx: Context<'_>,
+    key: ConfigKey,
+    channel_id: Option<serenity::ChannelId>,
 ) -> Result<(), Error> {
     let guild_id = match ctx.guild_id() {
         Some(id) => id.to_string(),
@@ -57,36 +199,20 @@ pub async fn set(
 
     ctx.defer().await?;
 
-    let channel_id = channel.id().to_string();
+    let channel_id = channel_id.map(|id| id.to_string());
     let data = ctx.data();
 
     // Fetch existing config or create new
-    // Check cache first
-    let mut config = if let Some(cached) = data.guild_configs.get(&guild_id) {
-        cached.clone()
-    } else {
-        match data.firebase.get_document("guilds", &guild_id).await {
-            Ok(Some(doc)) => serde_json::from_value::<GuildConfig>(doc).unwrap_or_default(),
-            Ok(None) => GuildConfig::default(),
-            Err(e) => {
-                error!("Failed to fetch guild config: {:?}", e);
-                ctx.say("Failed to fetch configuration.").await?;
-                return Ok(());
-            }
+    let mut config = match load_config_for_update(data, &guild_id).await {
+        Ok(c) => c,
+        Err(e) => {
+            error!("Failed to fetch guild config: {:?}", e);
+            ctx.say("Failed to fetch configuration.").await?;
+            return Ok(());
         }
     };
 
-    // Update config
-    match key {
-        ConfigKey::AyumiChannel => config.ayumi_channel_id = Some(channel_id.clone()),
-        ConfigKey::QuizChannel => config.quiz_channel_id = Some(channel_id.clone()),
-        ConfigKey::QuizCategory => config.quiz_category_id = Some(channel_id.clone()),
-
-        ConfigKey::ImmersionChannel => config.immersion_channel_id = Some(channel_id.clone()),
-        ConfigKey::RoleRankAnnouncement => {
-            config.role_rank_announcement_channel_id = Some(channel_id.clone())
-        }
-    }
+    *key.slot(&mut config) = channel_id.clone();
 
     // Save back to Firebase
     let json_val = serde_json::to_value(&config)?;
@@ -97,15 +223,19 @@ pub async fn set(
     {
         Ok(_) => {
             info!(
-                "Updated config for guild {}: {:?} -> {}",
+                "Updated config for guild {}: {:?} -> {:?}",
                 guild_id, key, channel_id
             );
             // Update cache
             data.guild_configs.insert(guild_id.clone(), config);
 
+            let description = match channel_id {
+                Some(id) => format!("**{}** set to <#{}>", key.name(), id),
+                None => format!("**{}** cleared", key.name()),
+            };
             let embed = serenity::CreateEmbed::new()
                 .title("Configuration Updated")
-                .description(format!("**{:?}** set to <#{}>", key, channel_id))
+                .description(description)
                 .color(colors::SUCCESS);
             ctx.send(poise::CreateReply::default().embed(embed)).await?;
         }
@@ -118,9 +248,13 @@ pub async fn set(
     Ok(())
 }
 
-/// Get current configuration
+/// Enable or disable an optional feature
 #[poise::command(slash_command)]
-pub async fn get(ctx: Context<'_>) -> Result<(), Error> {
+pub async fn feature(
+    ctx: Context<'_>,
+    #[description = "Feature to toggle"] feature: GuildFeature,
+    #[description = "Enable or disable"] enabled: bool,
+) -> Result<(), Error> {
     let guild_id = match ctx.guild_id() {
         Some(id) => id.to_string(),
         None => {
@@ -141,54 +275,662 @@ pub async fn get(ctx: Context<'_>) -> Result<(), Error> {
     ctx.defer().await?;
     let data = ctx.data();
 
-    // Check cache first
-    let config = if let Some(cached) = data.guild_configs.get(&guild_id) {
-        cached.clone()
-    } else {
-        match data.firebase.get_document("guilds", &guild_id).await {
-            Ok(Some(doc)) => {
-                let cfg = serde_json::from_value::<GuildConfig>(doc).unwrap_or_default();
-                // Populate cache
-                data.guild_configs.insert(guild_id.clone(), cfg.clone());
-                cfg
+    let mut config = match load_config_for_update(data, &guild_id).await {
+        Ok(c) => c,
+        Err(e) => {
+            error!("Failed to fetch guild config: {:?}", e);
+            ctx.say("Failed to fetch configuration.").await?;
+            return Ok(());
+        }
+    };
+
+    match feature {
+        GuildFeature::UnfurlLearningLinks => config.unfurl_learning_links = enabled,
+        GuildFeature::StatLookup => config.allow_stat_lookup = Some(enabled),
+    }
+
+    let json_val = serde_json::to_value(&config)?;
+    match data
+        .firebase
+        .set_document("guilds", &guild_id, &json_val)
+        .await
+    {
+        Ok(_) => {
+            info!(
+                "Updated feature for guild {}: {:?} -> {}",
+                guild_id, feature, enabled
+            );
+            data.guild_configs.insert(guild_id.clone(), config);
+
+            let embed = serenity::CreateEmbed::new()
+                .title("Configuration Updated")
+                .description(format!(
+                    "**{:?}** {}",
+                    feature,
+                    if enabled { "enabled" } else { "disabled" }
+                ))
+                .color(colors::SUCCESS);
+            ctx.send(poise::CreateReply::default().embed(embed)).await?;
+        }
+        Err(e) => {
+            error!("Failed to save guild config: {:?}", e);
+            ctx.say("Failed to save configuration.").await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Set the language of the bot's messages in this server
+#[poise::command(slash_command, rename = "language")]
+pub async fn set_language(
+    ctx: Context<'_>,
+    #[description = "Language of role rank, AFK and channel notices"] language: Language,
+) -> Result<(), Error> {
+    let guild_id = match ctx.guild_id() {
+        Some(id) => id.to_string(),
+        None => {
+            ctx.say("This command can only be used in a server.")
+                .await?;
+            return Ok(());
+        }
+    };
+
+    if !check_access(ctx).await? {
+        ctx.say(
+            "You do not have permission to use this command (Requires MANAGE_GUILD or Bot Owner).",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    ctx.defer().await?;
+    let data = ctx.data();
+
+    let mut config = match load_config_for_update(data, &guild_id).await {
+        Ok(c) => c,
+        Err(e) => {
+            error!("Failed to fetch guild config: {:?}", e);
+            ctx.say("Failed to fetch configuration.").await?;
+            return Ok(());
+        }
+    };
+
+    config.language = Some(language.code().to_string());
+
+    let json_val = serde_json::to_value(&config)?;
+    match data
+        .firebase
+        .set_document("guilds", &guild_id, &json_val)
+        .await
+    {
+        Ok(_) => {
+            info!(
+                "Updated language for guild {}: {}",
+                guild_id,
+                language.code()
+            );
+            data.guild_configs.insert(guild_id.clone(), config);
+
+            let embed = serenity::CreateEmbed::new()
+                .title("Configuration Updated")
+                .description(format!(
+                    "Bot messages are now in **{}**. Existing quiz selectors keep their text until they're refreshed.",
+                    language.name()
+                ))
+                .color(colors::SUCCESS);
+            ctx.send(poise::CreateReply::default().embed(embed)).await?;
+        }
+        Err(e) => {
+            error!("Failed to save guild config: {:?}", e);
+            ctx.say("Failed to save configuration.").await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Configure how many points each media type is worth in this server
+#[poise::command(slash_command, subcommands("points_set", "points_view"))]
+pub async fn points(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Set the points multiplier for a media type
+#[poise::command(slash_command, rename = "set")]
+pub async fn points_set(
+    ctx: Context<'_>,
+    #[description = "Media type"] media_type: MediaType,
+    #[description = "Points per unit (e.g. 13 for anime episodes)"]
+    #[min = 0]
+    #[max = 1000]
+    multiplier: f64,
+) -> Result<(), Error> {
+    let guild_id = match ctx.guild_id() {
+        Some(id) => id.to_string(),
+        None => {
+            ctx.say("This command can only be used in a server.")
+                .await?;
+            return Ok(());
+        }
+    };
+
+    if !check_access(ctx).await? {
+        ctx.say(
+            "You do not have permission to use this command (Requires MANAGE_GUILD or Bot Owner).",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    if !multiplier.is_finite() || !(0.0..=1000.0).contains(&multiplier) {
+        ctx.say("Multiplier must be between 0 and 1000.").await?;
+        return Ok(());
+    }
+
+    ctx.defer().await?;
+    let data = ctx.data();
+
+    let mut config = match load_config_for_update(data, &guild_id).await {
+        Ok(c) => c,
+        Err(e) => {
+            error!("Failed to fetch guild config: {:?}", e);
+            ctx.say("Failed to fetch configuration.").await?;
+            return Ok(());
+        }
+    };
+
+    config
+        .points_overrides
+        .get_or_insert_with(Default::default)
+        .insert(media_type.as_str().to_string(), multiplier);
+
+    let json_val = serde_json::to_value(&config)?;
+    match data
+        .firebase
+        .set_document("guilds", &guild_id, &json_val)
+        .await
+    {
+        Ok(_) => {
+            info!(
+                "Updated points multiplier for guild {}: {} -> {}",
+                guild_id,
+                media_type.as_str(),
+                multiplier
+            );
+            data.guild_configs.insert(guild_id.clone(), config);
+
+            let embed = serenity::CreateEmbed::new()
+                .title("Configuration Updated")
+                .description(format!(
+                    "**{}** is now worth **{}** points per unit",
+                    get_media_label(media_type.as_str()),
+                    multiplier
+                ))
+                .color(colors::SUCCESS);
+            ctx.send(poise::CreateReply::default().embed(embed)).await?;
+        }
+        Err(e) => {
+            error!("Failed to save guild config: {:?}", e);
+            ctx.say("Failed to save configuration.").await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Show the points multipliers used in this server
+#[poise::command(slash_command, rename = "view")]
+pub async fn points_view(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = match ctx.guild_id() {
+        Some(id) => id.to_string(),
+        None => {
+            ctx.say("This command can only be used in a server.")
+                .await?;
+            return Ok(());
+        }
+    };
+
+    if !check_access(ctx).await? {
+        ctx.say(
+            "You do not have permission to use this command (Requires MANAGE_GUILD or Bot Owner).",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    ctx.defer().await?;
+    let data = ctx.data();
+
+    let config = match load_config_for_update(data, &guild_id).await {
+        Ok(c) => c,
+        Err(e) => {
+            error!("Failed to fetch guild config: {:?}", e);
+            ctx.say("Failed to fetch configuration.").await?;
+            return Ok(());
+        }
+    };
+
+    let embed = serenity::CreateEmbed::new()
+        .title("Points Multipliers")
+        .description(format_multipliers(config.points_overrides.as_ref()))
+        .footer(serenity::CreateEmbedFooter::new(
+            "Points are recalculated from logged amounts, so changes apply to past logs too",
+        ))
+        .color(colors::INFO);
+
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
+/// One line per media type with the effective multiplier, marking the built-in ones
+fn format_multipliers(overrides: Option<&HashMap<String, f64>>) -> String {
+    let mut media_types: Vec<&str> = points_multipliers().into_keys().collect();
+    media_types.sort_unstable();
+
+    media_types
+        .into_iter()
+        .map(|media_type| {
+            let multiplier = effective_multiplier(media_type, overrides);
+            let is_override = overrides.is_some_and(|o| o.contains_key(media_type));
+            format!(
+                "**{}**: {}{}",
+                get_media_label(media_type),
+                format_multiplier(multiplier),
+                if is_override { "" } else { " *(default)*" }
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn format_multiplier(multiplier: f64) -> String {
+    let formatted = format!("{:.4}", multiplier);
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// Configure which Kotoba-compatible bots may finish role rank quizzes
+#[poise::command(
+    slash_command,
+    rename = "quiz_bot",
+    subcommands("quiz_bot_add", "quiz_bot_remove")
+)]
+pub async fn quiz_bot(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Accept quiz results from another bot (Kotoba counts only while none are configured)
+#[poise::command(slash_command, rename = "add")]
+pub async fn quiz_bot_add(
+    ctx: Context<'_>,
+    #[description = "User ID of the quiz bot"] bot_id: String,
+) -> Result<(), Error> {
+    let Some(id) = parse_bot_id(&bot_id) else {
+        ctx.say(format!(
+            "`{}` is not a valid Discord user ID.",
+            bot_id.trim()
+        ))
+        .await?;
+        return Ok(());
+    };
+    update_quiz_bots(ctx, |ids| {
+        if ids
+            .iter()
+            .any(|existing| parse_bot_id(existing) == Some(id))
+        {
+            return Err(format!("<@{}> is already a quiz bot.", id));
+        }
+        ids.push(id.to_string());
+        Ok(())
+    })
+    .await
+}
+
+/// Stop accepting quiz results from a bot
+#[poise::command(slash_command, rename = "remove")]
+pub async fn quiz_bot_remove(
+    ctx: Context<'_>,
+    #[description = "User ID of the quiz bot"] bot_id: String,
+) -> Result<(), Error> {
+    let Some(id) = parse_bot_id(&bot_id) else {
+        ctx.say(format!(
+            "`{}` is not a valid Discord user ID.",
+            bot_id.trim()
+        ))
+        .await?;
+        return Ok(());
+    };
+    update_quiz_bots(ctx, |ids| {
+        let before = ids.len();
+        ids.retain(|existing| parse_bot_id(existing) != Some(id));
+        if ids.len() == before {
+            return Err(format!("<@{}> is not a configured quiz bot.", id));
+        }
+        Ok(())
+    })
+    .await
+}
+
+/// Apply a change to the guild's quiz bot list and save it.
+/// `change` returns the message to show when there is nothing to change.
+async fn update_quiz_bots(
+    ctx: Context<'_>,
+    change: impl FnOnce(&mut Vec<String>) -> Result<(), String>,
+) -> Result<(), Error> {
+    update_config(ctx, "quiz bots", |config| {
+        change(&mut config.quiz_bot_ids)?;
+        Ok(format!(
+            "Quiz results are accepted from: {}\nExisting quiz channels keep their old permissions.",
+            format_quiz_bots(config)
+        ))
+    })
+    .await
+}
+
+/// Set the role a quiz level gives in this server
+#[poise::command(slash_command)]
+pub async fn quiz_role(
+    ctx: Context<'_>,
+    #[description = "Quiz level (0 is Kanji Wakaran)"]
+    #[min = 0]
+    #[max = 7]
+    level: i32,
+    #[description = "Role for passing it, leave out to use the built-in role"] role: Option<
+        serenity::Role,
+    >,
+) -> Result<(), Error> {
+    let Some(quiz) = QUIZZES.values().find(|q| q.level == level) else {
+        ctx.say(format!("There is no quiz level {}.", level))
+            .await?;
+        return Ok(());
+    };
+    update_config(ctx, "quiz roles", |config| {
+        let roles = config.quiz_role_ids.get_or_insert_with(Default::default);
+        match &role {
+            Some(role) => {
+                roles.insert(level.to_string(), role.id.to_string());
             }
-            Ok(None) => GuildConfig::default(),
-            Err(e) => {
-                error!("Failed to fetch guild config: {:?}", e);
-                ctx.say("Failed to fetch configuration.").await?;
-                return Ok(());
+            None => {
+                roles.remove(&level.to_string());
             }
         }
+        if roles.is_empty() {
+            config.quiz_role_ids = None;
+        }
+        Ok(format!(
+            "Passing **{}** now gives <@&{}>.\nMembers keep the roles they already have.",
+            quiz.label,
+            quiz.role_in(Some(config))
+        ))
+    })
+    .await
+}
+
+/// Customize the quiz selector message, leave an option out to use the built-in text
+#[poise::command(slash_command)]
+pub async fn quiz_selector(
+    ctx: Context<'_>,
+    #[description = "Embed title"]
+    #[max_length = 256]
+    title: Option<String>,
+    #[description = "Embed description"]
+    #[max_length = 4000]
+    description: Option<String>,
+) -> Result<(), Error> {
+    let non_empty =
+        |text: Option<String>| text.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+    let (title, description) = (non_empty(title), non_empty(description));
+    update_config(ctx, "quiz selector", |config| {
+        config.quiz_selector_title = title;
+        config.quiz_selector_description = description;
+        Ok(format!(
+            "The quiz selector is now titled **{}**.\nRun `/role_rank setup` in the quiz channel to post it again.",
+            config
+                .quiz_selector_title
+                .as_deref()
+                .unwrap_or(DEFAULT_SELECTOR_TITLE)
+        ))
+    })
+    .await
+}
+
+/// Log a fixed amount when members react on messages in a channel
+#[poise::command(slash_command)]
+pub async fn reaction_log(
+    ctx: Context<'_>,
+    #[description = "Channel of the prompts, leave out to turn reaction logging off"]
+    #[channel_types("Text", "News")]
+    channel: Option<serenity::GuildChannel>,
+    #[description = "Emoji to react with, e.g. 📖"] emoji: Option<String>,
+    #[description = "Media type each reaction logs"] media_type: Option<MediaType>,
+    #[description = "Amount each reaction logs"] amount: Option<f64>,
+) -> Result<(), Error> {
+    update_config(ctx, "reaction log", |config| {
+        let Some(channel) = channel else {
+            config.reaction_log = None;
+            return Ok("Reaction logging is off.".to_string());
+        };
+        let (Some(emoji), Some(media_type), Some(amount)) = (emoji, media_type, amount) else {
+            return Err("Reaction logging needs an emoji, a media type and an amount.".to_string());
+        };
+        if parse_emoji(&emoji).is_none() {
+            return Err(format!("**{}** isn't an emoji.", emoji));
+        }
+        validate_amount(media_type.as_str(), amount).map_err(|e| e.to_string())?;
+        let settings = ReactionLogConfig {
+            channel_id: channel.id.to_string(),
+            emoji: emoji.trim().to_string(),
+            media_type: media_type.as_str().to_string(),
+            amount,
+        };
+        let confirmation = format!(
+            "Reacting {} on messages in <#{}> now logs **{}**.\nEach message logs once per member.",
+            settings.emoji,
+            settings.channel_id,
+            format_reaction_amount(&settings)
+        );
+        config.reaction_log = Some(settings);
+        Ok(confirmation)
+    })
+    .await
+}
+
+/// "15 minutes of Reading Time"
+fn format_reaction_amount(settings: &ReactionLogConfig) -> String {
+    format!(
+        "{} {} of {}",
+        settings.amount,
+        get_unit(&settings.media_type),
+        get_media_label(&settings.media_type)
+    )
+}
+
+/// Apply a change to the guild config and save it. `change` returns the confirmation,
+/// or the message to show when there is nothing to change.
+async fn update_config(
+    ctx: Context<'_>,
+    setting: &str,
+    change: impl FnOnce(&mut GuildConfig) -> Result<String, String>,
+) -> Result<(), Error> {
+    let guild_id = match ctx.guild_id() {
+        Some(id) => id.to_string(),
+        None => {
+            ctx.say("This command can only be used in a server.")
+                .await?;
+            return Ok(());
+        }
     };
 
-    let ayumi = config
-        .ayumi_channel_id
-        .map(|id| format!("<#{}>", id))
-        .unwrap_or_else(|| "Not set".to_string());
-    let quiz = config
-        .quiz_channel_id
-        .map(|id| format!("<#{}>", id))
-        .unwrap_or_else(|| "Not set".to_string());
-    let quiz_cat = config
-        .quiz_category_id
-        .map(|id| format!("<#{}>", id))
-        .unwrap_or_else(|| "Not set".to_string());
-    let immersion = config
-        .immersion_channel_id
-        .map(|id| format!("<#{}>", id))
-        .unwrap_or_else(|| "Not set".to_string());
-    let role_rank = config
-        .role_rank_announcement_channel_id
-        .map(|id| format!("<#{}>", id))
-        .unwrap_or_else(|| "Not set".to_string());
+    if !check_access(ctx).await? {
+        ctx.say(
+            "You do not have permission to use this command (Requires MANAGE_GUILD or Bot Owner).",
+        )
+        .await?;
+        return Ok(());
+    }
 
-    let embed = serenity::CreateEmbed::new()
-        .title("Server Configuration")
-        .field("Ayumi Channel", ayumi, true)
-        .field("Quiz Channel", quiz, true)
-        .field("Quiz Category", quiz_cat, true)
-        .field("Immersion Channel", immersion, true)
-        .field("Role Rank Updates", role_rank, true)
+    ctx.defer().await?;
+    let data = ctx.data();
+
+    let mut config = match load_config_for_update(data, &guild_id).await {
+        Ok(c) => c,
+        Err(e) => {
+            error!("Failed to fetch guild config: {:?}", e);
+            ctx.say("Failed to fetch configuration.").await?;
+            return Ok(());
+        }
+    };
+
+    let confirmation = match change(&mut config) {
+        Ok(confirmation) => confirmation,
+        Err(message) => {
+            ctx.say(message).await?;
+            return Ok(());
+        }
+    };
+
+    let json_val = serde_json::to_value(&config)?;
+    match data
+        .firebase
+        .set_document("guilds", &guild_id, &json_val)
+        .await
+    {
+        Ok(_) => {
+            info!("Updated {} for guild {}", setting, guild_id);
+            let embed = serenity::CreateEmbed::new()
+                .title("Configuration Updated")
+                .description(confirmation)
+                .color(colors::SUCCESS);
+            data.guild_configs.insert(guild_id.clone(), config);
+            ctx.send(poise::CreateReply::default().embed(embed)).await?;
+        }
+        Err(e) => {
+            error!("Failed to save guild config: {:?}", e);
+            ctx.say("Failed to save configuration.").await?;
+        }
+    }
+
+    Ok(())
+}
+
+fn format_quiz_bots(config: &GuildConfig) -> String {
+    let bots = configured_quiz_bots(Some(config))
+        .iter()
+        .map(|id| format!("<@{}>", id))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if config.quiz_bot_ids.is_empty() {
+        format!("{} (Kotoba, default)", bots)
+    } else {
+        bots
+    }
+}
+
+/// Show every setting of this server
+#[poise::command(slash_command)]
+pub async fn view(ctx: Context<'_>) -> Result<(), Error> {
+    show_config(ctx).await
+}
+
+/// Get current configuration, same as `/config view`
+#[poise::command(slash_command)]
+pub async fn get(ctx: Context<'_>) -> Result<(), Error> {
+    show_config(ctx).await
+}
+
+async fn show_config(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = match ctx.guild_id() {
+        Some(id) => id.to_string(),
+        None => {
+            ctx.say("This command can only be used in a server.")
+                .await?;
+            return Ok(());
+        }
+    };
+
+    if !check_access(ctx).await? {
+        ctx.say(
+            "You do not have permission to use this command (Requires MANAGE_GUILD or Bot Owner).",
+        )
+        .await?;
+        return Ok(());
+    }
+
+    ctx.defer().await?;
+    let data = ctx.data();
+
+    let config = match load_config_for_update(data, &guild_id).await {
+        Ok(c) => c,
+        Err(e) => {
+            error!("Failed to fetch guild config: {:?}", e);
+            ctx.say("Failed to fetch configuration.").await?;
+            return Ok(());
+        }
+    };
+    // Later reads can use it
+    data.guild_configs.insert(guild_id, config.clone());
+
+    let enabled = |on: bool| if on { "Enabled" } else { "Disabled" };
+    let custom_points = config.points_overrides.as_ref().map_or(0, |o| o.len());
+    let embed = ConfigKey::ALL
+        .iter()
+        .fold(
+            serenity::CreateEmbed::new().title("Server Configuration"),
+            |embed, key| embed.field(key.name(), format_setting(*key, &config), true),
+        )
+        .field("Quiz Bots", format_quiz_bots(&config), true)
+        .field(
+            "Unfurl Learning Links",
+            enabled(config.unfurl_learning_links),
+            true,
+        )
+        .field("Stat Lookup", enabled(config.stat_lookup_allowed()), true)
+        .field("Language", Language::of(Some(&config)).name(), true)
+        .field(
+            "Quiz Roles",
+            match config.quiz_role_ids.as_ref().map_or(0, |r| r.len()) {
+                0 => "Built-in".to_string(),
+                n => format!("{} of {} levels custom", n, QUIZZES.len()),
+            },
+            true,
+        )
+        .field(
+            "Quiz Selector",
+            config
+                .quiz_selector_title
+                .as_deref()
+                .unwrap_or(DEFAULT_SELECTOR_TITLE),
+            true,
+        )
+        .field(
+            "Reaction Log",
+            match &config.reaction_log {
+                Some(settings) => format!(
+                    "{} in <#{}>: {}",
+                    settings.emoji,
+                    settings.channel_id,
+                    format_reaction_amount(settings)
+                ),
+                None => "Off".to_string(),
+            },
+            true,
+        )
+        .field(
+            "Points Multipliers",
+            if custom_points == 0 {
+                "Defaults".to_string()
+            } else {
+                format!("{} custom, see `/config points view`", custom_points)
+            },
+            true,
+        )
+        .footer(serenity::CreateEmbedFooter::new(
+            "Change with /config set, unset with /config clear",
+        ))
         .color(colors::INFO);
 
     ctx.send(poise::CreateReply::default().embed(embed)).await?;
@@ -196,8 +938,30 @@ pub async fn get(ctx: Context<'_>) -> Result<(), Error> {
     Ok(())
 }
 
+/// Channel mention of a setting, or the "not set" placeholder
+fn format_setting(key: ConfigKey, config: &GuildConfig) -> String {
+    key.value(config)
+        .map(|id| format!("<#{}>", id))
+        .unwrap_or_else(|| "Not set".to_string())
+}
+
+/// Load the guild config for modification (cache first, empty config if none stored)
+pub async fn load_config_for_update(
+    data: &crate::Data,
+    guild_id: &str,
+) -> anyhow::Result<GuildConfig> {
+    if let Some(cached) = data.guild_configs.get(guild_id) {
+        return Ok(cached.clone());
+    }
+
+    match data.firebase.get_document("guilds", guild_id).await? {
+        Some(doc) => Ok(GuildConfig::from_doc(doc)),
+        None => Ok(GuildConfig::default()),
+    }
+}
+
 /// Check if user has access (Owner OR Manage Guild)
-async fn check_access(ctx: Context<'_>) -> Result<bool, Error> {
+pub(crate) async fn check_access(ctx: Context<'_>) -> Result<bool, Error> {
     // 1. Check Owner
     if ctx.framework().options().owners.contains(&ctx.author().id) {
         return Ok(true);
@@ -229,3 +993,60 @@ async fn check_access(ctx: Context<'_>) -> Result<bool, Error> {
 
     Ok(false)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_type_must_match_key() {
+        use poise::serenity_prelude::ChannelType;
+
+        assert_eq!(
+            channel_mismatch(ConfigKey::QuizCategory, ChannelType::Category),
+            None
+        );
+        assert!(channel_mismatch(ConfigKey::QuizCategory, ChannelType::Text).is_some());
+        assert_eq!(
+            channel_mismatch(ConfigKey::ImmersionChannel, ChannelType::Text),
+            None
+        );
+        assert_eq!(
+            channel_mismatch(ConfigKey::RoleRankAnnouncement, ChannelType::News),
+            None
+        );
+        assert!(channel_mismatch(ConfigKey::AyumiChannel, ChannelType::Category).is_some());
+        assert!(channel_mismatch(ConfigKey::AyumiChannel, ChannelType::Voice).is_some());
+    }
+
+    #[test]
+    fn test_every_key_has_its_own_field() {
+        let mut config = GuildConfig::default();
+        for (i, key) in ConfigKey::ALL.iter().enumerate() {
+            *key.slot(&mut config) = Some(i.to_string());
+        }
+        for (i, key) in ConfigKey::ALL.iter().enumerate() {
+            assert_eq!(format_setting(*key, &config), format!("<#{}>", i));
+        }
+        assert_eq!(config.welcome_channel_id.as_deref(), Some("3"));
+
+        *ConfigKey::RecapChannel.slot(&mut config) = None;
+        assert_eq!(format_setting(ConfigKey::RecapChannel, &config), "Not set");
+    }
+
+    #[test]
+    fn test_format_multipliers_marks_defaults() {
+        let overrides = HashMap::from([("anime".to_string(), 20.0)]);
+        let text = format_multipliers(Some(&overrides));
+
+        let anime = text.lines().find(|l| l.contains("Anime")).unwrap();
+        assert!(anime.ends_with(": 20"), "{}", anime);
+        let manga = text.lines().find(|l| l.contains("Manga")).unwrap();
+        assert!(manga.ends_with(": 0.25 *(default)*"), "{}", manga);
+
+        assert_eq!(text.lines().count(), points_multipliers().len());
+        assert!(!format_multipliers(None)
+            .lines()
+            .any(|l| !l.ends_with("*(default)*")));
+    }
+}
@@ -3,9 +3,11 @@
 
 use chrono::{DateTime, Duration, Utc};
 use poise::serenity_prelude as serenity;
+use std::collections::HashMap;
 use tracing::error;
 
 use crate::utils::config::get_media_label;
+use crate::utils::formatters::{format_number, truncate_bytes_lossy};
 use crate::{Context, Error};
 
 /// Timeframe options for export
@@ -34,8 +36,7 @@ impl Timeframe {
         }
     }
 
-    fn get_start_date(&self) -> DateTime<Utc> {
-        let now = Utc::now();
+    fn get_start_date(&self, now: DateTime<Utc>) -> DateTime<Utc> {
         match self {
             Timeframe::Day => now - Duration::days(1),
             Timeframe::Week => now - Duration::days(7),
@@ -95,6 +96,15 @@ impl ExportMediaType {
     }
 }
 
+/// Logs per Firestore page when streaming an all-time export
+const EXPORT_PAGE_SIZE: usize = 300;
+
+/// Pages between progress updates on the deferred reply
+const PROGRESS_EVERY_PAGES: usize = 3;
+
+/// Largest attachment sent, safely under Discord's 8MB upload limit
+const MAX_ATTACHMENT_BYTES: usize = 7_500_000;
+
 /// Export your immersion logs as a text file
 #[poise::command(slash_command, prefix_command)]
 pub async fn export(
@@ -108,63 +118,9 @@ pub async fn export(
     let user_id = user.id.to_string();
     let firebase = &ctx.data().firebase;
     let media_filter = mediatype.unwrap_or(ExportMediaType::All);
-
-    // Fetch user logs from Firebase subcollection
-    let logs_result = firebase
-        .query_subcollection("users", &user_id, "immersion_logs")
-        .await;
-
-    let all_logs: Vec<serde_json::Value> = match logs_result {
-        Ok(logs) => logs,
-        Err(e) => {
-            error!("Failed to fetch logs: {:?}", e);
-            ctx.say("Failed to export logs. Please try again later.")
-                .await?;
-            return Ok(());
-        }
-    };
-
-    // Filter logs by timeframe and media type
-    let start_date = timeframe.get_start_date();
+    let start_date = timeframe.get_start_date(ctx.data().clock.now_utc());
     let media_type_str = media_filter.as_str();
 
-    let filtered_logs: Vec<&serde_json::Value> = all_logs
-        .iter()
-        .filter(|log| {
-            // Filter by timestamp
-            let created = log
-                .get("timestamps")
-                .and_then(|t| t.get("created"))
-                .and_then(|c| c.as_str())
-                .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
-                .map(|dt| dt.with_timezone(&Utc))
-                .unwrap_or(Utc::now());
-
-            if created < start_date {
-                return false;
-            }
-
-            // Filter by media type
-            if let Some(filter_type) = media_type_str {
-                let log_type = log
-                    .get("activity")
-                    .and_then(|a| a.get("type"))
-                    .and_then(|t| t.as_str())
-                    .unwrap_or("");
-
-                if log_type != filter_type {
-                    return false;
-                }
-            }
-
-            true
-        })
-        .collect();
-
-    // Generate export content
-    let content = generate_export_content(&filtered_logs, &timeframe, &media_filter, &user.name);
-
-    // Create filename
     let timeframe_label = match timeframe {
         Timeframe::Day => "24h",
         Timeframe::Week => "7d",
@@ -173,147 +129,368 @@ pub async fn export(
         Timeframe::All => "all",
     };
     let media_label = media_filter.as_str().unwrap_or("all");
-    let filename = format!(
-        "immersion_logs_{}_{}_{}_{}.txt",
+    let base_name = format!(
+        "immersion_logs_{}_{}_{}_{}",
         user.name,
         timeframe_label,
         media_label,
         Utc::now().format("%Y%m%d")
     );
-
-    // Create attachment
-    let attachment = serenity::CreateAttachment::bytes(content.as_bytes().to_vec(), filename);
-
-    // Send file
     let media_type_text = if media_filter.as_str().is_some() {
         format!(" ({})", media_filter.label())
     } else {
         String::new()
     };
+    let message = format!(
+        "**{}'s** immersion log export for {}{}:",
+        user.name,
+        timeframe.as_str(),
+        media_type_text
+    );
+
+    // A first page that is also the last one is a small export, it comes out ordered as
+    // the logs are listed like it always has
+    let mut first_page = None;
+    if let Timeframe::All = timeframe {
+        match firebase
+            .get_user_logs_page(&user_id, None, EXPORT_PAGE_SIZE)
+            .await
+        {
+            Ok((logs, Some(next))) => first_page = Some((logs, next)),
+            Ok((_, None)) => {}
+            Err(e) => {
+                error!("Failed to fetch logs: {:?}", e);
+                ctx.say("Failed to export logs. Please try again later.")
+                    .await?;
+                return Ok(());
+            }
+        }
+    }
+
+    let mut writer = ExportWriter::default();
+    let mut sent = 0usize;
+    let mut progress = None;
+    if let Some((first_logs, first_next)) = first_page {
+        // Page by page in creation order, parts sent as they fill up
+        let (mut logs, mut next) = (first_logs, Some(first_next));
+        let mut fetched = 0usize;
+        let mut pages = 0usize;
+        loop {
+            fetched += logs.len();
+            for log in logs
+                .iter()
+                .filter(|log| matches_filters(log, start_date, media_type_str))
+            {
+                if let Some(part) = writer.push(log) {
+                    sent += 1;
+                    send_part(ctx, &base_name, &message, part, sent, None).await?;
+                }
+            }
 
+            pages += 1;
+            let Some(cursor) = next else {
+                break;
+            };
+            if pages.is_multiple_of(PROGRESS_EVERY_PAGES) {
+                let text = format!("Fetched {} logs…", format_number(fetched as i64));
+                match &progress {
+                    None => progress = Some(ctx.say(text).await?),
+                    Some(handle) => {
+                        let _ = handle
+                            .edit(ctx, poise::CreateReply::default().content(text))
+                            .await;
+                    }
+                }
+            }
+            (logs, next) = match firebase
+                .get_user_logs_page(&user_id, Some(&cursor), EXPORT_PAGE_SIZE)
+                .await
+            {
+                Ok(page) => page,
+                Err(e) => {
+                    error!("Failed to fetch logs: {:?}", e);
+                    ctx.say("Failed to export logs. Please try again later.")
+                        .await?;
+                    return Ok(());
+                }
+            };
+        }
+    } else {
+        // Fetch user logs from Firebase subcollection
+        let all_logs = match firebase.get_user_logs(&user_id).await {
+            Ok(logs) => logs,
+            Err(e) => {
+                error!("Failed to fetch logs: {:?}", e);
+                ctx.say("Failed to export logs. Please try again later.")
+                    .await?;
+                return Ok(());
+            }
+        };
+        for log in all_logs
+            .iter()
+            .filter(|log| matches_filters(log, start_date, media_type_str))
+        {
+            if let Some(part) = writer.push(log) {
+                sent += 1;
+                send_part(ctx, &base_name, &message, part, sent, None).await?;
+            }
+        }
+    }
+
+    // The header and summary go last, with what's left of the logs
+    let content = writer.finish(&timeframe, &media_filter, &user.name, Utc::now());
+    let parts = split_content(content, MAX_ATTACHMENT_BYTES);
+    let total = sent + parts.len();
+    for part in parts {
+        sent += 1;
+        send_part(ctx, &base_name, &message, part, sent, Some(total)).await?;
+    }
+
+    if let Some(handle) = progress {
+        let _ = handle.delete(ctx).await;
+    }
+
+    Ok(())
+}
+
+/// Send one part of the export as its own attachment. `total` isn't known yet for parts
+/// sent while the logs are still coming in.
+async fn send_part(
+    ctx: Context<'_>,
+    base_name: &str,
+    message: &str,
+    part: String,
+    number: usize,
+    total: Option<usize>,
+) -> Result<(), Error> {
+    let (filename, text) = match total {
+        Some(1) => (format!("{}.txt", base_name), message.to_string()),
+        Some(total) => (
+            format!("{}_part{}of{}.txt", base_name, number, total),
+            format!("{} (part {}/{})", message, number, total),
+        ),
+        None => (
+            format!("{}_part{}.txt", base_name, number),
+            format!("{} (part {})", message, number),
+        ),
+    };
+    let attachment = serenity::CreateAttachment::bytes(part.into_bytes(), filename);
     ctx.send(
         poise::CreateReply::default()
-            .content(format!(
-                "**{}'s** immersion log export for {}{}:",
-                user.name,
-                timeframe.as_str(),
-                media_type_text
-            ))
+            .content(text)
             .attachment(attachment),
     )
     .await?;
-
     Ok(())
 }
 
-fn generate_export_content(
-    logs: &[&serde_json::Value],
-    timeframe: &Timeframe,
-    media_type: &ExportMediaType,
-    username: &str,
-) -> String {
-    let mut content = String::new();
-
-    content.push_str("Immersion Logs Export\n");
-    content.push_str("====================\n\n");
-    content.push_str(&format!("User: {}\n", username));
-    content.push_str(&format!("Timeframe: {}\n", timeframe.as_str()));
-    content.push_str(&format!("Media Type: {}\n", media_type.label()));
-    content.push_str(&format!("Total Logs: {}\n", logs.len()));
-    content.push_str(&format!(
-        "Export Date: {}\n\n",
-        Utc::now().format("%Y-%m-%d %H:%M:%S UTC")
-    ));
-
-    if logs.is_empty() {
-        content.push_str("No immersion logs found for the selected timeframe and media type.\n");
-        return content;
+/// Whether a log was created in the timeframe and has the requested media type
+fn matches_filters(
+    log: &serde_json::Value,
+    start_date: DateTime<Utc>,
+    media_type: Option<&str>,
+) -> bool {
+    // Filter by timestamp
+    let created = log
+        .get("timestamps")
+        .and_then(|t| t.get("created"))
+        .and_then(|c| c.as_str())
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or(Utc::now());
+
+    if created < start_date {
+        return false;
     }
 
-    // Summary statistics
-    use std::collections::HashMap;
-    let mut stats: HashMap<String, (i32, f64)> = HashMap::new();
-
-    for log in logs {
-        if let Some(activity) = log.get("activity") {
-            let log_type = activity
-                .get("type")
-                .and_then(|t| t.as_str())
-                .unwrap_or("unknown");
-            let amount = activity
-                .get("amount")
-                .and_then(|a| a.as_f64())
-                .unwrap_or(0.0);
-
-            let entry = stats.entry(log_type.to_string()).or_insert((0, 0.0));
-            entry.0 += 1;
-            entry.1 += amount;
+    // Filter by media type
+    if let Some(filter_type) = media_type {
+        let log_type = log
+            .get("activity")
+            .and_then(|a| a.get("type"))
+            .and_then(|t| t.as_str())
+            .unwrap_or("");
+
+        if log_type != filter_type {
+            return false;
         }
     }
 
-    content.push_str("Summary Statistics:\n");
-    content.push_str("------------------\n");
-    for (type_name, (count, total)) in &stats {
-        let label = get_media_label(type_name);
-        let unit = get_unit_for_type(type_name);
-        content.push_str(&format!(
-            "{}: {} sessions, {:.1} total {}\n",
-            label, count, total, unit
-        ));
+    true
+}
+
+/// Export text built one log at a time: the detailed lines are rendered as logs arrive,
+/// the header and summary once all of them are in. Details that outgrow a part are cut
+/// off and handed out, so a large export isn't held whole.
+struct ExportWriter {
+    count: usize,
+    stats: HashMap<String, (i32, f64)>,
+    details: String,
+    /// Most details kept before a part is cut off
+    part_bytes: usize,
+    parts_cut: usize,
+}
+
+impl Default for ExportWriter {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            stats: HashMap::new(),
+            details: String::new(),
+            part_bytes: MAX_ATTACHMENT_BYTES,
+            parts_cut: 0,
+        }
     }
-    content.push_str("\n\n");
-
-    // Detailed logs
-    content.push_str("Detailed Logs:\n");
-    content.push_str("-------------\n");
-
-    for (index, log) in logs.iter().enumerate() {
-        if let Some(activity) = log.get("activity") {
-            let amount = activity
-                .get("amount")
-                .and_then(|a| a.as_f64())
-                .unwrap_or(0.0);
-            let unit = activity.get("unit").and_then(|u| u.as_str()).unwrap_or("");
-            let type_label = activity
-                .get("typeLabel")
-                .and_then(|t| t.as_str())
-                .unwrap_or("Unknown");
-            let title = activity
-                .get("title")
-                .and_then(|t| t.as_str())
-                .unwrap_or("-");
+}
 
-            content.push_str(&format!(
-                "{}. {:.0} {} of {}\n",
-                index + 1,
-                amount,
-                unit,
-                type_label
-            ));
+impl ExportWriter {
+    /// Add a log, returning a full part of details once they outgrow `part_bytes`
+    fn push(&mut self, log: &serde_json::Value) -> Option<String> {
+        self.count += 1;
+        let activity = log.get("activity")?;
+
+        let log_type = activity
+            .get("type")
+            .and_then(|t| t.as_str())
+            .unwrap_or("unknown");
+        let amount = activity
+            .get("amount")
+            .and_then(|a| a.as_f64())
+            .unwrap_or(0.0);
+
+        let entry = self.stats.entry(log_type.to_string()).or_insert((0, 0.0));
+        entry.0 += 1;
+        entry.1 += amount;
+
+        let unit = activity.get("unit").and_then(|u| u.as_str()).unwrap_or("");
+        let type_label = activity
+            .get("typeLabel")
+            .and_then(|t| t.as_str())
+            .unwrap_or("Unknown");
+        let title = activity
+            .get("title")
+            .and_then(|t| t.as_str())
+            .unwrap_or("-");
+
+        let content = &mut self.details;
+        content.push_str(&format!(
+            "{}. {:.0} {} of {}\n",
+            self.count, amount, unit, type_label
+        ));
 
-            if title != "-" && !title.is_empty() {
-                content.push_str(&format!("   Title: {}\n", title));
-            }
+        if title != "-" && !title.is_empty() {
+            content.push_str(&format!("   Title: {}\n", title));
+        }
 
-            if let Some(timestamps) = log.get("timestamps") {
-                if let Some(created) = timestamps.get("created").and_then(|c| c.as_str()) {
-                    if let Ok(dt) = DateTime::parse_from_rfc3339(created) {
-                        content.push_str(&format!("   Date: {}\n", dt.format("%Y-%m-%d %H:%M")));
-                    }
+        if let Some(timestamps) = log.get("timestamps") {
+            if let Some(created) = timestamps.get("created").and_then(|c| c.as_str()) {
+                if let Ok(dt) = DateTime::parse_from_rfc3339(created) {
+                    content.push_str(&format!("   Date: {}\n", dt.format("%Y-%m-%d %H:%M")));
                 }
             }
+        }
 
-            if let Some(note) = log.get("note").and_then(|n| n.as_str()) {
-                if !note.is_empty() {
-                    content.push_str(&format!("   Note: {}\n", note));
-                }
+        if let Some(note) = log.get("note").and_then(|n| n.as_str()) {
+            if !note.is_empty() {
+                content.push_str(&format!("   Note: {}\n", note));
             }
+        }
+
+        content.push('\n');
 
-            content.push_str("\n");
+        if self.details.len() <= self.part_bytes {
+            return None;
         }
+        let rest = self
+            .details
+            .split_off(cut_at(&self.details, self.part_bytes));
+        self.parts_cut += 1;
+        Some(std::mem::replace(&mut self.details, rest))
     }
 
-    content
+    fn finish(
+        self,
+        timeframe: &Timeframe,
+        media_type: &ExportMediaType,
+        username: &str,
+        export_date: DateTime<Utc>,
+    ) -> String {
+        let mut content = String::new();
+
+        content.push_str("Immersion Logs Export\n");
+        content.push_str("====================\n\n");
+        content.push_str(&format!("User: {}\n", username));
+        content.push_str(&format!("Timeframe: {}\n", timeframe.as_str()));
+        content.push_str(&format!("Media Type: {}\n", media_type.label()));
+        content.push_str(&format!("Total Logs: {}\n", self.count));
+        content.push_str(&format!(
+            "Export Date: {}\n\n",
+            export_date.format("%Y-%m-%d %H:%M:%S UTC")
+        ));
+
+        if self.count == 0 {
+            content
+                .push_str("No immersion logs found for the selected timeframe and media type.\n");
+            return content;
+        }
+
+        // Summary statistics
+        content.push_str("Summary Statistics:\n");
+        content.push_str("------------------\n");
+        for (type_name, (count, total)) in &self.stats {
+            let label = get_media_label(type_name);
+            let unit = get_unit_for_type(type_name);
+            content.push_str(&format!(
+                "{}: {} sessions, {:.1} total {}\n",
+                label, count, total, unit
+            ));
+        }
+        content.push_str("\n\n");
+
+        // Detailed logs
+        if self.parts_cut > 0 {
+            content.push_str(&format!(
+                "Detailed Logs (continued from part {}):\n",
+                self.parts_cut
+            ));
+        } else {
+            content.push_str("Detailed Logs:\n");
+        }
+        content.push_str("-------------\n");
+        content.push_str(&self.details);
+
+        content
+    }
+}
+
+/// Cut the export into pieces of at most `max_bytes`, between logs where possible
+fn split_content(content: String, max_bytes: usize) -> Vec<String> {
+    if content.len() <= max_bytes {
+        return vec![content];
+    }
+
+    let mut parts = Vec::new();
+    let mut rest = content.as_str();
+    while rest.len() > max_bytes {
+        let end = cut_at(rest, max_bytes);
+        parts.push(rest[..end].to_string());
+        rest = &rest[end..];
+    }
+    if !rest.is_empty() {
+        parts.push(rest.to_string());
+    }
+    parts
+}
+
+/// Where to end a piece of `text` of at most `max_bytes`, between logs where possible
+fn cut_at(text: &str, max_bytes: usize) -> usize {
+    let window = truncate_bytes_lossy(text, max_bytes);
+    window
+        .rfind("\n\n")
+        .map(|i| i + 2)
+        .or_else(|| window.rfind('\n').map(|i| i + 1))
+        .filter(|&end| end > 0)
+        .unwrap_or(window.len())
 }
 
 fn get_unit_for_type(media_type: &str) -> &'static str {
@@ -325,3 +502,129 @@ fn get_unit_for_type(media_type: &str) -> &'static str {
         _ => "units",
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_export_content_format() {
+        let mut writer = ExportWriter::default();
+        writer.push(&json!({
+            "activity": {
+                "type": "anime",
+                "typeLabel": "Anime",
+                "amount": 3.0,
+                "unit": "episodes",
+                "title": "Frieren"
+            },
+            "timestamps": { "created": "2025-01-15T10:30:00+00:00" }
+        }));
+        writer.push(&json!({
+            "activity": {
+                "type": "anime",
+                "typeLabel": "Anime",
+                "amount": 1.0,
+                "unit": "episodes",
+                "title": "-"
+            },
+            "timestamps": { "created": "2025-01-16T08:00:00+00:00" },
+            "note": "rewatch"
+        }));
+        let export_date = DateTime::parse_from_rfc3339("2025-02-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let content = writer.finish(
+            &Timeframe::Month,
+            &ExportMediaType::Anime,
+            "yuki",
+            export_date,
+        );
+        assert_eq!(
+            content,
+            "Immersion Logs Export\n\
+             ====================\n\n\
+             User: yuki\n\
+             Timeframe: Last 30 Days\n\
+             Media Type: Anime\n\
+             Total Logs: 2\n\
+             Export Date: 2025-02-01 12:00:00 UTC\n\n\
+             Summary Statistics:\n\
+             ------------------\n\
+             Anime: 2 sessions, 4.0 total episodes\n\
+             \n\n\
+             Detailed Logs:\n\
+             -------------\n\
+             1. 3 episodes of Anime\n   \
+             Title: Frieren\n   \
+             Date: 2025-01-15 10:30\n\
+             \n\
+             2. 1 episodes of Anime\n   \
+             Date: 2025-01-16 08:00\n   \
+             Note: rewatch\n\
+             \n"
+        );
+    }
+
+    #[test]
+    fn test_empty_export() {
+        let content = ExportWriter::default().finish(
+            &Timeframe::All,
+            &ExportMediaType::All,
+            "yuki",
+            Utc::now(),
+        );
+        assert!(content
+            .ends_with("\n\nNo immersion logs found for the selected timeframe and media type.\n"));
+        assert!(content.contains("Total Logs: 0\n"));
+    }
+
+    #[test]
+    fn test_writer_hands_out_full_parts() {
+        let log = |title: &str| {
+            json!({
+                "activity": { "type": "anime", "typeLabel": "Anime", "amount": 1.0, "unit": "episodes", "title": title },
+                "timestamps": { "created": "2025-01-15T10:30:00+00:00" }
+            })
+        };
+        let mut writer = ExportWriter {
+            part_bytes: 150,
+            ..Default::default()
+        };
+        let mut parts: Vec<String> = ["A", "B", "C", "D"]
+            .iter()
+            .filter_map(|title| writer.push(&log(title)))
+            .collect();
+        assert_eq!(parts.len(), 1);
+        assert!(parts[0].len() <= 150);
+        assert!(parts[0].starts_with("1. 1 episodes of Anime\n   Title: A\n"));
+        assert!(parts[0].ends_with("\n\n"));
+
+        let last = writer.finish(&Timeframe::All, &ExportMediaType::All, "yuki", Utc::now());
+        assert!(last.contains("Total Logs: 4\n"));
+        assert!(last.contains("Anime: 4 sessions, 4.0 total episodes\n"));
+        assert!(last.contains("Detailed Logs (continued from part 1):\n"));
+        parts.push(last);
+        // Every log makes it into exactly one part
+        for (i, title) in ["A", "B", "C", "D"].iter().enumerate() {
+            let line = format!("{}. 1 episodes of Anime\n   Title: {}\n", i + 1, title);
+            assert_eq!(parts.iter().filter(|p| p.contains(&line)).count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_split_content_between_logs() {
+        let content = "header\n\n1. log\n\n2. log\n\n3. log\n\n".to_string();
+        assert_eq!(split_content(content.clone(), 100), vec![content.clone()]);
+
+        let parts = split_content(content.clone(), 20);
+        assert_eq!(parts, vec!["header\n\n1. log\n\n", "2. log\n\n3. log\n\n"]);
+        assert!(parts.iter().all(|p| p.len() <= 20));
+        assert_eq!(parts.concat(), content);
+
+        // No line break to cut at, and multi-byte characters stay whole
+        let parts = split_content("日本語日本語".to_string(), 7);
+        assert_eq!(parts, vec!["日本", "語日", "本語"]);
+    }
+}
@@ -0,0 +1,185 @@
+// Goal command - set and track monthly immersion goals
+
+use poise::serenity_prelude as serenity;
+use std::collections::BTreeMap;
+use tracing::error;
+
+use crate::commands::immersion::MediaType;
+use crate::models::goal::{self, Goal};
+use crate::utils::config::{colors, get_effective_date, get_media_label, get_unit};
+use crate::utils::formatters::format_number;
+use crate::{Context, Error};
+
+/// Monthly immersion goals
+#[poise::command(slash_command, prefix_command, subcommands("set", "view", "clear"))]
+pub async fn goal(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Set a goal for this month
+#[poise::command(slash_command, prefix_command)]
+pub async fn set(
+    ctx: Context<'_>,
+    #[description = "Type of media"] media_type: MediaType,
+    #[description = "Target amount for this month"]
+    #[min = 1]
+    #[max = 10000000]
+    amount: f64,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+
+    let user_id = ctx.author().id.to_string();
+    let month = goal::month_key(get_effective_date());
+
+    let Some(mut goals) = load_goals(ctx, &user_id).await? else {
+        return Ok(());
+    };
+    goals.insert(
+        media_type.as_str().to_string(),
+        Goal::new(amount, month.clone()),
+    );
+
+    if let Err(e) = save_goals(ctx.data(), &user_id, &goals).await {
+        error!("Failed to save goal: {:?}", e);
+        ctx.say("Failed to save goal. Please try again.").await?;
+        return Ok(());
+    }
+
+    let embed = serenity::CreateEmbed::new()
+        .title("Goal Set")
+        .description(format!(
+            "**{}**: {} {} in {}",
+            get_media_label(media_type.as_str()),
+            format_number(amount.round() as i64),
+            get_unit(media_type.as_str()),
+            month
+        ))
+        .color(colors::SUCCESS);
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
+/// View your goals and progress for this month
+#[poise::command(slash_command, prefix_command)]
+pub async fn view(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer().await?;
+
+    let user_id = ctx.author().id.to_string();
+    let month = goal::month_key(get_effective_date());
+
+    let Some(goals) = load_goals(ctx, &user_id).await? else {
+        return Ok(());
+    };
+    if goals.is_empty() {
+        ctx.say("You have no goals yet. Set one with `/goal set`.")
+            .await?;
+        return Ok(());
+    }
+
+    let logs = ctx
+        .data()
+        .firebase
+        .get_user_logs(&user_id)
+        .await
+        .unwrap_or_default();
+
+    let mut embed = serenity::CreateEmbed::new()
+        .title(format!("Goals - {}", month))
+        .color(colors::INFO);
+
+    for (media_type, goal) in &goals {
+        let unit = get_unit(media_type);
+        // Last month's goal doesn't carry its progress into the new month
+        let value = if goal.is_active(&month) {
+            let progress = goal::month_progress(&logs, media_type, &month);
+            format!(
+                "`{}`\n{}",
+                goal::progress_bar(progress, goal.amount),
+                goal::progress_text(progress, goal.amount, unit)
+            )
+        } else {
+            format!(
+                "`{}`\nNot started for {} (last set for {}). Use `/goal set` to renew it.",
+                goal::progress_bar(0.0, goal.amount),
+                month,
+                goal.month
+            )
+        };
+        embed = embed.field(get_media_label(media_type), value, false);
+    }
+
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
+/// Remove a goal (or all of them)
+#[poise::command(slash_command, prefix_command)]
+pub async fn clear(
+    ctx: Context<'_>,
+    #[description = "Goal to remove (leave empty to remove all)"] media_type: Option<MediaType>,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+
+    let user_id = ctx.author().id.to_string();
+    let Some(mut goals) = load_goals(ctx, &user_id).await? else {
+        return Ok(());
+    };
+
+    let removed = match media_type {
+        Some(mt) => goals.remove(mt.as_str()).is_some() as usize,
+        None => {
+            let count = goals.len();
+            goals.clear();
+            count
+        }
+    };
+
+    if removed == 0 {
+        ctx.say("No matching goal to remove.").await?;
+        return Ok(());
+    }
+
+    if let Err(e) = save_goals(ctx.data(), &user_id, &goals).await {
+        error!("Failed to clear goal: {:?}", e);
+        ctx.say("Failed to remove goal. Please try again.").await?;
+        return Ok(());
+    }
+
+    ctx.say(format!(
+        "Removed {} goal{}.",
+        removed,
+        if removed == 1 { "" } else { "s" }
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Read the user's goals, replying with an error and returning `None` if the fetch fails
+async fn load_goals(
+    ctx: Context<'_>,
+    user_id: &str,
+) -> Result<Option<BTreeMap<String, Goal>>, Error> {
+    match ctx.data().firebase.get_user(user_id).await {
+        Ok(user) => Ok(Some(user.map(|u| u.goals()).unwrap_or_default())),
+        Err(e) => {
+            error!("Failed to fetch user data: {:?}", e);
+            ctx.say("Failed to fetch user data. Please try again.")
+                .await?;
+            Ok(None)
+        }
+    }
+}
+
+/// Overwrite the user's goals map
+pub async fn save_goals(
+    data: &crate::Data,
+    user_id: &str,
+    goals: &BTreeMap<String, Goal>,
+) -> anyhow::Result<()> {
+    data.firebase
+        .set_document("users", user_id, &goal::goals_update(goals))
+        .await
+}
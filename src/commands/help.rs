@@ -1,50 +1,222 @@
 // Help command - show usage guide
+// Built from the registered commands, so a new command or option shows up without
+// editing this file. Only the category a command belongs to is listed here.
 
+use crate::commands::config::check_access;
 use crate::utils::config::colors;
-use crate::{Context, Error};
+use crate::{Context, Data, Error};
 use poise::serenity_prelude as serenity;
 
-/// Show help and usage guide
-#[poise::command(slash_command, prefix_command)]
-pub async fn help(ctx: Context<'_>) -> Result<(), Error> {
-    let embed = serenity::CreateEmbed::new()
+/// How long the category menu keeps responding
+const MENU_TIMEOUT_SECS: u64 = 120;
+
+/// Discord's limit for an embed field value
+const FIELD_LIMIT: usize = 1024;
+
+/// Category for commands that need Manage Server, whatever their top-level command
+const ADMIN: &str = "Admin";
+
+/// Category for commands missing from `CATEGORIES`
+const GENERAL: &str = "General";
+
+/// Top-level commands of each category, in the order the overview shows them
+const CATEGORIES: &[(&str, &[&str])] = &[
+    (
+        "Immersion & Stats",
+        &[
+            "immersion",
+            "immersion_batch",
+            "stat",
+            "card",
+            "log",
+            "title",
+            "now",
+            "goal",
+            "reminder",
+            "timezone",
+            "export",
+            "recalculate",
+            "leaderboard",
+        ],
+    ),
+    (
+        "Media Tools",
+        &[
+            "novel",
+            "subs",
+            "vocab",
+            "react",
+            "exam",
+            "profile",
+            "jlpt_leaderboard",
+        ],
+    ),
+    ("Ayumi", &["ayumi", "prompt"]),
+    (ADMIN, &["config"]),
+];
+
+/// Commands that check Manage Server themselves instead of through `required_permissions`
+const MANUAL_ADMIN: &[&str] = &["config"];
+
+/// A slash command or subcommand as `/help` shows it
+#[derive(Debug, Clone, PartialEq)]
+struct HelpEntry {
+    /// Full name, e.g. "log view"
+    name: String,
+    description: String,
+    params: Vec<HelpParam>,
+    /// Needs Manage Server, hidden from members without it
+    admin: bool,
+    category: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct HelpParam {
+    name: String,
+    description: String,
+    required: bool,
+    choices: Vec<String>,
+}
+
+/// Every slash command and subcommand that help lists, in registration order.
+/// Owner-only and hidden commands are left out.
+fn help_entries(commands: &[poise::Command<Data, Error>]) -> Vec<HelpEntry> {
+    let mut entries = Vec::new();
+    for command in commands {
+        let admin = MANUAL_ADMIN.contains(&command.name.as_str());
+        collect(command, &command.name, "", admin, &mut entries);
+    }
+    entries
+}
+
+fn collect(
+    command: &poise::Command<Data, Error>,
+    top: &str,
+    parent: &str,
+    parent_admin: bool,
+    out: &mut Vec<HelpEntry>,
+) {
+    if command.hide_in_help || command.owners_only {
+        return;
+    }
+    let name = if parent.is_empty() {
+        command.name.clone()
+    } else {
+        format!("{} {}", parent, command.name)
+    };
+    let admin = parent_admin
+        || command
+            .required_permissions
+            .contains(serenity::Permissions::MANAGE_GUILD)
+        || command
+            .default_member_permissions
+            .contains(serenity::Permissions::MANAGE_GUILD);
+
+    if !command.subcommands.is_empty() {
+        for sub in &command.subcommands {
+            collect(sub, top, &name, admin, out);
+        }
+        return;
+    }
+    if command.slash_action.is_none() {
+        return;
+    }
+
+    out.push(HelpEntry {
+        name,
+        description: command
+            .description
+            .clone()
+            .unwrap_or_else(|| "No description".to_string()),
+        params: command
+            .parameters
+            .iter()
+            .map(|p| HelpParam {
+                name: p.name.clone(),
+                description: p.description.clone().unwrap_or_default(),
+                required: p.required,
+                choices: p.choices.iter().map(|c| c.name.clone()).collect(),
+            })
+            .collect(),
+        admin,
+        category: category_of(top, admin),
+    });
+}
+
+fn category_of(top: &str, admin: bool) -> &'static str {
+    if admin {
+        return ADMIN;
+    }
+    CATEGORIES
+        .iter()
+        .find(|(_, names)| names.contains(&top))
+        .map(|(category, _)| *category)
+        .unwrap_or(GENERAL)
+}
+
+/// Categories with at least one entry, `CATEGORIES` order first and General last
+fn categories(entries: &[&HelpEntry]) -> Vec<&'static str> {
+    CATEGORIES
+        .iter()
+        .map(|(category, _)| *category)
+        .chain(std::iter::once(GENERAL))
+        .filter(|category| entries.iter().any(|e| e.category == *category))
+        .collect()
+}
+
+/// e.g. "/log view timeframe:<Last 24 Hours> [media_type:<...>]"
+fn usage(entry: &HelpEntry) -> String {
+    let mut usage = format!("/{}", entry.name);
+    for param in &entry.params {
+        let value = match param.choices.first() {
+            Some(choice) => choice.clone(),
+            None => param.name.clone(),
+        };
+        if param.required {
+            usage.push_str(&format!(" {}:<{}>", param.name, value));
+        } else {
+            usage.push_str(&format!(" [{}:<{}>]", param.name, value));
+        }
+    }
+    usage
+}
+
+/// Join lines into a field value under Discord's limit, saying how many didn't fit
+fn field_value(lines: &[String]) -> String {
+    let mut value = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        let more = format!("…and {} more", lines.len() - i);
+        if value.len() + line.len() + more.len() + 2 > FIELD_LIMIT {
+            value.push_str(&more);
+            return value;
+        }
+        value.push_str(line);
+        value.push('\n');
+    }
+    value.trim_end().to_string()
+}
+
+fn overview_line(entry: &HelpEntry) -> String {
+    format!("`/{}` - {}", entry.name, entry.description)
+}
+
+fn overview_embed(entries: &[&HelpEntry]) -> serenity::CreateEmbed {
+    let mut embed = serenity::CreateEmbed::new()
         .title("Ayumi Bot - Help")
-        .description("A lightweight Japanese immersion tracker")
-        .color(colors::PRIMARY)
-        .field(
-            "Immersion Logging",
-            "`/immersion` - Log your immersion activities\n\
-            Supported: Anime, Manga, Visual Novel, Book, Reading, Listening",
-            false,
-        )
-        .field(
-            "Statistics",
-            "`/stat` - View your stats\n\
-            `/stat visual_type:heatmap` - Activity heatmap\n\
-            `/stat visual_type:barchart` - Bar chart\n\
-            `/export` - Export logs as text file",
-            false,
-        )
-        .field(
-            "Community",
-            "`/leaderboard` - View immersion rankings\n\
-            `/jlpt_leaderboard` - View JLPT quiz rankings\n\
-            `/log time` - View recent logs",
-            false,
-        )
-        .field(
-            "Content",
-            "`/novel` - Search & download light novels\n\
-            `/subs` - Download anime subtitles from Jimaku\n\
-            `/afk` - Set your AFK status",
-            false,
-        )
-        .field(
-            "Configuration",
-            "`/config set` - Configure bot channels\n\
-            `/config get` - View current configuration",
-            false,
+        .description(
+            "A lightweight Japanese immersion tracker\n\
+            Use `/help command:<name>` for a command's options.",
         )
+        .color(colors::PRIMARY);
+    for category in categories(entries) {
+        let lines: Vec<String> = entries
+            .iter()
+            .filter(|e| e.category == category)
+            .map(|e| overview_line(e))
+            .collect();
+        embed = embed.field(category, field_value(&lines), false);
+    }
+    embed
         .field(
             "Points System",
             "• Anime: 13 pts/episode\n\
@@ -55,9 +227,321 @@ pub async fn help(ctx: Context<'_>) -> Result<(), Error> {
         )
         .footer(serenity::CreateEmbedFooter::new(
             "Rust Edition • Built with Serenity & Poise",
-        ));
+        ))
+}
+
+/// Commands of one category, or the subcommands of one group, with their usage
+fn list_embed(title: &str, entries: &[&HelpEntry]) -> serenity::CreateEmbed {
+    let lines: Vec<String> = entries
+        .iter()
+        .map(|e| format!("{}\n`{}`", overview_line(e), usage(e)))
+        .collect();
+    serenity::CreateEmbed::new()
+        .title(format!("Help - {}", title))
+        .description(field_value(&lines))
+        .color(colors::PRIMARY)
+}
+
+fn command_embed(entry: &HelpEntry) -> serenity::CreateEmbed {
+    let mut embed = serenity::CreateEmbed::new()
+        .title(format!("/{}", entry.name))
+        .description(&entry.description)
+        .color(colors::PRIMARY)
+        .field("Usage", format!("`{}`", usage(entry)), false);
+
+    if !entry.params.is_empty() {
+        let lines: Vec<String> = entry
+            .params
+            .iter()
+            .map(|p| {
+                let mut line = format!(
+                    "`{}`{} - {}",
+                    p.name,
+                    if p.required { "" } else { " (optional)" },
+                    p.description
+                );
+                if !p.choices.is_empty() {
+                    line.push_str(&format!("\nChoices: {}", p.choices.join(", ")));
+                }
+                line
+            })
+            .collect();
+        embed = embed.field("Options", field_value(&lines), false);
+    }
+    if entry.admin {
+        embed = embed.footer(serenity::CreateEmbedFooter::new("Needs Manage Server"));
+    }
+    embed
+}
+
+fn category_menu(categories: &[&'static str], selected: Option<&str>) -> serenity::CreateActionRow {
+    let options = categories
+        .iter()
+        .map(|category| {
+            serenity::CreateSelectMenuOption::new(*category, *category)
+                .default_selection(Some(*category) == selected)
+        })
+        .collect();
+    serenity::CreateActionRow::SelectMenu(
+        serenity::CreateSelectMenu::new(
+            "help_category",
+            serenity::CreateSelectMenuKind::String { options },
+        )
+        .placeholder("Jump to a category"),
+    )
+}
+
+/// Edit distance between two names, for suggesting a command after a typo
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            current.push((prev[j] + cost).min(prev[j + 1] + 1).min(current[j] + 1));
+        }
+        prev = current;
+    }
+    prev[b.len()]
+}
+
+/// Closest command or group name, when it's close enough to be a typo
+fn closest<'a>(name: &str, entries: &[&'a HelpEntry]) -> Option<&'a str> {
+    let groups = entries
+        .iter()
+        .filter_map(|e| e.name.split_once(' ').map(|(group, _)| group));
+    entries
+        .iter()
+        .map(|e| e.name.as_str())
+        .chain(groups)
+        .map(|candidate| (levenshtein(name, candidate), candidate))
+        .filter(|(distance, candidate)| *distance <= (candidate.len() / 3).max(2))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// What `/help command:<name>` resolves to
+#[derive(Debug, PartialEq)]
+enum Lookup<'a> {
+    Command(&'a HelpEntry),
+    /// A command with subcommands, e.g. "log"
+    Group(Vec<&'a HelpEntry>),
+    Unknown(Option<&'a str>),
+}
+
+fn lookup<'a>(name: &str, entries: &[&'a HelpEntry]) -> Lookup<'a> {
+    let name = name.trim().trim_start_matches('/').to_lowercase();
+    if let Some(entry) = entries.iter().find(|e| e.name == name) {
+        return Lookup::Command(entry);
+    }
+    let prefix = format!("{} ", name);
+    let group: Vec<&HelpEntry> = entries
+        .iter()
+        .filter(|e| e.name.starts_with(&prefix))
+        .copied()
+        .collect();
+    if !group.is_empty() {
+        return Lookup::Group(group);
+    }
+    Lookup::Unknown(closest(&name, entries))
+}
+
+async fn autocomplete_command(ctx: Context<'_>, partial: &str) -> impl Iterator<Item = String> {
+    let partial = partial.to_lowercase();
+    help_entries(&ctx.framework().options().commands)
+        .into_iter()
+        .map(|e| e.name)
+        .filter(move |name| name.contains(&partial))
+        .take(25)
+}
+
+/// Show help and usage guide
+#[poise::command(slash_command, prefix_command)]
+pub async fn help(
+    ctx: Context<'_>,
+    #[description = "Command to explain, e.g. log view"]
+    #[autocomplete = "autocomplete_command"]
+    #[rest]
+    command: Option<String>,
+) -> Result<(), Error> {
+    let all = help_entries(&ctx.framework().options().commands);
+    let show_admin = ctx.guild_id().is_some() && check_access(ctx).await?;
+    let visible: Vec<&HelpEntry> = all.iter().filter(|e| show_admin || !e.admin).collect();
+    let categories = categories(&visible);
+
+    let embed = match command.as_deref() {
+        None => overview_embed(&visible),
+        // Asking by name shows admin commands too, they're just not advertised
+        Some(name) => match lookup(name, &all.iter().collect::<Vec<_>>()) {
+            Lookup::Command(entry) => command_embed(entry),
+            Lookup::Group(entries) => list_embed(&format!("/{}", name.trim()), &entries),
+            Lookup::Unknown(suggestion) => {
+                let message = match suggestion {
+                    Some(suggestion) => format!(
+                        "No command named `{}`. Did you mean `/{}`?",
+                        name, suggestion
+                    ),
+                    None => format!(
+                        "No command named `{}`. Use `/help` to see every command.",
+                        name
+                    ),
+                };
+                ctx.say(message).await?;
+                return Ok(());
+            }
+        },
+    };
 
-    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+    let reply = ctx
+        .send(
+            poise::CreateReply::default()
+                .embed(embed.clone())
+                .components(vec![category_menu(&categories, None)]),
+        )
+        .await?;
+    let msg = reply.message().await?.into_owned();
+
+    let mut current = embed;
+    while let Some(interaction) = msg
+        .await_component_interaction(ctx.serenity_context())
+        .author_id(ctx.author().id)
+        .timeout(std::time::Duration::from_secs(MENU_TIMEOUT_SECS))
+        .await
+    {
+        let selected = match &interaction.data.kind {
+            serenity::ComponentInteractionDataKind::StringSelect { values } => {
+                values.first().cloned()
+            }
+            _ => None,
+        };
+        let Some(category) = selected.and_then(|s| categories.iter().find(|c| **c == s)) else {
+            continue;
+        };
+        let entries: Vec<&HelpEntry> = visible
+            .iter()
+            .filter(|e| e.category == *category)
+            .copied()
+            .collect();
+        current = list_embed(category, &entries);
+        let _ = interaction
+            .create_response(
+                ctx.http(),
+                serenity::CreateInteractionResponse::UpdateMessage(
+                    serenity::CreateInteractionResponseMessage::new()
+                        .embed(current.clone())
+                        .components(vec![category_menu(&categories, Some(category))]),
+                ),
+            )
+            .await;
+    }
+
+    let _ = reply
+        .edit(
+            ctx,
+            poise::CreateReply::default()
+                .embed(current)
+                .components(vec![]),
+        )
+        .await;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries() -> Vec<HelpEntry> {
+        help_entries(&crate::get_commands())
+    }
+
+    #[test]
+    fn test_entries_from_commands() {
+        let entries = entries();
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+
+        // Groups are expanded, owner-only and prefix-only commands are left out
+        assert!(names.contains(&"log view"));
+        assert!(!names.contains(&"log"));
+        assert!(!names.contains(&"quarantine"));
+        assert!(!names.contains(&"status"));
+
+        let find = |name: &str| entries.iter().find(|e| e.name == name).unwrap();
+        assert_eq!(find("stat").category, "Immersion & Stats");
+        assert_eq!(find("ping").category, GENERAL);
+        assert!(find("config view").admin);
+        assert!(find("role_rank setup").admin);
+        assert_eq!(find("role_rank setup").category, ADMIN);
+        assert!(!find("immersion").admin);
+
+        let stat = find("stat");
+        let visual = stat
+            .params
+            .iter()
+            .find(|p| p.name == "visual_type")
+            .unwrap();
+        assert!(!visual.required);
+        assert!(!visual.choices.is_empty());
+        assert!(usage(stat).starts_with("/stat "));
+    }
+
+    #[test]
+    fn test_usage_example() {
+        let entry = HelpEntry {
+            name: "log view".to_string(),
+            description: "View logs".to_string(),
+            params: vec![
+                HelpParam {
+                    name: "timeframe".to_string(),
+                    description: String::new(),
+                    required: true,
+                    choices: vec!["Last 24 Hours".to_string(), "Last 7 Days".to_string()],
+                },
+                HelpParam {
+                    name: "user".to_string(),
+                    description: String::new(),
+                    required: false,
+                    choices: vec![],
+                },
+            ],
+            admin: false,
+            category: GENERAL,
+        };
+        assert_eq!(
+            usage(&entry),
+            "/log view timeframe:<Last 24 Hours> [user:<user>]"
+        );
+    }
+
+    #[test]
+    fn test_lookup_and_suggestion() {
+        let entries = entries();
+        let refs: Vec<&HelpEntry> = entries.iter().collect();
+
+        assert!(matches!(lookup("/stat", &refs), Lookup::Command(e) if e.name == "stat"));
+        assert!(matches!(lookup("Log View", &refs), Lookup::Command(e) if e.name == "log view"));
+        assert!(matches!(lookup("log", &refs), Lookup::Group(g) if g.len() > 1));
+        assert_eq!(lookup("stats", &refs), Lookup::Unknown(Some("stat")));
+        assert_eq!(
+            lookup("leaderbord", &refs),
+            Lookup::Unknown(Some("leaderboard"))
+        );
+        assert_eq!(lookup("xyzzyxyzzy", &refs), Lookup::Unknown(None));
+
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_field_value_fits() {
+        let lines: Vec<String> = (0..100).map(|i| format!("{:030}", i)).collect();
+        let value = field_value(&lines);
+        assert!(value.len() <= FIELD_LIMIT);
+        assert!(value.ends_with("more"));
+        assert_eq!(
+            field_value(&lines[..2]),
+            format!("{}\n{}", lines[0], lines[1])
+        );
+    }
+}
@@ -3,18 +3,32 @@
 
 use chrono::Datelike;
 use poise::serenity_prelude as serenity;
-use serde_json::json;
-use tracing::{debug, error};
+use serde_json::{json, Value};
+use tracing::{debug, error, warn};
 
+use crate::api::firebase::{self, generate_document_id, TransactionWrite};
+use crate::api::storage::Storage;
 use crate::api::{anilist, vndb, youtube};
-use crate::utils::config::{colors, get_effective_date, get_media_label, get_unit};
+use crate::commands::now;
+use crate::features::activity_feed::{self, FeedEntry};
+use crate::features::title_popularity;
+use crate::models::goal;
+use crate::models::user::{UserDoc, UserProfile};
+use crate::utils::config::{
+    colors, effective_date_in, get_media_label, get_unit, get_user_timezone,
+};
+use crate::utils::error_reply::ValidationError;
+use crate::utils::formatters::{fit_description, truncate_chars, EMBED_FIELD_LIMIT};
+use crate::utils::i18n::{tf, Msg};
 use crate::utils::points::calculate_points;
+use crate::utils::privacy::LeaderboardPrivacy;
 use crate::utils::streak;
+use crate::utils::validation::{validate_amount, AmountError};
 use crate::{Context, Error};
-use chrono::{DateTime, NaiveDate};
+use chrono::NaiveDate;
 
 /// Media type choices for the command
-#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+#[derive(Debug, Clone, Copy, PartialEq, poise::ChoiceParameter)]
 pub enum MediaType {
     #[name = "Visual Novel (characters)"]
     VisualNovel,
@@ -44,71 +58,81 @@ impl MediaType {
             MediaType::Reading => "reading",
         }
     }
+
+    /// The media type stored as `key`, e.g. "reading_time"
+    pub fn from_key(key: &str) -> Option<Self> {
+        [
+            MediaType::VisualNovel,
+            MediaType::Manga,
+            MediaType::Anime,
+            MediaType::Book,
+            MediaType::ReadingTime,
+            MediaType::Listening,
+            MediaType::Reading,
+        ]
+        .into_iter()
+        .find(|t| t.as_str() == key)
+    }
 }
 
 /// Log your Japanese immersion activity
 #[poise::command(slash_command, prefix_command)]
+#[allow(clippy::too_many_arguments)]
 pub async fn immersion(
     ctx: Context<'_>,
     #[description = "Type of media"] media_type: MediaType,
     #[description = "Amount (episodes, pages, minutes, characters)"]
     #[min = 1]
-    #[max = 100000]
+    #[max = 500000]
     amount: f64,
     #[description = "Title of the media"]
     #[autocomplete = "autocomplete_title"]
     title: Option<String>,
     #[description = "Optional comment"] comment: Option<String>,
     #[description = "Custom date (YYYY-MM-DD)"] date: Option<String>,
-    #[description = "YouTube URL (for listening)"] url: Option<String>,
+    #[description = "YouTube URL, several URLs or a playlist (for listening)"] url: Option<String>,
+    #[description = "Show the cover art even if you hide covers of airing shows"]
+    show_cover: Option<bool>,
+    #[description = "Admins only: log past the per-type amount limit (marathon sessions)"]
+    force: Option<bool>,
+    #[description = "Only you see this log: it counts for your stats, not feeds or rankings"]
+    private: Option<bool>,
 ) -> Result<(), Error> {
-    ctx.defer().await?;
+    let private = private.unwrap_or(false);
+    // Replies to a private log only go to its owner
+    if private {
+        ctx.defer_ephemeral().await?;
+    } else {
+        ctx.defer().await?;
+    }
 
-    // Check channel restriction
-    if let Some(guild_id) = ctx.guild_id() {
-        let data = ctx.data();
-        let gid = guild_id.to_string();
+    if !in_immersion_channel(ctx).await? {
+        return Ok(());
+    }
 
-        let config = if let Some(cached) = data.guild_configs.get(&gid) {
-            Some(cached.clone())
-        } else {
-            // Try fetch if not in cache (though cache should be populated on startup or first activity)
-            // For now, simple cache check or fetch
-            match data.firebase.get_document("guilds", &gid).await {
-                Ok(Some(doc)) => {
-                    let cfg = serde_json::from_value::<crate::models::guild::GuildConfig>(doc)
-                        .unwrap_or_default();
-                    data.guild_configs.insert(gid.clone(), cfg.clone());
-                    Some(cfg)
-                }
-                _ => None,
-            }
-        };
+    let user = ctx.author();
+    let data = ctx.data();
+    let media_type_str = media_type.as_str();
 
-        if let Some(cfg) = config {
-            if let Some(allowed_channel_id) = cfg.immersion_channel_id {
-                let current_channel = ctx.channel_id().to_string();
-                if current_channel != allowed_channel_id {
-                    ctx.send(
-                        poise::CreateReply::default()
-                            .content(format!(
-                                "Command ini hanya bisa digunakan di <#{}>.",
-                                allowed_channel_id
-                            ))
-                            .ephemeral(true),
-                    )
+    // Caught before any lookups, a wrong type is usually a slip worth fixing first
+    match validate_amount(media_type_str, amount) {
+        Ok(()) => {}
+        Err(AmountError::OverLimit { .. }) if force == Some(true) => {
+            if !crate::commands::config::check_access(ctx).await? {
+                ctx.say("Only admins can use `force` to log past the amount limit.")
                     .await?;
-                    return Ok(());
-                }
+                return Ok(());
             }
         }
+        Err(e @ AmountError::OverLimit { .. }) => {
+            return Err(ValidationError(format!(
+                "{}\nAdmins can log it anyway with `force:true`.",
+                e
+            ))
+            .into());
+        }
+        Err(e) => return Err(ValidationError(e.to_string()).into()),
     }
-
-    let user = ctx.author();
-    let data = ctx.data();
-    let media_type_str = media_type.as_str();
-    let label = get_media_label(media_type_str);
-    let unit = get_unit(media_type_str);
     // Initialize variables
     let mut raw_title = title.unwrap_or_else(|| "-".to_string());
     let mut final_amount = amount;
@@ -119,6 +143,8 @@ pub async fn immersion(
     let mut source = "manual";
     let mut vndb_metadata = None;
     let mut warning_msg = None;
+    let mut airing = false;
+    let mut video_breakdown = None;
 
     // 1. Handle Listening (YouTube) - Interactive flow
     if let MediaType::Listening = media_type {
@@ -129,7 +155,10 @@ pub async fn immersion(
             // No URL provided - prompt user to paste in chat
             let prompt_embed = serenity::CreateEmbed::new()
                 .title("Input YouTube Link")
-                .description("Paste your YouTube link below\n\n*Timeout in 60 seconds*")
+                .description(format!(
+                    "Paste your YouTube link below, or several links / a playlist (max {} videos)\n\n*Timeout in 60 seconds*",
+                    youtube::MAX_VIDEOS
+                ))
                 .color(colors::IMMERSION);
 
             let prompt_reply = ctx
@@ -180,20 +209,19 @@ pub async fn immersion(
             }
         };
 
-        if let Some(url_str) = url_str {
-            if let Some(video_id) = youtube::extract_video_id(&url_str) {
-                let yt_key = std::env::var("YOUTUBE_API_KEY").unwrap_or_default();
-                match youtube::get_video_info(&data.http_client, &yt_key, &video_id).await {
-                    Ok(Some(info)) => {
-                        raw_title = info.title;
-                        final_amount = (info.duration_seconds as f64 / 60.0).ceil(); // Convert to minutes
-                        thumbnail = info.thumbnail;
-                        log_url = Some(youtube::normalize_url(&video_id));
-                        source = "youtube";
-                    }
-                    Ok(None) => debug!("Video not found"),
-                    Err(e) => error!("YouTube API error: {:?}", e),
+        if let Some(input) = url_str.and_then(|u| youtube::parse_listening_input(&u)) {
+            let yt_key = std::env::var("YOUTUBE_API_KEY").unwrap_or_default();
+            match resolve_listening(&data.http_client, &yt_key, input).await {
+                Ok(Some(listening)) => {
+                    raw_title = listening.title;
+                    final_amount = listening.minutes;
+                    thumbnail = listening.thumbnail;
+                    log_url = Some(listening.url);
+                    source = "youtube";
+                    video_breakdown = listening.breakdown;
                 }
+                Ok(None) => debug!("Video not found"),
+                Err(e) => error!("YouTube API error: {:?}", e),
             }
         }
     }
@@ -227,478 +255,2163 @@ pub async fn immersion(
         }
     }
 
-    // 2. Handle Visual Novel (VNDB)
-    if let MediaType::VisualNovel = media_type {
-        if raw_title != "-" {
-            // Check if title contains ID (from autocomplete: "Title|ID")
-            if let Some((_, id_part)) = raw_title.rsplit_once('|') {
-                if let Ok(Some(vn)) = vndb::get_vn_by_id(&data.http_client, id_part).await {
-                    raw_title = vn.title;
-                    thumbnail = vn.image;
-                    vndb_url = Some(vn.url);
-                    source = "vndb";
-                    vndb_metadata = Some(json!({
-                        "developer": vn.developer,
-                        "released": vn.released,
-                        "length": vn.length,
-                        "description": vn.description
-                    }));
-                }
-            } else {
-                // Fallback search by title
-                if let Ok(vns) = vndb::search_vns(&data.http_client, &raw_title, 1).await {
-                    if let Some(vn) = vns.first() {
-                        raw_title = vn.title.clone();
-                        thumbnail = vn.image.clone();
-                        vndb_url = Some(vn.url.clone());
-                        source = "vndb";
-                        vndb_metadata = Some(json!({
-                            "developer": vn.developer,
-                            "released": vn.released,
-                            "length": vn.length,
-                            "description": None::<String>
-                        }));
-                    }
-                }
-            }
-        }
-    }
-
-    // 3. Handle Anime/Manga/Book/Reading (AniList)
-    if matches!(
-        media_type,
-        MediaType::Anime | MediaType::Manga | MediaType::Book | MediaType::Reading
-    ) {
-        if raw_title != "-" {
-            let al_type = if matches!(media_type, MediaType::Anime) {
-                anilist::MediaType::Anime
-            } else {
-                anilist::MediaType::Manga
-            };
-
-            if let Some((_, id_part)) = raw_title.rsplit_once('|') {
-                if let Ok(id) = id_part.parse::<i32>() {
-                    match anilist::get_media_by_id(&data.http_client, id, al_type).await {
-                        Ok(Some(media)) => {
-                            raw_title = media.title;
-                            thumbnail = media.image;
-                            anilist_url = Some(media.url);
-                            source = "anilist";
-                        }
-                        _ => {}
-                    }
-                }
-            } else {
-                // Fallback search
-                if let Ok(medias) =
-                    anilist::search_media(&data.http_client, &raw_title, al_type, 1).await
-                {
-                    if let Some(media) = medias.first() {
-                        raw_title = media.title.clone();
-                        thumbnail = media.image.clone();
-                        anilist_url = Some(media.url.clone());
-                        source = "anilist";
-                    }
-                }
-            }
+    // 2. Look the title up on VNDB or AniList
+    if raw_title != "-" {
+        if let Some(found) = lookup_title(&data.http_client, media_type, &raw_title).await {
+            raw_title = found.title;
+            thumbnail = found.thumbnail;
+            vndb_url = found.vndb_url.or(vndb_url);
+            anilist_url = found.anilist_url.or(anilist_url);
+            source = found.source;
+            vndb_metadata = found.vndb_metadata.or(vndb_metadata);
+            airing = found.airing;
         }
     }
 
     // Validate custom date if provided
-    let effective_date = get_effective_date();
-    let (date_str, date_for_log) = if let Some(ref custom_date) = date {
+    let timezone = get_user_timezone(data, &user.id.to_string()).await;
+    let effective_date = effective_date_in(timezone.as_deref(), data.clock.now_utc());
+    let date_for_log = if let Some(ref custom_date) = date {
         // Strict validation: YYYY-MM-DD
-        match NaiveDate::parse_from_str(custom_date, "%Y-%m-%d") {
-            Ok(parsed) => (parsed.format("%Y-%m-%d").to_string(), parsed),
-            Err(_) => {
-                ctx.say("Invalid date format. Please use YYYY-MM-DD (e.g. 2026-01-21)")
-                    .await?;
+        match parse_custom_date(custom_date) {
+            Some(parsed) => parsed,
+            None => {
+                ctx.say(INVALID_DATE_MESSAGE).await?;
                 return Ok(());
             }
         }
     } else {
-        (
-            effective_date.format("%Y-%m-%d").to_string(),
-            effective_date,
-        )
+        effective_date
     };
 
     // Calculate points
     let _points = calculate_points(media_type_str, final_amount);
 
-    // Build immersion log data
-    let user_id = user.id.to_string();
-    let now = chrono::Utc::now();
-
-    let log_data = json!({
-        "user": {
-            "id": user_id,
-            "username": user.name,
-            "displayName": user.global_name.as_ref().unwrap_or(&user.name),
-            "avatar": user.avatar_url().unwrap_or_default()
-        },
-        "activity": {
-            "type": media_type_str,
-            "typeLabel": label,
-            "amount": final_amount,
-            "unit": unit,
-            "title": raw_title,
-            "comment": if raw_title != "-" { comment.as_ref() } else { None },
-            "url": log_url,
-            "anilistUrl": anilist_url,
-            "vndbUrl": vndb_url
-        },
-        "metadata": {
-            "thumbnail": thumbnail.clone(),
-            "duration": if source == "youtube" { Some(final_amount) } else { None },
-            "source": source,
-            "vndbInfo": vndb_metadata
-        },
-        "timestamps": {
-            "created": now.to_rfc3339(),
-            "date": date_str,
-            "month": format!("{}-{:02}", date_for_log.year(), date_for_log.month()),
-            "year": date_for_log.year()
-        }
-    });
-
-    // Save to Firebase
-    let firebase = &data.firebase;
-
-    // 1. Add log to user's immersion_logs subcollection
-    match firebase
-        .add_to_subcollection("users", &user_id, "immersion_logs", &log_data)
+    // A double-tapped command shouldn't silently log twice
+    match data
+        .firebase
+        .get_latest_user_log(&user.id.to_string())
         .await
     {
-        Ok(log_id) => {
-            debug!("Created immersion log: {}", log_id);
+        Ok(Some(last))
+            if is_probable_duplicate(
+                &last,
+                media_type_str,
+                final_amount,
+                &raw_title,
+                chrono::Utc::now(),
+            ) =>
+        {
+            if !confirm_duplicate_log(ctx).await? {
+                return Ok(());
+            }
         }
+        Ok(_) => {}
+        Err(e) => warn!("Failed to check for a duplicate log: {:?}", e),
+    }
+
+    let request = LogRequest {
+        media_type,
+        amount: final_amount,
+        title: raw_title,
+        comment,
+        date: date_for_log,
+        timezone,
+        log_url,
+        anilist_url,
+        vndb_url,
+        thumbnail,
+        source,
+        vndb_metadata,
+        airing,
+        guild_id: ctx.guild_id(),
+        channel_id: Some(ctx.channel_id()),
+        message_id: None,
+        private,
+    };
+    let result = match log_immersion(ctx.serenity_context(), data, user, &request).await {
+        Ok(result) => result,
         Err(e) => {
             error!("Failed to save immersion log: {:?}", e);
             ctx.say("Failed to save log. Please try again.").await?;
             return Ok(());
         }
-    }
-
-    // Get existing user data
-    let user_doc = firebase.get_document("users", &user_id).await?;
+    };
 
-    let (mut stats, existing_summary, _existing_timestamps) = if let Some(ref doc) = user_doc {
-        (
-            doc.get("stats").cloned().unwrap_or(json!({})),
-            doc.get("summary").cloned().unwrap_or(json!({})),
-            doc.get("timestamps").cloned().unwrap_or(json!({})),
-        )
+    let embed = log_embed(
+        user,
+        &request,
+        &result,
+        warning_msg,
+        video_breakdown,
+        show_cover,
+    );
+    // "Log another" edits the message it's on, which an ephemeral reply can't be
+    let components = if private {
+        Vec::new()
     } else {
-        (json!({}), json!({}), json!({}))
+        relog_buttons(false)
     };
+    let reply = ctx
+        .send(
+            poise::CreateReply::default()
+                .embed(embed)
+                .components(components)
+                .ephemeral(private),
+        )
+        .await?;
 
-    // Get current stats for this media type
-    let current_total = stats
-        .get(media_type_str)
-        .and_then(|s| s.get("total"))
-        .and_then(|t| t.as_f64())
-        .unwrap_or(0.0);
-    let current_sessions = stats
-        .get(media_type_str)
-        .and_then(|s| s.get("sessions"))
-        .and_then(|t| t.as_i64())
-        .unwrap_or(0);
-    let best_streak = stats
-        .get(media_type_str)
-        .and_then(|s| s.get("bestStreak"))
-        .and_then(|t| t.as_i64())
-        .unwrap_or(0);
-    let current_streak = stats
-        .get(media_type_str)
-        .and_then(|s| s.get("currentStreak"))
-        .and_then(|t| t.as_i64())
-        .unwrap_or(0);
-
-    // Update stats for this media type (preserve existing fields)
-    stats[media_type_str] = json!({
-        "total": current_total + amount,
-        "sessions": current_sessions + 1,
-        "lastActivity": now.to_rfc3339(),
-        "bestStreak": best_streak,
-        "currentStreak": current_streak,
-        "unit": unit,
-        "label": label
-    });
-
-    // Calculate total sessions across all media types
-    let total_sessions: i64 = stats
-        .as_object()
-        .map(|obj| {
-            obj.values()
-                .filter_map(|s| s.get("sessions").and_then(|v| v.as_i64()))
-                .sum()
-        })
-        .unwrap_or(0);
+    if let Some(target) = result.goal_reached {
+        ctx.send(
+            poise::CreateReply::default()
+                .content(goal_reached_text(user, &request, target))
+                .ephemeral(private),
+        )
+        .await?;
+    }
+    if private {
+        return Ok(());
+    }
 
-    // Get active types
-    let active_types: Vec<String> = stats
-        .as_object()
-        .map(|obj| obj.keys().cloned().collect())
-        .unwrap_or_default();
+    let message = reply.message().await?.into_owned();
+    offer_relog(ctx, message, request, show_cover).await
+}
 
-    // Get join date (preserve existing or set new)
-    let join_date = existing_summary
-        .get("joinDate")
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string())
-        .unwrap_or_else(|| now.to_rfc3339());
+/// Whether the command may log here, telling the user where to log when the guild
+/// restricts logging to its immersion channel
+async fn in_immersion_channel(ctx: Context<'_>) -> Result<bool, Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(true);
+    };
+    let data = ctx.data();
+    let gid = guild_id.to_string();
 
-    // Build user update matching Node.js structure
-    let user_update = json!({
-        "profile": {
-            "id": user_id,
-            "username": user.name,
-            "displayName": user.global_name.as_ref().unwrap_or(&user.name),
-            "avatar": user.avatar_url().unwrap_or_default(),
-            "lastSeen": now.to_rfc3339()
-        },
-        "stats": stats,
-        "summary": {
-            "totalSessions": total_sessions,
-            "lastActivity": now.to_rfc3339(),
-            "joinDate": join_date,
-            "activeTypes": active_types
-        },
-        "timestamps": {
-            "updated": now.to_rfc3339(),
-            "lastLog": now.to_rfc3339()
+    let config = if let Some(cached) = data.guild_configs.get(&gid) {
+        Some(cached.clone())
+    } else {
+        // Try fetch if not in cache (though cache should be populated on startup or first activity)
+        // For now, simple cache check or fetch
+        match data.firebase.get_document("guilds", &gid).await {
+            Ok(Some(doc)) => {
+                let cfg = crate::models::guild::GuildConfig::from_doc(doc);
+                data.guild_configs.insert(gid.clone(), cfg.clone());
+                Some(cfg)
+            }
+            _ => None,
         }
-    });
+    };
 
-    if let Err(e) = firebase.set_document("users", &user_id, &user_update).await {
-        error!("Failed to update user stats: {:?}", e);
-        // Don't return error - log was saved successfully
+    if let Some(cfg) = config {
+        if let Some(allowed_channel_id) = &cfg.immersion_channel_id {
+            let current_channel = ctx.channel_id().to_string();
+            if &current_channel != allowed_channel_id {
+                let notice = tf(Some(&cfg), Msg::ImmersionChannelOnly, &[allowed_channel_id]);
+                ctx.send(
+                    poise::CreateReply::default()
+                        .content(notice)
+                        .ephemeral(true),
+                )
+                .await?;
+                return Ok(false);
+            }
+        }
     }
+    Ok(true)
+}
 
-    // Calculate new totals for display
-    let updated_total = current_total + amount;
-
-    // Calculate streak from immersion_logs
-    // We fetch logs, validte timestamps, and repair history to JST if needed
-    let global_streak = match firebase
-        .query_subcollection("users", &user_id, "immersion_logs")
-        .await
-    {
-        Ok(logs) => {
-            let mut dates: Vec<String> = logs
-                .iter()
-                .filter_map(|log| {
-                    let timestamps = log.get("timestamps")?;
-
-                    // Try to get explicit 'date' field first (YYYY-MM-DD)
-                    if let Some(date_str) = timestamps.get("date").and_then(|v| v.as_str()) {
-                        return Some(date_str.to_string());
-                    }
+/// How long the YouTube lookup of "Log as Immersion" may take before the modal asks for
+/// the amount instead, Discord drops a command that isn't answered within 3 seconds
+const LINK_LOOKUP_TIMEOUT_SECS: u64 = 2;
+
+/// Modal of "Log as Immersion" for links the amount can't be read off
+#[derive(Debug, Clone, poise::Modal)]
+#[name = "Log as Immersion"]
+struct LinkLogModal {
+    #[name = "Type (listening or reading_time)"]
+    media_type: String,
+    #[name = "Minutes"]
+    #[placeholder = "30"]
+    amount: String,
+}
 
-                    // Fallback to 'created' timestamp for legacy logs
-                    // Legacy bot (Node.js) used server local time (WIB/UTC+7) for raw dates
-                    if let Some(created_str) = timestamps.get("created").and_then(|v| v.as_str()) {
-                        if let Ok(created_utc) = DateTime::parse_from_rfc3339(created_str) {
-                            // Convert to UTC+7 (WIB) to match legacy behavior
-                            // Legacy toDateStringRaw just dumped local time
-                            let wib_offset = chrono::FixedOffset::east_opt(7 * 3600).unwrap();
-                            let wib_time = created_utc.with_timezone(&wib_offset);
-                            return Some(wib_time.format("%Y-%m-%d").to_string());
-                        }
-                    }
+/// Log the link of a message: YouTube videos with their length, other pages with the
+/// type and minutes asked in a modal
+#[poise::command(context_menu_command = "Log as Immersion")]
+pub async fn log_as_immersion(
+    ctx: poise::ApplicationContext<'_, crate::Data, Error>,
+    message: serenity::Message,
+) -> Result<(), Error> {
+    if !in_immersion_channel(ctx.into()).await? {
+        return Ok(());
+    }
+    let Some(url) = first_url(&message.content) else {
+        ctx.send(
+            poise::CreateReply::default()
+                .content("That message has no link to log.")
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    };
+    let data = ctx.data();
+    let user = ctx.author();
 
+    let video = match youtube::extract_video_id(url) {
+        Some(id) => {
+            let yt_key = std::env::var("YOUTUBE_API_KEY").unwrap_or_default();
+            let ids = [id.clone()];
+            let lookup = youtube::get_videos_info(&data.http_client, &yt_key, &ids);
+            match tokio::time::timeout(
+                std::time::Duration::from_secs(LINK_LOOKUP_TIMEOUT_SECS),
+                lookup,
+            )
+            .await
+            {
+                Ok(Ok(mut infos)) => infos.remove(&id).map(|info| (id, info)),
+                Ok(Err(e)) => {
+                    error!("YouTube API error: {:?}", e);
                     None
-                })
-                .collect();
-
-            // Inject current date to ensure it's counted even if DB read is stale
-            dates.push(date_str.clone());
+                }
+                Err(_) => {
+                    debug!("YouTube lookup timed out, asking for the amount");
+                    None
+                }
+            }
+        }
+        None => None,
+    };
 
-            streak::calculate_streak(&dates).current
+    // Live streams and failed lookups have no length to go by
+    let (media_type, amount) = match &video {
+        Some((_, info)) if info.duration_seconds > 0 => {
+            ctx.defer_ephemeral().await?;
+            let minutes = (info.duration_seconds as f64 / 60.0).ceil();
+            (MediaType::Listening, minutes)
         }
-        Err(e) => {
-            debug!("Failed to calculate streak: {:?}", e);
-            // Even if fetch fails, we know we have at least 1 streak from today's activity
-            1
+        _ => {
+            let defaults = LinkLogModal {
+                media_type: if video.is_some() {
+                    "listening"
+                } else {
+                    "reading_time"
+                }
+                .to_string(),
+                amount: String::new(),
+            };
+            let submitted = poise::execute_modal(
+                ctx,
+                Some(defaults),
+                Some(std::time::Duration::from_secs(120)),
+            )
+            .await?;
+            let Some(modal) = submitted else {
+                return Ok(());
+            };
+            let Some(media_type) = parse_link_media_type(&modal.media_type) else {
+                ctx.send(
+                    poise::CreateReply::default()
+                        .content("Type must be `listening` or `reading_time`.")
+                        .ephemeral(true),
+                )
+                .await?;
+                return Ok(());
+            };
+            match parse_relog_amount(&modal.amount, media_type) {
+                Ok(amount) => (media_type, amount),
+                Err(reason) => {
+                    ctx.send(
+                        poise::CreateReply::default()
+                            .content(reason)
+                            .ephemeral(true),
+                    )
+                    .await?;
+                    return Ok(());
+                }
+            }
         }
     };
-
-    // Build response embed matching Node.js format
-    let mut embed = serenity::CreateEmbed::new()
-        .author(serenity::CreateEmbedAuthor::new(format!(
-            "{} Logged",
-            label
-        )))
-        .title(if raw_title != "-" {
-            raw_title.clone()
-        } else {
-            String::new()
-        })
-        .field(
-            "Progress",
-            format!("+{} {}", format_amount(final_amount), unit),
-            true,
-        )
-        .field(
-            "Total",
-            format!("{} {}", format_amount(updated_total), unit),
-            true,
-        )
-        .field(
-            "Streak",
-            format!(
-                "{} day{}",
-                global_streak,
-                if global_streak == 1 { "" } else { "s" }
-            ),
-            true,
-        )
-        .color(colors::IMMERSION)
-        .footer(serenity::CreateEmbedFooter::new(
-            if let Some(warn) = warning_msg {
-                format!("{} | {}\n{}", user.name, label, warn)
-            } else {
-                format!("{} | {}", user.name, label)
-            },
-        ))
-        .thumbnail(thumbnail.unwrap_or_else(|| user.face()));
-
-    // Add clickable URL if available (YouTube, AniList, VNDB)
-    if let Some(ref url) = log_url {
-        embed = embed.url(url);
-    } else if let Some(ref url) = anilist_url {
-        embed = embed.url(url);
-    } else if let Some(ref url) = vndb_url {
-        embed = embed.url(url);
+    // A long stream can still be over the listening limit
+    if let Err(e) = validate_amount(media_type.as_str(), amount) {
+        return Err(ValidationError(e.to_string()).into());
     }
 
-    // Add comment if provided (Discord limit: 1024 characters for field value)
-    let embed = if let Some(ref c) = comment {
-        const MAX_COMMENT_LENGTH: usize = 1000; // Leave room for truncation message
-        let comment_text = if c.len() > MAX_COMMENT_LENGTH {
-            format!(
-                "{}... (dipotong, terlalu panjang)",
-                &c[0..MAX_COMMENT_LENGTH]
+    let (title, thumbnail, log_url, source) = match video {
+        Some((id, info)) => (
+            info.title,
+            info.thumbnail,
+            youtube::normalize_url(&id),
+            "youtube",
+        ),
+        None => {
+            let title = match fetch_page_title(&data.http_client, url).await {
+                Ok(title) => title,
+                Err(e) => {
+                    error!("Failed to fetch page title: {:?}", e);
+                    None
+                }
+            };
+            (
+                title.unwrap_or_else(|| "-".to_string()),
+                None,
+                url.to_string(),
+                "web",
             )
-        } else {
-            c.clone()
-        };
-        embed.field("Comment", comment_text, false)
-    } else {
-        embed
+        }
     };
 
-    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+    let timezone = get_user_timezone(data, &user.id.to_string()).await;
+    let request = LogRequest {
+        media_type,
+        amount,
+        title,
+        comment: None,
+        date: effective_date_in(timezone.as_deref(), data.clock.now_utc()),
+        timezone,
+        log_url: Some(log_url),
+        anilist_url: None,
+        vndb_url: None,
+        thumbnail,
+        source,
+        vndb_metadata: None,
+        airing: false,
+        guild_id: ctx.guild_id(),
+        channel_id: Some(ctx.channel_id()),
+        message_id: Some(message.id),
+        private: false,
+    };
+    let result = match log_immersion(ctx.serenity_context(), data, user, &request).await {
+        Ok(result) => result,
+        Err(e) => {
+            error!("Failed to save immersion log: {:?}", e);
+            ctx.send(
+                poise::CreateReply::default()
+                    .content("Failed to save log. Please try again.")
+                    .ephemeral(true),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
 
+    ctx.send(
+        poise::CreateReply::default()
+            .embed(log_embed(user, &request, &result, None, None, None))
+            .ephemeral(true),
+    )
+    .await?;
+    if let Some(target) = result.goal_reached {
+        ctx.send(poise::CreateReply::default().content(goal_reached_text(user, &request, target)))
+            .await?;
+    }
     Ok(())
 }
 
-/// Format amount for display (remove unnecessary decimal places)
-fn format_amount(n: f64) -> String {
-    if n == n.trunc() {
-        (n as i64).to_string()
-    } else {
-        format!("{:.1}", n)
-    }
+/// First http(s) link of a message, without the `<>` that suppresses its embed, spoiler
+/// bars or trailing punctuation
+fn first_url(text: &str) -> Option<&str> {
+    text.split_whitespace().find_map(|word| {
+        let start = word.find("https://").or_else(|| word.find("http://"))?;
+        let url = word[start..].trim_end_matches(['>', ')', ']', '|', ',', '.', '!', '?']);
+        url.split_once("://")
+            .is_some_and(|(_, rest)| !rest.is_empty())
+            .then_some(url)
+    })
 }
 
-// Local calculate_user_streak removed in favor of utils::streak::calculate_streak
+/// Type typed into the "Log as Immersion" modal, only the types counted in minutes
+fn parse_link_media_type(input: &str) -> Option<MediaType> {
+    match input
+        .trim()
+        .to_lowercase()
+        .replace([' ', '-'], "_")
+        .as_str()
+    {
+        "listening" | "listen" | "l" => Some(MediaType::Listening),
+        "reading_time" | "reading" | "read" | "r" => Some(MediaType::ReadingTime),
+        _ => None,
+    }
+}
 
-async fn autocomplete_title(ctx: Context<'_>, partial: &str) -> impl Iterator<Item = String> {
-    let mut results = Vec::new();
+/// Most lines one batch logs, the rest are listed as skipped
+const BATCH_MAX_ENTRIES: usize = 20;
 
-    // Attempt to find media_type in options
-    let media_type_val = if let poise::Context::Application(app_ctx) = ctx {
-        app_ctx
-            .interaction
-            .data
-            .options
-            .iter()
-            .find(|o| o.name == "media_type")
-            .and_then(|o| match &o.value {
-                serenity::model::application::CommandDataOptionValue::String(s) => Some(s.clone()),
-                serenity::model::application::CommandDataOptionValue::Integer(i) => match i {
-                    0 => Some("visual_novel".to_string()),
-                    1 => Some("manga".to_string()),
-                    2 => Some("anime".to_string()),
-                    _ => None,
-                },
-                _ => None,
-            })
-    } else {
-        None
-    };
+/// How long the batch preview waits for Confirm
+const BATCH_CONFIRM_TIMEOUT_SECS: u64 = 120;
 
-    let http = &ctx.data().http_client;
+/// `metadata.source` of logs made with /immersion_batch
+const BATCH_SOURCE: &str = "batch";
 
-    // Only search if length >= 2
-    if let Some(mt) = media_type_val.as_deref() {
-        match mt {
-            "visual_novel" | "VisualNovel" => {
-                if partial.len() >= 2 {
-                    if let Ok(vns) = vndb::search_vns(http, partial, 10).await {
-                        for vn in vns {
-                            let released = vn.released.unwrap_or_default();
-                            // Format: "Title (Year)|ID"
-                            let mut entry = format!("{} ({})|{}", vn.title, released, vn.id);
-
-                            // Truncate if too long (Discord limit 100)
-                            if entry.len() > 100 {
-                                let id_len = vn.id.len() + 1; // +1 for pipe
-                                let avail = 100 - id_len;
-                                if avail > 0 {
-                                    entry = format!(
-                                        "{}|{}",
-                                        &vn.title[0..avail.min(vn.title.len())],
-                                        vn.id
-                                    );
-                                }
-                            }
-
-                            results.push(entry);
-                        }
-                    }
-                }
-            }
-            "anime" | "Anime" | "manga" | "Manga" => {
-                if partial.len() >= 2 {
-                    let al_type = if mt.eq_ignore_ascii_case("anime") {
-                        anilist::MediaType::Anime
-                    } else {
-                        anilist::MediaType::Manga
-                    };
-                    if let Ok(medias) = anilist::search_media(http, partial, al_type, 10).await {
-                        for media in medias {
-                            let mut entry = format!("{}|{}", media.title, media.id);
-                            if entry.len() > 100 {
-                                let id_len = media.id.to_string().len() + 1;
-                                let avail = 100 - id_len;
-                                if avail > 0 {
-                                    entry = format!(
-                                        "{}|{}",
-                                        &media.title[0..avail.min(media.title.len())],
-                                        media.id
-                                    );
-                                }
-                            }
-                            results.push(entry);
-                        }
-                    }
-                }
-            }
-            _ => {}
-        }
-    } else {
-        results.push("⚠️ Select Media Type First".to_string());
-    }
+/// Modal of /immersion_batch, one log per line
+#[derive(Debug, Clone, poise::Modal)]
+#[name = "Batch Log"]
+struct BatchLogModal {
+    #[name = "One log per line: type amount [title]"]
+    #[placeholder = "anime 2 Frieren\nmanga 40 Yotsuba\nrt 45"]
+    #[paragraph]
+    #[max_length = 4000]
+    entries: String,
+}
 
-    // If no results, suggest the partial input itself
-    if results.is_empty() && !partial.is_empty() {
-        results.push(partial.to_string());
-    }
+/// One parsed line of a batch
+#[derive(Debug, Clone, PartialEq)]
+struct BatchEntry {
+    media_type: MediaType,
+    amount: f64,
+    title: Option<String>,
+}
 
-    results.into_iter()
+/// A non-empty line of the batch, numbered from 1, with what it parsed to or why it didn't
+#[derive(Debug, Clone, PartialEq)]
+struct BatchLine {
+    number: usize,
+    text: String,
+    parsed: Result<BatchEntry, String>,
+}
+
+/// Media type of a batch line: the stored key or one of its short names
+fn parse_batch_media_type(input: &str) -> Option<MediaType> {
+    match input.to_lowercase().replace('-', "_").as_str() {
+        "vn" => Some(MediaType::VisualNovel),
+        "rt" => Some(MediaType::ReadingTime),
+        "listen" => Some(MediaType::Listening),
+        "read" => Some(MediaType::Reading),
+        key => MediaType::from_key(key),
+    }
+}
+
+/// Parse `type amount [title]`, with the same amount limits as /immersion without `force`
+fn parse_batch_line(line: &str) -> Result<BatchEntry, String> {
+    let mut parts = line.trim().splitn(3, char::is_whitespace);
+    let (Some(media_type), Some(amount)) = (parts.next(), parts.next()) else {
+        return Err("Expected `type amount [title]`.".to_string());
+    };
+    let media_type = parse_batch_media_type(media_type).ok_or_else(|| {
+        format!(
+            "Unknown type `{}`, use vn, anime, manga, book, rt, listen or read.",
+            media_type
+        )
+    })?;
+    let amount = parse_relog_amount(amount, media_type)?;
+    let title = parts
+        .next()
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(str::to_string);
+    Ok(BatchEntry {
+        media_type,
+        amount,
+        title,
+    })
+}
+
+/// Parse every non-empty line. Lines past `BATCH_MAX_ENTRIES` valid ones are skipped.
+fn parse_batch(text: &str) -> Vec<BatchLine> {
+    let mut valid = 0;
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let mut parsed = parse_batch_line(line);
+            if parsed.is_ok() {
+                valid += 1;
+                if valid > BATCH_MAX_ENTRIES {
+                    parsed = Err(format!(
+                        "Over the limit of {} logs per batch.",
+                        BATCH_MAX_ENTRIES
+                    ));
+                }
+            }
+            BatchLine {
+                number: i + 1,
+                text: line.trim().to_string(),
+                parsed,
+            }
+        })
+        .collect()
+}
+
+/// "2 episodes of Anime — Frieren"
+fn batch_entry_text(entry: &BatchEntry) -> String {
+    let media_type = entry.media_type.as_str();
+    let mut text = format!(
+        "{} {} of {}",
+        format_amount(entry.amount),
+        get_unit(media_type),
+        get_media_label(media_type)
+    );
+    if let Some(ref title) = entry.title {
+        text.push_str(&format!(" — {}", truncate_chars(title, 60)));
+    }
+    text
+}
+
+/// Lines of the batch that won't be logged, with the reason
+fn batch_skipped_text(lines: &[BatchLine]) -> Option<String> {
+    let skipped: Vec<String> = lines
+        .iter()
+        .filter_map(|line| {
+            line.parsed.as_ref().err().map(|reason| {
+                format!(
+                    "Line {}: `{}` — {}",
+                    line.number,
+                    truncate_chars(&line.text, 40),
+                    reason
+                )
+            })
+        })
+        .collect();
+    (!skipped.is_empty()).then(|| fit_description(skipped, EMBED_FIELD_LIMIT).0)
+}
+
+fn batch_preview_embed(lines: &[BatchLine]) -> serenity::CreateEmbed {
+    let entries: Vec<String> = lines
+        .iter()
+        .filter_map(|line| line.parsed.as_ref().ok())
+        .map(|entry| format!("• {}", batch_entry_text(entry)))
+        .collect();
+    let mut embed = serenity::CreateEmbed::new()
+        .title("Batch Log Preview")
+        .description(entries.join("\n"))
+        .color(colors::IMMERSION)
+        .footer(serenity::CreateEmbedFooter::new(format!(
+            "{} to log · Confirm within {} seconds",
+            entries.len(),
+            BATCH_CONFIRM_TIMEOUT_SECS
+        )));
+    if let Some(skipped) = batch_skipped_text(lines) {
+        embed = embed.field("Skipped", skipped, false);
+    }
+    embed
+}
+
+fn batch_buttons() -> Vec<serenity::CreateActionRow> {
+    vec![serenity::CreateActionRow::Buttons(vec![
+        serenity::CreateButton::new("immersion_batch_confirm")
+            .label("Confirm")
+            .style(serenity::ButtonStyle::Success),
+        serenity::CreateButton::new("immersion_batch_cancel")
+            .label("Cancel")
+            .style(serenity::ButtonStyle::Secondary),
+    ])]
+}
+
+/// Log several activities at once, e.g. a weekend of anime across a few shows
+#[poise::command(slash_command)]
+pub async fn immersion_batch(
+    ctx: poise::ApplicationContext<'_, crate::Data, Error>,
+) -> Result<(), Error> {
+    if !in_immersion_channel(ctx.into()).await? {
+        return Ok(());
+    }
+
+    let submitted = poise::execute_modal::<_, _, BatchLogModal>(
+        ctx,
+        None,
+        Some(std::time::Duration::from_secs(300)),
+    )
+    .await?;
+    let Some(modal) = submitted else {
+        return Ok(());
+    };
+
+    let lines = parse_batch(&modal.entries);
+    let entries: Vec<&BatchEntry> = lines
+        .iter()
+        .filter_map(|line| line.parsed.as_ref().ok())
+        .collect();
+    if entries.is_empty() {
+        let mut embed = serenity::CreateEmbed::new()
+            .title("Nothing to Log")
+            .description("Write one log per line as `type amount [title]`, e.g. `anime 2 Frieren`.")
+            .color(colors::WARNING);
+        if let Some(skipped) = batch_skipped_text(&lines) {
+            embed = embed.field("Skipped", skipped, false);
+        }
+        ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+            .await?;
+        return Ok(());
+    }
+
+    let reply = ctx
+        .send(
+            poise::CreateReply::default()
+                .embed(batch_preview_embed(&lines))
+                .components(batch_buttons()),
+        )
+        .await?;
+    let msg = reply.message().await?.into_owned();
+    let interaction = msg
+        .await_component_interaction(ctx.serenity_context())
+        .author_id(ctx.author().id)
+        .timeout(std::time::Duration::from_secs(BATCH_CONFIRM_TIMEOUT_SECS))
+        .await;
+
+    let Some(interaction) = interaction.filter(|i| i.data.custom_id == "immersion_batch_confirm")
+    else {
+        let _ = reply
+            .edit(
+                ctx.into(),
+                poise::CreateReply::default()
+                    .content("Cancelled, nothing was logged.")
+                    .components(vec![]),
+            )
+            .await;
+        return Ok(());
+    };
+    let _ = interaction
+        .create_response(
+            ctx.http(),
+            serenity::CreateInteractionResponse::UpdateMessage(
+                serenity::CreateInteractionResponseMessage::new()
+                    .content(format!("Logging {} entries…", entries.len()))
+                    .components(vec![]),
+            ),
+        )
+        .await;
+
+    let data = ctx.data();
+    let user = ctx.author();
+    let overrides = match ctx.guild_id() {
+        Some(guild_id) => crate::utils::config::get_guild_config(data, &guild_id.to_string())
+            .await
+            .and_then(|c| c.points_overrides),
+        None => None,
+    };
+    let timezone = get_user_timezone(data, &user.id.to_string()).await;
+    let today = effective_date_in(timezone.as_deref(), data.clock.now_utc());
+
+    // Saved one by one, so each log's streak counts the ones before it
+    let mut logged = Vec::new();
+    let mut failed = Vec::new();
+    let mut last_result = None;
+    for entry in &entries {
+        let request = LogRequest {
+            media_type: entry.media_type,
+            amount: entry.amount,
+            title: entry.title.clone().unwrap_or_else(|| "-".to_string()),
+            comment: None,
+            date: today,
+            timezone: timezone.clone(),
+            log_url: None,
+            anilist_url: None,
+            vndb_url: None,
+            thumbnail: None,
+            source: BATCH_SOURCE,
+            vndb_metadata: None,
+            airing: false,
+            guild_id: ctx.guild_id(),
+            channel_id: Some(ctx.channel_id()),
+            message_id: None,
+            private: false,
+        };
+        match log_immersion(ctx.serenity_context(), data, user, &request).await {
+            Ok(result) => {
+                if let Some(target) = result.goal_reached {
+                    let _ = ctx
+                        .send(
+                            poise::CreateReply::default()
+                                .content(goal_reached_text(user, &request, target)),
+                        )
+                        .await;
+                }
+                logged.push(*entry);
+                last_result = Some(result);
+            }
+            Err(e) => {
+                error!("Failed to save batch log: {:?}", e);
+                failed.push(format!("• {}", batch_entry_text(entry)));
+            }
+        }
+    }
+
+    let points: i64 = logged
+        .iter()
+        .map(|entry| {
+            crate::utils::points::calculate_points_with(
+                entry.media_type.as_str(),
+                entry.amount,
+                overrides.as_ref(),
+            )
+        })
+        .sum();
+    let mut embed = serenity::CreateEmbed::new()
+        .author(serenity::CreateEmbedAuthor::new(format!(
+            "Batch Logged · {}",
+            user.name
+        )))
+        .description(
+            logged
+                .iter()
+                .map(|entry| format!("• {}", batch_entry_text(entry)))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )
+        .field("Logs", format!("{}/{}", logged.len(), entries.len()), true)
+        .field("Points", format!("+{}", points), true)
+        .color(colors::IMMERSION)
+        .thumbnail(user.face());
+    if let Some(ref result) = last_result {
+        embed = embed.field(
+            "Streak",
+            streak_text(result.streak, result.freeze.as_ref()),
+            true,
+        );
+    }
+    if !failed.is_empty() {
+        embed = embed.field(
+            "Failed to save, please log again",
+            fit_description(failed, EMBED_FIELD_LIMIT).0,
+            false,
+        );
+    }
+    if let Some(skipped) = batch_skipped_text(&lines) {
+        embed = embed.field("Skipped", skipped, false);
+    }
+
+    let _ = reply
+        .edit(
+            ctx.into(),
+            poise::CreateReply::default()
+                .content("")
+                .embed(embed)
+                .components(vec![]),
+        )
+        .await;
+    Ok(())
+}
+
+/// A log ready to be saved, shared by /immersion, its "Log another" button,
+/// "Log as Immersion" and /immersion_batch
+#[derive(Debug, Clone)]
+struct LogRequest {
+    media_type: MediaType,
+    amount: f64,
+    /// "-" when there is none
+    title: String,
+    comment: Option<String>,
+    date: NaiveDate,
+    /// The member's `/timezone`, `None` for WIB
+    timezone: Option<String>,
+    log_url: Option<String>,
+    anilist_url: Option<String>,
+    vndb_url: Option<String>,
+    thumbnail: Option<String>,
+    source: &'static str,
+    vndb_metadata: Option<Value>,
+    /// Whether the anime or manga is still releasing, for hiding its cover
+    airing: bool,
+    /// Guild the log was made in, `None` in DMs. Stored as `context.guildId`, and the guild
+    /// whose title ranking the log counts towards.
+    guild_id: Option<serenity::GuildId>,
+    channel_id: Option<serenity::ChannelId>,
+    /// Message the log was made from, so a reaction log isn't saved twice
+    message_id: Option<serenity::MessageId>,
+    /// Kept to the member, stored as `metadata.private`, see `privacy::is_private_log`
+    private: bool,
+}
+
+/// What saving a log changed
+struct LogResult {
+    updated_total: f64,
+    /// User document as it was read inside the transaction
+    user_doc: Option<UserDoc>,
+    /// Global streak including the new log
+    streak: i32,
+    freeze: Option<FreezeUpdate>,
+    /// Monthly goal progress, when the log counts towards this month's goal
+    goal_progress: Option<String>,
+    /// Target of the monthly goal this log just reached
+    goal_reached: Option<f64>,
+    /// How far through the VN the user is, from its VNDB length
+    completion: Option<String>,
+    /// The `/now` title to ask about updating, see `now::should_nudge`
+    now_nudge: Option<String>,
+}
+
+/// Save an untitled log for today made from `message` (its channel and id), returning its
+/// result embed.
+/// For quick logs that don't go through /immersion, like reaction logs.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn quick_log(
+    ctx: &serenity::Context,
+    data: &crate::Data,
+    user: &serenity::User,
+    media_type: MediaType,
+    amount: f64,
+    source: &'static str,
+    guild_id: Option<serenity::GuildId>,
+    message: (serenity::ChannelId, serenity::MessageId),
+) -> anyhow::Result<serenity::CreateEmbed> {
+    let (channel_id, message_id) = message;
+    let timezone = get_user_timezone(data, &user.id.to_string()).await;
+    let request = LogRequest {
+        media_type,
+        amount,
+        title: "-".to_string(),
+        comment: None,
+        date: effective_date_in(timezone.as_deref(), data.clock.now_utc()),
+        timezone,
+        log_url: None,
+        anilist_url: None,
+        vndb_url: None,
+        thumbnail: None,
+        source,
+        vndb_metadata: None,
+        airing: false,
+        guild_id,
+        channel_id: Some(channel_id),
+        message_id: Some(message_id),
+        private: false,
+    };
+    let result = log_immersion(ctx, data, user, &request).await?;
+    Ok(log_embed(user, &request, &result, None, None, None))
+}
+
+/// Save a log with its stats, streaks, title popularity and monthly goal
+async fn log_immersion(
+    ctx: &serenity::Context,
+    data: &crate::Data,
+    user: &serenity::User,
+    request: &LogRequest,
+) -> anyhow::Result<LogResult> {
+    let media_type_str = request.media_type.as_str();
+    let label = get_media_label(media_type_str);
+    let unit = get_unit(media_type_str);
+    let date_str = request.date.format("%Y-%m-%d").to_string();
+    let timezone = request.timezone.as_deref();
+    let today = effective_date_in(timezone, data.clock.now_utc());
+
+    // Build immersion log data
+    let user_id = user.id.to_string();
+    let now = chrono::Utc::now();
+
+    let log_data = json!({
+        "user": {
+            "id": user_id,
+            "username": user.name,
+            "displayName": user.global_name.as_ref().unwrap_or(&user.name),
+            "avatar": user.avatar_url().unwrap_or_default()
+        },
+        "activity": {
+            "type": media_type_str,
+            "typeLabel": label,
+            "amount": request.amount,
+            "unit": unit,
+            "title": request.title,
+            "comment": if request.title != "-" { request.comment.as_ref() } else { None },
+            "url": request.log_url,
+            "anilistUrl": request.anilist_url,
+            "vndbUrl": request.vndb_url
+        },
+        "metadata": {
+            "thumbnail": request.thumbnail,
+            "duration": if request.source == "youtube" { Some(request.amount) } else { None },
+            "source": request.source,
+            "vndbInfo": request.vndb_metadata,
+            "messageId": request.message_id.map(|id| id.to_string()),
+            "private": request.private
+        },
+        "context": {
+            "guildId": request.guild_id.map(|id| id.to_string()),
+            "channelId": request.channel_id.map(|id| id.to_string())
+        },
+        "timestamps": {
+            "created": now.to_rfc3339(),
+            "date": date_str,
+            "month": format!("{}-{:02}", request.date.year(), request.date.month()),
+            "year": request.date.year()
+        }
+    });
+
+    // Streaks are computed before the write so the per-type streak can be stored with the stats.
+    // Today's log isn't in the list yet, so its date is added by hand.
+    let prior_logs = data.firebase.get_user_logs(&user_id).await;
+    let (global_streak, type_streak) = match &prior_logs {
+        Ok(logs) => {
+            let mut dates = streak::log_dates_in(logs, None, timezone);
+            dates.push(date_str.clone());
+            let mut type_dates = streak::log_dates_in(logs, Some(media_type_str), timezone);
+            type_dates.push(date_str.clone());
+
+            (
+                streak::calculate_streak_on(&dates, today).current,
+                Some(streak::calculate_streak_on(&type_dates, today)),
+            )
+        }
+        Err(e) => {
+            debug!("Failed to calculate streak: {:?}", e);
+            // Even if fetch fails, we know we have at least 1 streak from today's activity
+            (1, None)
+        }
+    };
+
+    // Save to Firebase
+    // The id is generated up front so a retried commit can never create a second copy
+    let log_id = generate_document_id();
+    let increment = StatsIncrement {
+        media_type: media_type_str,
+        amount: request.amount,
+        unit,
+        label,
+        profile: UserProfile {
+            id: user_id.clone(),
+            username: user.name.clone(),
+            display_name: Some(
+                user.global_name
+                    .clone()
+                    .unwrap_or_else(|| user.name.clone()),
+            ),
+            avatar: Some(user.avatar_url().unwrap_or_default()),
+            last_seen: Some(now.to_rfc3339()),
+            ..Default::default()
+        },
+        now: now.to_rfc3339(),
+        streak: type_streak,
+        global_dates: prior_logs
+            .as_ref()
+            .ok()
+            .map(|logs| streak::log_dates_in(logs, None, timezone)),
+        date: date_str,
+        today,
+    };
+
+    let (updated_total, user_doc, freeze) = save_log(
+        data.firebase.as_ref(),
+        &user_id,
+        &log_id,
+        &log_data,
+        &increment,
+    )
+    .await?;
+    debug!("Created immersion log: {}", log_id);
+
+    // Count towards the guild's autocomplete ranking, in the background
+    if let (Some(guild_id), true) = (request.guild_id, request.title != "-" && !request.private) {
+        let cache = data.title_popularity.clone();
+        let (media_type, title, date) = (
+            media_type_str.to_string(),
+            request.title.clone(),
+            request.date,
+        );
+        tokio::spawn(async move {
+            if let Err(e) = cache
+                .record_log(&guild_id.to_string(), &media_type, &title, date)
+                .await
+            {
+                warn!("Failed to record title popularity: {:?}", e);
+            }
+        });
+    }
+
+    // Monthly goal progress, only for logs dated in the current month
+    let current_month = goal::month_key(today);
+    let log_month = goal::month_key(request.date);
+    let mut goals = user_doc.as_ref().map(UserDoc::goals).unwrap_or_default();
+    let mut goal_progress = None;
+    let mut goal_reached = None;
+    if let (Some(g), Ok(logs)) = (goals.get_mut(media_type_str), &prior_logs) {
+        if g.is_active(&current_month) && log_month == current_month {
+            let before = goal::month_progress(logs, media_type_str, &current_month);
+            let after = before + request.amount;
+            if goal::crossed(before, after, g.amount) && g.reached_at.is_none() {
+                g.reached_at = Some(now.to_rfc3339());
+                goal_reached = Some(g.amount);
+            }
+            goal_progress = Some(goal::progress_text(after, g.amount, unit));
+        }
+    }
+    if goal_reached.is_some() {
+        if let Err(e) = crate::commands::goal::save_goals(data, &user_id, &goals).await {
+            error!("Failed to mark goal as reached: {:?}", e);
+        }
+    }
+
+    let completion = prior_logs
+        .as_ref()
+        .ok()
+        .and_then(|logs| vn_completion(request, logs));
+
+    // Logging another VN or book for a while, the `/now` status is asked about once
+    let current = user_doc
+        .as_ref()
+        .and_then(|doc| doc.summary.current.as_ref());
+    let now_nudge = match (current, &prior_logs) {
+        (Some(current), Ok(logs))
+            if now::should_nudge(current, media_type_str, &request.title, logs) =>
+        {
+            if let Err(e) = data
+                .firebase
+                .set_document_merge_paths(
+                    "users",
+                    &user_id,
+                    &json!({ "summary": { "current": { "nudged": true } } }),
+                    &["summary.current.nudged"],
+                )
+                .await
+            {
+                warn!("Failed to mark now status as nudged: {:?}", e);
+            }
+            Some(current.title.clone())
+        }
+        _ => None,
+    };
+
+    // Announced in the guild's activity feed, unless the log or the member is kept off
+    // public rankings
+    if let (Some(guild_id), false) = (request.guild_id, request.private) {
+        // Without the user document there's no way to tell they didn't opt out
+        let public = user_doc
+            .as_ref()
+            .is_some_and(|doc| LeaderboardPrivacy::of(&doc.profile).is_public());
+        if public {
+            let overrides = crate::utils::config::get_guild_config(data, &guild_id.to_string())
+                .await
+                .and_then(|c| c.points_overrides);
+            let entry = FeedEntry {
+                display_name: user
+                    .global_name
+                    .clone()
+                    .unwrap_or_else(|| user.name.clone()),
+                media_type: media_type_str,
+                amount: request.amount,
+                points: crate::utils::points::calculate_points_with(
+                    media_type_str,
+                    request.amount,
+                    overrides.as_ref(),
+                ),
+                title: Some(request.title.clone()).filter(|title| title != "-"),
+                link: request
+                    .channel_id
+                    .zip(request.message_id)
+                    .map(|(channel_id, message_id)| {
+                        activity_feed::message_link(guild_id, channel_id, message_id)
+                    }),
+            };
+            activity_feed::post(ctx, data, guild_id, entry).await;
+        }
+    }
+
+    Ok(LogResult {
+        updated_total,
+        streak: freeze.as_ref().map_or(global_streak, |s| s.current),
+        user_doc,
+        freeze,
+        goal_progress,
+        goal_reached,
+        completion,
+        now_nudge,
+    })
+}
+
+/// VNDB id of a log, from the stored id or the VNDB URL of logs saved before it was stored
+fn log_vndb_id(log: &Value) -> Option<&str> {
+    log.pointer("/metadata/vndbInfo/id")
+        .and_then(|id| id.as_str())
+        .or_else(|| {
+            log.pointer("/activity/vndbUrl")
+                .and_then(|url| url.as_str())
+                .and_then(|url| url.strip_prefix("https://vndb.org/"))
+        })
+}
+
+/// "37% (of ~30h)" for a VNDB-resolved VN log with a length, counting every earlier log
+/// of the same VN
+fn vn_completion(request: &LogRequest, prior_logs: &[Value]) -> Option<String> {
+    let info = request.vndb_metadata.as_ref()?;
+    let id = info.get("id")?.as_str()?;
+    let length = info.get("length")?.as_i64()? as i32;
+    let (hours, characters) = (
+        vndb::estimated_hours(length)?,
+        vndb::estimated_characters(length)?,
+    );
+    let read: f64 = prior_logs
+        .iter()
+        .filter(|log| log_vndb_id(log) == Some(id))
+        .filter_map(|log| log.pointer("/activity/amount").and_then(|a| a.as_f64()))
+        .sum::<f64>()
+        + request.amount;
+    Some(format!(
+        "{:.0}% (of ~{}h)",
+        read / characters * 100.0,
+        hours
+    ))
+}
+
+/// Result embed of a saved log
+fn log_embed(
+    user: &serenity::User,
+    request: &LogRequest,
+    result: &LogResult,
+    warning_msg: Option<&str>,
+    video_breakdown: Option<String>,
+    show_cover: Option<bool>,
+) -> serenity::CreateEmbed {
+    let label = get_media_label(request.media_type.as_str());
+    let unit = get_unit(request.media_type.as_str());
+    let current_marker = result
+        .user_doc
+        .as_ref()
+        .and_then(|doc| doc.summary.current.as_ref())
+        .filter(|current| now::is_current(current, &request.title))
+        .map(|current| format!(" · currently {} ✓", now::verb(&current.media_type)))
+        .unwrap_or_default();
+    let private_marker = if request.private {
+        " · 🔒 private"
+    } else {
+        ""
+    };
+
+    // Build response embed matching Node.js format
+    let mut embed = serenity::CreateEmbed::new()
+        .author(serenity::CreateEmbedAuthor::new(format!(
+            "{} Logged",
+            label
+        )))
+        .title(if request.title != "-" {
+            request.title.clone()
+        } else {
+            String::new()
+        })
+        .field(
+            "Progress",
+            format!("+{} {}", format_amount(request.amount), unit),
+            true,
+        )
+        .field(
+            "Total",
+            format!("{} {}", format_amount(result.updated_total), unit),
+            true,
+        )
+        .field(
+            "Streak",
+            streak_text(result.streak, result.freeze.as_ref()),
+            true,
+        )
+        .color(colors::IMMERSION)
+        .footer(serenity::CreateEmbedFooter::new(
+            if let Some(warn) = warning_msg {
+                format!(
+                    "{} | {}{}{}\n{}",
+                    user.name, label, current_marker, private_marker, warn
+                )
+            } else {
+                format!(
+                    "{} | {}{}{}",
+                    user.name, label, current_marker, private_marker
+                )
+            },
+        ))
+        .thumbnail(
+            request
+                .thumbnail
+                .clone()
+                .filter(|_| {
+                    let hide = result
+                        .user_doc
+                        .as_ref()
+                        .is_some_and(|u| u.preference("hideAiringCovers"));
+                    should_show_cover(hide, request.airing, show_cover)
+                })
+                .unwrap_or_else(|| user.face()),
+        );
+
+    // Add clickable URL if available (YouTube, AniList, VNDB)
+    if let Some(ref url) = request.log_url {
+        embed = embed.url(url);
+    } else if let Some(ref url) = request.anilist_url {
+        embed = embed.url(url);
+    } else if let Some(ref url) = request.vndb_url {
+        embed = embed.url(url);
+    }
+
+    if let Some(breakdown) = video_breakdown {
+        embed = embed.field("Videos", breakdown, false);
+    }
+
+    if let Some(ref completion) = result.completion {
+        embed = embed.field("Est. completion", completion, true);
+    }
+
+    // Add comment if provided, cut to fit the field with the note saying so
+    if let Some(ref c) = request.comment {
+        const TRUNCATED_SUFFIX: &str = " (dipotong, terlalu panjang)";
+        let budget = EMBED_FIELD_LIMIT - TRUNCATED_SUFFIX.chars().count();
+        let comment_text = if c.chars().count() > budget {
+            format!("{}{}", truncate_chars(c, budget), TRUNCATED_SUFFIX)
+        } else {
+            c.clone()
+        };
+        embed = embed.field("Comment", comment_text, false);
+    }
+
+    if let Some(ref progress) = result.goal_progress {
+        embed = embed.description(format!("Goal: {}", progress));
+    }
+    if let Some(ref title) = result.now_nudge {
+        embed = embed.field(
+            "Still on it?",
+            format!(
+                "Your `/now` status says **{}**. Update it with `/now set` if you've moved on.",
+                title
+            ),
+            false,
+        );
+    }
+    embed
+}
+
+fn goal_reached_text(user: &serenity::User, request: &LogRequest, target: f64) -> String {
+    let media_type_str = request.media_type.as_str();
+    format!(
+        "🎉 <@{}> reached their {} goal for {}: {} {}!",
+        user.id,
+        get_media_label(media_type_str),
+        goal::month_key(request.date),
+        format_amount(target),
+        get_unit(media_type_str)
+    )
+}
+
+/// How long the "Log another" button of a log stays usable
+const RELOG_TIMEOUT_SECS: u64 = 15 * 60;
+
+/// Modal behind "Log another", everything but the amount is taken from the log above
+#[derive(Debug, Clone, poise::Modal)]
+#[name = "Log Another"]
+struct RelogModal {
+    #[name = "Amount"]
+    amount: String,
+}
+
+/// Validate the modal amount with the same limits as /immersion, without `force`
+fn parse_relog_amount(amount: &str, media_type: MediaType) -> Result<f64, String> {
+    let amount = amount
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|a| a.is_finite() && *a >= 1.0)
+        .ok_or("Amount must be a number of at least 1.")?;
+    validate_amount(media_type.as_str(), amount).map_err(|e| e.to_string())?;
+    Ok(amount)
+}
+
+fn relog_buttons(disabled: bool) -> Vec<serenity::CreateActionRow> {
+    vec![serenity::CreateActionRow::Buttons(vec![
+        serenity::CreateButton::new("immersion_relog")
+            .label("Log another")
+            .style(serenity::ButtonStyle::Secondary)
+            .disabled(disabled),
+    ])]
+}
+
+/// Let the author log the same title again from the result embed. Each new log gets
+/// its own button and retires the previous one; the last one is disabled on timeout.
+async fn offer_relog(
+    ctx: Context<'_>,
+    mut message: serenity::Message,
+    mut request: LogRequest,
+    show_cover: Option<bool>,
+) -> Result<(), Error> {
+    use futures::StreamExt;
+
+    let data = ctx.data();
+    let user = ctx.author();
+    loop {
+        let mut collector = message
+            .await_component_interactions(ctx)
+            .custom_ids(vec!["immersion_relog".to_string()])
+            .timeout(std::time::Duration::from_secs(RELOG_TIMEOUT_SECS))
+            .stream();
+
+        let mut next = None;
+        while let Some(interaction) = collector.next().await {
+            if interaction.user.id != user.id {
+                let _ = interaction
+                    .create_response(
+                        ctx.http(),
+                        serenity::CreateInteractionResponse::Message(
+                            serenity::CreateInteractionResponseMessage::new()
+                                .content("Only the person who made this log can log another.")
+                                .ephemeral(true),
+                        ),
+                    )
+                    .await;
+                continue;
+            }
+
+            let defaults = RelogModal {
+                amount: request.amount.to_string(),
+            };
+            let submitted = poise::execute_modal_on_component_interaction(
+                ctx,
+                interaction.clone(),
+                Some(defaults),
+                Some(std::time::Duration::from_secs(120)),
+            )
+            .await?;
+            let Some(modal) = submitted else {
+                continue;
+            };
+
+            let amount = match parse_relog_amount(&modal.amount, request.media_type) {
+                Ok(amount) => amount,
+                Err(reason) => {
+                    let _ = interaction
+                        .create_followup(
+                            ctx.http(),
+                            serenity::CreateInteractionResponseFollowup::new()
+                                .content(reason)
+                                .ephemeral(true),
+                        )
+                        .await;
+                    continue;
+                }
+            };
+
+            // Same media and title, logged for today
+            let relog = LogRequest {
+                amount,
+                comment: None,
+                date: effective_date_in(request.timezone.as_deref(), data.clock.now_utc()),
+                ..request.clone()
+            };
+            let result = match log_immersion(ctx.serenity_context(), data, user, &relog).await {
+                Ok(result) => result,
+                Err(e) => {
+                    error!("Failed to save immersion log: {:?}", e);
+                    let _ = interaction
+                        .create_followup(
+                            ctx.http(),
+                            serenity::CreateInteractionResponseFollowup::new()
+                                .content("Failed to save log. Please try again.")
+                                .ephemeral(true),
+                        )
+                        .await;
+                    continue;
+                }
+            };
+
+            let followup = interaction
+                .create_followup(
+                    ctx.http(),
+                    serenity::CreateInteractionResponseFollowup::new()
+                        .embed(log_embed(user, &relog, &result, None, None, show_cover))
+                        .components(relog_buttons(false)),
+                )
+                .await?;
+            if let Some(target) = result.goal_reached {
+                let _ = interaction
+                    .create_followup(
+                        ctx.http(),
+                        serenity::CreateInteractionResponseFollowup::new()
+                            .content(goal_reached_text(user, &relog, target)),
+                    )
+                    .await;
+            }
+            next = Some((followup, relog));
+            break;
+        }
+
+        // Timed out, or the new log carries the button from here
+        let _ = message
+            .edit(
+                ctx,
+                serenity::EditMessage::new().components(relog_buttons(true)),
+            )
+            .await;
+        match next {
+            Some((followup, relog)) => {
+                message = followup;
+                request = relog;
+            }
+            None => return Ok(()),
+        }
+    }
+}
+
+/// How recent an identical log has to be to ask before logging again
+const DUPLICATE_WINDOW_SECS: i64 = 120;
+
+/// How long the "Log anyway" prompt waits before cancelling
+const DUPLICATE_CONFIRM_TIMEOUT_SECS: u64 = 30;
+
+/// Whether `last` has the same type, amount and title and was created within
+/// `DUPLICATE_WINDOW_SECS` before `now`
+fn is_probable_duplicate(
+    last: &Value,
+    media_type: &str,
+    amount: f64,
+    title: &str,
+    now: chrono::DateTime<chrono::Utc>,
+) -> bool {
+    let activity = &last["activity"];
+    let same = activity["type"].as_str() == Some(media_type)
+        && activity["amount"]
+            .as_f64()
+            .is_some_and(|a| (a - amount).abs() < 1e-9)
+        && activity["title"].as_str().unwrap_or("-") == title;
+    let recent = last["timestamps"]["created"]
+        .as_str()
+        .and_then(|c| chrono::DateTime::parse_from_rfc3339(c).ok())
+        .is_some_and(|created| {
+            let age = now.signed_duration_since(created);
+            age >= chrono::Duration::zero() && age.num_seconds() < DUPLICATE_WINDOW_SECS
+        });
+    same && recent
+}
+
+/// Ask whether to save a log that looks like the one just made. Timing out cancels.
+async fn confirm_duplicate_log(ctx: Context<'_>) -> Result<bool, Error> {
+    let buttons = vec![serenity::CreateActionRow::Buttons(vec![
+        serenity::CreateButton::new("immersion_duplicate_log")
+            .label("Log anyway")
+            .style(serenity::ButtonStyle::Primary),
+        serenity::CreateButton::new("immersion_duplicate_cancel")
+            .label("Cancel")
+            .style(serenity::ButtonStyle::Secondary),
+    ])];
+    let reply = ctx
+        .send(
+            poise::CreateReply::default()
+                .content(format!(
+                    "You logged exactly this less than {} seconds ago. Log it again?",
+                    DUPLICATE_WINDOW_SECS
+                ))
+                .components(buttons),
+        )
+        .await?;
+    let msg = reply.message().await?.into_owned();
+
+    let interaction = msg
+        .await_component_interaction(ctx.serenity_context())
+        .author_id(ctx.author().id)
+        .timeout(std::time::Duration::from_secs(
+            DUPLICATE_CONFIRM_TIMEOUT_SECS,
+        ))
+        .await;
+
+    let confirmed = interaction
+        .as_ref()
+        .is_some_and(|i| i.data.custom_id == "immersion_duplicate_log");
+    let outcome = if confirmed {
+        "Logging it again."
+    } else {
+        "Cancelled, nothing was logged."
+    };
+    match interaction {
+        Some(interaction) => {
+            let _ = interaction
+                .create_response(
+                    ctx.http(),
+                    serenity::CreateInteractionResponse::UpdateMessage(
+                        serenity::CreateInteractionResponseMessage::new()
+                            .content(outcome)
+                            .components(vec![]),
+                    ),
+                )
+                .await;
+        }
+        None => {
+            let _ = reply
+                .edit(
+                    ctx,
+                    poise::CreateReply::default()
+                        .content(outcome)
+                        .components(vec![]),
+                )
+                .await;
+        }
+    }
+    Ok(confirmed)
+}
+
+/// A listening log resolved from YouTube
+struct ListeningLog {
+    title: String,
+    minutes: f64,
+    thumbnail: Option<String>,
+    url: String,
+    /// Per-video field for playlists and multiple videos
+    breakdown: Option<String>,
+}
+
+/// Look up the videos of a pasted listening message. Videos that can't be loaded are
+/// skipped; `None` when none could be.
+async fn resolve_listening(
+    client: &reqwest::Client,
+    api_key: &str,
+    input: youtube::ListeningInput,
+) -> anyhow::Result<Option<ListeningLog>> {
+    let (playlist, mut ids) = match input {
+        youtube::ListeningInput::Playlist(id) => {
+            let title = youtube::get_playlist_title(client, api_key, &id).await?;
+            // One extra to tell whether the cap was hit
+            let ids =
+                youtube::get_playlist_items(client, api_key, &id, youtube::MAX_VIDEOS + 1).await?;
+            (Some((id, title)), ids)
+        }
+        youtube::ListeningInput::Videos(ids) => (None, ids),
+    };
+    let capped = ids.len() > youtube::MAX_VIDEOS;
+    ids.truncate(youtube::MAX_VIDEOS);
+
+    let mut infos = youtube::get_videos_info(client, api_key, &ids).await?;
+    let videos: Vec<(String, youtube::VideoInfo)> = ids
+        .iter()
+        .filter_map(|id| infos.remove(id).map(|info| (id.clone(), info)))
+        .collect();
+    let skipped = ids.len() - videos.len();
+    let Some((first_id, first)) = videos.first() else {
+        return Ok(None);
+    };
+
+    let total_seconds: i32 = videos.iter().map(|(_, v)| v.duration_seconds).sum();
+    // Convert to minutes
+    let minutes = (total_seconds as f64 / 60.0).ceil();
+
+    if playlist.is_none() && ids.len() == 1 {
+        return Ok(Some(ListeningLog {
+            title: first.title.clone(),
+            minutes,
+            thumbnail: first.thumbnail.clone(),
+            url: youtube::normalize_url(first_id),
+            breakdown: None,
+        }));
+    }
+
+    let infos: Vec<&youtube::VideoInfo> = videos.iter().map(|(_, v)| v).collect();
+    let (title, url) = match playlist {
+        Some((id, title)) => (
+            title.unwrap_or_else(|| format!("{} videos", videos.len())),
+            youtube::playlist_url(&id),
+        ),
+        None => (
+            format!("{} videos", videos.len()),
+            youtube::normalize_url(first_id),
+        ),
+    };
+    Ok(Some(ListeningLog {
+        title,
+        minutes,
+        thumbnail: first.thumbnail.clone(),
+        url,
+        breakdown: Some(video_breakdown_text(&infos, skipped, capped)),
+    }))
+}
+
+/// One line per video with its minutes, cut to fit a field, plus notes on skipped videos
+fn video_breakdown_text(videos: &[&youtube::VideoInfo], skipped: usize, capped: bool) -> String {
+    let mut notes = Vec::new();
+    if skipped > 0 {
+        notes.push(format!(
+            "⚠️ {} video{} couldn't be loaded and {} skipped",
+            skipped,
+            if skipped == 1 { "" } else { "s" },
+            if skipped == 1 { "was" } else { "were" }
+        ));
+    }
+    if capped {
+        notes.push(format!(
+            "Only the first {} videos are counted",
+            youtube::MAX_VIDEOS
+        ));
+    }
+    let notes = notes.join("\n");
+
+    let lines: Vec<String> = videos
+        .iter()
+        .map(|video| {
+            let title: String = if video.title.chars().count() > 60 {
+                format!("{}…", video.title.chars().take(59).collect::<String>())
+            } else {
+                video.title.clone()
+            };
+            format!(
+                "• {} — {} min",
+                title,
+                (video.duration_seconds as f64 / 60.0).ceil()
+            )
+        })
+        .collect();
+    if notes.is_empty() {
+        return fit_description(lines, EMBED_FIELD_LIMIT).0;
+    }
+    if lines.is_empty() {
+        return notes;
+    }
+    // The notes always show, the videos get the room left after them
+    let budget = EMBED_FIELD_LIMIT - notes.chars().count() - 1;
+    format!("{}\n{}", fit_description(lines, budget).0, notes)
+}
+
+/// Whether the public embed shows the cover art. Covers of airing shows can spoil
+/// later episodes, so they are hidden for users who opted in, unless the log says otherwise.
+/// The cover is stored in the log metadata either way.
+fn should_show_cover(hide_airing_covers: bool, airing: bool, show_cover: Option<bool>) -> bool {
+    show_cover.unwrap_or(!(hide_airing_covers && airing))
+}
+
+/// Attempts for the log commit before giving up
+const SAVE_MAX_ATTEMPTS: u32 = 3;
+
+/// Summary fields a new log writes, `totalSessions` is usually incremented instead.
+/// Unset ones (no streak freezes left) are removed.
+const SUMMARY_FIELDS: [&str; 5] = [
+    "lastActivity",
+    "joinDate",
+    "activeTypes",
+    "streakFreezes",
+    "frozenDates",
+];
+
+/// Stats change applied to the user doc together with a new log
+struct StatsIncrement<'a> {
+    media_type: &'a str,
+    amount: f64,
+    unit: &'a str,
+    label: &'a str,
+    profile: UserProfile,
+    now: String,
+    /// Streak for this media type including the new log, if it could be computed
+    streak: Option<streak::StreakResult>,
+    /// Activity dates of the earlier logs, for the global streak and its freezes
+    global_dates: Option<Vec<String>>,
+    /// Activity date of the new log
+    date: String,
+    /// Effective date the streak is counted up to
+    today: NaiveDate,
+}
+
+/// Global streak after a log, with what it did to the user's streak freezes
+#[derive(Debug, Clone, PartialEq)]
+struct FreezeUpdate {
+    current: i32,
+    freezes_used: usize,
+    freezes_earned: u32,
+    freezes_left: u32,
+}
+
+/// Streak field of the log embed
+fn streak_text(current: i32, update: Option<&FreezeUpdate>) -> String {
+    let mut text = format!("{} day{}", current, if current == 1 { "" } else { "s" });
+    if let Some(update) = update {
+        if update.freezes_used > 0 {
+            text.push_str(&format!(
+                "\nStreak freeze used! 🔥 {} days preserved",
+                current
+            ));
+        }
+        if update.freezes_earned > 0 {
+            text.push_str(&format!(
+                "\n❄️ Streak freeze earned ({}/{})",
+                update.freezes_left,
+                streak::MAX_STREAK_FREEZES
+            ));
+        }
+    }
+    text
+}
+
+/// Save a log and its stats increment in one atomic commit, returning the new total,
+/// the user document as it was read and the streak freeze update.
+/// Totals and session counts are added by Firestore (`increment` transforms), so two
+/// logs saved at the same time both count even though both read the same user doc.
+/// Replays are safe: the log is created with an exists=false precondition, which fails
+/// the whole commit when an earlier attempt already went through.
+async fn save_log(
+    firebase: &dyn Storage,
+    user_id: &str,
+    log_id: &str,
+    log_data: &Value,
+    increment: &StatsIncrement<'_>,
+) -> Result<(f64, Option<UserDoc>, Option<FreezeUpdate>), anyhow::Error> {
+    let user_doc = firebase
+        .get_document("users", user_id)
+        .await?
+        .map(firebase::parse_user_doc)
+        .transpose()?;
+    let (writes, total, freezes) =
+        build_log_writes(user_id, log_id, log_data, user_doc.as_ref(), increment);
+
+    let mut attempt = 1;
+    loop {
+        match firebase.commit_writes(writes.clone()).await {
+            Ok(()) => return Ok((total, user_doc, freezes)),
+            // An earlier attempt already committed
+            Err(e) if firebase::is_already_exists(&e) => return Ok((total, user_doc, freezes)),
+            Err(e) if firebase::is_timeout(&e) && attempt < SAVE_MAX_ATTEMPTS => {
+                let backoff = std::time::Duration::from_millis(200 * 2u64.pow(attempt - 1));
+                debug!(
+                    "Retrying immersion log {} after {:?} (attempt {}): {:?}",
+                    log_id, backoff, attempt, e
+                );
+                tokio::time::sleep(backoff).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Writes for a new log: create the log document and, unless this log was already
+/// applied, the incremented user stats. Also returns the media type total after the write
+/// and, when the earlier logs are known, the global streak with its freezes.
+fn build_log_writes(
+    user_id: &str,
+    log_id: &str,
+    log_data: &Value,
+    user_doc: Option<&UserDoc>,
+    increment: &StatsIncrement<'_>,
+) -> (Vec<TransactionWrite>, f64, Option<FreezeUpdate>) {
+    let media_type = increment.media_type;
+
+    let mut writes = vec![TransactionWrite::Create {
+        document_path: format!("users/{}/immersion_logs/{}", user_id, log_id),
+        fields: log_data.clone(),
+    }];
+
+    let mut user = user_doc.cloned().unwrap_or_default();
+    let current_total = user.stats.total(media_type);
+    if user.last_applied_log.as_deref() == Some(log_id) {
+        return (writes, current_total, None);
+    }
+
+    // Documents from before totalSessions existed get it rewritten from the stats
+    let sessions_in_step = user.summary.total_sessions == user.stats.total_sessions();
+
+    // Update stats for this media type (fields from other clients are kept)
+    let stats = user.stats.entry(media_type);
+    stats.total += increment.amount;
+    stats.sessions += 1;
+    stats.last_activity = Some(increment.now.clone());
+    if let Some(s) = &increment.streak {
+        stats.current_streak = s.current as i64;
+        stats.best_streak = stats.best_streak.max(s.longest as i64);
+    }
+    stats.unit = Some(increment.unit.to_string());
+    stats.label = Some(increment.label.to_string());
+    let new_total = stats.total;
+
+    // Privacy settings belong to /privacy, the timezone to /timezone
+    user.profile = UserProfile {
+        leaderboard_opt_out: user.profile.leaderboard_opt_out,
+        leaderboard_opt_out_mode: user.profile.leaderboard_opt_out_mode.take(),
+        timezone: user.profile.timezone.take(),
+        extra: std::mem::take(&mut user.profile.extra),
+        ..increment.profile.clone()
+    };
+    user.summary.total_sessions = user.stats.total_sessions();
+    user.summary.last_activity = Some(increment.now.clone());
+    user.summary.join_date = user
+        .summary
+        .join_date
+        .take()
+        .or_else(|| Some(increment.now.clone()));
+    user.summary.active_types = user.stats.media_types();
+    let freezes = increment
+        .global_dates
+        .as_ref()
+        .map(|dates| apply_streak_freezes(&mut user, dates, increment));
+    user.timestamps.updated = Some(increment.now.clone());
+    user.timestamps.last_log = Some(increment.now.clone());
+
+    // Only the fields this log changes; goals and preferences are left to their commands.
+    // Stats are written leaf by leaf so other media types and fields written since the
+    // read (e.g. another client's bestStreak) survive. The counts are incremented on the
+    // server instead, the rest is last write wins.
+    let mut stat_fields = vec!["lastActivity", "unit", "label"];
+    if increment.streak.is_some() {
+        stat_fields.extend(["currentStreak", "bestStreak"]);
+    }
+    let mut field_paths: Vec<String> = stat_fields
+        .iter()
+        .map(|field| firebase::field_path(&["stats", media_type, field]))
+        .collect();
+    field_paths.extend(
+        SUMMARY_FIELDS
+            .iter()
+            .map(|field| firebase::field_path(&["summary", field])),
+    );
+    field_paths.extend(["profile", "timestamps", "lastAppliedLog"].map(String::from));
+    let mut increments = vec![
+        (
+            firebase::field_path(&["stats", media_type, "total"]),
+            json!(increment.amount),
+        ),
+        (
+            firebase::field_path(&["stats", media_type, "sessions"]),
+            json!(1),
+        ),
+    ];
+    let total_sessions = firebase::field_path(&["summary", "totalSessions"]);
+    if sessions_in_step {
+        increments.push((total_sessions, json!(1)));
+    } else {
+        field_paths.push(total_sessions);
+    }
+    let type_stats = user.stats.get(media_type).cloned().unwrap_or_default();
+    writes.push(TransactionWrite::UpdateWithTransforms {
+        document_path: format!("users/{}", user_id),
+        fields: json!({
+            "profile": user.profile,
+            "stats": { media_type: type_stats },
+            "summary": user.summary,
+            "timestamps": user.timestamps,
+            "lastAppliedLog": log_id
+        }),
+        field_paths,
+        increments,
+    });
+
+    (writes, new_total, freezes)
+}
+
+/// Spend and earn streak freezes for the global streak including the new log
+fn apply_streak_freezes(
+    user: &mut UserDoc,
+    prior_dates: &[String],
+    increment: &StatsIncrement<'_>,
+) -> FreezeUpdate {
+    let summary = &mut user.summary;
+    let held = summary.streak_freezes.min(streak::MAX_STREAK_FREEZES);
+    let before = streak::calculate_streak_with_freezes(
+        prior_dates,
+        &summary.frozen_dates,
+        held,
+        increment.today,
+    );
+    let mut dates = prior_dates.to_vec();
+    dates.push(increment.date.clone());
+    let after =
+        streak::calculate_streak_with_freezes(&dates, &summary.frozen_dates, held, increment.today);
+
+    let earned = streak::freezes_earned(before.current, after.current);
+    let left = (after.freezes_left + earned).min(streak::MAX_STREAK_FREEZES);
+    summary
+        .frozen_dates
+        .extend(after.newly_frozen.iter().cloned());
+    summary.streak_freezes = left;
+
+    FreezeUpdate {
+        current: after.current,
+        freezes_used: after.newly_frozen.len(),
+        freezes_earned: earned,
+        freezes_left: left,
+    }
+}
+
+/// Format amount for display (remove unnecessary decimal places)
+fn format_amount(n: f64) -> String {
+    if n == n.trunc() {
+        (n as i64).to_string()
+    } else {
+        format!("{:.1}", n)
+    }
+}
+
+// Local calculate_user_streak removed in favor of utils::streak::calculate_streak
+
+/// VNDB and AniList title search for the media type picked, also behind `/now set`
+pub(crate) async fn autocomplete_title(
+    ctx: Context<'_>,
+    partial: &str,
+) -> impl Iterator<Item = serenity::AutocompleteChoice> {
+    let mut results = Vec::new();
+
+    // Autocomplete only runs for slash commands, other options arrive as raw values
+    let media_type = match ctx {
+        poise::Context::Application(app_ctx) => {
+            find_option(&app_ctx.interaction.data.options, "media_type")
+                .and_then(media_type_from_option)
+        }
+        poise::Context::Prefix(_) => None,
+    };
+
+    let http = &ctx.data().http_client;
+    let mut candidates = Vec::new();
+
+    // Only search if length >= 2
+    match media_type {
+        Some(MediaType::VisualNovel) if partial.len() >= 2 => {
+            match vndb::search_vns(http, partial, 10).await {
+                Ok(vns) => {
+                    for vn in vns {
+                        let released = vn.released.unwrap_or_default();
+                        // Shown as "Title (Year)", the value is the id
+                        candidates.push(title_popularity::Candidate {
+                            display: format!("{} ({})", vn.title, released),
+                            title: vn.title,
+                            id: vn.id,
+                        });
+                    }
+                }
+                Err(e) => warn!("VNDB autocomplete search failed: {:?}", e),
+            }
+        }
+        Some(mt @ (MediaType::Anime | MediaType::Manga | MediaType::Book | MediaType::Reading))
+            if partial.len() >= 2 =>
+        {
+            // Same routing as the main command: only anime searches AniList anime
+            let al_type = if matches!(mt, MediaType::Anime) {
+                anilist::MediaType::Anime
+            } else {
+                anilist::MediaType::Manga
+            };
+            match anilist::search_media(http, partial, al_type, 10).await {
+                Ok(medias) => {
+                    for media in medias {
+                        candidates.push(title_popularity::Candidate {
+                            display: media.title.clone(),
+                            title: media.title,
+                            id: media.id.to_string(),
+                        });
+                    }
+                }
+                Err(e) => warn!("AniList autocomplete search failed: {:?}", e),
+            }
+        }
+        Some(_) => {}
+        None => results.push(serenity::AutocompleteChoice::new(
+            "⚠️ Select Media Type First",
+            "⚠️ Select Media Type First",
+        )),
+    }
+
+    // Titles the guild has been logging lately go first
+    if let (Some(mt), Some(guild_id)) = (media_type, ctx.guild_id()) {
+        let counts = ctx.data().title_popularity.get(&guild_id.to_string());
+        candidates = title_popularity::rank(candidates, mt.as_str(), &counts)
+            .into_iter()
+            .map(|(c, boosted)| title_popularity::Candidate {
+                display: title_popularity::label(&c.display, boosted),
+                ..c
+            })
+            .collect();
+    }
+    results.extend(
+        candidates
+            .iter()
+            .map(|c| autocomplete_choice(&c.display, &c.id)),
+    );
+
+    // If no results, suggest the partial input itself
+    if results.is_empty() && !partial.is_empty() {
+        results.push(serenity::AutocompleteChoice::new(
+            choice_name(partial),
+            partial.to_string(),
+        ));
+    }
+
+    results.into_iter()
+}
+
+/// Value of the option `name`, also inside the subcommand being run
+fn find_option<'a>(
+    options: &'a [serenity::CommandDataOption],
+    name: &str,
+) -> Option<&'a serenity::model::application::CommandDataOptionValue> {
+    use serenity::model::application::CommandDataOptionValue;
+
+    options.iter().find_map(|o| match &o.value {
+        CommandDataOptionValue::SubCommand(inner)
+        | CommandDataOptionValue::SubCommandGroup(inner) => find_option(inner, name),
+        value if o.name == name => Some(value),
+        _ => None,
+    })
+}
+
+/// Read the media_type option of an autocomplete interaction.
+/// Choice parameters are sent as their variant index; plain strings are accepted too.
+fn media_type_from_option(
+    value: &serenity::model::application::CommandDataOptionValue,
+) -> Option<MediaType> {
+    use poise::ChoiceParameter;
+    use serenity::model::application::CommandDataOptionValue;
+
+    match value {
+        CommandDataOptionValue::Integer(i) => {
+            usize::try_from(*i).ok().and_then(MediaType::from_index)
+        }
+        CommandDataOptionValue::String(s) => MediaType::from_name(s).or_else(|| {
+            (0..)
+                .map_while(MediaType::from_index)
+                .find(|mt| mt.as_str() == s || format!("{:?}", mt) == *s)
+        }),
+        _ => None,
+    }
+}
+
+/// Reply for a custom date that doesn't parse
+pub const INVALID_DATE_MESSAGE: &str =
+    "Invalid date format. Please use YYYY-MM-DD (e.g. 2026-01-21)";
+
+/// Custom date option, strictly YYYY-MM-DD
+pub fn parse_custom_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+/// Autocomplete choice showing the title, with only the id as its value
+fn autocomplete_choice(title: &str, id: &str) -> serenity::AutocompleteChoice {
+    serenity::AutocompleteChoice::new(choice_name(title), id.to_string())
+}
+
+/// Shorten a choice name to Discord's 100 character limit
+fn choice_name(title: &str) -> String {
+    const MAX_CHARS: usize = 100;
+
+    if title.chars().count() <= MAX_CHARS {
+        return title.to_string();
+    }
+    let mut name: String = title.chars().take(MAX_CHARS - 1).collect();
+    name.push('…');
+    name
+}
+
+/// What the title option holds: an id picked from autocomplete, or a typed title
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TitleInput {
+    VndbId(String),
+    AniListId(i32),
+    Search(String),
+}
+
+/// Bare `v12345` is a VNDB id and a bare number an AniList id, anything else is searched
+fn parse_title_input(media_type: MediaType, input: &str) -> TitleInput {
+    let input = input.trim();
+    let is_number = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+
+    match media_type {
+        MediaType::VisualNovel => {
+            if let Some(digits) = input
+                .strip_prefix(['v', 'V'])
+                .filter(|digits| is_number(digits))
+            {
+                return TitleInput::VndbId(format!("v{}", digits));
+            }
+        }
+        MediaType::Anime | MediaType::Manga | MediaType::Book | MediaType::Reading => {
+            if is_number(input) {
+                if let Ok(id) = input.parse() {
+                    return TitleInput::AniListId(id);
+                }
+            }
+        }
+        MediaType::ReadingTime | MediaType::Listening => {}
+    }
+    TitleInput::Search(input.to_string())
+}
+
+/// What VNDB or AniList know about a title being logged
+struct TitleMatch {
+    title: String,
+    thumbnail: Option<String>,
+    vndb_url: Option<String>,
+    anilist_url: Option<String>,
+    source: &'static str,
+    vndb_metadata: Option<Value>,
+    /// Whether the anime or manga is still releasing
+    airing: bool,
+}
+
+/// Look `input` up for a log of `media_type`: an autocomplete pick arrives as the bare VNDB
+/// or AniList id, anything else, or an id that isn't found, is searched as a title. `None`
+/// for media types without a database and when nothing matches.
+async fn lookup_title(
+    http: &reqwest::Client,
+    media_type: MediaType,
+    input: &str,
+) -> Option<TitleMatch> {
+    match media_type {
+        MediaType::VisualNovel => {
+            let by_id = match parse_title_input(media_type, input) {
+                TitleInput::VndbId(id) => vndb::get_vn_by_id(http, &id).await.ok().flatten(),
+                _ => None,
+            };
+            if let Some(vn) = by_id {
+                return Some(TitleMatch {
+                    vndb_metadata: Some(json!({
+                        "id": vn.id,
+                        "developer": vn.developer,
+                        "released": vn.released,
+                        "length": vn.length,
+                        "description": vn.description
+                    })),
+                    title: vn.title,
+                    thumbnail: vn.image,
+                    vndb_url: Some(vn.url),
+                    anilist_url: None,
+                    source: "vndb",
+                    airing: false,
+                });
+            }
+            // Fallback search by title
+            let vn = vndb::search_vns(http, input, 1)
+                .await
+                .ok()?
+                .into_iter()
+                .next()?;
+            Some(TitleMatch {
+                vndb_metadata: Some(json!({
+                    "id": vn.id,
+                    "developer": vn.developer,
+                    "released": vn.released,
+                    "length": vn.length,
+                    "description": None::<String>
+                })),
+                title: vn.title,
+                thumbnail: vn.image,
+                vndb_url: Some(vn.url),
+                anilist_url: None,
+                source: "vndb",
+                airing: false,
+            })
+        }
+        MediaType::Anime | MediaType::Manga | MediaType::Book | MediaType::Reading => {
+            let al_type = if matches!(media_type, MediaType::Anime) {
+                anilist::MediaType::Anime
+            } else {
+                anilist::MediaType::Manga
+            };
+            let by_id = match parse_title_input(media_type, input) {
+                TitleInput::AniListId(id) => anilist::get_media_by_id(http, id, al_type)
+                    .await
+                    .ok()
+                    .flatten(),
+                _ => None,
+            };
+            let media = match by_id {
+                Some(media) => media,
+                // Fallback search
+                None => anilist::search_media(http, input, al_type, 1)
+                    .await
+                    .ok()?
+                    .into_iter()
+                    .next()?,
+            };
+            Some(TitleMatch {
+                airing: media.is_releasing(),
+                title: media.title,
+                thumbnail: media.image,
+                vndb_url: None,
+                anilist_url: Some(media.url),
+                source: "anilist",
+                vndb_metadata: None,
+            })
+        }
+        MediaType::ReadingTime | MediaType::Listening => None,
+    }
+}
+
+/// The title /immersion would log for `input`, the input as typed when nothing is found
+pub(crate) async fn resolve_title_name(
+    http: &reqwest::Client,
+    media_type: MediaType,
+    input: &str,
+) -> String {
+    match lookup_title(http, media_type, input).await {
+        Some(found) => found.title,
+        None => input.trim().to_string(),
+    }
 }
 
 /// Helper function to fetch page title from URL
@@ -753,3 +2466,905 @@ fn extract_meta_property(html: &str, property: &str) -> Option<String> {
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::firebase::mock::MockFirestore;
+    use crate::api::firebase::QueryFilter;
+    use crate::api::memory_store::MemoryStore;
+    use futures::future::BoxFuture;
+
+    /// Memory store that yields after each read, so logs saved together all read the user doc
+    /// before any of them commits
+    #[derive(Default)]
+    struct YieldingReads(MemoryStore);
+
+    impl Storage for YieldingReads {
+        fn get_document<'a>(
+            &'a self,
+            collection: &'a str,
+            doc_id: &'a str,
+        ) -> BoxFuture<'a, anyhow::Result<Option<Value>>> {
+            Box::pin(async move {
+                let doc = self.0.get_document(collection, doc_id).await;
+                tokio::task::yield_now().await;
+                doc
+            })
+        }
+
+        fn set_document<'a>(
+            &'a self,
+            collection: &'a str,
+            doc_id: &'a str,
+            data: &'a Value,
+        ) -> BoxFuture<'a, anyhow::Result<()>> {
+            self.0.set_document(collection, doc_id, data)
+        }
+
+        fn set_document_merge_paths<'a>(
+            &'a self,
+            collection: &'a str,
+            doc_id: &'a str,
+            data: &'a Value,
+            field_paths: &'a [&'a str],
+        ) -> BoxFuture<'a, anyhow::Result<()>> {
+            self.0
+                .set_document_merge_paths(collection, doc_id, data, field_paths)
+        }
+
+        fn add_to_subcollection<'a>(
+            &'a self,
+            collection: &'a str,
+            doc_id: &'a str,
+            subcollection: &'a str,
+            data: &'a Value,
+        ) -> BoxFuture<'a, anyhow::Result<String>> {
+            self.0
+                .add_to_subcollection(collection, doc_id, subcollection, data)
+        }
+
+        fn query_subcollection_with_ids<'a>(
+            &'a self,
+            collection: &'a str,
+            doc_id: &'a str,
+            subcollection: &'a str,
+        ) -> BoxFuture<'a, anyhow::Result<Vec<(String, Value)>>> {
+            self.0
+                .query_subcollection_with_ids(collection, doc_id, subcollection)
+        }
+
+        fn delete_document<'a>(
+            &'a self,
+            collection: &'a str,
+            doc_id: &'a str,
+        ) -> BoxFuture<'a, anyhow::Result<()>> {
+            self.0.delete_document(collection, doc_id)
+        }
+
+        fn list_collection<'a>(
+            &'a self,
+            collection: &'a str,
+            fields: Option<&'a [&'a str]>,
+        ) -> BoxFuture<'a, anyhow::Result<Vec<(String, Value)>>> {
+            self.0.list_collection(collection, fields)
+        }
+
+        fn run_query_ordered<'a>(
+            &'a self,
+            parent_collection: &'a str,
+            parent_doc_id: &'a str,
+            subcollection: &'a str,
+            filters: Vec<QueryFilter>,
+            order_by: &'a [(&'a str, &'a str)],
+            limit: usize,
+            start_after: &'a [Value],
+        ) -> BoxFuture<'a, anyhow::Result<Vec<(String, Value)>>> {
+            self.0.run_query_ordered(
+                parent_collection,
+                parent_doc_id,
+                subcollection,
+                filters,
+                order_by,
+                limit,
+                start_after,
+            )
+        }
+
+        fn begin_transaction(&self) -> BoxFuture<'_, anyhow::Result<String>> {
+            self.0.begin_transaction()
+        }
+
+        fn commit_transaction<'a>(
+            &'a self,
+            transaction_id: &'a str,
+            writes: Vec<TransactionWrite>,
+        ) -> BoxFuture<'a, anyhow::Result<()>> {
+            self.0.commit_transaction(transaction_id, writes)
+        }
+
+        fn commit_writes(
+            &self,
+            writes: Vec<TransactionWrite>,
+        ) -> BoxFuture<'_, anyhow::Result<()>> {
+            self.0.commit_writes(writes)
+        }
+
+        fn rollback_transaction<'a>(
+            &'a self,
+            transaction_id: &'a str,
+        ) -> BoxFuture<'a, anyhow::Result<()>> {
+            self.0.rollback_transaction(transaction_id)
+        }
+
+        fn get_document_in_transaction<'a>(
+            &'a self,
+            transaction_id: &'a str,
+            collection: &'a str,
+            doc_id: &'a str,
+        ) -> BoxFuture<'a, anyhow::Result<Option<Value>>> {
+            Box::pin(async move {
+                tokio::task::yield_now().await;
+                self.0
+                    .get_document_in_transaction(transaction_id, collection, doc_id)
+                    .await
+            })
+        }
+    }
+
+    fn increment(amount: f64) -> StatsIncrement<'static> {
+        StatsIncrement {
+            media_type: "anime",
+            amount,
+            unit: "episodes",
+            label: "Anime",
+            profile: UserProfile {
+                id: "123".to_string(),
+                username: "yuki".to_string(),
+                ..Default::default()
+            },
+            now: "2025-01-15T10:00:00+00:00".to_string(),
+            streak: None,
+            global_dates: None,
+            date: "2025-01-15".to_string(),
+            today: NaiveDate::from_ymd_opt(2025, 1, 15).unwrap(),
+        }
+    }
+
+    fn stored_user(store: &MockFirestore) -> Option<UserDoc> {
+        store
+            .get("users/123")
+            .map(|doc| firebase::parse_user_doc(doc).unwrap())
+    }
+
+    #[test]
+    fn test_replayed_log_commit_is_noop() {
+        let mut store = MockFirestore::default();
+        let log = json!({ "activity": { "type": "anime", "amount": 3.0 } });
+        let inc = increment(3.0);
+
+        let (writes, total, _) = build_log_writes("123", "log1", &log, None, &inc);
+        store.commit(&writes).unwrap();
+        assert_eq!(total, 3.0);
+
+        // Same commit replayed after a client-side timeout
+        let err = store.commit(&writes).unwrap_err();
+        assert!(firebase::is_already_exists(&err));
+
+        // Fresh attempt that re-reads the user doc inside the transaction
+        let user_doc = stored_user(&store);
+        let (retry_writes, retry_total, _) =
+            build_log_writes("123", "log1", &log, user_doc.as_ref(), &inc);
+        assert_eq!(retry_writes.len(), 1);
+        assert_eq!(retry_total, 3.0);
+        assert!(firebase::is_already_exists(
+            &store.commit(&retry_writes).unwrap_err()
+        ));
+
+        let logs = store
+            .docs
+            .keys()
+            .filter(|k| k.starts_with("users/123/immersion_logs/"))
+            .count();
+        assert_eq!(logs, 1);
+
+        let user = store.get("users/123").unwrap();
+        assert_eq!(user["stats"]["anime"]["total"], 3.0);
+        assert_eq!(user["stats"]["anime"]["sessions"], 1);
+        assert_eq!(user["lastAppliedLog"], "log1");
+    }
+
+    #[tokio::test]
+    async fn test_parallel_logs_both_count() {
+        let store = YieldingReads::default();
+        let log = json!({});
+        let (three, two) = (increment(3.0), increment(2.0));
+        let (first, second) = tokio::join!(
+            save_log(&store, "123", "log1", &log, &three),
+            save_log(&store, "123", "log2", &log, &two),
+        );
+        // Both read the user doc before either wrote
+        assert!(first.unwrap().1.is_none());
+        assert!(second.unwrap().1.is_none());
+
+        let user = store.0.get_document("users", "123").await.unwrap().unwrap();
+        assert_eq!(user["stats"]["anime"]["total"], 5.0);
+        assert_eq!(user["stats"]["anime"]["sessions"], 2);
+        assert_eq!(user["summary"]["totalSessions"], 2);
+
+        // Retrying a log that went through counts nothing
+        save_log(&store, "123", "log1", &log, &three).await.unwrap();
+        let user = store.0.get_document("users", "123").await.unwrap().unwrap();
+        assert_eq!(user["stats"]["anime"]["sessions"], 2);
+    }
+
+    #[test]
+    fn test_new_log_increments_existing_stats() {
+        let mut store = MockFirestore::default();
+        let log = json!({});
+
+        let (writes, _, _) = build_log_writes("123", "log1", &log, None, &increment(3.0));
+        store.commit(&writes).unwrap();
+
+        let user_doc = stored_user(&store);
+        let (writes, total, _) =
+            build_log_writes("123", "log2", &log, user_doc.as_ref(), &increment(2.0));
+        store.commit(&writes).unwrap();
+
+        assert_eq!(total, 5.0);
+        let user = store.get("users/123").unwrap();
+        assert_eq!(user["stats"]["anime"]["sessions"], 2);
+        assert_eq!(user["summary"]["totalSessions"], 2);
+    }
+
+    #[test]
+    fn test_private_log_counts_only_for_its_owner() {
+        let mut store = MockFirestore::default();
+        let public = json!({ "activity": { "type": "anime", "amount": 2.0 } });
+        let private = json!({
+            "activity": { "type": "anime", "amount": 3.0 },
+            "metadata": { "private": true }
+        });
+
+        let (writes, _, _) = build_log_writes("123", "log1", &public, None, &increment(2.0));
+        store.commit(&writes).unwrap();
+        let user_doc = stored_user(&store);
+        let (writes, total, _) =
+            build_log_writes("123", "log2", &private, user_doc.as_ref(), &increment(3.0));
+        store.commit(&writes).unwrap();
+
+        // The member's own totals move
+        assert_eq!(total, 5.0);
+        assert_eq!(
+            store.get("users/123").unwrap()["stats"]["anime"]["sessions"],
+            2
+        );
+
+        // The leaderboard and recap only see the public log
+        let logs: Vec<&Value> = store
+            .docs
+            .iter()
+            .filter(|(k, _)| k.starts_with("users/123/immersion_logs/"))
+            .map(|(_, log)| log)
+            .collect();
+        assert_eq!(crate::utils::points::sum_log_points(logs, None, None), 26.0);
+    }
+
+    #[test]
+    fn test_type_streak_is_persisted() {
+        let user_doc: UserDoc = serde_json::from_value(json!({
+            "stats": { "anime": { "total": 1.0, "sessions": 1, "bestStreak": 9, "currentStreak": 0 } }
+        }))
+        .unwrap();
+        let mut inc = increment(1.0);
+        inc.streak = Some(streak::StreakResult {
+            current: 3,
+            longest: 4,
+        });
+
+        let (writes, _, _) = build_log_writes("123", "log1", &json!({}), Some(&user_doc), &inc);
+        let fields = match &writes[1] {
+            TransactionWrite::UpdateWithTransforms { fields, .. } => fields,
+            other => panic!("expected update, got {:?}", other),
+        };
+
+        assert_eq!(fields["stats"]["anime"]["currentStreak"], 3);
+        // Best never goes down
+        assert_eq!(fields["stats"]["anime"]["bestStreak"], 9);
+    }
+
+    #[test]
+    fn test_log_after_missed_day_spends_a_freeze() {
+        let user_doc: UserDoc =
+            serde_json::from_value(json!({ "summary": { "streakFreezes": 1 } })).unwrap();
+        let mut inc = increment(1.0);
+        // Logged the 12th and 13th, missed the 14th
+        inc.global_dates = Some(vec!["2025-01-12".to_string(), "2025-01-13".to_string()]);
+
+        let (writes, _, freezes) =
+            build_log_writes("123", "log1", &json!({}), Some(&user_doc), &inc);
+        assert_eq!(
+            freezes,
+            Some(FreezeUpdate {
+                current: 3,
+                freezes_used: 1,
+                freezes_earned: 0,
+                freezes_left: 0,
+            })
+        );
+        let fields = match &writes[1] {
+            TransactionWrite::UpdateWithTransforms { fields, .. } => fields,
+            other => panic!("expected update, got {:?}", other),
+        };
+        assert_eq!(fields["summary"]["frozenDates"], json!(["2025-01-14"]));
+        assert!(fields["summary"].get("streakFreezes").is_none());
+        assert!(streak_text(3, freezes.as_ref()).contains("3 days preserved"));
+    }
+
+    #[test]
+    fn test_thirtieth_day_earns_a_freeze() {
+        let mut inc = increment(1.0);
+        let today = inc.today;
+        inc.global_dates = Some(
+            (1..30)
+                .map(|i| {
+                    (today - chrono::Duration::days(i))
+                        .format("%Y-%m-%d")
+                        .to_string()
+                })
+                .collect(),
+        );
+        let user_doc: UserDoc =
+            serde_json::from_value(json!({ "summary": { "streakFreezes": 2 } })).unwrap();
+
+        let (_, _, freezes) = build_log_writes("123", "log1", &json!({}), None, &inc);
+        let freezes = freezes.unwrap();
+        assert_eq!((freezes.current, freezes.freezes_left), (30, 1));
+
+        // Never more than the cap
+        let (_, _, freezes) = build_log_writes("123", "log1", &json!({}), Some(&user_doc), &inc);
+        assert_eq!(freezes.unwrap().freezes_left, streak::MAX_STREAK_FREEZES);
+    }
+
+    #[test]
+    fn test_duplicate_log_detection() {
+        let now = chrono::DateTime::parse_from_rfc3339("2025-01-15T10:02:00+00:00")
+            .unwrap()
+            .to_utc();
+        let last = |created: &str, amount: f64, title: &str| {
+            json!({
+                "activity": { "type": "anime", "amount": amount, "title": title },
+                "timestamps": { "created": created }
+            })
+        };
+
+        let recent = last("2025-01-15T10:01:00+00:00", 1.0, "Frieren");
+        assert!(is_probable_duplicate(&recent, "anime", 1.0, "Frieren", now));
+        // Differs in type, amount or title
+        assert!(!is_probable_duplicate(
+            &recent, "manga", 1.0, "Frieren", now
+        ));
+        assert!(!is_probable_duplicate(
+            &recent, "anime", 2.0, "Frieren", now
+        ));
+        assert!(!is_probable_duplicate(
+            &recent, "anime", 1.0, "Mushishi", now
+        ));
+
+        // Window boundary
+        let old = last("2025-01-15T10:00:00+00:00", 1.0, "Frieren");
+        assert!(!is_probable_duplicate(&old, "anime", 1.0, "Frieren", now));
+        let edge = last("2025-01-15T10:00:01+00:00", 1.0, "Frieren");
+        assert!(is_probable_duplicate(&edge, "anime", 1.0, "Frieren", now));
+
+        // Untitled logs compare as "-", broken timestamps never match
+        let untitled = json!({
+            "activity": { "type": "listening", "amount": 30.0 },
+            "timestamps": { "created": "2025-01-15T10:01:30+00:00" }
+        });
+        assert!(is_probable_duplicate(
+            &untitled,
+            "listening",
+            30.0,
+            "-",
+            now
+        ));
+        let broken = last("yesterday", 1.0, "Frieren");
+        assert!(!is_probable_duplicate(
+            &broken, "anime", 1.0, "Frieren", now
+        ));
+    }
+
+    fn video(title: &str, seconds: i32) -> youtube::VideoInfo {
+        youtube::VideoInfo {
+            title: title.to_string(),
+            duration_seconds: seconds,
+            thumbnail: None,
+            channel: "ch".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_video_breakdown_lists_minutes_and_skips() {
+        let (a, b) = (video("ポッドキャスト #1", 610), video("Vlog", 60));
+        let text = video_breakdown_text(&[&a, &b], 1, false);
+        assert_eq!(
+            text,
+            "• ポッドキャスト #1 — 11 min\n• Vlog — 1 min\n⚠️ 1 video couldn't be loaded and was skipped"
+        );
+    }
+
+    #[test]
+    fn test_video_breakdown_fits_field_limit() {
+        let long = video(&"長".repeat(100), 900);
+        let videos: Vec<&youtube::VideoInfo> = std::iter::repeat_n(&long, 50).collect();
+        let text = video_breakdown_text(&videos, 3, true);
+        assert!(text.chars().count() <= EMBED_FIELD_LIMIT);
+        assert!(text.contains("more"));
+        assert!(text.ends_with("Only the first 50 videos are counted"));
+    }
+
+    #[test]
+    fn test_log_keeps_fields_it_does_not_manage() {
+        let mut store = MockFirestore::default();
+        store.docs.insert(
+            "users/123".to_string(),
+            json!({
+                "profile": {
+                    "id": "123",
+                    "username": "old",
+                    "pronouns": "they/them",
+                    "leaderboardOptOut": true,
+                    "timezone": "Europe/Berlin"
+                },
+                "stats": {
+                    "anime": { "total": 2, "sessions": 1, "favourite": true },
+                    "manga": { "total": 40, "sessions": 4 }
+                },
+                "summary": { "joinDate": "2024-06-01T00:00:00+00:00" },
+                "preferences": { "hideAiringCovers": true },
+                "goals": { "anime": { "amount": 20.0, "month": "2025-01" } }
+            }),
+        );
+
+        let user_doc = stored_user(&store);
+        let (writes, total, _) = build_log_writes(
+            "123",
+            "log1",
+            &json!({}),
+            user_doc.as_ref(),
+            &increment(3.0),
+        );
+        store.commit(&writes).unwrap();
+        assert_eq!(total, 5.0);
+
+        let user = stored_user(&store).unwrap();
+        assert_eq!(user.profile.username, "yuki");
+        assert_eq!(user.profile.extra["pronouns"], "they/them");
+        assert!(user.profile.leaderboard_opt_out);
+        assert_eq!(user.profile.timezone.as_deref(), Some("Europe/Berlin"));
+        assert_eq!(user.stats.get("anime").unwrap().extra["favourite"], true);
+        assert_eq!(user.stats.total("manga"), 40.0);
+        assert_eq!(user.summary.total_sessions, 6);
+        assert_eq!(
+            user.summary.join_date.as_deref(),
+            Some("2024-06-01T00:00:00+00:00")
+        );
+        assert_eq!(user.summary.active_types, vec!["anime", "manga"]);
+        assert!(user.preference("hideAiringCovers"));
+        assert_eq!(user.goals()["anime"].amount, 20.0);
+    }
+
+    #[test]
+    fn test_log_only_writes_its_stats_leaves() {
+        let mut store = MockFirestore::default();
+        store.docs.insert(
+            "users/123".to_string(),
+            json!({ "stats": { "anime": { "total": 2, "sessions": 1, "bestStreak": 3 } } }),
+        );
+        let user_doc = stored_user(&store);
+        let (writes, _, _) = build_log_writes(
+            "123",
+            "log1",
+            &json!({}),
+            user_doc.as_ref(),
+            &increment(1.0),
+        );
+
+        // Another write lands between the read and the commit
+        let doc = store.docs.get_mut("users/123").unwrap();
+        doc["stats"]["anime"]["bestStreak"] = json!(12);
+        doc["stats"]["manga"] = json!({ "total": 40, "sessions": 4 });
+        store.commit(&writes).unwrap();
+
+        let user = stored_user(&store).unwrap();
+        assert_eq!(user.stats.total("anime"), 3.0);
+        assert_eq!(user.stats.get("anime").unwrap().sessions, 2);
+        assert_eq!(user.stats.get("anime").unwrap().best_streak, 12);
+        assert_eq!(user.stats.total("manga"), 40.0);
+    }
+
+    #[test]
+    fn test_media_type_from_choice_index() {
+        use poise::serenity_prelude::CommandDataOptionValue as V;
+        use poise::ChoiceParameter;
+
+        let expected = [
+            "visual_novel",
+            "manga",
+            "anime",
+            "book",
+            "reading_time",
+            "listening",
+            "reading",
+        ];
+        for (index, key) in expected.iter().enumerate() {
+            let mt = media_type_from_option(&V::Integer(index as i64)).unwrap();
+            assert_eq!(mt.as_str(), *key);
+        }
+        // Every variant is reachable, so a new one has to be added here too
+        assert_eq!(MediaType::list().len(), expected.len());
+        assert!(media_type_from_option(&V::Integer(expected.len() as i64)).is_none());
+        assert!(media_type_from_option(&V::Integer(-1)).is_none());
+    }
+
+    #[test]
+    fn test_media_type_from_string_option() {
+        use poise::serenity_prelude::CommandDataOptionValue as V;
+
+        let by_name = media_type_from_option(&V::String("Book (pages)".into()));
+        assert!(matches!(by_name, Some(MediaType::Book)));
+        let by_key = media_type_from_option(&V::String("reading".into()));
+        assert!(matches!(by_key, Some(MediaType::Reading)));
+        assert!(media_type_from_option(&V::String("podcast".into())).is_none());
+    }
+
+    fn choice_json(choice: serenity::AutocompleteChoice) -> (String, String) {
+        let value = serde_json::to_value(choice).unwrap();
+        (
+            value["name"].as_str().unwrap().to_string(),
+            value["value"].as_str().unwrap().to_string(),
+        )
+    }
+
+    #[test]
+    fn test_autocomplete_choice_fits_limit() {
+        assert_eq!(
+            choice_json(autocomplete_choice("Clannad (2004-04-28)", "v4")),
+            ("Clannad (2004-04-28)".to_string(), "v4".to_string())
+        );
+
+        // Long titles are cut in the name only, the id stays intact
+        let long = "あ".repeat(150);
+        let (name, value) = choice_json(autocomplete_choice(&long, "12345"));
+        assert_eq!(name.chars().count(), 100);
+        assert!(name.ends_with('…'));
+        assert_eq!(value, "12345");
+
+        // The popularity marker lives in the name, never in the value
+        let boosted = title_popularity::label(&long, true);
+        let (name, value) = choice_json(autocomplete_choice(&boosted, "12345"));
+        assert_eq!(name.chars().count(), 100);
+        assert!(name.starts_with(title_popularity::MARKER));
+        assert_eq!(value, "12345");
+    }
+
+    #[test]
+    fn test_parse_title_input_ids() {
+        assert_eq!(
+            parse_title_input(MediaType::VisualNovel, "v12345"),
+            TitleInput::VndbId("v12345".to_string())
+        );
+        assert_eq!(
+            parse_title_input(MediaType::VisualNovel, " V17 "),
+            TitleInput::VndbId("v17".to_string())
+        );
+        assert_eq!(
+            parse_title_input(MediaType::Anime, "21"),
+            TitleInput::AniListId(21)
+        );
+        assert_eq!(
+            parse_title_input(MediaType::Reading, "30002"),
+            TitleInput::AniListId(30002)
+        );
+        // AniList ids don't apply to VNs, and VNDB ids don't apply to anime
+        assert_eq!(
+            parse_title_input(MediaType::VisualNovel, "21"),
+            TitleInput::Search("21".to_string())
+        );
+        assert_eq!(
+            parse_title_input(MediaType::Anime, "v21"),
+            TitleInput::Search("v21".to_string())
+        );
+        // Too large for an id
+        assert_eq!(
+            parse_title_input(MediaType::Manga, "99999999999"),
+            TitleInput::Search("99999999999".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_title_input_free_form_titles() {
+        for (media_type, title) in [
+            (MediaType::VisualNovel, "Fate|stay night"),
+            (
+                MediaType::VisualNovel,
+                "Summer Pockets | Reflection Blue|v20424",
+            ),
+            (MediaType::VisualNovel, "Clannad (2004)"),
+            (MediaType::Anime, "Steins;Gate (2011)"),
+            (MediaType::Anime, "葬送のフリーレン"),
+            (MediaType::Manga, "よつばと！"),
+            (MediaType::VisualNovel, "ゆめにっき"),
+            (MediaType::VisualNovel, "vivid"),
+        ] {
+            assert_eq!(
+                parse_title_input(media_type, title),
+                TitleInput::Search(title.to_string()),
+                "{}",
+                title
+            );
+        }
+    }
+
+    #[test]
+    fn test_cover_visibility() {
+        // Finished shows always show their cover
+        assert!(should_show_cover(true, false, None));
+        // Airing show, preference off
+        assert!(should_show_cover(false, true, None));
+        // Airing show, preference on
+        assert!(!should_show_cover(true, true, None));
+        // The per-log flag wins either way
+        assert!(should_show_cover(true, true, Some(true)));
+        assert!(!should_show_cover(false, false, Some(false)));
+    }
+
+    #[test]
+    fn test_parse_relog_amount() {
+        let anime = MediaType::Anime;
+        assert_eq!(parse_relog_amount(" 12 ", anime), Ok(12.0));
+        assert_eq!(parse_relog_amount("2.5", anime), Ok(2.5));
+        assert!(parse_relog_amount("0", anime).is_err());
+        assert!(parse_relog_amount("100001", anime).is_err());
+        assert!(parse_relog_amount("NaN", anime).is_err());
+        assert!(parse_relog_amount("ten", anime).is_err());
+        // The limit follows the media type
+        assert!(parse_relog_amount("51", anime).is_err());
+        assert_eq!(
+            parse_relog_amount("100001", MediaType::VisualNovel),
+            Ok(100001.0)
+        );
+    }
+
+    #[test]
+    fn test_parse_batch_line() {
+        assert_eq!(
+            parse_batch_line("anime 2 Sousou no Frieren "),
+            Ok(BatchEntry {
+                media_type: MediaType::Anime,
+                amount: 2.0,
+                title: Some("Sousou no Frieren".to_string()),
+            })
+        );
+        assert_eq!(
+            parse_batch_line("RT 45"),
+            Ok(BatchEntry {
+                media_type: MediaType::ReadingTime,
+                amount: 45.0,
+                title: None,
+            })
+        );
+        let media_type = |line: &str| parse_batch_line(line).map(|e| e.media_type);
+        assert_eq!(media_type("vn 12000"), Ok(MediaType::VisualNovel));
+        assert_eq!(media_type("listen 30"), Ok(MediaType::Listening));
+        assert_eq!(media_type("read 5000"), Ok(MediaType::Reading));
+        assert_eq!(media_type("reading_time 30"), Ok(MediaType::ReadingTime));
+        assert_eq!(media_type("book 20"), Ok(MediaType::Book));
+
+        assert!(parse_batch_line("anime").is_err());
+        assert!(parse_batch_line("podcast 30")
+            .unwrap_err()
+            .contains("podcast"));
+        assert!(parse_batch_line("anime two Frieren").is_err());
+        // Same per-type caps as /immersion
+        assert!(parse_batch_line("anime 51").is_err());
+    }
+
+    #[test]
+    fn test_parse_batch_keeps_valid_lines() {
+        let lines = parse_batch("anime 2 Frieren\n\n  \nanime lots\nmanga 40 Yotsuba\n");
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1].number, 4);
+        assert!(lines[1].parsed.is_err());
+        assert!(lines[2].parsed.is_ok());
+
+        let skipped = batch_skipped_text(&lines).unwrap();
+        assert!(skipped.starts_with("Line 4: `anime lots`"));
+        assert_eq!(
+            batch_entry_text(lines[2].parsed.as_ref().unwrap()),
+            "40 pages of Manga — Yotsuba"
+        );
+
+        // Past the cap valid lines are skipped too
+        let many = "rt 10\n".repeat(BATCH_MAX_ENTRIES + 2);
+        let lines = parse_batch(&many);
+        assert_eq!(
+            lines.iter().filter(|l| l.parsed.is_ok()).count(),
+            BATCH_MAX_ENTRIES
+        );
+        assert!(lines.last().unwrap().parsed.is_err());
+        assert_eq!(batch_skipped_text(&parse_batch("rt 10")), None);
+    }
+
+    #[test]
+    fn test_first_url() {
+        assert_eq!(
+            first_url("watched this https://youtu.be/dQw4w9WgXcQ today"),
+            Some("https://youtu.be/dQw4w9WgXcQ")
+        );
+        assert_eq!(
+            first_url("<https://example.com/news/1>, then http://other.jp"),
+            Some("https://example.com/news/1")
+        );
+        assert_eq!(
+            first_url("||https://example.com/spoiler||"),
+            Some("https://example.com/spoiler")
+        );
+        assert_eq!(
+            first_url("[article](https://example.com/a)."),
+            Some("https://example.com/a")
+        );
+        assert_eq!(first_url("no links, just https:// here"), None);
+        assert_eq!(first_url(""), None);
+    }
+
+    #[test]
+    fn test_parse_link_media_type() {
+        assert!(matches!(
+            parse_link_media_type(" Listening "),
+            Some(MediaType::Listening)
+        ));
+        assert!(matches!(
+            parse_link_media_type("reading time"),
+            Some(MediaType::ReadingTime)
+        ));
+        assert!(matches!(
+            parse_link_media_type("reading_time"),
+            Some(MediaType::ReadingTime)
+        ));
+        assert!(parse_link_media_type("anime").is_none());
+    }
+
+    fn embed_request(comment: Option<String>) -> LogRequest {
+        LogRequest {
+            media_type: MediaType::VisualNovel,
+            amount: 30_000.0,
+            title: "Sakura no Uta".to_string(),
+            comment,
+            date: NaiveDate::from_ymd_opt(2025, 1, 15).unwrap(),
+            timezone: None,
+            log_url: None,
+            anilist_url: None,
+            vndb_url: None,
+            thumbnail: None,
+            source: "vndb",
+            vndb_metadata: None,
+            airing: false,
+            guild_id: None,
+            channel_id: None,
+            message_id: None,
+            private: false,
+        }
+    }
+
+    fn embed_result(user_doc: Option<UserDoc>, now_nudge: Option<String>) -> LogResult {
+        LogResult {
+            updated_total: 30_000.0,
+            user_doc,
+            streak: 1,
+            freeze: None,
+            goal_progress: None,
+            goal_reached: None,
+            completion: None,
+            now_nudge,
+        }
+    }
+
+    /// The embed as Discord receives it
+    fn embed_json(request: &LogRequest, result: &LogResult) -> Value {
+        let user = serenity::User::default();
+        serde_json::to_value(log_embed(&user, request, result, None, None, None)).unwrap()
+    }
+
+    fn field<'a>(embed: &'a Value, name: &str) -> Option<&'a str> {
+        embed["fields"]
+            .as_array()?
+            .iter()
+            .find(|f| f["name"] == name)
+            .and_then(|f| f["value"].as_str())
+    }
+
+    #[test]
+    fn test_log_embed_fits_long_comment() {
+        let result = embed_result(None, None);
+        let long = "あ".repeat(1010);
+        let embed = embed_json(&embed_request(Some(long)), &result);
+        let comment = field(&embed, "Comment").unwrap();
+        assert!(comment.chars().count() <= EMBED_FIELD_LIMIT);
+        assert!(comment.ends_with("... (dipotong, terlalu panjang)"));
+
+        // A comment that fits is kept whole
+        let short = "あ".repeat(996);
+        let embed = embed_json(&embed_request(Some(short.clone())), &result);
+        assert_eq!(field(&embed, "Comment"), Some(short.as_str()));
+    }
+
+    #[test]
+    fn test_log_embed_marks_current_title_and_nudges() {
+        use crate::models::user::CurrentItem;
+
+        let mut doc = UserDoc::default();
+        doc.summary.current = Some(CurrentItem {
+            media_type: "visual_novel".to_string(),
+            title: "sakura no uta".to_string(),
+            set_at: "2025-01-10T00:00:00+00:00".to_string(),
+            nudged: false,
+        });
+        let embed = embed_json(&embed_request(None), &embed_result(Some(doc), None));
+        let footer = embed["footer"]["text"].as_str().unwrap();
+        assert!(footer.ends_with("| Visual Novel · currently reading ✓"));
+        assert_eq!(field(&embed, "Still on it?"), None);
+
+        // Logging something else after a while asks about the status instead
+        let result = embed_result(None, Some("Summer Pockets".to_string()));
+        let embed = embed_json(&embed_request(None), &result);
+        assert!(!embed["footer"]["text"]
+            .as_str()
+            .unwrap()
+            .contains("currently"));
+        assert!(field(&embed, "Still on it?")
+            .unwrap()
+            .contains("**Summer Pockets**"));
+    }
+
+    #[test]
+    fn test_vn_completion() {
+        let request = LogRequest {
+            media_type: MediaType::VisualNovel,
+            amount: 30_000.0,
+            title: "Sakura no Uta".to_string(),
+            comment: None,
+            date: NaiveDate::from_ymd_opt(2025, 1, 15).unwrap(),
+            timezone: None,
+            log_url: None,
+            anilist_url: None,
+            vndb_url: Some("https://vndb.org/v17".to_string()),
+            thumbnail: None,
+            source: "vndb",
+            vndb_metadata: Some(json!({ "id": "v17", "length": 3 })),
+            airing: false,
+            guild_id: None,
+            channel_id: None,
+            message_id: None,
+            private: false,
+        };
+        let prior = vec![
+            json!({ "activity": { "amount": 60_000.0 }, "metadata": { "vndbInfo": { "id": "v17" } } }),
+            // Saved before the id was stored
+            json!({ "activity": { "amount": 21_000.0, "vndbUrl": "https://vndb.org/v17" } }),
+            json!({ "activity": { "amount": 90_000.0 }, "metadata": { "vndbInfo": { "id": "v18" } } }),
+        ];
+        assert_eq!(
+            vn_completion(&request, &prior).as_deref(),
+            Some("25% (of ~30h)")
+        );
+
+        // No field without a length
+        let unknown = LogRequest {
+            vndb_metadata: Some(json!({ "id": "v17", "length": null })),
+            ..request
+        };
+        assert_eq!(vn_completion(&unknown, &prior), None);
+    }
+}
@@ -1,14 +1,39 @@
 // Leaderboard command - view community rankings
 // Ported from commands/leaderboard.js
 
-use crate::utils::config::colors;
-use crate::utils::points::calculate_points;
+use crate::api::cache::{Lookup, TtlCache};
+use crate::api::storage::Storage;
+use crate::utils::config::{colors, effective_date_at, get_guild_config};
+use crate::utils::formatters::{fit_description, EMBED_DESCRIPTION_LIMIT};
+use crate::utils::points::sum_log_points;
+use crate::utils::privacy::LeaderboardPrivacy;
+use crate::utils::streak::log_date;
 use crate::{Context, Error};
-use chrono::{DateTime, Datelike, Duration, NaiveDate};
+use chrono::{Datelike, Duration, NaiveDate};
+use futures::StreamExt;
+use once_cell::sync::Lazy;
 use poise::serenity_prelude as serenity;
 use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use tracing::error;
 
+const PAGE_SIZE: usize = 10;
+
+/// Most members Discord returns per request
+const MEMBER_PAGE_SIZE: u64 = 1000;
+
+/// How long a guild's member list is reused before it's paged through again
+const MEMBERS_TTL: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
+/// Member IDs per guild, so each server leaderboard doesn't page through every member again
+static MEMBERS_CACHE: Lazy<TtlCache<serenity::GuildId, Arc<HashSet<String>>>> =
+    Lazy::new(|| TtlCache::new("guild members", 100, MEMBERS_TTL));
+
+/// Parts of a user document the ranking reads: names and opt-out from the profile. Points
+/// come from the logs, the rest of each document stays on the server.
+const USER_FIELDS: [&str; 1] = ["profile"];
+
 /// Time period for leaderboard
 #[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
 pub enum TimePeriod {
@@ -82,6 +107,15 @@ impl LeaderboardMediaType {
     }
 }
 
+/// Whose logs the leaderboard ranks
+#[derive(Debug, Clone, Copy, PartialEq, poise::ChoiceParameter)]
+pub enum LeaderboardScope {
+    #[name = "Global"]
+    Global,
+    #[name = "This Server"]
+    Server,
+}
+
 /// Month choice for leaderboard
 #[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
 pub enum MonthChoice {
@@ -121,17 +155,38 @@ pub async fn leaderboard(
     #[description = "Year"]
     #[min = 2020]
     year: Option<i32>,
+    #[description = "Everyone, or only members of this server (default Global)"] scope: Option<
+        LeaderboardScope,
+    >,
 ) -> Result<(), Error> {
+    let scope = scope.unwrap_or(LeaderboardScope::Global);
+    let server_id = match (scope, ctx.guild_id()) {
+        (LeaderboardScope::Global, _) => None,
+        (LeaderboardScope::Server, Some(guild_id)) => Some(guild_id),
+        (LeaderboardScope::Server, None) => {
+            ctx.say("The This Server scope only works in a server.")
+                .await?;
+            return Ok(());
+        }
+    };
     ctx.defer().await?;
 
     let data = ctx.data();
     let media_type_filter = media_type.as_str();
-    let effective_date = crate::utils::config::get_effective_date();
+    let effective_date = effective_date_at(data.clock.now_utc());
     let period_filter = PeriodFilter::new(timestamp, month, year, effective_date);
     let title = period_filter.title();
 
-    // Fetch all users
-    let users = match data.firebase.get_all_users().await {
+    // Guild leaderboards use the server's multipliers, DMs use the defaults
+    let points_overrides = match ctx.guild_id() {
+        Some(guild_id) => get_guild_config(data, &guild_id.to_string())
+            .await
+            .and_then(|c| c.points_overrides),
+        None => None,
+    };
+
+    // Fetch all users, only the parts the ranking reads
+    let users = match data.firebase.get_all_users(Some(&USER_FIELDS)).await {
         Ok(u) => u,
         Err(e) => {
             error!("Failed to fetch users: {:?}", e);
@@ -145,14 +200,29 @@ pub async fn leaderboard(
         return Ok(());
     }
 
+    // Current members rather than logs made here, so ones who log elsewhere still count
+    let members = match server_id {
+        Some(guild_id) => match member_ids(ctx.http(), guild_id).await {
+            Ok(ids) => Some(ids),
+            Err(e) => {
+                error!("Failed to fetch guild members: {:?}", e);
+                ctx.say("Failed to fetch this server's members.").await?;
+                return Ok(());
+            }
+        },
+        None => None,
+    };
+
     let mut leaderboard: Vec<LeaderboardEntry> = Vec::new();
 
     for user_doc in users {
         let user_id = user_doc.get("_id").and_then(|v| v.as_str()).unwrap_or("");
-        if user_id.is_empty() {
+        if user_id.is_empty() || members.as_ref().is_some_and(|m| !m.contains(user_id)) {
             continue;
         }
 
+        // Opted-out members are left out or shown as Anonymous
+        let privacy = LeaderboardPrivacy::of_doc(&user_doc);
         let profile = user_doc.get("profile");
         let display_name = profile
             .and_then(|p| p.get("displayName"))
@@ -163,15 +233,23 @@ pub async fn leaderboard(
                     .and_then(|v| v.as_str())
             })
             .unwrap_or("Unknown");
-
-        let total_points = if matches!(timestamp, TimePeriod::AllTime) {
-            calculate_all_time_points(&user_doc, media_type_filter)
-        } else {
-            calculate_interval_points(data, user_id, &period_filter, media_type_filter).await
+        let Some(display_name) = privacy.display_name(display_name) else {
+            continue;
         };
 
+        // Summed from the logs even for all-time, the stored totals count private logs
+        let total_points = calculate_period_points(
+            data.firebase.as_ref(),
+            user_id,
+            &period_filter,
+            media_type_filter,
+            points_overrides.as_ref(),
+        )
+        .await;
+
         if total_points > 0.0 {
             leaderboard.push(LeaderboardEntry {
+                user_id: user_id.to_string(),
                 display_name: display_name.to_string(),
                 points: total_points,
             });
@@ -198,64 +276,161 @@ pub async fn leaderboard(
         return Ok(());
     }
 
-    let mut description = String::from("Here's the list of top immersionists:\n\n");
-    let top_count = leaderboard.len().min(10);
-
-    for (i, entry) in leaderboard.iter().take(top_count).enumerate() {
-        description.push_str(&format!(
-            "**#{}. {}**: {:.2} Pts\n",
-            i + 1,
-            entry.display_name,
-            entry.points
-        ));
+    let title = match scope {
+        LeaderboardScope::Global => format!("{} ({})", title, media_type.label()),
+        LeaderboardScope::Server => format!("{} ({}, This Server)", title, media_type.label()),
+    };
+    let rank_line = your_rank_line(&leaderboard, &ctx.author().id.to_string());
+    let total_pages = leaderboard.len().div_ceil(PAGE_SIZE);
+
+    let reply = ctx
+        .send(
+            poise::CreateReply::default()
+                .embed(build_embed(
+                    &leaderboard,
+                    0,
+                    total_pages,
+                    &title,
+                    &rank_line,
+                ))
+                .components(nav_buttons(0, total_pages, false)),
+        )
+        .await?;
+
+    if total_pages <= 1 {
+        return Ok(());
     }
 
-    let embed = serenity::CreateEmbed::new()
-        .title(format!("{} ({})", title, media_type.label()))
-        .description(description)
-        .color(colors::PRIMARY);
+    let msg = reply.message().await?;
+    let mut current_page: usize = 0;
+
+    let mut collector = msg
+        .await_component_interactions(ctx)
+        .author_id(ctx.author().id)
+        .timeout(std::time::Duration::from_secs(60))
+        .stream();
 
-    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+    while let Some(interaction) = collector.next().await {
+        match interaction.data.custom_id.as_str() {
+            "leaderboard_prev" if current_page > 0 => current_page -= 1,
+            "leaderboard_next" if current_page < total_pages - 1 => current_page += 1,
+            _ => continue,
+        }
+
+        interaction
+            .create_response(
+                ctx,
+                serenity::CreateInteractionResponse::UpdateMessage(
+                    serenity::CreateInteractionResponseMessage::new()
+                        .embed(build_embed(
+                            &leaderboard,
+                            current_page,
+                            total_pages,
+                            &title,
+                            &rank_line,
+                        ))
+                        .components(nav_buttons(current_page, total_pages, false)),
+                ),
+            )
+            .await?;
+    }
+
+    // Disable buttons on timeout
+    let _ = reply
+        .edit(
+            ctx,
+            poise::CreateReply::default()
+                .embed(build_embed(
+                    &leaderboard,
+                    current_page,
+                    total_pages,
+                    &title,
+                    &rank_line,
+                ))
+                .components(nav_buttons(current_page, total_pages, true)),
+        )
+        .await;
 
     Ok(())
 }
 
-fn calculate_all_time_points(user_doc: &Value, media_type_filter: Option<&str>) -> f64 {
-    let stats = match user_doc.get("stats") {
-        Some(s) if s.is_object() => s,
-        _ => return 0.0,
-    };
-
-    let mut total_points = 0.0;
-    if let Some(stats_obj) = stats.as_object() {
-        for (media_type, data) in stats_obj {
-            if let Some(filter) = media_type_filter {
-                if media_type != filter {
-                    continue;
-                }
-            }
+fn build_embed(
+    leaderboard: &[LeaderboardEntry],
+    page: usize,
+    total_pages: usize,
+    title: &str,
+    rank_line: &str,
+) -> serenity::CreateEmbed {
+    let start = page * PAGE_SIZE;
+    let end = (start + PAGE_SIZE).min(leaderboard.len());
+
+    const HEADER: &str = "Here's the list of top immersionists:\n\n";
+    let lines: Vec<String> = leaderboard[start..end]
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            format!(
+                "**#{}. {}**: {:.2} Pts",
+                start + i + 1,
+                entry.display_name,
+                entry.points
+            )
+        })
+        .collect();
+    // Room left by the header and the viewer's rank below the list
+    let room = EMBED_DESCRIPTION_LIMIT
+        .saturating_sub(HEADER.chars().count() + rank_line.chars().count() + 2);
+    let (ranking, _) = fit_description(lines, room);
+    let description = format!("{}{}\n\n{}", HEADER, ranking, rank_line);
+
+    serenity::CreateEmbed::new()
+        .title(title)
+        .description(description)
+        .color(colors::PRIMARY)
+        .footer(serenity::CreateEmbedFooter::new(format!(
+            "Page {}/{}",
+            page + 1,
+            total_pages
+        )))
+}
 
-            let amount = data.get("total").and_then(|v| v.as_f64()).unwrap_or(0.0);
-            if amount > 0.0 {
-                total_points += calculate_points(media_type, amount) as f64;
-            }
-        }
+fn nav_buttons(page: usize, total_pages: usize, disabled: bool) -> Vec<serenity::CreateActionRow> {
+    if total_pages <= 1 {
+        return Vec::new();
     }
 
-    total_points
+    vec![serenity::CreateActionRow::Buttons(vec![
+        serenity::CreateButton::new("leaderboard_prev")
+            .label("< Prev")
+            .style(serenity::ButtonStyle::Secondary)
+            .disabled(disabled || page == 0),
+        serenity::CreateButton::new("leaderboard_next")
+            .label("Next >")
+            .style(serenity::ButtonStyle::Primary)
+            .disabled(disabled || page >= total_pages - 1),
+    ])]
 }
 
-async fn calculate_interval_points(
-    data: &crate::Data,
+/// Rank line for the invoking user, computed from the sorted leaderboard
+fn your_rank_line(leaderboard: &[LeaderboardEntry], user_id: &str) -> String {
+    match leaderboard.iter().position(|e| e.user_id == user_id) {
+        Some(pos) => format!(
+            "Your rank: #{} with {:.2} pts",
+            pos + 1,
+            leaderboard[pos].points
+        ),
+        None => "Your rank: unranked".to_string(),
+    }
+}
+
+async fn calculate_period_points(
+    store: &dyn Storage,
     user_id: &str,
     period_filter: &PeriodFilter,
     media_type_filter: Option<&str>,
+    points_overrides: Option<&HashMap<String, f64>>,
 ) -> f64 {
-    let logs = match data
-        .firebase
-        .query_subcollection("users", user_id, "immersion_logs")
-        .await
-    {
+    let logs = match store.get_user_logs(user_id).await {
         Ok(logs) => logs,
         Err(e) => {
             error!(
@@ -266,58 +441,16 @@ async fn calculate_interval_points(
         }
     };
 
-    let mut total_points = 0.0;
-
-    for log in logs {
-        if !period_filter.matches_log(&log) {
-            continue;
-        }
-
-        let activity = match log.get("activity") {
-            Some(activity) if activity.is_object() => activity,
-            _ => continue,
-        };
-
-        let log_media_type = match activity.get("type").and_then(|v| v.as_str()) {
-            Some(media_type) => media_type,
-            None => continue,
-        };
-
-        if let Some(filter) = media_type_filter {
-            if log_media_type != filter {
-                continue;
-            }
-        }
-
-        let amount = activity
-            .get("amount")
-            .and_then(|v| v.as_f64())
-            .unwrap_or(0.0);
-        if amount > 0.0 {
-            total_points += calculate_points(log_media_type, amount) as f64;
-        }
-    }
-
-    total_points
+    sum_log_points(
+        logs.iter().filter(|log| period_filter.matches_log(log)),
+        media_type_filter,
+        points_overrides,
+    )
 }
 
+/// Activity date of a log, legacy ones dated in WIB like every period boundary here
 fn extract_log_date(log: &Value) -> Option<NaiveDate> {
-    let timestamps = log.get("timestamps")?;
-
-    if let Some(date) = timestamps.get("date").and_then(|v| v.as_str()) {
-        if let Ok(parsed) = NaiveDate::parse_from_str(date, "%Y-%m-%d") {
-            return Some(parsed);
-        }
-    }
-
-    if let Some(created) = timestamps.get("created").and_then(|v| v.as_str()) {
-        if let Ok(created_utc) = DateTime::parse_from_rfc3339(created) {
-            let wib_offset = chrono::FixedOffset::east_opt(7 * 3600)?;
-            return Some(created_utc.with_timezone(&wib_offset).date_naive());
-        }
-    }
-
-    None
+    NaiveDate::parse_from_str(&log_date(log)?, "%Y-%m-%d").ok()
 }
 
 fn month_name(month: u32) -> &'static str {
@@ -446,7 +579,87 @@ impl PeriodFilter {
     }
 }
 
+/// Every member of a guild, from the cache while it's fresh
+async fn member_ids(
+    http: &serenity::Http,
+    guild_id: serenity::GuildId,
+) -> Result<Arc<HashSet<String>>, Error> {
+    if let Lookup::Fresh(ids) = MEMBERS_CACHE.get(&guild_id).await {
+        return Ok(ids);
+    }
+    let ids = Arc::new(fetch_member_ids(http, guild_id).await?);
+    MEMBERS_CACHE.insert(guild_id, ids.clone()).await;
+    Ok(ids)
+}
+
+/// Every member of a guild, a page of `MEMBER_PAGE_SIZE` at a time
+async fn fetch_member_ids(
+    http: &serenity::Http,
+    guild_id: serenity::GuildId,
+) -> Result<HashSet<String>, Error> {
+    let mut ids = HashSet::new();
+    let mut after = None;
+    loop {
+        let page = guild_id
+            .members(http, Some(MEMBER_PAGE_SIZE), after)
+            .await?;
+        ids.extend(page.iter().map(|m| m.user.id.to_string()));
+        if (page.len() as u64) < MEMBER_PAGE_SIZE {
+            break;
+        }
+        after = page.last().map(|m| m.user.id);
+    }
+    Ok(ids)
+}
+
 struct LeaderboardEntry {
+    user_id: String,
     display_name: String,
     points: f64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::memory_store::MemoryStore;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_all_time_points_leave_out_private_logs() {
+        let store = MemoryStore::new();
+        let storage: &dyn Storage = &store;
+        // The stored totals count both logs
+        storage
+            .set_document(
+                "users",
+                "123",
+                &json!({ "stats": { "anime": { "total": 5.0, "sessions": 2 } } }),
+            )
+            .await
+            .unwrap();
+        storage
+            .set_document(
+                "users/123/immersion_logs",
+                "public",
+                &json!({ "activity": { "type": "anime", "amount": 2.0 } }),
+            )
+            .await
+            .unwrap();
+        storage
+            .set_document(
+                "users/123/immersion_logs",
+                "private",
+                &json!({
+                    "activity": { "type": "anime", "amount": 3.0 },
+                    "metadata": { "private": true }
+                }),
+            )
+            .await
+            .unwrap();
+
+        let today = NaiveDate::from_ymd_opt(2025, 1, 15).unwrap();
+        let all_time = PeriodFilter::new(TimePeriod::AllTime, None, None, today);
+        let points = calculate_period_points(storage, "123", &all_time, None, None).await;
+        assert_eq!(points, 26.0);
+    }
+}
@@ -0,0 +1,112 @@
+// Link command - connect outside accounts to show on /stat and /card
+// The API key is checked with jpdb, stored on the user document and never shown again
+
+use serde_json::json;
+use tracing::{error, info, warn};
+
+use crate::api::jpdb;
+use crate::{Context, Error};
+
+/// Connect an outside account
+#[poise::command(slash_command, subcommands("link_jpdb"))]
+pub async fn link(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Show your jpdb.io known words on /stat and /card
+#[poise::command(slash_command, rename = "jpdb")]
+pub async fn link_jpdb(
+    ctx: Context<'_>,
+    #[description = "API key from the bottom of jpdb.io/settings"] api_key: String,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let data = ctx.data();
+    let api_key = api_key.trim();
+    match jpdb::validate_key(&data.http_client, api_key).await {
+        Ok(true) => {}
+        Ok(false) => {
+            ctx.say("jpdb didn't accept that API key. Copy it again from jpdb.io/settings.")
+                .await?;
+            return Ok(());
+        }
+        Err(e) => {
+            warn!("Couldn't validate a jpdb key: {:?}", e);
+            ctx.say("jpdb: unavailable. Try linking again in a bit.")
+                .await?;
+            return Ok(());
+        }
+    }
+
+    let user_id = ctx.author().id.to_string();
+    if let Err(e) = data
+        .firebase
+        .set_document_merge_paths(
+            "users",
+            &user_id,
+            &json!({
+                "integrations": {
+                    "jpdb": {
+                        "apiKey": api_key,
+                        "linkedAt": data.clock.now_utc().to_rfc3339(),
+                    }
+                }
+            }),
+            &["integrations.jpdb"],
+        )
+        .await
+    {
+        error!("Failed to save jpdb link: {:?}", e);
+        ctx.say("Failed to save the link.").await?;
+        return Ok(());
+    }
+    info!("User {} linked jpdb", user_id);
+
+    let known = jpdb::refresh_known_words(&data.http_client, &user_id, api_key)
+        .await
+        .unwrap_or_else(|e| {
+            warn!("Failed to fetch jpdb known words: {:?}", e);
+            None
+        });
+    ctx.say(format!(
+        "Linked your jpdb account (jpdb: {}). It now shows on /stat and /card, `/unlink jpdb` removes it.",
+        jpdb::known_words_text(known)
+    ))
+    .await?;
+    Ok(())
+}
+
+/// Disconnect an outside account
+#[poise::command(slash_command, subcommands("unlink_jpdb"))]
+pub async fn unlink(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Remove your jpdb.io API key
+#[poise::command(slash_command, rename = "jpdb")]
+pub async fn unlink_jpdb(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let user_id = ctx.author().id.to_string();
+    // A path missing from the data deletes it
+    if let Err(e) = ctx
+        .data()
+        .firebase
+        .set_document_merge_paths(
+            "users",
+            &user_id,
+            &json!({ "integrations": {} }),
+            &["integrations.jpdb"],
+        )
+        .await
+    {
+        error!("Failed to remove jpdb link: {:?}", e);
+        ctx.say("Failed to remove the link.").await?;
+        return Ok(());
+    }
+    info!("User {} unlinked jpdb", user_id);
+
+    ctx.say("Unlinked your jpdb account and deleted its API key.")
+        .await?;
+    Ok(())
+}
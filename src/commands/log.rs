@@ -1,13 +1,18 @@
 // Log command - view and manage immersion logs
 // Full implementation ported from commands/log.js
 
-use chrono::{DateTime, Duration, Utc};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
 use futures::StreamExt;
 use poise::serenity_prelude as serenity;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, error};
 
-use crate::utils::config::get_media_label;
+use crate::api::storage::{LogCursor, Storage};
+use crate::commands::immersion::{parse_custom_date, INVALID_DATE_MESSAGE};
+use crate::utils::config::{
+    colors, day_start_in, effective_date_at, get_media_label, local_date_in, local_datetime_in,
+};
+use crate::utils::formatters::{fit_description, truncate_chars, EMBED_DESCRIPTION_LIMIT};
 use crate::{Context, Error};
 
 // ============ Data Structures ============
@@ -18,6 +23,11 @@ pub struct ImmersionLog {
     pub id: String,
     pub activity: LogActivity,
     pub timestamps: LogTimestamps,
+    #[serde(default)]
+    pub metadata: LogFlags,
+    /// Set while the log is in the trash
+    #[serde(rename = "deletedAt", default)]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,6 +40,16 @@ pub struct LogActivity {
     pub unit: String,
     #[serde(default)]
     pub title: Option<String>,
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+/// The parts of a log's `metadata` the browser reads
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LogFlags {
+    /// Kept to its owner, see `privacy::is_private_log`
+    #[serde(default)]
+    pub private: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,42 +57,254 @@ pub struct LogTimestamps {
     pub created: DateTime<Utc>,
     #[serde(default)]
     pub updated: Option<DateTime<Utc>>,
+    /// Activity date (YYYY-MM-DD), missing on legacy logs
+    #[serde(default)]
+    pub date: Option<String>,
+}
+
+impl ImmersionLog {
+    /// Activity date, falling back to the WIB date of creation for legacy logs
+    pub(crate) fn activity_date(&self) -> String {
+        self.timestamps.date.clone().unwrap_or_else(|| {
+            local_date_in(None, self.timestamps.created)
+                .format("%Y-%m-%d")
+                .to_string()
+        })
+    }
+}
+
+/// Modal for editing an existing log
+#[derive(Debug, Clone, poise::Modal)]
+#[name = "Edit Log"]
+struct EditLogModal {
+    #[name = "Amount"]
+    amount: String,
+    #[name = "Title"]
+    title: Option<String>,
+    #[name = "Date (YYYY-MM-DD)"]
+    #[min_length = 10]
+    #[max_length = 10]
+    date: String,
+    #[name = "Comment"]
+    #[paragraph]
+    #[max_length = 1000]
+    comment: Option<String>,
+    #[name = "Private, only you see it (yes/no)"]
+    #[max_length = 3]
+    private: Option<String>,
+}
+
+impl EditLogModal {
+    fn from_log(log: &ImmersionLog) -> Self {
+        Self {
+            amount: log.activity.amount.to_string(),
+            title: log.activity.title.clone().filter(|t| t != "-"),
+            date: log.activity_date(),
+            comment: log.activity.comment.clone(),
+            private: Some(if log.metadata.private { "yes" } else { "no" }.to_string()),
+        }
+    }
+}
+
+/// Modal asking for the day to clear with "Delete All on Date"
+#[derive(Debug, Clone, poise::Modal)]
+#[name = "Delete All on Date"]
+struct BulkDeleteModal {
+    #[name = "Date (YYYY-MM-DD)"]
+    #[placeholder = "2026-01-21"]
+    #[min_length = 10]
+    #[max_length = 10]
+    date: String,
+}
+
+/// Validated values from the edit modal
+#[derive(Debug, Clone, PartialEq)]
+struct LogEdit {
+    amount: f64,
+    title: String,
+    date: NaiveDate,
+    comment: Option<String>,
+    private: bool,
+}
+
+/// Validate the modal input, using the same limits and date format as /immersion
+fn parse_log_edit(modal: &EditLogModal, today: NaiveDate) -> Result<LogEdit, String> {
+    let amount = modal
+        .amount
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|a| a.is_finite() && (1.0..=100000.0).contains(a))
+        .ok_or("Amount must be a number between 1 and 100000.")?;
+
+    let date = NaiveDate::parse_from_str(modal.date.trim(), "%Y-%m-%d")
+        .map_err(|_| "Invalid date format. Please use YYYY-MM-DD (e.g. 2026-01-21)")?;
+    if date > today {
+        return Err("The date can't be in the future.".to_string());
+    }
+
+    let title = modal
+        .title
+        .as_deref()
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .unwrap_or("-")
+        .to_string();
+    let comment = modal
+        .comment
+        .as_deref()
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(str::to_string);
+    let private = match modal.private.as_deref().map(str::trim) {
+        None | Some("") => false,
+        Some(answer) if answer.eq_ignore_ascii_case("yes") || answer.eq_ignore_ascii_case("y") => {
+            true
+        }
+        Some(answer) if answer.eq_ignore_ascii_case("no") || answer.eq_ignore_ascii_case("n") => {
+            false
+        }
+        Some(_) => return Err("Private must be `yes` or `no`.".to_string()),
+    };
+
+    Ok(LogEdit {
+        amount,
+        title,
+        date,
+        comment,
+        private,
+    })
 }
 
-#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, poise::ChoiceParameter)]
 pub enum LogTimeframe {
     #[name = "Last 24 Hours"]
     Day,
     #[name = "Last 7 Days"]
     Week,
+    #[name = "Last 30 Days"]
+    Month,
+}
+
+/// Logs shown by `/log view`: a timeframe up to now or a range of days
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LogWindow {
+    Last(LogTimeframe),
+    /// Inclusive, in effective days
+    Range(NaiveDate, NaiveDate),
 }
 
-impl LogTimeframe {
-    fn to_string(&self) -> &'static str {
+impl LogWindow {
+    /// Form used in button ids, without underscores: `24h`, `7d`, `30d` or
+    /// `YYYYMMDD-YYYYMMDD`
+    fn token(self) -> String {
         match self {
-            LogTimeframe::Day => "24h",
-            LogTimeframe::Week => "7d",
+            LogWindow::Last(LogTimeframe::Day) => "24h".to_string(),
+            LogWindow::Last(LogTimeframe::Week) => "7d".to_string(),
+            LogWindow::Last(LogTimeframe::Month) => "30d".to_string(),
+            LogWindow::Range(from, to) => {
+                format!("{}-{}", from.format("%Y%m%d"), to.format("%Y%m%d"))
+            }
+        }
+    }
+
+    fn from_token(token: &str) -> Option<Self> {
+        match token {
+            "24h" => Some(LogWindow::Last(LogTimeframe::Day)),
+            "7d" => Some(LogWindow::Last(LogTimeframe::Week)),
+            "30d" => Some(LogWindow::Last(LogTimeframe::Month)),
+            _ => {
+                let (from, to) = token.split_once('-')?;
+                Some(LogWindow::Range(
+                    NaiveDate::parse_from_str(from, "%Y%m%d").ok()?,
+                    NaiveDate::parse_from_str(to, "%Y%m%d").ok()?,
+                ))
+            }
+        }
+    }
+
+    fn label(self) -> String {
+        match self {
+            LogWindow::Last(LogTimeframe::Day) => "Last 24 Hours".to_string(),
+            LogWindow::Last(LogTimeframe::Week) => "Last 7 Days".to_string(),
+            LogWindow::Last(LogTimeframe::Month) => "Last 30 Days".to_string(),
+            LogWindow::Range(from, to) if from == to => from.format("%Y-%m-%d").to_string(),
+            LogWindow::Range(from, to) => {
+                format!("{} to {}", from.format("%Y-%m-%d"), to.format("%Y-%m-%d"))
+            }
+        }
+    }
+
+    /// Creation time bounds, the end exclusive. Days of a range start at `DAY_END_HOUR` WIB
+    /// like effective dates do.
+    fn bounds(self, now: DateTime<Utc>) -> (DateTime<Utc>, Option<DateTime<Utc>>) {
+        let day_start = |date: NaiveDate| day_start_in(None, date);
+        match self {
+            LogWindow::Last(LogTimeframe::Day) => (now - Duration::hours(24), None),
+            LogWindow::Last(LogTimeframe::Week) => (now - Duration::days(7), None),
+            LogWindow::Last(LogTimeframe::Month) => (now - Duration::days(30), None),
+            LogWindow::Range(from, to) => {
+                (day_start(from), Some(day_start(to + Duration::days(1))))
+            }
         }
     }
 }
 
+/// The window for `/log view`: `from`/`to` override the timeframe, `to` defaults to today
+fn parse_log_window(
+    timeframe: LogTimeframe,
+    from: Option<&str>,
+    to: Option<&str>,
+    today: NaiveDate,
+) -> Result<LogWindow, String> {
+    let parse = |date: &str| parse_custom_date(date.trim()).ok_or(INVALID_DATE_MESSAGE);
+    let (from, to) = match (from, to) {
+        (None, None) => return Ok(LogWindow::Last(timeframe)),
+        (None, Some(_)) => return Err("Please also give a `from` date.".to_string()),
+        (Some(from), None) => (parse(from)?, today),
+        (Some(from), Some(to)) => (parse(from)?, parse(to)?),
+    };
+    if from > to {
+        return Err("`from` must not be after `to`.".to_string());
+    }
+    if from > today {
+        return Err("The date can't be in the future.".to_string());
+    }
+    Ok(LogWindow::Range(from, to))
+}
+
 const LOGS_PER_PAGE: usize = 10;
 
 // ============ Main Command ============
 
 /// View and manage your immersion logs
+#[poise::command(slash_command, prefix_command, subcommands("view", "trash"))]
+pub async fn log(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// View your recent immersion logs
 #[poise::command(slash_command, prefix_command)]
-pub async fn log(
+pub async fn view(
     ctx: Context<'_>,
     #[description = "Timeframe to view"] timeframe: LogTimeframe,
+    #[description = "First day to view (YYYY-MM-DD), overrides the timeframe"] from: Option<String>,
+    #[description = "Last day to view (YYYY-MM-DD), defaults to today"] to: Option<String>,
 ) -> Result<(), Error> {
     ctx.defer_ephemeral().await?;
 
-    let timeframe_str = timeframe.to_string();
+    let today = effective_date_at(ctx.data().clock.now_utc());
+    let window = match parse_log_window(timeframe, from.as_deref(), to.as_deref(), today) {
+        Ok(window) => window,
+        Err(reason) => {
+            ctx.say(reason).await?;
+            return Ok(());
+        }
+    };
 
     // Show media type selection
-    let embed = create_media_selection_embed(timeframe_str, &ctx.author().name);
-    let components = create_media_selection_buttons(timeframe_str);
+    let embed = create_media_selection_embed(window, &ctx.author().name);
+    let components = create_media_selection_buttons(window);
 
     let reply = ctx
         .send(
@@ -86,19 +318,122 @@ pub async fn log(
     let msg = reply.message().await?.into_owned();
 
     // Handle button interactions
-    handle_log_interactions(ctx, &msg, timeframe_str).await?;
+    handle_log_interactions(ctx, &msg, window).await?;
+
+    Ok(())
+}
+
+/// Restore logs deleted in the last 7 days
+#[poise::command(slash_command, prefix_command)]
+pub async fn trash(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let data = ctx.data();
+    let user_id = ctx.author().id.get().to_string();
+    let username = ctx.author().name.clone();
+
+    let mut logs = fetch_trash(data, &user_id).await;
+
+    let reply = ctx
+        .send(
+            poise::CreateReply::default()
+                .embed(create_trash_embed(&logs, &username))
+                .components(create_trash_buttons(&logs))
+                .ephemeral(true),
+        )
+        .await?;
+    let msg = reply.message().await?.into_owned();
+
+    let mut collector = msg
+        .await_component_interactions(ctx.serenity_context())
+        .timeout(std::time::Duration::from_secs(60))
+        .author_id(ctx.author().id)
+        .stream();
+
+    while let Some(interaction) = collector.next().await {
+        let Some(log_id) = interaction.data.custom_id.strip_prefix("log_restore_") else {
+            continue;
+        };
+        let Some(pos) = logs.iter().position(|l| l.id == log_id) else {
+            continue;
+        };
+
+        let result = restore_log_in_firebase(
+            &*data.firebase,
+            &user_id,
+            log_id,
+            &logs[pos].activity,
+            &data.clock.now_utc().to_rfc3339(),
+        )
+        .await;
+        // A log someone else already restored just drops off the list below
+        let failed = result
+            .as_ref()
+            .err()
+            .filter(|e| e.downcast_ref::<LogMoved>().is_none());
+        if let Some(e) = failed {
+            error!("Failed to restore log: {:?}", e);
+            let _ = interaction
+                .create_response(
+                    ctx.http(),
+                    serenity::CreateInteractionResponse::Message(
+                        serenity::CreateInteractionResponseMessage::new()
+                            .content(
+                                "Failed to restore the log. Nothing was changed, please try again.",
+                            )
+                            .ephemeral(true),
+                    ),
+                )
+                .await;
+            continue;
+        }
+
+        let restored = logs.remove(pos);
+        let _ = interaction
+            .create_response(
+                ctx.http(),
+                serenity::CreateInteractionResponse::UpdateMessage(
+                    serenity::CreateInteractionResponseMessage::new()
+                        .embed(create_trash_embed(&logs, &username))
+                        .components(create_trash_buttons(&logs)),
+                ),
+            )
+            .await;
+        let notice = if result.is_ok() {
+            format!(
+                "Restored log: **{} {} of {}**",
+                restored.activity.amount, restored.activity.unit, restored.activity.type_label
+            )
+        } else {
+            "That log isn't in the trash anymore, nothing was changed.".to_string()
+        };
+        let _ = interaction
+            .create_followup(
+                ctx.http(),
+                serenity::CreateInteractionResponseFollowup::new()
+                    .content(notice)
+                    .ephemeral(true),
+            )
+            .await;
+    }
+
+    let _ = ctx
+        .http()
+        .edit_message(
+            msg.channel_id,
+            msg.id,
+            &serenity::EditMessage::new().components(vec![]),
+            vec![],
+        )
+        .await;
 
     Ok(())
 }
 
 // ============ Embed Builders ============
 
-fn create_media_selection_embed(timeframe: &str, username: &str) -> serenity::CreateEmbed {
-    let timeframe_label = if timeframe == "24h" {
-        "Last 24 Hours"
-    } else {
-        "Last 7 Days"
-    };
+fn create_media_selection_embed(window: LogWindow, username: &str) -> serenity::CreateEmbed {
+    let timeframe_label = window.label();
 
     serenity::CreateEmbed::new()
         .color(0x2b2d31)
@@ -126,7 +461,8 @@ fn create_media_selection_embed(timeframe: &str, username: &str) -> serenity::Cr
         .timestamp(Utc::now())
 }
 
-fn create_media_selection_buttons(timeframe: &str) -> Vec<serenity::CreateActionRow> {
+fn create_media_selection_buttons(window: LogWindow) -> Vec<serenity::CreateActionRow> {
+    let timeframe = window.token();
     let row1 = serenity::CreateActionRow::Buttons(vec![
         serenity::CreateButton::new(format!("log_media_visual_novel_{}", timeframe))
             .label("Visual Novel")
@@ -164,15 +500,11 @@ fn create_log_embed(
     logs: &[ImmersionLog],
     page: usize,
     total_pages: usize,
-    timeframe: &str,
+    window: LogWindow,
     media_type: Option<&str>,
     username: &str,
 ) -> serenity::CreateEmbed {
-    let timeframe_label = if timeframe == "24h" {
-        "Last 24 Hours"
-    } else {
-        "Last 7 Days"
-    };
+    let timeframe_label = window.label();
     let media_label = media_type
         .map(|m| get_media_label(m).to_string())
         .unwrap_or_else(|| "All Types".to_string());
@@ -199,40 +531,40 @@ fn create_log_embed(
             media_label
         ));
     } else {
-        let mut description = format!("**{}**\n\n", media_label);
-
-        for (i, log) in page_logs.iter().enumerate() {
-            let log_num = start_idx + i + 1;
-            let activity = &log.activity;
-            let wib_offset = chrono::FixedOffset::east_opt(7 * 3600).unwrap();
-            let time = log
-                .timestamps
-                .created
-                .with_timezone(&wib_offset)
-                .format("%Y-%m-%d %H:%M")
-                .to_string();
-
-            let title_line = if let Some(ref title) = activity.title {
-                if title != "-" && !title.is_empty() {
-                    // Use char-based truncation to avoid panic on multi-byte UTF-8
-                    let truncated: String = if title.chars().count() > 50 {
-                        format!("{}...", title.chars().take(50).collect::<String>())
-                    } else {
-                        title.clone()
-                    };
-                    format!("*{}*\n", truncated)
-                } else {
-                    String::new()
-                }
-            } else {
-                String::new()
-            };
+        let header = format!("**{}**\n\n", media_label);
+        let entries: Vec<String> = page_logs
+            .iter()
+            .enumerate()
+            .map(|(i, log)| {
+                let log_num = start_idx + i + 1;
+                let activity = &log.activity;
+                let time = local_datetime_in(None, log.timestamps.created)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string();
+
+                let title_line = match activity.title {
+                    Some(ref title) if title != "-" && !title.is_empty() => {
+                        format!("*{}*\n", truncate_chars(title, 53))
+                    }
+                    _ => String::new(),
+                };
 
-            description.push_str(&format!(
-                "**{}.** {} {} of {}\n{}{}\n\n",
-                log_num, activity.amount, activity.unit, activity.type_label, title_line, time
-            ));
-        }
+                let lock = if log.metadata.private { " 🔒" } else { "" };
+
+                format!(
+                    "**{}.**{} {} {} of {}\n{}{}\n",
+                    log_num,
+                    lock,
+                    activity.amount,
+                    activity.unit,
+                    activity.type_label,
+                    title_line,
+                    time
+                )
+            })
+            .collect();
+        let room = EMBED_DESCRIPTION_LIMIT.saturating_sub(header.chars().count());
+        let description = format!("{}{}", header, fit_description(entries, room).0);
 
         embed = embed.description(description);
     }
@@ -243,12 +575,13 @@ fn create_log_embed(
 fn create_navigation_buttons(
     page: usize,
     total_pages: usize,
-    timeframe: &str,
+    window: LogWindow,
     media_type: Option<&str>,
     logs: &[ImmersionLog],
 ) -> Vec<serenity::CreateActionRow> {
     let mut rows = Vec::new();
     let media = media_type.unwrap_or("all");
+    let timeframe = window.token();
 
     // Navigation row
     let nav_buttons = vec![
@@ -267,22 +600,25 @@ fn create_navigation_buttons(
         serenity::CreateButton::new(format!("log_back_{}", timeframe))
             .label("Back to Selection")
             .style(serenity::ButtonStyle::Secondary),
+        serenity::CreateButton::new("log_bulk_delete")
+            .label("Delete All on Date")
+            .style(serenity::ButtonStyle::Danger),
     ];
     rows.push(serenity::CreateActionRow::Buttons(nav_buttons));
 
-    // Delete buttons for current page logs
+    // Delete and edit buttons for current page logs (at most 2 rows each, 5 rows total)
     let start_idx = page * LOGS_PER_PAGE;
     let end_idx = (start_idx + LOGS_PER_PAGE).min(logs.len());
     let page_logs = &logs[start_idx..end_idx];
 
     if !page_logs.is_empty() {
         // Max 5 buttons per row
-        for chunk in page_logs.chunks(5) {
+        for (chunk_idx, chunk) in page_logs.chunks(5).enumerate() {
             let delete_buttons: Vec<serenity::CreateButton> = chunk
                 .iter()
                 .enumerate()
                 .map(|(i, log)| {
-                    let global_idx = start_idx + i + 1;
+                    let global_idx = start_idx + chunk_idx * 5 + i + 1;
                     serenity::CreateButton::new(format!("log_delete_{}", log.id))
                         .label(format!("Delete {}", global_idx))
                         .style(serenity::ButtonStyle::Danger)
@@ -290,17 +626,179 @@ fn create_navigation_buttons(
                 .collect();
             rows.push(serenity::CreateActionRow::Buttons(delete_buttons));
         }
+
+        for (chunk_idx, chunk) in page_logs.chunks(5).enumerate() {
+            let edit_buttons: Vec<serenity::CreateButton> = chunk
+                .iter()
+                .enumerate()
+                .map(|(i, log)| {
+                    let global_idx = start_idx + chunk_idx * 5 + i + 1;
+                    serenity::CreateButton::new(format!("log_edit_{}", log.id))
+                        .label(format!("Edit {}", global_idx))
+                        .style(serenity::ButtonStyle::Secondary)
+                })
+                .collect();
+            rows.push(serenity::CreateActionRow::Buttons(edit_buttons));
+        }
     }
 
     rows
 }
 
+fn create_trash_embed(logs: &[ImmersionLog], username: &str) -> serenity::CreateEmbed {
+    let mut embed = serenity::CreateEmbed::new()
+        .color(0x2b2d31)
+        .title("Deleted Logs")
+        .footer(serenity::CreateEmbedFooter::new(format!(
+            "{} • Deleted logs are removed for good after {} days",
+            username, TRASH_RETENTION_DAYS
+        )))
+        .timestamp(Utc::now());
+
+    if logs.is_empty() {
+        return embed.description("_Nothing in the trash._");
+    }
+
+    let mut description = String::new();
+    for (i, log) in logs.iter().take(LOGS_PER_PAGE).enumerate() {
+        let deleted = log
+            .deleted_at
+            .map(|at| format!("<t:{}:R>", at.timestamp()))
+            .unwrap_or_else(|| "unknown".to_string());
+        description.push_str(&format!(
+            "**{}.** {} {} of {} ({})\nDeleted {}\n\n",
+            i + 1,
+            log.activity.amount,
+            log.activity.unit,
+            log.activity.type_label,
+            log.activity_date(),
+            deleted
+        ));
+    }
+    if logs.len() > LOGS_PER_PAGE {
+        description.push_str(&format!(
+            "_...and {} more. Restore some to see the rest._",
+            logs.len() - LOGS_PER_PAGE
+        ));
+    }
+
+    embed = embed.description(description);
+    embed
+}
+
+fn create_trash_buttons(logs: &[ImmersionLog]) -> Vec<serenity::CreateActionRow> {
+    let page_logs = &logs[..logs.len().min(LOGS_PER_PAGE)];
+    page_logs
+        .chunks(5)
+        .enumerate()
+        .map(|(chunk_idx, chunk)| {
+            let buttons = chunk
+                .iter()
+                .enumerate()
+                .map(|(i, log)| {
+                    serenity::CreateButton::new(format!("log_restore_{}", log.id))
+                        .label(format!("Restore {}", chunk_idx * 5 + i + 1))
+                        .style(serenity::ButtonStyle::Success)
+                })
+                .collect();
+            serenity::CreateActionRow::Buttons(buttons)
+        })
+        .collect()
+}
+
+/// Confirmation for an edit, listing each changed field as old → new
+fn create_edit_embed(old: &ImmersionLog, new: &ImmersionLog) -> serenity::CreateEmbed {
+    fn show(value: Option<&str>) -> String {
+        match value {
+            Some(v) if !v.is_empty() && v != "-" => v.to_string(),
+            _ => "_none_".to_string(),
+        }
+    }
+
+    let mut embed = serenity::CreateEmbed::new()
+        .color(colors::SUCCESS)
+        .title(format!("{} Log Updated", new.activity.type_label));
+
+    let mut changed = false;
+    if old.activity.amount != new.activity.amount {
+        embed = embed.field(
+            "Amount",
+            format!(
+                "{} → {} {}",
+                old.activity.amount, new.activity.amount, new.activity.unit
+            ),
+            false,
+        );
+        changed = true;
+    }
+    if old.activity.title != new.activity.title {
+        embed = embed.field(
+            "Title",
+            format!(
+                "{} → {}",
+                show(old.activity.title.as_deref()),
+                show(new.activity.title.as_deref())
+            ),
+            false,
+        );
+        changed = true;
+    }
+    if old.activity_date() != new.activity_date() {
+        embed = embed.field(
+            "Date",
+            format!("{} → {}", old.activity_date(), new.activity_date()),
+            false,
+        );
+        changed = true;
+    }
+    if old.activity.comment != new.activity.comment {
+        let preview = |c: Option<&str>| {
+            let text = show(c);
+            if text.chars().count() > 200 {
+                format!("{}...", text.chars().take(200).collect::<String>())
+            } else {
+                text
+            }
+        };
+        embed = embed.field(
+            "Comment",
+            format!(
+                "{} → {}",
+                preview(old.activity.comment.as_deref()),
+                preview(new.activity.comment.as_deref())
+            ),
+            false,
+        );
+        changed = true;
+    }
+
+    if old.metadata.private != new.metadata.private {
+        let show = |private: bool| if private { "🔒 private" } else { "public" };
+        embed = embed.field(
+            "Visibility",
+            format!(
+                "{} → {}",
+                show(old.metadata.private),
+                show(new.metadata.private)
+            ),
+            false,
+        );
+        changed = true;
+    }
+
+    if !changed {
+        embed = embed.description("Nothing changed.");
+    }
+
+    embed
+}
+
 // ============ Interaction Handler ============
 
 async fn handle_log_interactions(
     ctx: Context<'_>,
     msg: &serenity::Message,
-    initial_timeframe: &str,
+    initial_window: LogWindow,
 ) -> Result<(), Error> {
     let data = ctx.data();
     let user_id = ctx.author().id.get().to_string();
@@ -312,7 +810,7 @@ async fn handle_log_interactions(
         .author_id(ctx.author().id)
         .stream();
 
-    let mut current_timeframe = initial_timeframe.to_string();
+    let mut current_window = initial_window;
     let mut current_media: Option<String> = None;
     let mut current_page: usize = 0;
     let mut current_logs: Vec<ImmersionLog> = Vec::new();
@@ -321,49 +819,34 @@ async fn handle_log_interactions(
         let custom_id = &interaction.data.custom_id;
         debug!("Log button interaction: {}", custom_id);
 
-        if custom_id.starts_with("log_media_") {
-            // Media type selection
-            let parts: Vec<&str> = custom_id.split('_').collect();
-
-            // Parse media type and timeframe from button ID
-            let (media_type, timeframe) =
-                if parts.len() >= 5 && parts[2] == "reading" && parts[3] == "time" {
-                    (Some("reading_time".to_string()), parts[4].to_string())
-                } else if parts.len() >= 5 && parts[2] == "visual" && parts[3] == "novel" {
-                    (Some("visual_novel".to_string()), parts[4].to_string())
-                } else if parts.len() >= 4 {
-                    let mt = if parts[2] == "all" {
-                        None
-                    } else {
-                        Some(parts[2].to_string())
-                    };
-                    (mt, parts[3].to_string())
-                } else {
-                    (None, current_timeframe.clone())
-                };
-
-            current_timeframe = timeframe;
+        if let Some(rest) = custom_id.strip_prefix("log_media_") {
+            // Media type selection, the window token is the last part of the button ID
+            let (media, token) = rest.rsplit_once('_').unwrap_or((rest, ""));
+            let media_type = (media != "all").then(|| media.to_string());
+            if let Some(window) = LogWindow::from_token(token) {
+                current_window = window;
+            }
             current_media = media_type;
             current_page = 0;
 
             // Fetch logs from Firebase
             current_logs =
-                fetch_user_logs(data, &user_id, &current_timeframe, current_media.as_deref()).await;
+                fetch_user_logs(data, &user_id, current_window, current_media.as_deref()).await;
 
-            let total_pages = (current_logs.len() + LOGS_PER_PAGE - 1) / LOGS_PER_PAGE;
+            let total_pages = current_logs.len().div_ceil(LOGS_PER_PAGE);
             let total_pages = if total_pages == 0 { 1 } else { total_pages };
 
             let embed = create_log_embed(
                 &current_logs,
                 current_page,
                 total_pages,
-                &current_timeframe,
+                current_window,
                 current_media.as_deref(),
                 &username,
             );
             let components = if current_logs.is_empty() {
                 vec![serenity::CreateActionRow::Buttons(vec![
-                    serenity::CreateButton::new(format!("log_back_{}", current_timeframe))
+                    serenity::CreateButton::new(format!("log_back_{}", current_window.token()))
                         .label("Back to Selection")
                         .style(serenity::ButtonStyle::Secondary),
                 ])]
@@ -371,7 +854,7 @@ async fn handle_log_interactions(
                 create_navigation_buttons(
                     current_page,
                     total_pages,
-                    &current_timeframe,
+                    current_window,
                     current_media.as_deref(),
                     &current_logs,
                 )
@@ -389,13 +872,15 @@ async fn handle_log_interactions(
                 .await;
         } else if custom_id.starts_with("log_back_") {
             // Back to media selection
-            let timeframe = custom_id
+            if let Some(window) = custom_id
                 .strip_prefix("log_back_")
-                .unwrap_or(&current_timeframe);
-            current_timeframe = timeframe.to_string();
+                .and_then(LogWindow::from_token)
+            {
+                current_window = window;
+            }
 
-            let embed = create_media_selection_embed(&current_timeframe, &username);
-            let components = create_media_selection_buttons(&current_timeframe);
+            let embed = create_media_selection_embed(current_window, &username);
+            let components = create_media_selection_buttons(current_window);
 
             let _ = interaction
                 .create_response(
@@ -409,8 +894,9 @@ async fn handle_log_interactions(
                 .await;
         } else if custom_id.starts_with("log_prev_") || custom_id.starts_with("log_next_") {
             // Pagination
-            let parts: Vec<&str> = custom_id.split('_').collect();
-            if parts.len() >= 5 {
+            // log_prev_{page}_{window}_{media}, the media type may contain underscores
+            let parts: Vec<&str> = custom_id.splitn(5, '_').collect();
+            if parts.len() == 5 {
                 let old_page: usize = parts[2].parse().unwrap_or(0);
                 let is_next = custom_id.starts_with("log_next_");
 
@@ -419,28 +905,30 @@ async fn handle_log_interactions(
                 } else {
                     old_page.saturating_sub(1)
                 };
-                current_timeframe = parts[3].to_string();
+                if let Some(window) = LogWindow::from_token(parts[3]) {
+                    current_window = window;
+                }
                 current_media = if parts[4] == "all" {
                     None
                 } else {
                     Some(parts[4].to_string())
                 };
 
-                let total_pages = (current_logs.len() + LOGS_PER_PAGE - 1) / LOGS_PER_PAGE;
+                let total_pages = current_logs.len().div_ceil(LOGS_PER_PAGE);
                 let total_pages = if total_pages == 0 { 1 } else { total_pages };
 
                 let embed = create_log_embed(
                     &current_logs,
                     current_page,
                     total_pages,
-                    &current_timeframe,
+                    current_window,
                     current_media.as_deref(),
                     &username,
                 );
                 let components = create_navigation_buttons(
                     current_page,
                     total_pages,
-                    &current_timeframe,
+                    current_window,
                     current_media.as_deref(),
                     &current_logs,
                 );
@@ -456,90 +944,259 @@ async fn handle_log_interactions(
                     )
                     .await;
             }
-        } else if custom_id.starts_with("log_delete_") {
-            // Delete log
-            let log_id = custom_id.strip_prefix("log_delete_").unwrap_or("");
+        } else if custom_id.starts_with("log_edit_") {
+            // Edit log through a modal
+            let log_id = custom_id
+                .strip_prefix("log_edit_")
+                .unwrap_or("")
+                .to_string();
+            let Some(pos) = current_logs.iter().position(|l| l.id == log_id) else {
+                continue;
+            };
 
-            if let Some(pos) = current_logs.iter().position(|l| l.id == log_id) {
-                let deleted_log = current_logs.remove(pos);
+            let defaults = EditLogModal::from_log(&current_logs[pos]);
+            let submitted = poise::execute_modal_on_component_interaction(
+                ctx,
+                interaction.clone(),
+                Some(defaults),
+                Some(std::time::Duration::from_secs(120)),
+            )
+            .await?;
+            let Some(modal) = submitted else {
+                continue;
+            };
 
-                // Delete from Firebase
-                if let Err(e) =
-                    delete_log_from_firebase(data, &user_id, log_id, &deleted_log.activity).await
-                {
-                    error!("Failed to delete log: {:?}", e);
+            let today = effective_date_at(data.clock.now_utc());
+            let edit = match parse_log_edit(&modal, today) {
+                Ok(edit) => edit,
+                Err(reason) => {
+                    let _ = interaction
+                        .create_followup(
+                            ctx.http(),
+                            serenity::CreateInteractionResponseFollowup::new()
+                                .content(reason)
+                                .ephemeral(true),
+                        )
+                        .await;
+                    continue;
                 }
+            };
 
-                // Respond with confirmation
+            let old_log = current_logs[pos].clone();
+            if let Err(e) = edit_log_in_firebase(data, &user_id, &log_id, &edit).await {
+                error!("Failed to edit log: {:?}", e);
                 let _ = interaction
-                    .create_response(
+                    .create_followup(
                         ctx.http(),
-                        serenity::CreateInteractionResponse::Message(
-                            serenity::CreateInteractionResponseMessage::new()
-                                .content(format!(
-                                    "Deleted log: **{} {} of {}**{}",
-                                    deleted_log.activity.amount,
-                                    deleted_log.activity.unit,
-                                    deleted_log.activity.type_label,
-                                    deleted_log
-                                        .activity
-                                        .title
-                                        .as_ref()
-                                        .filter(|t| t != &"-" && !t.is_empty())
-                                        .map(|t| format!(" - {}", t))
-                                        .unwrap_or_default()
-                                ))
-                                .ephemeral(true),
-                        ),
+                        serenity::CreateInteractionResponseFollowup::new()
+                            .content(
+                                "Failed to edit the log. Nothing was changed, please try again.",
+                            )
+                            .ephemeral(true),
                     )
                     .await;
+                continue;
+            }
 
-                // Update the view
-                let total_pages = (current_logs.len() + LOGS_PER_PAGE - 1) / LOGS_PER_PAGE;
-                let total_pages = if total_pages == 0 { 1 } else { total_pages };
+            // Keep the local copy in sync
+            let log = &mut current_logs[pos];
+            log.activity.amount = edit.amount;
+            log.activity.title = Some(edit.title.clone());
+            log.activity.comment = edit.comment.clone();
+            log.timestamps.date = Some(edit.date.format("%Y-%m-%d").to_string());
+            log.metadata.private = edit.private;
 
-                // Adjust page if needed
-                if current_page >= total_pages && current_page > 0 {
-                    current_page = total_pages - 1;
+            let _ = interaction
+                .create_followup(
+                    ctx.http(),
+                    serenity::CreateInteractionResponseFollowup::new()
+                        .embed(create_edit_embed(&old_log, &current_logs[pos]))
+                        .ephemeral(true),
+                )
+                .await;
+
+            let total_pages = current_logs.len().div_ceil(LOGS_PER_PAGE).max(1);
+            let embed = create_log_embed(
+                &current_logs,
+                current_page,
+                total_pages,
+                current_window,
+                current_media.as_deref(),
+                &username,
+            );
+            let components = create_navigation_buttons(
+                current_page,
+                total_pages,
+                current_window,
+                current_media.as_deref(),
+                &current_logs,
+            );
+
+            let _ = ctx
+                .http()
+                .edit_message(
+                    msg.channel_id,
+                    msg.id,
+                    &serenity::EditMessage::new()
+                        .embed(embed)
+                        .components(components),
+                    vec![],
+                )
+                .await;
+        } else if custom_id == "log_bulk_delete" {
+            // Delete every log of one day, the date comes from a modal
+            let submitted = poise::execute_modal_on_component_interaction::<BulkDeleteModal>(
+                ctx,
+                interaction.clone(),
+                None,
+                Some(std::time::Duration::from_secs(120)),
+            )
+            .await?;
+            let Some(modal) = submitted else {
+                continue;
+            };
+            let Some(date) = parse_custom_date(modal.date.trim()) else {
+                followup_ephemeral(ctx, &interaction, INVALID_DATE_MESSAGE).await;
+                continue;
+            };
+            let date = date.format("%Y-%m-%d").to_string();
+
+            let day_logs = match fetch_user_logs_on(data, &user_id, &date).await {
+                Ok(logs) => logs,
+                Err(e) => {
+                    error!("Failed to fetch logs on {}: {:?}", date, e);
+                    followup_ephemeral(
+                        ctx,
+                        &interaction,
+                        "Failed to load the logs of that day. Please try again.",
+                    )
+                    .await;
+                    continue;
                 }
+            };
+            if day_logs.is_empty() {
+                followup_ephemeral(ctx, &interaction, &format!("You have no logs on {}.", date))
+                    .await;
+                continue;
+            }
+            if day_logs.len() > BULK_DELETE_MAX {
+                followup_ephemeral(
+                    ctx,
+                    &interaction,
+                    &format!(
+                        "You have {} logs on {}, but at most {} can be deleted at once. Delete some of them one by one first.",
+                        day_logs.len(),
+                        date,
+                        BULK_DELETE_MAX
+                    ),
+                )
+                .await;
+                continue;
+            }
 
-                let embed = create_log_embed(
-                    &current_logs,
-                    current_page,
-                    total_pages,
-                    &current_timeframe,
-                    current_media.as_deref(),
-                    &username,
-                );
-                let components = if current_logs.is_empty() {
-                    vec![serenity::CreateActionRow::Buttons(vec![
-                        serenity::CreateButton::new(format!("log_back_{}", current_timeframe))
-                            .label("Back to Selection")
-                            .style(serenity::ButtonStyle::Secondary),
-                    ])]
+            let prompt = format!(
+                "Delete all **{}** logs on **{}**? ({})\nThey stay in `/log trash` for {} days.",
+                day_logs.len(),
+                date,
+                media_counts_text(&day_logs),
+                TRASH_RETENTION_DAYS
+            );
+            let Some(confirmed) = confirm_delete(ctx, &interaction, prompt).await? else {
+                continue;
+            };
+
+            let result = bulk_delete_logs_from_firebase(
+                &*data.firebase,
+                &user_id,
+                &date,
+                &day_logs,
+                &data.clock.now_utc().to_rfc3339(),
+            )
+            .await;
+            if let Err(e) = result {
+                let message = if e.downcast_ref::<LogMoved>().is_some() {
+                    "Some of those logs were changed since this list was shown. Nothing was \
+                     deleted, open `/log` again to see them as they are."
                 } else {
-                    create_navigation_buttons(
-                        current_page,
-                        total_pages,
-                        &current_timeframe,
-                        current_media.as_deref(),
-                        &current_logs,
-                    )
+                    error!("Failed to delete logs on {}: {:?}", date, e);
+                    "Failed to delete the logs. Nothing was changed, please try again."
                 };
+                finish_confirmation(ctx, &confirmed, message).await;
+                continue;
+            }
+            finish_confirmation(
+                ctx,
+                &confirmed,
+                &format!(
+                    "Deleted {} logs on {}: {}",
+                    day_logs.len(),
+                    date,
+                    media_counts_text(&day_logs)
+                ),
+            )
+            .await;
+
+            current_logs.retain(|log| !day_logs.iter().any(|d| d.id == log.id));
+            redraw_log_view(
+                ctx,
+                msg,
+                &current_logs,
+                &mut current_page,
+                current_window,
+                current_media.as_deref(),
+                &username,
+            )
+            .await;
+        } else if let Some(log_id) = custom_id.strip_prefix("log_delete_") {
+            // Delete log, after the member confirms it
+            let Some(pos) = current_logs.iter().position(|l| l.id == log_id) else {
+                continue;
+            };
 
-                // Edit original message
-                let _ = ctx
-                    .http()
-                    .edit_message(
-                        msg.channel_id,
-                        msg.id,
-                        &serenity::EditMessage::new()
-                            .embed(embed)
-                            .components(components),
-                        vec![],
-                    )
-                    .await;
+            let _ = interaction
+                .create_response(ctx.http(), serenity::CreateInteractionResponse::Acknowledge)
+                .await;
+            let prompt = format!(
+                "Delete **{}**?\nIt stays in `/log trash` for {} days.",
+                log_summary(&current_logs[pos]),
+                TRASH_RETENTION_DAYS
+            );
+            let Some(confirmed) = confirm_delete(ctx, &interaction, prompt).await? else {
+                continue;
+            };
+
+            // Delete from Firebase first so the view never shows a half-applied delete
+            if let Err(e) =
+                delete_log_from_firebase(data, &user_id, log_id, &current_logs[pos].activity).await
+            {
+                error!("Failed to delete log: {:?}", e);
+                finish_confirmation(
+                    ctx,
+                    &confirmed,
+                    "Failed to delete the log. Nothing was changed, please try again.",
+                )
+                .await;
+                continue;
             }
+
+            let deleted_log = current_logs.remove(pos);
+            finish_confirmation(
+                ctx,
+                &confirmed,
+                &format!("Deleted log: **{}**", log_summary(&deleted_log)),
+            )
+            .await;
+
+            redraw_log_view(
+                ctx,
+                msg,
+                &current_logs,
+                &mut current_page,
+                current_window,
+                current_media.as_deref(),
+                &username,
+            )
+            .await;
         }
     }
 
@@ -547,7 +1204,7 @@ async fn handle_log_interactions(
     let expired_embed = serenity::CreateEmbed::new()
         .color(0x5865f2)
         .title("Session Expired")
-        .description("This immersion log session has expired due to inactivity.\n\nUse `/log` to start a new session.")
+        .description("This immersion log session has expired due to inactivity.\n\nUse `/log view` to start a new session.")
         .footer(serenity::CreateEmbedFooter::new("Session automatically closed after 60 seconds"))
         .timestamp(Utc::now());
 
@@ -566,131 +1223,1251 @@ async fn handle_log_interactions(
     Ok(())
 }
 
+/// Send an ephemeral follow-up to a component interaction that was already answered
+async fn followup_ephemeral(
+    ctx: Context<'_>,
+    interaction: &serenity::ComponentInteraction,
+    content: &str,
+) {
+    let _ = interaction
+        .create_followup(
+            ctx.http(),
+            serenity::CreateInteractionResponseFollowup::new()
+                .content(content)
+                .ephemeral(true),
+        )
+        .await;
+}
+
+/// How long the Confirm/Cancel prompt waits for a click
+const CONFIRM_TIMEOUT_SECS: u64 = 30;
+
+/// Ask for confirmation in an ephemeral follow-up with Confirm/Cancel buttons.
+/// Returns the Confirm click, still to be answered with `finish_confirmation`, or `None`
+/// when the member cancelled or didn't click in time.
+async fn confirm_delete(
+    ctx: Context<'_>,
+    interaction: &serenity::ComponentInteraction,
+    prompt: String,
+) -> Result<Option<serenity::ComponentInteraction>, Error> {
+    let buttons = vec![serenity::CreateActionRow::Buttons(vec![
+        serenity::CreateButton::new("log_confirm")
+            .label("Confirm")
+            .style(serenity::ButtonStyle::Danger),
+        serenity::CreateButton::new("log_cancel")
+            .label("Cancel")
+            .style(serenity::ButtonStyle::Secondary),
+    ])];
+    let prompt_msg = interaction
+        .create_followup(
+            ctx.http(),
+            serenity::CreateInteractionResponseFollowup::new()
+                .content(prompt)
+                .components(buttons)
+                .ephemeral(true),
+        )
+        .await?;
+
+    let click = prompt_msg
+        .await_component_interaction(ctx.serenity_context())
+        .author_id(ctx.author().id)
+        .timeout(std::time::Duration::from_secs(CONFIRM_TIMEOUT_SECS))
+        .await;
+    match click {
+        Some(click) if click.data.custom_id == "log_confirm" => Ok(Some(click)),
+        Some(click) => {
+            finish_confirmation(ctx, &click, "Cancelled, nothing was deleted.").await;
+            Ok(None)
+        }
+        None => {
+            let _ = interaction
+                .edit_followup(
+                    ctx.http(),
+                    prompt_msg.id,
+                    serenity::CreateInteractionResponseFollowup::new()
+                        .content("Timed out, nothing was deleted.")
+                        .components(vec![]),
+                )
+                .await;
+            Ok(None)
+        }
+    }
+}
+
+/// Replace the confirmation prompt with the outcome
+async fn finish_confirmation(
+    ctx: Context<'_>,
+    click: &serenity::ComponentInteraction,
+    content: &str,
+) {
+    let _ = click
+        .create_response(
+            ctx.http(),
+            serenity::CreateInteractionResponse::UpdateMessage(
+                serenity::CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .components(vec![]),
+            ),
+        )
+        .await;
+}
+
+/// Show the logs left after a delete, moving back a page when the current one emptied
+async fn redraw_log_view(
+    ctx: Context<'_>,
+    msg: &serenity::Message,
+    logs: &[ImmersionLog],
+    page: &mut usize,
+    window: LogWindow,
+    media_type: Option<&str>,
+    username: &str,
+) {
+    let total_pages = logs.len().div_ceil(LOGS_PER_PAGE).max(1);
+    if *page >= total_pages {
+        *page = total_pages - 1;
+    }
+
+    let embed = create_log_embed(logs, *page, total_pages, window, media_type, username);
+    let components = if logs.is_empty() {
+        vec![serenity::CreateActionRow::Buttons(vec![
+            serenity::CreateButton::new(format!("log_back_{}", window.token()))
+                .label("Back to Selection")
+                .style(serenity::ButtonStyle::Secondary),
+        ])]
+    } else {
+        create_navigation_buttons(*page, total_pages, window, media_type, logs)
+    };
+
+    let _ = ctx
+        .http()
+        .edit_message(
+            msg.channel_id,
+            msg.id,
+            &serenity::EditMessage::new()
+                .embed(embed)
+                .components(components),
+            vec![],
+        )
+        .await;
+}
+
+/// "3 episodes of Anime - Frieren", the title left out when there is none
+fn log_summary(log: &ImmersionLog) -> String {
+    format!(
+        "{} {} of {}{}",
+        log.activity.amount,
+        log.activity.unit,
+        log.activity.type_label,
+        log.activity
+            .title
+            .as_ref()
+            .filter(|t| t != &"-" && !t.is_empty())
+            .map(|t| format!(" - {}", t))
+            .unwrap_or_default()
+    )
+}
+
+/// How many of the logs are of each media type, e.g. "Anime: 2, Reading: 1"
+fn media_counts_text(logs: &[ImmersionLog]) -> String {
+    let mut counts = std::collections::BTreeMap::new();
+    for log in logs {
+        let label = if log.activity.type_label.is_empty() {
+            get_media_label(&log.activity.activity_type).to_string()
+        } else {
+            log.activity.type_label.clone()
+        };
+        *counts.entry(label).or_insert(0usize) += 1;
+    }
+    counts
+        .iter()
+        .map(|(label, count)| format!("{}: {}", label, count))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 // ============ Firebase Functions ============
 
-/// Maximum logs to fetch per query (server-side limit)
-const MAX_LOGS_PER_QUERY: usize = 100;
+/// Most logs shown in one view
+const MAX_LOGS_PER_QUERY: usize = 250;
 
+/// Logs per Firestore page while filling a view
+const FETCH_PAGE_SIZE: usize = 100;
+
+/// Logs in the window, newest first. Only the time range is filtered by Firestore,
+/// adding the media type would need a composite index.
 async fn fetch_user_logs(
     data: &crate::Data,
     user_id: &str,
-    timeframe: &str,
+    window: LogWindow,
     media_type: Option<&str>,
 ) -> Vec<ImmersionLog> {
-    let now = Utc::now();
-    let start_date = if timeframe == "24h" {
-        now - Duration::hours(24)
-    } else {
-        now - Duration::days(7)
-    };
+    let (start, end) = window.bounds(data.clock.now_utc());
+    // Stored with `to_rfc3339`, so the bounds compare as strings
+    let (start, end) = (start.to_rfc3339(), end.map(|e| e.to_rfc3339()));
+
+    let mut logs = Vec::new();
+    let mut cursor: Option<LogCursor> = None;
+    loop {
+        let page = data
+            .firebase
+            .get_user_logs_created_between(
+                user_id,
+                &start,
+                end.as_deref(),
+                FETCH_PAGE_SIZE,
+                cursor.as_ref(),
+            )
+            .await;
+        let (docs, next) = match page {
+            Ok(page) => page,
+            Err(e) => {
+                error!("Failed to fetch immersion logs: {:?}", e);
+                break;
+            }
+        };
 
-    // Query Firebase using existing method (no composite index required)
-    // TODO: Once Firestore indexes are created, switch to run_query for efficiency
-    match data
-        .firebase
-        .query_subcollection_with_ids("users", user_id, "immersion_logs")
-        .await
-    {
-        Ok(docs) => {
-            let mut logs: Vec<ImmersionLog> = docs
-                .into_iter()
-                .filter_map(|(id, value)| {
-                    let mut log: ImmersionLog = serde_json::from_value(value).ok()?;
-                    log.id = id;
-
-                    // Filter by time
-                    if log.timestamps.created < start_date {
-                        return None;
-                    }
+        logs.extend(docs.into_iter().filter_map(|(id, value)| {
+            let mut log: ImmersionLog = serde_json::from_value(value).ok()?;
+            log.id = id;
 
-                    // Filter by media type
-                    if let Some(mt) = media_type {
-                        if log.activity.activity_type != mt {
-                            return None;
-                        }
-                    }
+            // Filter by media type
+            if let Some(mt) = media_type {
+                if log.activity.activity_type != mt {
+                    return None;
+                }
+            }
 
-                    Some(log)
-                })
-                .collect();
+            Some(log)
+        }));
+
+        // Limit results to prevent memory bloat
+        if logs.len() >= MAX_LOGS_PER_QUERY {
+            break;
+        }
+        match next {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
 
-            // Sort by created date (newest first)
-            logs.sort_by(|a, b| b.timestamps.created.cmp(&a.timestamps.created));
+    // Sort by created date (newest first)
+    logs.sort_by_key(|l| std::cmp::Reverse(l.timestamps.created));
+    logs.truncate(MAX_LOGS_PER_QUERY);
+    logs
+}
 
-            // Limit results to prevent memory bloat
-            logs.truncate(MAX_LOGS_PER_QUERY);
+/// All of the user's logs, outside the trash and in no particular order
+pub(crate) async fn fetch_all_user_logs(
+    data: &crate::Data,
+    user_id: &str,
+) -> Result<Vec<ImmersionLog>, Error> {
+    let docs = data.firebase.get_user_logs_with_ids(user_id).await?;
+    Ok(docs
+        .into_iter()
+        .filter_map(|(id, value)| {
+            let mut log: ImmersionLog = serde_json::from_value(value).ok()?;
+            log.id = id;
+            Some(log)
+        })
+        .collect())
+}
 
-            logs
+/// The user's logs dated on one day, outside the trash. Legacy logs without an activity
+/// date aren't found.
+async fn fetch_user_logs_on(
+    data: &crate::Data,
+    user_id: &str,
+    date: &str,
+) -> Result<Vec<ImmersionLog>, Error> {
+    let docs = data
+        .firebase
+        .get_user_logs_on_with_ids(user_id, date)
+        .await?;
+    Ok(docs
+        .into_iter()
+        .filter_map(|(id, value)| {
+            let mut log: ImmersionLog = serde_json::from_value(value).ok()?;
+            log.id = id;
+            Some(log)
+        })
+        .collect())
+}
+
+/// Attempts for a log transaction before giving up
+const TRANSACTION_MAX_ATTEMPTS: u32 = 3;
+
+/// Run a log transaction, starting over from the reads when Firestore reports contention
+async fn retry_on_conflict<F, Fut>(
+    action: &str,
+    target: &str,
+    mut run: F,
+) -> Result<(), anyhow::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<(), anyhow::Error>>,
+{
+    use crate::api::firebase::is_transaction_conflict;
+
+    let mut attempt = 1;
+    loop {
+        match run().await {
+            Ok(()) => return Ok(()),
+            Err(e) if is_transaction_conflict(&e) && attempt < TRANSACTION_MAX_ATTEMPTS => {
+                let backoff = std::time::Duration::from_millis(200 * 2u64.pow(attempt - 1));
+                debug!(
+                    "{} transaction conflict for {} (attempt {}), retrying in {:?}",
+                    action, target, attempt, backoff
+                );
+                tokio::time::sleep(backoff).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
         }
+    }
+}
+
+/// A log isn't where an action expected it: restored, deleted or purged by another one
+/// since it was listed
+#[derive(Debug)]
+struct LogMoved;
+
+impl std::fmt::Display for LogMoved {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the log was changed by another action")
+    }
+}
+
+impl std::error::Error for LogMoved {}
+
+/// One attempt of a log transaction: `stage` reads inside it and returns the writes to
+/// commit. When staging fails nothing is committed and the transaction is rolled back.
+async fn commit_staged<F, Fut>(store: &dyn Storage, stage: F) -> Result<(), anyhow::Error>
+where
+    F: FnOnce(String) -> Fut,
+    Fut: std::future::Future<
+        Output = Result<Vec<crate::api::firebase::TransactionWrite>, anyhow::Error>,
+    >,
+{
+    let tx_id = store.begin_transaction().await?;
+    match stage(tx_id.clone()).await {
+        Ok(writes) => store.commit_transaction(&tx_id, writes).await,
         Err(e) => {
-            error!("Failed to fetch immersion logs: {:?}", e);
-            Vec::new()
+            store.abandon_transaction(&tx_id).await;
+            Err(e)
         }
     }
 }
 
+/// Move a log to the trash and take it out of the stats
 async fn delete_log_from_firebase(
     data: &crate::Data,
     user_id: &str,
     log_id: &str,
     activity: &LogActivity,
 ) -> Result<(), anyhow::Error> {
-    use crate::api::firebase::TransactionWrite;
+    retry_on_conflict("Delete", &format!("log {}", log_id), || {
+        try_delete_log(data, user_id, log_id, activity)
+    })
+    .await
+}
+
+/// One attempt of the delete: read the user doc and commit soft delete + stats update atomically
+async fn try_delete_log(
+    data: &crate::Data,
+    user_id: &str,
+    log_id: &str,
+    activity: &LogActivity,
+) -> Result<(), anyhow::Error> {
+    commit_staged(&*data.firebase, |tx_id| async move {
+        // Read user document within transaction
+        let user_doc = data
+            .firebase
+            .get_document_in_transaction(&tx_id, "users", user_id)
+            .await?;
+
+        Ok(build_delete_writes(
+            user_id,
+            log_id,
+            activity,
+            user_doc,
+            &data.clock.now_utc().to_rfc3339(),
+        ))
+    })
+    .await
+}
 
-    // Begin transaction for atomic delete + stats update
-    let tx_id = data.firebase.begin_transaction().await?;
+/// Most logs one "Delete All on Date" takes, a safety valve against clearing a busy day
+/// by mistake
+const BULK_DELETE_MAX: usize = 50;
 
-    // Read user document within transaction
-    let user_doc = data
-        .firebase
-        .get_document_in_transaction(&tx_id, "users", user_id)
-        .await?;
+/// Move all of a day's logs to the trash in one transaction. `LogMoved` when one of them
+/// was deleted or restored since the list was shown, nothing is changed then.
+async fn bulk_delete_logs_from_firebase(
+    store: &dyn Storage,
+    user_id: &str,
+    date: &str,
+    logs: &[ImmersionLog],
+    now: &str,
+) -> Result<(), anyhow::Error> {
+    let targets: Vec<(&str, &LogActivity)> = logs
+        .iter()
+        .map(|log| (log.id.as_str(), &log.activity))
+        .collect();
+    let targets = &targets;
+
+    retry_on_conflict("Bulk delete", &format!("logs on {}", date), || {
+        commit_staged(store, |tx_id| async move {
+            // Another delete, or a restore from the trash, may have got to some of them
+            for (log_id, _) in targets {
+                let log_doc = store
+                    .get_document_in_transaction(
+                        &tx_id,
+                        &format!("users/{}/immersion_logs", user_id),
+                        log_id,
+                    )
+                    .await?;
+                if log_doc
+                    .as_ref()
+                    .is_none_or(crate::api::firebase::is_soft_deleted)
+                {
+                    return Err(LogMoved.into());
+                }
+            }
+            let user_doc = store
+                .get_document_in_transaction(&tx_id, "users", user_id)
+                .await?;
+
+            Ok(build_bulk_delete_writes(user_id, targets, user_doc, now))
+        })
+    })
+    .await
+}
 
-    let mut writes = Vec::new();
+/// Writes for deleting a log: the trash flag on the log plus the decremented user stats
+fn build_delete_writes(
+    user_id: &str,
+    log_id: &str,
+    activity: &LogActivity,
+    user_doc: Option<serde_json::Value>,
+    updated_at: &str,
+) -> Vec<crate::api::firebase::TransactionWrite> {
+    build_bulk_delete_writes(user_id, &[(log_id, activity)], user_doc, updated_at)
+}
 
-    // 1. Delete the log document
-    let log_path = format!("users/{}/immersion_logs/{}", user_id, log_id);
-    writes.push(TransactionWrite::Delete {
-        document_path: log_path,
-    });
+/// Writes for deleting several logs: the trash flag on each, then one update of the user
+/// stats taking out the summed amounts and sessions per media type
+fn build_bulk_delete_writes(
+    user_id: &str,
+    logs: &[(&str, &LogActivity)],
+    user_doc: Option<serde_json::Value>,
+    updated_at: &str,
+) -> Vec<crate::api::firebase::TransactionWrite> {
+    use crate::api::firebase::TransactionWrite;
 
-    // 2. Update user stats (if user doc exists)
-    if let Some(mut user_data) = user_doc {
-        let mut stats_changed = false;
+    let mut writes: Vec<TransactionWrite> = logs
+        .iter()
+        .map(|(log_id, _)| TransactionWrite::Update {
+            document_path: format!("users/{}/immersion_logs/{}", user_id, log_id),
+            fields: serde_json::json!({
+                "deleted": true,
+                "deletedAt": updated_at
+            }),
+        })
+        .collect();
+
+    let mut user_data = match user_doc {
+        Some(d) => d,
+        None => return writes,
+    };
 
-        if let Some(stats) = user_data.get_mut("stats") {
-            if let Some(type_stats) = stats.get_mut(&activity.activity_type) {
+    // Summed amount and session count per media type
+    let mut removed: std::collections::BTreeMap<&str, (f64, i64)> =
+        std::collections::BTreeMap::new();
+    for (_, activity) in logs {
+        let entry = removed.entry(&activity.activity_type).or_default();
+        entry.0 += activity.amount;
+        entry.1 += 1;
+    }
+
+    let mut update = serde_json::Map::new();
+
+    if let Some(stats) = user_data.get_mut("stats") {
+        for (activity_type, (amount, count)) in &removed {
+            if let Some(type_stats) = stats.get_mut(*activity_type) {
                 if let Some(total) = type_stats.get_mut("total") {
                     if let Some(t) = total.as_f64() {
-                        *total = serde_json::json!(f64::max(0.0, t - activity.amount));
-                        stats_changed = true;
+                        *total = serde_json::json!(f64::max(0.0, t - amount));
                     }
                 }
                 if let Some(sessions) = type_stats.get_mut("sessions") {
                     if let Some(s) = sessions.as_i64() {
-                        *sessions = serde_json::json!(i64::max(0, s - 1));
-                        stats_changed = true;
+                        *sessions = serde_json::json!(i64::max(0, s - count));
                     }
                 }
             }
         }
+        update.insert("stats".to_string(), stats.take());
+    }
+
+    if let Some(timestamps) = user_data.get_mut("timestamps") {
+        timestamps["updated"] = serde_json::json!(updated_at);
+        update.insert("timestamps".to_string(), timestamps.take());
+    }
+
+    // Only the touched top-level fields go in the update mask
+    if !update.is_empty() {
+        writes.push(TransactionWrite::Update {
+            document_path: format!("users/{}", user_id),
+            fields: serde_json::Value::Object(update),
+        });
+    }
 
-        // Update timestamps
-        if let Some(timestamps) = user_data.get_mut("timestamps") {
-            timestamps["updated"] = serde_json::json!(Utc::now().to_rfc3339());
-            stats_changed = true;
+    writes
+}
+
+async fn edit_log_in_firebase(
+    data: &crate::Data,
+    user_id: &str,
+    log_id: &str,
+    edit: &LogEdit,
+) -> Result<(), anyhow::Error> {
+    retry_on_conflict("Edit", &format!("log {}", log_id), || {
+        try_edit_log(data, user_id, log_id, edit)
+    })
+    .await
+}
+
+/// One attempt of the edit: read the log and user doc, then patch both atomically
+async fn try_edit_log(
+    data: &crate::Data,
+    user_id: &str,
+    log_id: &str,
+    edit: &LogEdit,
+) -> Result<(), anyhow::Error> {
+    commit_staged(&*data.firebase, |tx_id| async move {
+        let log_doc = data
+            .firebase
+            .get_document_in_transaction(
+                &tx_id,
+                &format!("users/{}/immersion_logs", user_id),
+                log_id,
+            )
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Log {} no longer exists", log_id))?;
+        let user_doc = data
+            .firebase
+            .get_document_in_transaction(&tx_id, "users", user_id)
+            .await?;
+
+        Ok(build_edit_writes(
+            user_id,
+            log_id,
+            log_doc,
+            user_doc,
+            edit,
+            &data.clock.now_utc().to_rfc3339(),
+        ))
+    })
+    .await
+}
+
+/// Writes for editing a log: the patched log document plus the user's stats total
+/// shifted by the difference between the old and new amount
+fn build_edit_writes(
+    user_id: &str,
+    log_id: &str,
+    mut log_doc: serde_json::Value,
+    user_doc: Option<serde_json::Value>,
+    edit: &LogEdit,
+    updated_at: &str,
+) -> Vec<crate::api::firebase::TransactionWrite> {
+    use crate::api::firebase::TransactionWrite;
+    use serde_json::json;
+
+    let activity_type = log_doc["activity"]["type"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    let old_amount = log_doc["activity"]["amount"].as_f64().unwrap_or(0.0);
+
+    let mut activity = log_doc["activity"].take();
+    if !activity.is_object() {
+        activity = json!({});
+    }
+    activity["amount"] = json!(edit.amount);
+    activity["title"] = json!(edit.title);
+    activity["comment"] = json!(edit.comment);
+
+    let mut timestamps = log_doc["timestamps"].take();
+    if !timestamps.is_object() {
+        timestamps = json!({});
+    }
+    timestamps["date"] = json!(edit.date.format("%Y-%m-%d").to_string());
+    timestamps["month"] = json!(format!("{}-{:02}", edit.date.year(), edit.date.month()));
+    timestamps["year"] = json!(edit.date.year());
+    timestamps["updated"] = json!(updated_at);
+
+    let mut metadata = log_doc["metadata"].take();
+    if !metadata.is_object() {
+        metadata = json!({});
+    }
+    metadata["private"] = json!(edit.private);
+
+    let mut writes = vec![TransactionWrite::Update {
+        document_path: format!("users/{}/immersion_logs/{}", user_id, log_id),
+        fields: json!({
+            "activity": activity,
+            "timestamps": timestamps,
+            "metadata": metadata
+        }),
+    }];
+
+    let delta = edit.amount - old_amount;
+    if delta == 0.0 {
+        return writes;
+    }
+
+    let mut user_data = match user_doc {
+        Some(d) => d,
+        None => return writes,
+    };
+
+    let mut update = serde_json::Map::new();
+
+    if let Some(stats) = user_data.get_mut("stats") {
+        if let Some(total) = stats
+            .get_mut(&activity_type)
+            .and_then(|s| s.get_mut("total"))
+        {
+            if let Some(t) = total.as_f64() {
+                *total = json!(f64::max(0.0, t + delta));
+            }
+        }
+        update.insert("stats".to_string(), stats.take());
+    }
+
+    if let Some(timestamps) = user_data.get_mut("timestamps") {
+        timestamps["updated"] = json!(updated_at);
+        update.insert("timestamps".to_string(), timestamps.take());
+    }
+
+    if !update.is_empty() {
+        writes.push(TransactionWrite::Update {
+            document_path: format!("users/{}", user_id),
+            fields: serde_json::Value::Object(update),
+        });
+    }
+
+    writes
+}
+
+/// Days a deleted log stays restorable
+const TRASH_RETENTION_DAYS: i64 = 7;
+
+fn is_trash_expired(deleted_at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+    now - deleted_at > Duration::days(TRASH_RETENTION_DAYS)
+}
+
+/// Fetch the user's trash, newest first, hard-deleting logs past the retention window
+async fn fetch_trash(data: &crate::Data, user_id: &str) -> Vec<ImmersionLog> {
+    let now = data.clock.now_utc();
+    let docs = match data.firebase.get_deleted_user_logs(user_id).await {
+        Ok(docs) => docs,
+        Err(e) => {
+            error!("Failed to fetch deleted logs: {:?}", e);
+            return Vec::new();
         }
+    };
 
-        if stats_changed {
-            writes.push(TransactionWrite::Update {
-                document_path: format!("users/{}", user_id),
-                fields: user_data,
-            });
+    let collection = format!("users/{}/immersion_logs", user_id);
+    let mut logs = Vec::new();
+    for (id, value) in docs {
+        let Ok(mut log) = serde_json::from_value::<ImmersionLog>(value) else {
+            continue;
+        };
+        log.id = id;
+
+        if log.deleted_at.is_some_and(|at| is_trash_expired(at, now)) {
+            if let Err(e) = data.firebase.delete_document(&collection, &log.id).await {
+                error!("Failed to purge expired log {}: {:?}", log.id, e);
+            }
+            continue;
         }
+        logs.push(log);
     }
 
-    // Commit transaction atomically
-    data.firebase.commit_transaction(&tx_id, writes).await?;
+    logs.sort_by_key(|l| std::cmp::Reverse(l.deleted_at));
+    logs
+}
 
-    Ok(())
+/// Take a log out of the trash and add it back to the stats
+async fn restore_log_in_firebase(
+    store: &dyn Storage,
+    user_id: &str,
+    log_id: &str,
+    activity: &LogActivity,
+    now: &str,
+) -> Result<(), anyhow::Error> {
+    retry_on_conflict("Restore", &format!("log {}", log_id), || {
+        commit_staged(store, |tx_id| async move {
+            // A second click, or another /log trash, may have restored it already
+            let log_doc = store
+                .get_document_in_transaction(
+                    &tx_id,
+                    &format!("users/{}/immersion_logs", user_id),
+                    log_id,
+                )
+                .await?;
+            if !log_doc
+                .as_ref()
+                .is_some_and(crate::api::firebase::is_soft_deleted)
+            {
+                return Err(LogMoved.into());
+            }
+            let user_doc = store
+                .get_document_in_transaction(&tx_id, "users", user_id)
+                .await?;
+
+            Ok(build_restore_writes(
+                user_id, log_id, activity, user_doc, now,
+            ))
+        })
+    })
+    .await
+}
+
+/// Writes for restoring a log: clear the trash flag and re-apply the stats delete took away
+fn build_restore_writes(
+    user_id: &str,
+    log_id: &str,
+    activity: &LogActivity,
+    user_doc: Option<serde_json::Value>,
+    updated_at: &str,
+) -> Vec<crate::api::firebase::TransactionWrite> {
+    use crate::api::firebase::TransactionWrite;
+    use serde_json::json;
+
+    let mut writes = vec![TransactionWrite::Update {
+        document_path: format!("users/{}/immersion_logs/{}", user_id, log_id),
+        fields: json!({
+            "deleted": false,
+            "deletedAt": null
+        }),
+    }];
+
+    let mut user_data = match user_doc {
+        Some(d) => d,
+        None => return writes,
+    };
+
+    let mut update = serde_json::Map::new();
+
+    let stats = user_data
+        .as_object_mut()
+        .map(|obj| obj.entry("stats").or_insert_with(|| json!({})));
+    if let Some(stats) = stats {
+        let type_stats = &mut stats[&activity.activity_type];
+        let total = type_stats["total"].as_f64().unwrap_or(0.0);
+        let sessions = type_stats["sessions"].as_i64().unwrap_or(0);
+        type_stats["total"] = json!(total + activity.amount);
+        type_stats["sessions"] = json!(sessions + 1);
+        update.insert("stats".to_string(), stats.take());
+    }
+
+    if let Some(timestamps) = user_data.get_mut("timestamps") {
+        timestamps["updated"] = json!(updated_at);
+        update.insert("timestamps".to_string(), timestamps.take());
+    }
+
+    if !update.is_empty() {
+        writes.push(TransactionWrite::Update {
+            document_path: format!("users/{}", user_id),
+            fields: serde_json::Value::Object(update),
+        });
+    }
+
+    writes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::firebase::TransactionWrite;
+
+    fn anime_activity(amount: f64) -> LogActivity {
+        LogActivity {
+            activity_type: "anime".to_string(),
+            type_label: "Anime".to_string(),
+            amount,
+            unit: "episodes".to_string(),
+            title: None,
+            comment: None,
+        }
+    }
+
+    fn edit_modal(amount: &str, date: &str) -> EditLogModal {
+        EditLogModal {
+            amount: amount.to_string(),
+            title: Some("  Frieren ".to_string()),
+            date: date.to_string(),
+            comment: Some(String::new()),
+            private: Some("no".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_build_delete_writes() {
+        let user_doc = serde_json::json!({
+            "profile": { "displayName": "Tanaka" },
+            "stats": { "anime": { "total": 10.0, "sessions": 4 } },
+            "timestamps": { "updated": "old" }
+        });
+
+        let writes = build_delete_writes(
+            "123",
+            "abc",
+            &anime_activity(3.0),
+            Some(user_doc),
+            "2025-01-15T10:00:00Z",
+        );
+
+        assert_eq!(writes.len(), 2);
+        match &writes[0] {
+            TransactionWrite::Update {
+                document_path,
+                fields,
+            } => {
+                assert_eq!(document_path, "users/123/immersion_logs/abc");
+                assert_eq!(fields["deleted"], true);
+                assert_eq!(fields["deletedAt"], "2025-01-15T10:00:00Z");
+            }
+            other => panic!("expected soft delete, got {:?}", other),
+        }
+        match &writes[1] {
+            TransactionWrite::Update {
+                document_path,
+                fields,
+            } => {
+                assert_eq!(document_path, "users/123");
+                // Profile is untouched and must stay out of the mask
+                assert!(fields.get("profile").is_none());
+                assert_eq!(fields["stats"]["anime"]["total"], 7.0);
+                assert_eq!(fields["stats"]["anime"]["sessions"], 3);
+                assert_eq!(fields["timestamps"]["updated"], "2025-01-15T10:00:00Z");
+            }
+            other => panic!("expected update, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_build_delete_writes_without_user_doc() {
+        let writes = build_delete_writes("123", "abc", &anime_activity(1.0), None, "now");
+        assert_eq!(writes.len(), 1);
+    }
+
+    #[test]
+    fn test_build_bulk_delete_writes_sums_per_media_type() {
+        use crate::api::firebase::mock::MockFirestore;
+
+        let mut db = MockFirestore::default();
+        db.docs.insert(
+            "users/123".to_string(),
+            serde_json::json!({
+                "stats": {
+                    "anime": { "total": 10.0, "sessions": 4 },
+                    "reading": { "total": 60.0, "sessions": 3 }
+                },
+                "timestamps": {}
+            }),
+        );
+        for id in ["a", "b", "c"] {
+            db.docs.insert(
+                format!("users/123/immersion_logs/{}", id),
+                serde_json::json!({ "activity": { "type": "anime" } }),
+            );
+        }
+        let reading = LogActivity {
+            activity_type: "reading".to_string(),
+            type_label: "Reading".to_string(),
+            amount: 45.0,
+            unit: "minutes".to_string(),
+            title: None,
+            comment: None,
+        };
+        let (two, three) = (anime_activity(2.0), anime_activity(3.0));
+        let logs = [("a", &two), ("b", &three), ("c", &reading)];
+
+        let writes = build_bulk_delete_writes("123", &logs, db.get("users/123"), "t1");
+        // One trash flag per log, then a single stats update
+        assert_eq!(writes.len(), 4);
+        assert_eq!(writes[3].document_path(), "users/123");
+
+        db.commit(&writes).unwrap();
+        for id in ["a", "b", "c"] {
+            let log = db.get(&format!("users/123/immersion_logs/{}", id)).unwrap();
+            assert!(crate::api::firebase::is_soft_deleted(&log));
+        }
+        let stats = &db.get("users/123").unwrap()["stats"];
+        assert_eq!(stats["anime"]["total"], 5.0);
+        assert_eq!(stats["anime"]["sessions"], 2);
+        assert_eq!(stats["reading"]["total"], 15.0);
+        assert_eq!(stats["reading"]["sessions"], 2);
+    }
+
+    #[test]
+    fn test_media_counts_text() {
+        let log = |activity: LogActivity| ImmersionLog {
+            id: String::new(),
+            activity,
+            timestamps: LogTimestamps {
+                created: Utc::now(),
+                updated: None,
+                date: None,
+            },
+            metadata: LogFlags::default(),
+            deleted_at: None,
+        };
+        let mut untitled = anime_activity(1.0);
+        untitled.type_label = String::new();
+        let logs = vec![
+            log(anime_activity(1.0)),
+            log(LogActivity {
+                activity_type: "manga".to_string(),
+                type_label: "Manga".to_string(),
+                amount: 20.0,
+                unit: "pages".to_string(),
+                title: Some("Yotsuba".to_string()),
+                comment: None,
+            }),
+            log(untitled),
+        ];
+        assert_eq!(media_counts_text(&logs), "Anime: 2, Manga: 1");
+        assert_eq!(log_summary(&logs[1]), "20 pages of Manga - Yotsuba");
+        assert_eq!(log_summary(&logs[0]), "1 episodes of Anime");
+    }
+
+    #[test]
+    fn test_parse_log_edit() {
+        let today = NaiveDate::from_ymd_opt(2025, 1, 15).unwrap();
+
+        let edit = parse_log_edit(&edit_modal("4", "2025-01-15"), today).unwrap();
+        assert_eq!(edit.amount, 4.0);
+        assert_eq!(edit.title, "Frieren");
+        assert_eq!(edit.comment, None);
+        assert!(!edit.private);
+
+        let private = EditLogModal {
+            private: Some(" Yes".to_string()),
+            ..edit_modal("4", "2025-01-15")
+        };
+        assert!(parse_log_edit(&private, today).unwrap().private);
+        let unclear = EditLogModal {
+            private: Some("idk".to_string()),
+            ..edit_modal("4", "2025-01-15")
+        };
+        assert!(parse_log_edit(&unclear, today).is_err());
+
+        assert!(parse_log_edit(&edit_modal("4", "2025-01-16"), today).is_err());
+        assert!(parse_log_edit(&edit_modal("4", "15-01-2025"), today).is_err());
+        assert!(parse_log_edit(&edit_modal("0", "2025-01-15"), today).is_err());
+        assert!(parse_log_edit(&edit_modal("lots", "2025-01-15"), today).is_err());
+    }
+
+    #[test]
+    fn test_build_edit_writes_applies_amount_delta() {
+        let log_doc = serde_json::json!({
+            "activity": { "type": "anime", "amount": 3.0, "title": "-", "url": "keep" },
+            "metadata": { "source": "manual" },
+            "timestamps": { "created": "2025-01-10T10:00:00Z", "date": "2025-01-10" }
+        });
+        let user_doc = serde_json::json!({
+            "profile": { "displayName": "Tanaka" },
+            "stats": { "anime": { "total": 10.0, "sessions": 4 } },
+            "timestamps": { "updated": "old" }
+        });
+        let edit = LogEdit {
+            amount: 5.0,
+            title: "Frieren".to_string(),
+            date: NaiveDate::from_ymd_opt(2025, 1, 9).unwrap(),
+            comment: Some("fixed typo".to_string()),
+            private: true,
+        };
+
+        let writes = build_edit_writes(
+            "123",
+            "abc",
+            log_doc,
+            Some(user_doc),
+            &edit,
+            "2025-01-15T10:00:00Z",
+        );
+
+        assert_eq!(writes.len(), 2);
+        match &writes[0] {
+            TransactionWrite::Update {
+                document_path,
+                fields,
+            } => {
+                assert_eq!(document_path, "users/123/immersion_logs/abc");
+                assert_eq!(fields["activity"]["amount"], 5.0);
+                assert_eq!(fields["activity"]["title"], "Frieren");
+                assert_eq!(fields["activity"]["url"], "keep");
+                assert_eq!(fields["timestamps"]["date"], "2025-01-09");
+                assert_eq!(fields["timestamps"]["month"], "2025-01");
+                assert_eq!(fields["timestamps"]["created"], "2025-01-10T10:00:00Z");
+                assert_eq!(fields["metadata"]["private"], true);
+                assert_eq!(fields["metadata"]["source"], "manual");
+            }
+            other => panic!("expected update, got {:?}", other),
+        }
+        match &writes[1] {
+            TransactionWrite::Update { fields, .. } => {
+                assert!(fields.get("profile").is_none());
+                assert_eq!(fields["stats"]["anime"]["total"], 12.0);
+                assert_eq!(fields["stats"]["anime"]["sessions"], 4);
+            }
+            other => panic!("expected update, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_build_edit_writes_same_amount_leaves_stats() {
+        let log_doc = serde_json::json!({
+            "activity": { "type": "anime", "amount": 3.0 },
+            "timestamps": {}
+        });
+        let edit = LogEdit {
+            amount: 3.0,
+            title: "-".to_string(),
+            date: NaiveDate::from_ymd_opt(2025, 1, 9).unwrap(),
+            comment: None,
+            private: false,
+        };
+
+        let writes = build_edit_writes(
+            "123",
+            "abc",
+            log_doc,
+            Some(serde_json::json!({ "stats": {} })),
+            &edit,
+            "now",
+        );
+        assert_eq!(writes.len(), 1);
+    }
+
+    #[test]
+    fn test_delete_then_restore_round_trips_stats() {
+        use crate::api::firebase::mock::MockFirestore;
+
+        let original_stats = serde_json::json!({
+            "anime": { "total": 10.0, "sessions": 4 },
+            "manga": { "total": 50.0, "sessions": 2 }
+        });
+        let mut db = MockFirestore::default();
+        db.docs.insert(
+            "users/123".to_string(),
+            serde_json::json!({ "stats": original_stats, "timestamps": {} }),
+        );
+        db.docs.insert(
+            "users/123/immersion_logs/abc".to_string(),
+            serde_json::json!({ "activity": { "type": "anime", "amount": 3.0 } }),
+        );
+        let activity = anime_activity(3.0);
+
+        let writes = build_delete_writes("123", "abc", &activity, db.get("users/123"), "t1");
+        db.commit(&writes).unwrap();
+        let log = db.get("users/123/immersion_logs/abc").unwrap();
+        assert!(crate::api::firebase::is_soft_deleted(&log));
+        assert_eq!(db.get("users/123").unwrap()["stats"]["anime"]["total"], 7.0);
+
+        let writes = build_restore_writes("123", "abc", &activity, db.get("users/123"), "t2");
+        db.commit(&writes).unwrap();
+        let log = db.get("users/123/immersion_logs/abc").unwrap();
+        assert!(!crate::api::firebase::is_soft_deleted(&log));
+        assert_eq!(db.get("users/123").unwrap()["stats"], original_stats);
+    }
+
+    #[tokio::test]
+    async fn test_restore_twice_counts_once() {
+        use crate::api::memory_store::MemoryStore;
+
+        let store = MemoryStore::new();
+        let storage: &dyn Storage = &store;
+        storage
+            .set_document(
+                "users",
+                "123",
+                &serde_json::json!({ "stats": { "anime": { "total": 7.0, "sessions": 3 } } }),
+            )
+            .await
+            .unwrap();
+        storage
+            .set_document(
+                "users/123/immersion_logs",
+                "abc",
+                &serde_json::json!({ "activity": { "type": "anime", "amount": 3.0 }, "deleted": true }),
+            )
+            .await
+            .unwrap();
+        let activity = anime_activity(3.0);
+
+        restore_log_in_firebase(storage, "123", "abc", &activity, "t1")
+            .await
+            .unwrap();
+        // A stale trash list clicking it again finds the log back already
+        let again = restore_log_in_firebase(storage, "123", "abc", &activity, "t2").await;
+        assert!(again.unwrap_err().downcast_ref::<LogMoved>().is_some());
+        let user = storage.get_document("users", "123").await.unwrap().unwrap();
+        assert_eq!(user["stats"]["anime"]["total"], 10.0);
+        assert_eq!(user["stats"]["anime"]["sessions"], 4);
+    }
+
+    #[tokio::test]
+    async fn test_bulk_delete_changes_nothing_when_a_log_moved() {
+        use crate::api::memory_store::MemoryStore;
+
+        let store = MemoryStore::new();
+        let storage: &dyn Storage = &store;
+        storage
+            .set_document(
+                "users",
+                "123",
+                &serde_json::json!({ "stats": { "anime": { "total": 7.0, "sessions": 3 } } }),
+            )
+            .await
+            .unwrap();
+        for (id, deleted) in [("a", false), ("b", true)] {
+            storage
+                .set_document(
+                    "users/123/immersion_logs",
+                    id,
+                    &serde_json::json!({
+                        "activity": { "type": "anime", "amount": 2.0 },
+                        "deleted": deleted
+                    }),
+                )
+                .await
+                .unwrap();
+        }
+        let log = |id: &str| ImmersionLog {
+            id: id.to_string(),
+            activity: anime_activity(2.0),
+            timestamps: LogTimestamps {
+                created: Utc::now(),
+                updated: None,
+                date: None,
+            },
+            metadata: LogFlags::default(),
+            deleted_at: None,
+        };
+
+        // "b" went to the trash from another /log after the list was shown
+        let moved = bulk_delete_logs_from_firebase(
+            storage,
+            "123",
+            "2025-01-15",
+            &[log("a"), log("b")],
+            "t1",
+        )
+        .await;
+        assert!(moved.unwrap_err().downcast_ref::<LogMoved>().is_some());
+        let user = storage.get_document("users", "123").await.unwrap().unwrap();
+        assert_eq!(user["stats"]["anime"]["total"], 7.0);
+        let a = storage
+            .get_document("users/123/immersion_logs", "a")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!crate::api::firebase::is_soft_deleted(&a));
+
+        bulk_delete_logs_from_firebase(storage, "123", "2025-01-15", &[log("a")], "t2")
+            .await
+            .unwrap();
+        let user = storage.get_document("users", "123").await.unwrap().unwrap();
+        assert_eq!(user["stats"]["anime"]["total"], 5.0);
+        assert_eq!(user["stats"]["anime"]["sessions"], 2);
+    }
+
+    #[test]
+    fn test_trash_expiry_boundary() {
+        let deleted_at = DateTime::parse_from_rfc3339("2025-01-08T10:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert!(!is_trash_expired(
+            deleted_at,
+            deleted_at + Duration::days(7)
+        ));
+        assert!(is_trash_expired(
+            deleted_at,
+            deleted_at + Duration::days(7) + Duration::seconds(1)
+        ));
+    }
+
+    #[test]
+    fn test_log_window_tokens_round_trip() {
+        let day = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let windows = [
+            LogWindow::Last(LogTimeframe::Day),
+            LogWindow::Last(LogTimeframe::Week),
+            LogWindow::Last(LogTimeframe::Month),
+            LogWindow::Range(day("2025-01-01"), day("2025-01-31")),
+        ];
+        for window in windows {
+            let token = window.token();
+            assert!(!token.contains('_'), "{}", token);
+            assert_eq!(LogWindow::from_token(&token), Some(window));
+        }
+        assert_eq!(LogWindow::from_token("20250101-2025"), None);
+        assert_eq!(
+            LogWindow::Range(day("2025-01-01"), day("2025-01-31")).label(),
+            "2025-01-01 to 2025-01-31"
+        );
+    }
+
+    #[test]
+    fn test_range_bounds_follow_effective_days() {
+        let day = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let (start, end) =
+            LogWindow::Range(day("2025-01-10"), day("2025-01-11")).bounds(Utc::now());
+        // 02:00 WIB on the first day to 02:00 WIB after the last one
+        assert_eq!(start.to_rfc3339(), "2025-01-09T19:00:00+00:00");
+        assert_eq!(end.unwrap().to_rfc3339(), "2025-01-11T19:00:00+00:00");
+    }
+
+    #[test]
+    fn test_parse_log_window() {
+        let day = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let today = day("2025-02-01");
+        assert_eq!(
+            parse_log_window(LogTimeframe::Week, None, None, today),
+            Ok(LogWindow::Last(LogTimeframe::Week))
+        );
+        assert_eq!(
+            parse_log_window(LogTimeframe::Day, Some("2025-01-05"), None, today),
+            Ok(LogWindow::Range(day("2025-01-05"), today))
+        );
+        assert_eq!(
+            parse_log_window(
+                LogTimeframe::Day,
+                Some("2025-01-05"),
+                Some(" 2025-01-20 "),
+                today
+            ),
+            Ok(LogWindow::Range(day("2025-01-05"), day("2025-01-20")))
+        );
+        assert!(parse_log_window(LogTimeframe::Day, None, Some("2025-01-20"), today).is_err());
+        assert_eq!(
+            parse_log_window(LogTimeframe::Day, Some("05-01-2025"), None, today),
+            Err(INVALID_DATE_MESSAGE.to_string())
+        );
+        assert!(parse_log_window(
+            LogTimeframe::Day,
+            Some("2025-01-20"),
+            Some("2025-01-05"),
+            today
+        )
+        .is_err());
+        assert!(parse_log_window(LogTimeframe::Day, Some("2025-03-01"), None, today).is_err());
+    }
 }
@@ -1,16 +1,30 @@
 // Commands module
+pub mod admin;
 pub mod afk;
+pub mod ayumi;
 pub mod ayumu_exam;
 pub mod config;
 pub mod export;
+pub mod goal;
 pub mod help;
 pub mod immersion;
 pub mod leaderboard;
+pub mod link;
 pub mod log;
 pub mod novel;
+pub mod now;
+pub mod ping;
+pub mod privacy;
+pub mod profile;
 pub mod prompt;
+pub mod quarantine;
 pub mod react;
+pub mod recalculate;
 pub mod register;
+pub mod reminder;
 pub mod role_rank;
 pub mod stat;
 pub mod subs;
+pub mod timezone;
+pub mod title;
+pub mod vocab;
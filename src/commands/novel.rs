@@ -2,14 +2,14 @@
 // Download links: libgen.li/get.php?md5={md5} — no session/timer needed, instant redirect
 
 use futures::StreamExt;
-use once_cell::sync::Lazy;
 use poise::serenity_prelude as serenity;
 use scraper::{Html, Selector};
-use serde::Deserialize;
 use std::time::Duration;
 use tracing::{info, warn};
 
+use crate::features::novel_recommender::{get_novels, rank_matches};
 use crate::utils::config::colors;
+use crate::utils::formatters::{fit_description, truncate_chars, EMBED_DESCRIPTION_LIMIT};
 use crate::{Context, Error};
 
 const ANNAS_BASE_URL: &str = "https://annas-archive.gl";
@@ -24,6 +24,8 @@ pub struct AnnaResult {
     pub format: Option<String>,
     pub size: Option<String>,
     pub detail_url: String,
+    /// How well a local result matched the query, Anna's Archive ranks its own
+    pub score: Option<u32>,
 }
 
 // ── Search ────────────────────────────────────────────────────────
@@ -121,6 +123,7 @@ fn parse_search_results(
             format,
             size,
             detail_url,
+            score: None,
         });
     }
 
@@ -128,47 +131,20 @@ fn parse_search_results(
     Ok(results)
 }
 
-// ── Local fallback: novelList.json ────────────────────────────────
-
-#[derive(Debug, Clone, Deserialize)]
-pub struct NovelEntry {
-    #[allow(dead_code)]
-    pub id: String,
-    pub title: String,
-    pub url: String,
-    pub size: String,
-    pub format: String,
-}
-
-static NOVELS: Lazy<Vec<NovelEntry>> = Lazy::new(|| {
-    let paths = [
-        "Ayumi/utils/novelList.json",
-        "src/data/novelList.json",
-        "data/novelList.json",
-    ];
-    for path in paths {
-        if let Ok(content) = std::fs::read_to_string(path) {
-            if let Ok(novels) = serde_json::from_str::<Vec<NovelEntry>>(&content) {
-                info!("Loaded {} local novels from {}", novels.len(), path);
-                return novels;
-            }
-        }
-    }
-    warn!("novelList.json not found — local fallback unavailable");
-    Vec::new()
-});
+// ── Local fallback: novelList.json, shared with the recommender ───
 
+/// Local novels matching the query, best match first
 fn search_local(query: &str) -> Vec<AnnaResult> {
-    let q = query.to_lowercase();
-    NOVELS
-        .iter()
-        .filter(|n| n.title.to_lowercase().contains(&q))
-        .map(|n| AnnaResult {
+    let list = get_novels();
+    rank_matches(query, list.indexed())
+        .into_iter()
+        .map(|(n, score)| AnnaResult {
             title: n.title.clone(),
             author: None,
             format: Some(n.format.clone()),
             size: Some(n.size.clone()),
             detail_url: n.url.clone(),
+            score: Some(score),
         })
         .collect()
 }
@@ -210,7 +186,7 @@ pub async fn novel(
     };
 
     let total = results.len();
-    let total_pages = (total + PAGE_SIZE - 1) / PAGE_SIZE;
+    let total_pages = total.div_ceil(PAGE_SIZE);
 
     let reply = ctx
         .send(
@@ -273,7 +249,8 @@ fn build_embed(
     let start = page * PAGE_SIZE;
     let end = (start + PAGE_SIZE).min(results.len());
 
-    let description = results[start..end]
+    let page_results = &results[start..end];
+    let entries: Vec<String> = page_results
         .iter()
         .enumerate()
         .map(|(i, r)| {
@@ -281,39 +258,44 @@ fn build_embed(
             let fmt = r.format.as_deref().unwrap_or("?");
             let sz = r.size.as_deref().unwrap_or("?");
 
+            // A blank line between results
+            let gap = if i + 1 < page_results.len() { "\n" } else { "" };
             format!(
-                "**{}.** [Link]({})\n{}\nAuthor: {} | Format: {} | Size: {}",
+                "**{}.** [Link]({})\n{}\nAuthor: {} | Format: {} | Size: {}{}",
                 start + i + 1,
                 r.detail_url,
-                truncate(&r.title, 60),
+                truncate_chars(&r.title, 60),
                 author,
                 fmt,
                 sz,
+                gap,
             )
         })
-        .collect::<Vec<_>>()
-        .join("\n\n");
+        .collect();
+    let (description, _) = fit_description(entries, EMBED_DESCRIPTION_LIMIT);
+
+    let mut footer = format!("Source: {} | {}-{} of {}", source, start + 1, end, total);
+    if let Some(scores) = score_range(&results[start..end]) {
+        footer.push_str(&format!(" | {}", scores));
+    }
 
     serenity::CreateEmbed::new()
         .title("Light Novel Search")
         .description(description)
         .color(colors::INFO)
-        .footer(serenity::CreateEmbedFooter::new(format!(
-            "Source: {} | {}-{} of {}",
-            source,
-            start + 1,
-            end,
-            total
-        )))
+        .footer(serenity::CreateEmbedFooter::new(footer))
         .timestamp(serenity::Timestamp::now())
 }
 
-fn truncate(s: &str, max: usize) -> String {
-    if s.chars().count() <= max {
-        s.to_string()
+/// "match 100-60" for a page of local results, best first
+fn score_range(results: &[AnnaResult]) -> Option<String> {
+    let best = results.first()?.score?;
+    let worst = results.last()?.score?;
+    Some(if best == worst {
+        format!("match {}", best)
     } else {
-        format!("{}...", s.chars().take(max - 3).collect::<String>())
-    }
+        format!("match {}-{}", best, worst)
+    })
 }
 
 fn nav_buttons(page: usize, total_pages: usize) -> Vec<serenity::CreateActionRow> {
@@ -0,0 +1,345 @@
+// Now command - what a member is reading or watching right now
+// Stored as `summary.current` on the user document and shown to anyone with /now view. Logs
+// of the title get a marker, logging another VN or book a few times asks once to update it.
+
+use chrono::DateTime;
+use poise::serenity_prelude as serenity;
+use serde_json::{json, Value};
+use tracing::{error, info};
+
+use crate::commands::immersion::{autocomplete_title, resolve_title_name, MediaType};
+use crate::commands::log::fetch_all_user_logs;
+use crate::commands::title::{summarize, title_key, TitleSummary};
+use crate::models::user::CurrentItem;
+use crate::utils::config::{colors, get_media_label};
+use crate::utils::formatters::format_number;
+use crate::{Context, Error};
+
+/// Logs of another VN or book, since the status was set, before the member is asked to
+/// update it
+pub const NUDGE_AFTER_LOGS: usize = 5;
+
+/// "reading", "watching" or "listening to", as the media type is taken in
+pub fn verb(media_type: &str) -> &'static str {
+    match media_type {
+        "anime" => "watching",
+        "listening" => "listening to",
+        _ => "reading",
+    }
+}
+
+/// Whether a log titled `title` is of the current item
+pub fn is_current(current: &CurrentItem, title: &str) -> bool {
+    let key = title_key(title);
+    !key.is_empty() && key == title_key(&current.title)
+}
+
+/// Whether logging `title` should ask the member to update their status: a VN or book other
+/// than the current item, logged `NUDGE_AFTER_LOGS` times since the status was set with this
+/// log included. Asked once per status.
+pub fn should_nudge(
+    current: &CurrentItem,
+    media_type: &str,
+    title: &str,
+    prior_logs: &[Value],
+) -> bool {
+    if current.nudged
+        || !matches!(media_type, "visual_novel" | "book")
+        || title == "-"
+        || is_current(current, title)
+    {
+        return false;
+    }
+    let Ok(set_at) = DateTime::parse_from_rfc3339(&current.set_at) else {
+        return false;
+    };
+    let key = title_key(title);
+    let earlier = prior_logs
+        .iter()
+        .filter(|log| log.pointer("/activity/type").and_then(Value::as_str) == Some(media_type))
+        .filter(|log| {
+            log.pointer("/activity/title")
+                .and_then(Value::as_str)
+                .is_some_and(|t| title_key(t) == key)
+        })
+        .filter(|log| {
+            log.pointer("/timestamps/created")
+                .and_then(Value::as_str)
+                .and_then(|at| DateTime::parse_from_rfc3339(at).ok())
+                .is_some_and(|created| created >= set_at)
+        })
+        .count();
+    earlier + 1 >= NUDGE_AFTER_LOGS
+}
+
+/// Share what you're reading or watching right now
+#[poise::command(slash_command, subcommands("now_set", "now_clear", "now_view"))]
+pub async fn now(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Set what you're currently reading or watching, anyone can see it
+#[poise::command(slash_command, rename = "set")]
+pub async fn now_set(
+    ctx: Context<'_>,
+    #[description = "Type of media"] media_type: MediaType,
+    #[description = "Title of the media"]
+    #[autocomplete = "autocomplete_title"]
+    title: String,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let data = ctx.data();
+    let title = resolve_title_name(&data.http_client, media_type, &title).await;
+    if title.is_empty() || title == "-" {
+        ctx.say("Give the title you're on, e.g. `Sousou no Frieren`.")
+            .await?;
+        return Ok(());
+    }
+
+    let user_id = ctx.author().id.to_string();
+    let current = CurrentItem {
+        media_type: media_type.as_str().to_string(),
+        title: title.clone(),
+        set_at: data.clock.now_utc().to_rfc3339(),
+        nudged: false,
+    };
+    if let Err(e) = data
+        .firebase
+        .set_document_merge_paths(
+            "users",
+            &user_id,
+            &json!({ "summary": { "current": current } }),
+            &["summary.current"],
+        )
+        .await
+    {
+        error!("Failed to save now status: {:?}", e);
+        ctx.say("Failed to save your status. Please try again later.")
+            .await?;
+        return Ok(());
+    }
+    info!(
+        "User {} is now {} {}",
+        user_id,
+        verb(&current.media_type),
+        title
+    );
+
+    ctx.say(format!(
+        "You're now {} **{}** ({}). Others can see it with `/now view`.",
+        verb(&current.media_type),
+        title,
+        get_media_label(&current.media_type)
+    ))
+    .await?;
+    Ok(())
+}
+
+/// Clear what you're currently reading or watching
+#[poise::command(slash_command, rename = "clear")]
+pub async fn now_clear(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let user_id = ctx.author().id.to_string();
+    if let Err(e) = ctx
+        .data()
+        .firebase
+        .set_document_merge_paths("users", &user_id, &json!({}), &["summary.current"])
+        .await
+    {
+        error!("Failed to clear now status: {:?}", e);
+        ctx.say("Failed to clear your status. Please try again later.")
+            .await?;
+        return Ok(());
+    }
+    info!("User {} cleared their now status", user_id);
+
+    ctx.say("Your status is cleared.").await?;
+    Ok(())
+}
+
+/// See what someone is currently reading or watching
+#[poise::command(slash_command, rename = "view")]
+pub async fn now_view(
+    ctx: Context<'_>,
+    #[description = "Member to look at (you by default)"] user: Option<serenity::User>,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+
+    let data = ctx.data();
+    let target = user.as_ref().unwrap_or_else(|| ctx.author());
+    let user_id = target.id.to_string();
+    let current = match data.firebase.get_user(&user_id).await {
+        // A status cleared while a log marked it as nudged leaves only that flag behind
+        Ok(user) => user
+            .and_then(|u| u.summary.current)
+            .filter(|c| !c.title.is_empty()),
+        Err(e) => {
+            error!("Failed to fetch now status: {:?}", e);
+            ctx.say("Failed to fetch that status. Please try again later.")
+                .await?;
+            return Ok(());
+        }
+    };
+    let Some(current) = current else {
+        let message = if target.id == ctx.author().id {
+            "You haven't set what you're on. Use `/now set` to share it.".to_string()
+        } else {
+            format!("**{}** hasn't shared what they're on.", target.name)
+        };
+        ctx.say(message).await?;
+        return Ok(());
+    };
+
+    let summary = match fetch_all_user_logs(data, &user_id).await {
+        Ok(logs) => summarize(&logs, &current.title),
+        Err(e) => {
+            error!("Failed to fetch logs for now status: {:?}", e);
+            None
+        }
+    };
+    ctx.send(poise::CreateReply::default().embed(now_embed(target, &current, summary.as_ref())))
+        .await?;
+    Ok(())
+}
+
+fn now_embed(
+    user: &serenity::User,
+    current: &CurrentItem,
+    summary: Option<&TitleSummary>,
+) -> serenity::CreateEmbed {
+    let mut embed = serenity::CreateEmbed::new()
+        .author(serenity::CreateEmbedAuthor::new(format!(
+            "{} is currently {}",
+            user.global_name.as_deref().unwrap_or(&user.name),
+            verb(&current.media_type)
+        )))
+        .title(&current.title)
+        .color(colors::IMMERSION)
+        .thumbnail(user.face())
+        .field("Type", get_media_label(&current.media_type), true);
+    if let Ok(set_at) = DateTime::parse_from_rfc3339(&current.set_at) {
+        embed = embed.field("Since", format!("<t:{}:R>", set_at.timestamp()), true);
+    }
+    embed.field("Logged", logged_text(current, summary), false)
+}
+
+/// Total logged under the title, in the unit of the status's media type when there's any
+fn logged_text(current: &CurrentItem, summary: Option<&TitleSummary>) -> String {
+    let Some(summary) = summary else {
+        return "Nothing logged yet".to_string();
+    };
+    let (amount, unit) = summary
+        .totals
+        .get(&current.media_type)
+        .or_else(|| summary.totals.values().next())
+        .cloned()
+        .unwrap_or_default();
+    format!(
+        "{} {} over {} session{}",
+        format_number(amount.round() as i64),
+        unit,
+        summary.sessions,
+        if summary.sessions == 1 { "" } else { "s" }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_support::log;
+
+    fn current(nudged: bool) -> CurrentItem {
+        CurrentItem {
+            media_type: "visual_novel".to_string(),
+            title: "Summer Pockets".to_string(),
+            set_at: "2025-01-10T00:00:00+00:00".to_string(),
+            nudged,
+        }
+    }
+
+    #[test]
+    fn test_is_current() {
+        let current = current(false);
+        assert!(is_current(&current, "summer pockets"));
+        assert!(!is_current(&current, "Summer Pockets Reflection Blue"));
+        assert!(!is_current(&current, ""));
+    }
+
+    #[test]
+    fn test_should_nudge() {
+        let other = |created: &str| {
+            log("visual_novel", 1000.0)
+                .title("Sakura no Uta")
+                .created(created)
+                .build()
+        };
+        let mut logs = vec![
+            // Before the status was set, not counted
+            other("2025-01-09T12:00:00+00:00"),
+            other("2025-01-11T12:00:00+00:00"),
+            other("2025-01-12T12:00:00+00:00"),
+            other("2025-01-13T12:00:00+00:00"),
+            log("anime", 1.0)
+                .title("Sakura no Uta")
+                .created("2025-01-13T12:00:00+00:00")
+                .build(),
+        ];
+        assert!(!should_nudge(
+            &current(false),
+            "visual_novel",
+            "Sakura no Uta",
+            &logs
+        ));
+
+        logs.push(other("2025-01-14T12:00:00+00:00"));
+        assert!(should_nudge(
+            &current(false),
+            "visual_novel",
+            "Sakura no Uta",
+            &logs
+        ));
+        // Asked once, and never about the current item or anime
+        assert!(!should_nudge(
+            &current(true),
+            "visual_novel",
+            "Sakura no Uta",
+            &logs
+        ));
+        assert!(!should_nudge(
+            &current(false),
+            "visual_novel",
+            "Summer Pockets",
+            &logs
+        ));
+        assert!(!should_nudge(
+            &current(false),
+            "anime",
+            "Sakura no Uta",
+            &logs
+        ));
+    }
+
+    #[test]
+    fn test_logged_text() {
+        let current = current(false);
+        assert_eq!(logged_text(&current, None), "Nothing logged yet");
+        let summary = TitleSummary {
+            title: "Summer Pockets".to_string(),
+            totals: [(
+                "visual_novel".to_string(),
+                (123456.0, "characters".to_string()),
+            )]
+            .into(),
+            reading_minutes: 0.0,
+            first_date: "2025-01-10".to_string(),
+            last_date: "2025-01-12".to_string(),
+            sessions: 3,
+        };
+        assert_eq!(
+            logged_text(&current, Some(&summary)),
+            "123,456 characters over 3 sessions"
+        );
+    }
+}
@@ -0,0 +1,174 @@
+// Ping command - gateway, REST and Firestore latency diagnostics
+
+use poise::serenity_prelude as serenity;
+use std::time::{Duration, Instant};
+
+use crate::utils::formatters::sparkline;
+use crate::utils::health::{self, Health, Metric, ProbeResult, PROBE_TIMEOUT};
+use crate::{Context, Error};
+
+/// Check how fast the bot is responding
+#[poise::command(slash_command, prefix_command)]
+pub async fn ping(ctx: Context<'_>) -> Result<(), Error> {
+    let data = ctx.data();
+    let gateway = gateway_latency(ctx).await;
+
+    let reply = ctx
+        .send(poise::CreateReply::default().content("🏓 Measuring..."))
+        .await?;
+
+    // The Firestore probe is capped at PROBE_TIMEOUT and runs alongside the timed edit
+    let rest = async {
+        let start = Instant::now();
+        reply
+            .edit(
+                ctx,
+                poise::CreateReply::default().content("🏓 Measuring... (checking database)"),
+            )
+            .await
+            .map(|_| start.elapsed())
+    };
+    let (rest, probe) = tokio::join!(
+        rest,
+        health::probe_firestore(data.firebase.as_ref(), PROBE_TIMEOUT)
+    );
+    let rest = rest?;
+    data.probe_history.record(probe);
+
+    let gateway_health = gateway.map(|g| Metric::Gateway.classify(g));
+    let rest_health = Metric::Rest.classify(rest);
+    let overall = health::worst(
+        gateway_health
+            .into_iter()
+            .chain([rest_health, probe.health()]),
+    );
+
+    let gateway_value = match (gateway, gateway_health) {
+        (Some(latency), Some(h)) => format!("{} {}ms", h.emoji(), latency.as_millis()),
+        _ => "⚪ not measured yet".to_string(),
+    };
+
+    let embed = serenity::CreateEmbed::new()
+        .title("🏓 Pong!")
+        .field("Gateway heartbeat", gateway_value, true)
+        .field(
+            "REST round-trip",
+            format!("{} {}ms", rest_health.emoji(), rest.as_millis()),
+            true,
+        )
+        .field(
+            "Firestore",
+            format!("{} {}", probe.health().emoji(), probe.describe()),
+            true,
+        )
+        .color(overall.color());
+
+    reply
+        .edit(ctx, poise::CreateReply::default().content("").embed(embed))
+        .await?;
+
+    Ok(())
+}
+
+/// Show recent Firestore probe results (owner only)
+#[poise::command(prefix_command, hide_in_help, owners_only = true)]
+pub async fn status(ctx: Context<'_>) -> Result<(), Error> {
+    let probes = ctx.data().probe_history.snapshot();
+    let gateway = gateway_latency(ctx).await;
+
+    let gateway_line = match gateway {
+        Some(latency) => format!(
+            "{} {}ms",
+            Metric::Gateway.classify(latency).emoji(),
+            latency.as_millis()
+        ),
+        None => "not measured yet".to_string(),
+    };
+
+    let embed = serenity::CreateEmbed::new()
+        .title("Bot Status")
+        .field("Gateway heartbeat", gateway_line, false)
+        .field(
+            "Firestore (last /ping probes)",
+            describe_trend(&probes),
+            false,
+        )
+        .color(
+            probes
+                .last()
+                .map(|p| p.health())
+                .unwrap_or(Health::Good)
+                .color(),
+        );
+
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
+/// Heartbeat latency of the shard handling this command
+async fn gateway_latency(ctx: Context<'_>) -> Option<Duration> {
+    let shard_id = ctx.serenity_context().shard_id;
+    ctx.framework()
+        .shard_manager()
+        .runners
+        .lock()
+        .await
+        .get(&shard_id)
+        .and_then(|runner| runner.latency)
+}
+
+/// Sparkline plus a short summary of the recorded probes
+fn describe_trend(probes: &[ProbeResult]) -> String {
+    if probes.is_empty() {
+        return "No probes yet, run `/ping` first.".to_string();
+    }
+
+    let failed = probes
+        .iter()
+        .filter(|p| !matches!(p, ProbeResult::Ok(_)))
+        .count();
+    let ok: Vec<u128> = probes
+        .iter()
+        .filter_map(|p| match p {
+            ProbeResult::Ok(latency) => Some(latency.as_millis()),
+            _ => None,
+        })
+        .collect();
+    let average = if ok.is_empty() {
+        "n/a".to_string()
+    } else {
+        format!("{}ms", ok.iter().sum::<u128>() / ok.len() as u128)
+    };
+
+    format!(
+        "`{}`\n{} probes, avg {}, {} degraded",
+        sparkline(&health::trend_values(probes)),
+        probes.len(),
+        average,
+        failed
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_trend() {
+        assert!(describe_trend(&[]).contains("/ping"));
+
+        let probes = [
+            ProbeResult::Ok(Duration::from_millis(100)),
+            ProbeResult::Ok(Duration::from_millis(300)),
+            ProbeResult::TimedOut,
+        ];
+        let text = describe_trend(&probes);
+        assert!(text.starts_with("`▁▂█`"), "{}", text);
+        assert!(
+            text.ends_with("3 probes, avg 200ms, 1 degraded"),
+            "{}",
+            text
+        );
+    }
+}
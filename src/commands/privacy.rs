@@ -0,0 +1,86 @@
+// Privacy command - per-user privacy settings
+
+use serde_json::json;
+use tracing::{error, info};
+
+use crate::utils::privacy::LeaderboardPrivacy;
+use crate::{Context, Error};
+
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum OnOff {
+    #[name = "On"]
+    On,
+    #[name = "Off"]
+    Off,
+}
+
+/// What happens to your entry while opted out
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum OptOutMode {
+    #[name = "Hide me"]
+    Hide,
+    #[name = "Show me as Anonymous"]
+    Anonymize,
+}
+
+/// Manage your privacy settings
+#[poise::command(slash_command, subcommands("leaderboard"))]
+pub async fn privacy(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Keep yourself off the leaderboard and weekly recap
+#[poise::command(slash_command)]
+pub async fn leaderboard(
+    ctx: Context<'_>,
+    #[description = "On keeps you off public rankings"] opt_out: OnOff,
+    #[description = "Hide you entirely (default) or show you as Anonymous"] mode: Option<
+        OptOutMode,
+    >,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let privacy = match (opt_out, mode) {
+        (OnOff::Off, _) => LeaderboardPrivacy::Public,
+        (OnOff::On, Some(OptOutMode::Anonymize)) => LeaderboardPrivacy::Anonymous,
+        (OnOff::On, _) => LeaderboardPrivacy::Hidden,
+    };
+    let user_id = ctx.author().id.to_string();
+
+    // Only these two fields, so profile data written by logging is untouched.
+    // A missing mode deletes it.
+    let mut profile = json!({ "leaderboardOptOut": !privacy.is_public() });
+    if let Some(mode) = privacy.mode() {
+        profile["leaderboardOptOutMode"] = json!(mode);
+    }
+    if let Err(e) = ctx
+        .data()
+        .firebase
+        .set_document_merge_paths(
+            "users",
+            &user_id,
+            &json!({ "profile": profile }),
+            &["profile.leaderboardOptOut", "profile.leaderboardOptOutMode"],
+        )
+        .await
+    {
+        error!("Failed to save privacy setting: {:?}", e);
+        ctx.say("Failed to save your setting.").await?;
+        return Ok(());
+    }
+    info!("User {} set leaderboard privacy to {:?}", user_id, privacy);
+
+    let reply = match privacy {
+        LeaderboardPrivacy::Public => {
+            "You're back on the leaderboard and weekly recap, and others can look up your stats."
+        }
+        LeaderboardPrivacy::Anonymous => {
+            "You'll show up as **Anonymous** on the leaderboard and weekly recap, and others can't look up your stats."
+        }
+        LeaderboardPrivacy::Hidden => {
+            "You're hidden from the leaderboard and weekly recap, and others can't look up your stats."
+        }
+    };
+    ctx.say(reply).await?;
+    Ok(())
+}
@@ -0,0 +1,56 @@
+// Card command - one card with a member's stats, streak and quiz level
+
+use poise::serenity_prelude as serenity;
+use tracing::error;
+
+use crate::features::profile::{load_profile, profile_embed};
+use crate::utils::config::get_guild_config;
+use crate::utils::privacy::LOOKUP_OPTED_OUT;
+use crate::{Context, Error};
+
+/// View your (or another member's) profile card
+// Named card, /profile is the Ayumu exam profile
+#[poise::command(slash_command, prefix_command, rename = "card")]
+pub async fn profile(
+    ctx: Context<'_>,
+    #[description = "Lihat profil member lain"] user: Option<serenity::User>,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+
+    let data = ctx.data();
+    let is_lookup = user.as_ref().is_some_and(|u| u.id != ctx.author().id);
+    let user = user.as_ref().unwrap_or_else(|| ctx.author());
+
+    // Same switch as looking up someone's /stat
+    if is_lookup {
+        if let Some(guild_id) = ctx.guild_id() {
+            let allowed = get_guild_config(data, &guild_id.to_string())
+                .await
+                .map(|c| c.stat_lookup_allowed())
+                .unwrap_or(true);
+            if !allowed {
+                ctx.say("Melihat profil member lain dinonaktifkan di server ini.")
+                    .await?;
+                return Ok(());
+            }
+        }
+    }
+
+    let profile = match load_profile(data, &ctx.serenity_context().http, user, ctx.guild_id()).await
+    {
+        Ok(p) => p,
+        Err(e) => {
+            error!("Failed to load profile of {}: {:?}", user.id, e);
+            ctx.say("Gagal memuat profil.").await?;
+            return Ok(());
+        }
+    };
+    if is_lookup && !profile.privacy.is_public() {
+        ctx.say(LOOKUP_OPTED_OUT).await?;
+        return Ok(());
+    }
+
+    ctx.send(poise::CreateReply::default().embed(profile_embed(&profile, user.face())))
+        .await?;
+    Ok(())
+}
@@ -1,7 +1,7 @@
 use poise::serenity_prelude as serenity;
 use tracing::{error, info};
 
-use crate::features::custom_prompt;
+use crate::features::custom_prompt::{self, preset_key, SaveOutcome, UserPrompts, MAX_PRESETS};
 use crate::utils::config::colors;
 use crate::{Context, Error};
 
@@ -14,6 +14,12 @@ pub enum PromptAction {
     View,
     #[name = "Delete Prompt"]
     Delete,
+    #[name = "Save Preset"]
+    Save,
+    #[name = "Use Preset"]
+    Use,
+    #[name = "List Presets"]
+    List,
 }
 
 /// Manage your custom Ayumi personality prompt
@@ -21,91 +27,70 @@ pub enum PromptAction {
 pub async fn prompt(
     ctx: Context<'_>,
     #[description = "Action to perform"] action: PromptAction,
-    #[description = "Rentry URL (required for Set action)"] url: Option<String>,
+    #[description = "Rentry URL (required for Set, Save copies your active prompt without one)"]
+    url: Option<String>,
+    #[description = "Preset name (for Save and Use, Delete removes the active one without it)"]
+    name: Option<String>,
 ) -> Result<(), Error> {
     ctx.defer_ephemeral().await?;
     let user_id = ctx.author().id.get();
 
-    match action {
-        PromptAction::Set => {
-            let url = match url {
-                Some(u) => u,
-                None => {
-                    ctx.say("Please provide a Rentry URL to set your prompt.")
-                        .await?;
-                    return Ok(());
-                }
-            };
-
-            // Rate limit check
-            if let Err(time_left) = custom_prompt::is_rate_limited(user_id) {
-                ctx.say(format!(
-                    "Please wait {} seconds before updating your prompt again.",
-                    time_left
-                ))
+    // Read before any Rentry fetch, so saving checks against the revision we started from
+    let prompts = match ctx.data().prompt_store.load(user_id).await {
+        Ok(prompts) => prompts.unwrap_or_default(),
+        Err(e) => {
+            error!("Failed to load custom prompt: {:?}", e);
+            ctx.say("Failed to load your custom prompt. Please try again later.")
                 .await?;
-                return Ok(());
-            }
+            return Ok(());
+        }
+    };
 
-            // Validate URL
-            if !custom_prompt::is_valid_rentry_url(&url) {
-                ctx.say("Invalid URL. Please provide a valid Rentry.co URL (e.g., https://rentry.co/xxxxx).")
+    match action {
+        PromptAction::Set => {
+            let Some(url) = url else {
+                ctx.say("Please provide a Rentry URL to set your prompt.")
                     .await?;
                 return Ok(());
-            }
-
-            // Fetch content
-            let content = match custom_prompt::fetch_prompt_from_rentry(
-                &ctx.data().http_client,
-                &url,
-            )
-            .await
-            {
-                Ok(c) => c,
-                Err(e) => {
-                    error!("Failed to fetch Rentry prompt: {:?}", e);
-                    ctx.say(format!("Failed to fetch prompt from Rentry: {}", e))
-                        .await?;
-                    return Ok(());
-                }
             };
-
-            // Validate content
-            if let Err(e) = custom_prompt::validate_prompt_content(&content) {
-                ctx.say(format!("Invalid prompt content: {}", e)).await?;
+            let Some(content) = fetch_prompt(ctx, user_id, &url).await? else {
                 return Ok(());
-            }
-
-            // Save prompt
-            if custom_prompt::save_user_custom_prompt(user_id, &content) {
-                info!("Updated custom prompt for user {}", user_id);
-
-                let embed = serenity::CreateEmbed::new()
-                    .title("Custom Prompt Updated")
-                    .description("Your custom Ayumi personality has been successfully updated!")
-                    .field("Source", &url, false)
-                    .field("Length", format!("{} characters", content.len()), true)
-                    .color(colors::SUCCESS);
+            };
 
-                ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
-                    .await?;
-            } else {
-                ctx.say("Failed to save custom prompt. Please try again later.")
-                    .await?;
+            let mut prompts = prompts;
+            if let Err(e) = prompts.set_active_prompt(user_id, content.clone()) {
+                ctx.say(e.to_string()).await?;
+                return Ok(());
             }
+            let embed = serenity::CreateEmbed::new()
+                .title("Custom Prompt Updated")
+                .description("Your custom Ayumi personality has been successfully updated!")
+                .field("Source", &url, false)
+                .field(
+                    "Length",
+                    format!("{} characters", content.chars().count()),
+                    true,
+                )
+                .color(colors::SUCCESS);
+            save_and_reply(ctx, user_id, prompts, embed).await?;
         }
         PromptAction::View => {
-            if let Some(prompt) = custom_prompt::get_user_custom_prompt(user_id) {
-                let display_prompt = if prompt.len() > 1900 {
-                    format!("{}...", &prompt[..1900])
-                } else {
-                    prompt.clone()
-                };
+            if let Some(active) = prompts.active_prompt() {
+                let display_prompt = preview(&active.prompt, 1900);
 
                 let embed = serenity::CreateEmbed::new()
                     .title("Your Custom Prompt")
                     .description(format!("```\n{}\n```", display_prompt))
-                    .field("Full Length", format!("{} characters", prompt.len()), true)
+                    .field(
+                        "Preset",
+                        prompts.active.as_deref().unwrap_or_default(),
+                        true,
+                    )
+                    .field(
+                        "Full Length",
+                        format!("{} characters", active.prompt.len()),
+                        true,
+                    )
                     .color(colors::INFO);
 
                 ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
@@ -121,28 +106,248 @@ pub async fn prompt(
             }
         }
         PromptAction::Delete => {
-            // Check if exists first
-            if custom_prompt::get_user_custom_prompt(user_id).is_none() {
+            let key = match name.as_deref().map(preset_key).transpose() {
+                Ok(key) => key.or_else(|| prompts.active.clone()),
+                Err(e) => {
+                    ctx.say(e.to_string()).await?;
+                    return Ok(());
+                }
+            };
+            let Some(key) = key else {
                 ctx.say("You don't have a custom prompt set.").await?;
                 return Ok(());
+            };
+            let was_active = prompts.active.as_deref() == Some(key.as_str());
+            let mut prompts = prompts;
+            if let Err(e) = prompts.delete_preset(&key) {
+                ctx.say(e.to_string()).await?;
+                return Ok(());
             }
 
-            if custom_prompt::delete_user_custom_prompt(user_id) {
-                info!("Deleted custom prompt for user {}", user_id);
-
-                let embed = serenity::CreateEmbed::new()
-                    .title("Custom Prompt Deleted")
-                    .description("Your custom prompt has been removed. Ayumi has reverted to her default personality.")
-                    .color(colors::SUCCESS);
+            let description = if was_active {
+                format!(
+                    "Preset **{}** has been removed. Ayumi has reverted to her default personality.",
+                    key
+                )
+            } else {
+                format!("Preset **{}** has been removed.", key)
+            };
+            let embed = serenity::CreateEmbed::new()
+                .title("Custom Prompt Deleted")
+                .description(description)
+                .color(colors::SUCCESS);
 
+            // Nothing left to keep
+            if prompts.presets.is_empty() {
+                if let Err(e) = ctx.data().prompt_store.delete(user_id).await {
+                    error!("Failed to delete custom prompt: {:?}", e);
+                    ctx.say("Failed to delete custom prompt. Please try again later.")
+                        .await?;
+                    return Ok(());
+                }
+                info!("Deleted custom prompt for user {}", user_id);
                 ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
                     .await?;
-            } else {
-                ctx.say("Failed to delete custom prompt. Please try again later.")
+                return Ok(());
+            }
+            save_and_reply(ctx, user_id, prompts, embed).await?;
+        }
+        PromptAction::Save => {
+            let Some(name) = name else {
+                ctx.say("Please provide a name for the preset.").await?;
+                return Ok(());
+            };
+            let key = match preset_key(&name) {
+                Ok(key) => key,
+                Err(e) => {
+                    ctx.say(e.to_string()).await?;
+                    return Ok(());
+                }
+            };
+            let content = match (&url, prompts.active_prompt()) {
+                (Some(url), _) => match fetch_prompt(ctx, user_id, url).await? {
+                    Some(content) => content,
+                    None => return Ok(()),
+                },
+                (None, Some(active)) => active.prompt.clone(),
+                (None, None) => {
+                    ctx.say("Provide a Rentry URL, or set a prompt first to save a copy of it.")
+                        .await?;
+                    return Ok(());
+                }
+            };
+
+            let mut prompts = prompts;
+            let length = content.chars().count();
+            if let Err(e) = prompts.save_preset(user_id, &key, content) {
+                ctx.say(e.to_string()).await?;
+                return Ok(());
+            }
+            let embed = serenity::CreateEmbed::new()
+                .title("Preset Saved")
+                .description(format!(
+                    "Saved preset **{}**. Switch to it with the Use Preset action.",
+                    key
+                ))
+                .field("Length", format!("{} characters", length), true)
+                .field(
+                    "Presets",
+                    format!("{}/{}", prompts.presets.len(), MAX_PRESETS),
+                    true,
+                )
+                .color(colors::SUCCESS);
+            save_and_reply(ctx, user_id, prompts, embed).await?;
+        }
+        PromptAction::Use => {
+            let Some(name) = name else {
+                ctx.say("Please provide the name of the preset to use.")
                     .await?;
+                return Ok(());
+            };
+            let mut prompts = prompts;
+            if let Err(e) = preset_key(&name).and_then(|key| prompts.use_preset(&key)) {
+                ctx.say(e.to_string()).await?;
+                return Ok(());
             }
+            let embed = serenity::CreateEmbed::new()
+                .title("Preset Activated")
+                .description(format!(
+                    "Ayumi now uses your **{}** preset.",
+                    prompts.active.as_deref().unwrap_or_default()
+                ))
+                .color(colors::SUCCESS);
+            save_and_reply(ctx, user_id, prompts, embed).await?;
+        }
+        PromptAction::List => {
+            let embed = serenity::CreateEmbed::new()
+                .title(format!(
+                    "Your Presets ({}/{})",
+                    prompts.presets.len(),
+                    MAX_PRESETS
+                ))
+                .description(preset_list(&prompts))
+                .color(colors::INFO);
+            ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+                .await?;
         }
     }
 
     Ok(())
 }
+
+/// Fetch and check a prompt from Rentry, replying with the reason when it can't be used
+async fn fetch_prompt(ctx: Context<'_>, user_id: u64, url: &str) -> Result<Option<String>, Error> {
+    // Rate limit check
+    if let Err(time_left) = custom_prompt::is_rate_limited(user_id, ctx.data().clock.as_ref()) {
+        ctx.say(format!(
+            "Please wait {} seconds before updating your prompt again.",
+            time_left
+        ))
+        .await?;
+        return Ok(None);
+    }
+
+    // Validate URL
+    if !custom_prompt::is_valid_rentry_url(url) {
+        ctx.say(
+            "Invalid URL. Please provide a valid Rentry.co URL (e.g., https://rentry.co/xxxxx).",
+        )
+        .await?;
+        return Ok(None);
+    }
+
+    // Fetch content
+    let content = match custom_prompt::fetch_prompt_from_rentry(&ctx.data().http_client, url).await
+    {
+        Ok(c) => c,
+        Err(e) => {
+            error!("Failed to fetch Rentry prompt: {:?}", e);
+            ctx.say(format!("Failed to fetch prompt from Rentry: {}", e))
+                .await?;
+            return Ok(None);
+        }
+    };
+
+    // Validate content
+    if let Err(e) = custom_prompt::validate_prompt_content(&content) {
+        ctx.say(format!("Invalid prompt content: {}", e)).await?;
+        return Ok(None);
+    }
+    Ok(Some(content))
+}
+
+/// Save the presets and reply with `embed`, or with the newer copy when they changed elsewhere
+async fn save_and_reply(
+    ctx: Context<'_>,
+    user_id: u64,
+    prompts: UserPrompts,
+    embed: serenity::CreateEmbed,
+) -> Result<(), Error> {
+    match ctx.data().prompt_store.save(user_id, prompts).await {
+        Ok(SaveOutcome::Saved(_)) => {
+            info!("Updated custom prompt for user {}", user_id);
+            ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+                .await?;
+        }
+        Ok(SaveOutcome::Conflict(newer)) => {
+            info!("Custom prompt save conflict for user {}", user_id);
+
+            let mut embed = serenity::CreateEmbed::new()
+                .title("Prompt Changed Elsewhere")
+                .description(
+                    "Your prompt changed elsewhere, showing the newer one — retry to overwrite.",
+                )
+                .color(colors::WARNING);
+            if let Some(active) = newer.as_ref().and_then(UserPrompts::active_prompt) {
+                embed = embed.field(
+                    "Current Prompt",
+                    format!("```\n{}\n```", preview(&active.prompt, 1000)),
+                    false,
+                );
+            }
+
+            ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+                .await?;
+        }
+        Err(e) => {
+            error!("Failed to save custom prompt: {:?}", e);
+            ctx.say("Failed to save custom prompt. Please try again later.")
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// One line per preset with its length, marking the active one
+fn preset_list(prompts: &UserPrompts) -> String {
+    if prompts.presets.is_empty() {
+        return "No presets yet. Save one with the Save Preset action.".to_string();
+    }
+    prompts
+        .presets
+        .iter()
+        .map(|(name, data)| {
+            let marker = if prompts.active.as_deref() == Some(name.as_str()) {
+                " ✅ *(active)*"
+            } else {
+                ""
+            };
+            format!(
+                "**{}** — {} characters{}",
+                name,
+                data.prompt.chars().count(),
+                marker
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Char-safe prompt preview for embeds
+fn preview(prompt: &str, max_chars: usize) -> String {
+    if prompt.chars().count() > max_chars {
+        format!("{}...", prompt.chars().take(max_chars).collect::<String>())
+    } else {
+        prompt.to_string()
+    }
+}
@@ -0,0 +1,54 @@
+// Quarantine command - inspect and reset background task quarantines (owner only)
+
+use crate::{Context, Error};
+
+/// Inspect guilds skipped by background tasks after repeated failures
+#[poise::command(
+    prefix_command,
+    hide_in_help,
+    owners_only = true,
+    subcommands("list", "clear")
+)]
+pub async fn quarantine(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// List quarantined guilds
+#[poise::command(prefix_command, hide_in_help, owners_only = true)]
+pub async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let statuses = ctx.data().quarantine.list();
+    if statuses.is_empty() {
+        ctx.say("No guilds are quarantined.").await?;
+        return Ok(());
+    }
+
+    let lines: Vec<String> = statuses
+        .iter()
+        .map(|s| {
+            format!(
+                "`{}` **{}** - {} failures, until <t:{}:R>",
+                s.guild_id,
+                s.task,
+                s.consecutive_failures,
+                s.until.timestamp()
+            )
+        })
+        .collect();
+    ctx.say(lines.join("\n")).await?;
+    Ok(())
+}
+
+/// Reset all quarantines for a guild
+#[poise::command(prefix_command, hide_in_help, owners_only = true)]
+pub async fn clear(
+    ctx: Context<'_>,
+    #[description = "Guild ID"] guild_id: String,
+) -> Result<(), Error> {
+    let cleared = ctx.data().quarantine.clear(&guild_id);
+    ctx.say(format!(
+        "Cleared {} quarantined task(s) for guild `{}`.",
+        cleared, guild_id
+    ))
+    .await?;
+    Ok(())
+}
@@ -1,16 +1,131 @@
 // React command - react to messages with animated emojis
 // Ported from commands/react.js
 
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
 use poise::serenity_prelude as serenity;
-use tracing::error;
+use std::time::{Duration, Instant};
+use tracing::{error, warn};
 
 use crate::utils::config::colors;
-use crate::utils::emojis::{get_emoji_by_id, EMOJIS};
+use crate::utils::emojis::{Emoji, EMOJIS};
 use crate::{Context, Error};
 
 const EMOJIS_PER_PAGE: usize = 20;
 const BUTTONS_PER_ROW: usize = 5;
 
+/// How long a guild's emoji list is reused before fetching it again
+const GUILD_EMOJI_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// An emoji offered as a react button
+#[derive(Debug, Clone, PartialEq)]
+struct ReactEmoji {
+    id: u64,
+    name: String,
+    animated: bool,
+}
+
+impl ReactEmoji {
+    fn reaction(&self) -> serenity::ReactionType {
+        serenity::ReactionType::Custom {
+            animated: self.animated,
+            id: serenity::EmojiId::new(self.id),
+            name: Some(self.name.clone()),
+        }
+    }
+
+    fn image_url(&self) -> String {
+        let extension = if self.animated { "gif" } else { "png" };
+        format!(
+            "https://cdn.discordapp.com/emojis/{}.{}",
+            self.id, extension
+        )
+    }
+}
+
+static GUILD_EMOJI_CACHE: Lazy<DashMap<serenity::GuildId, (Instant, Vec<ReactEmoji>)>> =
+    Lazy::new(DashMap::new);
+
+/// The guild's usable emojis, animated first, cached for `GUILD_EMOJI_TTL`.
+/// Empty when they can't be fetched, the hardcoded list still works then.
+async fn guild_emojis(ctx: Context<'_>, guild_id: serenity::GuildId) -> Vec<ReactEmoji> {
+    if let Some(entry) = GUILD_EMOJI_CACHE.get(&guild_id) {
+        if entry.0.elapsed() < GUILD_EMOJI_TTL {
+            return entry.1.clone();
+        }
+    }
+
+    let emojis = match guild_id.emojis(ctx.http()).await {
+        Ok(emojis) => emojis,
+        Err(e) => {
+            warn!("Failed to fetch emojis of guild {}: {:?}", guild_id, e);
+            return Vec::new();
+        }
+    };
+    let mut emojis: Vec<ReactEmoji> = emojis
+        .into_iter()
+        // Emojis over the guild's boost limit can't be used
+        .filter(|emoji| emoji.available)
+        .map(|emoji| ReactEmoji {
+            id: emoji.id.get(),
+            name: emoji.name,
+            animated: emoji.animated,
+        })
+        .collect();
+    emojis.sort_by_key(|emoji| !emoji.animated);
+    GUILD_EMOJI_CACHE.insert(guild_id, (Instant::now(), emojis.clone()));
+    emojis
+}
+
+/// The guild's emojis followed by the hardcoded ones it doesn't have. On a shared ID the
+/// guild's copy wins, it has the current name.
+fn merge_emojis(guild: Vec<ReactEmoji>, hardcoded: &[Emoji]) -> Vec<ReactEmoji> {
+    let mut merged = guild;
+    for emoji in hardcoded {
+        let Ok(id) = emoji.id.parse::<u64>() else {
+            continue;
+        };
+        if merged.iter().all(|e| e.id != id) {
+            merged.push(ReactEmoji {
+                id,
+                name: emoji.name.to_string(),
+                animated: true,
+            });
+        }
+    }
+    merged
+}
+
+fn total_pages(emoji_count: usize) -> usize {
+    emoji_count.div_ceil(EMOJIS_PER_PAGE).max(1)
+}
+
+/// Why a react failed, from Discord's error code
+fn react_failure_message(code: Option<isize>) -> &'static str {
+    match code {
+        // Missing Permissions
+        Some(50013) => "Bot tidak punya izin Add Reactions di channel itu.",
+        // Missing Access
+        Some(50001) => "Bot tidak bisa mengakses channel itu.",
+        // Unknown Emoji
+        Some(10014) => "Emoji itu tidak tersedia untuk bot di server ini. Coba emoji lain, ya.",
+        // Maximum number of reactions reached
+        Some(30010) => "Pesan itu sudah mencapai batas jumlah reaction.",
+        // Reaction blocked
+        Some(90001) => "Reaction diblokir karena penulis pesan memblokir bot.",
+        _ => "Gagal menambahkan react. Bot mungkin tidak punya permission.",
+    }
+}
+
+fn discord_error_code(e: &serenity::Error) -> Option<isize> {
+    match e {
+        serenity::Error::Http(serenity::http::HttpError::UnsuccessfulRequest(resp)) => {
+            Some(resp.error.code)
+        }
+        _ => None,
+    }
+}
+
 /// Parse message link to extract channel_id and message_id
 fn parse_message_link(input: &str) -> Option<(u64, u64)> {
     // Format: https://discord.com/channels/GUILD_ID/CHANNEL_ID/MESSAGE_ID
@@ -103,9 +218,11 @@ pub async fn react(
         ))
         .color(colors::INFO);
 
+    // The guild of the message, normally the one /react ran in, so its emojis work there
+    let emojis = merge_emojis(guild_emojis(ctx, guild_channel.guild_id).await, EMOJIS);
+
     // Generate emoji buttons
-    let mut current_page = 0;
-    let components = generate_emoji_rows(current_page, channel_id, message_id);
+    let components = generate_emoji_rows(&emojis, 0);
 
     let reply = ctx
         .send(
@@ -129,72 +246,63 @@ pub async fn react(
     while let Some(interaction) = collector.next().await {
         let custom_id = &interaction.data.custom_id;
 
-        if custom_id.starts_with("react_") {
-            // Extract emoji ID
-            let parts: Vec<&str> = custom_id.split('_').collect();
-            if parts.len() >= 2 {
-                let emoji_id = parts[1];
-
-                // Try to react
-                let reaction = serenity::ReactionType::Custom {
-                    animated: true,
-                    id: serenity::EmojiId::new(emoji_id.parse().unwrap_or(0)),
-                    name: get_emoji_by_id(emoji_id).map(|e| e.name.to_string()),
-                };
-
-                match message.react(ctx.http(), reaction).await {
-                    Ok(_) => {
-                        let emoji_name =
-                            get_emoji_by_id(emoji_id).map(|e| e.name).unwrap_or("emoji");
-
-                        let success_embed = serenity::CreateEmbed::new()
-                            .title("React Berhasil")
-                            .description(format!(
-                                "Pesan berhasil direact dengan emoji **{}**",
-                                emoji_name
-                            ))
-                            .color(0x00FF00)
-                            .image(format!(
-                                "https://cdn.discordapp.com/emojis/{}.gif",
-                                emoji_id
-                            ))
-                            .footer(serenity::CreateEmbedFooter::new(format!(
-                                "Emoji ID: {}",
-                                emoji_id
-                            )));
-
-                        let _ = interaction
-                            .create_response(
-                                ctx.http(),
-                                serenity::CreateInteractionResponse::UpdateMessage(
-                                    serenity::CreateInteractionResponseMessage::new()
-                                        .embed(success_embed)
-                                        .components(vec![]),
-                                ),
-                            )
-                            .await;
-                        break;
-                    }
-                    Err(e) => {
-                        error!("Failed to react: {:?}", e);
-                        let _ = interaction.create_response(
+        if let Some(emoji_id) = custom_id.strip_prefix("react_") {
+            let Some(emoji) = emoji_id
+                .parse::<u64>()
+                .ok()
+                .and_then(|id| emojis.iter().find(|e| e.id == id))
+            else {
+                continue;
+            };
+
+            match message.react(ctx.http(), emoji.reaction()).await {
+                Ok(_) => {
+                    let success_embed = serenity::CreateEmbed::new()
+                        .title("React Berhasil")
+                        .description(format!(
+                            "Pesan berhasil direact dengan emoji **{}**",
+                            emoji.name
+                        ))
+                        .color(0x00FF00)
+                        .image(emoji.image_url())
+                        .footer(serenity::CreateEmbedFooter::new(format!(
+                            "Emoji ID: {}",
+                            emoji.id
+                        )));
+
+                    let _ = interaction
+                        .create_response(
+                            ctx.http(),
+                            serenity::CreateInteractionResponse::UpdateMessage(
+                                serenity::CreateInteractionResponseMessage::new()
+                                    .embed(success_embed)
+                                    .components(vec![]),
+                            ),
+                        )
+                        .await;
+                    break;
+                }
+                Err(e) => {
+                    error!("Failed to react: {:?}", e);
+                    let _ = interaction
+                        .create_response(
                             ctx.http(),
                             serenity::CreateInteractionResponse::Message(
                                 serenity::CreateInteractionResponseMessage::new()
-                                    .content("Gagal menambahkan react. Bot mungkin tidak punya permission.")
-                                    .ephemeral(true)
-                            )
-                        ).await;
-                    }
+                                    .content(react_failure_message(discord_error_code(&e)))
+                                    .ephemeral(true),
+                            ),
+                        )
+                        .await;
                 }
             }
         } else if custom_id.starts_with("page_") {
             // Pagination
             let parts: Vec<&str> = custom_id.split('_').collect();
             if parts.len() >= 2 {
-                current_page = parts[1].parse().unwrap_or(0);
+                let page = parts[1].parse().unwrap_or(0);
 
-                let components = generate_emoji_rows(current_page, channel_id, message_id);
+                let components = generate_emoji_rows(&emojis, page);
 
                 let _ = interaction
                     .create_response(
@@ -213,13 +321,11 @@ pub async fn react(
     Ok(())
 }
 
-fn generate_emoji_rows(
-    page: usize,
-    _channel_id: u64,
-    _message_id: u64,
-) -> Vec<serenity::CreateActionRow> {
+fn generate_emoji_rows(emojis: &[ReactEmoji], page: usize) -> Vec<serenity::CreateActionRow> {
+    let total_pages = total_pages(emojis.len());
+    let page = page.min(total_pages - 1);
     let start = page * EMOJIS_PER_PAGE;
-    let page_emojis: Vec<_> = EMOJIS.iter().skip(start).take(EMOJIS_PER_PAGE).collect();
+    let page_emojis: Vec<_> = emojis.iter().skip(start).take(EMOJIS_PER_PAGE).collect();
 
     let mut rows = Vec::new();
 
@@ -230,11 +336,7 @@ fn generate_emoji_rows(
             .map(|emoji| {
                 serenity::CreateButton::new(format!("react_{}", emoji.id))
                     .style(serenity::ButtonStyle::Secondary)
-                    .emoji(serenity::ReactionType::Custom {
-                        animated: true,
-                        id: serenity::EmojiId::new(emoji.id.parse().unwrap_or(0)),
-                        name: Some(emoji.name.to_string()),
-                    })
+                    .emoji(emoji.reaction())
             })
             .collect();
 
@@ -242,7 +344,6 @@ fn generate_emoji_rows(
     }
 
     // Navigation buttons
-    let total_pages = (EMOJIS.len() + EMOJIS_PER_PAGE - 1) / EMOJIS_PER_PAGE;
     if total_pages > 1 {
         let nav_buttons = vec![
             serenity::CreateButton::new(format!("page_{}", page.saturating_sub(1)))
@@ -259,3 +360,68 @@ fn generate_emoji_rows(
 
     rows
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn emoji(id: u64, name: &str, animated: bool) -> ReactEmoji {
+        ReactEmoji {
+            id,
+            name: name.to_string(),
+            animated,
+        }
+    }
+
+    #[test]
+    fn test_merge_prefers_guild_emojis() {
+        let hardcoded = [
+            Emoji {
+                id: "1",
+                name: "old_name",
+            },
+            Emoji {
+                id: "2",
+                name: "fire",
+            },
+        ];
+        let merged = merge_emojis(
+            vec![emoji(5, "wave", true), emoji(1, "new_name", false)],
+            &hardcoded,
+        );
+        assert_eq!(
+            merged,
+            vec![
+                emoji(5, "wave", true),
+                emoji(1, "new_name", false),
+                emoji(2, "fire", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pages_follow_emoji_count() {
+        assert_eq!(total_pages(0), 1);
+        assert_eq!(total_pages(20), 1);
+        assert_eq!(total_pages(21), 2);
+
+        let emojis: Vec<ReactEmoji> = (1..=45).map(|id| emoji(id, "e", true)).collect();
+        // 4 rows of emojis and the navigation row, the last page has 5 left
+        assert_eq!(generate_emoji_rows(&emojis, 0).len(), 5);
+        assert_eq!(generate_emoji_rows(&emojis, 2).len(), 2);
+        // Past the end shows the last page
+        assert_eq!(generate_emoji_rows(&emojis, 9).len(), 2);
+        // A single page has no navigation
+        assert_eq!(generate_emoji_rows(&emojis[..3], 0).len(), 1);
+    }
+
+    #[test]
+    fn test_react_failure_message() {
+        assert!(react_failure_message(Some(50013)).contains("Add Reactions"));
+        assert!(react_failure_message(Some(10014)).contains("tidak tersedia"));
+        assert_eq!(
+            react_failure_message(None),
+            react_failure_message(Some(12345))
+        );
+    }
+}
@@ -0,0 +1,160 @@
+// Recalculate command - rebuild stats from immersion logs
+
+use poise::serenity_prelude as serenity;
+use tracing::{error, info};
+
+use crate::commands::config::check_access;
+use crate::models::user::UserDoc;
+use crate::utils::stats_rebuild;
+use crate::{Context, Data, Error};
+
+/// How long the Apply button stays usable
+const CONFIRM_TIMEOUT_SECS: u64 = 120;
+
+/// Rebuild your stats from your immersion logs
+#[poise::command(slash_command)]
+pub async fn recalculate(
+    ctx: Context<'_>,
+    #[description = "Member to recalculate (needs Manage Server)"] user: Option<serenity::User>,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let target = user.unwrap_or_else(|| ctx.author().clone());
+    if target.id != ctx.author().id && !check_access(ctx).await? {
+        ctx.say("You need the Manage Server permission to recalculate someone else's stats.")
+            .await?;
+        return Ok(());
+    }
+    let user_id = target.id.to_string();
+
+    let (before, after) = match rebuild(ctx.data(), &user_id).await {
+        Ok(Some(docs)) => docs,
+        Ok(None) => {
+            ctx.say(format!("{} has no stats yet.", target.name))
+                .await?;
+            return Ok(());
+        }
+        Err(e) => {
+            error!("Failed to rebuild stats of {}: {:?}", user_id, e);
+            ctx.say("Failed to load the logs, try again later.").await?;
+            return Ok(());
+        }
+    };
+
+    let changes = stats_rebuild::diff(&before, &after);
+    if changes.is_empty() {
+        ctx.say(format!("{}'s stats already match their logs.", target.name))
+            .await?;
+        return Ok(());
+    }
+
+    let lines: Vec<String> = changes.iter().map(|c| c.line()).collect();
+    let embed = serenity::CreateEmbed::new()
+        .title(format!("Recalculated stats for {}", target.name))
+        .description(lines.join("\n"))
+        .color(0xf1c40f)
+        .footer(serenity::CreateEmbedFooter::new(
+            "Nothing changes until you press Apply",
+        ));
+    let buttons = vec![serenity::CreateActionRow::Buttons(vec![
+        serenity::CreateButton::new("recalculate_apply")
+            .label("Apply")
+            .style(serenity::ButtonStyle::Primary),
+        serenity::CreateButton::new("recalculate_cancel")
+            .label("Cancel")
+            .style(serenity::ButtonStyle::Secondary),
+    ])];
+    let reply = ctx
+        .send(
+            poise::CreateReply::default()
+                .embed(embed.clone())
+                .components(buttons),
+        )
+        .await?;
+    let msg = reply.message().await?.into_owned();
+
+    let interaction = msg
+        .await_component_interaction(ctx.serenity_context())
+        .author_id(ctx.author().id)
+        .timeout(std::time::Duration::from_secs(CONFIRM_TIMEOUT_SECS))
+        .await;
+
+    let Some(interaction) = interaction else {
+        let _ = reply
+            .edit(
+                ctx,
+                poise::CreateReply::default()
+                    .embed(embed)
+                    .content("Timed out, nothing was changed.")
+                    .components(vec![]),
+            )
+            .await;
+        return Ok(());
+    };
+
+    // The rebuild can outlast the three seconds Discord waits for an answer
+    let _ = interaction
+        .create_response(ctx.http(), serenity::CreateInteractionResponse::Acknowledge)
+        .await;
+
+    let outcome = if interaction.data.custom_id != "recalculate_apply" {
+        "Cancelled, nothing was changed.".to_string()
+    } else {
+        // Rebuilt again so a log saved while the diff was open isn't undone
+        match apply(ctx.data(), &user_id).await {
+            Ok(()) => {
+                info!(
+                    "{} recalculated stats of {}: {}",
+                    ctx.author().id,
+                    user_id,
+                    lines.join("; ")
+                );
+                "Stats updated.".to_string()
+            }
+            Err(e) => {
+                error!("Failed to save rebuilt stats of {}: {:?}", user_id, e);
+                "Failed to save the stats, nothing was changed.".to_string()
+            }
+        }
+    };
+
+    let _ = interaction
+        .edit_response(
+            ctx.http(),
+            serenity::EditInteractionResponse::new()
+                .content(outcome)
+                .embed(embed)
+                .components(vec![]),
+        )
+        .await;
+
+    Ok(())
+}
+
+/// The stored user document and the same document rebuilt from the logs, `None` for
+/// users without one
+async fn rebuild(data: &Data, user_id: &str) -> anyhow::Result<Option<(UserDoc, UserDoc)>> {
+    let Some(before) = data.firebase.get_user(user_id).await? else {
+        return Ok(None);
+    };
+    let logs = data.firebase.get_user_logs(user_id).await?;
+    let mut after = before.clone();
+    stats_rebuild::apply(
+        &mut after,
+        &stats_rebuild::rebuild_stats(&logs, data.clock.as_ref()),
+    );
+    Ok(Some((before, after)))
+}
+
+/// Rebuild and write only the rebuilt fields, leaving profile, goals and the rest alone
+async fn apply(data: &Data, user_id: &str) -> anyhow::Result<()> {
+    let Some((_, after)) = rebuild(data, user_id).await? else {
+        return Ok(());
+    };
+    let value = serde_json::to_value(&after)?;
+    let paths = stats_rebuild::field_paths(&after);
+    let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+    data.firebase
+        .set_document_merge_paths("users", user_id, &value, &paths)
+        .await
+}
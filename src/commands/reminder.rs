@@ -0,0 +1,99 @@
+// Reminder command - opt in to DMs that keep a streak from breaking
+// The setting lives on the user document, `streak_reminders` indexes who is opted in
+
+use serde_json::json;
+use tracing::{error, info};
+
+use crate::features::streak_reminder::{self, DEFAULT_HOUR};
+use crate::{Context, Error};
+
+/// Whether to get the reminder
+#[derive(Debug, Clone, Copy, PartialEq, poise::ChoiceParameter)]
+pub enum ReminderState {
+    #[name = "on"]
+    On,
+    #[name = "off"]
+    Off,
+}
+
+/// Reminders sent by DM
+#[poise::command(slash_command, subcommands("reminder_streak"))]
+pub async fn reminder(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Get a DM on days you have a streak going and haven't logged yet
+#[poise::command(slash_command, rename = "streak")]
+pub async fn reminder_streak(
+    ctx: Context<'_>,
+    #[description = "Turn the reminder on or off"] state: ReminderState,
+    #[description = "Hour to remind you at, in your /timezone or WIB (default 20)"]
+    #[min = 0]
+    #[max = 23]
+    hour: Option<u32>,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let data = ctx.data();
+    let user_id = ctx.author().id.to_string();
+    let enabled = state == ReminderState::On;
+    let user = data.firebase.get_user(&user_id).await.ok().flatten();
+    let timezone = user.as_ref().and_then(|u| u.profile.timezone.clone());
+    let hour = match hour {
+        Some(hour) => hour,
+        // Turning it back on keeps the hour picked before
+        None => user
+            .and_then(|user| user.streak_reminder())
+            .map_or(DEFAULT_HOUR, |settings| settings.hour),
+    };
+
+    let saved = data
+        .firebase
+        .set_document_merge_paths(
+            "users",
+            &user_id,
+            &json!({ "reminders": { "streak": { "enabled": enabled, "hour": hour } } }),
+            &["reminders.streak.enabled", "reminders.streak.hour"],
+        )
+        .await;
+    let indexed = match saved {
+        Ok(()) if enabled => {
+            data.firebase
+                .set_document(
+                    streak_reminder::COLLECTION,
+                    &user_id,
+                    &streak_reminder::index_doc(hour, timezone.as_deref()),
+                )
+                .await
+        }
+        Ok(()) => {
+            data.firebase
+                .delete_document(streak_reminder::COLLECTION, &user_id)
+                .await
+        }
+        Err(e) => Err(e),
+    };
+    if let Err(e) = indexed {
+        error!("Failed to save streak reminder: {:?}", e);
+        ctx.say("Failed to save your reminder setting. Please try again later.")
+            .await?;
+        return Ok(());
+    }
+    info!(
+        "User {} turned streak reminders {}",
+        user_id,
+        if enabled { "on" } else { "off" }
+    );
+
+    if enabled {
+        ctx.say(format!(
+            "Streak reminders are on. On days you have a streak going and nothing logged yet, I'll DM you at {:02}:00 {}. Keep your DMs open so they reach you.",
+            hour,
+            timezone.as_deref().unwrap_or("WIB")
+        ))
+        .await?;
+    } else {
+        ctx.say("Streak reminders are off.").await?;
+    }
+    Ok(())
+}
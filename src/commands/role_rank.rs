@@ -1,36 +1,224 @@
 use poise::serenity_prelude as serenity;
-use tracing::error;
+use tracing::{error, info};
 
-use crate::features::role_rank::QUIZZES;
+use crate::commands::config::check_access;
+use crate::features::role_rank::{
+    guild_level_counts, hierarchy_hint, highest_quiz_level, level_label, next_quiz,
+    quiz_channel_overwrites, quizzes_by_level, remove_quiz_roles, RoleRemoval, QUIZZES,
+    QUIZ_SELECT_ID,
+};
+use crate::features::role_rank_audit;
+use crate::models::guild::GuildConfig;
+use crate::utils::config::colors;
+use crate::utils::config::get_guild_config;
+use crate::utils::discord::send_checked;
+use crate::utils::i18n::{phrase, Language, Msg};
 use crate::{Context, Error};
 
 /// Manage Role Rank (Quiz) system
+// Admin subcommands require MANAGE_GUILD themselves, parent permissions would lock out status/stats
 #[poise::command(
     slash_command,
     prefix_command,
-    required_permissions = "MANAGE_GUILD",
-    subcommands("setup", "delete")
+    subcommands(
+        "setup",
+        "delete",
+        "clear",
+        "migrate_category",
+        "status",
+        "stats",
+        "history"
+    )
 )]
 pub async fn role_rank(_ctx: Context<'_>) -> Result<(), Error> {
     Ok(())
 }
 
+/// Show your quiz level, the next quiz and your active quiz channel
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn status(ctx: Context<'_>) -> Result<(), Error> {
+    let Some(member) = ctx.author_member().await else {
+        ctx.say("Could not load your server membership.").await?;
+        return Ok(());
+    };
+    let config = get_guild_config(ctx.data(), &member.guild_id.to_string()).await;
+    let level = highest_quiz_level(&member.roles, config.as_ref());
+    let current = QUIZZES.values().find(|q| q.level == level);
+
+    let current_text = current
+        .map(|q| format!("**{}**", q.label))
+        .unwrap_or_else(|| "No quiz role yet".to_string());
+    let next_text = match next_quiz(level) {
+        Some(q) => format!("**{}**\n{}", q.label, q.description),
+        None => "You have the highest level 🎉".to_string(),
+    };
+    let session_text = match ctx.data().role_rank_sessions.get(&ctx.author().id) {
+        Some(session) => {
+            let quiz = QUIZZES.get(&session.quiz_id);
+            format!(
+                "<#{}> — {} (stage {}/{})",
+                session.thread_id,
+                quiz.map(|q| q.label).unwrap_or(session.quiz_id.as_str()),
+                session.progress + 1,
+                quiz.map(|q| q.commands.len()).unwrap_or(1)
+            )
+        }
+        None => "None".to_string(),
+    };
+
+    let embed = serenity::CreateEmbed::new()
+        .title(format!("Role Rank - {}", ctx.author().display_name()))
+        .field("Current Level", current_text, false)
+        .field("Next Quiz", next_text, false)
+        .field("Active Session", session_text, false)
+        .thumbnail(ctx.author().face())
+        .color(colors::INFO);
+    ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
+
+/// Show how many members hold each quiz level
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn stats(ctx: Context<'_>) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    ctx.defer().await?;
+
+    let counts = match guild_level_counts(ctx.http(), ctx.data(), guild_id).await {
+        Ok(counts) => counts,
+        Err(e) => {
+            error!("Failed to count quiz levels in guild {}: {:?}", guild_id, e);
+            ctx.say("Failed to load the member list.").await?;
+            return Ok(());
+        }
+    };
+
+    let mut quizzes: Vec<_> = QUIZZES.values().collect();
+    quizzes.sort_by_key(|q| q.level);
+    let mut lines: Vec<String> = quizzes
+        .iter()
+        .map(|q| {
+            format!(
+                "**{}**: {}",
+                q.label,
+                counts.get(&q.level).copied().unwrap_or(0)
+            )
+        })
+        .collect();
+    let ranked: usize = counts
+        .iter()
+        .filter(|(&level, _)| level >= 0)
+        .map(|(_, n)| n)
+        .sum();
+    lines.push(format!(
+        "\n**{}** members with a quiz role, **{}** without",
+        ranked,
+        counts.get(&-1).copied().unwrap_or(0)
+    ));
+
+    let embed = serenity::CreateEmbed::new()
+        .title("Role Rank - Members per Level")
+        .description(lines.join("\n"))
+        .footer(serenity::CreateEmbedFooter::new(
+            "Members holding several quiz roles count at their highest level. Updated every few minutes.",
+        ))
+        .color(colors::INFO);
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
+/// Promotions shown by `/role_rank history`
+const HISTORY_LIMIT: usize = 10;
+
+/// Show a member's latest quiz role promotions (Admin only)
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD"
+)]
+pub async fn history(
+    ctx: Context<'_>,
+    #[description = "Member to look up"] user: serenity::Member,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let records = match role_rank_audit::load_user_history(
+        ctx.data().firebase.as_ref(),
+        &user.guild_id.to_string(),
+        &user.user.id.to_string(),
+        HISTORY_LIMIT,
+    )
+    .await
+    {
+        Ok(records) => records,
+        Err(e) => {
+            error!("Failed to load promotion history: {:?}", e);
+            ctx.say("Failed to load the promotion history.").await?;
+            return Ok(());
+        }
+    };
+
+    let description = if records.is_empty() {
+        "No promotions recorded.".to_string()
+    } else {
+        records
+            .iter()
+            .map(|r| {
+                let when = chrono::DateTime::parse_from_rfc3339(&r.timestamp)
+                    .map(|t| format!("<t:{}:f>", t.timestamp()))
+                    .unwrap_or_else(|_| r.timestamp.clone());
+                format!(
+                    "{} — {} → **{}** in <#{}>",
+                    when,
+                    level_label(r.from_level),
+                    level_label(r.to_level),
+                    r.channel_id
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let embed = serenity::CreateEmbed::new()
+        .title(format!("Role Rank History - {}", user.display_name()))
+        .description(description)
+        .thumbnail(user.face())
+        .footer(serenity::CreateEmbedFooter::new(format!(
+            "Latest {} promotions",
+            HISTORY_LIMIT
+        )))
+        .color(colors::INFO);
+    ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
+
 /// Setup the quiz selector in the current channel
 #[poise::command(slash_command, prefix_command, required_permissions = "MANAGE_GUILD")]
 pub async fn setup(ctx: Context<'_>) -> Result<(), Error> {
     ctx.defer().await?;
 
-    // Call helper
-    send_quiz_selector(ctx.http(), ctx.channel_id()).await?;
+    let config = match ctx.guild_id() {
+        Some(guild_id) => get_guild_config(ctx.data(), &guild_id.to_string()).await,
+        None => None,
+    };
+    send_quiz_selector(ctx, ctx.channel_id(), None, config.as_ref()).await?;
 
     Ok(())
 }
 
-/// Helper function to send/resend the quiz selector
-pub async fn send_quiz_selector(
-    http: &serenity::Http,
-    channel_id: serenity::ChannelId,
-) -> Result<(), Error> {
+/// Title of the quiz selector when the guild config sets none
+pub const DEFAULT_SELECTOR_TITLE: &str = "Quiz Selector";
+
+/// The quiz dropdown, nothing selected
+pub fn quiz_select_row(config: Option<&GuildConfig>) -> serenity::CreateActionRow {
+    let lang = Language::of(config);
     // Create Dropdown Options from QUIZZES
     // Sort logic: we want levels 0-7 ordered.
     // HashMap iteration order is random, so collect and sort.
@@ -46,35 +234,56 @@ pub async fn send_quiz_selector(
     }
 
     let select_menu = serenity::CreateSelectMenu::new(
-        "quiz_select",
+        QUIZ_SELECT_ID,
         serenity::CreateSelectMenuKind::String { options },
     )
-    .placeholder("Pilih Quiz / Select Quiz")
+    .placeholder(phrase(lang, Msg::QuizSelectorPlaceholder))
     .min_values(1)
     .max_values(1);
 
-    let row = serenity::CreateActionRow::SelectMenu(select_menu);
+    serenity::CreateActionRow::SelectMenu(select_menu)
+}
+
+/// Helper function to send/resend the quiz selector, titled and described as the guild
+/// config says. When the bot can't post there, `report_to` hears about it (see `send_checked`).
+pub async fn send_quiz_selector(
+    cache_http: impl serenity::CacheHttp,
+    channel_id: serenity::ChannelId,
+    report_to: Option<serenity::ChannelId>,
+    config: Option<&GuildConfig>,
+) -> Result<(), Error> {
+    let lang = Language::of(config);
+    let row = quiz_select_row(config);
 
     let embed = serenity::CreateEmbed::new()
-        .title("Quiz Selector")
-        .description("Pilih quiz di bawah ini untuk memulai tes kenaikan role.\nSelect a quiz below to start the role advancement test.")
+        .title(
+            config
+                .and_then(|c| c.quiz_selector_title.as_deref())
+                .unwrap_or(DEFAULT_SELECTOR_TITLE),
+        )
+        .description(
+            config
+                .and_then(|c| c.quiz_selector_description.as_deref())
+                .unwrap_or(phrase(lang, Msg::QuizSelectorDescription)),
+        )
         .color(0x00ADEF)
         .image("https://media.discordapp.net/attachments/1176743181803602022/1329665790408261683/role_rank_header.png?ex=6790757d&is=678f23fd&hm=0856017300438183060768407484742790956488390770678125477430045472&"); // Placeholder or use the one from original if available
 
-    channel_id
-        .send_message(
-            http,
-            serenity::CreateMessage::new()
-                .embed(embed)
-                .components(vec![row]),
-        )
-        .await?;
+    send_checked(
+        cache_http,
+        channel_id,
+        serenity::CreateMessage::new()
+            .embed(embed)
+            .components(vec![row]),
+        report_to,
+    )
+    .await?;
 
     Ok(())
 }
 
-/// Manually delete a quiz channel (Admin only)
-#[poise::command(slash_command, prefix_command, required_permissions = "MANAGE_GUILD")]
+/// Delete a quiz channel, your own or any as an admin
+#[poise::command(slash_command, prefix_command, guild_only)]
 pub async fn delete(ctx: Context<'_>) -> Result<(), Error> {
     ctx.defer_ephemeral().await?;
 
@@ -84,45 +293,47 @@ pub async fn delete(ctx: Context<'_>) -> Result<(), Error> {
         let guild_id = gc.guild_id.to_string();
         let data = ctx.data();
 
-        let category_id = if let Some(config) = data.guild_configs.get(&guild_id) {
-            config
-                .quiz_category_id
-                .as_ref()
-                .and_then(|id| id.parse::<u64>().ok())
-                .map(serenity::ChannelId::new)
-        } else {
-            None
-        };
-
-        if let Some(cat_id) = category_id {
-            if gc.parent_id == Some(cat_id) {
-                // Check if this is the configured selector channel
-                if let Some(config) = data.guild_configs.get(&guild_id) {
-                    if let Some(selector_id) = &config.quiz_channel_id {
-                        if gc.id.to_string() == *selector_id {
-                            ctx.say("Cannot delete main selector channel (Protected via Config).")
-                                .await?;
-                            return Ok(());
-                        }
-                    }
-                }
+        if !crate::features::role_rank::is_quiz_channel(data, &gc).await {
+            ctx.say("This command can only be used in quiz channels.")
+                .await?;
+            return Ok(());
+        }
 
-                ctx.say("Deleting channel in 3 seconds...").await?;
-                tokio::time::sleep(std::time::Duration::from_secs(3)).await;
+        // Quiz takers may close their own channel, anyone else's needs MANAGE_GUILD
+        let owns_channel = data
+            .role_rank_sessions
+            .get(&ctx.author().id)
+            .is_some_and(|session| session.thread_id == gc.id);
+        if !owns_channel && !check_access(ctx).await? {
+            ctx.say("Only the quiz taker or someone with MANAGE_GUILD can delete this channel.")
+                .await?;
+            return Ok(());
+        }
 
-                if let Err(e) = gc.delete(&ctx.http()).await {
-                    error!("Failed to delete channel: {:?}", e);
-                    ctx.say(format!("Failed to delete channel: {}", e)).await?;
-                } else {
-                    let data = ctx.data();
-                    data.role_rank_sessions.retain(|_, v| v.thread_id != gc.id);
-                    crate::features::role_rank::persist_active_sessions(&data.role_rank_sessions);
+        // Check if this is the configured selector channel
+        if let Some(config) = crate::utils::config::get_guild_config(data, &guild_id).await {
+            if let Some(selector_id) = &config.quiz_channel_id {
+                if gc.id.to_string() == *selector_id {
+                    ctx.say("Cannot delete main selector channel (Protected via Config).")
+                        .await?;
+                    return Ok(());
                 }
-            } else {
-                ctx.say("This command can only be used in quiz channels.")
-                    .await?;
             }
-        } // closing category_id
+        }
+
+        ctx.say("Deleting channel in 3 seconds...").await?;
+        tokio::time::sleep(std::time::Duration::from_secs(3)).await;
+
+        if let Err(e) = gc.delete(&ctx.http()).await {
+            error!("Failed to delete channel: {:?}", e);
+            ctx.say(format!("Failed to delete channel: {}", e)).await?;
+        } else {
+            data.role_rank_sessions.retain(|_, v| v.thread_id != gc.id);
+            crate::features::role_rank::persist_active_sessions(
+                &data.firebase,
+                &data.role_rank_sessions,
+            );
+        }
     } else {
         ctx.say("This command must be used in a guild channel.")
             .await?;
@@ -130,3 +341,202 @@ pub async fn delete(ctx: Context<'_>) -> Result<(), Error> {
 
     Ok(())
 }
+
+/// Remove all quiz roles from a member and end their quiz session (Admin only)
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD"
+)]
+pub async fn clear(
+    ctx: Context<'_>,
+    #[description = "Member to reset"] user: serenity::Member,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    let data = ctx.data();
+
+    let config = get_guild_config(data, &user.guild_id.to_string()).await;
+    let config = config.as_ref();
+    let outcomes = remove_quiz_roles(ctx.http(), &user, &quizzes_by_level(), config).await;
+    let mut lines: Vec<String> = outcomes
+        .iter()
+        .map(|(quiz, outcome)| format!("<@&{}> {}", quiz.role_in(config), outcome.status()))
+        .collect();
+    let any_failed = outcomes.iter().any(|(_, outcome)| outcome.is_failure());
+
+    if let Some((_, session)) = data.role_rank_sessions.remove(&user.user.id) {
+        crate::features::role_rank::persist_active_sessions(
+            &data.firebase,
+            &data.role_rank_sessions,
+        );
+        lines.push(format!(
+            "\nQuiz session ended, its channel <#{}> is left for `/role_rank delete`.",
+            session.thread_id
+        ));
+    }
+
+    let refused = outcomes
+        .iter()
+        .find(|(_, outcome)| *outcome == RoleRemoval::MissingPermissions);
+    if let Some((quiz, _)) = refused {
+        if let Some(hint) =
+            hierarchy_hint(ctx.serenity_context(), user.guild_id, quiz.role_in(config)).await
+        {
+            lines.push(format!("\n{}", hint));
+        }
+    }
+
+    info!(
+        "{} cleared quiz roles of {} in guild {}",
+        ctx.author().id,
+        user.user.id,
+        user.guild_id
+    );
+
+    let embed = serenity::CreateEmbed::new()
+        .title(if any_failed {
+            "Reset Incomplete"
+        } else {
+            "Reset Complete"
+        })
+        .description(format!("<@{}>\n{}", user.user.id, lines.join("\n")))
+        .color(if any_failed {
+            colors::WARNING
+        } else {
+            colors::SUCCESS
+        });
+    ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
+
+/// Move all active quiz channels to a new category and make it the configured one
+#[poise::command(
+    slash_command,
+    prefix_command,
+    rename = "migrate-category",
+    required_permissions = "MANAGE_GUILD"
+)]
+pub async fn migrate_category(
+    ctx: Context<'_>,
+    #[description = "New quiz category"]
+    #[channel_types("Category")]
+    new_category: serenity::GuildChannel,
+) -> Result<(), Error> {
+    let guild_id = match ctx.guild_id() {
+        Some(id) => id,
+        None => {
+            ctx.say("This command can only be used in a server.")
+                .await?;
+            return Ok(());
+        }
+    };
+    if new_category.kind != serenity::ChannelType::Category || new_category.guild_id != guild_id {
+        ctx.say("Please pick a category from this server.").await?;
+        return Ok(());
+    }
+
+    ctx.defer().await?;
+    let data = ctx.data();
+    let bot_id = ctx.cache().current_user().id;
+    let quiz_bots = crate::features::role_rank::guild_quiz_bots(data, guild_id);
+
+    // Snapshot first so no map guard is held across Discord calls
+    let sessions: Vec<(serenity::UserId, serenity::ChannelId)> = data
+        .role_rank_sessions
+        .iter()
+        .map(|e| (*e.key(), e.thread_id))
+        .collect();
+
+    let mut lines = Vec::new();
+    let mut moved = 0usize;
+    let mut failed = 0usize;
+    for (user_id, channel_id) in sessions {
+        // Sessions are global, only touch channels of this server
+        let channel = match channel_id.to_channel(ctx).await.map(|c| c.guild()) {
+            Ok(Some(gc)) if gc.guild_id == guild_id => gc,
+            Ok(_) => continue,
+            Err(e) => {
+                failed += 1;
+                lines.push(format!("❌ `{}`: {}", channel_id, e));
+                continue;
+            }
+        };
+
+        let edit = serenity::EditChannel::new()
+            .category(new_category.id)
+            .permissions(quiz_channel_overwrites(
+                guild_id, user_id, bot_id, &quiz_bots,
+            ));
+        match channel.id.edit(ctx, edit).await {
+            Ok(_) => {
+                moved += 1;
+                if let Some(mut session) = data.role_rank_sessions.get_mut(&user_id) {
+                    session.category_id = Some(new_category.id);
+                }
+                lines.push(format!("✅ <#{}>", channel.id));
+            }
+            Err(e) => {
+                failed += 1;
+                error!("Failed to move quiz channel {}: {:?}", channel.id, e);
+                lines.push(format!("❌ <#{}>: {}", channel.id, e));
+            }
+        }
+    }
+    crate::features::role_rank::persist_active_sessions(&data.firebase, &data.role_rank_sessions);
+
+    // Point the config at the new category
+    let guild_id_str = guild_id.to_string();
+    let config_saved =
+        match crate::commands::config::load_config_for_update(data, &guild_id_str).await {
+            Ok(mut config) => {
+                config.quiz_category_id = Some(new_category.id.to_string());
+                let json_val = serde_json::to_value(&config)?;
+                match data
+                    .firebase
+                    .set_document("guilds", &guild_id_str, &json_val)
+                    .await
+                {
+                    Ok(_) => {
+                        data.guild_configs.insert(guild_id_str.clone(), config);
+                        true
+                    }
+                    Err(e) => {
+                        error!("Failed to save guild config: {:?}", e);
+                        false
+                    }
+                }
+            }
+            Err(e) => {
+                error!("Failed to fetch guild config: {:?}", e);
+                false
+            }
+        };
+
+    info!(
+        "Migrated quiz category for guild {} to {}: {} moved, {} failed",
+        guild_id, new_category.id, moved, failed
+    );
+
+    let mut report = format!(
+        "Quiz category set to **{}**. {} channel(s) moved, {} failed.",
+        new_category.name, moved, failed
+    );
+    if !config_saved {
+        report.push_str("\n⚠️ The configuration could not be saved, run `/config set` again.");
+    }
+    if !lines.is_empty() {
+        report.push_str("\n\n");
+        report.push_str(&lines.join("\n"));
+    }
+    // Stay under the message limit when many channels are listed
+    if report.chars().count() > 1900 {
+        report = report.chars().take(1900).collect::<String>() + "\n…";
+    }
+
+    ctx.say(report).await?;
+
+    Ok(())
+}
@@ -2,14 +2,30 @@
 // Ported from commands/stat.js
 
 use poise::serenity_prelude as serenity;
-use tracing::error;
+use serde_json::Value;
+use std::collections::HashMap;
+use tracing::{error, warn};
 
-use crate::utils::config::{colors, get_media_label, get_unit};
-use crate::utils::points::calculate_points;
+use crate::api::jpdb;
+use crate::commands::immersion::{parse_custom_date, MediaType, INVALID_DATE_MESSAGE};
+use crate::models::goal;
+use crate::models::user::UserStats;
+use crate::utils::config::{
+    colors, effective_date_at, effective_date_in, get_effective_date, get_guild_config,
+    get_media_label, get_unit,
+};
+use crate::utils::daily::{self, DailyTotals, DayDetail};
+use crate::utils::images;
+use crate::utils::points::calculate_points_with;
+use crate::utils::privacy::{is_private_log, public_logs, LeaderboardPrivacy, LOOKUP_OPTED_OUT};
+use crate::utils::reading_speed;
 use crate::utils::streak;
-use crate::utils::visualizations::{generate_bar_chart, generate_heatmap, BarData};
+use crate::utils::time_of_day;
+use crate::utils::visualizations::{
+    generate_bar_chart, generate_heatmap, generate_line_chart, generate_stacked_bar_chart,
+    heatmap_active_days, BarData, ChartTheme, HeatmapRange,
+};
 use crate::{Context, Error};
-use chrono::{DateTime, Datelike};
 
 /// Visualization type choices
 #[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
@@ -18,8 +34,25 @@ pub enum VisualType {
     Barchart,
     #[name = "Heatmap"]
     Heatmap,
+    #[name = "Reading Speed"]
+    ReadingSpeed,
+    #[name = "Daily Chart"]
+    DailyChart,
+    #[name = "Time of Day"]
+    TimeOfDay,
 }
 
+/// Every supported media type, in the order charts draw them
+const MEDIA_TYPES: [&str; 7] = [
+    "anime",
+    "listening",
+    "reading",
+    "manga",
+    "visual_novel",
+    "book",
+    "reading_time",
+];
+
 /// Days choice for bar chart
 #[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
 pub enum DaysChoice {
@@ -29,56 +62,80 @@ pub enum DaysChoice {
     ThirtyDays = 30,
 }
 
-/// View your immersion statistics
+/// View your (or another member's) immersion statistics
 #[poise::command(slash_command, prefix_command)]
 pub async fn stat(
     ctx: Context<'_>,
     #[description = "Pilih jenis visualisasi"] visual_type: Option<VisualType>,
     #[description = "Periode waktu (7 atau 30 hari)"] _days: Option<DaysChoice>,
-    #[description = "Tahun untuk heatmap (default: tahun ini)"]
+    #[description = "Tahun untuk heatmap (default: 365 hari terakhir)"]
     #[min = 2020]
     #[max = 2030]
     _year: Option<i32>,
+    #[description = "Lihat statistik member lain"] user: Option<serenity::User>,
+    #[description = "Detail satu hari (YYYY-MM-DD), atau \"top\" untuk 10 hari terbaik"]
+    day: Option<String>,
+    #[description = "Hanya satu jenis media (heatmap dan daily chart)"] media_type: Option<
+        MediaType,
+    >,
 ) -> Result<(), Error> {
     ctx.defer().await?;
 
-    let user = ctx.author();
     let data = ctx.data();
+    let is_lookup = user.as_ref().is_some_and(|u| u.id != ctx.author().id);
+    let user = user.as_ref().unwrap_or_else(|| ctx.author());
     let user_id = user.id.to_string();
 
+    // Admins can turn off looking up other members
+    if is_lookup {
+        if let Some(guild_id) = ctx.guild_id() {
+            let allowed = get_guild_config(data, &guild_id.to_string())
+                .await
+                .map(|c| c.stat_lookup_allowed())
+                .unwrap_or(true);
+            if !allowed {
+                ctx.say("Melihat statistik member lain dinonaktifkan di server ini.")
+                    .await?;
+                return Ok(());
+            }
+        }
+    }
+
+    // Points follow the server's multipliers when used in a guild
+    let points_overrides = match ctx.guild_id() {
+        Some(guild_id) => get_guild_config(data, &guild_id.to_string())
+            .await
+            .and_then(|c| c.points_overrides),
+        None => None,
+    };
+
     // Fetch user data from Firebase
-    let user_doc = match data.firebase.get_document("users", &user_id).await {
+    let user_doc = match data.firebase.get_user(&user_id).await {
         Ok(doc) => doc,
         Err(e) => {
             error!("Failed to fetch user data: {:?}", e);
-            ctx.say("Failed to fetch your data. Please try again.")
+            ctx.say("Failed to fetch user data. Please try again.")
                 .await?;
             return Ok(());
         }
     };
 
+    // Members who opted out of the leaderboard aren't looked up either
+    if is_lookup
+        && user_doc
+            .as_ref()
+            .is_some_and(|doc| !LeaderboardPrivacy::of(&doc.profile).is_public())
+    {
+        ctx.say(LOOKUP_OPTED_OUT).await?;
+        return Ok(());
+    }
+
     // Check if user has data
     let user_data = match user_doc {
-        Some(doc) => doc,
-        None => {
-            let embed = serenity::CreateEmbed::new()
-                .title(format!("Immersion Stats - {}", user.name))
-                .description("**Total Points: 0** | **Total Sessions: 0**\n\n*Tip: Use `/stat visual_type:barchart` or `/stat visual_type:heatmap` to see visualizations!*")
-                .color(colors::SUCCESS)
-                .field("No data", "Start logging with `/immersion`!", false)
-                .thumbnail(user.face());
-
-            ctx.send(poise::CreateReply::default().embed(embed)).await?;
-            return Ok(());
-        }
-    };
-
-    // Get stats object
-    let stats = match user_data.get("stats") {
-        Some(s) if s.is_object() => s,
+        Some(doc) if !doc.stats.is_empty() => doc,
         _ => {
             let embed = serenity::CreateEmbed::new()
-                .title(format!("Immersion Stats - {}", user.name))
+                .title(format!("Immersion Stats - {}", user.display_name()))
                 .description("**Total Points: 0** | **Total Sessions: 0**\n\n*Tip: Use `/stat visual_type:barchart` or `/stat visual_type:heatmap` to see visualizations!*")
                 .color(colors::SUCCESS)
                 .field("No data", "Start logging with `/immersion`!", false)
@@ -90,25 +147,36 @@ pub async fn stat(
     };
 
     // Get profile info
-    let profile = user_data.get("profile");
-    let display_name = profile
-        .and_then(|p| p.get("displayName"))
-        .and_then(|v| v.as_str())
-        .unwrap_or(&user.name);
-    let avatar = profile
-        .and_then(|p| p.get("avatar"))
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
+    let display_name = user_data
+        .profile
+        .display_name
+        .as_deref()
+        .unwrap_or_else(|| user.display_name());
+    let avatar = user_data.profile.avatar.clone();
+    let timezone = user_data.profile.timezone.as_deref();
+    let theme = ChartTheme::for_user(&user_data);
+
+    let media_filter = media_type.map(|m| m.as_str());
+    let media_label = media_filter.map(get_media_label);
+
+    if let Some(day) = day {
+        return day_stats(
+            ctx,
+            &user_id,
+            display_name,
+            &day,
+            points_overrides.as_ref(),
+            is_lookup,
+            timezone,
+        )
+        .await;
+    }
 
     // Handle visualization types
     match visual_type {
         Some(VisualType::Heatmap) => {
             // Get immersion logs to calculate daily points
-            let logs = match data
-                .firebase
-                .query_subcollection("users", &user_id, "immersion_logs")
-                .await
-            {
+            let logs = match fetch_logs(data, &user_id, is_lookup).await {
                 Ok(l) => l,
                 Err(e) => {
                     error!("Failed to fetch logs for heatmap: {:?}", e);
@@ -121,33 +189,8 @@ pub async fn stat(
             let mut daily_points: std::collections::HashMap<String, i64> =
                 std::collections::HashMap::new();
             for log in &logs {
-                // Get date (smart JST conversion)
-                // Get date with fallback logic (Legacy Node.js behavior)
-                let timestamps = log.get("timestamps");
-
-                let date = if let Some(d) = timestamps
-                    .and_then(|t| t.get("date"))
-                    .and_then(|d| d.as_str())
-                {
-                    Some(d.to_string())
-                } else if let Some(c) = timestamps
-                    .and_then(|t| t.get("created"))
-                    .and_then(|s| s.as_str())
-                {
-                    // Fallback to 'created' timestamp for legacy logs (UTC+7)
-                    if let Ok(utc) = DateTime::parse_from_rfc3339(c) {
-                        let wib_offset = chrono::FixedOffset::east_opt(7 * 3600).unwrap();
-                        Some(
-                            utc.with_timezone(&wib_offset)
-                                .format("%Y-%m-%d")
-                                .to_string(),
-                        )
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                };
+                // Legacy logs without a stored date fall back to `created`
+                let date = streak::log_date_in(log, timezone);
 
                 // Get activity type and amount to calculate points
                 let activity = log.get("activity");
@@ -158,8 +201,12 @@ pub async fn stat(
                     .and_then(|a| a.get("amount"))
                     .and_then(|a| a.as_f64());
 
+                if media_filter.is_some_and(|f| media_type != Some(f)) {
+                    continue;
+                }
                 if let (Some(date), Some(media_type), Some(amount)) = (date, media_type, amount) {
-                    let points = calculate_points(media_type, amount);
+                    let points =
+                        calculate_points_with(media_type, amount, points_overrides.as_ref());
                     *daily_points.entry(date.to_string()).or_insert(0) += points;
                 }
             }
@@ -172,15 +219,58 @@ pub async fn stat(
                 daily_points.values().sum::<i64>()
             );
 
-            let year = _year.unwrap_or_else(|| chrono::Utc::now().year());
+            // The trailing year unless a calendar year was asked for
+            let range = _year.map_or(HeatmapRange::Trailing365, HeatmapRange::Year);
+            let today = effective_date_in(timezone, data.clock.now_utc());
 
-            match generate_heatmap(&daily_points, year, display_name) {
+            // An empty grid says little when it's only one type
+            if let Some(label) = media_label {
+                if heatmap_active_days(&daily_points, range, today).is_ok_and(|days| days == 0) {
+                    let period = match range {
+                        HeatmapRange::Year(year) => format!("in {}", year),
+                        HeatmapRange::Trailing365 => "in the last 365 days".to_string(),
+                    };
+                    ctx.say(format!(
+                        "No {} logs {} yet, log some with `/immersion` to fill this heatmap!",
+                        label, period
+                    ))
+                    .await?;
+                    return Ok(());
+                }
+            }
+
+            match generate_heatmap(
+                &daily_points,
+                range,
+                today,
+                display_name,
+                media_label,
+                &theme,
+            ) {
                 Ok(png_bytes) => {
-                    let attachment = serenity::CreateAttachment::bytes(png_bytes, "heatmap.png");
+                    let bytes = images::ensure_under_limit(
+                        png_bytes,
+                        "image/png",
+                        images::DEFAULT_UPLOAD_LIMIT,
+                    );
+                    let filename = images::file_name("heatmap", &bytes);
+                    let attachment = serenity::CreateAttachment::bytes(bytes, filename.as_str());
                     let embed = serenity::CreateEmbed::new()
-                        .title(format!("Immersion Heatmap {} - {}", year, display_name))
+                        .title(match range {
+                            HeatmapRange::Year(year) => format!(
+                                "{} Heatmap {} - {}",
+                                media_label.unwrap_or("Immersion"),
+                                year,
+                                display_name
+                            ),
+                            HeatmapRange::Trailing365 => format!(
+                                "{} Heatmap (last 365 days) - {}",
+                                media_label.unwrap_or("Immersion"),
+                                display_name
+                            ),
+                        })
                         .color(colors::SUCCESS)
-                        .image("attachment://heatmap.png");
+                        .image(format!("attachment://{}", filename));
 
                     ctx.send(
                         poise::CreateReply::default()
@@ -209,11 +299,7 @@ pub async fn stat(
             });
 
             // Fetch immersion logs
-            let logs = match data
-                .firebase
-                .query_subcollection("users", &user_id, "immersion_logs")
-                .await
-            {
+            let logs = match fetch_logs(data, &user_id, is_lookup).await {
                 Ok(l) => l,
                 Err(e) => {
                     error!("Failed to fetch logs for barchart: {:?}", e);
@@ -227,33 +313,8 @@ pub async fn stat(
                 std::collections::HashMap::new();
 
             for log in &logs {
-                // Get date (smart JST conversion)
-                // Get date with fallback logic (Legacy Node.js behavior)
-                let timestamps = log.get("timestamps");
-
-                let date = if let Some(d) = timestamps
-                    .and_then(|t| t.get("date"))
-                    .and_then(|d| d.as_str())
-                {
-                    Some(d.to_string())
-                } else if let Some(c) = timestamps
-                    .and_then(|t| t.get("created"))
-                    .and_then(|s| s.as_str())
-                {
-                    // Fallback to 'created' timestamp for legacy logs (UTC+7)
-                    if let Ok(utc) = DateTime::parse_from_rfc3339(c) {
-                        let wib_offset = chrono::FixedOffset::east_opt(7 * 3600).unwrap();
-                        Some(
-                            utc.with_timezone(&wib_offset)
-                                .format("%Y-%m-%d")
-                                .to_string(),
-                        )
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                };
+                // Legacy logs without a stored date fall back to `created`
+                let date = streak::log_date_in(log, timezone);
 
                 // Apply date filter if specified
                 if let Some(ref cutoff) = cutoff_date {
@@ -274,22 +335,14 @@ pub async fn stat(
                     .and_then(|a| a.as_f64());
 
                 if let (Some(media_type), Some(amount)) = (media_type, amount) {
-                    let points = calculate_points(media_type, amount) as f64;
+                    let points =
+                        calculate_points_with(media_type, amount, points_overrides.as_ref()) as f64;
                     *media_points.entry(media_type.to_string()).or_insert(0.0) += points;
                 }
             }
 
             // All supported media types - initialize with 0
-            let all_media_types = [
-                "anime",
-                "listening",
-                "reading",
-                "manga",
-                "visual_novel",
-                "book",
-                "reading_time",
-            ];
-            for mt in &all_media_types {
+            for mt in &MEDIA_TYPES {
                 media_points.entry(mt.to_string()).or_insert(0.0);
             }
 
@@ -330,13 +383,19 @@ pub async fn stat(
                 _ => format!("Stats - {}", display_name),
             };
 
-            match generate_bar_chart(&bar_data, &title, "Points") {
+            match generate_bar_chart(&bar_data, &title, "Points", &theme) {
                 Ok(png_bytes) => {
-                    let attachment = serenity::CreateAttachment::bytes(png_bytes, "chart.png");
+                    let bytes = images::ensure_under_limit(
+                        png_bytes,
+                        "image/png",
+                        images::DEFAULT_UPLOAD_LIMIT,
+                    );
+                    let filename = images::file_name("chart", &bytes);
+                    let attachment = serenity::CreateAttachment::bytes(bytes, filename.as_str());
                     let embed = serenity::CreateEmbed::new()
                         .title(format!("Immersion Chart - {}", display_name))
                         .color(colors::SUCCESS)
-                        .image("attachment://chart.png");
+                        .image(format!("attachment://{}", filename));
 
                     ctx.send(
                         poise::CreateReply::default()
@@ -352,33 +411,150 @@ pub async fn stat(
             }
             return Ok(());
         }
+        Some(VisualType::DailyChart) => {
+            let logs = match fetch_logs(data, &user_id, is_lookup).await {
+                Ok(l) => l,
+                Err(e) => {
+                    error!("Failed to fetch logs for daily chart: {:?}", e);
+                    ctx.say("Failed to generate chart.").await?;
+                    return Ok(());
+                }
+            };
+
+            let days_count = _days.unwrap_or(DaysChoice::SevenDays) as i64;
+            let today = effective_date_in(timezone, data.clock.now_utc());
+            let days = daily::trailing_days(today, days_count);
+            // Every type in a fixed order so each keeps its color between charts
+            let media_types = match media_filter {
+                Some(media_type) => vec![media_type],
+                None => MEDIA_TYPES.to_vec(),
+            };
+            let series: Vec<(String, Vec<f64>)> = daily::daily_media_points(
+                &logs,
+                &days,
+                &media_types,
+                points_overrides.as_ref(),
+                timezone,
+            )
+            .into_iter()
+            .map(|(media_type, values)| (get_media_label(&media_type).to_string(), values))
+            .collect();
+
+            if series
+                .iter()
+                .all(|(_, values)| values.iter().all(|v| *v <= 0.0))
+            {
+                ctx.say(format!(
+                    "No {} data found for the last {} days.",
+                    media_label.unwrap_or("immersion"),
+                    days_count
+                ))
+                .await?;
+                return Ok(());
+            }
+
+            let title = match media_label {
+                Some(label) => format!(
+                    "Daily {} Points ({} Days) - {}",
+                    label, days_count, display_name
+                ),
+                None => format!("Daily Points ({} Days) - {}", days_count, display_name),
+            };
+            match generate_stacked_bar_chart(&days, &series, &title, &theme) {
+                Ok(png_bytes) => {
+                    let bytes = images::ensure_under_limit(
+                        png_bytes,
+                        "image/png",
+                        images::DEFAULT_UPLOAD_LIMIT,
+                    );
+                    let filename = images::file_name("daily_chart", &bytes);
+                    let attachment = serenity::CreateAttachment::bytes(bytes, filename.as_str());
+                    let embed = serenity::CreateEmbed::new()
+                        .title(format!("Daily Immersion Mix - {}", display_name))
+                        .color(colors::SUCCESS)
+                        .image(format!("attachment://{}", filename));
+
+                    ctx.send(
+                        poise::CreateReply::default()
+                            .embed(embed)
+                            .attachment(attachment),
+                    )
+                    .await?;
+                }
+                Err(e) => {
+                    error!("Daily chart generation failed: {}", e);
+                    ctx.say("Failed to generate chart image.").await?;
+                }
+            }
+            return Ok(());
+        }
+        Some(VisualType::ReadingSpeed) => {
+            let logs = match fetch_logs(data, &user_id, is_lookup).await {
+                Ok(l) => l,
+                Err(e) => {
+                    error!("Failed to fetch logs for reading speed: {:?}", e);
+                    ctx.say("Failed to calculate reading speed.").await?;
+                    return Ok(());
+                }
+            };
+            let today = effective_date_at(data.clock.now_utc());
+            return reading_speed_stats(ctx, &logs, today, display_name, &theme).await;
+        }
+        Some(VisualType::TimeOfDay) => {
+            let logs = match fetch_logs(data, &user_id, is_lookup).await {
+                Ok(l) => l,
+                Err(e) => {
+                    error!("Failed to fetch logs for time of day: {:?}", e);
+                    ctx.say("Failed to calculate time of day stats.").await?;
+                    return Ok(());
+                }
+            };
+            let hourly = time_of_day::hourly_points(&logs, points_overrides.as_ref(), timezone);
+            return time_of_day_stats(ctx, &hourly, display_name, timezone).await;
+        }
         None => {
             // Default: show text stats
         }
     }
 
+    let logs = match data.firebase.get_user_logs(&user_id).await {
+        Ok(logs) => logs,
+        // Without the logs there's no telling what the private ones added to the totals
+        Err(e) if is_lookup => {
+            error!("Failed to fetch logs for stat lookup: {:?}", e);
+            ctx.say("Failed to fetch user data. Please try again.")
+                .await?;
+            return Ok(());
+        }
+        Err(e) => {
+            warn!("Failed to fetch logs for stats: {:?}", e);
+            Vec::new()
+        }
+    };
+    let (stats, logs) = if is_lookup {
+        (
+            without_private_logs(&user_data.stats, &logs),
+            public_logs(&logs).cloned().collect(),
+        )
+    } else {
+        (user_data.stats.clone(), logs)
+    };
+
     // Calculate stats
-    let stats_obj = stats.as_object().unwrap();
     let mut total_points: i64 = 0;
     let mut total_sessions: i64 = 0;
     let mut stat_entries: Vec<StatEntry> = Vec::new();
 
-    for (media_type, media_stats) in stats_obj {
-        let total = media_stats
-            .get("total")
-            .and_then(|v| v.as_f64())
-            .unwrap_or(0.0);
-        let sessions = media_stats
-            .get("sessions")
-            .and_then(|v| v.as_i64())
-            .unwrap_or(0);
+    for (media_type, media_stats) in stats.iter() {
+        let (total, sessions) = (media_stats.total, media_stats.sessions);
 
         if total > 0.0 {
-            let points = calculate_points(media_type, total);
+            let points = calculate_points_with(media_type, total, points_overrides.as_ref());
             total_points += points;
             total_sessions += sessions;
 
             stat_entries.push(StatEntry {
+                media_type: media_type.clone(),
                 label: get_media_label(media_type).to_string(),
                 total,
                 unit: get_unit(media_type).to_string(),
@@ -389,58 +565,42 @@ pub async fn stat(
     }
 
     // Sort by points (highest first)
-    stat_entries.sort_by(|a, b| b.points.cmp(&a.points));
-
-    // Calculate streaks
-    let (current_streak, longest_streak) = {
-        let logs = match data
-            .firebase
-            .query_subcollection("users", &user_id, "immersion_logs")
-            .await
-        {
-            Ok(l) => l,
-            Err(_) => Vec::new(),
-        };
-
-        let dates: Vec<String> = logs
-            .iter()
-            .filter_map(|log| {
-                let timestamps = log.get("timestamps")?;
-
-                // Try explicit date first
-                if let Some(d) = timestamps.get("date").and_then(|v| v.as_str()) {
-                    return Some(d.to_string());
-                }
+    stat_entries.sort_by_key(|e| std::cmp::Reverse(e.points));
 
-                // Fallback to created timestamp (UTC+7)
-                if let Some(c) = timestamps.get("created").and_then(|v| v.as_str()) {
-                    if let Ok(utc) = DateTime::parse_from_rfc3339(c) {
-                        let wib_offset = chrono::FixedOffset::east_opt(7 * 3600).unwrap();
-                        return Some(
-                            utc.with_timezone(&wib_offset)
-                                .format("%Y-%m-%d")
-                                .to_string(),
-                        );
-                    }
-                }
-
-                None
-            })
-            .collect();
-
-        let result = streak::calculate_streak(&dates);
-        (result.current, result.longest)
-    };
+    // Calculate streaks (global and per media type)
+    let today = effective_date_in(timezone, data.clock.now_utc());
+    let global_dates = streak::log_dates_in(&logs, None, timezone);
+    let global = streak::calculate_streak_on(&global_dates, today);
+    // Freezes can keep the streak going past a missed day
+    let summary = &user_data.summary;
+    let frozen = streak::calculate_streak_with_freezes(
+        &global_dates,
+        &summary.frozen_dates,
+        summary.streak_freezes.min(streak::MAX_STREAK_FREEZES),
+        today,
+    );
+    let current_streak = global.current.max(frozen.current);
+    let longest_streak = global.longest.max(current_streak);
 
     // Build stats text (grouped in one field)
     let mut stats_text = String::new();
     for stat in &stat_entries {
+        let type_streak = streak::calculate_streak_on(
+            &streak::log_dates_in(&logs, Some(&stat.media_type), timezone),
+            today,
+        )
+        .current;
+
         stats_text.push_str(&format!(
-            "**{}**: {} {}\n",
+            "**{}**: {} {}",
             stat.label,
             format_number_f64(stat.total),
             stat.unit
         ));
+        if type_streak > 0 {
+            stats_text.push_str(&format!(" — 🔥{}d", type_streak));
+        }
+        stats_text.push('\n');
     }
 
     if stats_text.is_empty() {
@@ -451,15 +611,60 @@ pub async fn stat(
     let mut embed = serenity::CreateEmbed::new()
         .title(format!("Immersion Stats - {}", display_name))
         .description(format!(
-            "**{}** pts | **{}** sessions\nStreak: **{}** days | Best: **{}** days",
+            "**{}** pts | **{}** sessions\nStreak: **{}** days | Best: **{}** days | Freezes: **{}**/{}",
             format_number(total_points),
             total_sessions,
             current_streak,
-            longest_streak
+            longest_streak,
+            frozen.freezes_left,
+            streak::MAX_STREAK_FREEZES
         ))
         .field("Stats", stats_text, false)
         .color(colors::SUCCESS);
 
+    // Goals set for the current month
+    let month = goal::month_key(get_effective_date());
+    let goals_text: Vec<String> = user_data
+        .goals()
+        .iter()
+        .filter(|(_, g)| g.is_active(&month))
+        .map(|(media_type, g)| {
+            let progress = goal::month_progress(&logs, media_type, &month);
+            format!(
+                "**{}** `{}`\n{}",
+                get_media_label(media_type),
+                goal::progress_bar(progress, g.amount),
+                goal::progress_text(progress, g.amount, get_unit(media_type))
+            )
+        })
+        .collect();
+    if !goals_text.is_empty() {
+        embed = embed.field("Goals This Month", goals_text.join("\n"), false);
+    }
+
+    // Known words of a linked jpdb account
+    if let Some(api_key) = user_data.jpdb_api_key() {
+        let known = jpdb::known_words(&data.http_client, &user_id, api_key)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Failed to fetch jpdb known words: {:?}", e);
+                None
+            });
+        embed = embed.field("jpdb", jpdb::known_words_text(known), true);
+    }
+
+    // Logs only carry their guild since it started being recorded
+    if let Some(guild_id) = ctx.guild_id() {
+        let here = guild_log_count(&logs, guild_id);
+        if here > 0 {
+            embed = embed.footer(serenity::CreateEmbedFooter::new(format!(
+                "{} of {} logs made in this server",
+                format_number(here as i64),
+                format_number(logs.len() as i64)
+            )));
+        }
+    }
+
     // Add avatar
     if let Some(ref avatar_url) = avatar {
         if !avatar_url.is_empty() {
@@ -474,8 +679,368 @@ pub async fn stat(
     Ok(())
 }
 
+/// A member's logs, leaving out their private ones when someone else is looking
+async fn fetch_logs(
+    data: &crate::Data,
+    user_id: &str,
+    is_lookup: bool,
+) -> anyhow::Result<Vec<Value>> {
+    let logs = data.firebase.get_user_logs(user_id).await?;
+    if is_lookup {
+        return Ok(public_logs(&logs).cloned().collect());
+    }
+    Ok(logs)
+}
+
+/// Stats as other members see them: what the member's private logs added taken back out
+fn without_private_logs(stats: &UserStats, logs: &[Value]) -> UserStats {
+    let mut stats = stats.clone();
+    for log in logs.iter().filter(|log| is_private_log(log)) {
+        let activity = &log["activity"];
+        let (Some(media_type), Some(amount)) =
+            (activity["type"].as_str(), activity["amount"].as_f64())
+        else {
+            continue;
+        };
+        let Some(media_stats) = stats.0.get_mut(media_type) else {
+            continue;
+        };
+        media_stats.total = (media_stats.total - amount).max(0.0);
+        media_stats.sessions = (media_stats.sessions - 1).max(0);
+    }
+    stats
+}
+
+/// Logs made in `guild_id`, from their `context.guildId`
+fn guild_log_count(logs: &[Value], guild_id: serenity::GuildId) -> usize {
+    let guild_id = guild_id.to_string();
+    logs.iter()
+        .filter(|log| {
+            log.pointer("/context/guildId").and_then(|g| g.as_str()) == Some(guild_id.as_str())
+        })
+        .count()
+}
+
+/// Logs read when there are no daily aggregates to rank days with
+const MAX_SCANNED_LOGS: usize = 5000;
+
+/// Daily totals and, when they came from a scan, the scanned logs
+struct DailySource {
+    totals: DailyTotals,
+    logs: Option<Vec<Value>>,
+    /// The scan hit `MAX_SCANNED_LOGS`, so some days may be missing or low
+    approximate: bool,
+}
+
+/// Prefer `daily_aggregates`, which only hold default points, so servers with their own
+/// multipliers always scan the logs. So do lookups, the aggregates count private logs.
+async fn load_daily_totals(
+    data: &crate::Data,
+    user_id: &str,
+    overrides: Option<&HashMap<String, f64>>,
+    is_lookup: bool,
+    tz: Option<&str>,
+) -> anyhow::Result<DailySource> {
+    if overrides.is_none() && !is_lookup {
+        match data.firebase.get_daily_aggregates(user_id).await {
+            Ok(docs) if !docs.is_empty() => {
+                return Ok(DailySource {
+                    totals: daily::totals_from_aggregates(&docs),
+                    logs: None,
+                    approximate: false,
+                });
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Failed to read daily aggregates of {}: {:?}", user_id, e),
+        }
+    }
+
+    let (mut logs, approximate) = data
+        .firebase
+        .get_user_logs_capped(user_id, MAX_SCANNED_LOGS)
+        .await?;
+    if is_lookup {
+        logs.retain(|log| !is_private_log(log));
+    }
+    if approximate {
+        warn!(
+            "User {} has more than {} logs, /stat day rankings are approximate",
+            user_id, MAX_SCANNED_LOGS
+        );
+    }
+    Ok(DailySource {
+        totals: daily::daily_totals(&logs, overrides, tz),
+        logs: Some(logs),
+        approximate,
+    })
+}
+
+/// Characters per minute over the last four weeks, for `/stat visual_type:ReadingSpeed`
+async fn reading_speed_stats(
+    ctx: Context<'_>,
+    logs: &[Value],
+    today: chrono::NaiveDate,
+    display_name: &str,
+    theme: &ChartTheme,
+) -> Result<(), Error> {
+    let start = today - chrono::Duration::days(reading_speed::TREND_DAYS - 1);
+    let speeds = reading_speed::daily_speeds(logs, start, today);
+    if speeds.is_empty() {
+        ctx.say("No reading speed data in the last 4 weeks. Log characters (Visual Novel or Reading) and Reading Time on the same day to see it.")
+            .await?;
+        return Ok(());
+    }
+
+    let (this_week, last_week) = reading_speed::weekly_trend(&speeds, today);
+    let format_speed = |speed: Option<f64>| {
+        speed
+            .map(|s| format!("{} chars/min", format_number(s.round() as i64)))
+            .unwrap_or_else(|| "—".to_string())
+    };
+    let mut description = format!(
+        "**This week:** {}\n**Last week:** {}",
+        format_speed(this_week),
+        format_speed(last_week)
+    );
+    if let (Some(this_week), Some(last_week)) = (this_week, last_week) {
+        let change = (this_week - last_week) / last_week * 100.0;
+        description.push_str(&format!(" ({:+.0}%)", change));
+    }
+    description.push_str(&format!(
+        "\n\nFrom {} day{} with both characters and reading time logged.",
+        speeds.len(),
+        if speeds.len() == 1 { "" } else { "s" }
+    ));
+
+    let title = format!("Reading Speed (4 Weeks) - {}", display_name);
+    let embed = serenity::CreateEmbed::new()
+        .title(&title)
+        .description(description)
+        .color(colors::SUCCESS);
+
+    match generate_line_chart(&speeds, &title, "Chars/min", theme) {
+        Ok(png_bytes) => {
+            let bytes =
+                images::ensure_under_limit(png_bytes, "image/png", images::DEFAULT_UPLOAD_LIMIT);
+            let filename = images::file_name("reading_speed", &bytes);
+            let attachment = serenity::CreateAttachment::bytes(bytes, filename.as_str());
+            ctx.send(
+                poise::CreateReply::default()
+                    .embed(embed.image(format!("attachment://{}", filename)))
+                    .attachment(attachment),
+            )
+            .await?;
+        }
+        Err(e) => {
+            // The numbers are still worth showing
+            error!("Reading speed chart generation failed: {}", e);
+            ctx.send(poise::CreateReply::default().embed(embed)).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Points per hour of the day. Drawn as text: `generate_bar_chart` gives every bar its
+/// own legend entry, and 24 of them don't fit.
+async fn time_of_day_stats(
+    ctx: Context<'_>,
+    hourly: &time_of_day::HourlyPoints,
+    display_name: &str,
+    timezone: Option<&str>,
+) -> Result<(), Error> {
+    let Some(summary) = time_of_day::summary(hourly) else {
+        ctx.say("No logs with a time of day yet. Logs saved for another day don't count.")
+            .await?;
+        return Ok(());
+    };
+
+    let mut embed = serenity::CreateEmbed::new()
+        .title(format!("Time of Day - {}", display_name))
+        .description(format!(
+            "**{}**\n```\n{}\n```",
+            summary,
+            time_of_day::histogram(hourly)
+        ))
+        .color(colors::SUCCESS);
+    let footer = format!("Points per hour ({})", timezone.unwrap_or("WIB"));
+    let footer = if hourly.excluded > 0 {
+        format!(
+            "{} | {} log{} without a time left out",
+            footer,
+            hourly.excluded,
+            if hourly.excluded == 1 { "" } else { "s" }
+        )
+    } else {
+        footer
+    };
+    embed = embed.footer(serenity::CreateEmbedFooter::new(footer));
+
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// `/stat day`: one day's logs and how it ranks, or the best days with "top"
+async fn day_stats(
+    ctx: Context<'_>,
+    user_id: &str,
+    display_name: &str,
+    day: &str,
+    overrides: Option<&HashMap<String, f64>>,
+    is_lookup: bool,
+    timezone: Option<&str>,
+) -> Result<(), Error> {
+    let day = day.trim();
+    let date = if day.eq_ignore_ascii_case("top") {
+        None
+    } else {
+        match parse_custom_date(day) {
+            Some(date) => Some(date),
+            None => {
+                ctx.say(INVALID_DATE_MESSAGE).await?;
+                return Ok(());
+            }
+        }
+    };
+
+    let data = ctx.data();
+    let source = match load_daily_totals(data, user_id, overrides, is_lookup, timezone).await {
+        Ok(source) => source,
+        Err(e) => {
+            error!("Failed to load daily totals: {:?}", e);
+            ctx.say("Failed to fetch logs. Please try again.").await?;
+            return Ok(());
+        }
+    };
+
+    let mut embed = match date {
+        None => top_days_embed(&source.totals, display_name),
+        Some(date) => {
+            let logs = match source.logs {
+                Some(logs) => logs,
+                None => match data
+                    .firebase
+                    .get_user_logs_on(user_id, &date.format("%Y-%m-%d").to_string())
+                    .await
+                {
+                    Ok(logs) if is_lookup => public_logs(&logs).cloned().collect(),
+                    Ok(logs) => logs,
+                    Err(e) => {
+                        error!("Failed to fetch logs of the day: {:?}", e);
+                        ctx.say("Failed to fetch logs. Please try again.").await?;
+                        return Ok(());
+                    }
+                },
+            };
+            let detail = daily::day_detail(date, &logs, &source.totals, overrides, timezone);
+            day_embed(&detail, &source.totals, display_name)
+        }
+    };
+    if source.approximate {
+        embed = embed.footer(serenity::CreateEmbedFooter::new(format!(
+            "Only {} logs were read, rankings are approximate",
+            MAX_SCANNED_LOGS
+        )));
+    }
+
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+fn day_embed(
+    detail: &DayDetail,
+    totals: &DailyTotals,
+    display_name: &str,
+) -> serenity::CreateEmbed {
+    let embed = serenity::CreateEmbed::new()
+        .title(format!("Immersion on {} - {}", detail.date, display_name))
+        .color(colors::SUCCESS);
+
+    if detail.entries.is_empty() {
+        return embed.description("No logs on this day.");
+    }
+
+    let mut description = format!(
+        "**{}** pts | **{}** logs",
+        format_number(detail.total_points),
+        detail.entries.len()
+    );
+    if let Some(percentile) = daily::percentile_rank(totals, detail.total_points) {
+        description.push_str(&format!(
+            "\nPercentile: **{:.0}** among your {} logged days",
+            percentile,
+            totals.len()
+        ));
+    }
+    match detail.streak_day {
+        0 => {}
+        1 => description.push_str("\nStarted a streak"),
+        n => description.push_str(&format!("\n🔥 Day **{}** of a streak", n)),
+    }
+
+    let lines: Vec<String> = detail
+        .entries
+        .iter()
+        .map(|entry| {
+            let mut line = format!(
+                "`{}` **{}** {} {}",
+                entry.time.as_deref().unwrap_or("--:--"),
+                get_media_label(&entry.media_type),
+                format_number_f64(entry.amount),
+                get_unit(&entry.media_type)
+            );
+            if let Some(title) = &entry.title {
+                line.push_str(&format!(" — {}", title));
+            }
+            line.push_str(&format!(" ({} pts)", format_number(entry.points)));
+            line
+        })
+        .collect();
+
+    embed
+        .description(description)
+        .field("Logs", truncate_field(&lines), false)
+}
+
+fn top_days_embed(totals: &DailyTotals, display_name: &str) -> serenity::CreateEmbed {
+    let top = daily::top_days(totals, daily::TOP_DAYS);
+    let description = if top.is_empty() {
+        "No logged days yet.".to_string()
+    } else {
+        top.iter()
+            .enumerate()
+            .map(|(i, (date, points))| {
+                format!("{}. **{}** — {} pts", i + 1, date, format_number(*points))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    serenity::CreateEmbed::new()
+        .title(format!("Top Days - {}", display_name))
+        .description(description)
+        .color(colors::SUCCESS)
+}
+
+/// Join lines into one embed field value, cutting off at Discord's 1024 characters
+fn truncate_field(lines: &[String]) -> String {
+    const MAX_CHARS: usize = 1024;
+
+    let mut value = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        let more = format!("\n…and {} more", lines.len() - i);
+        if value.chars().count() + line.chars().count() + 1 + more.chars().count() > MAX_CHARS {
+            value.push_str(more.trim_start());
+            break;
+        }
+        value.push_str(line);
+        value.push('\n');
+    }
+    value
+}
+
 #[derive(Debug)]
 struct StatEntry {
+    media_type: String,
     label: String,
     total: f64,
     unit: String,
@@ -491,7 +1056,7 @@ fn format_number(n: i64) -> String {
     let chars: Vec<char> = s.chars().collect();
 
     for (i, c) in chars.iter().enumerate() {
-        if i > 0 && (chars.len() - i) % 3 == 0 && *c != '-' {
+        if i > 0 && (chars.len() - i).is_multiple_of(3) && *c != '-' {
             result.push(',');
         }
         result.push(*c);
@@ -509,4 +1074,48 @@ fn format_number_f64(n: f64) -> String {
     }
 }
 
-// Local calculate_user_streaks removed in favor of utils::streak::calculate_streak
+// Local calculate_user_streaks removed in favor of utils::streak::calculate_streak_with_clock
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::user::MediaStats;
+    use serde_json::json;
+
+    #[test]
+    fn test_without_private_logs() {
+        let mut stats = UserStats::default();
+        *stats.entry("anime") = MediaStats {
+            total: 5.0,
+            sessions: 2,
+            ..Default::default()
+        };
+        let logs = [
+            json!({ "activity": { "type": "anime", "amount": 2.0 } }),
+            json!({
+                "activity": { "type": "anime", "amount": 3.0 },
+                "metadata": { "private": true }
+            }),
+        ];
+
+        let seen = without_private_logs(&stats, &logs);
+        assert_eq!(seen.total("anime"), 2.0);
+        assert_eq!(seen.get("anime").unwrap().sessions, 1);
+        // The member's own view keeps everything
+        assert_eq!(stats.total("anime"), 5.0);
+    }
+
+    #[test]
+    fn test_guild_log_count() {
+        let guild_id = serenity::GuildId::new(42);
+        let logs = [
+            json!({ "context": { "guildId": "42" } }),
+            json!({ "context": { "guildId": "7" } }),
+            // Made before the guild was recorded
+            json!({ "activity": { "type": "anime" } }),
+            json!({ "context": { "guildId": "42", "channelId": "1" } }),
+        ];
+        assert_eq!(guild_log_count(&logs, guild_id), 2);
+        assert_eq!(guild_log_count(&[], guild_id), 0);
+    }
+}
@@ -6,10 +6,33 @@ use std::env;
 use tracing::{error, info};
 
 use crate::api::anilist::{search_media, MediaType};
-use crate::api::jimaku::{download_file, get_entry, get_files, search_anime};
+use crate::api::jimaku::{
+    download_file, filter_files, get_entry, get_files, search_anime, JimakuEntry,
+    LanguagePreference, SubtitleFormat,
+};
+use crate::utils::formatters::{fit_description, truncate_chars, EMBED_DESCRIPTION_LIMIT};
 use crate::{Context, Error};
 
-const MAX_FILE_SIZE: u64 = 8 * 1024 * 1024; // 8MB Discord limit
+const MAX_FILE_SIZE: u64 = 8 * 1024 * 1024; // 8MB Discord limit, per DM message
+
+/// Files sent as they are; more than this get zipped
+const DIRECT_FILES: usize = 4;
+
+/// Most files sent for one search
+const MAX_FILES: usize = 25;
+
+/// Messages a split delivery may use, and attachments per message
+const MAX_MESSAGES: usize = 5;
+const FILES_PER_MESSAGE: usize = 10;
+
+/// Search results offered when a name matches several entries
+const MAX_CHOICES: usize = 10;
+
+/// How long the user has to pick one of them
+const PICK_TIMEOUT_SECS: u64 = 60;
+
+/// Discord's limit for choice and select option labels
+const LABEL_LIMIT: usize = 100;
 
 /// Download anime subtitles from Jimaku
 #[poise::command(slash_command, prefix_command)]
@@ -19,6 +42,10 @@ pub async fn subs(
     #[autocomplete = "autocomplete_anime"]
     name: String,
     #[description = "Episode number (optional)"] episode: Option<i32>,
+    #[description = "Subtitle format (default Any)"] format: Option<SubtitleFormat>,
+    #[description = "Leave out files tagged as English (default Any)"] preference: Option<
+        LanguagePreference,
+    >,
 ) -> Result<(), Error> {
     let api_key = match env::var("JIMAKU_API_KEY") {
         Ok(key) => key,
@@ -39,13 +66,18 @@ pub async fn subs(
         // Search for the anime
         let results = search_anime(http_client, &api_key, &name).await?;
 
-        if results.is_empty() {
-            ctx.say(format!("No anime found with keyword: **{}**", name))
-                .await?;
-            return Ok(());
+        match results.as_slice() {
+            [] => {
+                ctx.say(format!("No anime found with keyword: **{}**", name))
+                    .await?;
+                return Ok(());
+            }
+            [only] => only.id,
+            _ => match pick_entry(ctx, &results).await? {
+                Some(id) => id,
+                None => return Ok(()),
+            },
         }
-
-        results[0].id
     };
 
     // Get entry info
@@ -69,16 +101,30 @@ pub async fn subs(
 
     // Get files
     let files = get_files(http_client, &api_key, entry_id, episode).await?;
+    let found = files.len();
+    // Filtered before the cap, so the files sent are the ones asked for
+    let (files, excluded) = filter_files(
+        files,
+        format.unwrap_or_default(),
+        preference.unwrap_or_default(),
+    );
 
     if files.is_empty() {
         let episode_text = episode
             .map(|e| format!(" episode {}", e))
             .unwrap_or_default();
-        ctx.say(format!(
-            "No subtitle files found for **{}**{}",
-            entry.name, episode_text
-        ))
-        .await?;
+        let message = if found > 0 {
+            format!(
+                "None of the {} subtitle files for **{}**{} match your filters",
+                found, entry.name, episode_text
+            )
+        } else {
+            format!(
+                "No subtitle files found for **{}**{}",
+                entry.name, episode_text
+            )
+        };
+        ctx.say(message).await?;
         return Ok(());
     }
 
@@ -129,41 +175,96 @@ pub async fn subs(
 
     dm_embed = dm_embed.field("Entry ID", format!("`{}`", entry_id), true);
 
-    // Build file list and download files
-    let limited_files = files.iter().take(4).collect::<Vec<_>>();
-    let mut file_list = String::new();
-    let mut attachments: Vec<serenity::CreateAttachment> = Vec::new();
+    // Whole seasons go out zipped or over several messages, a few files as they are
+    let wanted = if files.len() > DIRECT_FILES {
+        MAX_FILES
+    } else {
+        DIRECT_FILES
+    };
+    let limited_files = files.iter().take(wanted).collect::<Vec<_>>();
+    if limited_files.len() > DIRECT_FILES {
+        ctx.say(format!("Preparing {} files…", limited_files.len()))
+            .await?;
+    }
+
+    let mut file_list: Vec<String> = Vec::new();
+    let mut downloaded: Vec<(String, Vec<u8>)> = Vec::new();
 
     for file in &limited_files {
-        let file_size_kb = file.size as f64 / 1024.0;
-        file_list.push_str(&format!("**{}**\n", file.name));
-        file_list.push_str(&format!("Size: {:.2} KB\n", file_size_kb));
+        let mut entry_text = format!("**{}** ({:.2} KB)", file.name, file.size as f64 / 1024.0);
 
         // Download file if not too large
         if file.size < MAX_FILE_SIZE {
             match download_file(http_client, &file.url).await {
-                Ok(data) => {
-                    let attachment = serenity::CreateAttachment::bytes(data, &file.name);
-                    attachments.push(attachment);
-                }
+                Ok(data) => downloaded.push((file.name.clone(), data)),
                 Err(e) => {
                     error!("Error downloading file {}: {:?}", file.name, e);
-                    file_list.push_str("*Error downloading this file*\n");
+                    entry_text.push_str("\n*Error downloading this file*");
                 }
             }
         } else {
-            file_list.push_str("*File too large for Discord upload*\n");
-            file_list.push_str(&format!("[Manual Download]({})\n", file.url));
+            entry_text.push_str("\n*File too large for Discord upload*");
+            entry_text.push_str(&format!("\n[Manual Download]({})", file.url));
         }
-        file_list.push('\n');
+        file_list.push(entry_text);
     }
 
-    dm_embed = dm_embed.description(&file_list);
+    // One zip when it fits an upload, otherwise as many messages as needed
+    let zipped = if downloaded.len() > DIRECT_FILES {
+        match build_zip(&downloaded) {
+            Ok(zip) if zip.len() as u64 <= MAX_FILE_SIZE => Some(zip),
+            Ok(_) => None,
+            Err(e) => {
+                error!("Failed to zip subtitle files: {:?}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let messages: Vec<Vec<serenity::CreateAttachment>> = match zipped {
+        Some(zip) => vec![vec![serenity::CreateAttachment::bytes(
+            zip,
+            zip_name(&entry.name),
+        )]],
+        None => {
+            let sizes: Vec<u64> = downloaded.iter().map(|(_, d)| d.len() as u64).collect();
+            let (batches, left_out) = pack_messages(&sizes);
+            for &i in &left_out {
+                file_list.push(format!("*Not sent, too many files:* {}", downloaded[i].0));
+            }
+            batches
+                .into_iter()
+                .map(|batch| {
+                    batch
+                        .into_iter()
+                        .map(|i| {
+                            serenity::CreateAttachment::bytes(
+                                downloaded[i].1.clone(),
+                                &downloaded[i].0,
+                            )
+                        })
+                        .collect()
+                })
+                .collect()
+        }
+    };
+
+    let (file_list, _) = fit_description(file_list, EMBED_DESCRIPTION_LIMIT);
+    dm_embed = dm_embed.description(file_list);
 
-    if files.len() > 4 {
+    if files.len() > limited_files.len() {
         dm_embed = dm_embed.field(
             "Info",
-            format!("Showing 4 of {} files. Use Entry ID `{}` for specific downloads or use episode parameter.", files.len(), entry_id),
+            format!("Showing {} of {} files. Use Entry ID `{}` for specific downloads or use episode parameter.", limited_files.len(), files.len(), entry_id),
+            false,
+        );
+    }
+
+    if excluded > 0 {
+        dm_embed = dm_embed.field(
+            "Filters",
+            format!("{} of {} files excluded by your filters.", excluded, found),
             false,
         );
     }
@@ -180,27 +281,181 @@ pub async fn subs(
         }
     };
 
-    // Build message with attachments
-    let mut dm_message = serenity::CreateMessage::new().embed(dm_embed);
-
-    for attachment in attachments {
-        dm_message = dm_message.add_file(attachment);
-    }
+    // The embed goes with the first message, or alone when nothing was downloaded
+    let mut batches = messages.into_iter();
+    let mut dm_messages = vec![serenity::CreateMessage::new()
+        .embed(dm_embed)
+        .files(batches.next().unwrap_or_default())];
+    dm_messages.extend(batches.map(|batch| serenity::CreateMessage::new().files(batch)));
 
-    match dm_channel.send_message(ctx, dm_message).await {
-        Ok(_) => {
-            info!("Sent subtitle files to user {} via DM", user.name);
-        }
-        Err(e) => {
+    for dm_message in dm_messages {
+        if let Err(e) = dm_channel.send_message(ctx, dm_message).await {
             error!("Error sending DM: {:?}", e);
             ctx.say("Cannot send DM. Please check your privacy settings and try again.")
                 .await?;
+            return Ok(());
         }
     }
+    info!("Sent subtitle files to user {} via DM", user.name);
 
     Ok(())
 }
 
+/// In-memory zip of the downloaded files
+fn build_zip(files: &[(String, Vec<u8>)]) -> anyhow::Result<Vec<u8>> {
+    use std::io::Write;
+
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    let mut seen = std::collections::HashSet::new();
+    for (name, data) in files {
+        // Duplicate names would make an invalid archive
+        if !seen.insert(name.as_str()) {
+            continue;
+        }
+        zip.start_file(name.as_str(), options)?;
+        zip.write_all(data)?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
+
+/// File name of the zip for an entry, without characters file systems reject
+fn zip_name(entry_name: &str) -> String {
+    let name: String = entry_name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .take(80)
+        .collect();
+    format!("{}.zip", name.trim())
+}
+
+/// Group files (by size) into DM messages in order, within the per-message upload
+/// and attachment limits and at most `MAX_MESSAGES`. Returns the batches of indices
+/// and the files that didn't fit.
+fn pack_messages(sizes: &[u64]) -> (Vec<Vec<usize>>, Vec<usize>) {
+    let mut batches: Vec<Vec<usize>> = Vec::new();
+    let mut batch_size = 0;
+    let mut left_out = Vec::new();
+    for (i, &size) in sizes.iter().enumerate() {
+        let open = batches
+            .last_mut()
+            .filter(|batch| batch.len() < FILES_PER_MESSAGE && batch_size + size <= MAX_FILE_SIZE);
+        if let Some(batch) = open {
+            batch_size += size;
+            batch.push(i);
+        } else if batches.len() < MAX_MESSAGES {
+            batch_size = size;
+            batches.push(vec![i]);
+        } else {
+            left_out.push(i);
+        }
+    }
+    (batches, left_out)
+}
+
+/// Let the user choose among several search results. `None` when they didn't in time.
+async fn pick_entry(ctx: Context<'_>, results: &[JimakuEntry]) -> Result<Option<i32>, Error> {
+    let options = results
+        .iter()
+        .take(MAX_CHOICES)
+        .map(|entry| {
+            let option = serenity::CreateSelectMenuOption::new(
+                truncate_label(&entry.name),
+                entry.id.to_string(),
+            );
+            match entry_description(entry) {
+                Some(description) => option.description(truncate_label(&description)),
+                None => option,
+            }
+        })
+        .collect();
+    let menu = serenity::CreateSelectMenu::new(
+        "subs_pick_entry",
+        serenity::CreateSelectMenuKind::String { options },
+    )
+    .placeholder("Pilih anime / Select an entry")
+    .min_values(1)
+    .max_values(1);
+
+    let shown = results.len().min(MAX_CHOICES);
+    let reply = ctx
+        .send(
+            poise::CreateReply::default()
+                .content(format!(
+                    "Found {} entries, showing {}. Which one do you mean?",
+                    results.len(),
+                    shown
+                ))
+                .components(vec![serenity::CreateActionRow::SelectMenu(menu)]),
+        )
+        .await?;
+    let msg = reply.message().await?.into_owned();
+
+    let interaction = msg
+        .await_component_interaction(ctx.serenity_context())
+        .author_id(ctx.author().id)
+        .timeout(std::time::Duration::from_secs(PICK_TIMEOUT_SECS))
+        .await;
+
+    let picked = interaction.as_ref().and_then(|i| match &i.data.kind {
+        serenity::ComponentInteractionDataKind::StringSelect { values } => values
+            .first()
+            .and_then(|v| v.parse::<i32>().ok())
+            .and_then(|id| results.iter().find(|e| e.id == id)),
+        _ => None,
+    });
+    let outcome = match picked {
+        Some(entry) => format!("Selected **{}**", entry.name),
+        None => "No entry selected, search cancelled.".to_string(),
+    };
+    match interaction {
+        Some(interaction) => {
+            let _ = interaction
+                .create_response(
+                    ctx.http(),
+                    serenity::CreateInteractionResponse::UpdateMessage(
+                        serenity::CreateInteractionResponseMessage::new()
+                            .content(outcome)
+                            .components(vec![]),
+                    ),
+                )
+                .await;
+        }
+        None => {
+            let _ = reply
+                .edit(
+                    ctx,
+                    poise::CreateReply::default()
+                        .content(outcome)
+                        .components(vec![]),
+                )
+                .await;
+        }
+    }
+
+    Ok(picked.map(|entry| entry.id))
+}
+
+/// Second line of a search result: English name and whether it's a movie
+fn entry_description(entry: &JimakuEntry) -> Option<String> {
+    let parts: Vec<&str> = entry
+        .english_name
+        .as_deref()
+        .filter(|e| !e.is_empty() && *e != entry.name)
+        .into_iter()
+        .chain(entry.flags.movie.then_some("Movie"))
+        .collect();
+    (!parts.is_empty()).then(|| parts.join(" · "))
+}
+
+fn truncate_label(text: &str) -> String {
+    truncate_chars(text, LABEL_LIMIT)
+}
+
 /// Autocomplete for anime search
 async fn autocomplete_anime<'a>(
     ctx: Context<'a>,
@@ -219,29 +474,118 @@ async fn autocomplete_anime<'a>(
         let http_client = &ctx.data().http_client;
 
         match search_anime(http_client, &api_key, partial).await {
-            Ok(results) => {
-                results
-                    .into_iter()
-                    .take(25)
-                    .map(|anime| {
-                        let display = if let Some(ref eng) = anime.english_name {
-                            format!("{} ({})", anime.name, eng)
-                        } else {
-                            anime.name.clone()
-                        };
-                        // Truncate to 100 chars for Discord limit
-                        let display = if display.len() > 100 {
-                            format!("{}...", &display[..97])
-                        } else {
-                            display
-                        };
-                        serenity::AutocompleteChoice::new(display, anime.id.to_string())
-                    })
-                    .collect()
-            }
+            Ok(results) => results
+                .into_iter()
+                .take(25)
+                .map(|anime| {
+                    let display = if let Some(ref eng) = anime.english_name {
+                        format!("{} ({})", anime.name, eng)
+                    } else {
+                        anime.name.clone()
+                    };
+                    serenity::AutocompleteChoice::new(
+                        truncate_label(&display),
+                        anime.id.to_string(),
+                    )
+                })
+                .collect(),
             Err(_) => vec![],
         }
     };
 
     results.await.into_iter()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, english: Option<&str>, movie: bool) -> JimakuEntry {
+        serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "name": name,
+            "english_name": english,
+            "flags": { "anime": true, "movie": movie }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_entry_description() {
+        assert_eq!(
+            entry_description(&entry("Sousou no Frieren", Some("Frieren"), false)).as_deref(),
+            Some("Frieren")
+        );
+        assert_eq!(
+            entry_description(&entry("Kimi no Na wa.", Some("Your Name."), true)).as_deref(),
+            Some("Your Name. · Movie")
+        );
+        assert_eq!(
+            entry_description(&entry("Frieren", Some("Frieren"), false)),
+            None
+        );
+        // Entries from the API without flags
+        let bare: JimakuEntry =
+            serde_json::from_value(serde_json::json!({ "id": 2, "name": "Mushishi" })).unwrap();
+        assert_eq!(entry_description(&bare), None);
+    }
+
+    #[test]
+    fn test_pack_messages_respects_limits() {
+        const MB: u64 = 1024 * 1024;
+
+        // Size limit: 5 + 2 fit together, the next 5 starts a new message
+        let (batches, left_out) = pack_messages(&[5 * MB, 2 * MB, 5 * MB, MB]);
+        assert_eq!(batches, vec![vec![0, 1], vec![2, 3]]);
+        assert!(left_out.is_empty());
+
+        // Attachment limit: 25 small files make three messages
+        let (batches, _) = pack_messages(&[1024; 25]);
+        assert_eq!(
+            batches.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![10, 10, 5]
+        );
+
+        // Message limit: what doesn't fit in five messages is left out
+        let (batches, left_out) = pack_messages(&[7 * MB; 7]);
+        assert_eq!(batches.len(), MAX_MESSAGES);
+        assert_eq!(left_out, vec![5, 6]);
+    }
+
+    #[test]
+    fn test_build_zip_roundtrip() {
+        let files = vec![
+            (
+                "ep01.srt".to_string(),
+                b"1\n00:00:01,000 --> 00:00:02,000\n".to_vec(),
+            ),
+            ("ep02.ass".to_string(), "字幕".repeat(1000).into_bytes()),
+            ("ep01.srt".to_string(), b"duplicate".to_vec()),
+        ];
+        let zip = build_zip(&files).unwrap();
+        // Repetitive subtitles compress well
+        assert!(zip.len() < files[1].1.len());
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(zip)).unwrap();
+        assert_eq!(archive.len(), 2);
+        let mut content = String::new();
+        std::io::Read::read_to_string(&mut archive.by_name("ep02.ass").unwrap(), &mut content)
+            .unwrap();
+        assert_eq!(content, "字幕".repeat(1000));
+    }
+
+    #[test]
+    fn test_zip_name_is_file_safe() {
+        assert_eq!(zip_name("Re:Zero / Season 2"), "Re_Zero _ Season 2.zip");
+        assert_eq!(zip_name("葬送のフリーレン"), "葬送のフリーレン.zip");
+    }
+
+    #[test]
+    fn test_truncate_label_multibyte() {
+        let long = "葬送のフリーレン".repeat(20);
+        let label = truncate_label(&long);
+        assert_eq!(label.chars().count(), LABEL_LIMIT);
+        assert!(label.ends_with("..."));
+        assert_eq!(truncate_label("Frieren"), "Frieren");
+    }
+}
@@ -0,0 +1,99 @@
+// Timezone command - the timezone a member's day, streaks and reminders follow
+// Stored as `profile.timezone` on the user document, members without one stay on WIB
+
+use serde_json::json;
+use tracing::{error, info};
+
+use crate::features::streak_reminder;
+use crate::utils::config::{effective_date_in, parse_timezone, DAY_END_HOUR};
+use crate::{Context, Error};
+
+/// Timezone names matching what was typed so far
+async fn autocomplete_timezone(_ctx: Context<'_>, partial: &str) -> impl Iterator<Item = String> {
+    let partial = partial.to_lowercase();
+    chrono_tz::TZ_VARIANTS
+        .iter()
+        .map(|tz| tz.name())
+        .filter(move |name| name.to_lowercase().contains(&partial))
+        .map(str::to_string)
+        .take(25)
+}
+
+/// Your timezone, used for dates, streaks and reminders
+#[poise::command(slash_command, subcommands("timezone_set"))]
+pub async fn timezone(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Set the timezone your day follows (WIB until you set one)
+#[poise::command(slash_command, rename = "set")]
+pub async fn timezone_set(
+    ctx: Context<'_>,
+    #[description = "Timezone name, e.g. Europe/Berlin"]
+    #[autocomplete = "autocomplete_timezone"]
+    tz: String,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let Some(parsed) = parse_timezone(&tz) else {
+        ctx.say(format!(
+            "`{}` isn't a timezone I know. Pick one from the list, e.g. `Europe/Berlin`.",
+            tz.trim()
+        ))
+        .await?;
+        return Ok(());
+    };
+    let name = parsed.name();
+
+    let data = ctx.data();
+    let user_id = ctx.author().id.to_string();
+    if let Err(e) = data
+        .firebase
+        .set_document_merge_paths(
+            "users",
+            &user_id,
+            &json!({ "profile": { "timezone": name } }),
+            &["profile.timezone"],
+        )
+        .await
+    {
+        error!("Failed to save timezone: {:?}", e);
+        ctx.say("Failed to save your timezone. Please try again later.")
+            .await?;
+        return Ok(());
+    }
+    info!("User {} set their timezone to {}", user_id, name);
+
+    // A reminder keeps its hour, now read on the new clock
+    let reminder = data
+        .firebase
+        .get_user(&user_id)
+        .await
+        .ok()
+        .flatten()
+        .and_then(|user| user.streak_reminder())
+        .filter(|settings| settings.enabled);
+    if let Some(settings) = reminder {
+        if let Err(e) = data
+            .firebase
+            .set_document(
+                streak_reminder::COLLECTION,
+                &user_id,
+                &streak_reminder::index_doc(settings.hour, Some(name)),
+            )
+            .await
+        {
+            error!("Failed to move streak reminder to {}: {:?}", name, e);
+        }
+    }
+
+    let today = effective_date_in(Some(name), data.clock.now_utc());
+    ctx.say(format!(
+        "Your timezone is now **{}**. Your day ends at {:02}:00 there, so new logs count for **{}** right now.",
+        name,
+        DAY_END_HOUR,
+        today.format("%Y-%m-%d")
+    ))
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,382 @@
+// Title history command - everything a user logged under one title
+
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use poise::serenity_prelude as serenity;
+use std::collections::{BTreeMap, HashSet};
+use std::time::{Duration, Instant};
+
+use crate::commands::log::{fetch_all_user_logs, ImmersionLog};
+use crate::features::novel_recommender::normalize_string;
+use crate::utils::config::{colors, get_media_label};
+use crate::utils::formatters::{format_duration, format_number, truncate_chars};
+use crate::{Context, Error};
+
+/// How long a user's titles are reused for autocomplete
+const TITLE_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Discord's limit for autocomplete choices and select options
+const MAX_CHOICES: usize = 25;
+
+/// Discord's limit for choice and select option labels
+const LABEL_LIMIT: usize = 100;
+
+/// How long the user has to pick one of several matching titles
+const PICK_TIMEOUT_SECS: u64 = 60;
+
+/// Each user's distinct titles, most recently logged first
+static TITLE_CACHE: Lazy<DashMap<serenity::UserId, (Instant, Vec<String>)>> =
+    Lazy::new(DashMap::new);
+
+/// Totals of the logs under one title
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TitleSummary {
+    pub title: String,
+    /// Amount and unit per media type
+    pub totals: BTreeMap<String, (f64, String)>,
+    /// Reading time logged on the days the title was logged
+    pub reading_minutes: f64,
+    pub first_date: String,
+    pub last_date: String,
+    pub sessions: usize,
+}
+
+/// Show everything you logged under a title
+#[poise::command(slash_command, prefix_command)]
+pub async fn title(
+    ctx: Context<'_>,
+    #[description = "Title to look up"]
+    #[autocomplete = "autocomplete_user_title"]
+    query: String,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+
+    let user_id = ctx.author().id;
+    let logs = fetch_all_user_logs(ctx.data(), &user_id.to_string()).await?;
+    let titles = distinct_titles(&logs);
+    TITLE_CACHE.insert(user_id, (Instant::now(), titles.clone()));
+
+    let matches = matching_titles(&titles, &query);
+    let title = match matches.as_slice() {
+        [] => {
+            ctx.say(format!("You haven't logged anything titled **{}**.", query))
+                .await?;
+            return Ok(());
+        }
+        [only] => only.clone(),
+        _ => match pick_title(ctx, &matches).await? {
+            Some(title) => title,
+            None => return Ok(()),
+        },
+    };
+
+    let Some(summary) = summarize(&logs, &title) else {
+        return Ok(());
+    };
+    ctx.send(poise::CreateReply::default().embed(create_title_embed(&summary)))
+        .await?;
+    Ok(())
+}
+
+/// Key titles are compared by
+pub(crate) fn title_key(title: &str) -> String {
+    normalize_string(title)
+}
+
+/// A log's title, unless it has none
+fn log_title(log: &ImmersionLog) -> Option<&str> {
+    log.activity
+        .title
+        .as_deref()
+        .map(str::trim)
+        .filter(|t| !t.is_empty() && *t != "-")
+}
+
+/// Titles the user has logged, one spelling per title, most recently logged first
+fn distinct_titles(logs: &[ImmersionLog]) -> Vec<String> {
+    let mut sorted: Vec<&ImmersionLog> = logs.iter().collect();
+    sorted.sort_by_key(|l| std::cmp::Reverse(l.timestamps.created));
+    let mut seen = HashSet::new();
+    sorted
+        .into_iter()
+        .filter_map(log_title)
+        .filter(|title| seen.insert(title_key(title)))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Titles the query names. An exact match wins, otherwise every title containing it.
+fn matching_titles(titles: &[String], query: &str) -> Vec<String> {
+    let query = title_key(query);
+    if query.is_empty() {
+        return vec![];
+    }
+    if let Some(exact) = titles.iter().find(|t| title_key(t) == query) {
+        return vec![exact.clone()];
+    }
+    titles
+        .iter()
+        .filter(|t| title_key(t).contains(&query))
+        .cloned()
+        .collect()
+}
+
+/// Totals of the logs under `title`, `None` when there are none
+pub(crate) fn summarize(logs: &[ImmersionLog], title: &str) -> Option<TitleSummary> {
+    let key = title_key(title);
+    let matched: Vec<&ImmersionLog> = logs
+        .iter()
+        .filter(|l| log_title(l).is_some_and(|t| title_key(t) == key))
+        .collect();
+    if matched.is_empty() {
+        return None;
+    }
+
+    let mut totals: BTreeMap<String, (f64, String)> = BTreeMap::new();
+    for log in &matched {
+        let entry = totals
+            .entry(log.activity.activity_type.clone())
+            .or_insert_with(|| (0.0, log.activity.unit.clone()));
+        entry.0 += log.activity.amount;
+    }
+
+    let days: HashSet<String> = matched.iter().map(|l| l.activity_date()).collect();
+    // Reading time is seldom titled, so it's counted by day
+    let reading_minutes = logs
+        .iter()
+        .filter(|l| l.activity.activity_type == "reading_time" && days.contains(&l.activity_date()))
+        .map(|l| l.activity.amount)
+        .sum();
+
+    Some(TitleSummary {
+        title: title.to_string(),
+        totals,
+        reading_minutes,
+        first_date: days.iter().min()?.clone(),
+        last_date: days.iter().max()?.clone(),
+        sessions: matched.len(),
+    })
+}
+
+fn create_title_embed(summary: &TitleSummary) -> serenity::CreateEmbed {
+    let totals = summary
+        .totals
+        .iter()
+        .map(|(media_type, (amount, unit))| {
+            format!(
+                "**{}:** {} {}",
+                get_media_label(media_type),
+                format_number(amount.round() as i64),
+                unit
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let dates = if summary.first_date == summary.last_date {
+        summary.first_date.clone()
+    } else {
+        format!("{} → {}", summary.first_date, summary.last_date)
+    };
+
+    let mut embed = serenity::CreateEmbed::new()
+        .title(format!("📚 {}", summary.title))
+        .color(colors::IMMERSION)
+        .field("Total", totals, false)
+        .field("Sessions", summary.sessions.to_string(), true)
+        .field("Logged", dates, true);
+    if summary.reading_minutes > 0.0 {
+        embed = embed.field(
+            "Reading time on those days",
+            format_duration(summary.reading_minutes.round() as i64),
+            true,
+        );
+    }
+    embed
+}
+
+/// Let the user choose among several matching titles. `None` when they didn't in time.
+async fn pick_title(ctx: Context<'_>, titles: &[String]) -> Result<Option<String>, Error> {
+    let shown = &titles[..titles.len().min(MAX_CHOICES)];
+    let options = shown
+        .iter()
+        .enumerate()
+        .map(|(i, title)| {
+            serenity::CreateSelectMenuOption::new(truncate_label(title), i.to_string())
+        })
+        .collect();
+    let menu = serenity::CreateSelectMenu::new(
+        "title_pick",
+        serenity::CreateSelectMenuKind::String { options },
+    )
+    .placeholder("Select a title")
+    .min_values(1)
+    .max_values(1);
+
+    let reply = ctx
+        .send(
+            poise::CreateReply::default()
+                .content(format!(
+                    "Found {} titles, showing {}. Which one do you mean?",
+                    titles.len(),
+                    shown.len()
+                ))
+                .components(vec![serenity::CreateActionRow::SelectMenu(menu)]),
+        )
+        .await?;
+    let msg = reply.message().await?.into_owned();
+
+    let interaction = msg
+        .await_component_interaction(ctx.serenity_context())
+        .author_id(ctx.author().id)
+        .timeout(Duration::from_secs(PICK_TIMEOUT_SECS))
+        .await;
+
+    let picked = interaction.as_ref().and_then(|i| match &i.data.kind {
+        serenity::ComponentInteractionDataKind::StringSelect { values } => values
+            .first()
+            .and_then(|v| v.parse::<usize>().ok())
+            .and_then(|i| shown.get(i))
+            .cloned(),
+        _ => None,
+    });
+    let outcome = match &picked {
+        Some(title) => format!("Selected **{}**", title),
+        None => "No title selected.".to_string(),
+    };
+    match interaction {
+        Some(interaction) => {
+            let _ = interaction
+                .create_response(
+                    ctx.http(),
+                    serenity::CreateInteractionResponse::UpdateMessage(
+                        serenity::CreateInteractionResponseMessage::new()
+                            .content(outcome)
+                            .components(vec![]),
+                    ),
+                )
+                .await;
+        }
+        None => {
+            let _ = reply
+                .edit(
+                    ctx,
+                    poise::CreateReply::default()
+                        .content(outcome)
+                        .components(vec![]),
+                )
+                .await;
+        }
+    }
+
+    Ok(picked)
+}
+
+fn truncate_label(text: &str) -> String {
+    truncate_chars(text, LABEL_LIMIT)
+}
+
+/// The user's titles, fetched once and reused while the cache is fresh
+async fn cached_titles(ctx: Context<'_>) -> Vec<String> {
+    let user_id = ctx.author().id;
+    if let Some(entry) = TITLE_CACHE.get(&user_id) {
+        if entry.0.elapsed() < TITLE_CACHE_TTL {
+            return entry.1.clone();
+        }
+    }
+    let titles = match fetch_all_user_logs(ctx.data(), &user_id.to_string()).await {
+        Ok(logs) => distinct_titles(&logs),
+        Err(e) => {
+            tracing::warn!("Failed to fetch titles for autocomplete: {:?}", e);
+            return vec![];
+        }
+    };
+    TITLE_CACHE.insert(user_id, (Instant::now(), titles.clone()));
+    titles
+}
+
+/// Autocomplete over the titles the user has logged
+async fn autocomplete_user_title<'a>(
+    ctx: Context<'a>,
+    partial: &'a str,
+) -> impl Iterator<Item = serenity::AutocompleteChoice> + 'a {
+    let partial = title_key(partial);
+    cached_titles(ctx)
+        .await
+        .into_iter()
+        .filter(move |title| title_key(title).contains(&partial))
+        .take(MAX_CHOICES)
+        .map(|title| serenity::AutocompleteChoice::new(truncate_label(&title), title))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_support::log;
+
+    fn sample_logs() -> Vec<ImmersionLog> {
+        vec![
+            log("visual_novel", 10_000.0)
+                .title("Steins;Gate")
+                .created("2025-01-10T10:00:00Z")
+                .date("2025-01-10")
+                .into_log(),
+            log("visual_novel", 5_000.0)
+                .title("steins;gate")
+                .created("2025-01-12T10:00:00Z")
+                .date("2025-01-12")
+                .into_log(),
+            log("anime", 3.0)
+                .title("Steins;Gate 0")
+                .created("2025-01-11T10:00:00Z")
+                .date("2025-01-11")
+                .into_log(),
+            log("reading_time", 45.0)
+                .created("2025-01-12T11:00:00Z")
+                .date("2025-01-12")
+                .into_log(),
+            log("reading_time", 30.0)
+                .title("-")
+                .created("2025-01-13T11:00:00Z")
+                .date("2025-01-13")
+                .into_log(),
+        ]
+    }
+
+    #[test]
+    fn test_distinct_titles() {
+        assert_eq!(
+            distinct_titles(&sample_logs()),
+            vec!["steins;gate".to_string(), "Steins;Gate 0".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_matching_titles() {
+        let titles = distinct_titles(&sample_logs());
+        // Punctuation and case don't matter, an exact match beats longer titles
+        assert_eq!(matching_titles(&titles, "SteinsGate!"), vec!["steins;gate"]);
+        assert_eq!(
+            matching_titles(&titles, "steins"),
+            vec!["steins;gate", "Steins;Gate 0"]
+        );
+        assert!(matching_titles(&titles, "clannad").is_empty());
+        assert!(matching_titles(&titles, "  ").is_empty());
+    }
+
+    #[test]
+    fn test_summarize() {
+        let summary = summarize(&sample_logs(), "Steins;Gate").unwrap();
+        assert_eq!(summary.sessions, 2);
+        assert_eq!(
+            summary.totals.get("visual_novel"),
+            Some(&(15_000.0, "characters".to_string()))
+        );
+        assert_eq!(summary.totals.len(), 1);
+        // Only the reading time on the 12th shares a day with the title
+        assert_eq!(summary.reading_minutes, 45.0);
+        assert_eq!(summary.first_date, "2025-01-10");
+        assert_eq!(summary.last_date, "2025-01-12");
+        assert_eq!(summarize(&sample_logs(), "Clannad"), None);
+    }
+}
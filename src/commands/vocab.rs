@@ -0,0 +1,324 @@
+// Vocab command - dictionary lookups through Jisho, without going through Ayumi
+
+use futures::StreamExt;
+use poise::serenity_prelude as serenity;
+use tracing::{error, warn};
+
+use crate::api::jisho::{self, JishoEntry, JishoSense};
+use crate::api::tatoeba::{self, ExampleSentence};
+use crate::utils::config::colors;
+use crate::utils::kana::romaji_to_hiragana;
+use crate::{Context, Error};
+
+/// Candidate entries offered when the query is ambiguous
+const MAX_CANDIDATES: usize = 3;
+/// Senses listed per entry, the rest are on Jisho
+const MAX_SENSES: usize = 8;
+const MAX_EXAMPLES: usize = 5;
+const BUTTON_TIMEOUT_SECS: u64 = 120;
+
+/// Look up a Japanese word (kanji, kana, romaji or English)
+#[poise::command(slash_command, prefix_command)]
+pub async fn vocab(
+    ctx: Context<'_>,
+    #[description = "Kata yang dicari, mis. 食べる, たべる atau taberu"] word: String,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+
+    let client = &ctx.data().http_client;
+    let candidates = match lookup(client, &word).await {
+        Ok(c) => c,
+        Err(e) => {
+            error!("Jisho lookup for {:?} failed: {:?}", word, e);
+            ctx.say("Gagal menghubungi Jisho. Coba lagi nanti.").await?;
+            return Ok(());
+        }
+    };
+    if candidates.is_empty() {
+        ctx.say(format!("Tidak ada hasil untuk **{}**.", word.trim()))
+            .await?;
+        return Ok(());
+    }
+
+    let mut selected = 0;
+    let mut examples: Vec<Option<Vec<ExampleSentence>>> = vec![None; candidates.len()];
+    let mut showing_examples = false;
+
+    let reply = ctx
+        .send(
+            poise::CreateReply::default()
+                .embed(entry_embed(
+                    &candidates[selected],
+                    selected,
+                    candidates.len(),
+                ))
+                .components(buttons(&candidates, selected, showing_examples, false)),
+        )
+        .await?;
+
+    let msg = reply.message().await?;
+    let mut collector = msg
+        .await_component_interactions(ctx)
+        .author_id(ctx.author().id)
+        .timeout(std::time::Duration::from_secs(BUTTON_TIMEOUT_SECS))
+        .stream();
+
+    while let Some(interaction) = collector.next().await {
+        let custom_id = interaction.data.custom_id.as_str();
+        if custom_id == "vocab_examples" {
+            showing_examples = !showing_examples;
+        } else if let Some(i) = custom_id
+            .strip_prefix("vocab_entry_")
+            .and_then(|i| i.parse::<usize>().ok())
+            .filter(|i| *i < candidates.len())
+        {
+            selected = i;
+        } else {
+            continue;
+        }
+
+        // Fetching sentences can take longer than an interaction may wait
+        interaction
+            .create_response(ctx, serenity::CreateInteractionResponse::Acknowledge)
+            .await?;
+
+        let embed = if showing_examples {
+            let entry = &candidates[selected];
+            if examples[selected].is_none() {
+                examples[selected] = Some(
+                    tatoeba::example_sentences(client, entry.headword())
+                        .await
+                        .unwrap_or_else(|e| {
+                            warn!("Example sentences for {} failed: {:?}", entry.slug, e);
+                            Vec::new()
+                        }),
+                );
+            }
+            examples_embed(entry, examples[selected].as_deref().unwrap_or_default())
+        } else {
+            entry_embed(&candidates[selected], selected, candidates.len())
+        };
+
+        reply
+            .edit(
+                ctx,
+                poise::CreateReply::default()
+                    .embed(embed)
+                    .components(buttons(&candidates, selected, showing_examples, false)),
+            )
+            .await?;
+    }
+
+    // Disable buttons on timeout
+    let _ = reply
+        .edit(
+            ctx,
+            poise::CreateReply::default().components(buttons(
+                &candidates,
+                selected,
+                showing_examples,
+                true,
+            )),
+        )
+        .await;
+
+    Ok(())
+}
+
+/// Best entries for `word`. Romaji is searched as kana so "taberu" finds the same
+/// entry as たべる; when nothing reads like that it was English after all.
+async fn lookup(client: &reqwest::Client, word: &str) -> anyhow::Result<Vec<JishoEntry>> {
+    let word = word.trim();
+    if let Some(kana) = romaji_to_hiragana(word) {
+        let entries = jisho::search_words(client, &kana).await?;
+        if entries.iter().any(|e| e.has_reading(&kana)) {
+            return Ok(jisho::best_candidates(
+                entries,
+                word,
+                Some(&kana),
+                MAX_CANDIDATES,
+            ));
+        }
+    }
+    let entries = jisho::search_words(client, word).await?;
+    Ok(jisho::best_candidates(entries, word, None, MAX_CANDIDATES))
+}
+
+/// Numbered senses, with the part of speech shown whenever it changes
+fn sense_lines(senses: &[JishoSense]) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut last_pos: &[String] = &[];
+    for (i, sense) in senses.iter().take(MAX_SENSES).enumerate() {
+        if !sense.parts_of_speech.is_empty() && sense.parts_of_speech != last_pos {
+            lines.push(format!("*{}*", sense.parts_of_speech.join(", ")));
+            last_pos = &sense.parts_of_speech;
+        }
+        let mut line = format!("{}. {}", i + 1, sense.english_definitions.join("; "));
+        let notes: Vec<&str> = sense
+            .tags
+            .iter()
+            .chain(&sense.info)
+            .map(String::as_str)
+            .collect();
+        if !notes.is_empty() {
+            line.push_str(&format!(" ({})", notes.join(", ")));
+        }
+        lines.push(line);
+    }
+    if senses.len() > MAX_SENSES {
+        lines.push(format!("…and {} more", senses.len() - MAX_SENSES));
+    }
+    lines
+}
+
+/// Other written forms and readings, deduplicated, headword first
+fn forms(entry: &JishoEntry) -> String {
+    let mut forms: Vec<String> = Vec::new();
+    for japanese in &entry.japanese {
+        let form = match (&japanese.word, &japanese.reading) {
+            (Some(word), Some(reading)) => format!("{}【{}】", word, reading),
+            (Some(word), None) => word.clone(),
+            (None, Some(reading)) => reading.clone(),
+            (None, None) => continue,
+        };
+        if !forms.contains(&form) {
+            forms.push(form);
+        }
+    }
+    forms.join("、")
+}
+
+fn jisho_url(entry: &JishoEntry) -> String {
+    format!(
+        "https://jisho.org/word/{}",
+        urlencoding::encode(&entry.slug)
+    )
+}
+
+fn entry_embed(entry: &JishoEntry, index: usize, total: usize) -> serenity::CreateEmbed {
+    let mut badges = Vec::new();
+    if entry.is_common {
+        badges.push("Common word".to_string());
+    }
+    if let Some(level) = entry.jlpt_level() {
+        badges.push(format!("JLPT {}", level));
+    }
+
+    let mut description = forms(entry);
+    if !badges.is_empty() {
+        description.push_str(&format!("\n**{}**", badges.join(" · ")));
+    }
+    description.push_str("\n\n");
+    description.push_str(&sense_lines(&entry.senses).join("\n"));
+
+    let mut embed = serenity::CreateEmbed::new()
+        .title(entry.headword())
+        .url(jisho_url(entry))
+        .description(description)
+        .color(colors::PRIMARY);
+    if total > 1 {
+        embed = embed.footer(serenity::CreateEmbedFooter::new(format!(
+            "Entry {}/{} · Data from Jisho.org",
+            index + 1,
+            total
+        )));
+    } else {
+        embed = embed.footer(serenity::CreateEmbedFooter::new("Data from Jisho.org"));
+    }
+    embed
+}
+
+fn examples_embed(entry: &JishoEntry, examples: &[ExampleSentence]) -> serenity::CreateEmbed {
+    let description = if examples.is_empty() {
+        "No example sentences found.".to_string()
+    } else {
+        examples
+            .iter()
+            .take(MAX_EXAMPLES)
+            .map(|s| match &s.english {
+                Some(english) => format!("{}\n*{}*", s.japanese, english),
+                None => s.japanese.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    };
+
+    serenity::CreateEmbed::new()
+        .title(format!("Examples - {}", entry.headword()))
+        .url(jisho_url(entry))
+        .description(description)
+        .color(colors::PRIMARY)
+        .footer(serenity::CreateEmbedFooter::new("Sentences from Tatoeba"))
+}
+
+fn buttons(
+    candidates: &[JishoEntry],
+    selected: usize,
+    showing_examples: bool,
+    disabled: bool,
+) -> Vec<serenity::CreateActionRow> {
+    let mut row = Vec::new();
+    if candidates.len() > 1 {
+        for (i, entry) in candidates.iter().enumerate() {
+            row.push(
+                serenity::CreateButton::new(format!("vocab_entry_{}", i))
+                    .label(entry.headword())
+                    .style(if i == selected {
+                        serenity::ButtonStyle::Primary
+                    } else {
+                        serenity::ButtonStyle::Secondary
+                    })
+                    .disabled(disabled),
+            );
+        }
+    }
+    row.push(
+        serenity::CreateButton::new("vocab_examples")
+            .label(if showing_examples {
+                "Definitions"
+            } else {
+                "Examples"
+            })
+            .style(serenity::ButtonStyle::Success)
+            .disabled(disabled),
+    );
+    vec![serenity::CreateActionRow::Buttons(row)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sense(definitions: &[&str], pos: &[&str]) -> JishoSense {
+        JishoSense {
+            english_definitions: definitions.iter().map(|s| s.to_string()).collect(),
+            parts_of_speech: pos.iter().map(|s| s.to_string()).collect(),
+            tags: Vec::new(),
+            info: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_sense_lines() {
+        let mut slang = sense(&["to live on"], &["Ichidan verb"]);
+        slang.tags.push("Colloquial".to_string());
+        let senses = vec![
+            sense(&["to eat"], &["Ichidan verb", "Transitive verb"]),
+            sense(
+                &["to live on (e.g. a salary)"],
+                &["Ichidan verb", "Transitive verb"],
+            ),
+            slang,
+        ];
+        assert_eq!(
+            sense_lines(&senses),
+            vec![
+                "*Ichidan verb, Transitive verb*",
+                "1. to eat",
+                "2. to live on (e.g. a salary)",
+                "*Ichidan verb*",
+                "3. to live on (Colloquial)",
+            ]
+        );
+    }
+}
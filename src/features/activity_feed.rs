@@ -0,0 +1,142 @@
+// Activity feed
+// Guilds that set an Activity Feed Channel get one line there for every immersion log, a
+// passive view of what the community is up to. Members who opted out of the leaderboard
+// are left out of it too.
+
+use poise::serenity_prelude as serenity;
+
+use crate::utils::config::{get_guild_config, get_media_label, get_unit};
+use crate::utils::discord::send_checked;
+use crate::utils::formatters::{format_number, truncate_chars};
+use crate::Data;
+
+/// Longest title shown, the line stays a line
+const TITLE_MAX_CHARS: usize = 80;
+
+/// One log as the feed shows it
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedEntry {
+    pub display_name: String,
+    /// Stats key of the media type, e.g. "anime"
+    pub media_type: &'static str,
+    pub amount: f64,
+    /// What the log is worth in the guild, with its points overrides
+    pub points: i64,
+    /// `None` for untitled logs
+    pub title: Option<String>,
+    /// The message the log was made from, when there is one
+    pub link: Option<String>,
+}
+
+/// Link to a message in a guild channel
+pub fn message_link(
+    guild_id: serenity::GuildId,
+    channel_id: serenity::ChannelId,
+    message_id: serenity::MessageId,
+) -> String {
+    format!(
+        "https://discord.com/channels/{}/{}/{}",
+        guild_id, channel_id, message_id
+    )
+}
+
+/// The feed line of a log
+pub fn feed_line(entry: &FeedEntry) -> String {
+    let amount = if entry.amount == entry.amount.trunc() {
+        format_number(entry.amount as i64)
+    } else {
+        format!("{:.1}", entry.amount)
+    };
+    let mut line = format!(
+        "**{}** logged **{} {}** of {}",
+        entry.display_name,
+        amount,
+        get_unit(entry.media_type),
+        get_media_label(entry.media_type)
+    );
+    if let Some(title) = &entry.title {
+        line.push_str(&format!(" — *{}*", truncate_chars(title, TITLE_MAX_CHARS)));
+    }
+    line.push_str(&format!(" · +{} pts", format_number(entry.points)));
+    if let Some(link) = &entry.link {
+        line.push_str(&format!(" · [jump]({})", link));
+    }
+    line
+}
+
+/// Post a log to the guild's feed in the background, when it has one. Sending never holds
+/// up the command; a channel the bot can't post in is logged and reported to the mod log.
+pub async fn post(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild_id: serenity::GuildId,
+    entry: FeedEntry,
+) {
+    let Some(config) = get_guild_config(data, &guild_id.to_string()).await else {
+        return;
+    };
+    let Some(channel_id) = config
+        .activity_feed_channel_id
+        .as_deref()
+        .and_then(|id| id.parse().ok())
+        .map(serenity::ChannelId::new)
+    else {
+        return;
+    };
+    let report_to = config
+        .mod_log_channel_id
+        .as_deref()
+        .and_then(|id| id.parse().ok())
+        .map(serenity::ChannelId::new);
+
+    let message = serenity::CreateMessage::new()
+        .content(feed_line(&entry))
+        // Titles are user text, nothing in them should ping anyone
+        .allowed_mentions(serenity::CreateAllowedMentions::new());
+    let ctx = ctx.clone();
+    tokio::spawn(async move {
+        // Failures are logged by send_checked
+        let _ = send_checked(&ctx, channel_id, message, report_to).await;
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feed_line() {
+        let mut entry = FeedEntry {
+            display_name: "Yuki".to_string(),
+            media_type: "anime",
+            amount: 3.0,
+            points: 285,
+            title: Some("Sousou no Frieren".to_string()),
+            link: None,
+        };
+        assert_eq!(
+            feed_line(&entry),
+            "**Yuki** logged **3 episodes** of Anime — *Sousou no Frieren* · +285 pts"
+        );
+
+        entry.title = Some("あ".repeat(100));
+        entry.link = Some(message_link(
+            serenity::GuildId::new(1),
+            serenity::ChannelId::new(2),
+            serenity::MessageId::new(3),
+        ));
+        let line = feed_line(&entry);
+        assert!(line.contains(&format!("*{}...*", "あ".repeat(77))));
+        assert!(line.ends_with("· [jump](https://discord.com/channels/1/2/3)"));
+
+        entry.media_type = "visual_novel";
+        entry.amount = 12500.0;
+        entry.points = 1250;
+        entry.title = None;
+        entry.link = None;
+        assert_eq!(
+            feed_line(&entry),
+            "**Yuki** logged **12,500 characters** of Visual Novel · +1,250 pts"
+        );
+    }
+}
@@ -4,26 +4,50 @@
 use poise::serenity_prelude as serenity;
 use tracing::{debug, error, info};
 
-use crate::commands::afk::{get_afk_data, is_afk, remove_afk};
+use crate::commands::afk::unix_now;
+use crate::utils::config::get_guild_config;
+use crate::utils::i18n::{t, tf, Msg};
+use crate::Data;
 
 /// Handle AFK-related events on message create
 pub async fn handle_afk_message(
     ctx: &serenity::Context,
     msg: &serenity::Message,
+    data: &Data,
 ) -> Result<(), anyhow::Error> {
     // Ignore bots
     if msg.author.bot {
         return Ok(());
     }
 
-    // Check if the message author is AFK - remove their status
+    // Cache only, most messages neither come from nor mention an AFK user
     let author_id = msg.author.id.get();
-    if is_afk(author_id).await {
+    let mentions_afk = msg
+        .mentions
+        .iter()
+        .any(|user| !user.bot && data.afk.maybe_afk(user.id.get()));
+    if !data.afk.maybe_afk(author_id) && !mentions_afk {
+        return Ok(());
+    }
+    let config = match msg.guild_id {
+        Some(guild_id) => get_guild_config(data, &guild_id.to_string()).await,
+        None => None,
+    };
+    let config = config.as_ref();
+
+    // Check if the message author is AFK - remove their status
+    if data.afk.maybe_afk(author_id) {
         info!(
             "[AFK] User {} ({}) sent a message while AFK, removing status",
             msg.author.name, author_id
         );
-        if let Some(_afk_data) = remove_afk(author_id).await {
+        // A status that lapsed on its own is cleared without a welcome back
+        if let Some(_afk_data) = data
+            .afk
+            .clear(author_id)
+            .await
+            .filter(|afk_data| !afk_data.is_expired(unix_now()))
+        {
             let embed = serenity::CreateEmbed::new()
                 .color(0x2ecc71) // Green
                 .author(
@@ -33,8 +57,8 @@ pub async fn handle_afk_message(
                             .unwrap_or_else(|| msg.author.default_avatar_url()),
                     ),
                 )
-                .title("Selamat Datang Kembali")
-                .description("Status AFK kamu telah dihapus")
+                .title(t(config, Msg::AfkWelcomeBackTitle))
+                .description(t(config, Msg::AfkWelcomeBackBody))
                 .timestamp(serenity::Timestamp::now());
 
             match msg
@@ -59,7 +83,7 @@ pub async fn handle_afk_message(
             }
         } else {
             debug!(
-                "[AFK] Race condition: AFK already removed for {} ({})",
+                "[AFK] AFK already removed or lapsed for {} ({})",
                 msg.author.name, author_id
             );
         }
@@ -73,22 +97,31 @@ pub async fn handle_afk_message(
         }
 
         let mentioned_id = mentioned_user.id.get();
-        if let Some(afk_data) = get_afk_data(mentioned_id).await {
+        if !data.afk.maybe_afk(mentioned_id) {
+            continue;
+        }
+        if let Some(afk_data) = data.afk.get(mentioned_id, unix_now()).await {
             debug!(
                 "[AFK] User {} mentioned AFK user {} ({})",
                 msg.author.name, afk_data.username, mentioned_id
             );
+            let mut description = format!(
+                "{}\n{}",
+                tf(config, Msg::AfkReason, &[&afk_data.reason]),
+                tf(config, Msg::AfkSince, &[&afk_data.timestamp])
+            );
+            if let Some(at) = afk_data.expires_at {
+                description.push('\n');
+                description.push_str(&tf(config, Msg::AfkBack, &[&at]));
+            }
             let embed = serenity::CreateEmbed::new()
                 .color(0xe67e22) // Orange
                 .author(
                     serenity::CreateEmbedAuthor::new(&afk_data.username)
                         .icon_url(&afk_data.avatar_url),
                 )
-                .title(format!("{} sedang AFK", afk_data.username))
-                .description(format!(
-                    "**Alasan:** {}\n**Sejak:** <t:{}:R>",
-                    afk_data.reason, afk_data.timestamp
-                ))
+                .title(tf(config, Msg::AfkIsAfk, &[&afk_data.username]))
+                .description(description)
                 .timestamp(serenity::Timestamp::now());
 
             if let Err(e) = msg
@@ -0,0 +1,157 @@
+// AFK store
+// AFK statuses live in Firestore (`afk/{user id}`) so they survive restarts, with a cache
+// in front so the message handler doesn't read Firestore for every message.
+//
+// The cache is filled at startup and updated on every set and clear. A cached entry only
+// means "maybe AFK": mentions confirm it against Firestore, since another shard may have
+// cleared it. Users missing from the cache are not AFK.
+
+use dashmap::DashMap;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::api::storage::Storage;
+use crate::models::afk::AfkData;
+
+pub struct AfkStore {
+    firebase: Arc<dyn Storage>,
+    cache: DashMap<u64, AfkData>,
+}
+
+/// What a mention check concludes about a cached AFK user
+#[derive(Debug, PartialEq)]
+enum Status {
+    Afk(AfkData),
+    /// Cleared elsewhere, only the cache still had it
+    Gone,
+    /// Lapsed and should be deleted
+    Expired,
+}
+
+/// Combine the cached entry with the stored document. Firestore wins; when it can't be
+/// read the cached entry is trusted.
+fn resolve(cached: AfkData, stored: anyhow::Result<Option<AfkData>>, now: u64) -> Status {
+    let data = match stored {
+        Ok(Some(stored)) => stored,
+        Ok(None) => return Status::Gone,
+        Err(e) => {
+            warn!("Failed to read AFK status, using the cached one: {:?}", e);
+            cached
+        }
+    };
+    if data.is_expired(now) {
+        Status::Expired
+    } else {
+        Status::Afk(data)
+    }
+}
+
+impl AfkStore {
+    pub fn new(firebase: Arc<dyn Storage>) -> Self {
+        Self {
+            firebase,
+            cache: DashMap::new(),
+        }
+    }
+
+    /// Fill the cache with every stored status, at startup
+    pub async fn load(&self) {
+        match self.firebase.get_all_afk().await {
+            Ok(statuses) => {
+                info!("Loaded {} AFK statuses", statuses.len());
+                for (user_id, data) in statuses {
+                    self.cache.insert(user_id, data);
+                }
+            }
+            Err(e) => warn!("Failed to load AFK statuses: {:?}", e),
+        }
+    }
+
+    pub async fn set(&self, user_id: u64, data: AfkData) -> anyhow::Result<()> {
+        self.firebase.set_afk(user_id, &data).await?;
+        self.cache.insert(user_id, data);
+        Ok(())
+    }
+
+    /// Whether the cache has a status for the user, without touching Firestore
+    pub fn maybe_afk(&self, user_id: u64) -> bool {
+        self.cache.contains_key(&user_id)
+    }
+
+    /// The user's current status, confirmed against Firestore when the cache has one.
+    /// Expired statuses are removed here.
+    pub async fn get(&self, user_id: u64, now: u64) -> Option<AfkData> {
+        let cached = self.cache.get(&user_id).map(|entry| entry.clone())?;
+        match resolve(cached, self.firebase.get_afk(user_id).await, now) {
+            Status::Afk(data) => {
+                self.cache.insert(user_id, data.clone());
+                Some(data)
+            }
+            Status::Gone => {
+                self.cache.remove(&user_id);
+                None
+            }
+            Status::Expired => {
+                self.clear(user_id).await;
+                None
+            }
+        }
+    }
+
+    /// Remove the user's status. Returns it when this call was the one that removed it,
+    /// so two messages racing each other don't both welcome the user back.
+    pub async fn clear(&self, user_id: u64) -> Option<AfkData> {
+        let (_, data) = self.cache.remove(&user_id)?;
+        if let Err(e) = self.firebase.delete_afk(user_id).await {
+            warn!("Failed to delete AFK status of {}: {:?}", user_id, e);
+        }
+        Some(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(reason: &str, expires_at: Option<u64>) -> AfkData {
+        AfkData {
+            username: "yuki".to_string(),
+            reason: reason.to_string(),
+            timestamp: 100,
+            avatar_url: String::new(),
+            expires_at,
+            guild_id: None,
+        }
+    }
+
+    #[test]
+    fn test_stored_status_wins() {
+        let cached = data("makan", None);
+        assert_eq!(
+            resolve(cached.clone(), Ok(Some(data("tidur", None))), 150),
+            Status::Afk(data("tidur", None))
+        );
+        assert_eq!(resolve(cached.clone(), Ok(None), 150), Status::Gone);
+        assert_eq!(
+            resolve(cached.clone(), Err(anyhow::anyhow!("offline")), 150),
+            Status::Afk(cached)
+        );
+    }
+
+    #[test]
+    fn test_expired_status() {
+        let expiring = data("makan", Some(200));
+        assert_eq!(
+            resolve(expiring.clone(), Ok(Some(expiring.clone())), 200),
+            Status::Expired
+        );
+        assert_eq!(
+            resolve(expiring.clone(), Err(anyhow::anyhow!("offline")), 250),
+            Status::Expired
+        );
+        assert_eq!(
+            resolve(expiring.clone(), Ok(Some(expiring.clone())), 199),
+            Status::Afk(expiring)
+        );
+    }
+}
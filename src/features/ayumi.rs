@@ -1,14 +1,17 @@
 use chrono::{DateTime, Utc};
+use dashmap::DashMap;
 use lru::LruCache;
 use once_cell::sync::Lazy;
 use poise::serenity_prelude as serenity;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, Semaphore};
 use tracing::{debug, error};
 
-use crate::api::llm::{completion_chat_with_fallback, ChatMessage};
+use crate::api::llm::{completion_chat_with_fallback, generate_image, ChatMessage};
 use crate::api::ocr;
 use crate::features::custom_prompt::get_user_custom_prompt;
 use crate::features::novel_recommender::smart_novel_search;
@@ -36,7 +39,13 @@ pub struct UserData {
 }
 
 impl UserData {
-    pub fn new(user_id: u64, username: &str, display_name: &str, nickname: Option<&str>) -> Self {
+    pub fn new(
+        user_id: u64,
+        username: &str,
+        display_name: &str,
+        nickname: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Self {
         let best_name = nickname.unwrap_or(display_name).to_string();
         Self {
             user_id,
@@ -45,7 +54,7 @@ impl UserData {
             nickname: nickname.map(|s| s.to_string()),
             best_name,
             interaction_count: 1,
-            last_interaction: Utc::now(),
+            last_interaction: now,
             conversation_history: Vec::new(),
         }
     }
@@ -60,6 +69,13 @@ static CONVERSATION_HISTORY: Lazy<Arc<Mutex<HistoryCache>>> =
 
 static USER_DATA: Lazy<Arc<Mutex<UserCache>>> = Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
 
+/// Users with a cached conversation, and the messages cached for them
+pub async fn history_cache_size() -> (usize, usize) {
+    let cache = CONVERSATION_HISTORY.lock().await;
+    let messages = cache.iter().map(|(_, history)| history.len()).sum();
+    (cache.len(), messages)
+}
+
 // ============ Detection Functions ============
 
 #[allow(dead_code)]
@@ -133,69 +149,451 @@ fn detect_novel_request(text: &str) -> bool {
     keywords.iter().any(|k| lower.contains(k))
 }
 
-// ============ Smart Message Chunking ============
+// ============ Image Generation Limits ============
 
-/// Split message by lines to avoid cutting words
-fn smart_chunk_message(text: &str, max_len: usize) -> Vec<String> {
-    if text.len() <= max_len {
-        return vec![text.to_string()];
+/// Generations a member may start per hour, unless `AYUMI_IMAGE_USER_LIMIT` says otherwise
+const DEFAULT_USER_IMAGE_LIMIT: usize = 3;
+
+/// Generations a guild may start per hour, unless `AYUMI_IMAGE_GUILD_LIMIT` says otherwise
+const DEFAULT_GUILD_IMAGE_LIMIT: usize = 20;
+
+/// Window the limits count over
+const IMAGE_LIMIT_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+/// Generations running at once, the rest wait their turn
+const IMAGE_GENERATION_PERMITS: usize = 2;
+
+/// How often a queued request's position is refreshed in its progress message
+const QUEUE_POSITION_REFRESH: Duration = Duration::from_secs(5);
+
+/// Image generation is off unless `AYUMI_IMAGE_GENERATION=1`, it costs per image
+fn image_generation_enabled() -> bool {
+    std::env::var("AYUMI_IMAGE_GENERATION").as_deref() == Ok("1")
+}
+
+fn parse_limit(value: Option<&str>, default: usize) -> usize {
+    value
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .filter(|limit| *limit > 0)
+        .unwrap_or(default)
+}
+
+/// Sliding-window limit on image generations per member and per guild. Costs are per
+/// generation, so a generation counts the moment it's allowed, whether or not it works.
+struct ImageRateLimiter {
+    user_limit: usize,
+    guild_limit: usize,
+    users: DashMap<u64, VecDeque<Instant>>,
+    guilds: DashMap<u64, VecDeque<Instant>>,
+}
+
+impl ImageRateLimiter {
+    fn new(user_limit: usize, guild_limit: usize) -> Self {
+        Self {
+            user_limit,
+            guild_limit,
+            users: DashMap::new(),
+            guilds: DashMap::new(),
+        }
     }
 
-    let mut chunks = Vec::new();
-    let mut current_chunk = String::new();
+    fn from_env() -> Self {
+        Self::new(
+            parse_limit(
+                std::env::var("AYUMI_IMAGE_USER_LIMIT").ok().as_deref(),
+                DEFAULT_USER_IMAGE_LIMIT,
+            ),
+            parse_limit(
+                std::env::var("AYUMI_IMAGE_GUILD_LIMIT").ok().as_deref(),
+                DEFAULT_GUILD_IMAGE_LIMIT,
+            ),
+        )
+    }
 
-    for line in text.lines() {
-        // If single line is too long, split it
-        if line.len() > max_len {
-            // First, push current chunk if not empty
-            if !current_chunk.is_empty() {
-                chunks.push(current_chunk);
-                current_chunk = String::new();
+    /// Count a generation for `user_id` in `guild_id`, or `Err(wait)` until the next one is
+    /// allowed when either is at its limit
+    fn try_acquire(&self, user_id: u64, guild_id: u64, now: Instant) -> Result<(), Duration> {
+        // Both entries stay locked until the generation is counted, always user first
+        let mut user = self.users.entry(user_id).or_default();
+        let mut guild = self.guilds.entry(guild_id).or_default();
+        let user_wait = Self::wait(&mut user, self.user_limit, now);
+        let guild_wait = Self::wait(&mut guild, self.guild_limit, now);
+        match user_wait.max(guild_wait) {
+            Some(wait) => Err(wait),
+            None => {
+                user.push_back(now);
+                guild.push_back(now);
+                Ok(())
             }
+        }
+    }
 
-            // Split long line by words
-            let mut word_chunk = String::new();
-            for word in line.split_whitespace() {
-                if word_chunk.len() + word.len() + 1 > max_len {
-                    if !word_chunk.is_empty() {
-                        chunks.push(word_chunk);
-                    }
-                    word_chunk = word.to_string();
-                } else {
-                    if !word_chunk.is_empty() {
-                        word_chunk.push(' ');
+    /// Drop generations older than the window, then how long until one more fits
+    fn wait(starts: &mut VecDeque<Instant>, limit: usize, now: Instant) -> Option<Duration> {
+        while starts
+            .front()
+            .is_some_and(|start| now.duration_since(*start) >= IMAGE_LIMIT_WINDOW)
+        {
+            starts.pop_front();
+        }
+        if starts.len() < limit {
+            return None;
+        }
+        // The oldest generations have to age out until there's room for one more
+        let oldest_blocking = starts[starts.len() - limit];
+        Some(IMAGE_LIMIT_WINDOW - now.duration_since(oldest_blocking))
+    }
+}
+
+static IMAGE_LIMITS: Lazy<ImageRateLimiter> = Lazy::new(ImageRateLimiter::from_env);
+
+static IMAGE_QUEUE: Semaphore = Semaphore::const_new(IMAGE_GENERATION_PERMITS);
+
+/// Tickets handed to requests that had to wait, in the order they queued
+static IMAGE_QUEUE_TICKETS: AtomicUsize = AtomicUsize::new(0);
+
+/// Queued requests that got their slot. The semaphore is fair, so they leave in ticket order.
+static IMAGE_QUEUE_SERVED: AtomicUsize = AtomicUsize::new(0);
+
+/// Place in the queue of the request holding `ticket`, 1 when it's next
+fn queue_position(ticket: usize, served: usize) -> usize {
+    ticket.saturating_sub(served) + 1
+}
+
+/// "12 menit" or "40 detik", rounded up so it's never too early
+fn wait_text(wait: Duration) -> String {
+    let secs = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+    if secs < 60 {
+        format!("{} detik", secs.max(1))
+    } else {
+        format!("{} menit", secs.div_ceil(60))
+    }
+}
+
+/// Replace the progress message's text, so it never outlives the request
+async fn edit_progress(
+    ctx: &serenity::Context,
+    progress: &mut Option<serenity::Message>,
+    content: &str,
+) {
+    if let Some(m) = progress.as_mut() {
+        let _ = m
+            .edit(ctx, serenity::EditMessage::new().content(content))
+            .await;
+    }
+}
+
+/// Generate the image a message asks for and post it, keeping the progress message up to
+/// date while it's limited or queued. Returns what Ayumi said, for the history.
+async fn generate_image_reply(
+    ctx: &serenity::Context,
+    msg: &serenity::Message,
+    data: &Data,
+    user_name: &str,
+) -> String {
+    let mut progress = msg
+        .reply(
+            ctx,
+            format!(
+                "{}, Ayumi lagi bikin gambar sesuai request kamu nih! Tunggu sebentar ya...",
+                user_name
+            ),
+        )
+        .await
+        .ok();
+    // Only guild messages reach Ayumi
+    let guild_id = msg.guild_id.map_or(0, |id| id.get());
+    let limited = IMAGE_LIMITS.try_acquire(msg.author.id.get(), guild_id, data.clock.now_instant());
+    if let Err(wait) = limited {
+        debug!("Image generation of {} rate limited", msg.author.id);
+        let response = format!(
+            "{}, Ayumi udah kebanyakan bikin gambar nih. Coba lagi {} lagi ya!",
+            user_name,
+            wait_text(wait)
+        );
+        edit_progress(ctx, &mut progress, &response).await;
+        return response;
+    }
+
+    let permit = match IMAGE_QUEUE.try_acquire() {
+        Ok(permit) => permit,
+        Err(_) => {
+            let ticket = IMAGE_QUEUE_TICKETS.fetch_add(1, Ordering::SeqCst);
+            let queued = |position: usize| {
+                format!(
+                    "{}, lagi banyak yang minta gambar. Kamu antrian ke-{}, tunggu sebentar ya...",
+                    user_name, position
+                )
+            };
+            let mut shown = queue_position(ticket, IMAGE_QUEUE_SERVED.load(Ordering::SeqCst));
+            edit_progress(ctx, &mut progress, &queued(shown)).await;
+
+            // Moves the shown position up as the requests ahead get their turn
+            let acquire = IMAGE_QUEUE.acquire();
+            tokio::pin!(acquire);
+            let permit = loop {
+                tokio::select! {
+                    permit = &mut acquire => break permit,
+                    _ = tokio::time::sleep(QUEUE_POSITION_REFRESH) => {
+                        let position =
+                            queue_position(ticket, IMAGE_QUEUE_SERVED.load(Ordering::SeqCst));
+                        if position != shown {
+                            shown = position;
+                            edit_progress(ctx, &mut progress, &queued(shown)).await;
+                        }
                     }
-                    word_chunk.push_str(word);
                 }
+            };
+            IMAGE_QUEUE_SERVED.fetch_add(1, Ordering::SeqCst);
+            let started = format!("{}, giliran kamu! Ayumi lagi bikin gambarnya...", user_name);
+            edit_progress(ctx, &mut progress, &started).await;
+            permit.expect("the image queue is never closed")
+        }
+    };
+    let result = generate_image(data, &msg.content).await;
+    drop(permit);
+
+    match result {
+        Ok(result) => {
+            if let Some(m) = progress {
+                let _ = m.delete(ctx).await;
             }
-            if !word_chunk.is_empty() {
-                current_chunk = word_chunk;
-            }
-        } else if current_chunk.len() + line.len() + 1 > max_len {
-            // Push current chunk and start new one
-            if !current_chunk.is_empty() {
-                chunks.push(current_chunk);
-            }
-            current_chunk = line.to_string();
+            let image_data = crate::utils::images::ensure_under_limit(
+                result.image_data,
+                &result.mime_type,
+                crate::utils::images::DEFAULT_UPLOAD_LIMIT,
+            );
+            let filename = crate::utils::images::file_name(
+                &format!("ayumi_generated_{}", chrono::Utc::now().timestamp()),
+                &image_data,
+            );
+            let attachment = serenity::CreateAttachment::bytes(image_data, filename);
+            let response = format!(
+                "{}, nih gambar yang Ayumi buatin! Gimana, sesuai ekspektasi gak?",
+                user_name
+            );
+            let _ = msg
+                .channel_id
+                .send_message(
+                    ctx,
+                    serenity::CreateMessage::new()
+                        .content(&response)
+                        .add_file(attachment),
+                )
+                .await;
+            response
+        }
+        Err(e) => {
+            error!("Image generation failed: {:?}", e);
+            let response = format!(
+                "{}, maaf nih Ayumi lagi gabisa bikin gambar. Coba lagi nanti ya",
+                user_name
+            );
+            edit_progress(ctx, &mut progress, &response).await;
+            response
+        }
+    }
+}
+
+// ============ Smart Message Chunking ============
+
+const FENCE: &str = "```";
+
+/// Room a chunk keeps for the `\n```` closing a code block it splits
+const FENCE_CLOSE_LEN: usize = FENCE.len() + 1;
+
+/// Where Japanese text without spaces is best broken
+const SENTENCE_ENDS: [char; 5] = ['。', '！', '？', '!', '?'];
+
+/// Split a message into chunks of at most `max_len` characters (Discord counts
+/// characters, not bytes). Breaks between lines, then at spaces or sentence ends,
+/// mid-text only as a last resort. A code block split across chunks is closed at
+/// the end of one and reopened at the start of the next.
+fn smart_chunk_message(text: &str, max_len: usize) -> Vec<String> {
+    if text.chars().count() <= max_len {
+        return vec![text.to_string()];
+    }
+
+    let mut chunker = Chunker {
+        max_len,
+        chunks: Vec::new(),
+        current: String::new(),
+        current_len: 0,
+        open_fence: None,
+    };
+    for line in text.lines() {
+        let is_fence = line.trim_start().starts_with(FENCE);
+        let in_fence_after = chunker.open_fence.is_some() != is_fence;
+        let width = if in_fence_after {
+            // A reopened chunk starts with the fence line
+            let header_len = chunker
+                .open_fence
+                .as_deref()
+                .unwrap_or(line)
+                .chars()
+                .count();
+            max_len.saturating_sub(header_len + 1 + FENCE_CLOSE_LEN)
         } else {
-            // Add line to current chunk
-            if !current_chunk.is_empty() {
-                current_chunk.push('\n');
-            }
-            current_chunk.push_str(line);
+            max_len
+        };
+        for piece in split_line(line, width.max(1)) {
+            chunker.push(piece, in_fence_after);
+        }
+        if is_fence {
+            chunker.open_fence = match chunker.open_fence {
+                Some(_) => None,
+                None => Some(reopen_fence(line)),
+            };
         }
     }
+    chunker.finish()
+}
 
-    // Don't forget last chunk
-    if !current_chunk.is_empty() {
-        chunks.push(current_chunk);
+struct Chunker {
+    max_len: usize,
+    chunks: Vec<String>,
+    current: String,
+    current_len: usize,
+    /// Fence line of the code block the chunk is in
+    open_fence: Option<String>,
+}
+
+impl Chunker {
+    /// Append a line, starting a new chunk when it doesn't fit. `in_fence_after`:
+    /// whether a code block is open once the line is added, so its closing must fit too.
+    fn push(&mut self, line: &str, in_fence_after: bool) {
+        let line_len = line.chars().count();
+        let separator = usize::from(!self.current.is_empty());
+        let reserve = if in_fence_after { FENCE_CLOSE_LEN } else { 0 };
+        if !self.current.is_empty()
+            && self.current_len + separator + line_len + reserve > self.max_len
+        {
+            self.flush();
+        }
+        if !self.current.is_empty() {
+            self.current.push('\n');
+            self.current_len += 1;
+        }
+        self.current.push_str(line);
+        self.current_len += line_len;
+    }
+
+    /// End the chunk, closing the open code block and reopening it in the next one
+    fn flush(&mut self) {
+        let mut chunk = std::mem::take(&mut self.current);
+        self.current_len = 0;
+        if let Some(fence) = &self.open_fence {
+            chunk.push('\n');
+            chunk.push_str(FENCE);
+            self.current_len = fence.chars().count();
+            self.current = fence.clone();
+        }
+        self.chunks.push(chunk);
     }
 
-    chunks
+    fn finish(mut self) -> Vec<String> {
+        // A reopened fence with nothing after it isn't worth a message
+        let only_fence = self.open_fence.as_ref() == Some(&self.current);
+        if !self.current.is_empty() && !only_fence {
+            self.chunks.push(self.current);
+        }
+        self.chunks
+    }
+}
+
+/// The fence line to reopen a split code block with, keeping its language
+fn reopen_fence(line: &str) -> String {
+    let fence = line.trim();
+    if fence.chars().count() <= 20 {
+        fence.to_string()
+    } else {
+        FENCE.to_string()
+    }
+}
+
+/// Split a line into pieces of at most `width` characters, at the last space or
+/// sentence end when there is one. Always cuts at char boundaries.
+fn split_line(line: &str, width: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut rest = line;
+    while rest.chars().count() > width {
+        // Byte offset just past `width` characters
+        let limit = rest
+            .char_indices()
+            .nth(width)
+            .map_or(rest.len(), |(i, _)| i);
+        let window = &rest[..limit];
+        let cut = window
+            .char_indices()
+            .rev()
+            .find(|&(i, c)| i > 0 && c.is_whitespace())
+            .map(|(i, _)| i)
+            .or_else(|| {
+                window
+                    .char_indices()
+                    .rev()
+                    .find(|(_, c)| SENTENCE_ENDS.contains(c))
+                    .map(|(i, c)| i + c.len_utf8())
+            })
+            .unwrap_or(limit);
+        pieces.push(rest[..cut].trim_end());
+        rest = rest[cut..].trim_start();
+    }
+    if !rest.is_empty() || pieces.is_empty() {
+        pieces.push(rest);
+    }
+    pieces
 }
 
 // ============ Main Handler ============
 
+/// Whether a message is a side conversation Ayumi should not answer,
+/// i.e. it starts with `//` or the word `!ignore`
+pub fn is_side_message(content: &str) -> bool {
+    let content = content.trim_start();
+    if content.starts_with("//") {
+        return true;
+    }
+    content
+        .strip_prefix("!ignore")
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+}
+
+/// Characters of a replied-to message passed on to the model
+const REPLY_CONTEXT_CHARS: usize = 500;
+
+/// Whether a reply or mention outside the Ayumi channel is too slight to answer: under 3
+/// characters, or nothing but emoji and punctuation
+fn is_trivial_reply(content: &str) -> bool {
+    let mut text = String::new();
+    let mut rest = content;
+    // Drop custom emoji and mentions (`<:name:id>`, `<@id>`)
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        match rest[start..].find('>') {
+            Some(end) => rest = &rest[start + end + 1..],
+            None => {
+                rest = &rest[start..];
+                break;
+            }
+        }
+    }
+    text.push_str(rest);
+
+    content.trim().chars().count() < 3 || !text.chars().any(char::is_alphanumeric)
+}
+
+/// The user's message with the message they replied to in front of it
+fn with_reply_context(author: &str, referenced: &str, content: &str) -> String {
+    let referenced: String = referenced.chars().take(REPLY_CONTEXT_CHARS).collect();
+    format!(
+        "(Membalas pesan {}: \"{}\")\n{}",
+        author,
+        referenced.trim(),
+        content
+    )
+}
+
 pub async fn handle_message(
     ctx: &serenity::Context,
     msg: &serenity::Message,
@@ -213,53 +611,64 @@ pub async fn handle_message(
     // Determine trigger mode: ayumi channel (free chat) vs other channels (direct @mention only)
     let bot_id = ctx.cache.current_user().id;
 
-    // Get guild config for ayumi_channel_id
-    let ayumi_channel_id = {
-        let config = if let Some(cached) = data.guild_configs.get(&guild_id) {
-            cached.clone()
-        } else {
-            match data.firebase.get_document("guilds", &guild_id).await {
-                Ok(Some(doc)) => {
-                    let cfg = serde_json::from_value::<GuildConfig>(doc).unwrap_or_default();
-                    data.guild_configs.insert(guild_id.clone(), cfg.clone());
-                    cfg
-                }
-                _ => GuildConfig::default(),
+    // Get guild config for ayumi_channel_id and the members who muted Ayumi
+    let config = if let Some(cached) = data.guild_configs.get(&guild_id) {
+        cached.clone()
+    } else {
+        match data.firebase.get_document("guilds", &guild_id).await {
+            Ok(Some(doc)) => {
+                let cfg = GuildConfig::from_doc(doc);
+                data.guild_configs.insert(guild_id.clone(), cfg.clone());
+                cfg
             }
-        };
-        config.ayumi_channel_id
+            _ => GuildConfig::default(),
+        }
     };
 
-    let in_ayumi_channel = ayumi_channel_id
+    let in_ayumi_channel = config
+        .ayumi_channel_id
         .as_ref()
-        .map_or(false, |id| msg.channel_id.to_string() == *id);
+        .is_some_and(|id| msg.channel_id.to_string() == *id);
+
+    if in_ayumi_channel && config.ayumi_muted(msg.author.id.get()) {
+        return Ok(());
+    }
 
     let clean_content = if in_ayumi_channel {
         // Ayumi channel: free chat, use message as-is
         msg.content.clone()
     } else {
-        // Other channels: require direct @mention only
+        // Other channels: a reply to Ayumi or a direct @mention, in guilds that set up an
+        // Ayumi channel
         let has_direct_mention = msg.mentions.iter().any(|u| u.id == bot_id);
-        // Block: no mention, @everyone/@here, or reply to bot's own message (auto-mention)
         let is_reply_to_bot = msg
             .referenced_message
             .as_ref()
-            .map_or(false, |r| r.author.id == bot_id);
-        if !has_direct_mention || msg.mention_everyone || is_reply_to_bot {
+            .is_some_and(|r| r.author.id == bot_id);
+        if config.ayumi_channel_id.is_none()
+            || msg.mention_everyone
+            || !(has_direct_mention || is_reply_to_bot)
+        {
             return Ok(());
         }
         // Strip mention tag
-        msg.content
+        let content = msg
+            .content
             .replace(&format!("<@{}>", bot_id), "")
             .replace(&format!("<@!{}>", bot_id), "")
             .trim()
-            .to_string()
+            .to_string();
+        if is_trivial_reply(&content) {
+            return Ok(());
+        }
+        content
     };
 
-    if clean_content.is_empty() {
+    if clean_content.is_empty() || is_side_message(&clean_content) {
         return Ok(());
     }
 
+    // Only once it's certain Ayumi answers
     let _typing = msg.channel_id.start_typing(&ctx.http);
 
     // Get or create user data
@@ -270,17 +679,18 @@ pub async fn handle_message(
         .global_name
         .as_deref()
         .unwrap_or(&msg.author.name);
+    let now = data.clock.now_utc();
 
     let (user_name, interaction_count) = {
         let mut users = USER_DATA.lock().await;
-        let user_data = users
-            .entry(user_id)
-            .or_insert_with(|| UserData::new(user_id, &msg.author.name, display_name, nickname));
+        let user_data = users.entry(user_id).or_insert_with(|| {
+            UserData::new(user_id, &msg.author.name, display_name, nickname, now)
+        });
         user_data.interaction_count += 1;
-        user_data.last_interaction = Utc::now();
-        if nickname.is_some() {
-            user_data.nickname = nickname.map(|s| s.to_string());
-            user_data.best_name = nickname.unwrap().to_string();
+        user_data.last_interaction = now;
+        if let Some(nick) = nickname {
+            user_data.nickname = Some(nick.to_string());
+            user_data.best_name = nick.to_string();
         }
         (user_data.best_name.clone(), user_data.interaction_count)
     };
@@ -292,16 +702,32 @@ pub async fn handle_message(
     };
 
     let mut messages = history_clone;
+    // The replied-to message, which may not be in this user's history
+    let user_message = match &msg.referenced_message {
+        Some(referenced) if !referenced.content.trim().is_empty() => {
+            let author = if referenced.author.id == bot_id {
+                "Ayumi"
+            } else {
+                referenced
+                    .author
+                    .global_name
+                    .as_deref()
+                    .unwrap_or(&referenced.author.name)
+            };
+            with_reply_context(author, &referenced.content, &clean_content)
+        }
+        _ => clean_content.clone(),
+    };
     messages.push(ChatMessage {
         role: "user".to_string(),
-        content: clean_content.clone(),
+        content: user_message,
     });
 
     // Check for image attachment
     let attachment = msg.attachments.iter().find(|a| {
         a.content_type
             .as_ref()
-            .map_or(false, |ct| ct.starts_with("image/"))
+            .is_some_and(|ct| ct.starts_with("image/"))
     });
 
     let response: String;
@@ -349,7 +775,8 @@ pub async fn handle_message(
             content: image_context,
         });
 
-        let system_prompt = get_user_custom_prompt(msg.author.id.get())
+        let system_prompt = get_user_custom_prompt(data.prompt_store.as_ref(), msg.author.id.get())
+            .await
             .unwrap_or_else(|| AYUMI_SYSTEM_PROMPT.to_string());
 
         response = match completion_chat_with_fallback(data, &system_prompt, messages.clone()).await
@@ -360,74 +787,23 @@ pub async fn handle_message(
                 "Maaf, Ayumi gak bisa baca teks di gambarnya...".to_string()
             }
         };
-    // } else if detect_image_generation(&msg.content) {
-    //     debug!("Processing image generation for user {}", user_name);
-    //
-    //     let generating_msg = msg
-    //         .reply(
-    //             ctx,
-    //             format!(
-    //                 "{}, Ayumi lagi bikin gambar sesuai request kamu nih! Tunggu sebentar ya...",
-    //                 user_name
-    //             ),
-    //         )
-    //         .await.ok();
-    //
-    //     match generate_image(data, &msg.content).await {
-    //         Ok(result) => {
-    //             if let Some(m) = generating_msg { let _ = m.delete(ctx).await; }
-    //             let extension = if result.mime_type.contains("png") {
-    //                 "png"
-    //             } else {
-    //                 "jpg"
-    //             };
-    //             let filename = format!(
-    //                 "ayumi_generated_{}.{}",
-    //                 chrono::Utc::now().timestamp(),
-    //                 extension
-    //             );
-    //
-    //             let attachment = serenity::CreateAttachment::bytes(result.image_data, filename);
-    //             let reply_content = format!(
-    //                 "{}, nih gambar yang Ayumi buatin! Gimana, sesuai ekspektasi gak?",
-    //                 user_name
-    //             );
-    //
-    //             msg.channel_id
-    //                 .send_message(
-    //                     ctx,
-    //                     serenity::CreateMessage::new()
-    //                         .content(&reply_content)
-    //                         .add_file(attachment),
-    //                 )
-    //                 .await.ok();
-    //
-    //             response = reply_content;
-    //         }
-    //         Err(e) => {
-    //             error!("Image generation failed: {:?}", e);
-    //             if let Some(m) = generating_msg { let _ = m.delete(ctx).await; }
-    //             response = format!(
-    //                 "{}, maaf nih Ayumi lagi gabisa bikin gambar. Coba lagi nanti ya",
-    //                 user_name
-    //             );
-    //             msg.reply(ctx, &response).await.ok();
-    //         }
-    //     };
-    //
-    //     // Update history and return
-    //     {
-    //         let mut cache = CONVERSATION_HISTORY.lock().await;
-    //         messages.push(ChatMessage {
-    //             role: "assistant".to_string(),
-    //             content: response.clone(),
-    //         });
-    //         if messages.len() > 20 {
-    //             messages = messages.iter().rev().take(20).rev().cloned().collect();
-    //         }
-    //         cache.put(user_id, messages);
-    //     }
-    //     return Ok(());
+    } else if image_generation_enabled() && detect_image_generation(&msg.content) {
+        debug!("Processing image generation for user {}", user_name);
+        let response = generate_image_reply(ctx, msg, data, &user_name).await;
+
+        // Update history and return
+        {
+            let mut cache = CONVERSATION_HISTORY.lock().await;
+            messages.push(ChatMessage {
+                role: "assistant".to_string(),
+                content: response.clone(),
+            });
+            if messages.len() > 20 {
+                messages = messages.iter().rev().take(20).rev().cloned().collect();
+            }
+            cache.put(user_id, messages);
+        }
+        return Ok(());
     } else if detect_avatar_question(&msg.content) {
         debug!("Processing avatar analysis for user {}", user_name);
 
@@ -470,7 +846,8 @@ pub async fn handle_message(
             user_name, interaction_count, clean_content, ocr_result
         );
 
-        let system_prompt = get_user_custom_prompt(msg.author.id.get())
+        let system_prompt = get_user_custom_prompt(data.prompt_store.as_ref(), msg.author.id.get())
+            .await
             .unwrap_or_else(|| AYUMI_SYSTEM_PROMPT.to_string());
 
         let avatar_msgs = vec![ChatMessage {
@@ -503,8 +880,9 @@ pub async fn handle_message(
             user_name, interaction_count
         );
 
-        let system_prompt =
-            get_user_custom_prompt(user_id).unwrap_or_else(|| AYUMI_SYSTEM_PROMPT.to_string());
+        let system_prompt = get_user_custom_prompt(data.prompt_store.as_ref(), user_id)
+            .await
+            .unwrap_or_else(|| AYUMI_SYSTEM_PROMPT.to_string());
 
         let full_prompt = format!("{}\n\n{}", system_prompt, user_context);
 
@@ -553,3 +931,200 @@ pub async fn handle_message(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_limit_per_user() {
+        let limiter = ImageRateLimiter::new(3, 20);
+        let start = Instant::now();
+        let minute = Duration::from_secs(60);
+        for i in 0..3 {
+            assert!(limiter.try_acquire(1, 100, start + minute * i).is_ok());
+        }
+        // The fourth waits for the first to leave the hour
+        assert_eq!(
+            limiter.try_acquire(1, 100, start + minute * 10),
+            Err(minute * 50)
+        );
+        // Someone else in the guild isn't held up
+        assert!(limiter.try_acquire(2, 100, start + minute * 10).is_ok());
+        assert!(limiter.try_acquire(1, 100, start + minute * 60).is_ok());
+        assert_eq!(
+            limiter.try_acquire(1, 100, start + minute * 60),
+            Err(minute)
+        );
+    }
+
+    #[test]
+    fn test_image_limit_per_guild() {
+        let limiter = ImageRateLimiter::new(3, 2);
+        let start = Instant::now();
+        assert!(limiter.try_acquire(1, 100, start).is_ok());
+        assert!(limiter
+            .try_acquire(2, 100, start + Duration::from_secs(30))
+            .is_ok());
+        assert_eq!(
+            limiter.try_acquire(3, 100, start + Duration::from_secs(60)),
+            Err(Duration::from_secs(59 * 60))
+        );
+        // Turned away requests don't count against the member
+        assert!(limiter.try_acquire(3, 200, start).is_ok());
+        assert_eq!(limiter.users.get(&3).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_image_limit_settings() {
+        assert_eq!(parse_limit(Some(" 5 "), 3), 5);
+        assert_eq!(parse_limit(Some("0"), 3), 3);
+        assert_eq!(parse_limit(Some("lots"), 20), 20);
+        assert_eq!(parse_limit(None, 20), 20);
+
+        assert_eq!(wait_text(Duration::from_millis(200)), "1 detik");
+        assert_eq!(wait_text(Duration::from_secs(45)), "45 detik");
+        assert_eq!(wait_text(Duration::from_secs(60)), "1 menit");
+        assert_eq!(wait_text(Duration::from_secs(61)), "2 menit");
+    }
+
+    #[test]
+    fn test_queue_position_moves_up() {
+        // Tickets 0 and 1 waited before ticket 2
+        assert_eq!(queue_position(2, 0), 3);
+        assert_eq!(queue_position(2, 1), 2);
+        assert_eq!(queue_position(2, 2), 1);
+    }
+
+    #[test]
+    fn test_trivial_replies_are_skipped() {
+        for content in [
+            "",
+            "ok",
+            "👍",
+            "😂😂😂",
+            "<:pepega:123456789>",
+            "!!!",
+            "<a:wave:42> 🙏",
+        ] {
+            assert!(is_trivial_reply(content), "{:?}", content);
+        }
+        for content in ["iya dong", "なるほど", "<:pepega:1> lucu banget", "why?"] {
+            assert!(!is_trivial_reply(content), "{:?}", content);
+        }
+    }
+
+    #[test]
+    fn test_reply_context_goes_first() {
+        assert_eq!(
+            with_reply_context("Ayumi", "Udah baca chapter 3?", "belum, seru gak?"),
+            "(Membalas pesan Ayumi: \"Udah baca chapter 3?\")\nbelum, seru gak?"
+        );
+        let long = "あ".repeat(REPLY_CONTEXT_CHARS + 50);
+        let message = with_reply_context("Rin", &long, "ini apa?");
+        assert_eq!(
+            message.chars().filter(|c| *c == 'あ').count(),
+            REPLY_CONTEXT_CHARS
+        );
+    }
+
+    #[test]
+    fn test_side_messages_are_skipped() {
+        assert!(is_side_message("// brb"));
+        assert!(is_side_message("  !ignore kenapa ya"));
+        assert!(!is_side_message("Ayumi, apa kabar?"));
+        // Links aren't side messages
+        assert!(!is_side_message("https://jpdb.io"));
+        assert!(is_side_message("!ignore"));
+        assert!(!is_side_message("!ignored"));
+    }
+
+    const LIMIT: usize = 1950;
+
+    fn assert_fits(chunks: &[String]) {
+        for chunk in chunks {
+            assert!(
+                chunk.chars().count() <= LIMIT,
+                "{} chars",
+                chunk.chars().count()
+            );
+            let fences = chunk
+                .lines()
+                .filter(|l| l.trim_start().starts_with(FENCE))
+                .count();
+            assert_eq!(fences % 2, 0, "unbalanced fences in {:?}", chunk);
+        }
+    }
+
+    #[test]
+    fn test_chunks_japanese_without_spaces() {
+        let text = "日本語の文章だけで書かれた長い段落です".repeat(220);
+        assert!(text.chars().count() > 4000);
+        let chunks = smart_chunk_message(&text, LIMIT);
+        assert_fits(&chunks);
+        assert_eq!(chunks.len(), 3);
+        // Nothing is lost when there are no spaces to drop
+        assert_eq!(chunks.concat(), text);
+
+        // Sentence ends are preferred over cutting mid-sentence
+        let sentences = "今日はアニメを見ました。".repeat(400);
+        let chunks = smart_chunk_message(&sentences, LIMIT);
+        assert_fits(&chunks);
+        assert!(chunks.iter().all(|c| c.ends_with('。')));
+        assert_eq!(chunks.concat(), sentences);
+    }
+
+    #[test]
+    fn test_chunks_count_characters_not_bytes() {
+        // Three bytes per character, well over the limit in bytes
+        let text = "あ".repeat(1500);
+        assert_eq!(smart_chunk_message(&text, LIMIT), vec![text]);
+    }
+
+    #[test]
+    fn test_chunks_exact_boundary() {
+        let exact = "a".repeat(LIMIT);
+        assert_eq!(smart_chunk_message(&exact, LIMIT), vec![exact.clone()]);
+
+        let over = format!("{}b", exact);
+        let chunks = smart_chunk_message(&over, LIMIT);
+        assert_fits(&chunks);
+        assert_eq!(chunks, vec![exact, "b".to_string()]);
+
+        let lines = format!("{}\n{}", "a".repeat(LIMIT), "b".repeat(LIMIT));
+        let chunks = smart_chunk_message(&lines, LIMIT);
+        assert_eq!(chunks, vec!["a".repeat(LIMIT), "b".repeat(LIMIT)]);
+    }
+
+    #[test]
+    fn test_chunks_keep_words_whole() {
+        let text = "immersion ".repeat(500).trim_end().to_string();
+        let chunks = smart_chunk_message(&text, LIMIT);
+        assert_fits(&chunks);
+        assert!(chunks
+            .iter()
+            .all(|c| c.split(' ').all(|w| w == "immersion")));
+    }
+
+    #[test]
+    fn test_chunks_reopen_split_code_block() {
+        let code = (0..300)
+            .map(|i| format!("let value_{} = {};", i, i))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let text = format!("Contoh kode:\n```rust\n{}\n```\nSemoga membantu!", code);
+        let chunks = smart_chunk_message(&text, LIMIT);
+        assert_fits(&chunks);
+        assert!(chunks.len() >= 3);
+
+        assert!(chunks[0].starts_with("Contoh kode:\n```rust\n"));
+        assert!(chunks[0].ends_with("\n```"));
+        for chunk in &chunks[1..] {
+            assert!(chunk.starts_with("```rust\n"), "{:?}", &chunk[..20]);
+        }
+        assert!(chunks.last().unwrap().ends_with("```\nSemoga membantu!"));
+        // Every line of code made it into a chunk
+        let all = chunks.join("\n");
+        assert!((0..300).all(|i| all.contains(&format!("let value_{} = {};", i, i))));
+    }
+}
@@ -1,23 +1,140 @@
 // Custom Prompt Manager
-// Allows users to set custom system prompts for Ayumi from Rentry.co URLs
+// Allows users to set custom system prompts for Ayumi from Rentry.co URLs, kept as named
+// presets of which one is active
 
 use anyhow::Result;
+use futures::future::BoxFuture;
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::PathBuf;
-use std::sync::RwLock;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 use tracing::{debug, error};
 
-/// User's custom prompt data
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::api::firebase::{is_transaction_conflict, TransactionWrite};
+use crate::api::storage::Storage;
+use crate::utils::clock::Clock;
+
+/// One custom prompt. Also the whole stored document before presets existed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct UserPromptData {
     pub user_id: String,
     pub prompt: String,
     pub timestamp: String,
     pub last_updated: u64,
+    /// Document revision the prompt was last written at
+    #[serde(default)]
+    pub revision: u64,
+}
+
+/// Most presets a user can keep
+pub const MAX_PRESETS: usize = 5;
+
+/// Most characters in a preset name
+const MAX_PRESET_NAME: usize = 32;
+
+/// Preset name given to a prompt stored before presets existed, and to the first one set
+pub const DEFAULT_PRESET: &str = "default";
+
+/// A user's prompt presets, stored as one document
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UserPrompts {
+    /// Keyed by lowercase name
+    #[serde(default)]
+    pub presets: BTreeMap<String, UserPromptData>,
+    /// Preset Ayumi uses, `None` for her default personality
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active: Option<String>,
+    /// Incremented on every save, used to detect concurrent edits
+    #[serde(default)]
+    pub revision: u64,
+}
+
+/// Why a preset change can't be made
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum PresetError {
+    #[error("Preset names are 1 to {MAX_PRESET_NAME} characters.")]
+    InvalidName,
+    #[error("You already have {MAX_PRESETS} presets, delete one first.")]
+    TooMany,
+    #[error("You don't have a preset named **{0}**.")]
+    NotFound(String),
+}
+
+/// The key a preset name is stored under
+pub fn preset_key(name: &str) -> Result<String, PresetError> {
+    let name = name.trim().to_lowercase();
+    if name.is_empty() || name.chars().count() > MAX_PRESET_NAME {
+        return Err(PresetError::InvalidName);
+    }
+    Ok(name)
+}
+
+impl UserPrompts {
+    /// Read a stored document. One from before presets (a bare `UserPromptData`) becomes
+    /// the active "default" preset, and is written in the new format on the next save.
+    pub fn from_value(value: serde_json::Value) -> Result<Self> {
+        if value.get("presets").is_none() && value.get("prompt").is_some() {
+            let legacy: UserPromptData = serde_json::from_value(value)?;
+            return Ok(Self {
+                revision: legacy.revision,
+                active: Some(DEFAULT_PRESET.to_string()),
+                presets: BTreeMap::from([(DEFAULT_PRESET.to_string(), legacy)]),
+            });
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+
+    pub fn active_prompt(&self) -> Option<&UserPromptData> {
+        self.presets.get(self.active.as_deref()?)
+    }
+
+    /// Store `prompt` as the preset `key`, keeping the cap on new names
+    pub fn save_preset(
+        &mut self,
+        user_id: u64,
+        key: &str,
+        prompt: String,
+    ) -> Result<(), PresetError> {
+        if !self.presets.contains_key(key) && self.presets.len() >= MAX_PRESETS {
+            return Err(PresetError::TooMany);
+        }
+        let data = new_prompt_data(user_id, prompt, self.revision + 1);
+        self.presets.insert(key.to_string(), data);
+        Ok(())
+    }
+
+    /// Replace the active preset's prompt, creating and activating "default" when none is active
+    pub fn set_active_prompt(&mut self, user_id: u64, prompt: String) -> Result<(), PresetError> {
+        let key = self
+            .active
+            .clone()
+            .unwrap_or_else(|| DEFAULT_PRESET.to_string());
+        self.save_preset(user_id, &key, prompt)?;
+        self.active = Some(key);
+        Ok(())
+    }
+
+    pub fn use_preset(&mut self, key: &str) -> Result<(), PresetError> {
+        if !self.presets.contains_key(key) {
+            return Err(PresetError::NotFound(key.to_string()));
+        }
+        self.active = Some(key.to_string());
+        Ok(())
+    }
+
+    /// Remove a preset, going back to the default personality if it was active
+    pub fn delete_preset(&mut self, key: &str) -> Result<(), PresetError> {
+        if self.presets.remove(key).is_none() {
+            return Err(PresetError::NotFound(key.to_string()));
+        }
+        if self.active.as_deref() == Some(key) {
+            self.active = None;
+        }
+        Ok(())
+    }
 }
 
 /// Rate limit data
@@ -31,119 +148,336 @@ const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60); // 1 minute
 const MAX_REQUESTS_PER_WINDOW: u32 = 3;
 
 // Custom prompt directory
-static PROMPT_DIR: Lazy<PathBuf> = Lazy::new(|| {
-    let dir = PathBuf::from("data/custom_prompts");
-    if !dir.exists() {
-        let _ = fs::create_dir_all(&dir);
-    }
-    dir
-});
+static PROMPT_DIR: Lazy<PathBuf> = Lazy::new(|| PathBuf::from("data/custom_prompts"));
+
+/// Fixed-window rate limiter keyed by user id
+#[derive(Default)]
+struct RateLimiter {
+    entries: RwLock<HashMap<u64, RateLimitEntry>>,
+}
 
 // Rate limit storage
-static RATE_LIMITS: Lazy<RwLock<HashMap<u64, RateLimitEntry>>> =
-    Lazy::new(|| RwLock::new(HashMap::new()));
+static RATE_LIMITS: Lazy<RateLimiter> = Lazy::new(RateLimiter::default);
 
 /// Check if user is rate limited
-pub fn is_rate_limited(user_id: u64) -> Result<bool, u64> {
-    let now = Instant::now();
-    let mut limits = RATE_LIMITS.write().unwrap();
-
-    if let Some(entry) = limits.get_mut(&user_id) {
-        if now.duration_since(entry.timestamp) > RATE_LIMIT_WINDOW {
-            // Reset window
-            entry.count = 1;
-            entry.timestamp = now;
-            Ok(false)
-        } else if entry.count >= MAX_REQUESTS_PER_WINDOW {
-            // Rate limited
-            let time_left =
-                RATE_LIMIT_WINDOW.as_secs() - now.duration_since(entry.timestamp).as_secs();
-            Err(time_left)
+pub fn is_rate_limited(user_id: u64, clock: &dyn Clock) -> Result<bool, u64> {
+    RATE_LIMITS.check(user_id, clock.now_instant())
+}
+
+impl RateLimiter {
+    /// Returns `Err(seconds_left)` when the user has used up the current window
+    fn check(&self, user_id: u64, now: Instant) -> Result<bool, u64> {
+        let mut limits = self.entries.write().unwrap();
+
+        if let Some(entry) = limits.get_mut(&user_id) {
+            if now.duration_since(entry.timestamp) > RATE_LIMIT_WINDOW {
+                // Reset window
+                entry.count = 1;
+                entry.timestamp = now;
+                Ok(false)
+            } else if entry.count >= MAX_REQUESTS_PER_WINDOW {
+                // Rate limited
+                let time_left =
+                    RATE_LIMIT_WINDOW.as_secs() - now.duration_since(entry.timestamp).as_secs();
+                Err(time_left)
+            } else {
+                entry.count += 1;
+                Ok(false)
+            }
         } else {
-            entry.count += 1;
+            limits.insert(
+                user_id,
+                RateLimitEntry {
+                    count: 1,
+                    timestamp: now,
+                },
+            );
             Ok(false)
         }
-    } else {
-        limits.insert(
-            user_id,
-            RateLimitEntry {
-                count: 1,
-                timestamp: now,
-            },
-        );
-        Ok(false)
     }
 }
 
-/// Get user's custom prompt from local file
-pub fn get_user_custom_prompt(user_id: u64) -> Option<String> {
-    let path = PROMPT_DIR.join(format!("{}.json", user_id));
+// ============ Storage ============
 
-    if !path.exists() {
-        return None;
+/// Result of a compare-and-swap save
+#[derive(Debug, Clone)]
+pub enum SaveOutcome {
+    /// Written with the returned (new) revision
+    Saved(UserPrompts),
+    /// The stored presets changed since they were read; this is the newer copy
+    Conflict(Option<UserPrompts>),
+}
+
+/// Backend for custom prompt storage.
+/// `save` only succeeds when the stored revision still equals the revision of the presets
+/// passed in, i.e. the one they were loaded at (0 meaning "nothing stored yet").
+pub trait PromptStore: Send + Sync {
+    fn load(&self, user_id: u64) -> BoxFuture<'_, Result<Option<UserPrompts>>>;
+    fn save(&self, user_id: u64, prompts: UserPrompts) -> BoxFuture<'_, Result<SaveOutcome>>;
+    fn delete(&self, user_id: u64) -> BoxFuture<'_, Result<bool>>;
+}
+
+/// Pick the prompt store from the environment.
+/// `PROMPT_STORE=firestore` keeps prompts in Firestore (needed for multi-instance deploys),
+/// anything else uses local JSON files.
+pub fn prompt_store_from_env(firebase: Arc<dyn Storage>) -> Arc<dyn PromptStore> {
+    match std::env::var("PROMPT_STORE").as_deref() {
+        Ok("firestore") => Arc::new(FirestorePromptStore { firebase }),
+        _ => Arc::new(FilePromptStore::new(PROMPT_DIR.clone())),
     }
+}
 
-    match fs::read_to_string(&path) {
-        Ok(content) => match serde_json::from_str::<UserPromptData>(&content) {
-            Ok(data) => Some(data.prompt),
-            Err(e) => {
-                error!("Failed to parse prompt file for user {}: {:?}", user_id, e);
-                None
-            }
-        },
+/// Get the prompt text of the user's active preset
+pub async fn get_user_custom_prompt(store: &dyn PromptStore, user_id: u64) -> Option<String> {
+    match store.load(user_id).await {
+        Ok(prompts) => prompts
+            .as_ref()
+            .and_then(UserPrompts::active_prompt)
+            .map(|d| d.prompt.clone()),
         Err(e) => {
-            error!("Failed to read prompt file for user {}: {:?}", user_id, e);
+            error!("Failed to load prompt for user {}: {:?}", user_id, e);
             None
         }
     }
 }
 
-/// Save user's custom prompt to local file
-pub fn save_user_custom_prompt(user_id: u64, prompt: &str) -> bool {
-    let path = PROMPT_DIR.join(format!("{}.json", user_id));
-
-    let data = UserPromptData {
+fn new_prompt_data(user_id: u64, prompt: String, revision: u64) -> UserPromptData {
+    let now = chrono::Utc::now();
+    UserPromptData {
         user_id: user_id.to_string(),
-        prompt: prompt.to_string(),
-        timestamp: chrono::Utc::now().to_rfc3339(),
-        last_updated: chrono::Utc::now().timestamp() as u64,
-    };
-
-    match serde_json::to_string_pretty(&data) {
-        Ok(json) => match fs::write(&path, json) {
-            Ok(_) => {
-                debug!("Saved custom prompt for user {}", user_id);
-                true
-            }
-            Err(e) => {
-                error!("Failed to write prompt file for user {}: {:?}", user_id, e);
-                false
+        prompt,
+        timestamp: now.to_rfc3339(),
+        last_updated: now.timestamp() as u64,
+        revision,
+    }
+}
+
+/// Local JSON files, one per user
+pub struct FilePromptStore {
+    dir: PathBuf,
+}
+
+/// A lock file older than this is assumed to be left over from a crash
+const STALE_LOCK_AGE: Duration = Duration::from_secs(10);
+const LOCK_WAIT: Duration = Duration::from_secs(2);
+
+impl FilePromptStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn path(&self, user_id: u64) -> PathBuf {
+        self.dir.join(format!("{}.json", user_id))
+    }
+
+    fn read(path: &Path) -> Result<Option<UserPrompts>> {
+        match fs::read_to_string(path) {
+            Ok(content) => Ok(Some(UserPrompts::from_value(serde_json::from_str(
+                &content,
+            )?)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Exclusive lock shared with other processes using the same directory
+    fn lock(path: &Path) -> Result<FileLock> {
+        let lock_path = path.with_extension("lock");
+        let started = Instant::now();
+
+        loop {
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&lock_path)
+            {
+                Ok(_) => return Ok(FileLock { path: lock_path }),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let stale = fs::metadata(&lock_path)
+                        .and_then(|m| m.modified())
+                        .ok()
+                        .and_then(|m| m.elapsed().ok())
+                        .is_some_and(|age| age > STALE_LOCK_AGE);
+                    if stale {
+                        let _ = fs::remove_file(&lock_path);
+                        continue;
+                    }
+                    if started.elapsed() > LOCK_WAIT {
+                        anyhow::bail!("Timed out waiting for prompt file lock");
+                    }
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                Err(e) => return Err(e.into()),
             }
-        },
-        Err(e) => {
-            error!("Failed to serialize prompt for user {}: {:?}", user_id, e);
-            false
         }
     }
+
+    fn save_blocking(path: &Path, user_id: u64, mut data: UserPrompts) -> Result<SaveOutcome> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let _lock = Self::lock(path)?;
+
+        let current = Self::read(path)?;
+        let current_revision = current.as_ref().map(|d| d.revision).unwrap_or(0);
+        if current_revision != data.revision {
+            return Ok(SaveOutcome::Conflict(current));
+        }
+        data.revision = current_revision + 1;
+
+        // Write to a temp file and rename so readers never see a partial file
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(&data)?)?;
+        fs::rename(&tmp_path, path)?;
+
+        debug!(
+            "Saved custom prompt for user {} (revision {})",
+            user_id, data.revision
+        );
+        Ok(SaveOutcome::Saved(data))
+    }
+}
+
+/// Removes the lock file when dropped
+struct FileLock {
+    path: PathBuf,
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl PromptStore for FilePromptStore {
+    fn load(&self, user_id: u64) -> BoxFuture<'_, Result<Option<UserPrompts>>> {
+        let path = self.path(user_id);
+        Box::pin(async move { tokio::task::spawn_blocking(move || Self::read(&path)).await? })
+    }
+
+    fn save(&self, user_id: u64, prompts: UserPrompts) -> BoxFuture<'_, Result<SaveOutcome>> {
+        let path = self.path(user_id);
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || Self::save_blocking(&path, user_id, prompts))
+                .await?
+        })
+    }
+
+    fn delete(&self, user_id: u64) -> BoxFuture<'_, Result<bool>> {
+        let path = self.path(user_id);
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || {
+                let _lock = Self::lock(&path)?;
+                match fs::remove_file(&path) {
+                    Ok(()) => {
+                        debug!("Deleted custom prompt for user {}", user_id);
+                        Ok(true)
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+                    Err(e) => Err(e.into()),
+                }
+            })
+            .await?
+        })
+    }
 }
 
-/// Delete user's custom prompt
-pub fn delete_user_custom_prompt(user_id: u64) -> bool {
-    let path = PROMPT_DIR.join(format!("{}.json", user_id));
+/// Firestore `custom_prompts/{userId}` documents, revision checked inside a transaction
+pub struct FirestorePromptStore {
+    firebase: Arc<dyn Storage>,
+}
+
+const PROMPT_COLLECTION: &str = "custom_prompts";
+
+/// Every top-level field of a prompt document, including the ones from before presets,
+/// so a save replaces them all and leaves no legacy fields behind
+const PROMPT_FIELDS: [&str; 7] = [
+    "presets",
+    "active",
+    "revision",
+    "user_id",
+    "prompt",
+    "timestamp",
+    "last_updated",
+];
+
+impl PromptStore for FirestorePromptStore {
+    fn load(&self, user_id: u64) -> BoxFuture<'_, Result<Option<UserPrompts>>> {
+        Box::pin(async move {
+            let doc = self
+                .firebase
+                .get_document(PROMPT_COLLECTION, &user_id.to_string())
+                .await?;
+            doc.map(UserPrompts::from_value).transpose()
+        })
+    }
 
-    if path.exists() {
-        match fs::remove_file(&path) {
-            Ok(_) => {
-                debug!("Deleted custom prompt for user {}", user_id);
-                true
+    fn save(&self, user_id: u64, mut data: UserPrompts) -> BoxFuture<'_, Result<SaveOutcome>> {
+        Box::pin(async move {
+            let doc_id = user_id.to_string();
+            let tx_id = self.firebase.begin_transaction().await?;
+            // Everything before the commit happens with the transaction open, so each way out
+            // of it rolls back
+            let staged = async {
+                let current = self
+                    .firebase
+                    .get_document_in_transaction(&tx_id, PROMPT_COLLECTION, &doc_id)
+                    .await?
+                    .map(UserPrompts::from_value)
+                    .transpose()?;
+
+                let current_revision = current.as_ref().map(|d| d.revision).unwrap_or(0);
+                if current_revision != data.revision {
+                    return Ok(Err(current));
+                }
+                data.revision = current_revision + 1;
+
+                Ok(Ok(TransactionWrite::UpdatePaths {
+                    document_path: format!("{}/{}", PROMPT_COLLECTION, doc_id),
+                    fields: serde_json::to_value(&data)?,
+                    field_paths: PROMPT_FIELDS.iter().map(|f| f.to_string()).collect(),
+                }))
             }
-            Err(e) => {
-                error!("Failed to delete prompt file for user {}: {:?}", user_id, e);
-                false
+            .await;
+            let write = match staged {
+                Ok(Ok(write)) => write,
+                Ok(Err(current)) => {
+                    self.firebase.abandon_transaction(&tx_id).await;
+                    return Ok(SaveOutcome::Conflict(current));
+                }
+                Err(e) => {
+                    self.firebase.abandon_transaction(&tx_id).await;
+                    return Err(e);
+                }
+            };
+
+            match self.firebase.commit_transaction(&tx_id, vec![write]).await {
+                Ok(()) => Ok(SaveOutcome::Saved(data)),
+                // Someone else committed between our read and commit
+                Err(e) if is_transaction_conflict(&e) => {
+                    Ok(SaveOutcome::Conflict(self.load(user_id).await?))
+                }
+                Err(e) => Err(e),
             }
-        }
-    } else {
-        false
+        })
+    }
+
+    fn delete(&self, user_id: u64) -> BoxFuture<'_, Result<bool>> {
+        Box::pin(async move {
+            let doc_id = user_id.to_string();
+            if self
+                .firebase
+                .get_document(PROMPT_COLLECTION, &doc_id)
+                .await?
+                .is_none()
+            {
+                return Ok(false);
+            }
+            self.firebase
+                .delete_document(PROMPT_COLLECTION, &doc_id)
+                .await?;
+            Ok(true)
+        })
     }
 }
 
@@ -219,3 +553,181 @@ pub fn validate_prompt_content(content: &str) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::clock::MockClock;
+
+    fn temp_store(name: &str) -> FilePromptStore {
+        let dir =
+            std::env::temp_dir().join(format!("ayumi-prompt-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        FilePromptStore::new(dir)
+    }
+
+    /// Presets loaded at `revision`, with `prompt` as the active one
+    fn prompts(prompt: &str, revision: u64) -> UserPrompts {
+        let mut prompts = UserPrompts {
+            revision,
+            ..Default::default()
+        };
+        prompts.set_active_prompt(1, prompt.to_string()).unwrap();
+        prompts
+    }
+
+    fn active(prompts: &UserPrompts) -> &str {
+        &prompts.active_prompt().unwrap().prompt
+    }
+
+    #[tokio::test]
+    async fn test_file_store_revision_conflict() {
+        let store = temp_store("conflict");
+
+        let first = store.save(1, prompts("one", 0)).await.unwrap();
+        assert!(matches!(first, SaveOutcome::Saved(ref d) if d.revision == 1));
+
+        // Stale writer that still thinks nothing is stored
+        match store.save(1, prompts("stale", 0)).await.unwrap() {
+            SaveOutcome::Conflict(Some(newer)) => assert_eq!(active(&newer), "one"),
+            other => panic!("expected conflict, got {:?}", other),
+        }
+
+        let second = store.save(1, prompts("two", 1)).await.unwrap();
+        assert!(matches!(second, SaveOutcome::Saved(ref d) if d.revision == 2));
+        assert_eq!(active(&store.load(1).await.unwrap().unwrap()), "two");
+
+        assert!(store.delete(1).await.unwrap());
+        assert!(store.load(1).await.unwrap().is_none());
+        let _ = fs::remove_dir_all(&store.dir);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_file_store_racing_writes() {
+        let store = Arc::new(temp_store("race"));
+
+        for round in 0..20u64 {
+            let user_id = 100 + round;
+            let a = {
+                let store = store.clone();
+                tokio::spawn(
+                    async move { store.save(user_id, prompts(&"a".repeat(5000), 0)).await },
+                )
+            };
+            let b = {
+                let store = store.clone();
+                tokio::spawn(
+                    async move { store.save(user_id, prompts(&"b".repeat(5000), 0)).await },
+                )
+            };
+
+            let outcomes = [a.await.unwrap().unwrap(), b.await.unwrap().unwrap()];
+            let winners: Vec<&UserPrompts> = outcomes
+                .iter()
+                .filter_map(|o| match o {
+                    SaveOutcome::Saved(d) => Some(d),
+                    SaveOutcome::Conflict(_) => None,
+                })
+                .collect();
+            assert_eq!(winners.len(), 1, "exactly one writer must win");
+
+            // The file parses and holds the winner's prompt in full
+            let stored = store.load(user_id).await.unwrap().unwrap();
+            assert_eq!(active(&stored), active(winners[0]));
+            assert_eq!(stored.revision, 1);
+        }
+        let _ = fs::remove_dir_all(&store.dir);
+    }
+
+    #[tokio::test]
+    async fn test_legacy_prompt_becomes_default_preset() {
+        let store = temp_store("legacy");
+        fs::create_dir_all(&store.dir).unwrap();
+        fs::write(
+            store.path(1),
+            r#"{"user_id":"1","prompt":"old persona","timestamp":"2025-01-15T10:00:00+00:00","last_updated":1736935200,"revision":3}"#,
+        )
+        .unwrap();
+
+        let mut loaded = store.load(1).await.unwrap().unwrap();
+        assert_eq!(loaded.active.as_deref(), Some(DEFAULT_PRESET));
+        assert_eq!(active(&loaded), "old persona");
+        assert_eq!(loaded.revision, 3);
+        assert_eq!(
+            get_user_custom_prompt(&store, 1).await.as_deref(),
+            Some("old persona")
+        );
+
+        // The next save writes the new format
+        loaded
+            .save_preset(1, "tsundere", "another persona".to_string())
+            .unwrap();
+        assert!(matches!(
+            store.save(1, loaded).await.unwrap(),
+            SaveOutcome::Saved(_)
+        ));
+        let stored: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(store.path(1)).unwrap()).unwrap();
+        assert!(stored.get("prompt").is_none());
+        assert_eq!(stored["presets"].as_object().unwrap().len(), 2);
+        assert_eq!(stored["revision"], 4);
+        let _ = fs::remove_dir_all(&store.dir);
+    }
+
+    #[test]
+    fn test_presets() {
+        let mut presets = UserPrompts::default();
+        assert_eq!(presets.active_prompt(), None);
+
+        for i in 0..MAX_PRESETS {
+            presets
+                .save_preset(1, &format!("p{}", i), "persona".to_string())
+                .unwrap();
+        }
+        assert_eq!(
+            presets.save_preset(1, "extra", "persona".to_string()),
+            Err(PresetError::TooMany)
+        );
+        // Overwriting an existing name is fine at the cap
+        presets.save_preset(1, "p0", "changed".to_string()).unwrap();
+        // Saving doesn't switch personas
+        assert_eq!(presets.active, None);
+
+        presets.use_preset("p0").unwrap();
+        assert_eq!(active(&presets), "changed");
+        assert_eq!(
+            presets.use_preset("missing"),
+            Err(PresetError::NotFound("missing".to_string()))
+        );
+
+        presets.set_active_prompt(1, "edited".to_string()).unwrap();
+        assert_eq!(presets.presets["p0"].prompt, "edited");
+
+        presets.delete_preset("p0").unwrap();
+        assert_eq!(presets.active, None);
+        assert_eq!(presets.presets.len(), MAX_PRESETS - 1);
+
+        assert_eq!(preset_key("  Tsundere "), Ok("tsundere".to_string()));
+        assert_eq!(preset_key(" "), Err(PresetError::InvalidName));
+        assert_eq!(preset_key(&"a".repeat(33)), Err(PresetError::InvalidName));
+    }
+
+    #[test]
+    fn test_rate_limit_window_expires() {
+        let clock = MockClock::at("2025-01-15T10:00:00Z");
+        let limiter = RateLimiter::default();
+
+        for _ in 0..MAX_REQUESTS_PER_WINDOW {
+            assert_eq!(limiter.check(42, clock.now_instant()), Ok(false));
+        }
+
+        clock.advance(chrono::Duration::seconds(20));
+        assert_eq!(limiter.check(42, clock.now_instant()), Err(40));
+
+        // Other users have their own window
+        assert_eq!(limiter.check(7, clock.now_instant()), Ok(false));
+
+        clock.advance(chrono::Duration::seconds(41));
+        assert_eq!(limiter.check(42, clock.now_instant()), Ok(false));
+    }
+}
@@ -0,0 +1,465 @@
+// Learning Link Unfurler
+// Shows a compact stats embed for jpdb, Bunpro and WaniKani links posted in the immersion channel
+
+use anyhow::Result;
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use poise::serenity_prelude as serenity;
+use scraper::{Html, Selector};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+use crate::utils::config::get_guild_config;
+use crate::Data;
+
+/// How long an extraction result (including a failed one) is reused
+const CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+/// Pages larger than this are not parsed
+const MAX_PAGE_BYTES: usize = 1024 * 1024;
+/// Only the first few links in a message are unfurled
+const MAX_LINKS_PER_MESSAGE: usize = 3;
+/// Redirects followed within the supported sites, e.g. http to https and a trailing slash
+const MAX_REDIRECTS: usize = 3;
+
+/// Checks every redirect hop against the supported sites before following it, so a
+/// profile page can't send the bot anywhere else
+static PAGE_CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .user_agent("Ayumi-Bot/1.0")
+        .redirect(reqwest::redirect::Policy::custom(|attempt| {
+            if redirect_allowed(attempt.url(), attempt.previous().len()) {
+                attempt.follow()
+            } else {
+                attempt.stop()
+            }
+        }))
+        .build()
+        .expect("Failed to create learning link HTTP client")
+});
+
+/// Supported learning sites
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LearningSite {
+    Jpdb,
+    Bunpro,
+    WaniKani,
+}
+
+impl LearningSite {
+    fn from_host(host: &str) -> Option<Self> {
+        let host = host.strip_prefix("www.").unwrap_or(host);
+        match host {
+            "jpdb.io" => Some(LearningSite::Jpdb),
+            "bunpro.jp" => Some(LearningSite::Bunpro),
+            "wanikani.com" => Some(LearningSite::WaniKani),
+            _ => None,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            LearningSite::Jpdb => "jpdb",
+            LearningSite::Bunpro => "Bunpro",
+            LearningSite::WaniKani => "WaniKani",
+        }
+    }
+
+    fn color(&self) -> u32 {
+        match self {
+            LearningSite::Jpdb => 0x5c9e31,
+            LearningSite::Bunpro => 0x1b86b0,
+            LearningSite::WaniKani => 0xe60e8a,
+        }
+    }
+
+    fn extract(&self, html: &str) -> Option<LearningStats> {
+        match self {
+            LearningSite::Jpdb => extract_jpdb(html),
+            LearningSite::Bunpro => extract_bunpro(html),
+            LearningSite::WaniKani => extract_wanikani(html),
+        }
+    }
+}
+
+/// Public stats pulled from a profile page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LearningStats {
+    pub name: Option<String>,
+    pub fields: Vec<(String, String)>,
+}
+
+static UNFURL_CACHE: Lazy<DashMap<String, (Instant, Option<LearningStats>)>> =
+    Lazy::new(DashMap::new);
+
+/// Handle learning link unfurling on message create
+pub async fn handle_message(
+    ctx: &serenity::Context,
+    msg: &serenity::Message,
+    data: &Data,
+) -> Result<()> {
+    if msg.author.bot {
+        return Ok(());
+    }
+
+    let guild_id = match msg.guild_id {
+        Some(id) => id.to_string(),
+        None => return Ok(()),
+    };
+
+    let links = find_learning_links(&msg.content);
+    if links.is_empty() {
+        return Ok(());
+    }
+
+    let config = match get_guild_config(data, &guild_id).await {
+        Some(c) => c,
+        None => return Ok(()),
+    };
+
+    if !config.unfurl_learning_links {
+        return Ok(());
+    }
+
+    let in_immersion_channel = config
+        .immersion_channel_id
+        .as_deref()
+        .is_some_and(|id| id == msg.channel_id.to_string());
+    if !in_immersion_channel {
+        return Ok(());
+    }
+
+    for (site, url) in links.into_iter().take(MAX_LINKS_PER_MESSAGE) {
+        let stats = match lookup(data, site, &url).await {
+            Some(s) => s,
+            None => continue,
+        };
+
+        let mut embed = serenity::CreateEmbed::new()
+            .title(match &stats.name {
+                Some(name) => format!("{} - {}", site.label(), name),
+                None => site.label().to_string(),
+            })
+            .url(&url)
+            .color(site.color());
+        for (name, value) in &stats.fields {
+            embed = embed.field(name, value, true);
+        }
+
+        msg.channel_id
+            .send_message(
+                &ctx.http,
+                serenity::CreateMessage::new()
+                    .embed(embed)
+                    .reference_message(msg)
+                    .allowed_mentions(serenity::CreateAllowedMentions::new()),
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Fetch and extract stats for a URL, going through the per-URL cache
+async fn lookup(data: &Data, site: LearningSite, url: &str) -> Option<LearningStats> {
+    let now = data.clock.now_instant();
+    if let Some(entry) = UNFURL_CACHE.get(url) {
+        if now.duration_since(entry.0) < CACHE_TTL {
+            return entry.1.clone();
+        }
+    }
+
+    let stats = match fetch_public_page(&PAGE_CLIENT, url).await {
+        Some(html) => site.extract(&html),
+        None => None,
+    };
+    if stats.is_none() {
+        debug!("No public stats extracted from {}", url);
+    }
+
+    UNFURL_CACHE.insert(url.to_string(), (now, stats.clone()));
+    stats
+}
+
+/// Whether a redirect to `url`, after `hops` earlier requests, stays on a supported site
+fn redirect_allowed(url: &reqwest::Url, hops: usize) -> bool {
+    hops <= MAX_REDIRECTS
+        && matches!(url.scheme(), "https" | "http")
+        && url
+            .host_str()
+            .is_some_and(|host| LearningSite::from_host(&host.to_ascii_lowercase()).is_some())
+}
+
+/// Fetch a public page, refusing redirects off-site, non-HTML responses and oversized bodies.
+/// `client` has to check redirects itself, see `PAGE_CLIENT`.
+async fn fetch_public_page(client: &reqwest::Client, url: &str) -> Option<String> {
+    let response = client
+        .get(url)
+        .timeout(std::time::Duration::from_secs(10))
+        .send()
+        .await
+        .ok()?;
+
+    if !response.status().is_success() {
+        return None;
+    }
+
+    // Private profiles usually bounce to a login page on the same site
+    if !redirect_allowed(response.url(), 0) || response.url().path().contains("login") {
+        return None;
+    }
+
+    let is_html = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("text/html"));
+    if !is_html {
+        return None;
+    }
+
+    if response
+        .content_length()
+        .is_some_and(|len| len as usize > MAX_PAGE_BYTES)
+    {
+        return None;
+    }
+
+    let bytes = response.bytes().await.ok()?;
+    if bytes.len() > MAX_PAGE_BYTES {
+        return None;
+    }
+
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Find supported learning site links in a message
+pub fn find_learning_links(content: &str) -> Vec<(LearningSite, String)> {
+    let mut links = Vec::new();
+
+    for token in content.split_whitespace() {
+        let token = token.trim_start_matches(['<', '(']);
+        let token = token.trim_end_matches(['>', ')', ',', '.', '!', '?']);
+
+        let rest = match token
+            .strip_prefix("https://")
+            .or_else(|| token.strip_prefix("http://"))
+        {
+            Some(r) => r,
+            None => continue,
+        };
+
+        let host = rest.split(['/', '?', '#']).next().unwrap_or("");
+        if let Some(site) = LearningSite::from_host(&host.to_ascii_lowercase()) {
+            let url = token.to_string();
+            if !links.iter().any(|(_, u)| u == &url) {
+                links.push((site, url));
+            }
+        }
+    }
+
+    links
+}
+
+/// jpdb: known/learning word counts from the og description
+fn extract_jpdb(html: &str) -> Option<LearningStats> {
+    let description = meta_content(html, "og:description")?;
+    let known = number_before(&description, "known")?;
+    let mut fields = vec![("Known words".to_string(), known)];
+    if let Some(learning) = number_before(&description, "learning") {
+        fields.push(("Learning".to_string(), learning));
+    }
+
+    Some(LearningStats {
+        name: meta_content(html, "og:title"),
+        fields,
+    })
+}
+
+/// Bunpro: studied grammar points from the meta tags
+fn extract_bunpro(html: &str) -> Option<LearningStats> {
+    let description =
+        meta_content(html, "og:description").or_else(|| meta_content(html, "description"))?;
+    let grammar = number_before(&description, "grammar")?;
+    let mut fields = vec![("Grammar points".to_string(), grammar)];
+    if let Some(level) = number_after(&description, "JLPT N").map(|n| format!("N{}", n)) {
+        fields.push(("JLPT".to_string(), level));
+    }
+
+    Some(LearningStats {
+        name: meta_content(html, "og:title"),
+        fields,
+    })
+}
+
+/// WaniKani: level from the public profile
+fn extract_wanikani(html: &str) -> Option<LearningStats> {
+    let document = Html::parse_document(html);
+
+    let level = Selector::parse(".public-profile__level-info, .level")
+        .ok()
+        .and_then(|sel| {
+            document
+                .select(&sel)
+                .find_map(|el| number_after(&el.text().collect::<String>(), "Level"))
+        })
+        .or_else(|| meta_content(html, "og:description").and_then(|d| number_after(&d, "Level")))?;
+
+    let name = Selector::parse(".public-profile__username, .username")
+        .ok()
+        .and_then(|sel| document.select(&sel).next())
+        .map(|el| el.text().collect::<String>().trim().to_string())
+        .filter(|s| !s.is_empty());
+
+    Some(LearningStats {
+        name,
+        fields: vec![("Level".to_string(), level)],
+    })
+}
+
+/// Read a `<meta property=...>` or `<meta name=...>` content attribute
+fn meta_content(html: &str, key: &str) -> Option<String> {
+    let document = Html::parse_document(html);
+    let selector =
+        Selector::parse(&format!(r#"meta[property="{key}"], meta[name="{key}"]"#)).ok()?;
+
+    document
+        .select(&selector)
+        .find_map(|el| el.value().attr("content"))
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty())
+}
+
+/// Number that directly precedes a keyword, e.g. "1,234 known words" -> "1,234"
+fn number_before(text: &str, keyword: &str) -> Option<String> {
+    let lower = text.to_ascii_lowercase();
+    let keyword = keyword.to_ascii_lowercase();
+
+    lower.match_indices(&keyword).find_map(|(idx, _)| {
+        // Only a whitespace-separated number counts, so "N3 grammar" is not "3 grammar"
+        let before = text[..idx].strip_suffix(' ')?.trim_end_matches(':');
+        let token = before.rsplit(' ').next().unwrap_or("");
+        let number = token.trim_end_matches([',', '.']);
+
+        if !number.is_empty()
+            && number.starts_with(|c: char| c.is_ascii_digit())
+            && number
+                .chars()
+                .all(|c| c.is_ascii_digit() || c == ',' || c == '.')
+        {
+            Some(number.to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// Number that directly follows a keyword, e.g. "Level 23" -> "23"
+fn number_after(text: &str, keyword: &str) -> Option<String> {
+    let idx = text.find(keyword)?;
+    let number: String = text[idx + keyword.len()..]
+        .trim_start()
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+
+    if number.is_empty() {
+        None
+    } else {
+        Some(number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JPDB_FIXTURE: &str = r#"<html><head>
+        <meta property="og:title" content="Tanaka">
+        <meta property="og:description" content="Tanaka's vocabulary: 4,321 known words, 210 learning.">
+        </head><body></body></html>"#;
+
+    const BUNPRO_FIXTURE: &str = r#"<html><head>
+        <meta name="description" content="Studying JLPT N3 grammar. 287 grammar points studied.">
+        </head></html>"#;
+
+    const WANIKANI_FIXTURE: &str = r#"<html><body>
+        <div class="public-profile__username">crabigator</div>
+        <div class="public-profile__level-info">Level 23</div>
+        </body></html>"#;
+
+    #[test]
+    fn test_redirect_allowed() {
+        let url = |s: &str| reqwest::Url::parse(s).unwrap();
+        assert!(redirect_allowed(&url("https://jpdb.io/user/Tanaka"), 1));
+        assert!(redirect_allowed(
+            &url("https://www.WaniKani.com/users/x"),
+            1
+        ));
+        assert!(!redirect_allowed(&url("https://example.com/"), 1));
+        assert!(!redirect_allowed(&url("http://169.254.169.254/latest"), 1));
+        assert!(!redirect_allowed(&url("https://jpdb.io.example.com/"), 1));
+        assert!(!redirect_allowed(&url("file:///etc/passwd"), 1));
+        assert!(!redirect_allowed(
+            &url("https://jpdb.io/user/Tanaka"),
+            MAX_REDIRECTS + 1
+        ));
+    }
+
+    #[test]
+    fn test_find_learning_links() {
+        let links = find_learning_links(
+            "cek <https://jpdb.io/user/123/stats> dan https://www.wanikani.com/users/foo. juga https://example.com",
+        );
+        assert_eq!(
+            links,
+            vec![
+                (
+                    LearningSite::Jpdb,
+                    "https://jpdb.io/user/123/stats".to_string()
+                ),
+                (
+                    LearningSite::WaniKani,
+                    "https://www.wanikani.com/users/foo".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_jpdb() {
+        let stats = extract_jpdb(JPDB_FIXTURE).unwrap();
+        assert_eq!(stats.name.as_deref(), Some("Tanaka"));
+        assert_eq!(
+            stats.fields,
+            vec![
+                ("Known words".to_string(), "4,321".to_string()),
+                ("Learning".to_string(), "210".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_bunpro() {
+        let stats = extract_bunpro(BUNPRO_FIXTURE).unwrap();
+        assert_eq!(
+            stats.fields[0],
+            ("Grammar points".to_string(), "287".to_string())
+        );
+        assert_eq!(stats.fields[1], ("JLPT".to_string(), "N3".to_string()));
+    }
+
+    #[test]
+    fn test_extract_wanikani() {
+        let stats = extract_wanikani(WANIKANI_FIXTURE).unwrap();
+        assert_eq!(stats.name.as_deref(), Some("crabigator"));
+        assert_eq!(stats.fields, vec![("Level".to_string(), "23".to_string())]);
+    }
+
+    #[test]
+    fn test_private_profile_extracts_nothing() {
+        let login_page = "<html><head><title>Sign in</title></head><body>Log in</body></html>";
+        assert!(extract_jpdb(login_page).is_none());
+        assert!(extract_bunpro(login_page).is_none());
+        assert!(extract_wanikani(login_page).is_none());
+    }
+}
@@ -1,5 +1,16 @@
+pub mod activity_feed;
 pub mod afk_handler;
+pub mod afk_store;
 pub mod ayumi;
 pub mod custom_prompt;
+pub mod learning_links;
 pub mod novel_recommender;
+pub mod profile;
+pub mod quiz_refresher;
+pub mod quiz_stats;
+pub mod reaction_log;
+pub mod recap;
 pub mod role_rank;
+pub mod role_rank_audit;
+pub mod streak_reminder;
+pub mod title_popularity;
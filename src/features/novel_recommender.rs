@@ -1,12 +1,14 @@
+use once_cell::sync::Lazy;
 use rand::prelude::IndexedRandom;
 use scraper::{Html, Selector};
 use serde::{Deserialize, Serialize};
-use std::sync::OnceLock;
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 use tracing::{debug, error, info, warn};
 use unicode_normalization::UnicodeNormalization;
 
 use crate::api::llm::completion_gemini;
+use crate::utils::kana::romaji_to_hiragana;
 use crate::Data;
 
 const ANNAS_BASE_URL: &str = "https://annas-archive.gl";
@@ -22,37 +24,73 @@ pub struct Novel {
     pub format: String,
 }
 
-static NOVELS: OnceLock<Vec<Novel>> = OnceLock::new();
+/// The local novels with the search key of each title, in the same order
+pub struct NovelList {
+    pub novels: Vec<Novel>,
+    pub keys: Vec<String>,
+}
 
-pub fn get_novels() -> &'static [Novel] {
-    NOVELS.get_or_init(|| {
-        let paths = [
-            "Ayumi/utils/novelList.json",
-            "src/data/novelList.json",
-            "data/novelList.json",
-        ];
+impl NovelList {
+    fn new(novels: Vec<Novel>) -> Self {
+        let keys = novels.iter().map(|n| search_key(&n.title)).collect();
+        Self { novels, keys }
+    }
 
-        for path in &paths {
-            if let Ok(content) = std::fs::read_to_string(path) {
-                match serde_json::from_str::<Vec<Novel>>(&content) {
-                    Ok(novels) => {
-                        info!(
-                            "Novel recommender loaded {} novels from {}",
-                            novels.len(),
-                            path
-                        );
-                        return novels;
-                    }
-                    Err(e) => {
-                        error!("Failed to parse {}: {:?}", path, e);
-                    }
+    /// Novels paired with their search keys, for `rank_matches`
+    pub fn indexed(&self) -> impl Iterator<Item = (&Novel, &str)> {
+        self.novels.iter().zip(self.keys.iter().map(String::as_str))
+    }
+}
+
+/// Where novelList.json is looked for, first found wins
+const NOVEL_LIST_PATHS: [&str; 3] = [
+    "Ayumi/utils/novelList.json",
+    "src/data/novelList.json",
+    "data/novelList.json",
+];
+
+/// Shared by /novel and the recommender, swapped whole by `reload_novels`
+static NOVELS: Lazy<RwLock<Arc<NovelList>>> = Lazy::new(|| {
+    let novels = read_novel_list().unwrap_or_else(|e| {
+        error!("{}", e);
+        Vec::new()
+    });
+    RwLock::new(Arc::new(NovelList::new(novels)))
+});
+
+fn read_novel_list() -> Result<Vec<Novel>, String> {
+    for path in NOVEL_LIST_PATHS {
+        if let Ok(content) = std::fs::read_to_string(path) {
+            match serde_json::from_str::<Vec<Novel>>(&content) {
+                Ok(novels) => {
+                    info!("Loaded {} local novels from {}", novels.len(), path);
+                    return Ok(novels);
+                }
+                Err(e) => {
+                    error!("Failed to parse {}: {:?}", path, e);
                 }
             }
         }
+    }
+    Err("Failed to load novelList.json from any path".to_string())
+}
 
-        error!("Failed to load novelList.json from any path");
-        Vec::new()
-    })
+pub fn get_novels() -> Arc<NovelList> {
+    NOVELS
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+
+/// Re-read novelList.json and swap it in, returning how many novels it has.
+/// The current list is kept when the file can't be read.
+pub fn reload_novels() -> Result<usize, String> {
+    let list = Arc::new(NovelList::new(read_novel_list()?));
+    let count = list.novels.len();
+    *NOVELS
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = list;
+    Ok(count)
 }
 
 // ── Anna's Archive integration ────────────────────────────────────
@@ -156,7 +194,7 @@ async fn search_annas(query: &str) -> Option<Vec<AnnaNovelResult>> {
 
 // ── Helpers ───────────────────────────────────────────────────────
 
-fn normalize_string(s: &str) -> String {
+pub(crate) fn normalize_string(s: &str) -> String {
     s.nfd()
         .filter(|c| !c.is_ascii_punctuation() && !matches!(c, '\u{0300}'..='\u{036f}'))
         .collect::<String>()
@@ -166,6 +204,103 @@ fn normalize_string(s: &str) -> String {
         .join(" ")
 }
 
+// ── Matching ──────────────────────────────────────────────────────
+
+/// Score of each kind of match; a fuzzy match one edit away gets `FUZZY_SCORE` and
+/// every further edit costs 10
+const EXACT_SCORE: u32 = 100;
+const PREFIX_SCORE: u32 = 80;
+const SUBSTRING_SCORE: u32 = 60;
+const FUZZY_SCORE: u32 = 40;
+
+/// Edits a fuzzy match may be away, one per 4 characters of the query up to this
+const MAX_EDITS: usize = 3;
+
+/// Title or query as it is matched: normalized, katakana folded into hiragana, without
+/// spaces or Japanese punctuation
+pub(crate) fn search_key(s: &str) -> String {
+    normalize_string(s)
+        .chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, '\u{3000}'..='\u{303f}' | '・'))
+        .map(|c| match c {
+            'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+            _ => c,
+        })
+        .collect()
+}
+
+/// Keys a query is matched with: its own, and its hiragana when typed in romaji
+pub(crate) fn query_keys(query: &str) -> Vec<String> {
+    let mut keys = vec![search_key(query)];
+    let kana: Option<String> = normalize_string(query)
+        .split_whitespace()
+        .map(romaji_to_hiragana)
+        .collect();
+    if let Some(kana) = kana.filter(|k| !k.is_empty()) {
+        keys.push(search_key(&kana));
+    }
+    keys.dedup();
+    keys
+}
+
+/// Edits between the query and the part of the text closest to it
+fn substring_distance(query: &[char], text: &[char]) -> usize {
+    // Matching may start anywhere in the text, so the first row costs nothing
+    let mut row = vec![0; text.len() + 1];
+    for (i, q) in query.iter().enumerate() {
+        let mut next = vec![i + 1; text.len() + 1];
+        for (j, t) in text.iter().enumerate() {
+            let substitution = row[j] + usize::from(q != t);
+            next[j + 1] = substitution.min(row[j + 1] + 1).min(next[j] + 1);
+        }
+        row = next;
+    }
+    row.into_iter().min().unwrap_or(query.len())
+}
+
+/// How well a title's key matches any of the query keys, `None` when it doesn't
+pub(crate) fn match_score(query_keys: &[String], title_key: &str) -> Option<u32> {
+    let text: Vec<char> = title_key.chars().collect();
+    query_keys
+        .iter()
+        .filter(|q| !q.is_empty())
+        .filter_map(|q| {
+            if title_key == q {
+                return Some(EXACT_SCORE);
+            }
+            if title_key.starts_with(q.as_str()) {
+                return Some(PREFIX_SCORE);
+            }
+            if title_key.contains(q.as_str()) {
+                return Some(SUBSTRING_SCORE);
+            }
+            let query: Vec<char> = q.chars().collect();
+            let allowed = (query.len() / 4).min(MAX_EDITS);
+            let edits = substring_distance(&query, &text);
+            (edits <= allowed).then(|| FUZZY_SCORE - 10 * (edits as u32).saturating_sub(1))
+        })
+        .max()
+}
+
+/// Items matching the query with their scores, best first. Ties go to the shorter title.
+pub(crate) fn rank_matches<'a, T>(
+    query: &str,
+    items: impl IntoIterator<Item = (&'a T, &'a str)>,
+) -> Vec<(&'a T, u32)> {
+    let keys = query_keys(query);
+    let mut matches: Vec<(&T, u32, usize)> = items
+        .into_iter()
+        .filter_map(|(item, key)| {
+            match_score(&keys, key).map(|score| (item, score, key.chars().count()))
+        })
+        .collect();
+    matches.sort_by(|a, b| b.1.cmp(&a.1).then(a.2.cmp(&b.2)));
+    matches
+        .into_iter()
+        .map(|(item, score, _)| (item, score))
+        .collect()
+}
+
 fn detect_jlpt_level(text: &str) -> Option<&'static str> {
     let lower = text.to_lowercase();
 
@@ -226,7 +361,8 @@ fn detect_genre(text: &str) -> Option<&'static str> {
 
 /// Random recommendation from local database (fallback)
 pub fn recommend_novels(count: usize) -> String {
-    let novels = get_novels();
+    let list = get_novels();
+    let novels = &list.novels;
     if novels.is_empty() {
         return "Maaf, database novel kosong.".to_string();
     }
@@ -285,7 +421,7 @@ pub async fn smart_novel_search(data: &Data, query: &str) -> String {
             .lines()
             .map(|l| l.trim())
             .filter(|l| !l.is_empty() && !l.starts_with(|c: char| c.is_ascii_digit()))
-            .map(|l| l.trim_start_matches(|c: char| c == '-' || c == '.' || c == ' '))
+            .map(|l| l.trim_start_matches(['-', '.', ' ']))
             .map(|l| l.trim_matches(|c: char| c == '"' || c == '\'' || c == '「' || c == '」'))
             .map(|s| s.to_string())
             .collect::<Vec<_>>(),
@@ -352,34 +488,30 @@ pub async fn smart_novel_search(data: &Data, query: &str) -> String {
 
     // Fallback: search local database
     warn!("Anna's Archive returned no results, falling back to local DB");
-    let novels = get_novels();
-    if novels.is_empty() {
+    let list = get_novels();
+    if list.novels.is_empty() {
         return "Maaf, database novel belum tersedia.".to_string();
     }
 
     // Match LLM suggestions against local DB
-    let normalized_suggestions: Vec<String> = suggested_titles
-        .iter()
-        .map(|t| normalize_string(t))
-        .collect();
+    let suggestion_keys: Vec<String> = suggested_titles.iter().map(|t| search_key(t)).collect();
 
-    let mut results: Vec<&Novel> = novels
-        .iter()
-        .filter(|novel| {
-            let norm_title = normalize_string(&novel.title);
-            normalized_suggestions
+    let mut results: Vec<&Novel> = list
+        .indexed()
+        .filter(|(_, key)| {
+            suggestion_keys
                 .iter()
-                .any(|s| norm_title.contains(s) || s.contains(&norm_title))
+                .any(|s| !s.is_empty() && (key.contains(s.as_str()) || s.contains(*key)))
         })
+        .map(|(novel, _)| novel)
         .take(10)
         .collect();
 
     // Direct search if no LLM match
     if results.is_empty() {
-        let norm_query = normalize_string(query);
-        results = novels
-            .iter()
-            .filter(|novel| normalize_string(&novel.title).contains(&norm_query))
+        results = rank_matches(query, list.indexed())
+            .into_iter()
+            .map(|(novel, _)| novel)
             .take(10)
             .collect();
     }
@@ -392,10 +524,10 @@ pub async fn smart_novel_search(data: &Data, query: &str) -> String {
         );
     }
 
-    let title_str = if level.is_some() {
-        format!("**Rekomendasi Novel untuk Level {}:**\n\n", level.unwrap())
-    } else if genre.is_some() {
-        format!("**Rekomendasi Novel Genre {}:**\n\n", genre.unwrap())
+    let title_str = if let Some(level) = level {
+        format!("**Rekomendasi Novel untuk Level {}:**\n\n", level)
+    } else if let Some(genre) = genre {
+        format!("**Rekomendasi Novel Genre {}:**\n\n", genre)
     } else {
         format!("**Hasil Pencarian '{}':**\n\n", query)
     };
@@ -415,3 +547,72 @@ pub async fn smart_novel_search(data: &Data, query: &str) -> String {
     response.push_str("Semoga suka ya! Jangan lupa baca~");
     response
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_key_folds_kana() {
+        assert_eq!(search_key("ヨリミチ"), search_key("よりみち"));
+        assert_eq!(search_key("Sword Art・Online!"), "swordartonline");
+        assert_eq!(search_key("「寄り道」"), "寄り道");
+    }
+
+    #[test]
+    fn test_romaji_query_matches_kana() {
+        let keys = query_keys("yorimichi");
+        assert_eq!(keys.len(), 2);
+        assert_eq!(
+            match_score(&keys, &search_key("ヨリミチ")),
+            Some(EXACT_SCORE)
+        );
+        assert_eq!(
+            match_score(&keys, &search_key("よりみち日和")),
+            Some(PREFIX_SCORE)
+        );
+        // Not romaji, only matched as typed
+        assert_eq!(query_keys("寄り道"), vec!["寄り道".to_string()]);
+    }
+
+    #[test]
+    fn test_match_score_order() {
+        let keys = query_keys("転生したらスライム");
+        let score = |title: &str| match_score(&keys, &search_key(title));
+        assert_eq!(score("転生したらスライム"), Some(EXACT_SCORE));
+        assert_eq!(score("転生したらスライムだった件"), Some(PREFIX_SCORE));
+        assert_eq!(
+            score("小説 転生したらスライムだった件"),
+            Some(SUBSTRING_SCORE)
+        );
+        // One kanji off
+        assert_eq!(score("転正したらスライムだった件"), Some(FUZZY_SCORE));
+        assert_eq!(score("本好きの下剋上"), None);
+        // Short queries aren't fuzzy matched
+        assert_eq!(match_score(&query_keys("ab"), "ac"), None);
+    }
+
+    #[test]
+    fn test_rank_matches() {
+        let titles = [
+            "小説 よりみち",
+            "よりみちの日々",
+            "よりみち",
+            "本好きの下剋上",
+        ];
+        let keys: Vec<String> = titles.iter().map(|t| search_key(t)).collect();
+        let ranked = rank_matches(
+            "Yorimichi",
+            titles.iter().zip(keys.iter().map(String::as_str)),
+        );
+        let order: Vec<(&str, u32)> = ranked.into_iter().map(|(t, s)| (*t, s)).collect();
+        assert_eq!(
+            order,
+            vec![
+                ("よりみち", EXACT_SCORE),
+                ("よりみちの日々", PREFIX_SCORE),
+                ("小説 よりみち", SUBSTRING_SCORE),
+            ]
+        );
+    }
+}
@@ -0,0 +1,308 @@
+// Member profile card
+// Stats, streak, favourite media and quiz level in one place, for /card and the recaps
+
+use chrono::{DateTime, NaiveDate};
+use poise::serenity_prelude as serenity;
+use serde_json::Value;
+use std::collections::HashMap;
+use tracing::warn;
+
+use crate::api::jpdb;
+use crate::features::role_rank::{highest_quiz_level, QUIZZES};
+use crate::models::user::UserDoc;
+use crate::utils::config::{colors, get_media_label};
+use crate::utils::points::calculate_points_with;
+use crate::utils::privacy::LeaderboardPrivacy;
+use crate::utils::streak;
+use crate::Data;
+
+/// Media types listed on the card
+const TOP_MEDIA: usize = 3;
+
+/// Everything a profile shows about one member
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    pub display_name: String,
+    pub avatar: Option<String>,
+    pub total_points: i64,
+    pub total_sessions: i64,
+    pub current_streak: i32,
+    pub best_streak: i32,
+    /// Best media types first, as (label, points)
+    pub top_media: Vec<(String, i64)>,
+    /// Quiz role label, `None` outside a guild or without a quiz role
+    pub quiz_level: Option<&'static str>,
+    /// Known words of a linked jpdb account, `Some(None)` when jpdb was unavailable
+    pub jpdb_known_words: Option<Option<u64>>,
+    pub join_date: Option<String>,
+    pub last_activity: Option<String>,
+    pub privacy: LeaderboardPrivacy,
+}
+
+impl Profile {
+    pub fn has_data(&self) -> bool {
+        self.total_sessions > 0
+    }
+}
+
+/// Assemble a profile from the user document and logs. `quiz_level` is the member's
+/// level in the guild it's shown in.
+pub fn build_profile(
+    user: &serenity::User,
+    user_doc: Option<&UserDoc>,
+    logs: &[Value],
+    quiz_level: Option<i32>,
+    overrides: Option<&HashMap<String, f64>>,
+    today: NaiveDate,
+) -> Profile {
+    let mut top_media: Vec<(String, i64)> = user_doc
+        .map(|doc| {
+            doc.stats
+                .iter()
+                .filter(|(_, s)| s.total > 0.0)
+                .map(|(media_type, s)| {
+                    (
+                        get_media_label(media_type).to_string(),
+                        calculate_points_with(media_type, s.total, overrides),
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    top_media.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let total_points = top_media.iter().map(|(_, p)| p).sum();
+    top_media.truncate(TOP_MEDIA);
+
+    let dates = streak::log_dates(logs, None);
+    let (frozen, freezes) = user_doc
+        .map(|doc| {
+            (
+                doc.summary.frozen_dates.as_slice(),
+                doc.summary.streak_freezes,
+            )
+        })
+        .unwrap_or_default();
+    let current_streak = streak::calculate_streak_with_freezes(
+        &dates,
+        frozen,
+        freezes.min(streak::MAX_STREAK_FREEZES),
+        today,
+    )
+    .current;
+    let best_streak = user_doc
+        .map(|doc| {
+            doc.stats
+                .iter()
+                .map(|(_, s)| s.best_streak as i32)
+                .max()
+                .unwrap_or(0)
+        })
+        .unwrap_or(0)
+        .max(streak::calculate_streak(&dates).longest)
+        .max(current_streak);
+
+    let quiz_level =
+        quiz_level.and_then(|level| QUIZZES.values().find(|q| q.level == level).map(|q| q.label));
+
+    Profile {
+        display_name: user_doc
+            .and_then(|doc| doc.profile.display_name.clone())
+            .unwrap_or_else(|| user.display_name().to_string()),
+        avatar: user_doc
+            .and_then(|doc| doc.profile.avatar.clone())
+            .filter(|a| !a.is_empty()),
+        total_points,
+        total_sessions: user_doc.map(|doc| doc.stats.total_sessions()).unwrap_or(0),
+        current_streak,
+        best_streak,
+        top_media,
+        quiz_level,
+        jpdb_known_words: None,
+        join_date: user_doc.and_then(|doc| doc.summary.join_date.clone()),
+        last_activity: user_doc.and_then(|doc| doc.summary.last_activity.clone()),
+        privacy: user_doc
+            .map(|doc| LeaderboardPrivacy::of(&doc.profile))
+            .unwrap_or_default(),
+    }
+}
+
+/// Load and assemble the profile of `user`, with the quiz level when `guild_id` is given
+pub async fn load_profile(
+    data: &Data,
+    http: &serenity::Http,
+    user: &serenity::User,
+    guild_id: Option<serenity::GuildId>,
+) -> anyhow::Result<Profile> {
+    let user_id = user.id.to_string();
+    let user_doc = data.firebase.get_user(&user_id).await?;
+    let logs = if user_doc.is_some() {
+        data.firebase.get_user_logs(&user_id).await?
+    } else {
+        Vec::new()
+    };
+
+    let (quiz_level, overrides) = match guild_id {
+        Some(guild_id) => {
+            let config = crate::utils::config::get_guild_config(data, &guild_id.to_string()).await;
+            // Members who left have no quiz level here
+            let quiz_level = guild_id
+                .member(http, user.id)
+                .await
+                .ok()
+                .map(|m| highest_quiz_level(&m.roles, config.as_ref()));
+            (quiz_level, config.and_then(|c| c.points_overrides))
+        }
+        None => (None, None),
+    };
+
+    let mut profile = build_profile(
+        user,
+        user_doc.as_ref(),
+        &logs,
+        quiz_level,
+        overrides.as_ref(),
+        crate::utils::config::effective_date_at(data.clock.now_utc()),
+    );
+    if let Some(api_key) = user_doc.as_ref().and_then(|doc| doc.jpdb_api_key()) {
+        let known = jpdb::known_words(&data.http_client, &user_id, api_key)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Failed to fetch jpdb known words: {:?}", e);
+                None
+            });
+        profile.jpdb_known_words = Some(known);
+    }
+    Ok(profile)
+}
+
+/// Discord timestamp markup for an RFC3339 date, `style` as in `<t:…:style>`
+fn discord_time(rfc3339: &str, style: char) -> Option<String> {
+    let at = DateTime::parse_from_rfc3339(rfc3339).ok()?;
+    Some(format!("<t:{}:{}>", at.timestamp(), style))
+}
+
+pub fn profile_embed(profile: &Profile, fallback_avatar: String) -> serenity::CreateEmbed {
+    let embed = serenity::CreateEmbed::new()
+        .title(format!("Profile - {}", profile.display_name))
+        .thumbnail(profile.avatar.clone().unwrap_or(fallback_avatar))
+        .color(colors::PRIMARY);
+
+    let quiz = profile.quiz_level.unwrap_or("No quiz role yet");
+    if !profile.has_data() {
+        let embed = embed
+            .description("No immersion logged yet. Start with `/immersion`!")
+            .field("Quiz Level", quiz, true);
+        return with_jpdb(embed, profile);
+    }
+
+    let top_media = profile
+        .top_media
+        .iter()
+        .enumerate()
+        .map(|(i, (label, points))| format!("{}. **{}** — {} pts", i + 1, label, points))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let dash = || "—".to_string();
+
+    let embed = embed
+        .description(format!(
+            "**{}** pts | **{}** sessions",
+            profile.total_points, profile.total_sessions
+        ))
+        .field(
+            "Streak",
+            format!(
+                "🔥 {} days (best {})",
+                profile.current_streak, profile.best_streak
+            ),
+            true,
+        )
+        .field("Quiz Level", quiz, true)
+        .field("Top Media", top_media, false)
+        .field(
+            "Joined",
+            profile
+                .join_date
+                .as_deref()
+                .and_then(|d| discord_time(d, 'D'))
+                .unwrap_or_else(dash),
+            true,
+        )
+        .field(
+            "Last Activity",
+            profile
+                .last_activity
+                .as_deref()
+                .and_then(|d| discord_time(d, 'R'))
+                .unwrap_or_else(dash),
+            true,
+        );
+    with_jpdb(embed, profile)
+}
+
+/// The jpdb field, for linked accounts only
+fn with_jpdb(embed: serenity::CreateEmbed, profile: &Profile) -> serenity::CreateEmbed {
+    match profile.jpdb_known_words {
+        Some(known) => embed.field("jpdb", jpdb::known_words_text(known), true),
+        None => embed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn user() -> serenity::User {
+        let mut user = serenity::User::default();
+        user.name = "yuki".to_string();
+        user
+    }
+
+    #[test]
+    fn test_profile_without_data() {
+        let today = NaiveDate::from_ymd_opt(2025, 1, 15).unwrap();
+        let profile = build_profile(&user(), None, &[], None, None, today);
+        assert!(!profile.has_data());
+        assert_eq!(profile.display_name, "yuki");
+        assert_eq!((profile.current_streak, profile.best_streak), (0, 0));
+        assert!(profile.top_media.is_empty());
+        assert_eq!(profile.quiz_level, None);
+    }
+
+    #[test]
+    fn test_profile_picks_top_media() {
+        let doc: UserDoc = serde_json::from_value(json!({
+            "profile": { "id": "1", "username": "yuki", "displayName": "Yuki" },
+            "stats": {
+                "anime": { "total": 10.0, "sessions": 5, "bestStreak": 7 },
+                "manga": { "total": 0.0, "sessions": 0 },
+                "listening": { "total": 60.0, "sessions": 2 },
+                "visual_novel": { "total": 20000.0, "sessions": 3 },
+                "book": { "total": 5.0, "sessions": 1 }
+            },
+            "summary": { "joinDate": "2024-06-01T00:00:00+00:00" }
+        }))
+        .unwrap();
+        let logs = vec![
+            json!({ "timestamps": { "date": "2025-01-14" } }),
+            json!({ "timestamps": { "date": "2025-01-15" } }),
+        ];
+        let today = NaiveDate::from_ymd_opt(2025, 1, 15).unwrap();
+        let profile = build_profile(&user(), Some(&doc), &logs, Some(-1), None, today);
+
+        assert_eq!(profile.display_name, "Yuki");
+        assert_eq!(profile.total_sessions, 11);
+        assert_eq!(profile.top_media.len(), TOP_MEDIA);
+        assert!(profile.top_media.windows(2).all(|w| w[0].1 >= w[1].1));
+        assert!(profile.total_points >= profile.top_media.iter().map(|m| m.1).sum());
+        assert_eq!(profile.current_streak, 2);
+        // The stored per-type best wins over the logs
+        assert_eq!(profile.best_streak, 7);
+        assert_eq!(profile.quiz_level, None);
+        assert_eq!(
+            discord_time(profile.join_date.as_deref().unwrap(), 'D').as_deref(),
+            Some("<t:1717200000:D>")
+        );
+    }
+}
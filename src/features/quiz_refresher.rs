@@ -0,0 +1,364 @@
+// Quiz selector refresher
+// Keeps the quiz selector message at the bottom of every guild's quiz channel
+
+use dashmap::DashMap;
+use futures::StreamExt;
+use poise::serenity_prelude as serenity;
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{error, warn};
+
+use crate::api::storage::Storage;
+use crate::features::role_rank::{is_unknown_channel, QUIZ_SELECT_ID};
+use crate::models::guild::GuildConfig;
+use crate::utils::clock::Clock;
+use crate::utils::quarantine::Quarantine;
+use crate::utils::shutdown::Shutdown;
+
+const DEFAULT_INTERVAL_SECS: u64 = 300;
+const QUARANTINE_TASK: &str = "quiz_selector_refresh";
+
+/// Consecutive failures before a channel is dropped for the rest of the process lifetime
+const MAX_CONSECUTIVE_FAILURES: u32 = 5;
+
+/// Upper bound for the per-channel retry delay
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// Read the refresh interval from `QUIZ_REFRESH_SECS`
+fn interval_from_env() -> Duration {
+    parse_interval(std::env::var("QUIZ_REFRESH_SECS").ok().as_deref())
+}
+
+fn parse_interval(value: Option<&str>) -> Duration {
+    let secs = value
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|secs| *secs > 0)
+        .unwrap_or(DEFAULT_INTERVAL_SECS);
+    Duration::from_secs(secs)
+}
+
+/// Random delay within the first quarter of the interval, so guilds are spread out
+fn jitter(interval: Duration) -> Duration {
+    let max_ms = (interval.as_millis() / 4).max(1) as u64;
+    Duration::from_millis(rand::rng().random_range(0..max_ms))
+}
+
+/// Whether a message's components hold the quiz selector dropdown
+fn is_quiz_selector(rows: &[serenity::ActionRow]) -> bool {
+    rows.iter().flat_map(|row| &row.components).any(|c| {
+        matches!(c, serenity::ActionRowComponent::SelectMenu(menu)
+            if menu.custom_id.as_deref() == Some(QUIZ_SELECT_ID))
+    })
+}
+
+/// Outcome of refreshing a single channel
+enum Outcome {
+    Refreshed,
+    Failed,
+    /// Discord says the channel is gone
+    Unknown,
+    /// Skipped because the guild is quarantined or the bot is shutting down
+    Skipped,
+}
+
+/// Retry state for one quiz channel
+#[derive(Debug, Clone, Default)]
+struct ChannelBackoff {
+    failures: u32,
+    next_attempt: Option<Instant>,
+}
+
+impl ChannelBackoff {
+    fn is_due(&self, now: Instant) -> bool {
+        self.next_attempt.is_none_or(|at| now >= at)
+    }
+
+    /// Record a failure, returns true once the channel should be given up on
+    fn record_failure(&mut self, now: Instant, interval: Duration) -> bool {
+        self.failures += 1;
+        let delay = interval
+            .saturating_mul(2u32.saturating_pow(self.failures))
+            .min(MAX_BACKOFF);
+        self.next_attempt = Some(now + delay);
+        self.failures >= MAX_CONSECUTIVE_FAILURES
+    }
+}
+
+/// Background task that refreshes quiz selectors for every configured guild
+pub struct QuizRefresher {
+    http: Arc<serenity::Http>,
+    firebase: Arc<dyn Storage>,
+    guild_configs: Arc<DashMap<String, GuildConfig>>,
+    quarantine: Arc<Quarantine>,
+    clock: Arc<dyn Clock>,
+    interval: Duration,
+    backoff: HashMap<String, ChannelBackoff>,
+    dead_channels: HashSet<String>,
+}
+
+impl QuizRefresher {
+    pub fn new(
+        http: Arc<serenity::Http>,
+        firebase: Arc<dyn Storage>,
+        guild_configs: Arc<DashMap<String, GuildConfig>>,
+        quarantine: Arc<Quarantine>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        Self {
+            http,
+            firebase,
+            guild_configs,
+            quarantine,
+            clock,
+            interval: interval_from_env(),
+            backoff: HashMap::new(),
+            dead_channels: HashSet::new(),
+        }
+    }
+
+    /// Run the refresh loop until shutdown
+    pub fn spawn(mut self, shutdown: &Shutdown) {
+        let shutdown_signal = shutdown.clone();
+        shutdown.spawn(async move {
+            let mut interval = tokio::time::interval(self.interval);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown_signal.cancelled() => break,
+                }
+                self.tick(&shutdown_signal).await;
+            }
+        });
+    }
+
+    /// Load every guild config from Firestore into the cache
+    async fn load_guild_configs(&self) {
+        match self.firebase.get_all_documents("guilds").await {
+            Ok(docs) => {
+                for doc in docs {
+                    let Some(guild_id) = doc["_id"].as_str().map(str::to_string) else {
+                        continue;
+                    };
+                    self.guild_configs
+                        .insert(guild_id, GuildConfig::from_doc(doc));
+                }
+            }
+            Err(e) => warn!(
+                "Failed to load guild configs for quiz refresh, using cached configs: {:?}",
+                e
+            ),
+        }
+    }
+
+    async fn tick(&mut self, shutdown: &Shutdown) {
+        self.load_guild_configs().await;
+
+        let now = self.clock.now_instant();
+        let due: Vec<(String, String)> = self
+            .guild_configs
+            .iter()
+            .filter_map(|entry| {
+                entry
+                    .value()
+                    .quiz_channel_id
+                    .clone()
+                    .map(|cid| (entry.key().clone(), cid))
+            })
+            .filter(|(_, cid)| !self.dead_channels.contains(cid))
+            .filter(|(_, cid)| self.backoff.get(cid).is_none_or(|b| b.is_due(now)))
+            .collect();
+
+        let this = &*self;
+        let results: Vec<(String, String, Outcome)> = futures::stream::iter(due)
+            .map(|(guild_id, channel_id)| {
+                let delay = jitter(this.interval);
+                async move {
+                    // Guilds not started yet are left for the next run after a restart
+                    let outcome = if shutdown.sleep(delay).await {
+                        this.refresh_guild(&guild_id, &channel_id).await
+                    } else {
+                        Outcome::Skipped
+                    };
+                    (guild_id, channel_id, outcome)
+                }
+            })
+            .buffer_unordered(10) // Process 10 guilds concurrently
+            .collect()
+            .await;
+
+        let now = self.clock.now_instant();
+        for (guild_id, channel_id, outcome) in results {
+            match outcome {
+                Outcome::Refreshed => {
+                    self.backoff.remove(&channel_id);
+                }
+                Outcome::Skipped => {}
+                Outcome::Unknown => {
+                    warn!(
+                        "Quiz channel {} in guild {} is invalid/deleted. Removing from refresh to stop errors.",
+                        channel_id, guild_id
+                    );
+                    self.drop_channel(&guild_id, &channel_id);
+                }
+                Outcome::Failed => {
+                    let gave_up = self
+                        .backoff
+                        .entry(channel_id.clone())
+                        .or_default()
+                        .record_failure(now, self.interval);
+                    if gave_up {
+                        warn!(
+                            "Quiz channel {} in guild {} failed {} times in a row. No longer refreshing it.",
+                            channel_id, guild_id, MAX_CONSECUTIVE_FAILURES
+                        );
+                        self.drop_channel(&guild_id, &channel_id);
+                    }
+                }
+            }
+        }
+    }
+
+    fn drop_channel(&mut self, guild_id: &str, channel_id: &str) {
+        self.backoff.remove(channel_id);
+        self.dead_channels.insert(channel_id.to_string());
+        if let Some(mut config) = self.guild_configs.get_mut(guild_id) {
+            if config.quiz_channel_id.as_deref() == Some(channel_id) {
+                config.quiz_channel_id = None;
+            }
+        }
+    }
+
+    async fn refresh_guild(&self, guild_id: &str, channel_id: &str) -> Outcome {
+        let Ok(channel_id) = channel_id.parse::<u64>().map(serenity::ChannelId::new) else {
+            return Outcome::Unknown;
+        };
+        let config = self.guild_configs.get(guild_id).map(|c| c.clone());
+        let report_to = config
+            .as_ref()
+            .and_then(|config| config.mod_log_channel_id.as_deref()?.parse().ok());
+        let result = self
+            .quarantine
+            .guard(guild_id, QUARANTINE_TASK)
+            .run(|| self.refresh_channel(channel_id, report_to, config.as_ref()))
+            .await;
+
+        match result {
+            None => Outcome::Skipped,
+            Some(Ok(outcome)) => outcome,
+            Some(Err(())) => Outcome::Failed,
+        }
+    }
+
+    async fn refresh_channel(
+        &self,
+        channel_id: serenity::ChannelId,
+        report_to: Option<serenity::ChannelId>,
+        config: Option<&GuildConfig>,
+    ) -> Result<Outcome, ()> {
+        let http = &self.http;
+
+        // Check last message in channel
+        let messages = match channel_id
+            .messages(http, serenity::GetMessages::new().limit(1))
+            .await
+        {
+            Ok(messages) => messages,
+            Err(e) if is_unknown_channel(&e) => return Ok(Outcome::Unknown),
+            Err(e) => {
+                error!("Failed to check quiz channel messages: {:?}", e);
+                return Err(());
+            }
+        };
+
+        let needs_refresh = if let Some(last_msg) = messages.first() {
+            !last_msg.author.bot
+        } else {
+            true
+        };
+
+        if needs_refresh {
+            // Find and delete old bot messages to clean up
+            if let Ok(history) = channel_id
+                .messages(http, serenity::GetMessages::new().limit(10))
+                .await
+            {
+                for msg in history {
+                    // Found by the dropdown, the title can be changed per guild
+                    if msg.author.bot && is_quiz_selector(&msg.components) {
+                        let _ = msg.delete(http).await;
+                    }
+                }
+            }
+
+            // Send new selector; a failure is already logged with what's missing
+            let sent =
+                crate::commands::role_rank::send_quiz_selector(http, channel_id, report_to, config)
+                    .await;
+            if sent.is_err() {
+                return Err(());
+            }
+        }
+
+        Ok(Outcome::Refreshed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(parse_interval(None), Duration::from_secs(300));
+        assert_eq!(parse_interval(Some("60")), Duration::from_secs(60));
+        assert_eq!(parse_interval(Some("0")), Duration::from_secs(300));
+        assert_eq!(parse_interval(Some("soon")), Duration::from_secs(300));
+    }
+
+    #[test]
+    fn test_is_quiz_selector() {
+        let row = |custom_id: &str| -> serenity::ActionRow {
+            serde_json::from_value(serde_json::json!({
+                "type": 1,
+                "components": [{ "type": 3, "custom_id": custom_id, "options": [] }]
+            }))
+            .unwrap()
+        };
+        assert!(is_quiz_selector(&[row(QUIZ_SELECT_ID)]));
+        assert!(!is_quiz_selector(&[row("subs_pick_entry")]));
+        assert!(!is_quiz_selector(&[]));
+    }
+
+    #[test]
+    fn test_jitter_stays_within_quarter_interval() {
+        let interval = Duration::from_secs(300);
+        for _ in 0..100 {
+            assert!(jitter(interval) < Duration::from_secs(75));
+        }
+    }
+
+    #[test]
+    fn test_channel_backoff_grows_and_gives_up() {
+        let interval = Duration::from_secs(300);
+        let start = Instant::now();
+        let mut backoff = ChannelBackoff::default();
+        assert!(backoff.is_due(start));
+
+        assert!(!backoff.record_failure(start, interval));
+        assert!(!backoff.is_due(start + Duration::from_secs(599)));
+        assert!(backoff.is_due(start + Duration::from_secs(600)));
+
+        assert!(!backoff.record_failure(start, interval));
+        assert!(!backoff.is_due(start + Duration::from_secs(1199)));
+        assert!(backoff.is_due(start + Duration::from_secs(1200)));
+
+        assert!(!backoff.record_failure(start, interval));
+        assert!(!backoff.record_failure(start, interval));
+        // Capped at an hour
+        assert!(backoff.is_due(start + MAX_BACKOFF));
+
+        assert!(backoff.record_failure(start, interval));
+    }
+}
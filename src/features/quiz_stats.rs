@@ -0,0 +1,406 @@
+// Role rank quiz statistics
+// Completion and failure events per quiz attempt, and the weekly per-level digest for mods
+//
+// Events live in `guilds/{gid}/quiz_events`, one document per finished or failed attempt
+
+use chrono::{DateTime, Duration, Utc};
+use poise::serenity_prelude as serenity;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::warn;
+
+use crate::api::firebase::QueryFilter;
+use crate::api::storage::Storage;
+use crate::features::role_rank::QUIZZES;
+use crate::utils::config::colors;
+
+/// Attempts without a Kotoba congratulation for this long count as abandoned
+pub const ATTEMPT_TIMEOUT_MINS: i64 = 30;
+
+/// Levels with fewer attempts don't get percentages
+const MIN_ATTEMPTS: usize = 3;
+
+/// Upper bound on events read for one digest
+const MAX_EVENTS: usize = 2000;
+
+/// How an attempt ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuizOutcome {
+    Completed,
+    /// Kotoba's result didn't match the expected deck/score, or someone else won
+    ValidationRejected,
+    /// No congratulations within `ATTEMPT_TIMEOUT_MINS`
+    TimedOut,
+    /// Passed, but the member already holds a higher tier
+    DowngradeRefused,
+}
+
+impl QuizOutcome {
+    fn describe(self) -> &'static str {
+        match self {
+            QuizOutcome::Completed => "completed",
+            QuizOutcome::ValidationRejected => "validation rejection",
+            QuizOutcome::TimedOut => "no congratulations within 30 min",
+            QuizOutcome::DowngradeRefused => "downgrade refusal",
+        }
+    }
+}
+
+/// One finished or failed attempt
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuizEvent {
+    pub quiz_id: String,
+    pub user_id: String,
+    /// Stage index the attempt ended on
+    pub stage: usize,
+    pub outcome: QuizOutcome,
+    /// Seconds from the first valid command to the congratulations, when known
+    #[serde(default)]
+    pub duration_secs: Option<i64>,
+    /// RFC3339 in UTC with a `Z` suffix, so string comparison orders it
+    pub at: String,
+}
+
+impl QuizEvent {
+    pub fn new(
+        quiz_id: &str,
+        user_id: serenity::UserId,
+        stage: usize,
+        outcome: QuizOutcome,
+        started_at: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            quiz_id: quiz_id.to_string(),
+            user_id: user_id.to_string(),
+            stage,
+            outcome,
+            duration_secs: started_at.map(|s| (now - s).num_seconds().max(0)),
+            at: timestamp(now),
+        }
+    }
+}
+
+fn timestamp(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
+/// Store an event in the background, failures are only logged
+pub fn record(
+    firebase: std::sync::Arc<dyn Storage>,
+    guild_id: Option<serenity::GuildId>,
+    event: QuizEvent,
+) {
+    let Some(guild_id) = guild_id else {
+        return;
+    };
+    tokio::spawn(async move {
+        let result = async {
+            let value = serde_json::to_value(&event)?;
+            firebase
+                .add_to_subcollection("guilds", &guild_id.to_string(), "quiz_events", &value)
+                .await
+        }
+        .await;
+        if let Err(e) = result {
+            warn!(
+                "Failed to record quiz event for guild {}: {:?}",
+                guild_id, e
+            );
+        }
+    });
+}
+
+/// Events from the last `days` days, at most `MAX_EVENTS`
+pub async fn load_recent(
+    firebase: &dyn Storage,
+    guild_id: &str,
+    now: DateTime<Utc>,
+    days: i64,
+) -> anyhow::Result<Vec<QuizEvent>> {
+    let docs = firebase
+        .run_query(
+            "guilds",
+            guild_id,
+            "quiz_events",
+            vec![QueryFilter::string_gte(
+                "at",
+                timestamp(now - Duration::days(days)),
+            )],
+            None,
+            MAX_EVENTS,
+            None,
+        )
+        .await?;
+    Ok(docs
+        .into_iter()
+        .filter_map(|(_, doc)| serde_json::from_value(doc).ok())
+        .collect())
+}
+
+/// Median of the values, `None` when empty
+pub fn median(values: &mut [i64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_unstable();
+    let mid = values.len() / 2;
+    Some(if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) as f64 / 2.0
+    } else {
+        values[mid] as f64
+    })
+}
+
+/// Digest numbers for one quiz level
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelSummary {
+    pub quiz_id: String,
+    pub attempts: usize,
+    pub completions: usize,
+    pub median_duration_secs: Option<f64>,
+    /// Most common (failure, stage) and how often it happened
+    pub top_failure: Option<(QuizOutcome, usize, usize)>,
+}
+
+impl LevelSummary {
+    pub fn has_enough_data(&self) -> bool {
+        self.attempts >= MIN_ATTEMPTS
+    }
+
+    pub fn completion_rate(&self) -> f64 {
+        if self.attempts == 0 {
+            0.0
+        } else {
+            self.completions as f64 / self.attempts as f64
+        }
+    }
+}
+
+/// Per-level summaries, ordered by quiz level (unknown quizzes last)
+pub fn summarize(events: &[QuizEvent]) -> Vec<LevelSummary> {
+    let mut by_quiz: HashMap<&str, Vec<&QuizEvent>> = HashMap::new();
+    for event in events {
+        by_quiz.entry(&event.quiz_id).or_default().push(event);
+    }
+
+    let mut summaries: Vec<LevelSummary> = by_quiz
+        .into_iter()
+        .map(|(quiz_id, events)| {
+            let mut durations: Vec<i64> = events
+                .iter()
+                .filter(|e| e.outcome == QuizOutcome::Completed)
+                .filter_map(|e| e.duration_secs)
+                .collect();
+
+            let mut failures: HashMap<(QuizOutcome, usize), usize> = HashMap::new();
+            for e in events
+                .iter()
+                .filter(|e| e.outcome != QuizOutcome::Completed)
+            {
+                *failures.entry((e.outcome, e.stage)).or_default() += 1;
+            }
+            // Highest count wins, ties go to the earlier outcome and stage
+            let top_failure = failures
+                .into_iter()
+                .max_by(|(a_key, a), (b_key, b)| a.cmp(b).then_with(|| b_key.cmp(a_key)))
+                .map(|((outcome, stage), count)| (outcome, stage, count));
+
+            LevelSummary {
+                quiz_id: quiz_id.to_string(),
+                attempts: events.len(),
+                completions: events
+                    .iter()
+                    .filter(|e| e.outcome == QuizOutcome::Completed)
+                    .count(),
+                median_duration_secs: median(&mut durations),
+                top_failure,
+            }
+        })
+        .collect();
+
+    summaries.sort_by_key(|s| {
+        (
+            QUIZZES.get(&s.quiz_id).map(|q| q.level).unwrap_or(i32::MAX),
+            s.quiz_id.clone(),
+        )
+    });
+    summaries
+}
+
+/// e.g. "12m 30s"
+fn format_duration(secs: f64) -> String {
+    let secs = secs.round() as i64;
+    if secs >= 60 {
+        format!("{}m {}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// One embed line per level
+pub fn summary_line(summary: &LevelSummary) -> String {
+    if !summary.has_enough_data() {
+        return format!(
+            "{} attempt{} — insufficient data",
+            summary.attempts,
+            if summary.attempts == 1 { "" } else { "s" }
+        );
+    }
+
+    let median = summary
+        .median_duration_secs
+        .map(format_duration)
+        .unwrap_or_else(|| "n/a".to_string());
+    let failure = match summary.top_failure {
+        Some((outcome, stage, count)) => {
+            let multi_stage = QUIZZES
+                .get(&summary.quiz_id)
+                .is_some_and(|q| q.commands.len() > 1);
+            if multi_stage {
+                format!("{} at stage {} ({}x)", outcome.describe(), stage + 1, count)
+            } else {
+                format!("{} ({}x)", outcome.describe(), count)
+            }
+        }
+        None => "none".to_string(),
+    };
+
+    format!(
+        "{} attempts — {:.0}% completed — median {}\nMost common failure: {}",
+        summary.attempts,
+        summary.completion_rate() * 100.0,
+        median,
+        failure
+    )
+}
+
+/// The mod digest embed for the trailing week
+pub fn digest_embed(summaries: &[LevelSummary]) -> serenity::CreateEmbed {
+    let mut embed = serenity::CreateEmbed::new()
+        .title("Kotoba Quiz Digest - last 7 days")
+        .color(colors::INFO);
+
+    if summaries.is_empty() {
+        return embed.description("No quiz attempts this week.");
+    }
+
+    for summary in summaries {
+        let name = QUIZZES
+            .get(&summary.quiz_id)
+            .map(|q| q.label.to_string())
+            .unwrap_or_else(|| summary.quiz_id.clone());
+        embed = embed.field(name, summary_line(summary), false);
+    }
+    embed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(
+        quiz_id: &str,
+        stage: usize,
+        outcome: QuizOutcome,
+        duration: Option<i64>,
+    ) -> QuizEvent {
+        QuizEvent {
+            quiz_id: quiz_id.to_string(),
+            user_id: "1".to_string(),
+            stage,
+            outcome,
+            duration_secs: duration,
+            at: "2025-01-15T10:00:00Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_median() {
+        assert_eq!(median(&mut []), None);
+        assert_eq!(median(&mut [30]), Some(30.0));
+        assert_eq!(median(&mut [50, 10, 30]), Some(30.0));
+        assert_eq!(median(&mut [40, 10, 20, 30]), Some(25.0));
+    }
+
+    #[test]
+    fn test_summarize_levels() {
+        use QuizOutcome::*;
+        let events = vec![
+            event("Level_4", 0, Completed, Some(600)),
+            event("Level_4", 1, Completed, Some(900)),
+            event("Level_4", 1, ValidationRejected, None),
+            event("Level_4", 1, ValidationRejected, None),
+            event("Level_4", 0, TimedOut, None),
+            event("Level_1", 0, Completed, Some(100)),
+            event("Level_1", 0, DowngradeRefused, Some(120)),
+        ];
+
+        let summaries = summarize(&events);
+        let ids: Vec<&str> = summaries.iter().map(|s| s.quiz_id.as_str()).collect();
+        assert_eq!(ids, vec!["Level_1", "Level_4"]);
+
+        let level_4 = &summaries[1];
+        assert_eq!(level_4.attempts, 5);
+        assert_eq!(level_4.completions, 2);
+        assert_eq!(level_4.median_duration_secs, Some(750.0));
+        assert_eq!(level_4.top_failure, Some((ValidationRejected, 1, 2)));
+        assert_eq!(
+            summary_line(level_4),
+            "5 attempts — 40% completed — median 12m 30s\nMost common failure: validation rejection at stage 2 (2x)"
+        );
+
+        // Refused downgrades don't count towards the median
+        assert_eq!(summaries[0].median_duration_secs, Some(100.0));
+    }
+
+    #[test]
+    fn test_failure_ties_pick_earliest_outcome() {
+        use QuizOutcome::*;
+        let events = vec![
+            event("Level_1", 0, TimedOut, None),
+            event("Level_1", 0, ValidationRejected, None),
+            event("Level_1", 0, Completed, None),
+        ];
+        let summary = &summarize(&events)[0];
+        assert_eq!(summary.top_failure, Some((ValidationRejected, 0, 1)));
+        assert_eq!(
+            summary_line(summary),
+            "3 attempts — 33% completed — median n/a\nMost common failure: validation rejection (1x)"
+        );
+    }
+
+    #[test]
+    fn test_small_levels_are_insufficient_data() {
+        let events = vec![
+            event("Level_2", 0, QuizOutcome::Completed, Some(60)),
+            event("Level_2", 0, QuizOutcome::TimedOut, None),
+        ];
+        let summary = &summarize(&events)[0];
+        assert!(!summary.has_enough_data());
+        assert_eq!(summary_line(summary), "2 attempts — insufficient data");
+    }
+
+    #[test]
+    fn test_event_duration_and_timestamp() {
+        let started = DateTime::parse_from_rfc3339("2025-01-15T10:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let event = QuizEvent::new(
+            "Level_1",
+            serenity::UserId::new(7),
+            0,
+            QuizOutcome::Completed,
+            Some(started),
+            started + Duration::seconds(95),
+        );
+        assert_eq!(event.duration_secs, Some(95));
+        assert_eq!(event.at, "2025-01-15T10:01:35Z");
+
+        let value = serde_json::to_value(&event).unwrap();
+        assert_eq!(value["outcome"], "completed");
+        assert_eq!(value["durationSecs"], 95);
+    }
+}
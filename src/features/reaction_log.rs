@@ -0,0 +1,228 @@
+// Reaction logging
+// Reacting with the guild's configured emoji on a message in its reaction log channel logs a
+// fixed amount, e.g. 📖 on the daily reading prompt for 15 minutes of reading time
+
+use dashmap::DashSet;
+use once_cell::sync::Lazy;
+use poise::serenity_prelude as serenity;
+use serde_json::Value;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::commands::immersion::{quick_log, MediaType};
+use crate::utils::config::get_guild_config;
+use crate::{Data, Error};
+
+/// `metadata.source` of logs made by reacting
+pub const REACTION_SOURCE: &str = "reaction";
+
+/// How long the in-channel confirmation stays when the member's DMs are closed
+const CONFIRMATION_SECS: u64 = 10;
+
+/// Reactions being logged, so a quick remove and re-add can't log the message twice
+static IN_FLIGHT: Lazy<DashSet<(serenity::UserId, serenity::MessageId)>> = Lazy::new(DashSet::new);
+
+/// The emoji as a reaction: a unicode emoji, or a custom one written "<:name:id>"
+pub fn parse_emoji(input: &str) -> Option<serenity::ReactionType> {
+    let input = input.trim();
+    // Plain words would be stored as a "unicode emoji" no reaction can match
+    if input.is_empty()
+        || !input.starts_with('<') && input.chars().any(|c| c.is_ascii_alphanumeric())
+    {
+        return None;
+    }
+    serenity::ReactionType::try_from(input).ok()
+}
+
+/// Whether a reaction is the configured emoji. Custom emojis match by id, so renaming one
+/// keeps it working, and unicode ones ignore the emoji variation selector.
+fn emoji_matches(configured: &str, reaction: &serenity::ReactionType) -> bool {
+    let strip = |s: &str| s.replace('\u{fe0f}', "");
+    match (parse_emoji(configured), reaction) {
+        (
+            Some(serenity::ReactionType::Custom { id: expected, .. }),
+            serenity::ReactionType::Custom { id, .. },
+        ) => expected == *id,
+        (Some(serenity::ReactionType::Unicode(expected)), serenity::ReactionType::Unicode(got)) => {
+            strip(&expected) == strip(got)
+        }
+        _ => false,
+    }
+}
+
+/// Whether a reaction on `message_id` was already logged
+fn already_logged(logs: &[Value], message_id: serenity::MessageId) -> bool {
+    let message_id = message_id.to_string();
+    logs.iter().any(|log| {
+        log.pointer("/metadata/source").and_then(|s| s.as_str()) == Some(REACTION_SOURCE)
+            && log.pointer("/metadata/messageId").and_then(|m| m.as_str())
+                == Some(message_id.as_str())
+    })
+}
+
+/// Handle a reaction being added
+pub async fn handle_reaction_add(
+    ctx: &serenity::Context,
+    reaction: &serenity::Reaction,
+    data: &Data,
+) -> Result<(), Error> {
+    let (Some(guild_id), Some(user_id)) = (reaction.guild_id, reaction.user_id) else {
+        return Ok(());
+    };
+    let Some(settings) = get_guild_config(data, &guild_id.to_string())
+        .await
+        .and_then(|c| c.reaction_log)
+    else {
+        return Ok(());
+    };
+    if reaction.channel_id.to_string() != settings.channel_id
+        || !emoji_matches(&settings.emoji, &reaction.emoji)
+    {
+        return Ok(());
+    }
+    let Some(media_type) = MediaType::from_key(&settings.media_type) else {
+        warn!(
+            "Reaction log of guild {} has unknown media type {}",
+            guild_id, settings.media_type
+        );
+        return Ok(());
+    };
+
+    let user = match &reaction.member {
+        Some(member) => member.user.clone(),
+        None => user_id.to_user(ctx).await?,
+    };
+    if user.bot {
+        return Ok(());
+    }
+
+    if !IN_FLIGHT.insert((user_id, reaction.message_id)) {
+        return Ok(());
+    }
+    let result = log_reaction(
+        ctx,
+        data,
+        &user,
+        guild_id,
+        reaction,
+        media_type,
+        settings.amount,
+    )
+    .await;
+    IN_FLIGHT.remove(&(user_id, reaction.message_id));
+    result
+}
+
+async fn log_reaction(
+    ctx: &serenity::Context,
+    data: &Data,
+    user: &serenity::User,
+    guild_id: serenity::GuildId,
+    reaction: &serenity::Reaction,
+    media_type: MediaType,
+    amount: f64,
+) -> Result<(), Error> {
+    let logs = data
+        .firebase
+        .get_user_logs_for_message(&user.id.to_string(), &reaction.message_id.to_string())
+        .await?;
+    if already_logged(&logs, reaction.message_id) {
+        return Ok(());
+    }
+
+    let embed = match quick_log(
+        ctx,
+        data,
+        user,
+        media_type,
+        amount,
+        REACTION_SOURCE,
+        Some(guild_id),
+        (reaction.channel_id, reaction.message_id),
+    )
+    .await
+    {
+        Ok(embed) => embed,
+        Err(e) => {
+            error!("Failed to save reaction log: {:?}", e);
+            return Ok(());
+        }
+    };
+    info!(
+        "Logged {} {} for {} by reaction on {}",
+        amount,
+        media_type.as_str(),
+        user.id,
+        reaction.message_id
+    );
+
+    // A DM keeps the prompt channel clean, closed DMs get a short-lived reply instead
+    if user
+        .direct_message(ctx, serenity::CreateMessage::new().embed(embed.clone()))
+        .await
+        .is_ok()
+    {
+        return Ok(());
+    }
+    let confirmation = reaction
+        .channel_id
+        .send_message(
+            ctx,
+            serenity::CreateMessage::new()
+                .content(format!("<@{}>", user.id))
+                .embed(embed)
+                .reference_message((reaction.channel_id, reaction.message_id)),
+        )
+        .await?;
+    let http = ctx.http.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_secs(CONFIRMATION_SECS)).await;
+        let _ = confirmation.delete(&http).await;
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_emoji_matches() {
+        let book = serenity::ReactionType::Unicode("📖".to_string());
+        assert!(emoji_matches("📖", &book));
+        assert!(!emoji_matches("🎧", &book));
+        // The variation selector some clients add
+        assert!(emoji_matches(
+            "❤️",
+            &serenity::ReactionType::Unicode("❤".to_string())
+        ));
+
+        let custom = serenity::ReactionType::Custom {
+            animated: false,
+            id: serenity::EmojiId::new(123456),
+            name: Some("renamed".to_string()),
+        };
+        assert!(emoji_matches("<:yomu:123456>", &custom));
+        assert!(!emoji_matches("<:yomu:654321>", &custom));
+        assert!(!emoji_matches("<:yomu:123456>", &book));
+
+        assert!(parse_emoji("book").is_none());
+        assert!(parse_emoji(" ").is_none());
+    }
+
+    #[test]
+    fn test_already_logged() {
+        let message_id = serenity::MessageId::new(42);
+        let logs = vec![
+            json!({ "metadata": { "source": "manual", "messageId": null } }),
+            json!({ "metadata": { "source": "web", "messageId": "42" } }),
+        ];
+        assert!(!already_logged(&logs, message_id));
+
+        let mut logs = logs;
+        logs.push(json!({ "metadata": { "source": "reaction", "messageId": "42" } }));
+        assert!(already_logged(&logs, message_id));
+        assert!(!already_logged(&logs, serenity::MessageId::new(43)));
+    }
+}
@@ -0,0 +1,527 @@
+// Weekly recap
+// Posts the week's top loggers and community totals to every guild with a recap channel,
+// and the Kotoba quiz digest to guilds with a mod log channel
+
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, NaiveDate, Utc, Weekday};
+use dashmap::DashMap;
+use poise::serenity_prelude as serenity;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{debug, error, info, warn};
+
+use crate::api::firebase::{self, QueryFilter};
+use crate::api::storage::Storage;
+use crate::features::quiz_stats;
+use crate::models::guild::GuildConfig;
+use crate::utils::clock::Clock;
+use crate::utils::config::{colors, effective_date_at};
+use crate::utils::formatters::format_number;
+use crate::utils::points::sum_log_points;
+use crate::utils::privacy::{public_logs, LeaderboardPrivacy};
+use crate::utils::shutdown::Shutdown;
+
+const DEFAULT_WEEKDAY: Weekday = Weekday::Sun;
+const DEFAULT_HOUR_UTC: u32 = 12;
+
+/// Users listed in the recap
+const TOP_USERS: usize = 5;
+
+/// Upper bound on logs read per user for one week
+const MAX_LOGS_PER_USER: usize = 1000;
+
+/// When the recap is posted, read from `RECAP_WEEKDAY` (e.g. "sun") and `RECAP_HOUR_UTC`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Schedule {
+    pub weekday: Weekday,
+    pub hour: u32,
+}
+
+impl Schedule {
+    pub fn from_env() -> Self {
+        Self::parse(
+            std::env::var("RECAP_WEEKDAY").ok().as_deref(),
+            std::env::var("RECAP_HOUR_UTC").ok().as_deref(),
+        )
+    }
+
+    fn parse(weekday: Option<&str>, hour: Option<&str>) -> Self {
+        Self {
+            weekday: weekday
+                .and_then(|w| w.trim().parse::<Weekday>().ok())
+                .unwrap_or(DEFAULT_WEEKDAY),
+            hour: hour
+                .and_then(|h| h.trim().parse::<u32>().ok())
+                .filter(|h| *h < 24)
+                .unwrap_or(DEFAULT_HOUR_UTC),
+        }
+    }
+
+    /// The first scheduled time strictly after `now`
+    pub fn next_run(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let days_ahead = (7 + self.weekday.num_days_from_monday() as i64
+            - now.weekday().num_days_from_monday() as i64)
+            % 7;
+        let candidate = (now.date_naive() + ChronoDuration::days(days_ahead))
+            .and_hms_opt(self.hour, 0, 0)
+            .expect("hour is validated")
+            .and_utc();
+        if candidate > now {
+            candidate
+        } else {
+            candidate + ChronoDuration::days(7)
+        }
+    }
+}
+
+/// One user's logs for the recap week
+pub struct UserWeek {
+    pub user_id: String,
+    pub privacy: LeaderboardPrivacy,
+    pub logs: Vec<Value>,
+}
+
+/// Totals for one guild
+#[derive(Debug, PartialEq)]
+pub struct Recap {
+    /// (mention or "Anonymous", points), highest first
+    pub top: Vec<(String, f64)>,
+    pub total_points: f64,
+    pub active_loggers: usize,
+}
+
+/// Points per user with the guild's multipliers; ties keep the input order.
+/// Opted-out members count towards the totals but aren't named, private logs don't count.
+pub fn summarize(weeks: &[UserWeek], overrides: Option<&HashMap<String, f64>>) -> Recap {
+    let points: Vec<(&UserWeek, f64)> = weeks
+        .iter()
+        .filter(|w| public_logs(&w.logs).next().is_some())
+        .map(|w| (w, sum_log_points(&w.logs, None, overrides)))
+        .collect();
+    let active_loggers = points.len();
+    let total_points = points.iter().map(|(_, p)| p).sum();
+
+    let mut top: Vec<(String, f64)> = points
+        .into_iter()
+        .filter(|(_, p)| *p > 0.0)
+        .filter_map(|(w, p)| {
+            let mention = format!("<@{}>", w.user_id);
+            let name = w.privacy.display_name(&mention)?.into_owned();
+            Some((name, p))
+        })
+        .collect();
+    top.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    top.truncate(TOP_USERS);
+
+    Recap {
+        top,
+        total_points,
+        active_loggers,
+    }
+}
+
+fn recap_embed(recap: &Recap, start: NaiveDate, end: NaiveDate) -> serenity::CreateEmbed {
+    const MEDALS: [&str; 3] = ["🥇", "🥈", "🥉"];
+
+    let top = if recap.top.is_empty() {
+        "*Nobody logged anything this week*".to_string()
+    } else {
+        recap
+            .top
+            .iter()
+            .enumerate()
+            .map(|(i, (name, points))| {
+                let rank = MEDALS
+                    .get(i)
+                    .map(|m| m.to_string())
+                    .unwrap_or_else(|| format!("**{}.**", i + 1));
+                format!(
+                    "{} {} — {} pts",
+                    rank,
+                    name,
+                    format_number(points.round() as i64)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    serenity::CreateEmbed::new()
+        .title(format!(
+            "Weekly Recap - {} to {}",
+            start.format("%Y-%m-%d"),
+            end.format("%Y-%m-%d")
+        ))
+        .description(format!(
+            "**{}** points from **{}** active logger{}",
+            format_number(recap.total_points.round() as i64),
+            recap.active_loggers,
+            if recap.active_loggers == 1 { "" } else { "s" }
+        ))
+        .field(format!("Top {}", TOP_USERS), top, false)
+        .color(colors::IMMERSION)
+}
+
+/// Background task posting the recap once a week
+pub struct WeeklyRecap {
+    http: Arc<serenity::Http>,
+    firebase: Arc<dyn Storage>,
+    guild_configs: Arc<DashMap<String, GuildConfig>>,
+    clock: Arc<dyn Clock>,
+    schedule: Schedule,
+}
+
+impl WeeklyRecap {
+    pub fn new(
+        http: Arc<serenity::Http>,
+        firebase: Arc<dyn Storage>,
+        guild_configs: Arc<DashMap<String, GuildConfig>>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        Self {
+            http,
+            firebase,
+            guild_configs,
+            clock,
+            schedule: Schedule::from_env(),
+        }
+    }
+
+    /// Sleep until each scheduled time and post, until shutdown
+    pub fn spawn(self, shutdown: &Shutdown) {
+        let shutdown_signal = shutdown.clone();
+        shutdown.spawn(async move {
+            loop {
+                let now = self.clock.now_utc();
+                let next = self.schedule.next_run(now);
+                info!("Next weekly recap at {}", next);
+                let wait = (next - now).to_std().unwrap_or_default();
+                if !shutdown_signal.sleep(wait).await {
+                    break;
+                }
+                self.run().await;
+            }
+        });
+    }
+
+    async fn run(&self) {
+        let guilds = self.scheduled_guilds().await;
+        if guilds.is_empty() {
+            debug!("No guilds have a recap or mod log channel, skipping weekly recap");
+            return;
+        }
+
+        let now = self.clock.now_utc();
+        let end = effective_date_at(now);
+        let start = end - ChronoDuration::days(6);
+        let weeks = if guilds.iter().any(|(_, c)| c.recap_channel_id.is_some()) {
+            match self.load_weeks(start, end).await {
+                Ok(weeks) => Some(weeks),
+                Err(e) => {
+                    error!("Failed to load logs for weekly recap: {:?}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        for (guild_id, config) in guilds {
+            if let (Some(weeks), Some(_)) = (&weeks, &config.recap_channel_id) {
+                if let Err(e) = self.post(&config, weeks, start, end).await {
+                    error!(
+                        "Failed to post weekly recap for guild {}: {:?}",
+                        guild_id, e
+                    );
+                }
+            }
+            if config.mod_log_channel_id.is_some() {
+                if let Err(e) = self.post_quiz_digest(&guild_id, &config, now).await {
+                    error!("Failed to post quiz digest for guild {}: {:?}", guild_id, e);
+                }
+            }
+        }
+    }
+
+    /// Guilds with a recap or mod log channel, from Firestore with the cache as fallback
+    async fn scheduled_guilds(&self) -> Vec<(String, GuildConfig)> {
+        match self.firebase.get_all_documents("guilds").await {
+            Ok(docs) => {
+                for doc in docs {
+                    let Some(guild_id) = doc["_id"].as_str().map(str::to_string) else {
+                        continue;
+                    };
+                    self.guild_configs
+                        .insert(guild_id, GuildConfig::from_doc(doc));
+                }
+            }
+            Err(e) => warn!(
+                "Failed to load guild configs for weekly recap, using cached configs: {:?}",
+                e
+            ),
+        }
+
+        self.guild_configs
+            .iter()
+            .filter(|entry| entry.recap_channel_id.is_some() || entry.mod_log_channel_id.is_some())
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect()
+    }
+
+    /// Every user's logs dated within the week
+    async fn load_weeks(&self, start: NaiveDate, end: NaiveDate) -> anyhow::Result<Vec<UserWeek>> {
+        let (start, end) = (
+            start.format("%Y-%m-%d").to_string(),
+            end.format("%Y-%m-%d").to_string(),
+        );
+        let users = self.firebase.get_all_users(None).await?;
+        let mut weeks = Vec::new();
+
+        for user in users {
+            let Some(user_id) = user["_id"].as_str() else {
+                continue;
+            };
+            // Logs this bot writes keep `created` as a string, so the range is on the date
+            let logs = match self
+                .firebase
+                .run_query(
+                    "users",
+                    user_id,
+                    "immersion_logs",
+                    vec![QueryFilter::string_gte("timestamps.date", start.as_str())],
+                    None,
+                    MAX_LOGS_PER_USER,
+                    None,
+                )
+                .await
+            {
+                Ok(logs) => logs,
+                Err(e) => {
+                    warn!("Skipping user {} in weekly recap: {:?}", user_id, e);
+                    continue;
+                }
+            };
+
+            weeks.push(UserWeek {
+                user_id: user_id.to_string(),
+                privacy: LeaderboardPrivacy::of_doc(&user),
+                logs: logs
+                    .into_iter()
+                    .map(|(_, log)| log)
+                    .filter(|log| !firebase::is_soft_deleted(log) && in_week(log, &end))
+                    .collect(),
+            });
+        }
+
+        Ok(weeks)
+    }
+
+    async fn post(
+        &self,
+        config: &GuildConfig,
+        weeks: &[UserWeek],
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> anyhow::Result<()> {
+        let channel_id: u64 = config
+            .recap_channel_id
+            .as_deref()
+            .unwrap_or_default()
+            .parse()?;
+        let recap = summarize(weeks, config.points_overrides.as_ref());
+        serenity::ChannelId::new(channel_id)
+            .send_message(
+                &self.http,
+                serenity::CreateMessage::new().embed(recap_embed(&recap, start, end)),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn post_quiz_digest(
+        &self,
+        guild_id: &str,
+        config: &GuildConfig,
+        now: DateTime<Utc>,
+    ) -> anyhow::Result<()> {
+        let channel_id: u64 = config
+            .mod_log_channel_id
+            .as_deref()
+            .unwrap_or_default()
+            .parse()?;
+        let events = quiz_stats::load_recent(self.firebase.as_ref(), guild_id, now, 7).await?;
+        let summaries = quiz_stats::summarize(&events);
+        serenity::ChannelId::new(channel_id)
+            .send_message(
+                &self.http,
+                serenity::CreateMessage::new().embed(quiz_stats::digest_embed(&summaries)),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+/// Custom dates can be in the future, those wait for their own week
+fn in_week(log: &Value, end: &str) -> bool {
+    log.get("timestamps")
+        .and_then(|t| t.get("date"))
+        .and_then(|d| d.as_str())
+        .is_some_and(|date| date <= end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_schedule_parse() {
+        assert_eq!(
+            Schedule::parse(None, None),
+            Schedule {
+                weekday: Weekday::Sun,
+                hour: 12
+            }
+        );
+        assert_eq!(
+            Schedule::parse(Some("fri"), Some("20")),
+            Schedule {
+                weekday: Weekday::Fri,
+                hour: 20
+            }
+        );
+        assert_eq!(
+            Schedule::parse(Some("someday"), Some("24")),
+            Schedule::parse(None, None)
+        );
+    }
+
+    #[test]
+    fn test_next_run() {
+        let schedule = Schedule::parse(None, None);
+        // Wednesday -> the coming Sunday
+        assert_eq!(
+            schedule.next_run(at("2025-01-15T08:00:00Z")),
+            at("2025-01-19T12:00:00Z")
+        );
+        // Sunday morning -> later that day
+        assert_eq!(
+            schedule.next_run(at("2025-01-19T11:59:00Z")),
+            at("2025-01-19T12:00:00Z")
+        );
+        // Right at the scheduled time -> next week, so a run is never repeated
+        assert_eq!(
+            schedule.next_run(at("2025-01-19T12:00:00Z")),
+            at("2025-01-26T12:00:00Z")
+        );
+    }
+
+    #[test]
+    fn test_summarize() {
+        let log = |media_type: &str, amount: f64| json!({ "activity": { "type": media_type, "amount": amount } });
+        let weeks = vec![
+            UserWeek {
+                user_id: "1".into(),
+                privacy: LeaderboardPrivacy::Public,
+                logs: vec![log("anime", 1.0)],
+            },
+            UserWeek {
+                user_id: "2".into(),
+                privacy: LeaderboardPrivacy::Public,
+                logs: vec![log("anime", 2.0), log("manga", 8.0)],
+            },
+            UserWeek {
+                user_id: "3".into(),
+                privacy: LeaderboardPrivacy::Public,
+                logs: vec![],
+            },
+            UserWeek {
+                user_id: "4".into(),
+                privacy: LeaderboardPrivacy::Public,
+                logs: vec![log("anime", 1.0)],
+            },
+        ];
+
+        let recap = summarize(&weeks, None);
+        assert_eq!(recap.active_loggers, 3);
+        assert_eq!(recap.total_points, 13.0 + 28.0 + 13.0);
+        assert_eq!(
+            recap.top,
+            vec![
+                ("<@2>".to_string(), 28.0),
+                ("<@1>".to_string(), 13.0),
+                ("<@4>".to_string(), 13.0)
+            ]
+        );
+
+        // The guild's multipliers apply
+        let overrides = HashMap::from([("manga".to_string(), 0.0)]);
+        assert_eq!(summarize(&weeks, Some(&overrides)).top[0].1, 26.0);
+    }
+
+    #[test]
+    fn test_summarize_leaves_out_private_logs() {
+        let log = |amount: f64, private: bool| {
+            json!({
+                "activity": { "type": "anime", "amount": amount },
+                "metadata": { "private": private }
+            })
+        };
+        let weeks = vec![
+            UserWeek {
+                user_id: "1".into(),
+                privacy: LeaderboardPrivacy::Public,
+                logs: vec![log(1.0, false), log(4.0, true)],
+            },
+            UserWeek {
+                user_id: "2".into(),
+                privacy: LeaderboardPrivacy::Public,
+                logs: vec![log(3.0, true)],
+            },
+        ];
+
+        let recap = summarize(&weeks, None);
+        assert_eq!(recap.active_loggers, 1);
+        assert_eq!(recap.total_points, 13.0);
+        assert_eq!(recap.top, vec![("<@1>".to_string(), 13.0)]);
+    }
+
+    #[test]
+    fn test_summarize_respects_leaderboard_opt_out() {
+        let week = |user_id: &str, privacy| UserWeek {
+            user_id: user_id.into(),
+            privacy,
+            logs: vec![json!({ "activity": { "type": "anime", "amount": 1.0 } })],
+        };
+        let weeks = vec![
+            week("1", LeaderboardPrivacy::Hidden),
+            week("2", LeaderboardPrivacy::Anonymous),
+            week("3", LeaderboardPrivacy::Public),
+        ];
+
+        let recap = summarize(&weeks, None);
+        assert_eq!(recap.active_loggers, 3);
+        assert_eq!(recap.total_points, 39.0);
+        assert_eq!(
+            recap.top,
+            vec![("Anonymous".to_string(), 13.0), ("<@3>".to_string(), 13.0)]
+        );
+    }
+
+    #[test]
+    fn test_in_week_skips_future_dates() {
+        assert!(in_week(
+            &json!({ "timestamps": { "date": "2025-01-19" } }),
+            "2025-01-19"
+        ));
+        assert!(!in_week(
+            &json!({ "timestamps": { "date": "2025-01-20" } }),
+            "2025-01-19"
+        ));
+        assert!(!in_week(&json!({}), "2025-01-19"));
+    }
+}
@@ -1,18 +1,32 @@
 use once_cell::sync::Lazy;
 use poise::serenity_prelude as serenity;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Arc;
 use tracing::{error, info, warn};
 
+use crate::api::jpdb;
+use crate::features::quiz_stats::{self, QuizEvent, QuizOutcome};
+use crate::features::role_rank_audit::{self, PromotionRecord};
+use crate::models::guild::GuildConfig;
+use crate::utils::config::get_guild_config;
+use crate::utils::discord::{say_checked, send_checked};
+use crate::utils::formatters::format_number;
+use crate::utils::i18n::{t, tf, Msg};
+use crate::utils::message_verdicts::{MessageVerdicts, Verdict};
 use crate::{Data, Error};
-use dashmap::DashMap;
+use dashmap::{DashMap, DashSet};
 use serde::{Deserialize, Serialize};
 use std::env;
 
 // --- Constants (Hardcoded from Go) ---
 pub const KOTOBA_BOT_ID: serenity::UserId = serenity::UserId::new(251239170058616833);
+/// Custom id of the quiz selector's dropdown, also how the refresher recognizes old selectors
+pub const QUIZ_SELECT_ID: &str = "quiz_select";
 // pub const QUIZ_SELECTOR_CHANNEL_ID: serenity::ChannelId = serenity::ChannelId::new(1392463011301691442); // Not strictly needed here but good for ref
-// const QUIZ_CHANNEL_TTL: u64 = 24 * 60 * 60; // 24 hours, handle via scheduled task later if needed
+/// Default lifetime of a private quiz channel, overridable with `QUIZ_SESSION_TTL_SECS`
+const DEFAULT_QUIZ_CHANNEL_TTL_SECS: i64 = 24 * 60 * 60;
+/// How long before expiry the channel gets a warning
+const QUIZ_CHANNEL_WARNING_SECS: i64 = 60 * 60;
 
 // --- Data Structures ---
 
@@ -21,13 +35,26 @@ pub struct QuizInfo {
     pub label: &'static str,
     pub description: &'static str,
     pub value: &'static str,
-    pub role_id: serenity::RoleId,
+    /// Role in our own server, see `role_in`
+    pub default_role_id: serenity::RoleId,
     pub commands: &'static [&'static str],
     pub deck_names: &'static [&'static str],
     pub score_limits: &'static [&'static str],
     pub level: i32,
 }
 
+impl QuizInfo {
+    /// Role the quiz gives in a guild: the one its config sets for the level, or the role
+    /// of our own server when none is set
+    pub fn role_in(&self, config: Option<&GuildConfig>) -> serenity::RoleId {
+        config
+            .and_then(|c| c.quiz_role_ids.as_ref())
+            .and_then(|roles| roles.get(&self.level.to_string()))
+            .and_then(|id| id.parse().ok())
+            .unwrap_or(self.default_role_id)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct QuizSession {
     pub user_id: serenity::UserId,
@@ -36,9 +63,28 @@ pub struct QuizSession {
     pub started: bool,
     pub active_attempt: bool,
     pub progress: usize,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    /// Last quiz command or Kotoba result in the channel, the TTL counts from here
+    pub last_activity_at: chrono::DateTime<chrono::Utc>,
+    /// Whether the "deleted in 1 hour" warning was already posted
+    pub expiry_warned: bool,
+    /// Category the channel was created under (unknown for sessions from older versions)
+    pub category_id: Option<serenity::ChannelId>,
+    /// Guild the quiz runs in (unknown for sessions from older versions)
+    pub guild_id: Option<serenity::GuildId>,
+    /// First valid command of the current attempt, for the quiz digest durations
+    pub attempt_started_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl QuizSession {
+    /// Note activity in the channel, pushing its expiry back and allowing a fresh warning
+    fn touch(&mut self, now: chrono::DateTime<chrono::Utc>) {
+        self.last_activity_at = now;
+        self.expiry_warned = false;
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 struct StoredQuizSession {
     user_id: String,
     quiz_id: String,
@@ -47,9 +93,26 @@ struct StoredQuizSession {
     active_attempt: bool,
     progress: usize,
     updated_at: String,
+    #[serde(default)]
+    created_at: Option<String>,
+    #[serde(default)]
+    last_activity_at: Option<String>,
+    #[serde(default)]
+    expiry_warned: bool,
+    #[serde(default)]
+    category_id: Option<String>,
+    #[serde(default)]
+    guild_id: Option<String>,
+    #[serde(default)]
+    attempt_started_at: Option<String>,
 }
 
-const SESSION_STORE_PATH: &str = "data/role_rank_sessions.json";
+/// Sessions are stored in `quiz_sessions/{user id}`
+const SESSION_COLLECTION: &str = "quiz_sessions";
+/// Where sessions were kept before they moved to Firestore, read once to migrate them
+const LEGACY_SESSION_STORE_PATH: &str = "data/role_rank_sessions.json";
+/// Messages of a restored quiz channel checked for a result missed while the bot was down
+const RESUME_SCAN_MESSAGES: u8 = 20;
 
 impl From<&QuizSession> for StoredQuizSession {
     fn from(session: &QuizSession) -> Self {
@@ -61,6 +124,12 @@ impl From<&QuizSession> for StoredQuizSession {
             active_attempt: session.active_attempt,
             progress: session.progress,
             updated_at: chrono::Utc::now().to_rfc3339(),
+            created_at: Some(session.created_at.to_rfc3339()),
+            last_activity_at: Some(session.last_activity_at.to_rfc3339()),
+            expiry_warned: session.expiry_warned,
+            category_id: session.category_id.map(|id| id.to_string()),
+            guild_id: session.guild_id.map(|id| id.to_string()),
+            attempt_started_at: session.attempt_started_at.map(|at| at.to_rfc3339()),
         }
     }
 }
@@ -83,6 +152,18 @@ impl TryFrom<StoredQuizSession> for QuizSession {
             );
         }
 
+        // Sessions stored before created_at existed fall back to their last update
+        let created_at = stored.created_at.as_deref().unwrap_or(&stored.updated_at);
+        let created_at = chrono::DateTime::parse_from_rfc3339(created_at)
+            .map(|dt| dt.with_timezone(&chrono::Utc))
+            .unwrap_or_else(|_| chrono::Utc::now());
+        // And before last_activity_at existed, to their creation
+        let last_activity_at = stored
+            .last_activity_at
+            .as_deref()
+            .and_then(|at| chrono::DateTime::parse_from_rfc3339(at).ok())
+            .map_or(created_at, |dt| dt.with_timezone(&chrono::Utc));
+
         Ok(Self {
             user_id: serenity::UserId::new(user_id),
             quiz_id: stored.quiz_id,
@@ -90,36 +171,122 @@ impl TryFrom<StoredQuizSession> for QuizSession {
             started: stored.started,
             active_attempt: stored.active_attempt,
             progress: stored.progress,
+            created_at,
+            last_activity_at,
+            expiry_warned: stored.expiry_warned,
+            category_id: stored
+                .category_id
+                .and_then(|id| id.parse::<u64>().ok())
+                .map(serenity::ChannelId::new),
+            guild_id: stored
+                .guild_id
+                .and_then(|id| id.parse::<u64>().ok())
+                .map(serenity::GuildId::new),
+            attempt_started_at: stored
+                .attempt_started_at
+                .and_then(|at| chrono::DateTime::parse_from_rfc3339(&at).ok())
+                .map(|at| at.with_timezone(&chrono::Utc)),
         })
     }
 }
 
-fn persist_role_rank_sessions(
+/// What was last written to Firestore, so a change only writes the sessions it touched
+#[derive(Default)]
+struct SessionSync {
+    /// Snapshot number of the last write, older snapshots finishing late are dropped
+    applied: u64,
+    synced: HashMap<String, StoredQuizSession>,
+}
+
+static SESSION_SYNC: Lazy<tokio::sync::Mutex<SessionSync>> = Lazy::new(Default::default);
+static SESSION_SNAPSHOTS: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+/// Sessions to write and user ids to delete to get from `synced` to `current`.
+/// `updated_at` alone doesn't count as a change.
+fn session_changes(
+    synced: &HashMap<String, StoredQuizSession>,
+    current: &HashMap<String, StoredQuizSession>,
+) -> (Vec<StoredQuizSession>, Vec<String>) {
+    let mut upserts: Vec<StoredQuizSession> = current
+        .values()
+        .filter(|session| {
+            synced.get(&session.user_id).is_none_or(|old| {
+                StoredQuizSession {
+                    updated_at: session.updated_at.clone(),
+                    ..old.clone()
+                } != **session
+            })
+        })
+        .cloned()
+        .collect();
+    upserts.sort_by(|a, b| a.user_id.cmp(&b.user_id));
+    let mut deletes: Vec<String> = synced
+        .keys()
+        .filter(|user_id| !current.contains_key(*user_id))
+        .cloned()
+        .collect();
+    deletes.sort();
+    (upserts, deletes)
+}
+
+/// Write the sessions that changed to Firestore in the background
+fn persist_or_log(
+    firebase: &Arc<dyn crate::api::storage::Storage>,
     sessions: &DashMap<serenity::UserId, QuizSession>,
-) -> Result<(), anyhow::Error> {
-    let stored: Vec<StoredQuizSession> = sessions
+) {
+    let current: HashMap<String, StoredQuizSession> = sessions
         .iter()
-        .map(|entry| StoredQuizSession::from(entry.value()))
+        .map(|entry| {
+            let stored = StoredQuizSession::from(entry.value());
+            (stored.user_id.clone(), stored)
+        })
         .collect();
+    let snapshot = SESSION_SNAPSHOTS.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+    let firebase = firebase.clone();
 
-    let path = std::path::Path::new(SESSION_STORE_PATH);
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent)?;
-    }
-
-    let json = serde_json::to_string_pretty(&stored)?;
-    std::fs::write(path, json)?;
-    Ok(())
-}
-
-fn persist_or_log(sessions: &DashMap<serenity::UserId, QuizSession>) {
-    if let Err(e) = persist_role_rank_sessions(sessions) {
-        error!("Failed to persist role rank sessions: {:?}", e);
-    }
+    tokio::spawn(async move {
+        let mut sync = SESSION_SYNC.lock().await;
+        if snapshot < sync.applied {
+            return;
+        }
+        sync.applied = snapshot;
+
+        let (upserts, deletes) = session_changes(&sync.synced, &current);
+        for session in upserts {
+            let result = match serde_json::to_value(&session) {
+                Ok(value) => {
+                    firebase
+                        .set_document(SESSION_COLLECTION, &session.user_id, &value)
+                        .await
+                }
+                Err(e) => Err(e.into()),
+            };
+            match result {
+                Ok(()) => {
+                    sync.synced.insert(session.user_id.clone(), session);
+                }
+                Err(e) => error!(
+                    "Failed to persist role rank session of {}: {:?}",
+                    session.user_id, e
+                ),
+            }
+        }
+        for user_id in deletes {
+            match firebase.delete_document(SESSION_COLLECTION, &user_id).await {
+                Ok(()) => {
+                    sync.synced.remove(&user_id);
+                }
+                Err(e) => error!("Failed to delete role rank session of {}: {:?}", user_id, e),
+            }
+        }
+    });
 }
 
-pub fn persist_active_sessions(sessions: &DashMap<serenity::UserId, QuizSession>) {
-    persist_or_log(sessions);
+pub fn persist_active_sessions(
+    firebase: &Arc<dyn crate::api::storage::Storage>,
+    sessions: &DashMap<serenity::UserId, QuizSession>,
+) {
+    persist_or_log(firebase, sessions);
 }
 
 async fn has_role_rank_admin_access(
@@ -242,7 +409,7 @@ async fn clear_all_quiz_channels_and_sessions(
     if !channel_ids.is_empty() {
         data.role_rank_sessions
             .retain(|_, session| !channel_ids.contains(&session.thread_id));
-        persist_or_log(&data.role_rank_sessions);
+        persist_or_log(&data.firebase, &data.role_rank_sessions);
     }
 
     let summary = if failed_channels == 0 {
@@ -261,43 +428,502 @@ async fn clear_all_quiz_channels_and_sessions(
     Ok(())
 }
 
-/// Restore active quiz sessions from local storage after bot restart.
-pub fn restore_role_rank_sessions(sessions: &Arc<DashMap<serenity::UserId, QuizSession>>) {
-    let path = std::path::Path::new(SESSION_STORE_PATH);
-    if !path.exists() {
+/// Sessions kept in the local file by older versions, `None` without one
+fn load_legacy_sessions() -> Option<Vec<StoredQuizSession>> {
+    let path = std::path::Path::new(LEGACY_SESSION_STORE_PATH);
+    let content = std::fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(sessions) => Some(sessions),
+        Err(e) => {
+            error!("Failed to parse legacy role rank session store: {:?}", e);
+            None
+        }
+    }
+}
+
+/// Restore quiz sessions from Firestore after a restart, dropping the ones whose channel
+/// was deleted meanwhile. Sessions from the old local file are migrated.
+pub async fn restore_role_rank_sessions(
+    http: &serenity::Http,
+    firebase: &Arc<dyn crate::api::storage::Storage>,
+    sessions: &DashMap<serenity::UserId, QuizSession>,
+) {
+    let mut stored: HashMap<String, StoredQuizSession> =
+        match firebase.get_all_documents(SESSION_COLLECTION).await {
+            Ok(docs) => docs
+                .into_iter()
+                .filter_map(|doc| serde_json::from_value::<StoredQuizSession>(doc).ok())
+                .map(|session| (session.user_id.clone(), session))
+                .collect(),
+            Err(e) => {
+                error!("Failed to load role rank sessions: {:?}", e);
+                return;
+            }
+        };
+    SESSION_SYNC.lock().await.synced = stored.clone();
+
+    let legacy = load_legacy_sessions();
+    let migrating = legacy.is_some();
+    for session in legacy.unwrap_or_default() {
+        stored.entry(session.user_id.clone()).or_insert(session);
+    }
+
+    let mut restored = 0;
+    let mut dropped = 0;
+    for stored in stored.into_values() {
+        let session = match QuizSession::try_from(stored) {
+            Ok(session) => session,
+            Err(e) => {
+                warn!("Skipping invalid role rank session: {:?}", e);
+                dropped += 1;
+                continue;
+            }
+        };
+        // Keep the session if Discord is only having trouble answering
+        if let Err(e) = http.get_channel(session.thread_id).await {
+            if is_unknown_channel(&e) {
+                dropped += 1;
+                continue;
+            }
+            warn!(
+                "Could not check quiz channel {}, keeping its session: {:?}",
+                session.thread_id, e
+            );
+        }
+        sessions.insert(session.user_id, session);
+        restored += 1;
+    }
+
+    if restored > 0 || dropped > 0 {
+        info!(
+            "Restored {} role rank quiz session(s), dropped {} stale",
+            restored, dropped
+        );
+    }
+    if dropped > 0 || migrating {
+        persist_or_log(firebase, sessions);
+    }
+    if migrating {
+        if let Err(e) = std::fs::remove_file(LEGACY_SESSION_STORE_PATH) {
+            warn!("Failed to remove legacy role rank session store: {:?}", e);
+        }
+    }
+}
+
+/// Process Kotoba results posted while the bot was down, for every restored session
+/// with an attempt running
+pub fn spawn_missed_result_scan(ctx: serenity::Context, data: Data) {
+    let running: Vec<(
+        serenity::ChannelId,
+        Option<serenity::GuildId>,
+        chrono::DateTime<chrono::Utc>,
+    )> = data
+        .role_rank_sessions
+        .iter()
+        .filter(|entry| entry.started && entry.active_attempt)
+        .map(|entry| {
+            (
+                entry.thread_id,
+                entry.guild_id,
+                entry.attempt_started_at.unwrap_or(entry.created_at),
+            )
+        })
+        .collect();
+    if running.is_empty() {
         return;
     }
 
-    let content = match std::fs::read_to_string(path) {
-        Ok(content) => content,
-        Err(e) => {
-            error!("Failed to read role rank session store: {:?}", e);
-            return;
+    tokio::spawn(async move {
+        for (channel_id, guild_id, since) in running {
+            let messages = match channel_id
+                .messages(
+                    &ctx.http,
+                    serenity::GetMessages::new().limit(RESUME_SCAN_MESSAGES),
+                )
+                .await
+            {
+                Ok(messages) => messages,
+                Err(e) => {
+                    warn!(
+                        "Failed to read quiz channel {} for missed results: {:?}",
+                        channel_id, e
+                    );
+                    continue;
+                }
+            };
+            // Newest first from Discord, replayed in the order they were posted
+            for mut msg in messages.into_iter().rev() {
+                if msg.embeds.is_empty()
+                    || *msg.timestamp < since
+                    || !is_quiz_bot(&data, guild_id, msg.author.id)
+                {
+                    continue;
+                }
+                // Fetched messages don't carry their guild
+                msg.guild_id = msg.guild_id.or(guild_id);
+                info!(
+                    "Processing quiz result {} posted in {} while the bot was down",
+                    msg.id, channel_id
+                );
+                if let Err(e) = handle_kotoba_message(&ctx, &msg, &data).await {
+                    error!("Failed to process missed quiz result {}: {:?}", msg.id, e);
+                }
+            }
         }
-    };
+    });
+}
 
-    let stored_sessions: Vec<StoredQuizSession> = match serde_json::from_str(&content) {
-        Ok(sessions) => sessions,
-        Err(e) => {
-            error!("Failed to parse role rank session store: {:?}", e);
-            return;
+/// A Discord user ID as typed in /config, `None` unless it is a non-zero snowflake
+pub fn parse_bot_id(id: &str) -> Option<serenity::UserId> {
+    id.trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|&id| id != 0)
+        .map(serenity::UserId::new)
+}
+
+/// Quiz bots whose results count in a guild, Kotoba when none are configured
+pub fn configured_quiz_bots(config: Option<&GuildConfig>) -> Vec<serenity::UserId> {
+    let mut bots: Vec<serenity::UserId> = Vec::new();
+    for id in config
+        .map(|c| c.quiz_bot_ids.as_slice())
+        .unwrap_or_default()
+    {
+        match parse_bot_id(id) {
+            Some(id) if !bots.contains(&id) => bots.push(id),
+            Some(_) => {}
+            None => warn!("Ignoring invalid quiz bot id {:?}", id),
         }
+    }
+    if bots.is_empty() {
+        bots.push(KOTOBA_BOT_ID);
+    }
+    bots
+}
+
+/// Quiz bots of a guild from the cached config
+pub fn guild_quiz_bots(data: &Data, guild_id: serenity::GuildId) -> Vec<serenity::UserId> {
+    let config = data.guild_configs.get(&guild_id.to_string());
+    configured_quiz_bots(config.as_deref())
+}
+
+/// Configured id list and the set parsed from it
+type QuizBotSet = (Vec<String>, HashSet<serenity::UserId>);
+
+/// Parsed quiz bot sets per guild
+static QUIZ_BOTS: Lazy<DashMap<serenity::GuildId, QuizBotSet>> = Lazy::new(DashMap::new);
+
+/// Whether a bot message may carry quiz results. Runs for every bot message, so the parsed
+/// set is only rebuilt when the guild's configured list changes.
+fn is_quiz_bot(data: &Data, guild_id: Option<serenity::GuildId>, author: serenity::UserId) -> bool {
+    let Some(guild_id) = guild_id else {
+        return author == KOTOBA_BOT_ID;
     };
+    let config = data.guild_configs.get(&guild_id.to_string());
+    let configured = config
+        .as_ref()
+        .map(|c| c.quiz_bot_ids.as_slice())
+        .unwrap_or_default();
 
-    let mut restored = 0;
-    for stored in stored_sessions {
-        match QuizSession::try_from(stored) {
-            Ok(session) => {
-                sessions.insert(session.user_id, session);
-                restored += 1;
+    if let Some(cached) = QUIZ_BOTS.get(&guild_id) {
+        if cached.0 == configured {
+            return cached.1.contains(&author);
+        }
+    }
+
+    let bots: HashSet<serenity::UserId> = configured_quiz_bots(config.as_deref())
+        .into_iter()
+        .collect();
+    let is_bot = bots.contains(&author);
+    QUIZ_BOTS.insert(guild_id, (configured.to_vec(), bots));
+    is_bot
+}
+
+/// Standard overwrites for a private quiz channel: hidden from everyone except
+/// the quiz taker, the guild's quiz bots and this bot
+pub fn quiz_channel_overwrites(
+    guild_id: serenity::GuildId,
+    user_id: serenity::UserId,
+    bot_id: serenity::UserId,
+    quiz_bots: &[serenity::UserId],
+) -> Vec<serenity::PermissionOverwrite> {
+    let mut overwrites = vec![
+        serenity::PermissionOverwrite {
+            allow: serenity::Permissions::empty(),
+            deny: serenity::Permissions::VIEW_CHANNEL,
+            kind: serenity::PermissionOverwriteType::Role(serenity::RoleId::new(guild_id.get())),
+        },
+        serenity::PermissionOverwrite {
+            allow: serenity::Permissions::VIEW_CHANNEL
+                | serenity::Permissions::SEND_MESSAGES
+                | serenity::Permissions::READ_MESSAGE_HISTORY,
+            deny: serenity::Permissions::empty(),
+            kind: serenity::PermissionOverwriteType::Member(user_id),
+        },
+    ];
+    for &member in quiz_bots.iter().chain(std::iter::once(&bot_id)) {
+        overwrites.push(serenity::PermissionOverwrite {
+            allow: serenity::Permissions::VIEW_CHANNEL
+                | serenity::Permissions::SEND_MESSAGES
+                | serenity::Permissions::READ_MESSAGE_HISTORY,
+            deny: serenity::Permissions::empty(),
+            kind: serenity::PermissionOverwriteType::Member(member),
+        });
+    }
+    overwrites
+}
+
+/// How the welcome message refers to the quiz bots
+fn quiz_bot_names(ctx: &serenity::Context, quiz_bots: &[serenity::UserId]) -> String {
+    if quiz_bots == [KOTOBA_BOT_ID] {
+        return "Kotoba Bot".to_string();
+    }
+    quiz_bots
+        .iter()
+        .map(|id| {
+            ctx.cache
+                .user(*id)
+                .map(|u| format!("**{}**", u.name))
+                .unwrap_or_else(|| format!("<@{}>", id))
+        })
+        .collect::<Vec<_>>()
+        .join(" / ")
+}
+
+/// Whether a channel counts as a private quiz channel: it sits either in the category its
+/// session was created under or in the currently configured quiz category
+pub fn in_quiz_category(
+    channel_parent: Option<serenity::ChannelId>,
+    session_category: Option<serenity::ChannelId>,
+    config_category: Option<serenity::ChannelId>,
+) -> bool {
+    channel_parent.is_some()
+        && (channel_parent == session_category || channel_parent == config_category)
+}
+
+/// Category check for a!del and `/role_rank delete`
+pub async fn is_quiz_channel(data: &Data, channel: &serenity::GuildChannel) -> bool {
+    let config_category =
+        crate::utils::config::get_guild_config(data, &channel.guild_id.to_string())
+            .await
+            .and_then(|config| config.quiz_category_id)
+            .and_then(|id| id.parse::<u64>().ok())
+            .map(serenity::ChannelId::new);
+    let session_category = data
+        .role_rank_sessions
+        .iter()
+        .find(|entry| entry.thread_id == channel.id)
+        .and_then(|entry| entry.category_id);
+
+    in_quiz_category(channel.parent_id, session_category, config_category)
+}
+
+// --- Session Expiry ---
+
+/// What the cleanup sweep should do with a session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SessionExpiry {
+    Keep,
+    Warn,
+    Expire,
+}
+
+fn session_expiry(
+    session: &QuizSession,
+    now: chrono::DateTime<chrono::Utc>,
+    ttl: chrono::Duration,
+) -> SessionExpiry {
+    let expires_at = session.last_activity_at + ttl;
+    if now >= expires_at {
+        SessionExpiry::Expire
+    } else if !session.expiry_warned
+        && now >= expires_at - chrono::Duration::seconds(QUIZ_CHANNEL_WARNING_SECS)
+    {
+        SessionExpiry::Warn
+    } else {
+        SessionExpiry::Keep
+    }
+}
+
+fn session_ttl_from_env() -> chrono::Duration {
+    let secs = env::var("QUIZ_SESSION_TTL_SECS")
+        .ok()
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .filter(|secs| *secs > 0)
+        .unwrap_or(DEFAULT_QUIZ_CHANNEL_TTL_SECS);
+    chrono::Duration::seconds(secs)
+}
+
+/// 404 / Unknown Channel (10003)
+pub fn is_unknown_channel(e: &serenity::Error) -> bool {
+    match e {
+        serenity::Error::Http(serenity::http::HttpError::UnsuccessfulRequest(resp)) => {
+            resp.status_code.as_u16() == 404 || resp.error.code == 10003
+        }
+        _ => false,
+    }
+}
+
+/// Whether a running attempt went too long without a Kotoba congratulation
+fn attempt_timed_out(session: &QuizSession, now: chrono::DateTime<chrono::Utc>) -> bool {
+    session.active_attempt
+        && session.attempt_started_at.is_some_and(|started| {
+            now - started >= chrono::Duration::minutes(quiz_stats::ATTEMPT_TIMEOUT_MINS)
+        })
+}
+
+/// Record timed out attempts; the session stays open so a late result still counts
+fn record_timed_out_attempts(
+    firebase: &Arc<dyn crate::api::storage::Storage>,
+    sessions: &DashMap<serenity::UserId, QuizSession>,
+    now: chrono::DateTime<chrono::Utc>,
+) {
+    let mut recorded = false;
+    for mut entry in sessions.iter_mut() {
+        if !attempt_timed_out(entry.value(), now) {
+            continue;
+        }
+        let started_at = entry.attempt_started_at.take();
+        let event = QuizEvent::new(
+            &entry.quiz_id,
+            entry.user_id,
+            entry.progress,
+            QuizOutcome::TimedOut,
+            started_at,
+            now,
+        );
+        quiz_stats::record(firebase.clone(), entry.guild_id, event);
+        recorded = true;
+    }
+    if recorded {
+        persist_or_log(firebase, sessions);
+    }
+}
+
+/// Periodically delete private quiz channels whose session outlived the TTL
+pub fn spawn_session_cleanup(
+    http: Arc<serenity::Http>,
+    firebase: Arc<dyn crate::api::storage::Storage>,
+    sessions: Arc<DashMap<serenity::UserId, QuizSession>>,
+    clock: Arc<dyn crate::utils::clock::Clock>,
+    shutdown: &crate::utils::shutdown::Shutdown,
+) {
+    let ttl = session_ttl_from_env();
+    let shutdown_signal = shutdown.clone();
+    shutdown.spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(300));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown_signal.cancelled() => break,
             }
-            Err(e) => warn!("Skipping invalid role rank session: {:?}", e),
+            let now = clock.now_utc();
+            record_timed_out_attempts(&firebase, &sessions, now);
+            sweep_expired_sessions(&http, &firebase, &sessions, now, ttl).await;
         }
+    });
+}
+
+async fn sweep_expired_sessions(
+    http: &serenity::Http,
+    firebase: &Arc<dyn crate::api::storage::Storage>,
+    sessions: &DashMap<serenity::UserId, QuizSession>,
+    now: chrono::DateTime<chrono::Utc>,
+    ttl: chrono::Duration,
+) {
+    // Snapshot first so no map guard is held across Discord calls
+    type Due = (
+        serenity::UserId,
+        serenity::ChannelId,
+        Option<serenity::ChannelId>,
+        SessionExpiry,
+    );
+    let due: Vec<Due> = sessions
+        .iter()
+        .map(|e| {
+            (
+                *e.key(),
+                e.thread_id,
+                e.category_id,
+                session_expiry(e.value(), now, ttl),
+            )
+        })
+        .filter(|(_, _, _, action)| *action != SessionExpiry::Keep)
+        .collect();
+
+    if due.is_empty() {
+        return;
     }
 
-    if restored > 0 {
-        info!("Restored {} role rank quiz session(s)", restored);
+    for (user_id, channel_id, category_id, action) in due {
+        // Never delete a channel that was moved out of the session's category
+        if let (SessionExpiry::Expire, Some(category_id)) = (action, category_id) {
+            match channel_id.to_channel(http).await {
+                Ok(channel) => {
+                    let parent = channel.guild().and_then(|gc| gc.parent_id);
+                    if !in_quiz_category(parent, Some(category_id), None) {
+                        warn!(
+                            "Expired quiz channel {} is no longer in category {}, dropping the session only",
+                            channel_id, category_id
+                        );
+                        sessions.remove(&user_id);
+                        continue;
+                    }
+                }
+                Err(e) if is_unknown_channel(&e) => {
+                    sessions.remove(&user_id);
+                    continue;
+                }
+                Err(e) => {
+                    warn!(
+                        "Failed to fetch expired quiz channel {}: {:?}",
+                        channel_id, e
+                    );
+                    continue;
+                }
+            }
+        }
+
+        match action {
+            SessionExpiry::Warn => {
+                let warning = format!(
+                    "<@{}> Channel ini akan dihapus dalam 1 jam karena tidak ada aktivitas.",
+                    user_id
+                );
+                match channel_id.say(http, warning).await {
+                    Ok(_) => {
+                        if let Some(mut session) = sessions.get_mut(&user_id) {
+                            session.expiry_warned = true;
+                        }
+                    }
+                    Err(e) if is_unknown_channel(&e) => {
+                        sessions.remove(&user_id);
+                    }
+                    Err(e) => warn!("Failed to warn quiz channel {}: {:?}", channel_id, e),
+                }
+            }
+            SessionExpiry::Expire => match channel_id.delete(http).await {
+                Ok(_) => {
+                    info!(
+                        "Deleted expired quiz channel {} for user {}",
+                        channel_id, user_id
+                    );
+                    sessions.remove(&user_id);
+                }
+                // Already deleted by hand
+                Err(e) if is_unknown_channel(&e) => {
+                    sessions.remove(&user_id);
+                }
+                Err(e) => warn!(
+                    "Failed to delete expired quiz channel {}: {:?}",
+                    channel_id, e
+                ),
+            },
+            SessionExpiry::Keep => {}
+        }
     }
+
+    persist_or_log(firebase, sessions);
 }
 
 // --- Quiz Data Definitions ---
@@ -312,7 +938,7 @@ pub static QUIZZES: Lazy<HashMap<String, QuizInfo>> = Lazy::new(|| {
             level: 0,
             description: "Hiragana + Katakana Quiz",
             value: "hiragana_katakana",
-            role_id: serenity::RoleId::new(1392065087216291891),
+            default_role_id: serenity::RoleId::new(1392065087216291891),
             commands: &[
                 "k!quiz hiragana+katakana nd mmq=10 dauq=1 font=5 atl=16 color=#f173ff size=100",
             ],
@@ -326,7 +952,7 @@ pub static QUIZZES: Lazy<HashMap<String, QuizInfo>> = Lazy::new(|| {
         level: 1,
         description: "JPDB Beginner Level (1-300)",
         value: "Level_1",
-        role_id: serenity::RoleId::new(1392065395984306246),
+        default_role_id: serenity::RoleId::new(1392065395984306246),
         commands: &["k!quiz jpdb300 20 hardcore nd mmq=10 dauq=1 font=5 atl=16 color=#f173ff size=100 effect=antiocr"],
         deck_names: &["jpdb300"],
         score_limits: &["20"],
@@ -337,7 +963,7 @@ pub static QUIZZES: Lazy<HashMap<String, QuizInfo>> = Lazy::new(|| {
         level: 2,
         description: "JPDB Intermediate Level (300-1000)",
         value: "Level_2",
-        role_id: serenity::RoleId::new(1392065532051591240),
+        default_role_id: serenity::RoleId::new(1392065532051591240),
         commands: &["k!quiz jpdb300to1k 25 hardcore nd mmq=10 dauq=1 font=5 atl=16 color=#f173ff size=100 effect=antiocr"],
         deck_names: &["jpdb300to1k"],
         score_limits: &["25"],
@@ -348,7 +974,7 @@ pub static QUIZZES: Lazy<HashMap<String, QuizInfo>> = Lazy::new(|| {
         level: 3,
         description: "JPDB Advance Level (100-3000)",
         value: "Level_3",
-        role_id: serenity::RoleId::new(1392065673185857627),
+        default_role_id: serenity::RoleId::new(1392065673185857627),
         commands: &["k!quiz jpdb1k3k 30 hardcore nd mmq=10 dauq=1 font=5 atl=16 color=#f173ff size=100 effect=antiocr"],
         deck_names: &["jpdb1k3k"],
         score_limits: &["30"],
@@ -359,7 +985,7 @@ pub static QUIZZES: Lazy<HashMap<String, QuizInfo>> = Lazy::new(|| {
         level: 4,
         description: "JPDB 5000 + gn2",
         value: "Level_4",
-        role_id: serenity::RoleId::new(1392066020235153408),
+        default_role_id: serenity::RoleId::new(1392066020235153408),
         commands: &[
             "k!quiz gn2 nd 20 mmq=4 atl=60",
             "k!quiz jpdb3k5k 40 hardcore nd mmq=10 dauq=1 font=5 atl=16 color=#f173ff size=100 effect=antiocr"
@@ -373,7 +999,7 @@ pub static QUIZZES: Lazy<HashMap<String, QuizInfo>> = Lazy::new(|| {
         level: 5,
         description: "JPDB 10K + gn1",
         value: "Level_5",
-        role_id: serenity::RoleId::new(1392066105677189121),
+        default_role_id: serenity::RoleId::new(1392066105677189121),
         commands: &[
             "k!quiz gn1 nd 20 mmq=4 atl=60",
             "k!quiz jpdb5k10k 40 hardcore nd mmq=10 dauq=1 font=5 atl=16 color=#f173ff size=100 effect=antiocr"
@@ -387,7 +1013,7 @@ pub static QUIZZES: Lazy<HashMap<String, QuizInfo>> = Lazy::new(|| {
         level: 6,
         description: "JPDB 20K + gn1",
         value: "Level_6",
-        role_id: serenity::RoleId::new(1392066278335840376),
+        default_role_id: serenity::RoleId::new(1392066278335840376),
         commands: &[
             "k!quiz gn1 nd 20 mmq=4 atl=60",
             "k!quiz jpdb10k20k 45 hardcore nd mmq=10 dauq=1 font=5 atl=16 color=#f173ff size=100 effect=antiocr"
@@ -401,7 +1027,7 @@ pub static QUIZZES: Lazy<HashMap<String, QuizInfo>> = Lazy::new(|| {
         level: 7,
         description: "JPDB 30K",
         value: "Level_7",
-        role_id: serenity::RoleId::new(1392066430467440742),
+        default_role_id: serenity::RoleId::new(1392066430467440742),
         commands: &["k!quiz jpdb20k30k+haado+cope+kunyomi1kfull+loli+Myouji+jpdefs+places_full 50 nd hardcore dauq=1 font=5 atl=16 mmq=9 color=#f173ff size=100 effect=antiocr"],
         deck_names: &["Multiple Deck Quiz"],
         score_limits: &["50"],
@@ -412,13 +1038,58 @@ pub static QUIZZES: Lazy<HashMap<String, QuizInfo>> = Lazy::new(|| {
 
 // --- Handlers ---
 
+/// Members whose quiz channel is being created, so a second click while Discord is slow
+/// can't open another one
+static STARTING: Lazy<DashSet<serenity::UserId>> = Lazy::new(DashSet::new);
+
+/// Maximum number of guild channels reached (30013), or the category's 50 channel limit,
+/// which Discord reports as an invalid `parent_id`
+fn is_channel_limit_response<'a>(
+    status: u16,
+    code: isize,
+    field_codes: impl IntoIterator<Item = &'a str>,
+) -> bool {
+    status == 400
+        && (code == 30013
+            || field_codes
+                .into_iter()
+                .any(|c| c == "CHANNEL_PARENT_MAX_CHANNELS"))
+}
+
+fn is_channel_limit(e: &serenity::Error) -> bool {
+    match e {
+        serenity::Error::Http(serenity::http::HttpError::UnsuccessfulRequest(resp)) => {
+            is_channel_limit_response(
+                resp.status_code.as_u16(),
+                resp.error.code,
+                resp.error.errors.iter().map(|e| e.code.as_str()),
+            )
+        }
+        _ => false,
+    }
+}
+
+/// Replace the deferred "thinking" reply with `content`
+async fn respond(
+    ctx: &serenity::Context,
+    interaction: &serenity::ComponentInteraction,
+    content: impl Into<String>,
+) {
+    let _ = interaction
+        .edit_response(
+            ctx,
+            serenity::EditInteractionResponse::new().content(content),
+        )
+        .await;
+}
+
 /// Handle "quiz_select" interaction
 pub async fn handle_interaction(
     ctx: &serenity::Context,
     interaction: &serenity::ComponentInteraction,
     data: &Data,
 ) -> Result<(), Error> {
-    if interaction.data.custom_id != "quiz_select" {
+    if interaction.data.custom_id != QUIZ_SELECT_ID {
         return Ok(());
     }
 
@@ -430,47 +1101,86 @@ pub async fn handle_interaction(
     }
     .ok_or("No quiz selected")?;
 
-    let quiz = match QUIZZES.get(quiz_id) {
-        Some(q) => q,
-        None => {
-            let _ = interaction
-                .create_response(
-                    ctx,
-                    serenity::CreateInteractionResponse::Message(
-                        serenity::CreateInteractionResponseMessage::new()
-                            .content("Quiz not found!")
-                            .ephemeral(true),
-                    ),
-                )
-                .await;
-            return Ok(());
-        }
+    // Discord waits 3 seconds for an answer, creating the channel can take longer
+    interaction.defer_ephemeral(ctx).await?;
+
+    // Put the dropdown back to its placeholder, the pick would stay shown otherwise
+    let config = get_guild_config(data, &guild_id.to_string()).await;
+    if let Err(e) = interaction
+        .message
+        .channel_id
+        .edit_message(
+            ctx,
+            interaction.message.id,
+            serenity::EditMessage::new().components(vec![
+                crate::commands::role_rank::quiz_select_row(config.as_ref()),
+            ]),
+        )
+        .await
+    {
+        warn!("Failed to reset quiz selector: {:?}", e);
+    }
+
+    let Some(quiz) = QUIZZES.get(quiz_id) else {
+        respond(ctx, interaction, "Quiz not found!").await;
+        return Ok(());
     };
 
-    // Check if user already has an active session
+    if !STARTING.insert(user.id) {
+        respond(
+            ctx,
+            interaction,
+            "Your quiz channel is still being created, hang on a moment.",
+        )
+        .await;
+        return Ok(());
+    }
+    let result = start_quiz(
+        ctx,
+        interaction,
+        data,
+        config.as_ref(),
+        guild_id,
+        quiz_id,
+        quiz,
+    )
+    .await;
+    STARTING.remove(&user.id);
+    result
+}
+
+/// Open a private channel for the quiz, unless the member already has one
+async fn start_quiz(
+    ctx: &serenity::Context,
+    interaction: &serenity::ComponentInteraction,
+    data: &Data,
+    config: Option<&GuildConfig>,
+    guild_id: serenity::GuildId,
+    quiz_id: &str,
+    quiz: &QuizInfo,
+) -> Result<(), Error> {
+    let user = &interaction.user;
+
+    // Check if user already has an active session, before anything gets created
     if let Some(session) = data.role_rank_sessions.get(&user.id) {
+        let thread_id = session.thread_id;
+        drop(session); // release lock
+
         // Verify if channel still exists
-        match ctx.http.get_channel(session.thread_id).await {
+        match ctx.http.get_channel(thread_id).await {
             Ok(_) => {
-                let _ = interaction
-                    .create_response(
-                        ctx,
-                        serenity::CreateInteractionResponse::Message(
-                            serenity::CreateInteractionResponseMessage::new()
-                                .content(
-                                    "You already have an active quiz session! Finish it first.",
-                                )
-                                .ephemeral(true),
-                        ),
-                    )
-                    .await;
+                respond(
+                    ctx,
+                    interaction,
+                    "You already have an active quiz session! Finish it first.",
+                )
+                .await;
                 return Ok(());
             }
             Err(_) => {
                 // Channel gone, remove session
-                drop(session); // release lock
                 data.role_rank_sessions.remove(&user.id);
-                persist_or_log(&data.role_rank_sessions);
+                persist_or_log(&data.firebase, &data.role_rank_sessions);
             }
         }
     }
@@ -488,34 +1198,9 @@ pub async fn handle_interaction(
             .to_lowercase()
     );
 
-    let permission_overwrites = vec![
-        serenity::PermissionOverwrite {
-            allow: serenity::Permissions::empty(),
-            deny: serenity::Permissions::VIEW_CHANNEL,
-            kind: serenity::PermissionOverwriteType::Role(serenity::RoleId::new(guild_id.get())),
-        },
-        serenity::PermissionOverwrite {
-            allow: serenity::Permissions::VIEW_CHANNEL
-                | serenity::Permissions::SEND_MESSAGES
-                | serenity::Permissions::READ_MESSAGE_HISTORY,
-            deny: serenity::Permissions::empty(),
-            kind: serenity::PermissionOverwriteType::Member(user.id),
-        },
-        serenity::PermissionOverwrite {
-            allow: serenity::Permissions::VIEW_CHANNEL
-                | serenity::Permissions::SEND_MESSAGES
-                | serenity::Permissions::READ_MESSAGE_HISTORY,
-            deny: serenity::Permissions::empty(),
-            kind: serenity::PermissionOverwriteType::Member(KOTOBA_BOT_ID),
-        },
-        serenity::PermissionOverwrite {
-            allow: serenity::Permissions::VIEW_CHANNEL
-                | serenity::Permissions::SEND_MESSAGES
-                | serenity::Permissions::READ_MESSAGE_HISTORY,
-            deny: serenity::Permissions::empty(),
-            kind: serenity::PermissionOverwriteType::Member(ctx.cache.current_user().id),
-        },
-    ];
+    let quiz_bots = guild_quiz_bots(data, guild_id);
+    let permission_overwrites =
+        quiz_channel_overwrites(guild_id, user.id, ctx.cache.current_user().id, &quiz_bots);
 
     // Get configured category ID or error
     let category_id = {
@@ -533,18 +1218,12 @@ pub async fn handle_interaction(
     let category_id = match category_id {
         Some(id) => id,
         None => {
-            let _ = interaction
-                .create_response(
-                    ctx,
-                    serenity::CreateInteractionResponse::Message(
-                        serenity::CreateInteractionResponseMessage::new()
-                            .content(
-                                "Quiz Category not configured! Ask admin to set it via /config.",
-                            )
-                            .ephemeral(true),
-                    ),
-                )
-                .await;
+            respond(
+                ctx,
+                interaction,
+                "Quiz Category not configured! Ask admin to set it via /config.",
+            )
+            .await;
             return Ok(());
         }
     };
@@ -556,18 +1235,23 @@ pub async fn handle_interaction(
 
     let channel = match guild_id.create_channel(&ctx.http, builder).await {
         Ok(c) => c,
+        Err(e) if is_channel_limit(&e) => {
+            warn!(
+                "Quiz category {} of guild {} is full: {:?}",
+                category_id, guild_id, e
+            );
+            respond(
+                ctx,
+                interaction,
+                "The quiz category is full, Discord allows 50 channels per category. \
+                 Ask an admin to clean up old quiz channels, then try again.",
+            )
+            .await;
+            return Ok(());
+        }
         Err(e) => {
             error!("Failed to create quiz channel: {:?}", e);
-            let _ = interaction
-                .create_response(
-                    ctx,
-                    serenity::CreateInteractionResponse::Message(
-                        serenity::CreateInteractionResponseMessage::new()
-                            .content("Failed to create private channel!")
-                            .ephemeral(true),
-                    ),
-                )
-                .await;
+            respond(ctx, interaction, "Failed to create private channel!").await;
             return Ok(());
         }
     };
@@ -577,38 +1261,49 @@ pub async fn handle_interaction(
         user.id,
         QuizSession {
             user_id: user.id,
-            quiz_id: quiz_id.clone(),
+            quiz_id: quiz_id.to_string(),
             thread_id: channel.id,
             started: false,
             active_attempt: false,
             progress: 0,
+            created_at: data.clock.now_utc(),
+            last_activity_at: data.clock.now_utc(),
+            expiry_warned: false,
+            category_id: Some(category_id),
+            guild_id: Some(guild_id),
+            attempt_started_at: None,
         },
     );
-    persist_or_log(&data.role_rank_sessions);
+    persist_or_log(&data.firebase, &data.role_rank_sessions);
 
     // Send Welcome Message
     let command_text = quiz.commands[0];
-    let welcome_msg = format!(
-        "Halo <@{}>! Untuk memulai quiz, copy dan paste command berikut:\n\n\
-        **Command:**\n```\n{}\n```\n\n\
-        **Cara bermain:**\n\
-        1. Copy command di atas\n\
-        2. Paste di channel ini\n\
-        3. Jawab pertanyaan dari Kotoba Bot\n\
-        4. Kamu akan mendapat role **{}** setelah menyelesaikan quiz!\n\
-        5. Kamu bisa hapus channel ini secara manual dengan `a!del` (atau `/role_rank delete`)\n\n\
-        Jangan lupa paste command langsung di channel ini ya!",
-        user.id, command_text, quiz.label
+    let welcome_msg = tf(
+        config,
+        Msg::QuizWelcome,
+        &[
+            &user.id,
+            &command_text,
+            &quiz_bot_names(ctx, &quiz_bots),
+            &quiz.label,
+        ],
     );
 
-    let _ = channel.say(&ctx.http, welcome_msg).await;
-
-    // Acknowledge Interaction
-    let _ = interaction.create_response(ctx, serenity::CreateInteractionResponse::Message(
-        serenity::CreateInteractionResponseMessage::new()
-            .content(format!("Channel private **{}** telah dibuat untuk quiz **{}**. Silakan lanjut di sana!", channel.name, quiz.label))
-            .ephemeral(true)
-    )).await;
+    let report_to = mod_log_channel(data, Some(guild_id)).await;
+    let _ = say_checked(ctx, channel.id, welcome_msg, report_to).await;
+
+    // Deliver the outcome
+    respond(
+        ctx,
+        interaction,
+        tf(
+            config,
+            Msg::QuizChannelCreated,
+            &[&channel.name, &quiz.label],
+        ),
+    )
+    .await;
+    suggest_from_jpdb(ctx, interaction, data, config, quiz).await;
 
     Ok(())
 }
@@ -622,50 +1317,17 @@ pub async fn handle_message(
     // 1. Handle User Starting Quiz
     if !msg.author.bot {
         if msg.content.starts_with("k!quiz") {
-            // Check if this is an active session channel
-            // We need to find if this channel belongs to ANY active session for THIS user
-            let mut response: Option<String> = None;
-            let mut should_persist = false;
-
+            if let Some(verdict) = answer_quiz_command(
+                ctx,
+                data,
+                msg.author.id,
+                msg.channel_id,
+                msg.id,
+                &msg.content,
+            )
+            .await
             {
-                if let Some(mut session) = data.role_rank_sessions.get_mut(&msg.author.id) {
-                    if session.thread_id == msg.channel_id {
-                        let quiz = match QUIZZES.get(&session.quiz_id) {
-                            Some(q) => q,
-                            None => return Ok(()),
-                        };
-
-                        let expected_command = quiz.commands[session.progress];
-
-                        if validate_command(&msg.content, expected_command) {
-                            session.started = true;
-                            session.active_attempt = true;
-                            should_persist = true;
-                            response = Some(
-                                "Command Valid! Menunggu hasil dari Kotoba Bot...".to_string(),
-                            );
-                        } else {
-                            session.active_attempt = false; // Invalidate previous attempt if any
-                            should_persist = true;
-                            response = Some(format!(
-                                "**Command Tidak Sesuai**\nUntuk role ini, kamu wajib menggunakan command yang persis sama:\n```\n{}\n```\nJika kamu sedang menjalankan quiz, selesaikan dulu atau ketik `k!quiz stop` lalu paste commandnya lagi.",
-                                expected_command
-                            ));
-                        }
-                    }
-                }
-            }
-
-            if should_persist {
-                persist_or_log(&data.role_rank_sessions);
-            }
-
-            if let Some(response) = response {
-                if response.starts_with("Command Valid!") {
-                    let _ = msg.channel_id.say(&ctx.http, response).await;
-                } else {
-                    let _ = msg.reply(&ctx.http, response).await;
-                }
+                COMMAND_VERDICTS.record(msg.id, verdict, &msg.content, data.clock.now_instant());
             }
         }
         // Handle a!q clear (bulk cleanup for stuck quiz channels/sessions)
@@ -685,59 +1347,53 @@ pub async fn handle_message(
         // Handle a!del (manual delete)
         else if msg.content.starts_with("a!del") {
             let channel = match msg.channel(&ctx.http).await {
-                Ok(c) => c.guild().map(|gc| gc),
+                Ok(c) => c.guild(),
                 Err(_) => None,
             };
 
             if let Some(gc) = channel {
+                if !is_quiz_channel(data, &gc).await {
+                    let _ = msg
+                        .reply(&ctx.http, "This command only works in quiz channels.")
+                        .await;
+                    return Ok(());
+                }
+
+                // Check if this is the configured selector channel
                 let guild_id = gc.guild_id.to_string();
-                let category_id = if let Some(config) =
-                    crate::utils::config::get_guild_config(data, &guild_id).await
+                if let Some(config) = crate::utils::config::get_guild_config(data, &guild_id).await
                 {
-                    config
-                        .quiz_category_id
-                        .as_ref()
-                        .and_then(|id| id.parse::<u64>().ok())
-                        .map(serenity::ChannelId::new)
-                } else {
-                    None
-                };
-
-                if let Some(cat_id) = category_id {
-                    if gc.parent_id == Some(cat_id) {
-                        // Check if this is the configured selector channel
-                        if let Some(config) =
-                            crate::utils::config::get_guild_config(data, &guild_id).await
-                        {
-                            if let Some(selector_id) = &config.quiz_channel_id {
-                                if gc.id.to_string() == *selector_id {
-                                    let _ = msg.reply(&ctx.http, "Cannot delete main selector channel (Protected via Config).").await;
-                                    return Ok(());
-                                }
-                            }
-                        }
-
-                        let _ = msg
-                            .reply(&ctx.http, "Deleting channel in 3 seconds...")
-                            .await;
-                        tokio::time::sleep(std::time::Duration::from_secs(3)).await;
-
-                        if let Err(e) = gc.delete(&ctx.http).await {
-                            error!("Failed to delete channel: {:?}", e);
+                    if let Some(selector_id) = &config.quiz_channel_id {
+                        if gc.id.to_string() == *selector_id {
                             let _ = msg
-                                .reply(&ctx.http, format!("Failed to delete channel: {}", e))
+                                .reply(
+                                    &ctx.http,
+                                    "Cannot delete main selector channel (Protected via Config).",
+                                )
                                 .await;
-                        } else {
-                            // Only drop the session after the channel is gone.
-                            data.role_rank_sessions.retain(|_, v| v.thread_id != gc.id);
-                            persist_or_log(&data.role_rank_sessions);
+                            return Ok(());
                         }
-                    } else {
-                        let _ = msg
-                            .reply(&ctx.http, "This command only works in quiz channels.")
-                            .await;
                     }
-                } // closing category_id
+                }
+
+                let _ = msg
+                    .reply(
+                        &ctx.http,
+                        "Deleting channel in 3 seconds...\n-# `a!del` is going away, use `/role_rank delete`.",
+                    )
+                    .await;
+                tokio::time::sleep(std::time::Duration::from_secs(3)).await;
+
+                if let Err(e) = gc.delete(&ctx.http).await {
+                    error!("Failed to delete channel: {:?}", e);
+                    let _ = msg
+                        .reply(&ctx.http, format!("Failed to delete channel: {}", e))
+                        .await;
+                } else {
+                    // Only drop the session after the channel is gone.
+                    data.role_rank_sessions.retain(|_, v| v.thread_id != gc.id);
+                    persist_or_log(&data.firebase, &data.role_rank_sessions);
+                }
             }
         }
         // Handle a!clear <user_id> (Manual Role Reset)
@@ -747,69 +1403,229 @@ pub async fn handle_message(
                 return Ok(());
             }
 
-            // 2. Parse User ID
-            let args: Vec<&str> = msg.content.split_whitespace().collect();
-            if args.len() < 2 {
+            // 2. Parse the members to reset
+            let args: Vec<&str> = msg.content.split_whitespace().skip(1).collect();
+            if args.is_empty() {
                 let _ = msg
-                    .reply(&ctx.http, "Usage: `a!clear <user_id>` (or mention)")
+                    .reply(
+                        &ctx.http,
+                        "Usage: `a!clear <user_id> [user_id ...]` (or mentions)",
+                    )
                     .await;
                 return Ok(());
             }
 
-            let target_str = args[1].trim_start_matches("<@").trim_end_matches(">");
-            let target_id = match target_str.parse::<u64>() {
-                Ok(id) => serenity::UserId::new(id),
-                Err(_) => {
-                    let _ = msg.reply(&ctx.http, "Invalid User ID format.").await;
-                    return Ok(());
-                }
-            };
+            let (targets, invalid) = parse_user_targets(&args);
+            if targets.is_empty() {
+                let _ = msg.reply(&ctx.http, "Invalid User ID format.").await;
+                return Ok(());
+            }
+            if targets.len() > MAX_CLEAR_TARGETS {
+                let _ = msg
+                    .reply(
+                        &ctx.http,
+                        format!("At most {} users per `a!clear`.", MAX_CLEAR_TARGETS),
+                    )
+                    .await;
+                return Ok(());
+            }
 
             // 3. Remove Roles
             if let Some(guild_id) = msg.guild_id {
-                match guild_id.member(&ctx.http, target_id).await {
-                    Ok(member) => {
-                        let mut removed_count = 0;
-                        for quiz in QUIZZES.values() {
-                            if member.roles.contains(&quiz.role_id) {
-                                if let Err(e) = member.remove_role(&ctx.http, quiz.role_id).await {
-                                    error!(
-                                        "Failed to remove role {} for user {}: {:?}",
-                                        quiz.role_id, target_id, e
-                                    );
-                                } else {
-                                    removed_count += 1;
+                let config = get_guild_config(data, &guild_id.to_string()).await;
+                let config = config.as_ref();
+                let quizzes = quizzes_by_level();
+                let mut fields = Vec::new();
+                let mut any_failed = false;
+                let mut refused_role = None;
+                for target_id in targets {
+                    match guild_id.member(&ctx.http, target_id).await {
+                        Ok(member) => {
+                            let outcomes =
+                                remove_quiz_roles(&ctx.http, &member, &quizzes, config).await;
+                            let mut lines = vec![format!("<@{}>", target_id)];
+                            for (quiz, outcome) in &outcomes {
+                                let role_id = quiz.role_in(config);
+                                any_failed |= outcome.is_failure();
+                                if *outcome == RoleRemoval::MissingPermissions {
+                                    refused_role.get_or_insert(role_id);
                                 }
+                                lines.push(format!("<@&{}> {}", role_id, outcome.status()));
                             }
+                            fields.push((member.user.name.clone(), lines.join("\n"), false));
+                        }
+                        Err(_) => {
+                            any_failed = true;
+                            fields.push((
+                                target_id.to_string(),
+                                "User not found in this server.".to_string(),
+                                false,
+                            ));
                         }
-
-                        let _ = msg
-                            .reply(
-                                &ctx.http,
-                                format!(
-                                    "**Reset Complete**: Removed {} quiz roles from <@{}>.",
-                                    removed_count, target_id
-                                ),
-                            )
-                            .await;
                     }
-                    Err(_) => {
-                        let _ = msg.reply(&ctx.http, "User not found in this server.").await;
+                }
+
+                let mut description = Vec::new();
+                if !invalid.is_empty() {
+                    description.push(format!("Skipped invalid IDs: {}", invalid.join(", ")));
+                }
+                if let Some(role_id) = refused_role {
+                    if let Some(hint) = hierarchy_hint(ctx, guild_id, role_id).await {
+                        description.push(hint);
                     }
                 }
+
+                let embed = serenity::CreateEmbed::new()
+                    .title(if any_failed {
+                        "Reset Incomplete"
+                    } else {
+                        "Reset Complete"
+                    })
+                    .description(description.join("\n\n"))
+                    .fields(fields)
+                    .footer(serenity::CreateEmbedFooter::new(
+                        "a!clear is going away, use /role_rank clear",
+                    ))
+                    .color(if any_failed {
+                        crate::utils::config::colors::WARNING
+                    } else {
+                        crate::utils::config::colors::SUCCESS
+                    });
+                let report_to = mod_log_channel(data, Some(guild_id)).await;
+                let _ = send_checked(
+                    ctx,
+                    msg.channel_id,
+                    serenity::CreateMessage::new()
+                        .embed(embed)
+                        .reference_message(msg),
+                    report_to,
+                )
+                .await;
             }
         }
         return Ok(());
     }
 
-    // 2. Handle Kotoba Bot Messages
-    if msg.author.id == KOTOBA_BOT_ID {
+    // 2. Handle Kotoba Bot Messages (or the guild's Kotoba-compatible bots)
+    if is_quiz_bot(data, msg.guild_id, msg.author.id) {
         handle_kotoba_message(ctx, msg, data).await?;
     }
 
     Ok(())
 }
 
+/// Handle edits to a k!quiz paste that was rejected earlier
+pub async fn handle_message_update(
+    ctx: &serenity::Context,
+    event: &serenity::MessageUpdateEvent,
+    data: &Data,
+) -> Result<(), Error> {
+    let (Some(author), Some(content)) = (&event.author, &event.content) else {
+        return Ok(());
+    };
+    if author.bot || !content.starts_with("k!quiz") {
+        return Ok(());
+    }
+
+    let waiting_for_command = data
+        .role_rank_sessions
+        .get(&author.id)
+        .is_some_and(|s| s.thread_id == event.channel_id && !s.started);
+    if !waiting_for_command {
+        return Ok(());
+    }
+
+    // Only messages we rejected before, and only when the text actually changed
+    let now = data.clock.now_instant();
+    if !COMMAND_VERDICTS.needs_recheck(event.id, content, now) {
+        return Ok(());
+    }
+
+    if let Some(verdict) =
+        answer_quiz_command(ctx, data, author.id, event.channel_id, event.id, content).await
+    {
+        COMMAND_VERDICTS.record(event.id, verdict, content, now);
+    }
+
+    Ok(())
+}
+
+/// Verdicts for k!quiz pastes, so an edited paste is re-checked exactly once per change
+static COMMAND_VERDICTS: Lazy<MessageVerdicts> =
+    Lazy::new(|| MessageVerdicts::new(std::time::Duration::from_secs(30 * 60)));
+
+/// Check a pasted k!quiz command against the author's current stage and answer it.
+/// Returns `None` when the message isn't in the author's quiz channel.
+async fn answer_quiz_command(
+    ctx: &serenity::Context,
+    data: &Data,
+    author_id: serenity::UserId,
+    channel_id: serenity::ChannelId,
+    message_id: serenity::MessageId,
+    content: &str,
+) -> Option<Verdict> {
+    // Check if this is an active session channel
+    // We need to find if this channel belongs to ANY active session for THIS user
+    let response;
+    let verdict;
+    let guild_id;
+
+    {
+        let mut session = data.role_rank_sessions.get_mut(&author_id)?;
+        if session.thread_id != channel_id {
+            return None;
+        }
+        session.touch(data.clock.now_utc());
+        guild_id = session.guild_id;
+        let quiz = QUIZZES.get(&session.quiz_id)?;
+
+        let expected_command = quiz.commands[session.progress];
+
+        if validate_command(content, expected_command) {
+            session.started = true;
+            session.active_attempt = true;
+            if session.attempt_started_at.is_none() {
+                session.attempt_started_at = Some(data.clock.now_utc());
+            }
+            verdict = Verdict::Accepted;
+            response = None;
+        } else {
+            session.active_attempt = false; // Invalidate previous attempt if any
+            verdict = Verdict::Rejected;
+            response = Some(expected_command);
+        }
+    }
+
+    // Looked up once the session is released
+    let config = match guild_id {
+        Some(guild_id) => get_guild_config(data, &guild_id.to_string()).await,
+        None => None,
+    };
+    let response = match response {
+        None => t(config.as_ref(), Msg::QuizCommandValid).to_string(),
+        Some(expected_command) => tf(
+            config.as_ref(),
+            Msg::QuizCommandMismatch,
+            &[&expected_command],
+        ),
+    };
+
+    persist_or_log(&data.firebase, &data.role_rank_sessions);
+
+    let report_to = mod_log_channel(data, guild_id).await;
+    if verdict == Verdict::Accepted {
+        let _ = say_checked(ctx, channel_id, response, report_to).await;
+    } else {
+        let reply = serenity::CreateMessage::new()
+            .content(response)
+            .reference_message((channel_id, message_id))
+            .allowed_mentions(serenity::CreateAllowedMentions::new().replied_user(false));
+        let _ = send_checked(ctx, channel_id, reply, report_to).await;
+    }
+
+    Some(verdict)
+}
+
 async fn handle_kotoba_message(
     ctx: &serenity::Context,
     msg: &serenity::Message,
@@ -818,6 +1634,12 @@ async fn handle_kotoba_message(
     if msg.embeds.is_empty() {
         return Ok(());
     }
+    let report_to = mod_log_channel(data, msg.guild_id).await;
+    let config = match msg.guild_id {
+        Some(guild_id) => get_guild_config(data, &guild_id.to_string()).await,
+        None => None,
+    };
+    let config = config.as_ref();
 
     for embed in &msg.embeds {
         // Check for "Congratulations!" in Title OR Description
@@ -858,11 +1680,31 @@ async fn handle_kotoba_message(
             }
         }
 
+        // Only the channel owner may finish their own quiz, and only a result naming them
+        // shows they did
+        let winners = parse_quiz_winners(embed.title.as_deref(), embed.description.as_deref());
+        if !owner_is_winner(ctx, msg.guild_id, user_id, &winners).await {
+            let reason = if winners.is_empty() {
+                warn!(
+                    "Could not identify the Kotoba quiz winner in channel {}, rejecting for session owner {}",
+                    msg.channel_id, user_id
+                );
+                Msg::QuizWinnerUnknown
+            } else {
+                Msg::QuizNotOwner
+            };
+            end_attempt(data, msg.guild_id, user_id, QuizOutcome::ValidationRejected);
+            let notice = tf(config, reason, &[&user_id]);
+            let _ = say_checked(ctx, msg.channel_id, notice, report_to).await;
+            return Ok(());
+        }
+
         let mut session = if let Some(s) = data.role_rank_sessions.get_mut(&user_id) {
             s
         } else {
             return Ok(());
         };
+        session.touch(data.clock.now_utc());
         let quiz = match QUIZZES.get(&session.quiz_id) {
             Some(q) => q,
             None => return Ok(()),
@@ -932,10 +1774,14 @@ async fn handle_kotoba_message(
             if !title_deck.contains(&expected_deck) || actual_score != expected_score {
                 // Double check if strict deck check is too strict or if title mismatch provided
                 if !score_limit_reached {
-                    let _ = msg.channel_id.say(&ctx.http,
-                        format!("⚠️ **Validasi Gagal**\nDeck atau Score tidak sesuai.\nExpected Deck: {}\nExpected Score: {}\nDetected Deck: {}\nDetected Score: {}",
-                        expected_deck, expected_score, title_deck, actual_score)
-                    ).await;
+                    drop(session);
+                    end_attempt(data, msg.guild_id, user_id, QuizOutcome::ValidationRejected);
+                    let notice = tf(
+                        config,
+                        Msg::QuizResultMismatch,
+                        &[&expected_deck, &expected_score, &title_deck, &actual_score],
+                    );
+                    let _ = say_checked(ctx, msg.channel_id, notice, report_to).await;
                     return Ok(());
                 }
             }
@@ -948,83 +1794,173 @@ async fn handle_kotoba_message(
             session.progress += 1;
             let next_cmd = quiz.commands[session.progress];
             drop(session);
-            persist_or_log(&data.role_rank_sessions);
+            persist_or_log(&data.firebase, &data.role_rank_sessions);
 
-            let _ = msg
-                .channel_id
-                .say(
-                    &ctx.http,
-                    format!(
-                        "Stage selesai! Lanjut ke tahap berikutnya:\n```{}```",
-                        next_cmd
-                    ),
-                )
-                .await;
+            let notice = tf(config, Msg::QuizStageComplete, &[&next_cmd]);
+            let _ = say_checked(ctx, msg.channel_id, notice, report_to).await;
         } else {
             // All stages complete!
             // Assign Role
             session.started = false; // Stop tracking
             session.active_attempt = false;
+            let (stage, started_at) = (session.progress, session.attempt_started_at.take());
             drop(session);
-            persist_or_log(&data.role_rank_sessions);
+            persist_or_log(&data.firebase, &data.role_rank_sessions);
 
             let guild_id = msg.guild_id.unwrap();
-            let member = guild_id.member(&ctx.http, user_id).await?;
-
-            // Check Current Roles (Prevent Downgrade/Duplicate)
-            // Implementation simplified: just add role and remove old ones if we implement exclusive logic later.
-            // For now, based on Go code:
+            let member = match guild_id.member(&ctx.http, user_id).await {
+                Ok(member) => Some(member),
+                // Left the server between the Kotoba result and now
+                Err(e) if is_unknown_member(&e) => None,
+                Err(e) => return Err(e.into()),
+            };
 
-            // Go code logic:
-            // 1. Get current level from owned roles.
-            // 2. If already same level -> Done.
-            // 3. If higher level -> "Downgrade not allowed".
-            // 4. Else -> Remove old role, Add new role.
+            if let Some(member) = member {
+                // Check Current Roles (Prevent Downgrade/Duplicate)
+                // Implementation simplified: just add role and remove old ones if we implement exclusive logic later.
+                // For now, based on Go code:
 
-            let current_level = get_current_quiz_level(&member);
+                // Go code logic:
+                // 1. Get current level from owned roles.
+                // 2. If already same level -> Done.
+                // 3. If higher level -> "Downgrade not allowed".
+                // 4. Else -> Remove old role, Add new role.
 
-            if current_level == quiz.level {
-                let _ = msg.channel_id.say(&ctx.http, format!("Kamu sudah memiliki role **{}**. Tidak ada perubahan.\nChannel akan dihapus dalam 30 detik.", quiz.label)).await;
-            } else if current_level > quiz.level {
-                let _ = msg.channel_id.say(&ctx.http, "Kamu sudah memiliki role tier lebih tinggi. Tidak bisa downgrade.\nChannel akan dihapus dalam 30 detik.").await;
-            } else {
-                // Remove old role (if any)
-                if current_level >= 0 {
-                    // find old role id
-                    for q in QUIZZES.values() {
-                        if q.level == current_level {
-                            let _ = member.remove_role(&ctx.http, q.role_id).await;
-                        }
-                    }
-                }
+                let current_level = highest_quiz_level(&member.roles, config);
+                let outcome = if current_level > quiz.level {
+                    QuizOutcome::DowngradeRefused
+                } else {
+                    QuizOutcome::Completed
+                };
+                quiz_stats::record(
+                    data.firebase.clone(),
+                    Some(guild_id),
+                    QuizEvent::new(
+                        quiz.value,
+                        user_id,
+                        stage,
+                        outcome,
+                        started_at,
+                        data.clock.now_utc(),
+                    ),
+                );
 
-                // Add new role
-                if let Err(e) = member.add_role(&ctx.http, quiz.role_id).await {
-                    error!("Failed to add role: {:?}", e);
-                    let _ = msg
-                        .channel_id
-                        .say(&ctx.http, "Gagal menambahkan role. Hubungi admin.")
-                        .await;
+                if current_level == quiz.level {
+                    let notice = tf(config, Msg::QuizRoleAlreadyHeld, &[&quiz.label]);
+                    let _ = say_checked(ctx, msg.channel_id, notice, report_to).await;
+                } else if current_level > quiz.level {
+                    let _ = say_checked(
+                        ctx,
+                        msg.channel_id,
+                        t(config, Msg::QuizNoDowngrade),
+                        report_to,
+                    )
+                    .await;
+                } else if bot_outranks(ctx, guild_id, quiz.role_in(config)).await == Some(false) {
+                    let notice = tf(config, Msg::QuizRoleBelowBot, &[&quiz.label]);
+                    let _ = say_checked(ctx, msg.channel_id, notice, report_to).await;
                 } else {
-                    let _ = msg.channel_id.say(&ctx.http, format!(
-                        "**SELAMAT**! Kamu sekarang mendapatkan role **{}**.\nChannel ini akan dihapus dalam 30 detik.",
-                        quiz.label
-                    )).await;
-
-                    // Announcement to public channel
-                    if let Some(cfg) =
-                        crate::utils::config::get_guild_config(data, &guild_id.to_string()).await
-                    {
-                        if let Some(annu_id) = &cfg.role_rank_announcement_channel_id {
-                            if let Ok(target_channel) = annu_id.parse::<serenity::ChannelId>() {
-                                let _ = target_channel.say(&ctx.http, format!(
-                                    "Selamat kepada <@{}> yang telah berhasil mendapatkan role **{}**!",
-                                    member.user.id, quiz.label
-                                )).await;
+                    // Remove every lower tier, a leftover one would make the level ambiguous
+                    let lower: Vec<_> = quizzes_by_level()
+                        .into_iter()
+                        .filter(|q| q.level < quiz.level)
+                        .collect();
+                    let (removals, result) =
+                        update_quiz_roles(&ctx.http, &member, &lower, Some(quiz), config).await;
+
+                    if let Err(e) = result {
+                        let failed: Vec<_> = removals
+                            .into_iter()
+                            .filter(|(_, outcome)| outcome.is_failure())
+                            .collect();
+                        let reported = !failed.is_empty()
+                            && report_old_role_failures(
+                                ctx, data, guild_id, user_id, &failed, config,
+                            )
+                            .await;
+                        let next_step = t(
+                            config,
+                            if reported {
+                                Msg::QuizAdminNotified
+                            } else {
+                                Msg::QuizContactAdmin
+                            },
+                        );
+                        let notice = tf(
+                            config,
+                            Msg::QuizRoleUpdateFailed,
+                            &[
+                                &role_changes_text(&member.roles, &lower, Some(quiz), config),
+                                &e,
+                                &next_step,
+                            ],
+                        );
+                        let _ = say_checked(ctx, msg.channel_id, notice, report_to).await;
+                    } else {
+                        let notice = tf(config, Msg::QuizPassed, &[&quiz.label]);
+                        let _ = say_checked(ctx, msg.channel_id, notice, report_to).await;
+
+                        let now = data.clock.now_utc();
+                        role_rank_audit::record(
+                            data.firebase.clone(),
+                            PromotionRecord::new(
+                                guild_id,
+                                user_id,
+                                current_level,
+                                quiz.level,
+                                msg.channel_id,
+                                now,
+                            ),
+                        );
+
+                        // Announcement to public channel
+                        if let Some(cfg) = config {
+                            if let Some(annu_id) = &cfg.role_rank_announcement_channel_id {
+                                if let Ok(target_channel) = annu_id.parse::<serenity::ChannelId>() {
+                                    let embed = promotion_embed(
+                                        &member.user,
+                                        current_level,
+                                        quiz,
+                                        now,
+                                        Some(cfg),
+                                    );
+                                    let _ = send_checked(
+                                        ctx,
+                                        target_channel,
+                                        serenity::CreateMessage::new().embed(embed),
+                                        report_to,
+                                    )
+                                    .await;
+                                }
                             }
                         }
                     }
                 }
+            } else {
+                quiz_stats::record(
+                    data.firebase.clone(),
+                    Some(guild_id),
+                    QuizEvent::new(
+                        quiz.value,
+                        user_id,
+                        stage,
+                        QuizOutcome::Completed,
+                        started_at,
+                        data.clock.now_utc(),
+                    ),
+                );
+                record_unclaimed_completion(UnclaimedCompletion {
+                    guild_id,
+                    user_id,
+                    quiz_id: quiz.value.to_string(),
+                    completed_at: data.clock.now_utc(),
+                });
+                let notice = tf(
+                    config,
+                    Msg::QuizMemberLeft,
+                    &[&user_id, &quiz.label, &UNCLAIMED_COMPLETION_DAYS],
+                );
+                let _ = say_checked(ctx, msg.channel_id, notice, report_to).await;
             }
 
             // Cleanup
@@ -1032,12 +1968,18 @@ async fn handle_kotoba_message(
             let channel_id = msg.channel_id;
             let u_id = user_id;
             let sessions = data.role_rank_sessions.clone();
-
-            tokio::spawn(async move {
-                tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+            let firebase = data.firebase.clone();
+            let shutdown = data.shutdown.clone();
+
+            data.shutdown.spawn(async move {
+                // On shutdown the session stays saved and the expiry sweep removes the
+                // channel after the restart
+                if !shutdown.sleep(std::time::Duration::from_secs(30)).await {
+                    return;
+                }
                 let _ = channel_id.delete(&http).await;
                 sessions.remove(&u_id);
-                persist_or_log(&sessions);
+                persist_or_log(&firebase, &sessions);
             });
         }
     }
@@ -1045,22 +1987,1510 @@ async fn handle_kotoba_message(
     Ok(())
 }
 
-fn get_current_quiz_level(member: &serenity::Member) -> i32 {
-    for role_id in &member.roles {
-        for quiz in QUIZZES.values() {
-            if role_id == &quiz.role_id {
-                return quiz.level;
+/// Record a failed attempt for the session's current stage and start the clock over
+fn end_attempt(
+    data: &Data,
+    guild_id: Option<serenity::GuildId>,
+    user_id: serenity::UserId,
+    outcome: QuizOutcome,
+) {
+    let event = {
+        let Some(mut session) = data.role_rank_sessions.get_mut(&user_id) else {
+            return;
+        };
+        session.touch(data.clock.now_utc());
+        let started_at = session.attempt_started_at.take();
+        QuizEvent::new(
+            &session.quiz_id,
+            user_id,
+            session.progress,
+            outcome,
+            started_at,
+            data.clock.now_utc(),
+        )
+    };
+    persist_or_log(&data.firebase, &data.role_rank_sessions);
+    quiz_stats::record(data.firebase.clone(), guild_id, event);
+}
+
+/// Label of a quiz level, for announcements and the promotion history
+pub fn level_label(level: i32) -> &'static str {
+    QUIZZES
+        .values()
+        .find(|q| q.level == level)
+        .map_or("No quiz role", |q| q.label)
+}
+
+/// Decks of every stage of the quiz, as written in its commands
+fn quiz_decks(quiz: &QuizInfo) -> String {
+    quiz.commands
+        .iter()
+        .filter_map(|c| parse_quiz_command(c).map(|c| c.deck))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Public announcement of a promotion
+fn promotion_embed(
+    user: &serenity::User,
+    from_level: i32,
+    quiz: &QuizInfo,
+    at: chrono::DateTime<chrono::Utc>,
+    config: Option<&GuildConfig>,
+) -> serenity::CreateEmbed {
+    serenity::CreateEmbed::new()
+        .title(t(config, Msg::PromotionTitle))
+        .description(tf(
+            config,
+            Msg::PromotionDescription,
+            &[&user.id, &quiz.label],
+        ))
+        .field(
+            "Level",
+            format!("{} → **{}**", level_label(from_level), quiz.label),
+            false,
+        )
+        .field("Deck", format!("`{}`", quiz_decks(quiz)), false)
+        .thumbnail(user.face())
+        .timestamp(serenity::Timestamp::from(at))
+        .color(crate::utils::config::colors::SUCCESS)
+}
+
+/// Who Kotoba says finished the quiz
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum QuizWinner {
+    Mention(serenity::UserId),
+    Name(String),
+}
+
+/// Extract the winners from a Kotoba congratulation embed.
+/// Title format: "The score limit of <SCORE> was reached by <USER>[ and <USER>]. Congratulations!"
+/// Description-only congratulations are matched by their user mentions.
+fn parse_quiz_winners(title: Option<&str>, description: Option<&str>) -> Vec<QuizWinner> {
+    let mut winners = Vec::new();
+
+    if let Some(rest) = title.and_then(|t| t.split_once("was reached by ").map(|(_, r)| r)) {
+        let names = rest
+            .split("Congratulations!")
+            .next()
+            .unwrap_or("")
+            .trim()
+            .trim_end_matches('.');
+
+        for name in names.split(" and ").flat_map(|part| part.split(", ")) {
+            let name = name.trim();
+            if name.is_empty() {
+                continue;
+            }
+            match parse_mentions(name).first() {
+                Some(id) => winners.push(QuizWinner::Mention(*id)),
+                None => winners.push(QuizWinner::Name(name.to_string())),
+            }
+        }
+    }
+
+    if let Some(desc) = description {
+        for id in parse_mentions(desc) {
+            let winner = QuizWinner::Mention(id);
+            if !winners.contains(&winner) {
+                winners.push(winner);
+            }
+        }
+    }
+
+    winners
+}
+
+/// User ids from `<@123>` / `<@!123>` mentions in a string
+fn parse_mentions(text: &str) -> Vec<serenity::UserId> {
+    let mut ids = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("<@") {
+        rest = &rest[start + 2..];
+        let body = rest.strip_prefix('!').unwrap_or(rest);
+        let Some(end) = body.find('>') else {
+            break;
+        };
+        if let Ok(id) = body[..end].parse::<u64>() {
+            if id != 0 {
+                ids.push(serenity::UserId::new(id));
+            }
+        }
+    }
+    ids
+}
+
+/// Whether any winner is the session owner, by mention or by one of their names
+fn winner_matches(
+    winners: &[QuizWinner],
+    owner_id: serenity::UserId,
+    owner_names: &[String],
+) -> bool {
+    winners.iter().any(|w| match w {
+        QuizWinner::Mention(id) => *id == owner_id,
+        QuizWinner::Name(name) => owner_names.iter().any(|n| n.eq_ignore_ascii_case(name)),
+    })
+}
+
+async fn owner_is_winner(
+    ctx: &serenity::Context,
+    guild_id: Option<serenity::GuildId>,
+    owner_id: serenity::UserId,
+    winners: &[QuizWinner],
+) -> bool {
+    if winners.is_empty() {
+        return false;
+    }
+    if winner_matches(winners, owner_id, &[]) {
+        return true;
+    }
+
+    // Name-only winners need the owner's username, global name and nickname
+    let mut names = Vec::new();
+    if let Some(guild_id) = guild_id {
+        if let Ok(member) = guild_id.member(&ctx.http, owner_id).await {
+            names.push(member.user.name.clone());
+            names.extend(member.user.global_name.clone());
+            names.extend(member.nick.clone());
+        }
+    }
+    winner_matches(winners, owner_id, &names)
+}
+
+// --- Unclaimed Completions ---
+
+/// Days a completion stays claimable after the user left the server
+const UNCLAIMED_COMPLETION_DAYS: i64 = 7;
+const UNCLAIMED_STORE_PATH: &str = "data/role_rank_unclaimed.json";
+
+/// A quiz finished by someone who left before the role could be given
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct UnclaimedCompletion {
+    guild_id: serenity::GuildId,
+    user_id: serenity::UserId,
+    quiz_id: String,
+    completed_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl UnclaimedCompletion {
+    fn is_expired(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        now - self.completed_at > chrono::Duration::days(UNCLAIMED_COMPLETION_DAYS)
+    }
+}
+
+static UNCLAIMED_COMPLETIONS: Lazy<std::sync::Mutex<Vec<UnclaimedCompletion>>> =
+    Lazy::new(|| std::sync::Mutex::new(load_unclaimed_completions()));
+
+fn load_unclaimed_completions() -> Vec<UnclaimedCompletion> {
+    match std::fs::read_to_string(UNCLAIMED_STORE_PATH) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            error!("Failed to parse unclaimed role rank completions: {:?}", e);
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn persist_unclaimed_completions(completions: &[UnclaimedCompletion]) {
+    let result = (|| -> Result<(), anyhow::Error> {
+        let path = std::path::Path::new(UNCLAIMED_STORE_PATH);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(completions)?)?;
+        Ok(())
+    })();
+    if let Err(e) = result {
+        error!("Failed to persist unclaimed role rank completions: {:?}", e);
+    }
+}
+
+fn record_unclaimed_completion(completion: UnclaimedCompletion) {
+    let mut completions = UNCLAIMED_COMPLETIONS.lock().unwrap();
+    // A newer completion replaces an older one for the same member
+    completions.retain(|c| !(c.guild_id == completion.guild_id && c.user_id == completion.user_id));
+    completions.push(completion);
+    persist_unclaimed_completions(&completions);
+}
+
+/// Put back a completion whose role couldn't be given, unless a newer one took its place
+fn return_unclaimed_completion(completion: UnclaimedCompletion) {
+    let mut completions = UNCLAIMED_COMPLETIONS.lock().unwrap();
+    if completions
+        .iter()
+        .any(|c| c.guild_id == completion.guild_id && c.user_id == completion.user_id)
+    {
+        return;
+    }
+    completions.push(completion);
+    persist_unclaimed_completions(&completions);
+}
+
+/// Remove and return the member's completion if it is still claimable, dropping expired ones
+fn take_unclaimed_completion(
+    completions: &mut Vec<UnclaimedCompletion>,
+    guild_id: serenity::GuildId,
+    user_id: serenity::UserId,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Option<UnclaimedCompletion> {
+    completions.retain(|c| !c.is_expired(now));
+    let pos = completions
+        .iter()
+        .position(|c| c.guild_id == guild_id && c.user_id == user_id)?;
+    Some(completions.remove(pos))
+}
+
+/// Unknown Member (10007)
+fn is_unknown_member(e: &serenity::Error) -> bool {
+    match e {
+        serenity::Error::Http(serenity::http::HttpError::UnsuccessfulRequest(resp)) => {
+            resp.status_code.as_u16() == 404 || resp.error.code == 10007
+        }
+        _ => false,
+    }
+}
+
+/// Most members `a!clear` takes at once, one embed field each
+const MAX_CLEAR_TARGETS: usize = 10;
+
+/// What happened to one quiz role of a member
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RoleRemoval {
+    Removed,
+    NotHeld,
+    /// Missing Permissions (50013), usually the role sits above the bot's highest role
+    MissingPermissions,
+    Failed(String),
+}
+
+impl RoleRemoval {
+    pub fn is_failure(&self) -> bool {
+        matches!(self, Self::MissingPermissions | Self::Failed(_))
+    }
+
+    pub fn status(&self) -> String {
+        match self {
+            Self::Removed => "✅ Removed".to_string(),
+            Self::NotHeld => "➖ Not held".to_string(),
+            Self::MissingPermissions => "⛔ Missing permissions".to_string(),
+            Self::Failed(reason) => {
+                format!(
+                    "❌ Failed: {}",
+                    reason.chars().take(100).collect::<String>()
+                )
             }
         }
     }
-    -1
 }
 
+/// Outcome of a removal, `None` when the member didn't hold the role
+pub fn classify_removal(result: Option<&serenity::Result<()>>) -> RoleRemoval {
+    match result {
+        None => RoleRemoval::NotHeld,
+        Some(Ok(())) => RoleRemoval::Removed,
+        Some(Err(e)) if is_missing_permissions(e) => RoleRemoval::MissingPermissions,
+        Some(Err(e)) => RoleRemoval::Failed(e.to_string()),
+    }
+}
+
+fn is_missing_permissions(e: &serenity::Error) -> bool {
+    match e {
+        serenity::Error::Http(serenity::http::HttpError::UnsuccessfulRequest(resp)) => {
+            is_missing_permissions_response(resp.status_code.as_u16(), resp.error.code)
+        }
+        // Refused by serenity's own cache checks before the request went out
+        serenity::Error::Model(
+            serenity::ModelError::Hierarchy | serenity::ModelError::InvalidPermissions { .. },
+        ) => true,
+        _ => false,
+    }
+}
+
+/// Forbidden (403) or Missing Permissions (50013)
+fn is_missing_permissions_response(status: u16, code: isize) -> bool {
+    status == 403 || code == 50013
+}
+
+/// Remove each of `quizzes` the member holds, reporting every role
+pub async fn remove_quiz_roles<'a>(
+    http: &serenity::Http,
+    member: &serenity::Member,
+    quizzes: &[&'a QuizInfo],
+    config: Option<&GuildConfig>,
+) -> Vec<(&'a QuizInfo, RoleRemoval)> {
+    update_quiz_roles(http, member, quizzes, None, config)
+        .await
+        .0
+}
+
+/// Remove each of `remove` the member holds and give them `add`, in one edit so a rate limit
+/// can't leave them half changed. Reports every removed role and the edit's result, `Ok`
+/// without a request when there was nothing to change.
+pub async fn update_quiz_roles<'a>(
+    http: &serenity::Http,
+    member: &serenity::Member,
+    remove: &[&'a QuizInfo],
+    add: Option<&QuizInfo>,
+    config: Option<&GuildConfig>,
+) -> (Vec<(&'a QuizInfo, RoleRemoval)>, serenity::Result<()>) {
+    let remove_ids: Vec<_> = remove.iter().map(|q| q.role_in(config)).collect();
+    let roles = role_set_after(&member.roles, &remove_ids, add.map(|q| q.role_in(config)));
+    let result = if roles == member.roles {
+        Ok(())
+    } else {
+        member
+            .guild_id
+            .edit_member(
+                http,
+                member.user.id,
+                serenity::EditMember::new().roles(roles),
+            )
+            .await
+            .map(|_| ())
+    };
+    if let Err(e) = &result {
+        error!(
+            "Failed to update roles of user {} ({}): {:?}",
+            member.user.id,
+            role_changes_text(&member.roles, remove, add, config),
+            e
+        );
+    }
+
+    let outcomes = remove
+        .iter()
+        .zip(&remove_ids)
+        .map(|(&quiz, role_id)| {
+            let held = member.roles.contains(role_id);
+            (quiz, classify_removal(held.then_some(&result)))
+        })
+        .collect();
+    (outcomes, result)
+}
+
+/// The member's roles without `remove` and with `add`, in their original order
+fn role_set_after(
+    current: &[serenity::RoleId],
+    remove: &[serenity::RoleId],
+    add: Option<serenity::RoleId>,
+) -> Vec<serenity::RoleId> {
+    let mut roles: Vec<_> = current
+        .iter()
+        .filter(|role| !remove.contains(role) || Some(**role) == add)
+        .copied()
+        .collect();
+    if let Some(add) = add.filter(|add| !roles.contains(add)) {
+        roles.push(add);
+    }
+    roles
+}
+
+/// What an edit changes, "+Senpai, −Kouhai" for the roles actually added or removed.
+/// Labels rather than mentions, so reporting it pings nobody.
+fn role_changes_text(
+    current: &[serenity::RoleId],
+    remove: &[&QuizInfo],
+    add: Option<&QuizInfo>,
+    config: Option<&GuildConfig>,
+) -> String {
+    let add_id = add.map(|q| q.role_in(config));
+    let added = add
+        .filter(|q| !current.contains(&q.role_in(config)))
+        .map(|q| format!("+{}", q.label));
+    let removed = remove
+        .iter()
+        .filter(|q| {
+            let role_id = q.role_in(config);
+            current.contains(&role_id) && Some(role_id) != add_id
+        })
+        .map(|q| format!("−{}", q.label));
+    added
+        .into_iter()
+        .chain(removed)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Whether the bot's highest role is above `role_id`, so Discord lets it hand the role out.
+/// `None` when the guild isn't cached.
+async fn bot_outranks(
+    ctx: &serenity::Context,
+    guild_id: serenity::GuildId,
+    role_id: serenity::RoleId,
+) -> Option<bool> {
+    let bot = guild_id.current_user_member(&ctx.http).await.ok()?;
+    let guild = ctx.cache.guild(guild_id)?;
+    let target = guild.roles.get(&role_id)?;
+    let outranks = guild
+        .member_highest_role(&bot)
+        .is_some_and(|highest| highest.position > target.position);
+    Some(outranks)
+}
+
+/// Quiz roles lowest tier first
+pub fn quizzes_by_level() -> Vec<&'static QuizInfo> {
+    let mut quizzes: Vec<_> = QUIZZES.values().collect();
+    quizzes.sort_by_key(|q| q.level);
+    quizzes
+}
+
+/// Users from mentions (`<@id>`, `<@!id>`) or raw ids, without repeats, plus what didn't parse
+pub fn parse_user_targets(args: &[&str]) -> (Vec<serenity::UserId>, Vec<String>) {
+    let mut users = Vec::new();
+    let mut invalid = Vec::new();
+    for arg in args {
+        let id = arg
+            .strip_prefix("<@")
+            .and_then(|rest| rest.strip_suffix('>'))
+            .map(|rest| rest.trim_start_matches('!'))
+            .unwrap_or(arg);
+        match id.parse::<u64>() {
+            Ok(id) if id != 0 => {
+                let user = serenity::UserId::new(id);
+                if !users.contains(&user) {
+                    users.push(user);
+                }
+            }
+            _ => invalid.push(arg.to_string()),
+        }
+    }
+    (users, invalid)
+}
+
+/// Where the bot's highest role sits next to `role_id`, so admins can fix the hierarchy
+pub async fn hierarchy_hint(
+    ctx: &serenity::Context,
+    guild_id: serenity::GuildId,
+    role_id: serenity::RoleId,
+) -> Option<String> {
+    let bot = guild_id.current_user_member(&ctx.http).await.ok()?;
+    let guild = ctx.cache.guild(guild_id)?;
+    let target_position = guild.roles.get(&role_id)?.position;
+    let bot_role = match guild.member_highest_role(&bot) {
+        Some(role) => format!("<@&{}> (position {})", role.id, role.position),
+        None => "@everyone (position 0)".to_string(),
+    };
+    Some(format!(
+        "My highest role is {}, <@&{}> is at position {}. Move my role above the quiz roles in Server Settings → Roles.",
+        bot_role, role_id, target_position
+    ))
+}
+
+/// The guild's mod log channel, where failures nobody else would see are reported
+async fn mod_log_channel(
+    data: &Data,
+    guild_id: Option<serenity::GuildId>,
+) -> Option<serenity::ChannelId> {
+    crate::utils::config::get_guild_config(data, &guild_id?.to_string())
+        .await?
+        .mod_log_channel_id?
+        .parse()
+        .ok()
+}
+
+/// Post old tier roles that couldn't be removed to the mod log, `false` without one
+async fn report_old_role_failures(
+    ctx: &serenity::Context,
+    data: &Data,
+    guild_id: serenity::GuildId,
+    user_id: serenity::UserId,
+    failed: &[(&QuizInfo, RoleRemoval)],
+    config: Option<&GuildConfig>,
+) -> bool {
+    let Some(channel_id) = mod_log_channel(data, Some(guild_id)).await else {
+        return false;
+    };
+
+    let mut lines: Vec<String> = failed
+        .iter()
+        .map(|(quiz, outcome)| format!("<@&{}> {}", quiz.role_in(config), outcome.status()))
+        .collect();
+    let refused = failed
+        .iter()
+        .find(|(_, outcome)| *outcome == RoleRemoval::MissingPermissions);
+    if let Some((quiz, _)) = refused {
+        if let Some(hint) = hierarchy_hint(ctx, guild_id, quiz.role_in(config)).await {
+            lines.push(String::new());
+            lines.push(hint);
+        }
+    }
+
+    let embed = serenity::CreateEmbed::new()
+        .title("Old Quiz Role Not Removed")
+        .description(format!(
+            "<@{}> passed a quiz but still holds a lower tier role.\n\n{}",
+            user_id,
+            lines.join("\n")
+        ))
+        .color(crate::utils::config::colors::WARNING);
+    match channel_id
+        .send_message(&ctx.http, serenity::CreateMessage::new().embed(embed))
+        .await
+    {
+        Ok(_) => true,
+        Err(e) => {
+            warn!("Failed to post to mod log {}: {:?}", channel_id, e);
+            false
+        }
+    }
+}
+
+/// Give back the role for a quiz finished shortly before the member left
+pub async fn handle_member_addition(
+    ctx: &serenity::Context,
+    member: &serenity::Member,
+    data: &Data,
+) -> Result<(), Error> {
+    let completion = {
+        let mut completions = UNCLAIMED_COMPLETIONS.lock().unwrap();
+        let before = completions.len();
+        let taken = take_unclaimed_completion(
+            &mut completions,
+            member.guild_id,
+            member.user.id,
+            data.clock.now_utc(),
+        );
+        if completions.len() != before {
+            persist_unclaimed_completions(&completions);
+        }
+        taken
+    };
+
+    let Some(completion) = completion else {
+        return Ok(());
+    };
+    let Some(quiz) = QUIZZES.get(&completion.quiz_id) else {
+        return Ok(());
+    };
+
+    let config = get_guild_config(data, &member.guild_id.to_string()).await;
+    if let Err(e) = member
+        .add_role(&ctx.http, quiz.role_in(config.as_ref()))
+        .await
+    {
+        // Still claimable the next time they join
+        return_unclaimed_completion(completion);
+        return Err(e.into());
+    }
+    info!(
+        "Restored role rank {} for returning member {}",
+        quiz.label, member.user.id
+    );
+
+    let message = tf(
+        config.as_ref(),
+        Msg::QuizRoleRestored,
+        &[&member.user.id, &quiz.label],
+    );
+    let announcement_channel = config
+        .and_then(|cfg| cfg.role_rank_announcement_channel_id)
+        .and_then(|id| id.parse::<serenity::ChannelId>().ok());
+
+    match announcement_channel {
+        Some(channel_id) => {
+            let report_to = mod_log_channel(data, Some(member.guild_id)).await;
+            let _ = say_checked(ctx, channel_id, message, report_to).await;
+        }
+        None => {
+            let _ = member
+                .user
+                .direct_message(&ctx.http, serenity::CreateMessage::new().content(message))
+                .await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Highest quiz level among the roles, -1 without a quiz role.
+/// Members from the old bot sometimes hold several quiz roles, the highest one counts.
+pub fn highest_quiz_level(roles: &[serenity::RoleId], config: Option<&GuildConfig>) -> i32 {
+    roles
+        .iter()
+        .filter_map(|role_id| QUIZZES.values().find(|q| q.role_in(config) == *role_id))
+        .map(|q| q.level)
+        .max()
+        .unwrap_or(-1)
+}
+
+/// The quiz to take after reaching `level`, `None` at the top level
+pub fn next_quiz(level: i32) -> Option<&'static QuizInfo> {
+    QUIZZES
+        .values()
+        .filter(|q| q.level > level)
+        .min_by_key(|q| q.level)
+}
+
+/// Words a jpdb deck covers up to, from its name: 300 for "jpdb300", 1000 for
+/// "jpdb300to1k", 3000 for "jpdb1k3k"
+fn jpdb_deck_words(deck: &str) -> Option<u64> {
+    let range = deck.strip_prefix("jpdb")?;
+    let mut upper = None;
+    let mut number: Option<u64> = None;
+    for c in range.chars().chain(std::iter::once(' ')) {
+        match (c.to_digit(10), number) {
+            (Some(d), n) => number = Some(n.unwrap_or(0) * 10 + u64::from(d)),
+            (None, Some(n)) => {
+                upper = Some(if c == 'k' { n * 1000 } else { n });
+                number = None;
+            }
+            (None, None) => {}
+        }
+    }
+    upper
+}
+
+/// Words known on jpdb once a quiz's deck range is covered. Multi-deck quizzes go by the
+/// decks their Kotoba command names.
+fn quiz_jpdb_words(quiz: &QuizInfo) -> Option<u64> {
+    let command_decks = quiz
+        .commands
+        .iter()
+        .filter_map(|c| c.split_whitespace().nth(1))
+        .flat_map(|decks| decks.split('+'));
+    quiz.deck_names
+        .iter()
+        .copied()
+        .chain(command_decks)
+        .find_map(jpdb_deck_words)
+}
+
+/// The hardest quiz whose jpdb deck range `known_words` covers, `None` below the first deck
+pub fn suggested_quiz(known_words: u64) -> Option<&'static QuizInfo> {
+    QUIZZES
+        .values()
+        .filter(|q| quiz_jpdb_words(q).is_some_and(|words| known_words >= words))
+        .max_by_key(|q| q.level)
+}
+
+/// Point a linked jpdb member at the quiz their known words suggest, when they picked another
+async fn suggest_from_jpdb(
+    ctx: &serenity::Context,
+    interaction: &serenity::ComponentInteraction,
+    data: &Data,
+    config: Option<&GuildConfig>,
+    quiz: &QuizInfo,
+) {
+    let user_id = interaction.user.id.to_string();
+    let Ok(Some(user_doc)) = data.firebase.get_user(&user_id).await else {
+        return;
+    };
+    let Some(api_key) = user_doc.jpdb_api_key() else {
+        return;
+    };
+    let known = match jpdb::known_words(&data.http_client, &user_id, api_key).await {
+        Ok(Some(known)) => known,
+        Ok(None) => return,
+        Err(e) => {
+            warn!("Failed to fetch jpdb known words: {:?}", e);
+            return;
+        }
+    };
+    let Some(suggested) = suggested_quiz(known).filter(|q| q.level != quiz.level) else {
+        return;
+    };
+    let _ = interaction
+        .create_followup(
+            ctx,
+            serenity::CreateInteractionResponseFollowup::new()
+                .content(tf(
+                    config,
+                    Msg::QuizJpdbSuggestion,
+                    &[&format_number(known as i64), &suggested.label],
+                ))
+                .ephemeral(true),
+        )
+        .await;
+}
+
+/// Members per highest quiz level (-1 for members without a quiz role)
+pub fn count_levels<'a>(
+    members: impl IntoIterator<Item = &'a [serenity::RoleId]>,
+    config: Option<&GuildConfig>,
+) -> BTreeMap<i32, usize> {
+    let mut counts = BTreeMap::new();
+    for roles in members {
+        *counts.entry(highest_quiz_level(roles, config)).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// How long guild level counts are reused before refetching the member list
+const LEVEL_COUNTS_TTL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+/// Members fetched per request, Discord's maximum
+const MEMBER_PAGE_SIZE: u64 = 1000;
+
+/// Members per level and when they were counted
+type LevelCounts = (std::time::Instant, BTreeMap<i32, usize>);
+
+static LEVEL_COUNTS: Lazy<DashMap<serenity::GuildId, LevelCounts>> = Lazy::new(DashMap::new);
+
+/// Members per level in a guild, cached for `LEVEL_COUNTS_TTL` since big guilds take many requests
+pub async fn guild_level_counts(
+    http: &serenity::Http,
+    data: &Data,
+    guild_id: serenity::GuildId,
+) -> Result<BTreeMap<i32, usize>, Error> {
+    let now = data.clock.now_instant();
+    if let Some(cached) = LEVEL_COUNTS.get(&guild_id) {
+        if now.duration_since(cached.0) < LEVEL_COUNTS_TTL {
+            return Ok(cached.1.clone());
+        }
+    }
+
+    let config = get_guild_config(data, &guild_id.to_string()).await;
+    let mut counts = BTreeMap::new();
+    let mut after = None;
+    loop {
+        let page = guild_id
+            .members(http, Some(MEMBER_PAGE_SIZE), after)
+            .await?;
+        for (level, n) in count_levels(page.iter().map(|m| m.roles.as_slice()), config.as_ref()) {
+            *counts.entry(level).or_insert(0) += n;
+        }
+        if (page.len() as u64) < MEMBER_PAGE_SIZE {
+            break;
+        }
+        after = page.last().map(|m| m.user.id);
+    }
+
+    LEVEL_COUNTS.insert(guild_id, (now, counts.clone()));
+    Ok(counts)
+}
+
+/// A k!quiz command split into the parts Kotoba distinguishes
+#[derive(Debug, PartialEq)]
+struct QuizCommand<'a> {
+    /// First argument, decks joined with `+`
+    deck: &'a str,
+    score_limit: Option<u32>,
+    /// Bare words such as `hardcore` and `nd`
+    flags: HashSet<String>,
+    /// `key=value` parameters, keys lowercased
+    params: HashMap<String, &'a str>,
+}
+
+/// Split a k!quiz command on whitespace. `None` when it isn't one or repeats a
+/// score limit or parameter, which would make its meaning ambiguous.
+fn parse_quiz_command(input: &str) -> Option<QuizCommand<'_>> {
+    let mut tokens = input.split_whitespace();
+    if !tokens.next()?.eq_ignore_ascii_case("k!quiz") {
+        return None;
+    }
+    let mut command = QuizCommand {
+        deck: tokens.next()?,
+        score_limit: None,
+        flags: HashSet::new(),
+        params: HashMap::new(),
+    };
+    for token in tokens {
+        if let Some((key, value)) = token.split_once('=') {
+            if command
+                .params
+                .insert(key.to_ascii_lowercase(), value)
+                .is_some()
+            {
+                return None;
+            }
+        } else if let Ok(limit) = token.parse::<u32>() {
+            if command.score_limit.replace(limit).is_some() {
+                return None;
+            }
+        } else if !command.flags.insert(token.to_ascii_lowercase()) {
+            return None;
+        }
+    }
+    Some(command)
+}
+
+/// Whether a pasted command runs the same quiz as the expected one. Whitespace and the
+/// order of parameters don't matter, the decks, score limit and flags must be identical.
 fn validate_command(user_input: &str, expected: &str) -> bool {
-    let u = user_input.trim();
-    let e = expected.trim();
+    match (parse_quiz_command(user_input), parse_quiz_command(expected)) {
+        (Some(u), Some(e)) => u == e,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session_created_at(created_at: &str) -> QuizSession {
+        QuizSession {
+            user_id: serenity::UserId::new(1),
+            quiz_id: "n5".to_string(),
+            thread_id: serenity::ChannelId::new(2),
+            started: false,
+            active_attempt: false,
+            progress: 0,
+            created_at: at(created_at),
+            last_activity_at: at(created_at),
+            expiry_warned: false,
+            category_id: None,
+            guild_id: None,
+            attempt_started_at: None,
+        }
+    }
+
+    fn at(ts: &str) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::parse_from_rfc3339(ts)
+            .unwrap()
+            .with_timezone(&chrono::Utc)
+    }
+
+    fn quiz_role(quiz_id: &str) -> serenity::RoleId {
+        QUIZZES[quiz_id].default_role_id
+    }
+
+    #[test]
+    fn test_highest_quiz_level_wins() {
+        let other_role = serenity::RoleId::new(42);
+        assert_eq!(highest_quiz_level(&[], None), -1);
+        assert_eq!(highest_quiz_level(&[other_role], None), -1);
+
+        let level_0 = quiz_role("hiragana_katakana");
+        let top = QUIZZES.values().max_by_key(|q| q.level).unwrap();
+        assert_eq!(highest_quiz_level(&[level_0, other_role], None), 0);
+        // Leftover roles from the old bot: order doesn't matter, the highest counts
+        assert_eq!(
+            highest_quiz_level(&[top.default_role_id, level_0], None),
+            top.level
+        );
+        assert_eq!(
+            highest_quiz_level(&[level_0, top.default_role_id], None),
+            top.level
+        );
+    }
+
+    #[test]
+    fn test_next_quiz() {
+        assert_eq!(next_quiz(-1).map(|q| q.level), Some(0));
+        assert_eq!(next_quiz(0).map(|q| q.level), Some(1));
+        let top = QUIZZES.values().map(|q| q.level).max().unwrap();
+        assert!(next_quiz(top).is_none());
+    }
+
+    #[test]
+    fn test_suggested_quiz_follows_jpdb_ranges() {
+        let level = |words| suggested_quiz(words).map(|q| q.value);
+        assert_eq!(level(120), None);
+        assert_eq!(level(300), Some("Level_1"));
+        assert_eq!(level(2999), Some("Level_2"));
+        assert_eq!(level(5000), Some("Level_4"));
+        assert_eq!(level(25_000), Some("Level_6"));
+        assert_eq!(level(40_000), Some("Level_7"));
+    }
+
+    #[test]
+    fn test_jpdb_deck_words() {
+        assert_eq!(jpdb_deck_words("jpdb300"), Some(300));
+        assert_eq!(jpdb_deck_words("jpdb300to1k"), Some(1000));
+        assert_eq!(jpdb_deck_words("jpdb10k20k"), Some(20_000));
+        assert_eq!(jpdb_deck_words("JLPT N1 Grammar Quiz"), None);
+        // Multi-deck quizzes read the range off their command
+        assert_eq!(quiz_jpdb_words(&QUIZZES["Level_7"]), Some(30_000));
+        assert_eq!(quiz_jpdb_words(&QUIZZES["hiragana_katakana"]), None);
+    }
+
+    #[test]
+    fn test_count_levels_counts_each_member_once() {
+        let level_0 = quiz_role("hiragana_katakana");
+        let level_1 = next_quiz(0).unwrap().default_role_id;
+        let members: Vec<Vec<serenity::RoleId>> = vec![
+            vec![],
+            vec![level_0],
+            vec![level_0, level_1],
+            vec![level_1, serenity::RoleId::new(42)],
+        ];
+        let counts = count_levels(members.iter().map(|r| r.as_slice()), None);
+        assert_eq!(counts, BTreeMap::from([(-1, 1), (0, 1), (1, 2)]));
+    }
+
+    #[test]
+    fn test_quiz_roles_per_guild() {
+        let level_0 = &QUIZZES["hiragana_katakana"];
+        let level_1 = next_quiz(0).unwrap();
+        assert_eq!(level_0.role_in(None), level_0.default_role_id);
+
+        let config = GuildConfig {
+            quiz_role_ids: Some(HashMap::from([("0".to_string(), "42".to_string())])),
+            ..Default::default()
+        };
+        let own_role = serenity::RoleId::new(42);
+        assert_eq!(level_0.role_in(Some(&config)), own_role);
+        // Levels the map leaves out keep our roles
+        assert_eq!(level_1.role_in(Some(&config)), level_1.default_role_id);
+
+        assert_eq!(highest_quiz_level(&[own_role], Some(&config)), 0);
+        assert_eq!(highest_quiz_level(&[own_role], None), -1);
+        assert_eq!(
+            highest_quiz_level(&[level_0.default_role_id], Some(&config)),
+            -1
+        );
+    }
+
+    #[test]
+    fn test_quiz_bots_default_to_kotoba() {
+        assert_eq!(configured_quiz_bots(None), vec![KOTOBA_BOT_ID]);
+        assert_eq!(
+            configured_quiz_bots(Some(&GuildConfig::default())),
+            vec![KOTOBA_BOT_ID]
+        );
+
+        // Nothing usable configured falls back too
+        let invalid = GuildConfig {
+            quiz_bot_ids: vec!["not-an-id".to_string(), "0".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(configured_quiz_bots(Some(&invalid)), vec![KOTOBA_BOT_ID]);
+
+        // A configured list replaces Kotoba, duplicates and junk are dropped
+        let custom = GuildConfig {
+            quiz_bot_ids: vec![
+                "1001".to_string(),
+                " 1002 ".to_string(),
+                "1001".to_string(),
+                "x".to_string(),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            configured_quiz_bots(Some(&custom)),
+            vec![serenity::UserId::new(1001), serenity::UserId::new(1002)]
+        );
+    }
+
+    #[test]
+    fn test_parse_bot_id() {
+        assert_eq!(parse_bot_id("251239170058616833"), Some(KOTOBA_BOT_ID));
+        assert_eq!(parse_bot_id("0"), None);
+        assert_eq!(parse_bot_id("-5"), None);
+        assert_eq!(parse_bot_id("<@123>"), None);
+        assert_eq!(parse_bot_id("99999999999999999999999"), None);
+    }
+
+    #[test]
+    fn test_overwrites_grant_each_quiz_bot() {
+        let guild_id = serenity::GuildId::new(1);
+        let user_id = serenity::UserId::new(2);
+        let bot_id = serenity::UserId::new(3);
+        let quiz_bots = [serenity::UserId::new(1001), serenity::UserId::new(1002)];
+
+        let overwrites = quiz_channel_overwrites(guild_id, user_id, bot_id, &quiz_bots);
+        let kinds: Vec<_> = overwrites.iter().map(|o| o.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                serenity::PermissionOverwriteType::Role(serenity::RoleId::new(1)),
+                serenity::PermissionOverwriteType::Member(user_id),
+                serenity::PermissionOverwriteType::Member(quiz_bots[0]),
+                serenity::PermissionOverwriteType::Member(quiz_bots[1]),
+                serenity::PermissionOverwriteType::Member(bot_id),
+            ]
+        );
+        assert_eq!(overwrites[0].deny, serenity::Permissions::VIEW_CHANNEL);
+        for member in &overwrites[1..] {
+            assert!(member.allow.contains(
+                serenity::Permissions::VIEW_CHANNEL | serenity::Permissions::SEND_MESSAGES
+            ));
+        }
+    }
+
+    #[test]
+    fn test_quiz_category_validation() {
+        let old = Some(serenity::ChannelId::new(10));
+        let new = Some(serenity::ChannelId::new(20));
+        let other = Some(serenity::ChannelId::new(30));
+
+        // Channel created under the old category still counts after the config moved on
+        assert!(in_quiz_category(old, old, new));
+        // Channels under the current category count even without a recorded session
+        assert!(in_quiz_category(new, None, new));
+        assert!(in_quiz_category(new, old, new));
+        // Anything else is not a quiz channel
+        assert!(!in_quiz_category(other, old, new));
+        assert!(!in_quiz_category(None, None, None));
+        assert!(!in_quiz_category(other, None, None));
+    }
+
+    #[test]
+    fn test_stored_session_keeps_category() {
+        let mut session = session_created_at("2025-01-15T10:00:00Z");
+        session.quiz_id = "hiragana_katakana".to_string();
+        session.category_id = Some(serenity::ChannelId::new(10));
+
+        let restored = QuizSession::try_from(StoredQuizSession::from(&session)).unwrap();
+        assert_eq!(restored.category_id, session.category_id);
+
+        // Sessions persisted before the field existed
+        let legacy: StoredQuizSession = serde_json::from_value(serde_json::json!({
+            "user_id": "1",
+            "quiz_id": "hiragana_katakana",
+            "thread_id": "2",
+            "started": false,
+            "active_attempt": false,
+            "progress": 0,
+            "updated_at": "2025-01-15T10:00:00Z"
+        }))
+        .unwrap();
+        assert!(QuizSession::try_from(legacy).unwrap().category_id.is_none());
+    }
+
+    #[test]
+    fn test_session_expiry_schedule() {
+        let ttl = chrono::Duration::hours(24);
+        let mut session = session_created_at("2025-01-15T10:00:00Z");
+
+        assert_eq!(
+            session_expiry(&session, at("2025-01-16T08:59:59Z"), ttl),
+            SessionExpiry::Keep
+        );
+        assert_eq!(
+            session_expiry(&session, at("2025-01-16T09:00:00Z"), ttl),
+            SessionExpiry::Warn
+        );
+
+        session.expiry_warned = true;
+        assert_eq!(
+            session_expiry(&session, at("2025-01-16T09:30:00Z"), ttl),
+            SessionExpiry::Keep
+        );
+        assert_eq!(
+            session_expiry(&session, at("2025-01-16T10:00:00Z"), ttl),
+            SessionExpiry::Expire
+        );
+    }
+
+    #[test]
+    fn test_session_expiry_counts_from_last_activity() {
+        let ttl = chrono::Duration::hours(24);
+        let mut session = session_created_at("2025-01-15T10:00:00Z");
+        session.quiz_id = "Level_1".to_string();
+        session.expiry_warned = true;
+
+        // Still taking the quiz the next morning
+        session.touch(at("2025-01-16T08:00:00Z"));
+        assert!(!session.expiry_warned);
+        assert_eq!(
+            session_expiry(&session, at("2025-01-16T10:00:00Z"), ttl),
+            SessionExpiry::Keep
+        );
+        assert_eq!(
+            session_expiry(&session, at("2025-01-17T07:00:00Z"), ttl),
+            SessionExpiry::Warn
+        );
+        assert_eq!(
+            session_expiry(&session, at("2025-01-17T08:00:00Z"), ttl),
+            SessionExpiry::Expire
+        );
+
+        // Survives a restart
+        let restored = QuizSession::try_from(StoredQuizSession::from(&session)).unwrap();
+        assert_eq!(restored.last_activity_at, at("2025-01-16T08:00:00Z"));
+    }
+
+    #[test]
+    fn test_attempt_timeout() {
+        let mut session = session_created_at("2025-01-15T10:00:00Z");
+        session.active_attempt = true;
+        assert!(!attempt_timed_out(&session, at("2025-01-15T12:00:00Z")));
+
+        session.attempt_started_at = Some(at("2025-01-15T10:00:00Z"));
+        assert!(!attempt_timed_out(&session, at("2025-01-15T10:29:59Z")));
+        assert!(attempt_timed_out(&session, at("2025-01-15T10:30:00Z")));
+
+        // Rejected pastes end the attempt, nothing is running
+        session.active_attempt = false;
+        assert!(!attempt_timed_out(&session, at("2025-01-15T11:00:00Z")));
+    }
+
+    #[test]
+    fn test_stored_session_keeps_attempt_start() {
+        let mut session = session_created_at("2025-01-15T10:00:00Z");
+        session.quiz_id = "Level_1".to_string();
+        session.guild_id = Some(serenity::GuildId::new(5));
+        session.attempt_started_at = Some(at("2025-01-15T10:05:00Z"));
+
+        let restored = QuizSession::try_from(StoredQuizSession::from(&session)).unwrap();
+        assert_eq!(restored.guild_id, session.guild_id);
+        assert_eq!(restored.attempt_started_at, session.attempt_started_at);
+    }
+
+    #[test]
+    fn test_session_changes_only_touch_changed_sessions() {
+        let stored = |user: u64, progress: usize, updated_at: &str| {
+            let mut session = session_created_at("2025-01-15T10:00:00Z");
+            session.user_id = serenity::UserId::new(user);
+            session.quiz_id = "Level_1".to_string();
+            session.progress = progress;
+            StoredQuizSession {
+                updated_at: updated_at.to_string(),
+                ..StoredQuizSession::from(&session)
+            }
+        };
+        let map = |sessions: Vec<StoredQuizSession>| -> HashMap<String, StoredQuizSession> {
+            sessions
+                .into_iter()
+                .map(|s| (s.user_id.clone(), s))
+                .collect()
+        };
+
+        let synced = map(vec![
+            stored(1, 0, "2025-01-15T10:00:00Z"),
+            stored(2, 0, "2025-01-15T10:00:00Z"),
+            stored(3, 0, "2025-01-15T10:00:00Z"),
+        ]);
+        let current = map(vec![
+            // Rewritten with a new timestamp only
+            stored(1, 0, "2025-01-15T11:00:00Z"),
+            stored(2, 1, "2025-01-15T11:00:00Z"),
+            stored(4, 0, "2025-01-15T11:00:00Z"),
+        ]);
+        let (upserts, deletes) = session_changes(&synced, &current);
+        let written: Vec<(&str, usize)> = upserts
+            .iter()
+            .map(|s| (s.user_id.as_str(), s.progress))
+            .collect();
+        assert_eq!(written, vec![("2", 1), ("4", 0)]);
+        assert_eq!(deletes, vec!["3".to_string()]);
+        assert_eq!(session_changes(&current, &current), (vec![], vec![]));
+    }
+
+    #[test]
+    fn test_legacy_stored_session_uses_updated_at() {
+        let stored: StoredQuizSession = serde_json::from_value(serde_json::json!({
+            "user_id": "1",
+            "quiz_id": QUIZZES.keys().next().unwrap(),
+            "thread_id": "2",
+            "started": false,
+            "active_attempt": false,
+            "progress": 0,
+            "updated_at": "2025-01-15T10:00:00Z"
+        }))
+        .unwrap();
+
+        let session = QuizSession::try_from(stored).unwrap();
+        assert_eq!(session.created_at, at("2025-01-15T10:00:00Z"));
+        assert_eq!(session.last_activity_at, session.created_at);
+        assert!(!session.expiry_warned);
+    }
+
+    fn name(n: &str) -> QuizWinner {
+        QuizWinner::Name(n.to_string())
+    }
+
+    #[test]
+    fn test_parse_single_winner_from_title() {
+        let winners = parse_quiz_winners(
+            Some("The score limit of 20 was reached by Tanaka. Congratulations!"),
+            None,
+        );
+        assert_eq!(winners, vec![name("Tanaka")]);
+    }
+
+    #[test]
+    fn test_parse_multiple_winners_from_title() {
+        let winners = parse_quiz_winners(
+            Some("The score limit of 20 was reached by Tanaka and Suzuki. Congratulations!"),
+            None,
+        );
+        assert_eq!(winners, vec![name("Tanaka"), name("Suzuki")]);
+
+        let winners = parse_quiz_winners(
+            Some("The score limit of 20 was reached by A, B and <@42>. Congratulations!"),
+            None,
+        );
+        assert_eq!(
+            winners,
+            vec![
+                name("A"),
+                name("B"),
+                QuizWinner::Mention(serenity::UserId::new(42))
+            ]
+        );
+    }
 
-    // Simple equality check for now (Strict Mode)
-    // We can make this smarter later if needed (e.g. order of params)
-    u == e
+    #[test]
+    fn test_parse_description_only_congratulations() {
+        let winners = parse_quiz_winners(
+            Some("JPDB 300 Ended"),
+            Some("Congratulations! <@!123> reached the score limit of 20"),
+        );
+        assert_eq!(
+            winners,
+            vec![QuizWinner::Mention(serenity::UserId::new(123))]
+        );
+
+        // No winner information at all
+        assert!(parse_quiz_winners(Some("JPDB 300 Ended"), Some("Congratulations!")).is_empty());
+    }
+
+    #[test]
+    fn test_winner_matches_owner() {
+        let owner = serenity::UserId::new(123);
+        let names = vec!["tanaka_t".to_string(), "Tanaka".to_string()];
+
+        assert!(winner_matches(&[QuizWinner::Mention(owner)], owner, &[]));
+        assert!(winner_matches(
+            &[name("tanaka"), name("Suzuki")],
+            owner,
+            &names
+        ));
+        assert!(!winner_matches(&[name("Suzuki")], owner, &names));
+        assert!(!winner_matches(
+            &[QuizWinner::Mention(serenity::UserId::new(7))],
+            owner,
+            &names
+        ));
+    }
+
+    #[test]
+    fn test_unparseable_result_does_not_match_owner() {
+        let owner = serenity::UserId::new(123);
+        let names = vec!["Tanaka".to_string()];
+        // A result whose winner can't be read never counts for the owner
+        let winners = parse_quiz_winners(
+            Some("Congratulations!"),
+            Some("Something went well, congratulations!"),
+        );
+        assert!(winners.is_empty());
+        assert!(!winner_matches(&winners, owner, &names));
+    }
+    fn completion(guild: u64, user: u64, completed_at: &str) -> UnclaimedCompletion {
+        UnclaimedCompletion {
+            guild_id: serenity::GuildId::new(guild),
+            user_id: serenity::UserId::new(user),
+            quiz_id: "n5".to_string(),
+            completed_at: at(completed_at),
+        }
+    }
+
+    #[test]
+    fn test_take_unclaimed_completion() {
+        let mut completions = vec![
+            completion(1, 10, "2025-01-10T10:00:00Z"),
+            completion(1, 11, "2025-01-10T10:00:00Z"),
+            completion(2, 10, "2025-01-10T10:00:00Z"),
+        ];
+        let now = at("2025-01-12T10:00:00Z");
+
+        let taken = take_unclaimed_completion(
+            &mut completions,
+            serenity::GuildId::new(1),
+            serenity::UserId::new(10),
+            now,
+        );
+        assert_eq!(taken, Some(completion(1, 10, "2025-01-10T10:00:00Z")));
+        assert_eq!(completions.len(), 2);
+
+        // Already claimed
+        assert!(take_unclaimed_completion(
+            &mut completions,
+            serenity::GuildId::new(1),
+            serenity::UserId::new(10),
+            now
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_unclaimed_completion_expires_after_seven_days() {
+        let mut completions = vec![completion(1, 10, "2025-01-10T10:00:00Z")];
+
+        // Exactly seven days later is still claimable
+        assert!(!completions[0].is_expired(at("2025-01-17T10:00:00Z")));
+
+        let taken = take_unclaimed_completion(
+            &mut completions,
+            serenity::GuildId::new(1),
+            serenity::UserId::new(10),
+            at("2025-01-17T10:00:01Z"),
+        );
+        assert!(taken.is_none());
+        assert!(completions.is_empty());
+    }
+
+    #[test]
+    fn test_classify_removal() {
+        assert_eq!(classify_removal(None), RoleRemoval::NotHeld);
+        assert_eq!(classify_removal(Some(&Ok(()))), RoleRemoval::Removed);
+        assert_eq!(
+            classify_removal(Some(&Err(serenity::Error::Model(
+                serenity::ModelError::Hierarchy
+            )))),
+            RoleRemoval::MissingPermissions
+        );
+        assert_eq!(
+            classify_removal(Some(&Err(serenity::Error::Other("connection reset")))),
+            RoleRemoval::Failed("connection reset".to_string())
+        );
+        assert!(!RoleRemoval::NotHeld.is_failure());
+        assert!(RoleRemoval::MissingPermissions.is_failure());
+    }
+
+    #[test]
+    fn test_role_set_after() {
+        let role = serenity::RoleId::new;
+        let current = [role(1), role(2), role(3)];
+        assert_eq!(
+            role_set_after(&current, &[role(2), role(9)], Some(role(4))),
+            vec![role(1), role(3), role(4)]
+        );
+        // Adding a role that is also in the removal list keeps it, once
+        assert_eq!(
+            role_set_after(&current, &[role(3)], Some(role(3))),
+            current.to_vec()
+        );
+        assert_eq!(role_set_after(&current, &[], None), current.to_vec());
+    }
+
+    #[test]
+    fn test_role_changes_text() {
+        let quizzes = quizzes_by_level();
+        let (low, mid, high) = (quizzes[0], quizzes[1], quizzes[2]);
+        let current = [low.role_in(None), serenity::RoleId::new(1)];
+        assert_eq!(
+            role_changes_text(&current, &[low, mid], Some(high), None),
+            format!("+{}, −{}", high.label, low.label)
+        );
+        assert_eq!(role_changes_text(&current, &[], None, None), "");
+    }
+
+    #[test]
+    fn test_missing_permissions_response() {
+        assert!(is_missing_permissions_response(403, 50013));
+        assert!(is_missing_permissions_response(403, 0));
+        assert!(!is_missing_permissions_response(404, 10011));
+        assert!(!is_missing_permissions_response(500, 0));
+    }
+
+    #[test]
+    fn test_channel_limit_response() {
+        assert!(is_channel_limit_response(400, 30013, []));
+        assert!(is_channel_limit_response(
+            400,
+            50035,
+            ["CHANNEL_PARENT_MAX_CHANNELS"]
+        ));
+        assert!(!is_channel_limit_response(
+            400,
+            50035,
+            ["BASE_TYPE_REQUIRED"]
+        ));
+        assert!(!is_channel_limit_response(403, 50013, []));
+    }
+
+    #[test]
+    fn test_parse_user_targets() {
+        let (users, invalid) =
+            parse_user_targets(&["<@123>", "<@!456>", "789", "123", "<@&111>", "someone"]);
+        assert_eq!(
+            users,
+            vec![
+                serenity::UserId::new(123),
+                serenity::UserId::new(456),
+                serenity::UserId::new(789),
+            ]
+        );
+        // Role mentions are not users
+        assert_eq!(invalid, vec!["<@&111>".to_string(), "someone".to_string()]);
+    }
+
+    #[test]
+    fn test_validate_command() {
+        let expected = "k!quiz jpdb300 20 hardcore nd mmq=10 dauq=1 font=5 atl=16 color=#f173ff size=100 effect=antiocr";
+        let cases = [
+            ("exact match", expected, true),
+            (
+                "trailing and doubled whitespace",
+                "  k!quiz jpdb300  20 hardcore nd mmq=10 dauq=1 font=5 atl=16 color=#f173ff size=100 effect=antiocr \n",
+                true,
+            ),
+            (
+                "reordered params",
+                "k!quiz jpdb300 20 nd hardcore effect=antiocr size=100 color=#f173ff atl=16 font=5 dauq=1 mmq=10",
+                true,
+            ),
+            (
+                "upper case keys",
+                "k!quiz jpdb300 20 hardcore nd MMQ=10 dauq=1 font=5 atl=16 color=#f173ff size=100 Effect=antiocr",
+                true,
+            ),
+            (
+                "missing effect=antiocr",
+                "k!quiz jpdb300 20 hardcore nd mmq=10 dauq=1 font=5 atl=16 color=#f173ff size=100",
+                false,
+            ),
+            (
+                "changed score",
+                "k!quiz jpdb300 10 hardcore nd mmq=10 dauq=1 font=5 atl=16 color=#f173ff size=100 effect=antiocr",
+                false,
+            ),
+            (
+                "extra deck appended",
+                "k!quiz jpdb300+n5 20 hardcore nd mmq=10 dauq=1 font=5 atl=16 color=#f173ff size=100 effect=antiocr",
+                false,
+            ),
+            (
+                "extra deck as its own argument",
+                "k!quiz jpdb300 n5 20 hardcore nd mmq=10 dauq=1 font=5 atl=16 color=#f173ff size=100 effect=antiocr",
+                false,
+            ),
+            (
+                "deck moved out of first place",
+                "k!quiz 20 jpdb300 hardcore nd mmq=10 dauq=1 font=5 atl=16 color=#f173ff size=100 effect=antiocr",
+                false,
+            ),
+            (
+                "without hardcore",
+                "k!quiz jpdb300 20 nd mmq=10 dauq=1 font=5 atl=16 color=#f173ff size=100 effect=antiocr",
+                false,
+            ),
+            (
+                "without nd",
+                "k!quiz jpdb300 20 hardcore mmq=10 dauq=1 font=5 atl=16 color=#f173ff size=100 effect=antiocr",
+                false,
+            ),
+            (
+                "changed param value",
+                "k!quiz jpdb300 20 hardcore nd mmq=5 dauq=1 font=5 atl=16 color=#f173ff size=100 effect=antiocr",
+                false,
+            ),
+            (
+                "param repeated with another value",
+                "k!quiz jpdb300 20 hardcore nd mmq=10 mmq=1 dauq=1 font=5 atl=16 color=#f173ff size=100 effect=antiocr",
+                false,
+            ),
+            ("not a quiz command", "k!stop", false),
+        ];
+        for (name, input, valid) in cases {
+            assert_eq!(validate_command(input, expected), valid, "{}", name);
+        }
+
+        // Score limit after the flags, as in the grammar stages
+        assert!(validate_command(
+            "k!quiz gn2 20 nd atl=60 mmq=4",
+            "k!quiz gn2 nd 20 mmq=4 atl=60"
+        ));
+        // No score limit at all
+        assert!(!validate_command(
+            "k!quiz hiragana+katakana nd 10 mmq=10",
+            "k!quiz hiragana+katakana nd mmq=10"
+        ));
+    }
+
+    #[test]
+    fn test_quiz_decks_and_level_labels() {
+        assert_eq!(
+            quiz_decks(&QUIZZES["hiragana_katakana"]),
+            "hiragana+katakana"
+        );
+        let two_stage = QUIZZES.values().find(|q| q.commands.len() == 2).unwrap();
+        assert_eq!(quiz_decks(two_stage).matches(", ").count(), 1);
+
+        assert_eq!(level_label(-1), "No quiz role");
+        assert_eq!(level_label(0), "Kanji Wakaran (漢字わからん)");
+    }
 }
@@ -0,0 +1,147 @@
+// Role rank promotion audit
+// One record per quiz role granted, so staff can reconstruct a member's promotion history
+//
+// Records live in `guilds/{gid}/role_rank_audit`
+
+use chrono::{DateTime, Utc};
+use poise::serenity_prelude as serenity;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::api::firebase::QueryFilter;
+use crate::api::storage::Storage;
+
+/// Upper bound on records read for one member's history
+const MAX_RECORDS: usize = 200;
+
+/// A member moving from one quiz level to another
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromotionRecord {
+    pub guild_id: String,
+    pub user_id: String,
+    /// -1 without a quiz role
+    pub from_level: i32,
+    pub to_level: i32,
+    /// RFC3339 in UTC with a `Z` suffix, so string comparison orders it
+    pub timestamp: String,
+    /// Quiz channel the promotion was earned in
+    pub channel_id: String,
+}
+
+impl PromotionRecord {
+    pub fn new(
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+        from_level: i32,
+        to_level: i32,
+        channel_id: serenity::ChannelId,
+        at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            guild_id: guild_id.to_string(),
+            user_id: user_id.to_string(),
+            from_level,
+            to_level,
+            timestamp: at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            channel_id: channel_id.to_string(),
+        }
+    }
+}
+
+/// Store a record in the background, failures are only logged
+pub fn record(firebase: std::sync::Arc<dyn Storage>, record: PromotionRecord) {
+    tokio::spawn(async move {
+        let result = async {
+            let value = serde_json::to_value(&record)?;
+            firebase
+                .add_to_subcollection("guilds", &record.guild_id, "role_rank_audit", &value)
+                .await
+        }
+        .await;
+        if let Err(e) = result {
+            warn!(
+                "Failed to record promotion of {} in guild {}: {:?}",
+                record.user_id, record.guild_id, e
+            );
+        }
+    });
+}
+
+/// The member's latest `limit` promotions, newest first
+pub async fn load_user_history(
+    firebase: &dyn Storage,
+    guild_id: &str,
+    user_id: &str,
+    limit: usize,
+) -> anyhow::Result<Vec<PromotionRecord>> {
+    // Filtering on the user alone needs no composite index, members only have a handful
+    let docs = firebase
+        .run_query(
+            "guilds",
+            guild_id,
+            "role_rank_audit",
+            vec![QueryFilter::string_eq("userId", user_id)],
+            None,
+            MAX_RECORDS,
+            None,
+        )
+        .await?;
+    let records = docs
+        .into_iter()
+        .filter_map(|(_, doc)| serde_json::from_value(doc).ok())
+        .collect();
+    Ok(latest(records, limit))
+}
+
+/// Newest first, at most `limit`
+fn latest(mut records: Vec<PromotionRecord>, limit: usize) -> Vec<PromotionRecord> {
+    records.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    records.truncate(limit);
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record_at(at: &str, to_level: i32) -> PromotionRecord {
+        PromotionRecord::new(
+            serenity::GuildId::new(1),
+            serenity::UserId::new(2),
+            to_level - 1,
+            to_level,
+            serenity::ChannelId::new(3),
+            DateTime::parse_from_rfc3339(at)
+                .unwrap()
+                .with_timezone(&Utc),
+        )
+    }
+
+    #[test]
+    fn test_record_serializes_audit_fields() {
+        let value = serde_json::to_value(record_at("2025-03-01T12:00:00+07:00", 2)).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "guildId": "1",
+                "userId": "2",
+                "fromLevel": 1,
+                "toLevel": 2,
+                "timestamp": "2025-03-01T05:00:00Z",
+                "channelId": "3"
+            })
+        );
+    }
+
+    #[test]
+    fn test_latest_orders_newest_first() {
+        let records = vec![
+            record_at("2025-01-01T00:00:00Z", 1),
+            record_at("2025-03-01T00:00:00Z", 3),
+            record_at("2025-02-01T00:00:00Z", 2),
+        ];
+        let levels: Vec<i32> = latest(records, 2).iter().map(|r| r.to_level).collect();
+        assert_eq!(levels, vec![3, 2]);
+    }
+}
@@ -0,0 +1,275 @@
+// Streak reminders
+// Members who opted in with /reminder streak get a DM at their chosen hour, in their
+// /timezone or WIB, when they have a streak going and nothing logged yet today.
+// `streak_reminders/{userId}` holds the hour and timezone of everyone opted in, so the
+// hourly run never reads the whole users collection.
+
+use chrono::{DateTime, Duration as ChronoDuration, NaiveDate, Timelike, Utc};
+use futures::StreamExt;
+use poise::serenity_prelude as serenity;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tracing::{debug, error, info, warn};
+
+use crate::api::storage::Storage;
+use crate::models::user::UserDoc;
+use crate::utils::clock::Clock;
+use crate::utils::config::{effective_date_in, local_hour_in};
+use crate::utils::i18n::{tf, Msg};
+use crate::utils::shutdown::Shutdown;
+
+/// Index of opted-in members, one document per user holding their `hour`
+pub const COLLECTION: &str = "streak_reminders";
+
+/// Hour used when a member doesn't pick one
+pub const DEFAULT_HOUR: u32 = 20;
+
+/// Members checked at once
+const CONCURRENCY: usize = 5;
+
+/// The start of the hour after `now`
+fn next_hour(now: DateTime<Utc>) -> DateTime<Utc> {
+    now.date_naive()
+        .and_hms_opt(now.hour(), 0, 0)
+        .expect("hour of a valid time")
+        .and_utc()
+        + ChronoDuration::hours(1)
+}
+
+/// Index entry of a member, `timezone` only when they set one. The reminders read it
+/// instead of each user document.
+pub fn index_doc(hour: u32, timezone: Option<&str>) -> Value {
+    match timezone {
+        Some(tz) => json!({ "hour": hour, "timezone": tz }),
+        None => json!({ "hour": hour }),
+    }
+}
+
+/// Ids of the index documents due at `now`, each hour read in the member's timezone
+fn due_user_ids(index: &[Value], now: DateTime<Utc>) -> Vec<String> {
+    index
+        .iter()
+        .filter(|doc| {
+            let tz = doc.get("timezone").and_then(|t| t.as_str());
+            // Firestore can hand integers back as doubles
+            doc.get("hour").and_then(|h| h.as_f64()) == Some(local_hour_in(tz, now) as f64)
+        })
+        .filter_map(|doc| doc.get("_id").and_then(|id| id.as_str()))
+        .map(str::to_string)
+        .collect()
+}
+
+/// The streak a member loses by not logging today: the longest current streak of a media
+/// type last logged the day before `today`. `None` when there's nothing to lose.
+pub fn streak_at_risk(user: &UserDoc, today: NaiveDate) -> Option<i64> {
+    let yesterday = today - ChronoDuration::days(1);
+    let timezone = user.profile.timezone.as_deref();
+    user.stats
+        .iter()
+        .filter(|(_, stats)| stats.current_streak > 0)
+        .filter(|(_, stats)| {
+            stats
+                .last_activity
+                .as_deref()
+                .and_then(|at| DateTime::parse_from_rfc3339(at).ok())
+                .is_some_and(|at| effective_date_in(timezone, at.with_timezone(&Utc)) == yesterday)
+        })
+        .map(|(_, stats)| stats.current_streak)
+        .max()
+}
+
+pub struct StreakReminder {
+    http: Arc<serenity::Http>,
+    firebase: Arc<dyn Storage>,
+    clock: Arc<dyn Clock>,
+}
+
+impl StreakReminder {
+    pub fn new(
+        http: Arc<serenity::Http>,
+        firebase: Arc<dyn Storage>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        Self {
+            http,
+            firebase,
+            clock,
+        }
+    }
+
+    /// Run at the start of every hour, until shutdown
+    pub fn spawn(self, shutdown: &Shutdown) {
+        let shutdown_signal = shutdown.clone();
+        shutdown.spawn(async move {
+            loop {
+                let now = self.clock.now_utc();
+                let wait = (next_hour(now) - now).to_std().unwrap_or_default();
+                if !shutdown_signal.sleep(wait).await {
+                    break;
+                }
+                self.run().await;
+            }
+        });
+    }
+
+    async fn run(&self) {
+        let now = self.clock.now_utc();
+
+        let index = match self.firebase.get_all_documents(COLLECTION).await {
+            Ok(index) => index,
+            Err(e) => {
+                error!("Failed to load streak reminders: {:?}", e);
+                return;
+            }
+        };
+        let due = due_user_ids(&index, now);
+        if due.is_empty() {
+            debug!("No streak reminders due at {}", now);
+            return;
+        }
+
+        let sent = futures::stream::iter(due)
+            .map(|user_id| async move {
+                match self.remind(&user_id, now).await {
+                    Ok(sent) => sent,
+                    Err(e) => {
+                        warn!("Failed to check streak reminder of {}: {:?}", user_id, e);
+                        false
+                    }
+                }
+            })
+            .buffer_unordered(CONCURRENCY)
+            .filter(|sent| std::future::ready(*sent))
+            .count()
+            .await;
+        info!("Sent {} streak reminders at {}", sent, now);
+    }
+
+    /// DM one member if they still have to log today, `true` when the DM went out
+    async fn remind(&self, user_id: &str, now: DateTime<Utc>) -> anyhow::Result<bool> {
+        let Some(user) = self.firebase.get_user(user_id).await? else {
+            return Ok(false);
+        };
+        let today = effective_date_in(user.profile.timezone.as_deref(), now);
+        let Some(settings) = user.streak_reminder().filter(|s| s.enabled) else {
+            return Ok(false);
+        };
+        let date = today.format("%Y-%m-%d").to_string();
+        if settings.last_reminded_date.as_deref() == Some(date.as_str()) {
+            return Ok(false);
+        }
+        let Some(streak) = streak_at_risk(&user, today) else {
+            return Ok(false);
+        };
+        if !self
+            .firebase
+            .get_user_logs_on(user_id, &date)
+            .await?
+            .is_empty()
+        {
+            return Ok(false);
+        }
+
+        let discord_id = serenity::UserId::new(user_id.parse()?);
+        let message =
+            serenity::CreateMessage::new().content(tf(None, Msg::StreakReminderDm, &[&streak]));
+        let sent = match discord_id.direct_message(&self.http, message).await {
+            Ok(_) => true,
+            Err(e) => {
+                // Closed DMs: marked as reminded anyway, so it isn't retried every hour
+                debug!("Couldn't DM streak reminder to {}: {:?}", user_id, e);
+                false
+            }
+        };
+
+        self.firebase
+            .set_document_merge_paths(
+                "users",
+                user_id,
+                &json!({ "reminders": { "streak": { "lastRemindedDate": date } } }),
+                &["reminders.streak.lastRemindedDate"],
+            )
+            .await?;
+        Ok(sent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_hours() {
+        assert_eq!(
+            next_hour(at("2025-01-15T13:30:12Z")),
+            at("2025-01-15T14:00:00Z")
+        );
+        assert_eq!(
+            next_hour(at("2025-01-15T23:00:00Z")),
+            at("2025-01-16T00:00:00Z")
+        );
+    }
+
+    #[test]
+    fn test_due_user_ids() {
+        let index = vec![
+            json!({ "_id": "1", "hour": 20 }),
+            json!({ "_id": "2", "hour": 8 }),
+            json!({ "_id": "3", "hour": 20.0 }),
+            json!({ "hour": 20 }),
+            json!({ "_id": "4", "hour": 14, "timezone": "Europe/Berlin" }),
+        ];
+        // 20:00 WIB is 14:00 in Berlin in winter
+        assert_eq!(
+            due_user_ids(&index, at("2025-01-15T13:00:00Z")),
+            vec!["1", "3", "4"]
+        );
+        assert!(due_user_ids(&index, at("2025-01-15T14:00:00Z")).is_empty());
+        // And 15:00 in summer, Berlin's 14:00 comes an hour earlier
+        assert_eq!(due_user_ids(&index, at("2025-07-15T12:00:00Z")), vec!["4"]);
+        assert_eq!(
+            index_doc(14, Some("Europe/Berlin")),
+            json!({ "hour": 14, "timezone": "Europe/Berlin" })
+        );
+        assert_eq!(index_doc(20, None), json!({ "hour": 20 }));
+    }
+
+    #[test]
+    fn test_streak_at_risk() {
+        let today = NaiveDate::from_ymd_opt(2025, 1, 15).unwrap();
+        let user: UserDoc = serde_json::from_value(json!({
+            "stats": {
+                // Logged yesterday evening WIB
+                "anime": { "currentStreak": 4, "lastActivity": "2025-01-14T13:00:00+00:00" },
+                // Logged at 01:30 WIB today, before the day ends at 02:00
+                "manga": { "currentStreak": 9, "lastActivity": "2025-01-14T18:30:00+00:00" },
+                // Last logged two days ago
+                "reading": { "currentStreak": 12, "lastActivity": "2025-01-13T13:00:00+00:00" }
+            }
+        }))
+        .unwrap();
+        assert_eq!(streak_at_risk(&user, today), Some(9));
+
+        // Already broken, or nothing logged
+        assert_eq!(
+            streak_at_risk(&user, NaiveDate::from_ymd_opt(2025, 1, 17).unwrap()),
+            None
+        );
+        assert_eq!(streak_at_risk(&UserDoc::default(), today), None);
+
+        // 19:00 UTC on the 14th is the 15th in WIB, but still the 14th in London
+        let mut user: UserDoc = serde_json::from_value(json!({
+            "stats": {
+                "anime": { "currentStreak": 3, "lastActivity": "2025-01-14T19:00:00+00:00" }
+            }
+        }))
+        .unwrap();
+        assert_eq!(streak_at_risk(&user, today), None);
+        user.profile.timezone = Some("Europe/London".to_string());
+        assert_eq!(streak_at_risk(&user, today), Some(3));
+    }
+}
@@ -0,0 +1,377 @@
+// Title popularity
+// Counts what each guild has logged over the last 30 days so autocomplete can float those titles up
+//
+// Stored at `guilds/{gid}/title_popularity/current` as
+// `{ "titles": { "anime:clannad": { "2025-01-15": 2 } }, "updated": ... }`
+
+use chrono::{Duration as ChronoDuration, NaiveDate};
+use dashmap::{DashMap, DashSet};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+use crate::api::storage::Storage;
+use crate::utils::clock::Clock;
+
+/// Days of logs that count towards popularity
+const WINDOW_DAYS: i64 = 30;
+
+/// Titles kept per guild, the least logged are dropped first
+const MAX_TITLES: usize = 200;
+
+/// How long a cached guild map is used before it is reloaded
+const CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Logs needed for the top tier
+const HOT_THRESHOLD: u32 = 5;
+
+/// Prefix for boosted autocomplete entries
+pub const MARKER: &str = "▲ ";
+
+/// Title counts for one guild, keyed like `anime:clannad`
+pub type Counts = HashMap<String, u32>;
+
+/// Lowercased title with punctuation dropped and whitespace collapsed
+pub fn normalize_title(title: &str) -> String {
+    title
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .flat_map(char::to_lowercase)
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Key of a title in the popularity map
+pub fn title_key(media_type: &str, title: &str) -> String {
+    format!("{}:{}", media_type, normalize_title(title))
+}
+
+/// Stored popularity document
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Popularity {
+    /// Title key -> date -> logs on that day
+    pub titles: BTreeMap<String, BTreeMap<String, u32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated: Option<String>,
+}
+
+impl Popularity {
+    /// Count one log, then drop days outside the window and trim to the top titles
+    pub fn record(&mut self, key: String, date: NaiveDate, today: NaiveDate) {
+        *self
+            .titles
+            .entry(key)
+            .or_default()
+            .entry(date.format("%Y-%m-%d").to_string())
+            .or_default() += 1;
+        self.prune(today);
+    }
+
+    fn prune(&mut self, today: NaiveDate) {
+        let cutoff = window_start(today);
+        for days in self.titles.values_mut() {
+            days.retain(|date, _| date.as_str() >= cutoff.as_str());
+        }
+        self.titles.retain(|_, days| !days.is_empty());
+
+        if self.titles.len() > MAX_TITLES {
+            let counts = self.counts(today);
+            let mut keys: Vec<&String> = self.titles.keys().collect();
+            // Most logged first, ties by key so the cut is deterministic
+            keys.sort_by(|a, b| counts[*b].cmp(&counts[*a]).then_with(|| a.cmp(b)));
+            let keep: Vec<String> = keys.into_iter().take(MAX_TITLES).cloned().collect();
+            self.titles.retain(|key, _| keep.contains(key));
+        }
+    }
+
+    /// Logs per title within the window ending today
+    pub fn counts(&self, today: NaiveDate) -> Counts {
+        let cutoff = window_start(today);
+        self.titles
+            .iter()
+            .map(|(key, days)| {
+                let count = days
+                    .iter()
+                    .filter(|(date, _)| date.as_str() >= cutoff.as_str())
+                    .map(|(_, n)| n)
+                    .sum();
+                (key.clone(), count)
+            })
+            .filter(|(_, count)| *count > 0)
+            .collect()
+    }
+}
+
+fn window_start(today: NaiveDate) -> String {
+    (today - ChronoDuration::days(WINDOW_DAYS - 1))
+        .format("%Y-%m-%d")
+        .to_string()
+}
+
+fn collection(guild_id: &str) -> String {
+    format!("guilds/{}/title_popularity", guild_id)
+}
+
+/// An autocomplete result before formatting
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    /// Provider title, used for matching
+    pub title: String,
+    /// What the user sees, e.g. "Clannad (2004-04-28)"
+    pub display: String,
+    pub id: String,
+}
+
+/// 0 for titles nobody logged, higher tiers are listed first
+fn tier(count: u32) -> u8 {
+    match count {
+        0 => 0,
+        c if c < HOT_THRESHOLD => 1,
+        _ => 2,
+    }
+}
+
+/// Sort candidates by popularity tier, keeping the provider's order within a tier.
+/// Returns each candidate with whether it was boosted.
+pub fn rank(
+    candidates: Vec<Candidate>,
+    media_type: &str,
+    counts: &Counts,
+) -> Vec<(Candidate, bool)> {
+    let mut ranked: Vec<(u8, Candidate)> = candidates
+        .into_iter()
+        .map(|c| {
+            let count = counts
+                .get(&title_key(media_type, &c.title))
+                .copied()
+                .unwrap_or(0);
+            (tier(count), c)
+        })
+        .collect();
+    // sort_by_key is stable, so provider order breaks ties
+    ranked.sort_by_key(|(tier, _)| std::cmp::Reverse(*tier));
+    ranked.into_iter().map(|(tier, c)| (c, tier > 0)).collect()
+}
+
+/// Display text with the marker for boosted entries
+pub fn label(display: &str, boosted: bool) -> String {
+    if boosted {
+        format!("{}{}", MARKER, display)
+    } else {
+        display.to_string()
+    }
+}
+
+/// Per-guild popularity maps, reloaded from Firestore at most every 10 minutes.
+/// Lookups never wait on Firestore: a stale or missing entry is served as-is
+/// while a background reload runs.
+pub struct PopularityCache {
+    firebase: Arc<dyn Storage>,
+    clock: Arc<dyn Clock>,
+    entries: DashMap<String, (Instant, Arc<Counts>)>,
+    loading: Arc<DashSet<String>>,
+}
+
+impl PopularityCache {
+    pub fn new(firebase: Arc<dyn Storage>, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            firebase,
+            clock,
+            entries: DashMap::new(),
+            loading: Arc::new(DashSet::new()),
+        }
+    }
+
+    /// Cached counts for a guild (empty until the first load finishes)
+    pub fn get(self: &Arc<Self>, guild_id: &str) -> Arc<Counts> {
+        let cached = self.entries.get(guild_id).map(|e| (e.0, e.1.clone()));
+        let fresh = cached
+            .as_ref()
+            .is_some_and(|(at, _)| self.clock.now_instant().duration_since(*at) < CACHE_TTL);
+        if !fresh && self.loading.insert(guild_id.to_string()) {
+            let cache = self.clone();
+            let guild_id = guild_id.to_string();
+            tokio::spawn(async move {
+                cache.reload(&guild_id).await;
+                cache.loading.remove(&guild_id);
+            });
+        }
+        cached.map(|(_, counts)| counts).unwrap_or_default()
+    }
+
+    async fn reload(&self, guild_id: &str) {
+        match self.load(guild_id).await {
+            Ok(popularity) => self.store(guild_id, &popularity),
+            Err(e) => {
+                warn!("Failed to load title popularity for {}: {:?}", guild_id, e);
+                // Keep serving what we had, retry after the TTL
+                let counts = self
+                    .entries
+                    .get(guild_id)
+                    .map(|e| e.1.clone())
+                    .unwrap_or_default();
+                self.entries
+                    .insert(guild_id.to_string(), (self.clock.now_instant(), counts));
+            }
+        }
+    }
+
+    async fn load(&self, guild_id: &str) -> anyhow::Result<Popularity> {
+        let doc = self
+            .firebase
+            .get_document(&collection(guild_id), "current")
+            .await?;
+        Ok(doc
+            .and_then(|d| serde_json::from_value(d).ok())
+            .unwrap_or_default())
+    }
+
+    fn store(&self, guild_id: &str, popularity: &Popularity) {
+        let today = self.today();
+        self.entries.insert(
+            guild_id.to_string(),
+            (self.clock.now_instant(), Arc::new(popularity.counts(today))),
+        );
+    }
+
+    fn today(&self) -> NaiveDate {
+        crate::utils::config::effective_date_at(self.clock.now_utc())
+    }
+
+    /// Count a new log. Read-modify-write without a transaction: two logs racing
+    /// can lose one count, which is fine for a ranking hint.
+    pub async fn record_log(
+        &self,
+        guild_id: &str,
+        media_type: &str,
+        title: &str,
+        date: NaiveDate,
+    ) -> anyhow::Result<()> {
+        if normalize_title(title).is_empty() {
+            return Ok(());
+        }
+
+        let mut popularity = self.load(guild_id).await?;
+        popularity.record(title_key(media_type, title), date, self.today());
+        popularity.updated = Some(self.clock.now_utc().to_rfc3339());
+
+        self.firebase
+            .set_document(
+                &collection(guild_id),
+                "current",
+                &serde_json::to_value(&popularity)?,
+            )
+            .await?;
+        debug!("Recorded popularity for {} in {}", title, guild_id);
+        self.store(guild_id, &popularity);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn candidate(title: &str) -> Candidate {
+        Candidate {
+            title: title.to_string(),
+            display: title.to_string(),
+            id: title.len().to_string(),
+        }
+    }
+
+    #[test]
+    fn test_normalize_title() {
+        assert_eq!(
+            normalize_title("  Frieren: Beyond  Journey's End "),
+            "frieren beyond journey s end"
+        );
+        assert_eq!(normalize_title("ぼっち・ざ・ろっく！"), "ぼっち ざ ろっく");
+        assert_eq!(title_key("anime", "CLANNAD"), "anime:clannad");
+    }
+
+    #[test]
+    fn test_rank_keeps_provider_order_within_tier() {
+        let counts: Counts = [
+            ("anime:c".to_string(), 2),
+            ("anime:d".to_string(), 9),
+            ("anime:e".to_string(), 1),
+        ]
+        .into();
+        let names: Vec<(String, bool)> = rank(
+            ["a", "b", "c", "d", "e"].map(candidate).to_vec(),
+            "anime",
+            &counts,
+        )
+        .into_iter()
+        .map(|(c, boosted)| (c.title, boosted))
+        .collect();
+
+        assert_eq!(
+            names,
+            vec![
+                ("d".to_string(), true),
+                // c and e are in the same tier, provider order decides
+                ("c".to_string(), true),
+                ("e".to_string(), true),
+                ("a".to_string(), false),
+                ("b".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rank_matches_by_media_type_and_normalized_title() {
+        let counts: Counts = [("manga:clannad".to_string(), 3)].into();
+        let ranked = rank(vec![candidate("Clannad!")], "anime", &counts);
+        assert!(!ranked[0].1);
+        let ranked = rank(vec![candidate("Clannad!")], "manga", &counts);
+        assert!(ranked[0].1);
+    }
+
+    #[test]
+    fn test_label() {
+        assert_eq!(label("Clannad", true), "▲ Clannad");
+        assert_eq!(label("Clannad", false), "Clannad");
+    }
+
+    #[test]
+    fn test_record_prunes_old_days_and_caps_titles() {
+        let today = date("2025-02-20");
+        let mut popularity = Popularity::default();
+        popularity.record("anime:old".into(), date("2025-01-01"), date("2025-01-01"));
+        popularity.record("anime:new".into(), date("2025-02-20"), today);
+        popularity.record("anime:new".into(), date("2025-01-22"), today);
+
+        assert!(!popularity.titles.contains_key("anime:old"));
+        assert_eq!(popularity.counts(today)["anime:new"], 2);
+        // The window is 30 days including today
+        assert_eq!(popularity.counts(date("2025-02-21"))["anime:new"], 1);
+
+        for i in 0..MAX_TITLES {
+            popularity.record(format!("anime:t{:03}", i), today, today);
+        }
+        assert_eq!(popularity.titles.len(), MAX_TITLES);
+        // The most logged title survives the cap
+        assert!(popularity.titles.contains_key("anime:new"));
+    }
+
+    #[test]
+    fn test_stored_format_roundtrip() {
+        let mut popularity = Popularity::default();
+        popularity.record("vn:clannad".into(), date("2025-01-15"), date("2025-01-15"));
+        let value = serde_json::to_value(&popularity).unwrap();
+        assert_eq!(value["titles"]["vn:clannad"]["2025-01-15"], 1);
+        let parsed: Popularity = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed, popularity);
+    }
+}
@@ -12,31 +12,53 @@ use std::sync::Arc;
 
 use dashmap::DashMap;
 use poise::serenity_prelude as serenity;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use crate::models::guild::GuildConfig;
 
 use crate::api::ayumu::AyumuClient;
-use crate::api::firebase::FirebaseClient;
+use crate::api::storage::Storage;
 
 /// User data shared across all commands
+#[derive(Clone)]
 pub struct Data {
     pub http_client: reqwest::Client,
-    pub firebase: Arc<FirebaseClient>,
+    pub firebase: Arc<dyn Storage>,
     pub ayumu: Arc<AyumuClient>,
     pub guild_configs: Arc<DashMap<String, GuildConfig>>,
     pub role_rank_sessions: Arc<DashMap<serenity::UserId, crate::features::role_rank::QuizSession>>,
+    pub clock: Arc<dyn utils::clock::Clock>,
+    pub prompt_store: Arc<dyn features::custom_prompt::PromptStore>,
+    pub quarantine: Arc<utils::quarantine::Quarantine>,
+    pub probe_history: Arc<utils::health::ProbeHistory>,
+    pub title_popularity: Arc<features::title_popularity::PopularityCache>,
+    pub afk: Arc<features::afk_store::AfkStore>,
+    /// Error notices recently posted per channel, so an outage isn't announced per command
+    pub error_notices: Arc<utils::error_reply::ErrorNotices>,
+    /// When the process started, for `/admin usage`
+    pub started_at: std::time::Instant,
+    /// Cancelled on SIGTERM or Ctrl+C, for background work to stop cleanly
+    pub shutdown: utils::shutdown::Shutdown,
 }
 
-// Manual Debug impl since FirebaseClient doesn't impl Debug
+// Manual Debug impl since the storage backend doesn't impl Debug
 impl std::fmt::Debug for Data {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Data")
             .field("http_client", &"reqwest::Client")
-            .field("firebase", &"FirebaseClient")
+            .field("firebase", &"Storage")
             .field("ayumu", &"AyumuClient")
             .field("guild_configs", &"DashMap")
+            .field("clock", &"Clock")
+            .field("prompt_store", &"PromptStore")
+            .field("quarantine", &"Quarantine")
+            .field("probe_history", &"ProbeHistory")
+            .field("title_popularity", &"PopularityCache")
+            .field("afk", &"AfkStore")
+            .field("error_notices", &"DashMap")
+            .field("started_at", &self.started_at)
+            .field("shutdown", &"Shutdown")
             .finish()
     }
 }
@@ -44,22 +66,43 @@ impl std::fmt::Debug for Data {
 type Error = Box<dyn std::error::Error + Send + Sync>;
 type Context<'a> = poise::Context<'a, Data, Error>;
 
+/// How long background tasks get to wind down after a shutdown signal
+const SHUTDOWN_GRACE: std::time::Duration = std::time::Duration::from_secs(10);
+
 /// Register all slash commands
 fn get_commands() -> Vec<poise::Command<Data, Error>> {
     vec![
         commands::immersion::immersion(),
+        commands::immersion::immersion_batch(),
+        commands::immersion::log_as_immersion(),
         commands::stat::stat(),
+        commands::profile::profile(),
         commands::leaderboard::leaderboard(),
         commands::log::log(),
+        commands::goal::goal(),
         commands::help::help(),
         commands::config::config(),
         commands::register::register(),
         commands::novel::novel(),
         commands::afk::afk(),
+        commands::ayumi::ayumi(),
         commands::subs::subs(),
+        commands::title::title(),
+        commands::now::now(),
+        commands::vocab::vocab(),
         commands::export::export(),
+        commands::recalculate::recalculate(),
         commands::react::react(),
         commands::prompt::prompt(),
+        commands::quarantine::quarantine(),
+        commands::admin::admin(),
+        commands::ping::ping(),
+        commands::ping::status(),
+        commands::privacy::privacy(),
+        commands::link::link(),
+        commands::link::unlink(),
+        commands::reminder::reminder(),
+        commands::timezone::timezone(),
         commands::role_rank::role_rank(),
         commands::ayumu_exam::exam(),
         commands::ayumu_exam::profile(),
@@ -69,6 +112,8 @@ fn get_commands() -> Vec<poise::Command<Data, Error>> {
 
 #[tokio::main]
 async fn main() {
+    let started_at = std::time::Instant::now();
+
     // Initialize logging
     tracing_subscriber::registry()
         .with(tracing_subscriber::EnvFilter::new(
@@ -93,10 +138,8 @@ async fn main() {
         .build()
         .expect("Failed to create HTTP client");
 
-    // Initialize Firebase client
-    let firebase = FirebaseClient::from_file(http_client.clone(), "firebase-key.json")
-        .expect("Failed to load Firebase credentials");
-    let firebase = Arc::new(firebase);
+    // Firestore, or the in-memory store for local development
+    let firebase = api::storage::storage_from_env(http_client.clone());
 
     // Initialize Ayumu client
     let ayumu_base_url =
@@ -106,11 +149,18 @@ async fn main() {
 
     let guild_configs = Arc::new(DashMap::new());
     let role_rank_sessions = Arc::new(DashMap::new());
-    features::role_rank::restore_role_rank_sessions(&role_rank_sessions);
-    info!("Firebase client initialized");
+
+    let clock: Arc<dyn utils::clock::Clock> = Arc::new(utils::clock::SystemClock);
+    let quarantine = Arc::new(utils::quarantine::Quarantine::new(clock.clone()));
 
     // Setup framework
     let guild_configs_clone = guild_configs.clone();
+    let quarantine_clone = quarantine.clone();
+    let firebase_clone = firebase.clone();
+    let clock_clone = clock.clone();
+    let role_rank_sessions_clone = role_rank_sessions.clone();
+    let shutdown = utils::shutdown::Shutdown::new();
+    let shutdown_clone = shutdown.clone();
     let framework = poise::Framework::builder()
         .options(poise::FrameworkOptions {
             commands: get_commands(),
@@ -129,12 +179,18 @@ async fn main() {
                 mention_as_prefix: false,
                 ..Default::default()
             },
+            pre_command: |ctx| {
+                Box::pin(async move {
+                    ctx.set_invocation_data(std::time::Instant::now()).await;
+                })
+            },
+            post_command: |ctx| Box::pin(utils::metrics::record_command(ctx, true)),
             on_error: |error| {
                 Box::pin(async move {
                     match error {
                         poise::FrameworkError::Command { error, ctx, .. } => {
-                            error!("Command error: {:?}", error);
-                            let _ = ctx.say(format!("Error: {}", error)).await;
+                            utils::metrics::record_command(ctx, false).await;
+                            utils::error_reply::reply(ctx, error).await;
                         }
                         poise::FrameworkError::MissingUserPermissions {
                             missing_permissions,
@@ -188,7 +244,7 @@ async fn main() {
                     if let serenity::FullEvent::Message { new_message } = event {
                         // Handle AFK status
                         if let Err(e) =
-                            features::afk_handler::handle_afk_message(ctx, new_message).await
+                            features::afk_handler::handle_afk_message(ctx, new_message, data).await
                         {
                             error!("Error in AFK handler: {:?}", e);
                         }
@@ -200,19 +256,49 @@ async fn main() {
                             error!("Error in Role Rank message handler: {:?}", e);
                         }
 
+                        // Handle learning site link unfurls
+                        if let Err(e) =
+                            features::learning_links::handle_message(ctx, new_message, data).await
+                        {
+                            error!("Error in learning link handler: {:?}", e);
+                        }
+
                         // Handle Ayumi AI
                         if let Err(e) =
                             features::ayumi::handle_message(ctx, new_message, data).await
                         {
                             error!("Error in Ayumi handler: {:?}", e);
                         }
-                    } else if let serenity::FullEvent::InteractionCreate { interaction } = event {
-                        if let serenity::Interaction::Component(component) = interaction {
-                            if let Err(e) =
-                                features::role_rank::handle_interaction(ctx, component, data).await
-                            {
-                                error!("Error in Role Rank interaction handler: {:?}", e);
-                            }
+                    } else if let serenity::FullEvent::MessageUpdate { event, .. } = event {
+                        // Re-check edited k!quiz pastes
+                        if let Err(e) =
+                            features::role_rank::handle_message_update(ctx, event, data).await
+                        {
+                            error!("Error in Role Rank message update handler: {:?}", e);
+                        }
+                    } else if let serenity::FullEvent::GuildMemberAddition { new_member } = event {
+                        // Give back quiz roles earned right before leaving
+                        if let Err(e) =
+                            features::role_rank::handle_member_addition(ctx, new_member, data).await
+                        {
+                            error!("Error in Role Rank member join handler: {:?}", e);
+                        }
+                    } else if let serenity::FullEvent::ReactionAdd { add_reaction } = event {
+                        // Quick logging by reaction
+                        if let Err(e) =
+                            features::reaction_log::handle_reaction_add(ctx, add_reaction, data)
+                                .await
+                        {
+                            error!("Error in reaction log handler: {:?}", e);
+                        }
+                    } else if let serenity::FullEvent::InteractionCreate {
+                        interaction: serenity::Interaction::Component(component),
+                    } = event
+                    {
+                        if let Err(e) =
+                            features::role_rank::handle_interaction(ctx, component, data).await
+                        {
+                            error!("Error in Role Rank interaction handler: {:?}", e);
                         }
                     }
                     Ok(())
@@ -242,21 +328,56 @@ async fn main() {
                 // Also register globally as a fallback
                 // poise::builtins::register_globally(ctx, &framework.options().commands).await?;
 
-                Ok(Data {
+                let prompt_store = features::custom_prompt::prompt_store_from_env(firebase.clone());
+                let title_popularity = Arc::new(features::title_popularity::PopularityCache::new(
+                    firebase.clone(),
+                    clock_clone.clone(),
+                ));
+
+                let afk = Arc::new(features::afk_store::AfkStore::new(firebase.clone()));
+                afk.load().await;
+
+                features::role_rank::restore_role_rank_sessions(
+                    &ctx.http,
+                    &firebase,
+                    &role_rank_sessions,
+                )
+                .await;
+
+                let data = Data {
                     http_client,
                     firebase,
                     ayumu,
                     guild_configs: guild_configs_clone,
                     role_rank_sessions: role_rank_sessions.clone(),
-                })
+                    clock: clock_clone,
+                    prompt_store,
+                    quarantine: quarantine_clone,
+                    probe_history: Arc::new(utils::health::ProbeHistory::new()),
+                    title_popularity,
+                    afk,
+                    error_notices: Arc::new(DashMap::new()),
+                    started_at,
+                    shutdown: shutdown_clone,
+                };
+                features::role_rank::spawn_missed_result_scan(ctx.clone(), data.clone());
+                Ok(data)
             })
         })
         .build();
 
     // Build client - note: MESSAGE_CONTENT is privileged, enable in Discord Dev Portal if needed
-    let intents = serenity::GatewayIntents::GUILDS
+    let mut intents = serenity::GatewayIntents::GUILDS
         | serenity::GatewayIntents::GUILD_MESSAGES
+        | serenity::GatewayIntents::GUILD_MESSAGE_REACTIONS
         | serenity::GatewayIntents::MESSAGE_CONTENT;
+    // GUILD_MEMBERS is privileged as well and only gives returning members their quiz role
+    // back, so it's asked for once the portal allows it and GUILD_MEMBERS_INTENT=1 says so
+    if env::var("GUILD_MEMBERS_INTENT").as_deref() == Ok("1") {
+        intents |= serenity::GatewayIntents::GUILD_MEMBERS;
+    } else {
+        info!("GUILD_MEMBERS_INTENT is off, returning members won't get their quiz role back");
+    }
 
     let mut client = serenity::ClientBuilder::new(token, intents)
         .framework(framework)
@@ -266,108 +387,58 @@ async fn main() {
     // Run with graceful shutdown
     let shard_manager = client.shard_manager.clone();
 
-    // Background Task: Quiz Selector Refresh
-    let http = client.http.clone();
-    let configs = guild_configs.clone(); // This clone works if guild_configs is available.
-                                         // BUT guild_configs was moved into setup() at line 174 (original view).
-                                         // Wait, in line 94: let guild_configs = Arc::new(DashMap::new());
-                                         // In setup(): ... guild_configs: guild_configs.clone() ... this moves the Arc clone? No, the variable itself if captured.
-
-    // Add imports at top of file needed for this: use futures::StreamExt;
-
-    tokio::spawn(async move {
-        use futures::StreamExt;
-        let mut interval = tokio::time::interval(std::time::Duration::from_secs(300)); // Check every 5 minutes
-
-        loop {
-            interval.tick().await;
-
-            // Snapshot the configs to avoid holding locks during async operations
-            // We collect (GuildID, ChannelID) to be able to cleanup invalid configs
-            let channels_to_check: Vec<(String, String)> = configs
-                .iter()
-                .filter_map(|entry| {
-                    entry
-                        .value()
-                        .quiz_channel_id
-                        .clone()
-                        .map(|cid| (entry.key().clone(), cid))
-                })
-                .collect();
-
-            // Create a stream of futures for concurrent processing
-            let tasks = futures::stream::iter(channels_to_check)
-                .map(|(guild_id, channel_id_str)| {
-                    let http = http.clone();
-                    let configs = configs.clone();
-
-                    async move {
-                        if let Ok(channel_id) = channel_id_str.parse::<u64>().map(serenity::ChannelId::new) {
-                            // Check last message in channel
-                            match channel_id.messages(&http, serenity::GetMessages::new().limit(1)).await {
-                                Ok(messages) => {
-                                    let needs_refresh = if let Some(last_msg) = messages.first() {
-                                        !last_msg.author.bot
-                                    } else {
-                                        true
-                                    };
-
-                                    if needs_refresh {
-                                        // Find and delete old bot messages to clean up
-                                        if let Ok(history) = channel_id.messages(&http, serenity::GetMessages::new().limit(10)).await {
-                                            for msg in history {
-                                                if msg.author.bot && msg.embeds.iter().any(|e| e.title.as_deref() == Some("Quiz Selector")) {
-                                                    let _ = msg.delete(&http).await;
-                                                }
-                                            }
-                                        }
-
-                                        // Send new selector
-                                        if let Err(e) = crate::commands::role_rank::send_quiz_selector(&http, channel_id).await {
-                                            error!("Failed to auto-refresh quiz selector: {:?}", e);
-                                        }
-                                    }
-                                },
-                                Err(e) => {
-                                    // Handle 404 Unknown Channel to stop log spam
-                                    let is_unknown_channel = match &e {
-                                        serenity::Error::Http(http_err) => {
-                                            match http_err {
-                                                serenity::http::HttpError::UnsuccessfulRequest(resp) => {
-                                                    resp.status_code.as_u16() == 404 || resp.error.code == 10003
-                                                },
-                                                _ => false
-                                            }
-                                        },
-                                        _ => false
-                                    };
-
-                                    if is_unknown_channel {
-                                        tracing::warn!("Quiz channel {} in guild {} is invalid/deleted. Removing from cache to stop errors.", channel_id, guild_id);
-                                        if let Some(mut config) = configs.get_mut(&guild_id) {
-                                            if config.quiz_channel_id == Some(channel_id_str) {
-                                                config.quiz_channel_id = None;
-                                            }
-                                        }
-                                    } else {
-                                        error!("Failed to check quiz channel messages: {:?}", e);
-                                    }
-                                }
-                            }
-                        }
-                    }
-                })
-                .buffer_unordered(10); // Process 10 guilds concurrently
+    // Background Task: Expire abandoned quiz channels
+    features::role_rank::spawn_session_cleanup(
+        client.http.clone(),
+        firebase_clone.clone(),
+        role_rank_sessions_clone,
+        clock.clone(),
+        &shutdown,
+    );
+
+    // Background Task: Weekly Recap
+    features::recap::WeeklyRecap::new(
+        client.http.clone(),
+        firebase_clone.clone(),
+        guild_configs.clone(),
+        clock.clone(),
+    )
+    .spawn(&shutdown);
+
+    // Background Task: Hourly streak reminder DMs
+    features::streak_reminder::StreakReminder::new(
+        client.http.clone(),
+        firebase_clone.clone(),
+        clock.clone(),
+    )
+    .spawn(&shutdown);
+
+    // Background Task: Hourly AniList/VNDB cache stats
+    api::cache::spawn_stats_logger();
+
+    // Background Task: Command, Firestore and LLM metrics every 10 minutes
+    utils::metrics::spawn_logger();
 
-            tasks.collect::<Vec<_>>().await;
-        }
-    });
+    // Background Task: Quiz Selector Refresh
+    features::quiz_refresher::QuizRefresher::new(
+        client.http.clone(),
+        firebase_clone,
+        guild_configs.clone(),
+        quarantine,
+        clock,
+    )
+    .spawn(&shutdown);
 
     tokio::spawn(async move {
-        tokio::signal::ctrl_c()
-            .await
-            .expect("Failed to register Ctrl+C handler");
+        utils::shutdown::signal().await;
         info!("Shutting down...");
+        // Background work finishes while the gateway is still up
+        if !shutdown.shutdown(SHUTDOWN_GRACE).await {
+            warn!(
+                "Background tasks still running after {:?}, stopping anyway",
+                SHUTDOWN_GRACE
+            );
+        }
         shard_manager.shutdown_all().await;
     });
 
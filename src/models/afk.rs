@@ -0,0 +1,86 @@
+// AFK data model
+// Matches the Firebase AFK document (`afk/{user id}`)
+
+use serde::{Deserialize, Serialize};
+
+/// AFK user data
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AfkData {
+    pub username: String,
+    pub reason: String,
+    /// Unix time the user went AFK
+    #[serde(rename = "since")]
+    pub timestamp: u64,
+    #[serde(default)]
+    pub avatar_url: String,
+    /// Unix time the status lapses on its own, `None` until the next message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    /// Guild the status was set in, `None` from a DM
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guild_id: Option<String>,
+}
+
+impl AfkData {
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|at| at <= now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn data() -> AfkData {
+        AfkData {
+            username: "yuki".to_string(),
+            reason: "AFK".to_string(),
+            timestamp: 100,
+            avatar_url: String::new(),
+            expires_at: Some(200),
+            guild_id: Some("42".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_expiry() {
+        let data = data();
+        assert!(!data.is_expired(199));
+        assert!(data.is_expired(200));
+        let forever = AfkData {
+            expires_at: None,
+            ..data
+        };
+        assert!(!forever.is_expired(u64::MAX));
+    }
+
+    #[test]
+    fn test_document_round_trip() {
+        let value = serde_json::to_value(data()).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "username": "yuki",
+                "reason": "AFK",
+                "since": 100,
+                "avatarUrl": "",
+                "expiresAt": 200,
+                "guildId": "42"
+            })
+        );
+        assert_eq!(serde_json::from_value::<AfkData>(value).unwrap(), data());
+
+        // Optional fields may be missing, `_id` from listing is ignored
+        let minimal: AfkData = serde_json::from_value(json!({
+            "_id": "7",
+            "username": "rin",
+            "reason": "tidur",
+            "since": 5
+        }))
+        .unwrap();
+        assert_eq!(minimal.expires_at, None);
+        assert_eq!(minimal.guild_id, None);
+    }
+}
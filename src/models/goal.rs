@@ -0,0 +1,190 @@
+// Monthly immersion goals
+// Stored on the user document as a `goals` map keyed by media type
+
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::utils::formatters::format_number;
+use crate::utils::streak::log_date;
+
+/// A target amount for one media type in one month
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Goal {
+    pub amount: f64,
+    /// "YYYY-MM", the same format as `timestamps.month` on logs
+    pub month: String,
+    /// When the goal was first reached, so the celebration is only sent once
+    #[serde(default)]
+    pub reached_at: Option<String>,
+}
+
+impl Goal {
+    pub fn new(amount: f64, month: String) -> Self {
+        Self {
+            amount,
+            month,
+            reached_at: None,
+        }
+    }
+
+    /// Goals only count for the month they were set in
+    pub fn is_active(&self, month: &str) -> bool {
+        self.month == month
+    }
+}
+
+/// Month key for a date, e.g. "2025-01"
+pub fn month_key(date: NaiveDate) -> String {
+    format!("{}-{:02}", date.year(), date.month())
+}
+
+/// Goals from a user document's `goals` field, unreadable entries are skipped
+pub fn parse_goals(goals: Option<&Value>) -> BTreeMap<String, Goal> {
+    goals
+        .and_then(|g| g.as_object())
+        .map(|goals| {
+            goals
+                .iter()
+                .filter_map(|(media_type, goal)| {
+                    let goal = serde_json::from_value::<Goal>(goal.clone()).ok()?;
+                    Some((media_type.clone(), goal))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Fields for saving the whole goals map (the update mask only covers top-level keys)
+pub fn goals_update(goals: &BTreeMap<String, Goal>) -> Value {
+    serde_json::json!({ "goals": goals })
+}
+
+/// Sum of a media type's log amounts in the given month
+pub fn month_progress(logs: &[Value], media_type: &str, month: &str) -> f64 {
+    logs.iter()
+        .filter(|log| {
+            log.get("activity")
+                .and_then(|a| a.get("type"))
+                .and_then(|t| t.as_str())
+                == Some(media_type)
+        })
+        .filter(|log| log_month(log).as_deref() == Some(month))
+        .filter_map(|log| {
+            log.get("activity")
+                .and_then(|a| a.get("amount"))
+                .and_then(|a| a.as_f64())
+        })
+        .sum()
+}
+
+/// Month of a log, from `timestamps.month` or the activity date for older logs
+fn log_month(log: &Value) -> Option<String> {
+    log.get("timestamps")
+        .and_then(|t| t.get("month"))
+        .and_then(|m| m.as_str())
+        .map(str::to_string)
+        .or_else(|| log_date(log).and_then(|d| d.get(..7).map(str::to_string)))
+}
+
+/// Whether this log pushed the total over the target
+pub fn crossed(before: f64, after: f64, target: f64) -> bool {
+    before < target && after >= target
+}
+
+/// Whole percent of the target reached (can go past 100)
+pub fn percent(progress: f64, target: f64) -> u32 {
+    if target <= 0.0 {
+        return 0;
+    }
+    (progress / target * 100.0).floor().max(0.0) as u32
+}
+
+/// Text progress bar, e.g. "▓▓▓▓░░░░░░"
+pub fn progress_bar(progress: f64, target: f64) -> String {
+    const WIDTH: usize = 10;
+    let filled = ((percent(progress, target) as usize) * WIDTH / 100).min(WIDTH);
+    format!("{}{}", "▓".repeat(filled), "░".repeat(WIDTH - filled))
+}
+
+/// e.g. "12,400 / 30,000 characters — 41%"
+pub fn progress_text(progress: f64, target: f64, unit: &str) -> String {
+    format!(
+        "{} / {} {} — {}%",
+        format_number(progress.round() as i64),
+        format_number(target.round() as i64),
+        unit,
+        percent(progress, target)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_support::log;
+
+    #[test]
+    fn test_month_progress_only_counts_that_month_and_type() {
+        let logs = vec![
+            log("reading", 10000.0)
+                .date("2025-01-03")
+                .month_of_date()
+                .build(),
+            log("reading", 2400.0)
+                .date("2025-01-31")
+                .month_of_date()
+                .build(),
+            log("reading", 5000.0)
+                .date("2024-12-31")
+                .month_of_date()
+                .build(),
+            log("anime", 3.0).date("2025-01-10").month_of_date().build(),
+            // Legacy log without a month field
+            log("reading", 100.0).date("2025-01-15").build(),
+        ];
+
+        assert_eq!(month_progress(&logs, "reading", "2025-01"), 12500.0);
+        assert_eq!(month_progress(&logs, "reading", "2025-02"), 0.0);
+    }
+
+    #[test]
+    fn test_progress_formatting() {
+        assert_eq!(
+            progress_text(12400.0, 30000.0, "characters"),
+            "12,400 / 30,000 characters — 41%"
+        );
+        assert_eq!(progress_bar(12400.0, 30000.0), "▓▓▓▓░░░░░░");
+        assert_eq!(progress_bar(45000.0, 30000.0), "▓▓▓▓▓▓▓▓▓▓");
+        assert_eq!(percent(45000.0, 30000.0), 150);
+    }
+
+    #[test]
+    fn test_crossed_only_on_the_log_that_reaches_it() {
+        assert!(crossed(29000.0, 30000.0, 30000.0));
+        assert!(!crossed(30000.0, 31000.0, 30000.0));
+        assert!(!crossed(1000.0, 2000.0, 30000.0));
+    }
+
+    #[test]
+    fn test_goals_roundtrip_and_month_rollover() {
+        let mut goals = BTreeMap::new();
+        goals.insert(
+            "reading".to_string(),
+            Goal::new(
+                30000.0,
+                month_key(NaiveDate::from_ymd_opt(2025, 1, 20).unwrap()),
+            ),
+        );
+
+        let doc = goals_update(&goals);
+        assert_eq!(doc["goals"]["reading"]["month"], "2025-01");
+        let parsed = parse_goals(doc.get("goals"));
+        assert_eq!(parsed, goals);
+
+        let goal = &parsed["reading"];
+        assert!(goal.is_active("2025-01"));
+        assert!(!goal.is_active("2025-02"));
+    }
+}
@@ -1,18 +1,259 @@
-use serde::{Deserialize, Serialize};
+// Guild data model
+// Matches the Firebase guild document (`guilds/{id}`)
+//
+// Documents written by the Node.js bot sometimes store ids as numbers, so every id
+// field accepts both forms and is written back as a string.
+
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use tracing::warn;
 
 /// Guild (Server) specific configuration
-#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
 pub struct GuildConfig {
     /// Channel ID where Ayumi (AI) is active
+    #[serde(deserialize_with = "opt_id")]
     pub ayumi_channel_id: Option<String>,
     /// Channel ID for Quiz events
+    #[serde(deserialize_with = "opt_id")]
     pub quiz_channel_id: Option<String>,
     /// Category ID for Quiz channels
+    #[serde(deserialize_with = "opt_id")]
     pub quiz_category_id: Option<String>,
     /// Channel ID for welcome messages
+    #[serde(deserialize_with = "opt_id")]
     pub welcome_channel_id: Option<String>,
     /// Channel ID for Immersion logs
+    #[serde(deserialize_with = "opt_id")]
     pub immersion_channel_id: Option<String>,
     /// Channel ID for Role Rank Announcements
+    #[serde(deserialize_with = "opt_id")]
     pub role_rank_announcement_channel_id: Option<String>,
+    /// Channel ID for the weekly recap (unset means no recap)
+    #[serde(deserialize_with = "opt_id")]
+    pub recap_channel_id: Option<String>,
+    /// Channel ID for moderator reports such as the weekly quiz digest
+    #[serde(deserialize_with = "opt_id")]
+    pub mod_log_channel_id: Option<String>,
+    /// Channel ID where every immersion log is announced in one line (unset means no feed)
+    #[serde(deserialize_with = "opt_id")]
+    pub activity_feed_channel_id: Option<String>,
+    /// User IDs of Kotoba-compatible quiz bots (empty means Kotoba itself)
+    #[serde(deserialize_with = "id_list")]
+    pub quiz_bot_ids: Vec<String>,
+    /// Unfurl jpdb/Bunpro/WaniKani links posted in the immersion channel
+    pub unfurl_learning_links: bool,
+    /// Whether members may view other members' /stat (unset means allowed)
+    pub allow_stat_lookup: Option<bool>,
+    /// Members who muted Ayumi with `/ayumi mute`, ignored in the Ayumi channel
+    #[serde(deserialize_with = "id_list")]
+    pub ayumi_muted_user_ids: Vec<String>,
+    /// Language code of bot messages ("id" or "en", unset means Indonesian)
+    pub language: Option<String>,
+    /// Title of the role rank quiz selector (unset means "Quiz Selector")
+    pub quiz_selector_title: Option<String>,
+    /// Description of the quiz selector (unset means the built-in text in the guild's language)
+    pub quiz_selector_description: Option<String>,
+    /// Role given for each quiz level, keyed by level ("0" to "7").
+    /// Unset means the roles of our own server.
+    #[serde(deserialize_with = "opt_id_map")]
+    pub quiz_role_ids: Option<HashMap<String, String>>,
+    /// Per-media-type points multipliers, keyed like the stats map (e.g. "anime")
+    pub points_overrides: Option<HashMap<String, f64>>,
+    /// Emoji that logs a fixed amount when reacted on a message in one channel
+    pub reaction_log: Option<ReactionLogConfig>,
+    /// Fields this version doesn't know about, kept so saving doesn't drop them
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Quick logging by reaction, e.g. 📖 on the daily reading prompt logs 15 minutes
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ReactionLogConfig {
+    #[serde(deserialize_with = "id")]
+    pub channel_id: String,
+    /// A unicode emoji, or a custom one as "<:name:id>"
+    pub emoji: String,
+    /// Stats key of the media type, e.g. "reading_time"
+    pub media_type: String,
+    pub amount: f64,
+}
+
+/// An id stored as a string or as a number
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Id {
+    Text(String),
+    Number(u64),
+}
+
+impl From<Id> for String {
+    fn from(id: Id) -> Self {
+        match id {
+            Id::Text(s) => s,
+            Id::Number(n) => n.to_string(),
+        }
+    }
+}
+
+fn id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(Id::deserialize(deserializer)?.into())
+}
+
+fn opt_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    Ok(Option::<Id>::deserialize(deserializer)?.map(String::from))
+}
+
+fn opt_id_map<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<HashMap<String, String>>, D::Error> {
+    Ok(
+        Option::<HashMap<String, Id>>::deserialize(deserializer)?.map(|map| {
+            map.into_iter()
+                .map(|(key, id)| (key, String::from(id)))
+                .collect()
+        }),
+    )
+}
+
+fn id_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    Ok(Option::<Vec<Id>>::deserialize(deserializer)?
+        .unwrap_or_default()
+        .into_iter()
+        .map(String::from)
+        .collect())
+}
+
+impl GuildConfig {
+    /// Config from a guild document. A field that can't be read is logged and left at
+    /// its default instead of discarding the whole config.
+    pub fn from_doc(doc: Value) -> Self {
+        let Value::Object(mut fields) = doc else {
+            warn!("Guild config is not an object, using defaults");
+            return Self::default();
+        };
+        // Added by `get_all_documents`, not part of the stored document
+        fields.remove("_id");
+
+        let fields = match serde_json::from_value(Value::Object(fields.clone())) {
+            Ok(config) => return config,
+            Err(_) => fields,
+        };
+        let mut readable = Map::new();
+        let mut failed = Vec::new();
+        for (key, value) in fields {
+            let single = Value::Object(Map::from_iter([(key.clone(), value.clone())]));
+            if serde_json::from_value::<Self>(single).is_ok() {
+                readable.insert(key, value);
+            } else {
+                failed.push(key);
+            }
+        }
+        warn!(
+            "Guild config fields could not be read, using defaults for: {}",
+            failed.join(", ")
+        );
+        serde_json::from_value(Value::Object(readable)).unwrap_or_default()
+    }
+
+    pub fn stat_lookup_allowed(&self) -> bool {
+        self.allow_stat_lookup.unwrap_or(true)
+    }
+
+    pub fn ayumi_muted(&self, user_id: u64) -> bool {
+        let user_id = user_id.to_string();
+        self.ayumi_muted_user_ids.contains(&user_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_reads_string_and_numeric_ids() {
+        let config = GuildConfig::from_doc(json!({
+            "_id": "123456789012345678",
+            "quiz_channel_id": "1100000000000000001",
+            "quiz_category_id": 1100000000000000002u64,
+            "immersion_channel_id": null,
+            "quiz_bot_ids": ["251239170058616833", 1100000000000000003u64],
+            "quiz_role_ids": { "0": "1100000000000000005", "1": 1100000000000000006u64 },
+        }));
+        assert_eq!(
+            config.quiz_channel_id.as_deref(),
+            Some("1100000000000000001")
+        );
+        assert_eq!(
+            config.quiz_category_id.as_deref(),
+            Some("1100000000000000002")
+        );
+        assert_eq!(config.immersion_channel_id, None);
+        assert_eq!(
+            config.quiz_bot_ids,
+            vec!["251239170058616833", "1100000000000000003"]
+        );
+        let roles = config.quiz_role_ids.as_ref().unwrap();
+        assert_eq!(roles["0"], "1100000000000000005");
+        assert_eq!(roles["1"], "1100000000000000006");
+        assert!(config.extra.is_empty());
+
+        let config = GuildConfig::from_doc(json!({
+            "reaction_log": {
+                "channel_id": 1100000000000000007u64,
+                "emoji": "📖",
+                "media_type": "reading_time",
+                "amount": 15
+            }
+        }));
+        let reaction_log = config.reaction_log.unwrap();
+        assert_eq!(reaction_log.channel_id, "1100000000000000007");
+        assert_eq!(reaction_log.amount, 15.0);
+    }
+
+    #[test]
+    fn test_legacy_document_round_trip() {
+        // Written by the Node.js bot: numeric ids, a field renamed since, no newer fields
+        let legacy = json!({
+            "ayumi_channel_id": 1100000000000000001u64,
+            "welcome_channel_id": "1100000000000000004",
+            "welcomeMessage": "Selamat datang!",
+            "levels": { "1": "N5" },
+        });
+        let config = GuildConfig::from_doc(legacy);
+        assert_eq!(
+            config.ayumi_channel_id.as_deref(),
+            Some("1100000000000000001")
+        );
+        assert!(config.quiz_bot_ids.is_empty());
+        assert!(config.stat_lookup_allowed());
+
+        let written = serde_json::to_value(&config).unwrap();
+        assert_eq!(written["ayumi_channel_id"], json!("1100000000000000001"));
+        assert_eq!(written["welcomeMessage"], json!("Selamat datang!"));
+        assert_eq!(written["levels"], json!({ "1": "N5" }));
+        assert_eq!(GuildConfig::from_doc(written), config);
+    }
+
+    #[test]
+    fn test_unreadable_field_keeps_the_rest() {
+        let config = GuildConfig::from_doc(json!({
+            "quiz_category_id": "1100000000000000002",
+            "unfurl_learning_links": "yes",
+            "points_overrides": { "anime": 1.5 },
+        }));
+        assert_eq!(
+            config.quiz_category_id.as_deref(),
+            Some("1100000000000000002")
+        );
+        assert!(!config.unfurl_learning_links);
+        assert_eq!(config.points_overrides.unwrap()["anime"], 1.5);
+        assert_eq!(
+            GuildConfig::from_doc(json!("broken")),
+            GuildConfig::default()
+        );
+    }
 }
@@ -48,6 +48,9 @@ pub struct LogMetadata {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct VndbInfo {
+    /// Missing on logs saved before the id was stored
+    #[serde(default)]
+    pub id: Option<String>,
     pub developer: Option<String>,
     pub released: Option<String>,
     pub length: Option<i32>,
@@ -78,7 +81,7 @@ pub struct ImmersionLog {
 
 impl ImmersionLog {
     /// Create a new immersion log
-    #[allow(dead_code)]
+    #[allow(dead_code, clippy::too_many_arguments)]
     pub fn new(
         user_id: &str,
         username: &str,
@@ -1,4 +1,6 @@
 // Data models module
+pub mod afk;
+pub mod goal;
 pub mod guild;
 pub mod immersion_log;
 pub mod stats;
@@ -1,90 +1,177 @@
 // User data model
 // Matches Firebase user document structure
+//
+// Every struct keeps the fields it doesn't know about in `extra`, so writing a document
+// back never drops data written by other tools (the Node.js bot, the web dashboard).
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+use crate::models::goal::{self, Goal};
 
 /// User profile information
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
 pub struct UserProfile {
     pub id: String,
     pub username: String,
-    #[serde(rename = "displayName")]
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub display_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub avatar: Option<String>,
-    #[serde(rename = "lastSeen")]
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub last_seen: Option<String>,
+    /// Kept off public rankings, see `utils::privacy`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub leaderboard_opt_out: bool,
+    /// "hide" or "anonymize", hide when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub leaderboard_opt_out_mode: Option<String>,
+    /// tz database name set with `/timezone set`, WIB when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 /// Per-media-type statistics
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
 pub struct MediaStats {
     pub total: f64,
-    pub sessions: i32,
-    #[serde(rename = "lastActivity")]
+    pub sessions: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub last_activity: Option<String>,
-    #[serde(rename = "currentStreak")]
-    pub current_streak: i32,
-    #[serde(rename = "bestStreak")]
-    pub best_streak: i32,
-    pub unit: String,
-    pub label: String,
+    pub current_streak: i64,
+    pub best_streak: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Stats keyed by media type (e.g. "anime")
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(transparent)]
+pub struct UserStats(pub BTreeMap<String, MediaStats>);
+
+impl UserStats {
+    pub fn get(&self, media_type: &str) -> Option<&MediaStats> {
+        self.0.get(media_type)
+    }
+
+    /// Stats for a media type, created empty if missing
+    pub fn entry(&mut self, media_type: &str) -> &mut MediaStats {
+        self.0.entry(media_type.to_string()).or_default()
+    }
+
+    /// Logged total for a media type, 0 when nothing was logged
+    pub fn total(&self, media_type: &str) -> f64 {
+        self.get(media_type).map(|s| s.total).unwrap_or(0.0)
+    }
+
+    pub fn total_sessions(&self) -> i64 {
+        self.0.values().map(|s| s.sessions).sum()
+    }
+
+    pub fn media_types(&self) -> Vec<String> {
+        self.0.keys().cloned().collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &MediaStats)> {
+        self.0.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
 }
 
 /// User summary data
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
 pub struct UserSummary {
-    #[serde(rename = "totalSessions")]
-    pub total_sessions: i32,
-    #[serde(rename = "lastActivity")]
+    pub total_sessions: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub last_activity: Option<String>,
-    #[serde(rename = "joinDate")]
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub join_date: Option<String>,
-    #[serde(rename = "activeTypes")]
     pub active_types: Vec<String>,
+    /// Streak freezes held, earned every 30 days of streak
+    #[serde(skip_serializing_if = "is_zero")]
+    pub streak_freezes: u32,
+    /// Missed days (YYYY-MM-DD) a streak freeze was spent on
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub frozen_dates: Vec<String>,
+    /// What the member is reading or watching now, set with /now
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current: Option<CurrentItem>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A member's "now reading/watching" status, under `summary.current`
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CurrentItem {
+    /// Stats key of the media type, e.g. "visual_novel"
+    #[serde(rename = "type")]
+    pub media_type: String,
+    pub title: String,
+    /// When it was set (RFC3339)
+    pub set_at: String,
+    /// Whether the member was already asked to update it, they're asked at most once
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub nudged: bool,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
 }
 
-/// Streak information
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-#[allow(dead_code)]
-pub struct StreakInfo {
-    pub current: i32,
-    pub longest: i32,
-    #[serde(rename = "lastUpdated")]
-    pub last_updated: Option<String>,
+/// Document timestamps
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct UserTimestamps {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_log: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
-/// Full user document
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-#[allow(dead_code)]
-pub struct User {
+/// Opt-in daily streak reminder, under `reminders.streak`
+#[derive(Debug, Clone, Deserialize, Default, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct StreakReminderSettings {
+    pub enabled: bool,
+    /// Hour of the day the reminder is sent at, in the member's timezone
+    pub hour: u32,
+    /// Effective date (YYYY-MM-DD) of the last reminder, at most one a day
+    pub last_reminded_date: Option<String>,
+}
+
+/// Full user document (`users/{id}`)
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct UserDoc {
     pub profile: UserProfile,
-    pub stats: HashMap<String, MediaStats>,
+    pub stats: UserStats,
     pub summary: UserSummary,
-    pub streaks: Option<StreakInfo>,
+    pub timestamps: UserTimestamps,
+    /// Id of the last log whose stats increment was applied, used to make saves idempotent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_applied_log: Option<String>,
+    /// Everything else (preferences, goals, fields from other clients)
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
-impl User {
-    /// Create a new user with basic info
-    #[allow(dead_code)]
-    pub fn new(id: &str, username: &str, display_name: Option<&str>, avatar: Option<&str>) -> Self {
-        Self {
-            profile: UserProfile {
-                id: id.to_string(),
-                username: username.to_string(),
-                display_name: display_name.map(|s| s.to_string()),
-                avatar: avatar.map(|s| s.to_string()),
-                last_seen: Some(chrono::Utc::now().to_rfc3339()),
-            },
-            stats: HashMap::new(),
-            summary: UserSummary::default(),
-            streaks: None,
-        }
-    }
-
+impl UserDoc {
     /// Get total points across all media types
     #[allow(dead_code)]
     pub fn total_points(&self) -> i64 {
@@ -96,9 +183,155 @@ impl User {
             .sum()
     }
 
-    /// Get total sessions
-    #[allow(dead_code)]
-    pub fn total_sessions(&self) -> i32 {
-        self.stats.values().map(|s| s.sessions).sum()
+    /// A boolean under `preferences`, false when unset
+    pub fn preference(&self, key: &str) -> bool {
+        self.extra
+            .get("preferences")
+            .and_then(|p| p.get(key))
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false)
+    }
+
+    /// API key of a linked jpdb.io account, under `integrations.jpdb.apiKey`
+    pub fn jpdb_api_key(&self) -> Option<&str> {
+        self.extra
+            .get("integrations")
+            .and_then(|i| i.get("jpdb"))
+            .and_then(|j| j.get("apiKey"))
+            .and_then(|k| k.as_str())
+            .filter(|k| !k.is_empty())
+    }
+
+    /// Streak reminder settings, `None` when never set up
+    pub fn streak_reminder(&self) -> Option<StreakReminderSettings> {
+        let value = self.extra.get("reminders")?.get("streak")?;
+        serde_json::from_value(value.clone()).ok()
+    }
+
+    /// Monthly goals stored on the document
+    pub fn goals(&self) -> BTreeMap<String, Goal> {
+        goal::parse_goals(self.extra.get("goals"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A document as written by /immersion plus fields this model doesn't know
+    fn existing_doc() -> Value {
+        json!({
+            "profile": {
+                "id": "123",
+                "username": "yuki",
+                "displayName": "Yuki",
+                "avatar": "https://cdn.example.com/a.png",
+                "lastSeen": "2025-01-15T10:00:00+00:00",
+                "pronouns": "they/them"
+            },
+            "stats": {
+                "anime": {
+                    "total": 24,
+                    "sessions": 3,
+                    "lastActivity": "2025-01-15T10:00:00+00:00",
+                    "bestStreak": 4,
+                    "currentStreak": 2,
+                    "unit": "episodes",
+                    "label": "Anime",
+                    "favourite": true
+                },
+                "reading": { "total": 35000.5, "sessions": 1, "bestStreak": 1, "currentStreak": 1 }
+            },
+            "summary": {
+                "totalSessions": 4,
+                "lastActivity": "2025-01-15T10:00:00+00:00",
+                "joinDate": "2024-06-01T00:00:00+00:00",
+                "activeTypes": ["anime", "reading"]
+            },
+            "timestamps": { "updated": "2025-01-15T10:00:00+00:00", "lastLog": "2025-01-15T10:00:00+00:00" },
+            "lastAppliedLog": "abc",
+            "preferences": { "colorblindMode": true },
+            "integrations": { "jpdb": { "apiKey": "secret", "linkedAt": "2025-01-10T00:00:00+00:00" } },
+            "reminders": { "streak": { "enabled": true, "hour": 20, "lastRemindedDate": "2025-01-14" } },
+            "goals": { "anime": { "amount": 20.0, "month": "2025-01" } },
+            "legacyNodeField": [1, 2, 3]
+        })
+    }
+
+    /// Numbers compare by value, since integer totals come back as floats
+    fn normalize(v: Value) -> Value {
+        match v {
+            Value::Number(n) => json!(n.as_f64().unwrap()),
+            Value::Array(a) => Value::Array(a.into_iter().map(normalize).collect()),
+            Value::Object(o) => {
+                Value::Object(o.into_iter().map(|(k, v)| (k, normalize(v))).collect())
+            }
+            other => other,
+        }
+    }
+
+    #[test]
+    fn test_existing_document_roundtrips_unchanged() {
+        let original = existing_doc();
+        let user: UserDoc = serde_json::from_value(original.clone()).unwrap();
+
+        assert_eq!(user.stats.total("anime"), 24.0);
+        assert_eq!(user.stats.get("anime").unwrap().best_streak, 4);
+        assert_eq!(user.profile.display_name.as_deref(), Some("Yuki"));
+        assert_eq!(user.last_applied_log.as_deref(), Some("abc"));
+        assert!(user.preference("colorblindMode"));
+        assert_eq!(user.jpdb_api_key(), Some("secret"));
+        assert_eq!(
+            user.streak_reminder(),
+            Some(StreakReminderSettings {
+                enabled: true,
+                hour: 20,
+                last_reminded_date: Some("2025-01-14".to_string()),
+            })
+        );
+        assert_eq!(user.goals()["anime"].amount, 20.0);
+
+        let written = serde_json::to_value(&user).unwrap();
+        assert_eq!(normalize(written), normalize(original));
+    }
+
+    #[test]
+    fn test_partial_documents_get_defaults() {
+        let user: UserDoc = serde_json::from_value(json!({
+            "stats": { "manga": { "total": 10 } }
+        }))
+        .unwrap();
+
+        assert_eq!(user.stats.total("manga"), 10.0);
+        assert_eq!(user.stats.get("manga").unwrap().sessions, 0);
+        assert_eq!(user.stats.total("anime"), 0.0);
+        assert!(user.summary.active_types.is_empty());
+        assert!(!user.preference("colorblindMode"));
+        assert_eq!(user.jpdb_api_key(), None);
+        assert_eq!(user.streak_reminder(), None);
+
+        // Missing optional fields are not written back as nulls
+        let written = serde_json::to_value(&user).unwrap();
+        assert!(written["stats"]["manga"].get("unit").is_none());
+        assert!(written.get("lastAppliedLog").is_none());
+    }
+
+    #[test]
+    fn test_updating_one_media_type_keeps_unknown_fields() {
+        let mut user: UserDoc = serde_json::from_value(existing_doc()).unwrap();
+        let anime = user.stats.entry("anime");
+        anime.total += 1.0;
+        anime.sessions += 1;
+        user.stats.entry("book").total = 5.0;
+
+        let written = serde_json::to_value(&user).unwrap();
+        assert_eq!(written["stats"]["anime"]["favourite"], true);
+        assert_eq!(written["stats"]["anime"]["sessions"], 4);
+        assert_eq!(written["stats"]["reading"]["total"], 35000.5);
+        assert_eq!(written["stats"]["book"]["total"], 5.0);
+        assert_eq!(written["profile"]["pronouns"], "they/them");
+        assert_eq!(written["legacyNodeField"], json!([1, 2, 3]));
+        assert_eq!(user.stats.total_sessions(), 5);
     }
 }
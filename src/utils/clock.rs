@@ -0,0 +1,90 @@
+// Clock abstraction
+// Lets time-dependent logic (streaks, effective dates, rate limits) run against a fake clock in tests
+
+use chrono::{DateTime, Utc};
+use std::time::Instant;
+
+/// Source of the current time
+pub trait Clock: Send + Sync {
+    /// Current wall-clock time in UTC
+    fn now_utc(&self) -> DateTime<Utc>;
+
+    /// Current monotonic instant (for cooldowns and TTLs)
+    fn now_instant(&self) -> Instant;
+}
+
+/// Real clock backed by the system time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_utc(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn now_instant(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Manually driven clock for tests
+/// Both the wall clock and the monotonic clock move together when advanced
+#[cfg(test)]
+pub struct MockClock {
+    state: std::sync::Mutex<(DateTime<Utc>, Instant)>,
+}
+
+#[cfg(test)]
+impl MockClock {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            state: std::sync::Mutex::new((start, Instant::now())),
+        }
+    }
+
+    /// Create a mock clock from an RFC 3339 timestamp
+    pub fn at(rfc3339: &str) -> Self {
+        let start = DateTime::parse_from_rfc3339(rfc3339)
+            .expect("invalid mock clock timestamp")
+            .with_timezone(&Utc);
+        Self::new(start)
+    }
+
+    /// Move the clock forward
+    pub fn advance(&self, by: chrono::Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.0 += by;
+        state.1 += by.to_std().expect("cannot advance mock clock backwards");
+    }
+}
+
+#[cfg(test)]
+impl Clock for MockClock {
+    fn now_utc(&self) -> DateTime<Utc> {
+        self.state.lock().unwrap().0
+    }
+
+    fn now_instant(&self) -> Instant {
+        self.state.lock().unwrap().1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_mock_clock_advances_both_clocks() {
+        let clock = MockClock::at("2025-01-15T10:00:00Z");
+        let start_instant = clock.now_instant();
+
+        clock.advance(Duration::minutes(5));
+
+        assert_eq!(clock.now_utc().to_rfc3339(), "2025-01-15T10:05:00+00:00");
+        assert_eq!(
+            clock.now_instant().duration_since(start_instant),
+            std::time::Duration::from_secs(300)
+        );
+    }
+}
@@ -4,10 +4,6 @@
 /// Activity at 1:30 AM on Jan 16 will count as Jan 15
 pub const DAY_END_HOUR: u32 = 2;
 
-/// Media type labels for display
-
-/// Units for each media type
-
 /// Get media type label
 pub fn get_media_label(media_type: &str) -> &'static str {
     match media_type {
@@ -41,7 +37,6 @@ pub fn get_unit(media_type: &str) -> &'static str {
 pub mod colors {
     pub const PRIMARY: u32 = 0x00bfff;
     pub const SUCCESS: u32 = 0x2ecc71;
-    #[allow(dead_code)]
     pub const ERROR: u32 = 0xff0000;
     pub const WARNING: u32 = 0xffa500;
     pub const INFO: u32 = 0x3498db;
@@ -52,10 +47,14 @@ pub mod colors {
 /// If current time is before DAY_END_HOUR (e.g., 2 AM), return yesterday's date
 #[allow(dead_code)]
 pub fn get_effective_date() -> chrono::NaiveDate {
-    use chrono::{Duration, Timelike, Utc};
+    effective_date_at(super::clock::SystemClock.now_utc())
+}
+
+/// Effective date for a given instant, same rules as `get_effective_date`
+pub fn effective_date_at(now_utc: chrono::DateTime<chrono::Utc>) -> chrono::NaiveDate {
+    use chrono::{Duration, Timelike};
 
     // WIB is UTC+7
-    let now_utc = Utc::now();
     let now_wib = now_utc + Duration::hours(7);
     let hours = now_wib.hour();
 
@@ -66,29 +65,75 @@ pub fn get_effective_date() -> chrono::NaiveDate {
     }
 }
 
-/// Get effective date string in YYYY-MM-DD format
-#[allow(dead_code)]
-pub fn get_effective_date_string() -> String {
-    get_effective_date().format("%Y-%m-%d").to_string()
+/// A timezone from the tz database, e.g. "Europe/Berlin"
+pub fn parse_timezone(name: &str) -> Option<chrono_tz::Tz> {
+    name.trim().parse().ok()
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_media_labels() {
-        assert_eq!(get_media_label("anime"), "Anime");
-        assert_eq!(get_media_label("visual_novel"), "Visual Novel");
+/// Effective date of an instant in a member's timezone, the day still ending at
+/// `DAY_END_HOUR` local time. Without a valid timezone it's WIB, as in `effective_date_at`.
+pub fn effective_date_in(
+    tz: Option<&str>,
+    now_utc: chrono::DateTime<chrono::Utc>,
+) -> chrono::NaiveDate {
+    use chrono::{Duration, Timelike};
+
+    let Some(tz) = tz.and_then(parse_timezone) else {
+        return effective_date_at(now_utc);
+    };
+    let local = now_utc.with_timezone(&tz);
+    if local.hour() < DAY_END_HOUR {
+        local.date_naive() - Duration::days(1)
+    } else {
+        local.date_naive()
     }
+}
 
-    #[test]
-    fn test_units() {
-        assert_eq!(get_unit("anime"), "episodes");
-        assert_eq!(get_unit("manga"), "pages");
+/// Wall-clock time of an instant in a member's timezone, WIB without a valid timezone
+pub fn local_datetime_in(
+    tz: Option<&str>,
+    at: chrono::DateTime<chrono::Utc>,
+) -> chrono::NaiveDateTime {
+    match tz.and_then(parse_timezone) {
+        Some(tz) => at.with_timezone(&tz).naive_local(),
+        None => (at + chrono::Duration::hours(7)).naive_utc(),
     }
 }
 
+/// Calendar date of an instant in a member's timezone, without the day end offset.
+/// WIB without a valid timezone.
+pub fn local_date_in(tz: Option<&str>, at: chrono::DateTime<chrono::Utc>) -> chrono::NaiveDate {
+    local_datetime_in(tz, at).date()
+}
+
+/// Hour of an instant in a member's timezone, WIB without a valid timezone
+pub fn local_hour_in(tz: Option<&str>, at: chrono::DateTime<chrono::Utc>) -> u32 {
+    use chrono::Timelike;
+
+    local_datetime_in(tz, at).hour()
+}
+
+/// When the effective day `date` starts in a member's timezone: `DAY_END_HOUR` local
+/// time, WIB without a valid timezone
+pub fn day_start_in(tz: Option<&str>, date: chrono::NaiveDate) -> chrono::DateTime<chrono::Utc> {
+    use chrono::{Offset, TimeZone};
+
+    let local = date.and_hms_opt(DAY_END_HOUR, 0, 0).unwrap();
+    let offset = match tz.and_then(parse_timezone) {
+        // The offset in effect at that instant, so a day starting in a DST gap still has one
+        Some(tz) => tz.offset_from_utc_datetime(&local).fix().local_minus_utc(),
+        None => 7 * 3600,
+    };
+    local.and_utc() - chrono::Duration::seconds(offset as i64)
+}
+
+/// Get effective date string in YYYY-MM-DD format
+#[allow(dead_code)]
+pub fn get_effective_date_string() -> String {
+    get_effective_date().format("%Y-%m-%d").to_string()
+}
+
+use super::clock::Clock;
 use crate::models::guild::GuildConfig;
 use crate::Data;
 use tracing::error;
@@ -103,7 +148,7 @@ pub async fn get_guild_config(data: &Data, guild_id: &str) -> Option<GuildConfig
     // 2. Fetch from Firebase
     match data.firebase.get_document("guilds", guild_id).await {
         Ok(Some(doc)) => {
-            let config = serde_json::from_value::<GuildConfig>(doc).unwrap_or_default();
+            let config = GuildConfig::from_doc(doc);
             // 3. Update Cache
             data.guild_configs
                 .insert(guild_id.to_string(), config.clone());
@@ -116,3 +161,89 @@ pub async fn get_guild_config(data: &Data, guild_id: &str) -> Option<GuildConfig
         }
     }
 }
+
+/// Timezone a member picked with `/timezone set`, `None` when unset or unreadable
+pub async fn get_user_timezone(data: &Data, user_id: &str) -> Option<String> {
+    match data.firebase.get_user(user_id).await {
+        Ok(user) => user.and_then(|u| u.profile.timezone),
+        Err(e) => {
+            error!("Failed to fetch timezone of {}: {:?}", user_id, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_media_labels() {
+        assert_eq!(get_media_label("anime"), "Anime");
+        assert_eq!(get_media_label("visual_novel"), "Visual Novel");
+    }
+
+    #[test]
+    fn test_units() {
+        assert_eq!(get_unit("anime"), "episodes");
+        assert_eq!(get_unit("manga"), "pages");
+    }
+
+    #[test]
+    fn test_effective_date_day_offset() {
+        use crate::utils::clock::MockClock;
+
+        // 01:30 WIB on Jan 16 still counts as Jan 15
+        let clock = MockClock::at("2025-01-15T18:30:00Z");
+        assert_eq!(effective_date_at(clock.now_utc()).to_string(), "2025-01-15");
+
+        // 02:00 WIB rolls over to Jan 16
+        clock.advance(chrono::Duration::minutes(30));
+        assert_eq!(effective_date_at(clock.now_utc()).to_string(), "2025-01-16");
+    }
+
+    #[test]
+    fn test_effective_date_in_timezone() {
+        let at = |s: &str| {
+            chrono::DateTime::parse_from_rfc3339(s)
+                .unwrap()
+                .with_timezone(&chrono::Utc)
+        };
+        let date = |tz, s| effective_date_in(tz, at(s)).to_string();
+
+        // No setting, or one that isn't a timezone, keeps WIB
+        for tz in [None, Some("Not/AZone")] {
+            assert_eq!(date(tz, "2025-01-15T18:30:00Z"), "2025-01-15");
+            assert_eq!(date(tz, "2025-01-15T19:00:00Z"), "2025-01-16");
+        }
+
+        // Berlin's day ends at 02:00 local, not at 20:00 like WIB's
+        let berlin = Some("Europe/Berlin");
+        assert_eq!(date(berlin, "2025-01-15T19:30:00Z"), "2025-01-15");
+        assert_eq!(date(berlin, "2025-01-16T00:59:00Z"), "2025-01-15");
+        assert_eq!(date(berlin, "2025-01-16T01:00:00Z"), "2025-01-16");
+
+        // Clocks jump from 02:00 to 03:00 on Mar 30, there's no 02:00 to end the day at
+        assert_eq!(date(berlin, "2025-03-30T00:59:00Z"), "2025-03-29");
+        assert_eq!(date(berlin, "2025-03-30T01:00:00Z"), "2025-03-30");
+        // Clocks fall back from 03:00 to 02:00 on Oct 26, 01:59 CEST is still Oct 25
+        assert_eq!(date(berlin, "2025-10-25T23:59:00Z"), "2025-10-25");
+        assert_eq!(date(berlin, "2025-10-26T00:00:00Z"), "2025-10-26");
+
+        assert_eq!(local_hour_in(berlin, at("2025-07-01T12:00:00Z")), 14);
+        assert_eq!(local_hour_in(None, at("2025-07-01T12:00:00Z")), 19);
+        assert_eq!(
+            local_date_in(Some("America/New_York"), at("2025-07-01T02:00:00Z")).to_string(),
+            "2025-06-30"
+        );
+        let day = chrono::NaiveDate::from_ymd_opt(2025, 7, 1).unwrap();
+        assert_eq!(day_start_in(None, day), at("2025-06-30T19:00:00Z"));
+        assert_eq!(day_start_in(berlin, day), at("2025-07-01T00:00:00Z"));
+
+        assert_eq!(
+            parse_timezone(" Asia/Tokyo ").map(|tz| tz.name()),
+            Some("Asia/Tokyo")
+        );
+        assert!(parse_timezone("UTC+7").is_none());
+    }
+}
@@ -0,0 +1,400 @@
+// Per-day immersion totals
+// Daily points of a user and the breakdown of a single day, for `/stat day`
+
+use chrono::{DateTime, Duration, NaiveDate};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+use super::config::local_datetime_in;
+use super::points::log_points;
+use super::streak::log_date_in;
+
+/// Days listed by `/stat day top`
+pub const TOP_DAYS: usize = 10;
+
+/// Points per activity date (YYYY-MM-DD)
+pub type DailyTotals = BTreeMap<String, i64>;
+
+/// Sum each log's points into its activity date, legacy logs dated in `tz`
+pub fn daily_totals<'a>(
+    logs: impl IntoIterator<Item = &'a Value>,
+    overrides: Option<&HashMap<String, f64>>,
+    tz: Option<&str>,
+) -> DailyTotals {
+    let mut totals = DailyTotals::new();
+    for log in logs {
+        if let Some(date) = log_date_in(log, tz) {
+            *totals.entry(date).or_insert(0) += log_points(log, None, overrides) as i64;
+        }
+    }
+    totals
+}
+
+/// The `count` days ending on `today`, oldest first
+pub fn trailing_days(today: NaiveDate, count: i64) -> Vec<NaiveDate> {
+    (0..count)
+        .rev()
+        .map(|back| today - Duration::days(back))
+        .collect()
+}
+
+/// Points of each media type on each of `days`, one series per entry of `media_types`
+/// in that order. Days without logs are 0, logs outside `days` are ignored. Legacy logs
+/// are dated in `tz`, see `log_date_in`.
+pub fn daily_media_points<'a>(
+    logs: impl IntoIterator<Item = &'a Value>,
+    days: &[NaiveDate],
+    media_types: &[&str],
+    overrides: Option<&HashMap<String, f64>>,
+    tz: Option<&str>,
+) -> Vec<(String, Vec<f64>)> {
+    let day_index: HashMap<String, usize> = days
+        .iter()
+        .enumerate()
+        .map(|(i, day)| (day.format("%Y-%m-%d").to_string(), i))
+        .collect();
+    let mut series: Vec<(String, Vec<f64>)> = media_types
+        .iter()
+        .map(|media_type| (media_type.to_string(), vec![0.0; days.len()]))
+        .collect();
+
+    for log in logs {
+        let Some(i) = log_date_in(log, tz).and_then(|date| day_index.get(&date).copied()) else {
+            continue;
+        };
+        let media_type = log["activity"]["type"].as_str();
+        if let Some((_, values)) = series
+            .iter_mut()
+            .find(|(name, _)| Some(name.as_str()) == media_type)
+        {
+            values[i] += log_points(log, None, overrides);
+        }
+    }
+    series
+}
+
+/// Totals from `daily_aggregates` documents (id = date, `points` = that day's points)
+pub fn totals_from_aggregates(docs: &[(String, Value)]) -> DailyTotals {
+    docs.iter()
+        .filter(|(id, _)| NaiveDate::parse_from_str(id, "%Y-%m-%d").is_ok())
+        .filter_map(|(id, doc)| {
+            let points = doc.get("points").and_then(|p| p.as_f64())?;
+            Some((id.clone(), points.round() as i64))
+        })
+        .collect()
+}
+
+/// Percentile rank of a day's points among all days, ties count half.
+/// `None` when there are no days to compare with.
+pub fn percentile_rank(totals: &DailyTotals, points: i64) -> Option<f64> {
+    if totals.is_empty() {
+        return None;
+    }
+    let below = totals.values().filter(|&&p| p < points).count() as f64;
+    let equal = totals.values().filter(|&&p| p == points).count() as f64;
+    Some((below + equal / 2.0) / totals.len() as f64 * 100.0)
+}
+
+/// Highest-point days, earlier dates first on ties
+pub fn top_days(totals: &DailyTotals, limit: usize) -> Vec<(String, i64)> {
+    let mut days: Vec<(String, i64)> = totals
+        .iter()
+        .filter(|(_, &points)| points > 0)
+        .map(|(date, &points)| (date.clone(), points))
+        .collect();
+    days.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    days.truncate(limit);
+    days
+}
+
+/// One log of the day
+#[derive(Debug, Clone, PartialEq)]
+pub struct DayEntry {
+    /// When it was logged, HH:MM in the member's timezone
+    pub time: Option<String>,
+    pub media_type: String,
+    pub amount: f64,
+    pub title: Option<String>,
+    pub points: i64,
+}
+
+/// Everything `/stat day` shows about one date
+#[derive(Debug, Clone, PartialEq)]
+pub struct DayDetail {
+    pub date: NaiveDate,
+    pub entries: Vec<DayEntry>,
+    pub total_points: i64,
+    /// Position of the day in its streak (1 = started one), 0 without logs
+    pub streak_day: u32,
+}
+
+/// Breakdown of `date` from the given logs, with the streak taken from the daily totals.
+/// Legacy logs are dated and all times shown in `tz`.
+pub fn day_detail(
+    date: NaiveDate,
+    logs: &[Value],
+    totals: &DailyTotals,
+    overrides: Option<&HashMap<String, f64>>,
+    tz: Option<&str>,
+) -> DayDetail {
+    let date_str = date.format("%Y-%m-%d").to_string();
+    let mut dated: Vec<(Option<&str>, DayEntry)> = logs
+        .iter()
+        .filter(|log| log_date_in(log, tz).as_deref() == Some(date_str.as_str()))
+        .map(|log| {
+            let activity = log.get("activity");
+            let field = |name: &str| activity.and_then(|a| a.get(name));
+            let created = log
+                .get("timestamps")
+                .and_then(|t| t.get("created"))
+                .and_then(|c| c.as_str());
+            let entry = DayEntry {
+                time: created.and_then(|c| local_time(c, tz)),
+                media_type: field("type")
+                    .and_then(|t| t.as_str())
+                    .unwrap_or("unknown")
+                    .to_string(),
+                amount: field("amount").and_then(|a| a.as_f64()).unwrap_or(0.0),
+                title: field("title")
+                    .and_then(|t| t.as_str())
+                    .filter(|t| !t.is_empty() && *t != "-")
+                    .map(str::to_string),
+                points: log_points(log, None, overrides) as i64,
+            };
+            (created, entry)
+        })
+        .collect();
+    // RFC3339 strings from one writer sort chronologically
+    dated.sort_by(|a, b| a.0.cmp(&b.0));
+    let entries: Vec<DayEntry> = dated.into_iter().map(|(_, entry)| entry).collect();
+
+    let streak_day = if entries.is_empty() {
+        0
+    } else {
+        let mut day = date;
+        let mut count = 1;
+        loop {
+            day -= Duration::days(1);
+            if !totals.contains_key(&day.format("%Y-%m-%d").to_string()) {
+                break;
+            }
+            count += 1;
+        }
+        count
+    };
+
+    DayDetail {
+        date,
+        total_points: entries.iter().map(|e| e.points).sum(),
+        entries,
+        streak_day,
+    }
+}
+
+/// HH:MM of an RFC3339 instant in `tz`, WIB without one
+fn local_time(rfc3339: &str, tz: Option<&str>) -> Option<String> {
+    let at = DateTime::parse_from_rfc3339(rfc3339).ok()?;
+    Some(
+        local_datetime_in(tz, at.with_timezone(&chrono::Utc))
+            .format("%H:%M")
+            .to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_support::log;
+    use serde_json::json;
+
+    fn totals(days: &[(&str, i64)]) -> DailyTotals {
+        days.iter().map(|(d, p)| (d.to_string(), *p)).collect()
+    }
+
+    #[test]
+    fn test_percentile_with_ties() {
+        let days = totals(&[
+            ("2025-01-01", 10),
+            ("2025-01-02", 20),
+            ("2025-01-03", 20),
+            ("2025-01-04", 40),
+        ]);
+        // One day below, two tied: (1 + 2/2) / 4
+        assert_eq!(percentile_rank(&days, 20), Some(50.0));
+        assert_eq!(percentile_rank(&days, 40), Some(87.5));
+        assert_eq!(percentile_rank(&days, 10), Some(12.5));
+        // Every day tied sits in the middle
+        assert_eq!(
+            percentile_rank(&totals(&[("2025-01-01", 5)]), 5),
+            Some(50.0)
+        );
+        assert_eq!(percentile_rank(&DailyTotals::new(), 5), None);
+    }
+
+    #[test]
+    fn test_top_days_order() {
+        let days = totals(&[
+            ("2025-03-01", 50),
+            ("2025-01-01", 50),
+            ("2025-02-01", 90),
+            ("2025-04-01", 0),
+        ]);
+        assert_eq!(
+            top_days(&days, 10),
+            vec![
+                ("2025-02-01".to_string(), 90),
+                ("2025-01-01".to_string(), 50),
+                ("2025-03-01".to_string(), 50),
+            ]
+        );
+        assert_eq!(top_days(&days, 1).len(), 1);
+    }
+
+    #[test]
+    fn test_day_detail_from_logs() {
+        let logs = vec![
+            log("manga", 40.0)
+                .title("Yotsuba&!")
+                .created("2025-08-14T13:00:00+00:00")
+                .date("2025-08-14")
+                .build(),
+            log("anime", 2.0)
+                .title("Frieren")
+                .created("2025-08-14T02:30:00+00:00")
+                .date("2025-08-14")
+                .build(),
+            log("anime", 1.0)
+                .title("Frieren")
+                .created("2025-08-13T12:00:00+00:00")
+                .date("2025-08-13")
+                .build(),
+            log("listening", 30.0)
+                .title("-")
+                .created("2025-08-15T12:00:00+00:00")
+                .date("2025-08-15")
+                .build(),
+            // Legacy log without a date: 2025-08-13T20:00Z is 03:00 WIB on the 14th
+            log("listening", 15.0)
+                .created("2025-08-13T20:00:00+00:00")
+                .build(),
+        ];
+        let totals = daily_totals(&logs, None, None);
+        let detail = day_detail(
+            NaiveDate::from_ymd_opt(2025, 8, 14).unwrap(),
+            &logs,
+            &totals,
+            None,
+            None,
+        );
+
+        let times: Vec<_> = detail.entries.iter().map(|e| e.time.as_deref()).collect();
+        assert_eq!(times, vec![Some("03:00"), Some("09:30"), Some("20:00")]);
+        assert_eq!(detail.entries[0].media_type, "listening");
+        assert_eq!(detail.entries[0].title, None);
+        assert_eq!(detail.entries[1].media_type, "anime");
+        assert_eq!(detail.entries[1].title.as_deref(), Some("Frieren"));
+        assert_eq!(
+            detail.total_points,
+            detail.entries.iter().map(|e| e.points).sum::<i64>()
+        );
+        assert_eq!(detail.total_points, totals["2025-08-14"]);
+        // The 13th had a log too
+        assert_eq!(detail.streak_day, 2);
+
+        let empty = day_detail(
+            NaiveDate::from_ymd_opt(2025, 8, 20).unwrap(),
+            &logs,
+            &totals,
+            None,
+            None,
+        );
+        assert!(empty.entries.is_empty());
+        assert_eq!(empty.streak_day, 0);
+
+        // In Berlin (CEST) the legacy log is 22:00 on the 13th
+        let berlin = Some("Europe/Berlin");
+        let totals = daily_totals(&logs, None, berlin);
+        let detail = day_detail(
+            NaiveDate::from_ymd_opt(2025, 8, 14).unwrap(),
+            &logs,
+            &totals,
+            None,
+            berlin,
+        );
+        let times: Vec<_> = detail.entries.iter().map(|e| e.time.as_deref()).collect();
+        assert_eq!(times, vec![Some("04:30"), Some("15:00")]);
+        assert_eq!(detail.total_points, totals["2025-08-14"]);
+        assert!(totals["2025-08-13"] > 0);
+    }
+
+    #[test]
+    fn test_totals_from_aggregates() {
+        let docs = vec![
+            ("2025-01-01".to_string(), json!({ "points": 120 })),
+            ("2025-01-02".to_string(), json!({ "points": 12.6 })),
+            ("summary".to_string(), json!({ "points": 999 })),
+            ("2025-01-03".to_string(), json!({})),
+        ];
+        assert_eq!(
+            totals_from_aggregates(&docs),
+            totals(&[("2025-01-01", 120), ("2025-01-02", 13)])
+        );
+    }
+
+    #[test]
+    fn test_daily_media_points_keeps_empty_days() {
+        let days = trailing_days(NaiveDate::from_ymd_opt(2025, 1, 12).unwrap(), 3);
+        assert_eq!(
+            days,
+            vec![
+                NaiveDate::from_ymd_opt(2025, 1, 10).unwrap(),
+                NaiveDate::from_ymd_opt(2025, 1, 11).unwrap(),
+                NaiveDate::from_ymd_opt(2025, 1, 12).unwrap(),
+            ]
+        );
+
+        let logs = vec![
+            log("anime", 2.0)
+                .title("A")
+                .created("2025-01-10T10:00:00+07:00")
+                .date("2025-01-10")
+                .build(),
+            log("anime", 1.0)
+                .title("A")
+                .created("2025-01-12T10:00:00+07:00")
+                .date("2025-01-12")
+                .build(),
+            log("listening", 30.0)
+                .title("B")
+                .created("2025-01-12T11:00:00+07:00")
+                .date("2025-01-12")
+                .build(),
+            // Legacy log dated by its creation in WIB: the 12th
+            log("listening", 15.0)
+                .created("2025-01-11T18:00:00Z")
+                .build(),
+            // Outside the window
+            log("anime", 5.0)
+                .title("A")
+                .created("2025-01-09T10:00:00+07:00")
+                .date("2025-01-09")
+                .build(),
+        ];
+        let media_types = ["anime", "manga", "listening"];
+        let series = daily_media_points(&logs, &days, &media_types, None, None);
+        let points = |i: usize| log_points(&logs[i], None, None);
+        let listening = points(2) + points(3);
+        assert_eq!(
+            series,
+            vec![
+                ("anime".to_string(), vec![points(0), 0.0, points(1)]),
+                ("manga".to_string(), vec![0.0, 0.0, 0.0]),
+                ("listening".to_string(), vec![0.0, 0.0, listening]),
+            ]
+        );
+
+        // 18:00 in London is still the 11th
+        let series = daily_media_points(&logs, &days, &media_types, None, Some("Europe/London"));
+        assert_eq!(series[2].1, vec![0.0, points(3), points(2)]);
+    }
+}
@@ -0,0 +1,200 @@
+// Discord sending helpers
+// Messages the bot posts on its own (quiz results, welcome messages, the quiz selector)
+// have nobody to show an error to. `send_checked` checks the bot's permissions first and
+// logs what's missing where (slowmode included), so a misconfigured channel shows up at
+// warn level instead of the feature silently doing nothing.
+
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use poise::serenity_prelude as serenity;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// How often a guild's admin channel hears about the same unusable channel
+const REPORT_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// When each unusable channel was last reported to an admin channel
+static REPORTED: Lazy<DashMap<serenity::ChannelId, Instant>> = Lazy::new(DashMap::new);
+
+/// Why `send_checked` didn't send
+#[derive(Debug, thiserror::Error)]
+pub enum SendError {
+    #[error("missing permissions: {0}")]
+    MissingPermissions(serenity::Permissions),
+    #[error(transparent)]
+    Discord(#[from] serenity::Error),
+}
+
+/// Permissions the message needs in its channel
+fn required_permissions(message: &serenity::CreateMessage) -> serenity::Permissions {
+    let mut required = serenity::Permissions::VIEW_CHANNEL | serenity::Permissions::SEND_MESSAGES;
+    // The builder keeps its fields private, its JSON body tells what it carries
+    let body = serde_json::to_value(message).unwrap_or_default();
+    if body["embeds"].as_array().is_some_and(|e| !e.is_empty()) {
+        required |= serenity::Permissions::EMBED_LINKS;
+    }
+    if !body["message_reference"].is_null() {
+        required |= serenity::Permissions::READ_MESSAGE_HISTORY;
+    }
+    required
+}
+
+/// The bot's permissions in a guild channel, when the cache has everything needed
+fn cached_permissions(
+    cache: &serenity::Cache,
+    channel_id: serenity::ChannelId,
+) -> Option<(serenity::GuildId, serenity::Permissions)> {
+    let guild_id = cache.guilds().into_iter().find(|guild_id| {
+        cache
+            .guild(*guild_id)
+            .is_some_and(|guild| guild.channels.contains_key(&channel_id))
+    })?;
+    let guild = cache.guild(guild_id)?;
+    let channel = guild.channels.get(&channel_id)?;
+    let member = guild.members.get(&cache.current_user().id)?;
+    Some((guild_id, guild.user_permissions_in(channel, member)))
+}
+
+/// Permissions a failed send points at, from Discord's error code
+fn missing_from_error(
+    e: &serenity::Error,
+    required: serenity::Permissions,
+) -> Option<serenity::Permissions> {
+    match e {
+        serenity::Error::Http(serenity::http::HttpError::UnsuccessfulRequest(resp)) => {
+            match resp.error.code {
+                // Missing Access
+                50001 => Some(serenity::Permissions::VIEW_CHANNEL),
+                // Missing Permissions, Discord doesn't say which
+                50013 => Some(required - serenity::Permissions::VIEW_CHANNEL),
+                // Slowmode rate limit, bots only skip it with Manage Messages
+                20016 => Some(serenity::Permissions::MANAGE_MESSAGES),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Whether a missing-permission report for the channel is due, marking it sent
+fn claim_report(channel_id: serenity::ChannelId, now: Instant) -> bool {
+    let mut due = false;
+    REPORTED
+        .entry(channel_id)
+        .and_modify(|last| {
+            if now.duration_since(*last) >= REPORT_INTERVAL {
+                *last = now;
+                due = true;
+            }
+        })
+        .or_insert_with(|| {
+            due = true;
+            now
+        });
+    due
+}
+
+/// Send a message the bot posts on its own. When the bot can't, the channel, guild and
+/// missing permissions are logged, and `report_to` (the guild's admin channel) is told
+/// once a day per channel.
+pub async fn send_checked(
+    cache_http: impl serenity::CacheHttp,
+    channel_id: serenity::ChannelId,
+    message: serenity::CreateMessage,
+    report_to: Option<serenity::ChannelId>,
+) -> Result<serenity::Message, SendError> {
+    let required = required_permissions(&message);
+    let cached = cache_http
+        .cache()
+        .and_then(|cache| cached_permissions(cache, channel_id));
+
+    let (guild_id, missing) = match cached {
+        Some((guild_id, permissions)) if !permissions.contains(required) => {
+            (Some(guild_id), required - permissions)
+        }
+        _ => match channel_id.send_message(cache_http.http(), message).await {
+            Ok(sent) => return Ok(sent),
+            Err(e) => match missing_from_error(&e, required) {
+                Some(missing) => (cached.map(|(guild_id, _)| guild_id), missing),
+                None => {
+                    warn!(
+                        "Failed to send a message to channel {}: {:?}",
+                        channel_id, e
+                    );
+                    return Err(e.into());
+                }
+            },
+        },
+    };
+
+    warn!(
+        channel_id = %channel_id,
+        guild_id = ?guild_id.map(|id| id.get()),
+        missing = %missing,
+        "Can't send to channel {}: the bot is missing {}",
+        channel_id,
+        missing
+    );
+
+    if let Some(report_to) = report_to.filter(|c| *c != channel_id) {
+        if claim_report(channel_id, Instant::now()) {
+            let notice = format!(
+                "⚠️ Aku tidak bisa mengirim pesan ke <#{}>. Izin yang belum diberikan: **{}**.",
+                channel_id, missing
+            );
+            if let Err(e) = report_to.say(cache_http.http(), notice).await {
+                warn!(
+                    "Failed to report channel {} to admin channel {}: {:?}",
+                    channel_id, report_to, e
+                );
+            }
+        }
+    }
+
+    Err(SendError::MissingPermissions(missing))
+}
+
+/// `send_checked` for a plain text message
+pub async fn say_checked(
+    cache_http: impl serenity::CacheHttp,
+    channel_id: serenity::ChannelId,
+    content: impl Into<String>,
+    report_to: Option<serenity::ChannelId>,
+) -> Result<serenity::Message, SendError> {
+    let message = serenity::CreateMessage::new().content(content);
+    send_checked(cache_http, channel_id, message, report_to).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_required_permissions() {
+        let base = serenity::Permissions::VIEW_CHANNEL | serenity::Permissions::SEND_MESSAGES;
+        assert_eq!(
+            required_permissions(&serenity::CreateMessage::new().content("hi")),
+            base
+        );
+        let embed = serenity::CreateMessage::new().embed(serenity::CreateEmbed::new().title("x"));
+        assert_eq!(
+            required_permissions(&embed),
+            base | serenity::Permissions::EMBED_LINKS
+        );
+        let reply = serenity::CreateMessage::new()
+            .reference_message((serenity::ChannelId::new(1), serenity::MessageId::new(2)));
+        assert_eq!(
+            required_permissions(&reply),
+            base | serenity::Permissions::READ_MESSAGE_HISTORY
+        );
+    }
+
+    #[test]
+    fn test_report_once_per_interval() {
+        let channel = serenity::ChannelId::new(987_654_321);
+        let now = Instant::now();
+        assert!(claim_report(channel, now));
+        assert!(!claim_report(channel, now + Duration::from_secs(60)));
+        assert!(claim_report(channel, now + REPORT_INTERVAL));
+    }
+}
@@ -92,7 +92,3 @@ pub const EMOJIS: &[Emoji] = &[
         name: "hanyaCheer",
     },
 ];
-
-pub fn get_emoji_by_id(id: &str) -> Option<&'static Emoji> {
-    EMOJIS.iter().find(|e| e.id == id)
-}
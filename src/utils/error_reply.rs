@@ -0,0 +1,231 @@
+// Command error replies
+// Turns a failed command into a message users can act on. The raw error (which can carry
+// Firestore URLs and response bodies) only goes to the log, and the same notice isn't
+// repeated in a channel while an outage lasts.
+
+use dashmap::DashMap;
+use poise::serenity_prelude as serenity;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+use tracing::error;
+
+/// How long a channel goes without hearing the same error notice again
+pub const NOTICE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// When each notice was last posted, keyed by channel and message
+pub type ErrorNotices = DashMap<(serenity::ChannelId, String), Instant>;
+
+/// An error caused by the user's input, its message is shown as is
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct ValidationError(pub String);
+
+/// What went wrong, as far as the user needs to know
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind {
+    /// Firestore or its token endpoint failed
+    Storage,
+    /// AniList, VNDB, YouTube, an LLM provider... timed out or is down
+    ExternalApi,
+    /// Discord refused for lack of permissions
+    Permission,
+    /// A `ValidationError`, e.g. an amount over its media type's cap
+    Validation(String),
+    /// Anything else, with a code to find it in the log
+    Unknown(String),
+}
+
+/// Hosts the storage backend talks to
+const STORAGE_HOSTS: [&str; 2] = ["firestore.googleapis.com", "oauth2.googleapis.com"];
+
+/// Classify an error by walking its source chain
+pub fn classify(error: &(dyn std::error::Error + 'static)) -> ErrorKind {
+    for cause in std::iter::successors(Some(error), |e| e.source()) {
+        if let Some(e) = cause.downcast_ref::<ValidationError>() {
+            return ErrorKind::Validation(e.0.clone());
+        }
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            let host = e.url().and_then(|url| url.host_str());
+            if host.is_some_and(|h| STORAGE_HOSTS.contains(&h)) {
+                return ErrorKind::Storage;
+            }
+            if e.is_timeout() || e.is_connect() || e.status().is_some_and(|s| s.is_server_error()) {
+                return ErrorKind::ExternalApi;
+            }
+        }
+        if cause
+            .downcast_ref::<tokio::time::error::Elapsed>()
+            .is_some()
+        {
+            return ErrorKind::ExternalApi;
+        }
+        if let Some(serenity::Error::Http(serenity::http::HttpError::UnsuccessfulRequest(resp))) =
+            cause.downcast_ref::<serenity::Error>()
+        {
+            // Missing Access, Missing Permissions
+            if matches!(resp.error.code, 50001 | 50013) {
+                return ErrorKind::Permission;
+            }
+        }
+        // The Firestore client reports failed responses as plain messages
+        if cause.to_string().starts_with("Firebase") {
+            return ErrorKind::Storage;
+        }
+    }
+    ErrorKind::Unknown(error_code(error))
+}
+
+/// Short code for an error, the same error always gets the same code
+pub fn error_code(error: &(dyn std::error::Error + 'static)) -> String {
+    let mut hasher = DefaultHasher::new();
+    for cause in std::iter::successors(Some(error), |e| e.source()) {
+        cause.to_string().hash(&mut hasher);
+    }
+    format!("E-{:06X}", hasher.finish() & 0xFF_FFFF)
+}
+
+impl ErrorKind {
+    /// What the user is told
+    pub fn message(&self) -> String {
+        match self {
+            ErrorKind::Storage => {
+                "⚠️ The database is having issues right now, please try again in a few minutes."
+                    .to_string()
+            }
+            ErrorKind::ExternalApi => {
+                "⚠️ An external service didn't respond in time, please try again in a few minutes."
+                    .to_string()
+            }
+            ErrorKind::Permission => {
+                "⚠️ I don't have the permissions needed to do that in this channel.".to_string()
+            }
+            ErrorKind::Validation(message) => message.clone(),
+            ErrorKind::Unknown(code) => format!(
+                "❌ Something went wrong. If it keeps happening, report code `{}` to an admin.",
+                code
+            ),
+        }
+    }
+}
+
+/// Whether the notice is due in the channel, marking it sent
+pub fn claim_notice(
+    notices: &ErrorNotices,
+    channel_id: serenity::ChannelId,
+    message: &str,
+    now: Instant,
+) -> bool {
+    let mut due = false;
+    notices
+        .entry((channel_id, message.to_string()))
+        .and_modify(|last| {
+            if now.duration_since(*last) >= NOTICE_INTERVAL {
+                *last = now;
+                due = true;
+            }
+        })
+        .or_insert_with(|| {
+            due = true;
+            now
+        });
+    due
+}
+
+/// Log a failed command in full and tell the user what they can do about it. A notice
+/// already posted in the channel recently is only repeated where nobody else sees it,
+/// input errors are always answered since they are about the user's own input.
+pub async fn reply(ctx: crate::Context<'_>, error: crate::Error) {
+    let kind = classify(error.as_ref());
+    let code = match &kind {
+        ErrorKind::Unknown(code) => code.clone(),
+        _ => error_code(error.as_ref()),
+    };
+    error!(
+        command = %ctx.command().qualified_name,
+        user_id = %ctx.author().id,
+        code = %code,
+        "Command /{} failed for user {} ({}): {:?}",
+        ctx.command().qualified_name,
+        ctx.author().id,
+        code,
+        error
+    );
+
+    let message = kind.message();
+    let reply = poise::CreateReply::default().content(message.clone());
+    let validation = matches!(kind, ErrorKind::Validation(_));
+    let sent = if validation
+        || claim_notice(
+            &ctx.data().error_notices,
+            ctx.channel_id(),
+            &message,
+            Instant::now(),
+        ) {
+        ctx.send(reply.ephemeral(validation)).await
+    } else if let poise::Context::Application(_) = ctx {
+        // The interaction still needs an answer
+        ctx.send(reply.ephemeral(true)).await
+    } else {
+        return;
+    };
+    if let Err(e) = sent {
+        error!("Failed to send the error reply: {:?}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let firebase = anyhow::anyhow!("Firebase error: 503 Service Unavailable");
+        let boxed: Box<dyn std::error::Error + Send + Sync> = firebase.into();
+        assert_eq!(classify(boxed.as_ref()), ErrorKind::Storage);
+
+        let invalid: Box<dyn std::error::Error + Send + Sync> =
+            Box::new(ValidationError("Amount must be positive".to_string()));
+        assert_eq!(
+            classify(invalid.as_ref()).message(),
+            "Amount must be positive"
+        );
+
+        let other: Box<dyn std::error::Error + Send + Sync> = "index out of range".into();
+        let kind = classify(other.as_ref());
+        assert_eq!(kind, ErrorKind::Unknown(error_code(other.as_ref())));
+        assert!(kind.message().contains("`E-"));
+    }
+
+    #[test]
+    fn test_error_code_is_stable() {
+        let a: Box<dyn std::error::Error + Send + Sync> = "boom".into();
+        let b: Box<dyn std::error::Error + Send + Sync> = "boom".into();
+        let c: Box<dyn std::error::Error + Send + Sync> = "bang".into();
+        assert_eq!(error_code(a.as_ref()), error_code(b.as_ref()));
+        assert_ne!(error_code(a.as_ref()), error_code(c.as_ref()));
+        assert_eq!(error_code(a.as_ref()).len(), 8);
+    }
+
+    #[test]
+    fn test_notice_once_per_interval() {
+        let notices = ErrorNotices::new();
+        let (channel, other) = (serenity::ChannelId::new(1), serenity::ChannelId::new(2));
+        let now = Instant::now();
+        assert!(claim_notice(&notices, channel, "down", now));
+        assert!(!claim_notice(
+            &notices,
+            channel,
+            "down",
+            now + Duration::from_secs(60)
+        ));
+        assert!(claim_notice(&notices, other, "down", now));
+        assert!(claim_notice(&notices, channel, "other", now));
+        assert!(claim_notice(
+            &notices,
+            channel,
+            "down",
+            now + NOTICE_INTERVAL
+        ));
+    }
+}
@@ -1,14 +1,13 @@
 // Formatting utilities
 
 /// Format a number with locale-aware thousands separators
-#[allow(dead_code)]
 pub fn format_number(n: i64) -> String {
     let s = n.to_string();
     let mut result = String::new();
     let chars: Vec<char> = s.chars().collect();
 
     for (i, c) in chars.iter().enumerate() {
-        if i > 0 && (chars.len() - i) % 3 == 0 && *c != '-' {
+        if i > 0 && (chars.len() - i).is_multiple_of(3) && *c != '-' {
             result.push(',');
         }
         result.push(*c);
@@ -45,13 +44,77 @@ pub fn format_points_short(points: i64) -> String {
     }
 }
 
-/// Truncate string to max length with ellipsis
-#[allow(dead_code)]
-pub fn truncate(s: &str, max_len: usize) -> String {
-    if s.len() <= max_len {
-        s.to_string()
-    } else {
-        format!("{}...", &s[..max_len.saturating_sub(3)])
+/// Truncate to at most `max_chars` characters, the last three being "..." when cut.
+/// Counts chars rather than bytes, so Japanese text can't be split mid-character, and
+/// doesn't leave a dangling joiner or variation selector of a cut emoji.
+pub fn truncate_chars(s: &str, max_chars: usize) -> String {
+    if s.chars().count() <= max_chars {
+        return s.to_string();
+    }
+    // No room for the ellipsis
+    if max_chars <= 3 {
+        return s.chars().take(max_chars).collect();
+    }
+    let kept: String = s.chars().take(max_chars.saturating_sub(3)).collect();
+    format!("{}...", kept.trim_end_matches(['\u{200d}', '\u{fe0f}']))
+}
+
+/// The longest prefix of `s` within `max_bytes`, backed off to a char boundary
+pub fn truncate_bytes_lossy(s: &str, max_bytes: usize) -> &str {
+    if s.len() <= max_bytes {
+        return s;
+    }
+    let mut end = max_bytes;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// Discord's limit for an embed description
+pub const EMBED_DESCRIPTION_LIMIT: usize = 4096;
+
+/// Discord's limit for an embed field value
+pub const EMBED_FIELD_LIMIT: usize = 1024;
+
+/// Join `lines` with newlines, keeping as many whole lines as fit in `max_chars` and
+/// ending with "…and N more" when some don't. A first line too long on its own is cut.
+/// Returns the text and how many lines were left out.
+pub fn fit_description(lines: Vec<String>, max_chars: usize) -> (String, usize) {
+    let total = lines.len();
+    let more = |left: usize| format!("\n…and {} more", left);
+    let lengths: Vec<usize> = lines.iter().map(|l| l.chars().count()).collect();
+
+    // Length of the first `k` lines joined, for every k
+    let mut joined = Vec::with_capacity(total + 1);
+    joined.push(0);
+    for (i, len) in lengths.iter().enumerate() {
+        joined.push(joined[i] + len + usize::from(i > 0));
+    }
+    if joined[total] <= max_chars {
+        return (lines.join("\n"), 0);
+    }
+
+    let kept = (1..total)
+        .rev()
+        .find(|&k| joined[k] + more(total - k).chars().count() <= max_chars);
+    match kept {
+        Some(k) => {
+            let mut text = lines[..k].join("\n");
+            text.push_str(&more(total - k));
+            (text, total - k)
+        }
+        None => {
+            // Not even the first line fits whole, what's left of the room goes to it
+            let suffix = if total > 1 {
+                more(total - 1)
+            } else {
+                String::new()
+            };
+            let room = max_chars.saturating_sub(suffix.chars().count());
+            let text = format!("{}{}", truncate_chars(&lines[0], room), suffix);
+            (text, total - 1)
+        }
     }
 }
 
@@ -72,6 +135,26 @@ pub fn format_relative_time(seconds_ago: i64) -> String {
     }
 }
 
+/// Unicode sparkline for a series of values (e.g. "▁▃▇█"), scaled between its min and max
+pub fn sparkline(values: &[f64]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+
+    values
+        .iter()
+        .map(|v| {
+            if range <= 0.0 {
+                return BARS[0];
+            }
+            let level = ((v - min) / range * (BARS.len() - 1) as f64).round() as usize;
+            BARS[level.min(BARS.len() - 1)]
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -98,8 +181,95 @@ mod tests {
     }
 
     #[test]
-    fn test_truncate() {
-        assert_eq!(truncate("hello", 10), "hello");
-        assert_eq!(truncate("hello world", 8), "hello...");
+    fn test_truncate_chars() {
+        assert_eq!(truncate_chars("hello", 10), "hello");
+        assert_eq!(truncate_chars("hello world", 8), "hello...");
+
+        // Kanji right at the limit stay whole, one past it gets cut
+        assert_eq!(truncate_chars("新世界より", 5), "新世界より");
+        assert_eq!(truncate_chars("新世界よりの", 5), "新世...");
+        // Mixed with ASCII, cut where a 3-byte char meets a 1-byte one
+        assert_eq!(
+            truncate_chars("Summer Pockets 夏の日々", 17),
+            "Summer Pockets..."
+        );
+        assert_eq!(
+            truncate_chars("Summer Pockets 夏の日々", 18),
+            "Summer Pockets ..."
+        );
+        assert_eq!(
+            truncate_chars("Summer Pockets 夏の日々", 19),
+            "Summer Pockets 夏の日々"
+        );
+
+        // A family emoji is several codepoints, a cut one doesn't end on its joiner
+        let family = "👨\u{200d}👩\u{200d}👧";
+        assert_eq!(truncate_chars(family, 5), family);
+        assert_eq!(truncate_chars(&format!("{}!", family), 5), "👨...");
+        assert_eq!(truncate_chars("❤\u{fe0f}❤\u{fe0f}❤\u{fe0f}", 5), "❤...");
+
+        // Too small a budget for the ellipsis
+        assert_eq!(truncate_chars("日本語", 2), "日本");
+        assert_eq!(truncate_chars("", 0), "");
+    }
+
+    #[test]
+    fn test_truncate_bytes_lossy() {
+        assert_eq!(truncate_bytes_lossy("hello", 10), "hello");
+        assert_eq!(truncate_bytes_lossy("hello", 3), "hel");
+        // "日" is 3 bytes, a budget inside it backs off to the previous char
+        assert_eq!(truncate_bytes_lossy("日本語", 3), "日");
+        assert_eq!(truncate_bytes_lossy("日本語", 5), "日");
+        assert_eq!(truncate_bytes_lossy("日本語", 2), "");
+        assert_eq!(truncate_bytes_lossy("a📖b", 4), "a");
+        assert_eq!(truncate_bytes_lossy("a📖b", 5), "a📖");
+        assert_eq!(truncate_bytes_lossy("日本語", 0), "");
+    }
+
+    #[test]
+    fn test_fit_description() {
+        let lines = |texts: &[&str]| texts.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+
+        // Everything fits, right up to the limit
+        assert_eq!(
+            fit_description(lines(&["aaaa", "bbbb"]), 9),
+            ("aaaa\nbbbb".to_string(), 0)
+        );
+        assert_eq!(fit_description(Vec::new(), 10), (String::new(), 0));
+
+        let ten = "a".repeat(10);
+        let three = || vec![ten.clone(), ten.clone(), ten.clone()];
+        assert_eq!(fit_description(three(), 32).1, 0);
+
+        // One over: whole lines are dropped until the note fits too
+        let (text, left_out) = fit_description(three(), 31);
+        assert_eq!(text, format!("{}\n…and 2 more", ten));
+        assert_eq!(left_out, 2);
+
+        // Note exactly at the limit
+        let mut four = three();
+        four.push(ten.clone());
+        let (text, left_out) = fit_description(four, 33);
+        assert_eq!(text, format!("{}\n{}\n…and 2 more", ten, ten));
+        assert_eq!(text.chars().count(), 33);
+        assert_eq!(left_out, 2);
+
+        // A single line over the limit is cut itself
+        let (text, left_out) = fit_description(lines(&["日本語の長いタイトル"]), 8);
+        assert_eq!(text, "日本語の長...");
+        assert_eq!(left_out, 0);
+
+        // An oversized first line followed by more
+        let (text, left_out) = fit_description(lines(&[&"x".repeat(50), "y"]), 20);
+        assert_eq!(text, "xxxxx...\n…and 1 more");
+        assert_eq!(text.chars().count(), 20);
+        assert_eq!(left_out, 1);
+    }
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(sparkline(&[]), "");
+        assert_eq!(sparkline(&[5.0, 5.0]), "▁▁");
+        assert_eq!(sparkline(&[0.0, 50.0, 100.0]), "▁▅█");
     }
 }
@@ -0,0 +1,204 @@
+// Latency diagnostics for /ping and /status
+// Classifies gateway, REST and Firestore timings and keeps a short history of Firestore probes
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::api::storage::Storage;
+use crate::utils::config::colors;
+
+/// Firestore probes taking longer than this are reported as degraded
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Number of probe results kept for the /status trend
+const HISTORY_LEN: usize = 30;
+
+/// Overall verdict for a single metric, ordered from best to worst
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Health {
+    Good,
+    Slow,
+    Degraded,
+}
+
+impl Health {
+    pub fn emoji(self) -> &'static str {
+        match self {
+            Health::Good => "🟢",
+            Health::Slow => "🟡",
+            Health::Degraded => "🔴",
+        }
+    }
+
+    pub fn color(self) -> u32 {
+        match self {
+            Health::Good => colors::SUCCESS,
+            Health::Slow => colors::WARNING,
+            Health::Degraded => colors::ERROR,
+        }
+    }
+}
+
+/// Which timing is being classified, each has its own thresholds
+#[derive(Debug, Clone, Copy)]
+pub enum Metric {
+    Gateway,
+    Rest,
+    Firestore,
+}
+
+impl Metric {
+    /// (good below, slow below) in milliseconds
+    fn thresholds_ms(self) -> (u128, u128) {
+        match self {
+            Metric::Gateway => (200, 500),
+            Metric::Rest => (400, 1000),
+            Metric::Firestore => (300, 1000),
+        }
+    }
+
+    pub fn classify(self, latency: Duration) -> Health {
+        let (good, slow) = self.thresholds_ms();
+        let ms = latency.as_millis();
+        if ms < good {
+            Health::Good
+        } else if ms < slow {
+            Health::Slow
+        } else {
+            Health::Degraded
+        }
+    }
+}
+
+/// Worst of several verdicts, `Good` when there is nothing to compare
+pub fn worst(healths: impl IntoIterator<Item = Health>) -> Health {
+    healths.into_iter().max().unwrap_or(Health::Good)
+}
+
+/// Result of one Firestore round-trip
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProbeResult {
+    Ok(Duration),
+    TimedOut,
+    Failed,
+}
+
+impl ProbeResult {
+    pub fn health(self) -> Health {
+        match self {
+            ProbeResult::Ok(latency) => Metric::Firestore.classify(latency),
+            ProbeResult::TimedOut | ProbeResult::Failed => Health::Degraded,
+        }
+    }
+
+    pub fn describe(self) -> String {
+        match self {
+            ProbeResult::Ok(latency) => format!("{}ms", latency.as_millis()),
+            ProbeResult::TimedOut => format!("degraded (no reply in {}s)", PROBE_TIMEOUT.as_secs()),
+            ProbeResult::Failed => "degraded (request failed)".to_string(),
+        }
+    }
+}
+
+/// Read the tiny `system/health` document, giving up after `timeout`
+pub async fn probe_firestore(firebase: &dyn Storage, timeout: Duration) -> ProbeResult {
+    let start = Instant::now();
+    match tokio::time::timeout(timeout, firebase.get_document("system", "health")).await {
+        Ok(Ok(_)) => ProbeResult::Ok(start.elapsed()),
+        Ok(Err(e)) => {
+            warn!("Firestore health probe failed: {:?}", e);
+            ProbeResult::Failed
+        }
+        Err(_) => ProbeResult::TimedOut,
+    }
+}
+
+/// Values to plot for a run of probes; failed probes are drawn at the timeout
+pub fn trend_values(results: &[ProbeResult]) -> Vec<f64> {
+    results
+        .iter()
+        .map(|r| match r {
+            ProbeResult::Ok(latency) => latency.as_millis() as f64,
+            ProbeResult::TimedOut | ProbeResult::Failed => PROBE_TIMEOUT.as_millis() as f64,
+        })
+        .collect()
+}
+
+/// Ring buffer of the most recent Firestore probes
+#[derive(Default)]
+pub struct ProbeHistory {
+    results: Mutex<VecDeque<ProbeResult>>,
+}
+
+impl ProbeHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, result: ProbeResult) {
+        let mut results = self.results.lock().unwrap();
+        if results.len() == HISTORY_LEN {
+            results.pop_front();
+        }
+        results.push_back(result);
+    }
+
+    /// Oldest first
+    pub fn snapshot(&self) -> Vec<ProbeResult> {
+        self.results.lock().unwrap().iter().copied().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn test_classify_thresholds() {
+        assert_eq!(Metric::Gateway.classify(ms(199)), Health::Good);
+        assert_eq!(Metric::Gateway.classify(ms(200)), Health::Slow);
+        assert_eq!(Metric::Gateway.classify(ms(500)), Health::Degraded);
+        assert_eq!(Metric::Rest.classify(ms(399)), Health::Good);
+        assert_eq!(Metric::Firestore.classify(ms(999)), Health::Slow);
+    }
+
+    #[test]
+    fn test_failed_probes_are_degraded_and_worst_wins() {
+        assert_eq!(ProbeResult::TimedOut.health(), Health::Degraded);
+        assert_eq!(ProbeResult::Failed.health(), Health::Degraded);
+        assert_eq!(ProbeResult::Ok(ms(50)).health(), Health::Good);
+
+        assert_eq!(
+            worst([Health::Good, Health::Slow, Health::Good]),
+            Health::Slow
+        );
+        assert_eq!(
+            worst([Health::Good, ProbeResult::TimedOut.health()]),
+            Health::Degraded
+        );
+        assert_eq!(worst([]), Health::Good);
+    }
+
+    #[test]
+    fn test_history_keeps_last_30() {
+        let history = ProbeHistory::new();
+        for i in 0..35 {
+            history.record(ProbeResult::Ok(ms(i)));
+        }
+        history.record(ProbeResult::TimedOut);
+
+        let snapshot = history.snapshot();
+        assert_eq!(snapshot.len(), HISTORY_LEN);
+        assert_eq!(snapshot[0], ProbeResult::Ok(ms(6)));
+        assert_eq!(
+            trend_values(&snapshot).last().copied(),
+            Some(PROBE_TIMEOUT.as_millis() as f64)
+        );
+    }
+}
@@ -0,0 +1,345 @@
+// Bot-facing phrases per guild language
+// Only the high-traffic messages (role rank, AFK, the immersion channel notice) are
+// translated. Indonesian stays the default, it's what the bot always spoke.
+
+use std::fmt::Display;
+
+use crate::models::guild::GuildConfig;
+
+/// Language of a guild's bot messages, stored as its code in `GuildConfig::language`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, poise::ChoiceParameter)]
+pub enum Language {
+    #[default]
+    #[name = "Bahasa Indonesia"]
+    Id,
+    #[name = "English"]
+    En,
+}
+
+impl Language {
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::Id => "id",
+            Language::En => "en",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code.trim().to_ascii_lowercase().as_str() {
+            "id" => Some(Language::Id),
+            "en" => Some(Language::En),
+            _ => None,
+        }
+    }
+
+    /// The guild's language, Indonesian when unset or unknown
+    pub fn of(config: Option<&GuildConfig>) -> Self {
+        config
+            .and_then(|c| c.language.as_deref())
+            .and_then(Language::from_code)
+            .unwrap_or_default()
+    }
+}
+
+/// Translated messages. Phrases with `{}` are filled in order by `tf`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Msg {
+    ImmersionChannelOnly,
+    AfkWelcomeBackTitle,
+    AfkWelcomeBackBody,
+    AfkIsAfk,
+    AfkReason,
+    AfkSince,
+    AfkBack,
+    AfkSet,
+    AfkSetFooter,
+    AfkInvalidDuration,
+    AfkSaveFailed,
+    QuizSelectorDescription,
+    QuizSelectorPlaceholder,
+    QuizChannelCreated,
+    QuizWelcome,
+    QuizCommandValid,
+    QuizCommandMismatch,
+    QuizNotOwner,
+    QuizWinnerUnknown,
+    QuizResultMismatch,
+    QuizStageComplete,
+    QuizRoleAlreadyHeld,
+    QuizNoDowngrade,
+    QuizRoleBelowBot,
+    QuizAdminNotified,
+    QuizContactAdmin,
+    QuizRoleUpdateFailed,
+    QuizPassed,
+    QuizMemberLeft,
+    PromotionTitle,
+    PromotionDescription,
+    QuizRoleRestored,
+    StreakReminderDm,
+    QuizJpdbSuggestion,
+}
+
+impl Msg {
+    #[cfg(test)]
+    const ALL: [Msg; 34] = [
+        Msg::ImmersionChannelOnly,
+        Msg::AfkWelcomeBackTitle,
+        Msg::AfkWelcomeBackBody,
+        Msg::AfkIsAfk,
+        Msg::AfkReason,
+        Msg::AfkSince,
+        Msg::AfkBack,
+        Msg::AfkSet,
+        Msg::AfkSetFooter,
+        Msg::AfkInvalidDuration,
+        Msg::AfkSaveFailed,
+        Msg::QuizSelectorDescription,
+        Msg::QuizSelectorPlaceholder,
+        Msg::QuizChannelCreated,
+        Msg::QuizWelcome,
+        Msg::QuizCommandValid,
+        Msg::QuizCommandMismatch,
+        Msg::QuizNotOwner,
+        Msg::QuizWinnerUnknown,
+        Msg::QuizResultMismatch,
+        Msg::QuizStageComplete,
+        Msg::QuizRoleAlreadyHeld,
+        Msg::QuizNoDowngrade,
+        Msg::QuizRoleBelowBot,
+        Msg::QuizAdminNotified,
+        Msg::QuizContactAdmin,
+        Msg::QuizRoleUpdateFailed,
+        Msg::QuizPassed,
+        Msg::QuizMemberLeft,
+        Msg::PromotionTitle,
+        Msg::PromotionDescription,
+        Msg::QuizRoleRestored,
+        Msg::StreakReminderDm,
+        Msg::QuizJpdbSuggestion,
+    ];
+}
+
+/// The phrase in a language
+pub fn phrase(lang: Language, key: Msg) -> &'static str {
+    use Language::{En, Id};
+
+    match key {
+        Msg::ImmersionChannelOnly => match lang {
+            Id => "Command ini hanya bisa digunakan di <#{}>.",
+            En => "This command can only be used in <#{}>.",
+        },
+        Msg::AfkWelcomeBackTitle => match lang {
+            Id => "Selamat Datang Kembali",
+            En => "Welcome Back",
+        },
+        Msg::AfkWelcomeBackBody => match lang {
+            Id => "Status AFK kamu telah dihapus",
+            En => "Your AFK status has been removed",
+        },
+        Msg::AfkIsAfk => match lang {
+            Id => "{} sedang AFK",
+            En => "{} is AFK",
+        },
+        Msg::AfkReason => match lang {
+            Id => "**Alasan:** {}",
+            En => "**Reason:** {}",
+        },
+        Msg::AfkSince => match lang {
+            Id => "**Sejak:** <t:{}:R>",
+            En => "**Since:** <t:{}:R>",
+        },
+        Msg::AfkBack => match lang {
+            Id => "**Kembali:** <t:{}:R>",
+            En => "**Back:** <t:{}:R>",
+        },
+        Msg::AfkSet => match lang {
+            Id => "User lain akan diberitahu kalau kamu sedang AFK.",
+            En => "Others will be told you're AFK when they mention you.",
+        },
+        Msg::AfkSetFooter => match lang {
+            Id => "Kirim pesan lagi untuk menghapus status AFK",
+            En => "Send a message to remove your AFK status",
+        },
+        Msg::AfkInvalidDuration => match lang {
+            Id => "Format durasi tidak valid. Gunakan angka diikuti `m` (menit), `h` (jam) atau `d` (hari), mis. `30m`, `2h`, `1d` atau `1h30m`. Maksimal 7 hari.",
+            En => "Invalid duration. Use a number followed by `m` (minutes), `h` (hours) or `d` (days), e.g. `30m`, `2h`, `1d` or `1h30m`. At most 7 days.",
+        },
+        Msg::AfkSaveFailed => match lang {
+            Id => "Gagal menyimpan status AFK, coba lagi nanti.",
+            En => "Failed to save your AFK status, please try again later.",
+        },
+        Msg::QuizSelectorDescription => match lang {
+            Id => "Pilih quiz di bawah ini untuk memulai tes kenaikan role.\nSelect a quiz below to start the role advancement test.",
+            En => "Select a quiz below to start the role advancement test.",
+        },
+        Msg::QuizSelectorPlaceholder => match lang {
+            Id => "Pilih Quiz / Select Quiz",
+            En => "Select Quiz",
+        },
+        Msg::QuizChannelCreated => match lang {
+            Id => "Channel private **{}** telah dibuat untuk quiz **{}**. Silakan lanjut di sana!",
+            En => "Private channel **{}** has been created for the **{}** quiz. Continue there!",
+        },
+        Msg::QuizWelcome => match lang {
+            Id => "Halo <@{}>! Untuk memulai quiz, copy dan paste command berikut:\n\n\
+                **Command:**\n```\n{}\n```\n\n\
+                **Cara bermain:**\n\
+                1. Copy command di atas\n\
+                2. Paste di channel ini\n\
+                3. Jawab pertanyaan dari {}\n\
+                4. Kamu akan mendapat role **{}** setelah menyelesaikan quiz!\n\
+                5. Kamu bisa hapus channel ini secara manual dengan `a!del` (atau `/role_rank delete`)\n\n\
+                Jangan lupa paste command langsung di channel ini ya!",
+            En => "Hi <@{}>! To start the quiz, copy and paste this command:\n\n\
+                **Command:**\n```\n{}\n```\n\n\
+                **How to play:**\n\
+                1. Copy the command above\n\
+                2. Paste it in this channel\n\
+                3. Answer the questions from {}\n\
+                4. You get the **{}** role once you finish the quiz!\n\
+                5. You can delete this channel yourself with `a!del` (or `/role_rank delete`)\n\n\
+                Remember to paste the command right in this channel!",
+        },
+        Msg::QuizCommandValid => match lang {
+            Id => "Command Valid! Menunggu hasil dari Kotoba Bot...",
+            En => "Command accepted! Waiting for the result from Kotoba Bot...",
+        },
+        Msg::QuizCommandMismatch => match lang {
+            Id => "**Command Tidak Sesuai**\nUntuk role ini, kamu wajib menggunakan command yang persis sama:\n```\n{}\n```\nJika kamu sedang menjalankan quiz, selesaikan dulu atau ketik `k!quiz stop` lalu paste commandnya lagi.",
+            En => "**Wrong Command**\nThis role needs exactly this command:\n```\n{}\n```\nIf a quiz is running, finish it first or type `k!quiz stop`, then paste the command again.",
+        },
+        Msg::QuizNotOwner => match lang {
+            Id => "⚠️ **Validasi Gagal**\nQuiz harus diselesaikan sendiri oleh pemilik channel (<@{}>). Progress tidak dihitung.",
+            En => "⚠️ **Validation Failed**\nThe quiz has to be finished by the channel owner (<@{}>) themselves. Progress wasn't counted.",
+        },
+        Msg::QuizWinnerUnknown => match lang {
+            Id => "⚠️ **Validasi Gagal**\nPemenang quiz tidak bisa dipastikan dari hasil Kotoba, jadi tidak bisa dicek apakah pemilik channel (<@{}>) yang menyelesaikannya. Progress tidak dihitung.",
+            En => "⚠️ **Validation Failed**\nThe quiz winner couldn't be read from Kotoba's result, so there's no telling the channel owner (<@{}>) finished it. Progress wasn't counted.",
+        },
+        Msg::QuizResultMismatch => match lang {
+            Id => "⚠️ **Validasi Gagal**\nDeck atau Score tidak sesuai.\nExpected Deck: {}\nExpected Score: {}\nDetected Deck: {}\nDetected Score: {}",
+            En => "⚠️ **Validation Failed**\nThe deck or score doesn't match.\nExpected Deck: {}\nExpected Score: {}\nDetected Deck: {}\nDetected Score: {}",
+        },
+        Msg::QuizStageComplete => match lang {
+            Id => "Stage selesai! Lanjut ke tahap berikutnya:\n```{}```",
+            En => "Stage complete! On to the next one:\n```{}```",
+        },
+        Msg::QuizRoleAlreadyHeld => match lang {
+            Id => "Kamu sudah memiliki role **{}**. Tidak ada perubahan.\nChannel akan dihapus dalam 30 detik.",
+            En => "You already have the **{}** role. Nothing changed.\nThis channel will be deleted in 30 seconds.",
+        },
+        Msg::QuizNoDowngrade => match lang {
+            Id => "Kamu sudah memiliki role tier lebih tinggi. Tidak bisa downgrade.\nChannel akan dihapus dalam 30 detik.",
+            En => "You already have a higher tier role, it can't be downgraded.\nThis channel will be deleted in 30 seconds.",
+        },
+        Msg::QuizRoleBelowBot => match lang {
+            Id => "Role saya ada di bawah **{}**, minta admin untuk memindahkannya ke atas.",
+            En => "My role is below **{}**, ask an admin to move it up.",
+        },
+        Msg::QuizAdminNotified => match lang {
+            Id => "Admin sudah diberi tahu.",
+            En => "The admins have been told.",
+        },
+        Msg::QuizContactAdmin => match lang {
+            Id => "Hubungi admin.",
+            En => "Please contact an admin.",
+        },
+        Msg::QuizRoleUpdateFailed => match lang {
+            Id => "Gagal mengubah role ({}): {}. {}",
+            En => "Couldn't update the roles ({}): {}. {}",
+        },
+        Msg::QuizPassed => match lang {
+            Id => "**SELAMAT**! Kamu sekarang mendapatkan role **{}**.\nChannel ini akan dihapus dalam 30 detik.",
+            En => "**CONGRATULATIONS**! You now have the **{}** role.\nThis channel will be deleted in 30 seconds.",
+        },
+        Msg::QuizMemberLeft => match lang {
+            Id => "<@{}> sudah keluar dari server, jadi role **{}** belum bisa diberikan. Role akan diberikan otomatis jika kembali dalam {} hari.\nChannel ini akan dihapus dalam 30 detik.",
+            En => "<@{}> has left the server, so the **{}** role couldn't be given yet. It's given automatically if they come back within {} days.\nThis channel will be deleted in 30 seconds.",
+        },
+        Msg::PromotionTitle => match lang {
+            Id => "Role Rank Naik! 🎉",
+            En => "Role Rank Up! 🎉",
+        },
+        Msg::PromotionDescription => match lang {
+            Id => "Selamat kepada <@{}> yang telah berhasil mendapatkan role **{}**!",
+            En => "Congratulations to <@{}> for earning the **{}** role!",
+        },
+        Msg::QuizRoleRestored => match lang {
+            Id => "Selamat datang kembali <@{}>! Role **{}** dari quiz yang sudah kamu selesaikan telah diberikan.",
+            En => "Welcome back <@{}>! The **{}** role from the quiz you finished has been given back.",
+        },
+        Msg::StreakReminderDm => match lang {
+            Id => "Jangan putus rantainya! 🔥 Streak kamu **{}** hari, tapi hari ini belum ada log. Catat dengan `/immersion` sebelum jam 02.00 WIB.\n-# Matikan dengan `/reminder streak off`",
+            En => "Don't break the chain! 🔥 You're on a **{}**-day streak but haven't logged today. Log with `/immersion` before 02:00 WIB.\n-# Turn this off with `/reminder streak off`",
+        },
+        Msg::QuizJpdbSuggestion => match lang {
+            Id => "Dengan **{}** kata yang kamu ketahui di jpdb, quiz **{}** mungkin lebih cocok untukmu.",
+            En => "With **{}** known words on jpdb, the **{}** quiz may suit you better.",
+        },
+    }
+}
+
+/// The phrase in the guild's language
+pub fn t(config: Option<&GuildConfig>, key: Msg) -> &'static str {
+    phrase(Language::of(config), key)
+}
+
+/// `t` with each `{}` replaced by the next argument
+pub fn tf(config: Option<&GuildConfig>, key: Msg, args: &[&(dyn Display + Sync)]) -> String {
+    fill(t(config, key), args)
+}
+
+fn fill(template: &str, args: &[&(dyn Display + Sync)]) -> String {
+    let mut args = args.iter();
+    let mut parts = template.split("{}");
+    let mut out = parts.next().unwrap_or_default().to_string();
+    for part in parts {
+        if let Some(arg) = args.next() {
+            out.push_str(&arg.to_string());
+        }
+        out.push_str(part);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(language: Option<&str>) -> GuildConfig {
+        GuildConfig {
+            language: language.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_guild_language() {
+        assert_eq!(Language::of(None), Language::Id);
+        assert_eq!(Language::of(Some(&config(None))), Language::Id);
+        assert_eq!(Language::of(Some(&config(Some("EN")))), Language::En);
+        assert_eq!(Language::of(Some(&config(Some("fr")))), Language::Id);
+        assert_eq!(
+            tf(
+                Some(&config(Some("en"))),
+                Msg::ImmersionChannelOnly,
+                &[&123]
+            ),
+            "This command can only be used in <#123>."
+        );
+        assert_eq!(tf(None, Msg::AfkIsAfk, &[&"yuki"]), "yuki sedang AFK");
+    }
+
+    #[test]
+    fn test_phrases_take_the_same_arguments() {
+        for key in Msg::ALL {
+            assert_eq!(
+                phrase(Language::Id, key).matches("{}").count(),
+                phrase(Language::En, key).matches("{}").count(),
+                "{:?}",
+                key
+            );
+        }
+    }
+}
@@ -0,0 +1,192 @@
+// Image size helpers for Discord attachments
+// Generated images are shrunk before upload so they don't fail on the attachment size limit
+
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat, Rgba, RgbaImage};
+use tracing::{debug, warn};
+
+/// Upload limit for servers without boosts
+pub const DEFAULT_UPLOAD_LIMIT: usize = 10 * 1024 * 1024;
+
+/// JPEG quality used when re-encoding
+const JPEG_QUALITY: u8 = 90;
+
+/// Downscale steps tried after a full-size re-encode
+const SCALE_STEPS: [f32; 3] = [0.9, 0.75, 0.5];
+
+/// Return image bytes that fit in `max_bytes`.
+/// Small images are returned untouched. Larger still images are re-encoded as JPEG and
+/// downscaled step by step; if nothing fits, the smallest attempt is returned.
+/// Animated images and anything that isn't PNG/JPEG/WebP are passed through as-is.
+pub fn ensure_under_limit(bytes: Vec<u8>, mime: &str, max_bytes: usize) -> Vec<u8> {
+    if bytes.len() <= max_bytes {
+        return bytes;
+    }
+
+    let format = match mime {
+        "image/png" => ImageFormat::Png,
+        "image/jpeg" | "image/jpg" => ImageFormat::Jpeg,
+        "image/webp" => ImageFormat::WebP,
+        _ => return bytes,
+    };
+    if is_animated(&bytes, format) {
+        return bytes;
+    }
+
+    let img = match image::load_from_memory_with_format(&bytes, format) {
+        Ok(img) => flatten(img),
+        Err(e) => {
+            warn!("Could not decode {} image for downscaling: {}", mime, e);
+            return bytes;
+        }
+    };
+
+    let mut best: Option<Vec<u8>> = None;
+    for scale in std::iter::once(1.0).chain(SCALE_STEPS) {
+        let candidate = if scale < 1.0 {
+            let width = ((img.width() as f32) * scale).round().max(1.0) as u32;
+            let height = ((img.height() as f32) * scale).round().max(1.0) as u32;
+            img.resize_exact(width, height, FilterType::Triangle)
+        } else {
+            img.clone()
+        };
+
+        let Some(encoded) = encode_jpeg(&candidate) else {
+            continue;
+        };
+        debug!(
+            "Re-encoded {} image at {:.0}%: {} -> {} bytes",
+            mime,
+            scale * 100.0,
+            bytes.len(),
+            encoded.len()
+        );
+
+        if encoded.len() <= max_bytes {
+            return encoded;
+        }
+        if best.as_ref().is_none_or(|b| encoded.len() < b.len()) {
+            best = Some(encoded);
+        }
+    }
+
+    match best {
+        Some(smallest) if smallest.len() < bytes.len() => {
+            warn!(
+                "Image still {} bytes after downscaling (limit {})",
+                smallest.len(),
+                max_bytes
+            );
+            smallest
+        }
+        _ => bytes,
+    }
+}
+
+/// File name with the extension matching the actual encoding (it may have changed to JPEG)
+pub fn file_name(stem: &str, bytes: &[u8]) -> String {
+    let extension = match image::guess_format(bytes) {
+        Ok(ImageFormat::Jpeg) => "jpg",
+        Ok(ImageFormat::WebP) => "webp",
+        Ok(ImageFormat::Gif) => "gif",
+        _ => "png",
+    };
+    format!("{}.{}", stem, extension)
+}
+
+/// APNG has an `acTL` chunk and animated WebP an `ANIM` chunk
+fn is_animated(bytes: &[u8], format: ImageFormat) -> bool {
+    let marker: &[u8] = match format {
+        ImageFormat::Png => b"acTL",
+        ImageFormat::WebP => b"ANIM",
+        _ => return false,
+    };
+    bytes.windows(4).any(|w| w == marker)
+}
+
+/// JPEG has no alpha, so transparent areas are composited onto white first
+fn flatten(img: DynamicImage) -> DynamicImage {
+    if !img.color().has_alpha() {
+        return img;
+    }
+
+    let rgba = img.to_rgba8();
+    let mut out = RgbaImage::from_pixel(rgba.width(), rgba.height(), Rgba([255, 255, 255, 255]));
+    for (dst, src) in out.pixels_mut().zip(rgba.pixels()) {
+        let alpha = src[3] as u32;
+        for c in 0..3 {
+            dst[c] = ((src[c] as u32 * alpha + 255 * (255 - alpha)) / 255) as u8;
+        }
+    }
+    DynamicImage::ImageRgba8(out)
+}
+
+fn encode_jpeg(img: &DynamicImage) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let encoder = JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY);
+    match img.to_rgb8().write_with_encoder(encoder) {
+        Ok(()) => Some(out),
+        Err(e) => {
+            warn!("JPEG encode failed: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn png_bytes(img: &RgbaImage) -> Vec<u8> {
+        let mut out = Vec::new();
+        img.write_to(&mut Cursor::new(&mut out), ImageFormat::Png)
+            .unwrap();
+        out
+    }
+
+    /// Deterministic noise, which PNG can barely compress
+    fn noise_png(size: u32) -> Vec<u8> {
+        let mut seed: u32 = 12345;
+        let img = RgbaImage::from_fn(size, size, |_, _| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let [r, g, b, _] = seed.to_le_bytes();
+            Rgba([r, g, b, 255])
+        });
+        png_bytes(&img)
+    }
+
+    #[test]
+    fn test_large_image_gets_under_limit() {
+        let original = noise_png(600);
+        let limit = original.len() / 4;
+
+        let shrunk = ensure_under_limit(original.clone(), "image/png", limit);
+        assert!(shrunk.len() <= limit, "{} > {}", shrunk.len(), limit);
+        assert_eq!(file_name("heatmap", &shrunk), "heatmap.jpg");
+    }
+
+    #[test]
+    fn test_small_image_passes_through() {
+        let img = RgbaImage::from_pixel(32, 32, Rgba([10, 200, 30, 128]));
+        let original = png_bytes(&img);
+
+        let result = ensure_under_limit(original.clone(), "image/png", DEFAULT_UPLOAD_LIMIT);
+        assert_eq!(result, original);
+        assert_eq!(file_name("chart", &result), "chart.png");
+    }
+
+    #[test]
+    fn test_non_image_and_animated_are_untouched() {
+        let subtitle = b"1\n00:00:01,000 --> 00:00:02,000\nHello\n".repeat(100);
+        assert_eq!(
+            ensure_under_limit(subtitle.clone(), "text/plain", 10),
+            subtitle
+        );
+
+        let mut apng = noise_png(64);
+        apng.extend_from_slice(b"acTL");
+        assert_eq!(ensure_under_limit(apng.clone(), "image/png", 10), apng);
+    }
+}
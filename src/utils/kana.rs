@@ -0,0 +1,220 @@
+// Romaji to kana conversion
+// Lets dictionary lookups typed in romaji (e.g. "taberu") search the same kana as たべる
+
+/// Syllables, longest first so "sha" wins over "s" + "ha"
+const SYLLABLES: &[(&str, &str)] = &[
+    ("kya", "きゃ"),
+    ("kyu", "きゅ"),
+    ("kyo", "きょ"),
+    ("gya", "ぎゃ"),
+    ("gyu", "ぎゅ"),
+    ("gyo", "ぎょ"),
+    ("sha", "しゃ"),
+    ("shu", "しゅ"),
+    ("sho", "しょ"),
+    ("shi", "し"),
+    ("she", "しぇ"),
+    ("sya", "しゃ"),
+    ("syu", "しゅ"),
+    ("syo", "しょ"),
+    ("cha", "ちゃ"),
+    ("chu", "ちゅ"),
+    ("cho", "ちょ"),
+    ("chi", "ち"),
+    ("che", "ちぇ"),
+    ("tya", "ちゃ"),
+    ("tyu", "ちゅ"),
+    ("tyo", "ちょ"),
+    ("tsu", "つ"),
+    ("nya", "にゃ"),
+    ("nyu", "にゅ"),
+    ("nyo", "にょ"),
+    ("hya", "ひゃ"),
+    ("hyu", "ひゅ"),
+    ("hyo", "ひょ"),
+    ("bya", "びゃ"),
+    ("byu", "びゅ"),
+    ("byo", "びょ"),
+    ("pya", "ぴゃ"),
+    ("pyu", "ぴゅ"),
+    ("pyo", "ぴょ"),
+    ("mya", "みゃ"),
+    ("myu", "みゅ"),
+    ("myo", "みょ"),
+    ("rya", "りゃ"),
+    ("ryu", "りゅ"),
+    ("ryo", "りょ"),
+    ("jya", "じゃ"),
+    ("jyu", "じゅ"),
+    ("jyo", "じょ"),
+    ("ja", "じゃ"),
+    ("ju", "じゅ"),
+    ("jo", "じょ"),
+    ("je", "じぇ"),
+    ("ji", "じ"),
+    ("ka", "か"),
+    ("ki", "き"),
+    ("ku", "く"),
+    ("ke", "け"),
+    ("ko", "こ"),
+    ("ga", "が"),
+    ("gi", "ぎ"),
+    ("gu", "ぐ"),
+    ("ge", "げ"),
+    ("go", "ご"),
+    ("sa", "さ"),
+    ("si", "し"),
+    ("su", "す"),
+    ("se", "せ"),
+    ("so", "そ"),
+    ("za", "ざ"),
+    ("zi", "じ"),
+    ("zu", "ず"),
+    ("ze", "ぜ"),
+    ("zo", "ぞ"),
+    ("ta", "た"),
+    ("ti", "ち"),
+    ("tu", "つ"),
+    ("te", "て"),
+    ("to", "と"),
+    ("da", "だ"),
+    ("di", "ぢ"),
+    ("du", "づ"),
+    ("de", "で"),
+    ("do", "ど"),
+    ("na", "な"),
+    ("ni", "に"),
+    ("nu", "ぬ"),
+    ("ne", "ね"),
+    ("no", "の"),
+    ("ha", "は"),
+    ("hi", "ひ"),
+    ("hu", "ふ"),
+    ("fu", "ふ"),
+    ("he", "へ"),
+    ("ho", "ほ"),
+    ("ba", "ば"),
+    ("bi", "び"),
+    ("bu", "ぶ"),
+    ("be", "べ"),
+    ("bo", "ぼ"),
+    ("pa", "ぱ"),
+    ("pi", "ぴ"),
+    ("pu", "ぷ"),
+    ("pe", "ぺ"),
+    ("po", "ぽ"),
+    ("ma", "ま"),
+    ("mi", "み"),
+    ("mu", "む"),
+    ("me", "め"),
+    ("mo", "も"),
+    ("ya", "や"),
+    ("yu", "ゆ"),
+    ("yo", "よ"),
+    ("ra", "ら"),
+    ("ri", "り"),
+    ("ru", "る"),
+    ("re", "れ"),
+    ("ro", "ろ"),
+    ("la", "ら"),
+    ("li", "り"),
+    ("lu", "る"),
+    ("le", "れ"),
+    ("lo", "ろ"),
+    ("wa", "わ"),
+    ("wo", "を"),
+    ("a", "あ"),
+    ("i", "い"),
+    ("u", "う"),
+    ("e", "え"),
+    ("o", "お"),
+    ("-", "ー"),
+];
+
+/// Hiragana for romaji input (Hepburn or Kunrei), `None` when it isn't romaji
+/// or has letters that don't make a syllable
+pub fn romaji_to_hiragana(input: &str) -> Option<String> {
+    let romaji = input.trim().to_ascii_lowercase();
+    if romaji.is_empty()
+        || !romaji
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b == b'-' || b == b'\'')
+    {
+        return None;
+    }
+
+    let bytes = romaji.as_bytes();
+    let mut kana = String::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let rest = &romaji[i..];
+        let next = bytes.get(i + 1).copied();
+
+        // "n" is ん at the end and before a consonant. "n'" and "nn" are too, but the
+        // second n of "konnichi" starts the next syllable.
+        if bytes[i] == b'n' {
+            let after = bytes.get(i + 2).copied();
+            let consumed = match next {
+                None => Some(1),
+                Some(b'\'') => Some(2),
+                Some(b'n') if after.is_some_and(|c| b"aiueoy".contains(&c)) => Some(1),
+                Some(b'n') => Some(2),
+                Some(c) if !b"aiueoy".contains(&c) => Some(1),
+                _ => None,
+            };
+            if let Some(consumed) = consumed {
+                kana.push('ん');
+                i += consumed;
+                continue;
+            }
+        }
+        // A doubled consonant is a small tsu ("kk", "tch")
+        if next == Some(bytes[i]) && !b"aiueon-'".contains(&bytes[i]) || rest.starts_with("tch") {
+            kana.push('っ');
+            i += 1;
+            continue;
+        }
+
+        let (romaji_len, syllable) = SYLLABLES
+            .iter()
+            .find(|(r, _)| rest.starts_with(r))
+            .map(|(r, k)| (r.len(), *k))?;
+        kana.push_str(syllable);
+        i += romaji_len;
+    }
+    Some(kana)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_romaji_to_hiragana() {
+        assert_eq!(romaji_to_hiragana("taberu").as_deref(), Some("たべる"));
+        assert_eq!(romaji_to_hiragana("Gakkou").as_deref(), Some("がっこう"));
+        assert_eq!(romaji_to_hiragana("shinbun").as_deref(), Some("しんぶん"));
+        assert_eq!(romaji_to_hiragana("kon'ya").as_deref(), Some("こんや"));
+        assert_eq!(romaji_to_hiragana("hannbun").as_deref(), Some("はんぶん"));
+        assert_eq!(
+            romaji_to_hiragana("konnichiha").as_deref(),
+            Some("こんにちは")
+        );
+        assert_eq!(
+            romaji_to_hiragana("kyoutsuu").as_deref(),
+            Some("きょうつう")
+        );
+        assert_eq!(romaji_to_hiragana("matcha").as_deref(), Some("まっちゃ"));
+        assert_eq!(romaji_to_hiragana("ra-men").as_deref(), Some("らーめん"));
+    }
+
+    #[test]
+    fn test_not_romaji() {
+        assert_eq!(romaji_to_hiragana("食べる"), None);
+        assert_eq!(romaji_to_hiragana("たべる"), None);
+        assert_eq!(romaji_to_hiragana(""), None);
+        // English words with letters no syllable starts with
+        assert_eq!(romaji_to_hiragana("xylophone"), None);
+        assert_eq!(romaji_to_hiragana("eat 2"), None);
+    }
+}
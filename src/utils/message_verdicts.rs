@@ -0,0 +1,124 @@
+// Message verdict tracking
+// Remembers how the bot answered a message so edits can be re-checked without repeating itself
+
+use dashmap::DashMap;
+use poise::serenity_prelude as serenity;
+use std::time::{Duration, Instant};
+
+/// How the bot answered a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Accepted,
+    Rejected,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    verdict: Verdict,
+    content: String,
+    recorded_at: Instant,
+}
+
+/// Short-lived map of message id -> last verdict and the content it was given for
+pub struct MessageVerdicts {
+    ttl: Duration,
+    entries: DashMap<serenity::MessageId, Entry>,
+}
+
+impl MessageVerdicts {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: DashMap::new(),
+        }
+    }
+
+    /// Store the verdict for a message, dropping expired entries along the way
+    pub fn record(
+        &self,
+        message_id: serenity::MessageId,
+        verdict: Verdict,
+        content: &str,
+        now: Instant,
+    ) {
+        self.entries
+            .retain(|_, e| now.duration_since(e.recorded_at) < self.ttl);
+        self.entries.insert(
+            message_id,
+            Entry {
+                verdict,
+                content: content.to_string(),
+                recorded_at: now,
+            },
+        );
+    }
+
+    /// Whether an edit should be re-checked: the message was seen before, was not
+    /// accepted, and its content actually changed (embed-only updates keep the same text)
+    pub fn needs_recheck(
+        &self,
+        message_id: serenity::MessageId,
+        content: &str,
+        now: Instant,
+    ) -> bool {
+        match self
+            .entries
+            .get(&message_id)
+            .filter(|e| now.duration_since(e.recorded_at) < self.ttl)
+        {
+            Some(e) => e.verdict == Verdict::Rejected && e.content != content,
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(600);
+
+    fn id(n: u64) -> serenity::MessageId {
+        serenity::MessageId::new(n)
+    }
+
+    #[test]
+    fn test_rejected_edit_is_rechecked_once_per_change() {
+        let verdicts = MessageVerdicts::new(TTL);
+        let now = Instant::now();
+
+        verdicts.record(id(1), Verdict::Rejected, "k!quiz typo", now);
+        assert!(verdicts.needs_recheck(id(1), "k!quiz fixed", now));
+        // Same text again (e.g. an embed-only update)
+        assert!(!verdicts.needs_recheck(id(1), "k!quiz typo", now));
+
+        // Still wrong after the edit: recorded with the new text, so repeating it is a no-op
+        verdicts.record(id(1), Verdict::Rejected, "k!quiz fixed", now);
+        assert!(!verdicts.needs_recheck(id(1), "k!quiz fixed", now));
+    }
+
+    #[test]
+    fn test_accepted_message_is_never_rechecked() {
+        let verdicts = MessageVerdicts::new(TTL);
+        let now = Instant::now();
+
+        verdicts.record(id(1), Verdict::Accepted, "k!quiz ok", now);
+        assert!(!verdicts.needs_recheck(id(1), "k!quiz ok edited", now));
+    }
+
+    #[test]
+    fn test_unknown_and_expired_messages_are_ignored() {
+        let verdicts = MessageVerdicts::new(TTL);
+        let now = Instant::now();
+
+        assert!(!verdicts.needs_recheck(id(2), "k!quiz", now));
+
+        verdicts.record(id(1), Verdict::Rejected, "k!quiz typo", now);
+        let later = now + TTL;
+        assert!(!verdicts.needs_recheck(id(1), "k!quiz fixed", later));
+
+        // Expired entries are pruned on the next write
+        verdicts.record(id(3), Verdict::Accepted, "x", later);
+        assert_eq!(verdicts.entries.len(), 1);
+    }
+}
@@ -0,0 +1,327 @@
+// Operational metrics
+// Call counts, failures and latency of commands, Firestore requests and LLM calls, plus LLM
+// token spend. Logged every 10 minutes and shown on /admin usage.
+//
+// Recording is a sharded map lookup by the borrowed name and a few relaxed atomic adds; the
+// name is only copied the first time it's seen.
+
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tracing::info;
+
+use crate::Context;
+
+/// How often the snapshot is logged
+const LOG_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Upper bounds of the latency buckets, in milliseconds. Slower calls land in a last,
+/// unbounded bucket.
+const BUCKETS_MS: [u64; 7] = [50, 100, 250, 500, 1000, 2500, 5000];
+
+/// What was timed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Kind {
+    Command,
+    Firestore,
+    Llm,
+}
+
+impl Kind {
+    const ALL: [Kind; 3] = [Kind::Command, Kind::Firestore, Kind::Llm];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Kind::Command => "command",
+            Kind::Firestore => "firestore",
+            Kind::Llm => "llm",
+        }
+    }
+}
+
+/// Counters and a latency histogram of one name
+#[derive(Default)]
+struct Timing {
+    count: AtomicU64,
+    errors: AtomicU64,
+    total_ms: AtomicU64,
+    max_ms: AtomicU64,
+    buckets: [AtomicU64; BUCKETS_MS.len() + 1],
+}
+
+impl Timing {
+    fn record(&self, elapsed: Duration, ok: bool) {
+        let ms = elapsed.as_millis() as u64;
+        self.count.fetch_add(1, Ordering::Relaxed);
+        if !ok {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        self.total_ms.fetch_add(ms, Ordering::Relaxed);
+        self.max_ms.fetch_max(ms, Ordering::Relaxed);
+        let bucket = BUCKETS_MS
+            .iter()
+            .position(|bound| ms <= *bound)
+            .unwrap_or(BUCKETS_MS.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Tokens an LLM provider reported
+#[derive(Default)]
+struct Tokens {
+    prompt: AtomicU64,
+    completion: AtomicU64,
+}
+
+#[derive(Default)]
+struct Registry {
+    /// One map per kind, indexed by `Kind as usize`, so lookups take the name as a `&str`
+    timings: [DashMap<String, Timing>; Kind::ALL.len()],
+    tokens: DashMap<String, Tokens>,
+}
+
+impl Registry {
+    fn record(&self, kind: Kind, name: &str, elapsed: Duration, ok: bool) {
+        // Read lock on the hot path; the write lock only for a name seen the first time
+        let timings = &self.timings[kind as usize];
+        if let Some(timing) = timings.get(name) {
+            timing.record(elapsed, ok);
+            return;
+        }
+        timings
+            .entry(name.to_string())
+            .or_default()
+            .record(elapsed, ok);
+    }
+
+    fn record_tokens(&self, model: &str, prompt: u64, completion: u64) {
+        let tokens = self.tokens.entry(model.to_string()).or_default();
+        tokens.prompt.fetch_add(prompt, Ordering::Relaxed);
+        tokens.completion.fetch_add(completion, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        let mut timings: Vec<TimingRow> = Kind::ALL
+            .iter()
+            .flat_map(|kind| {
+                self.timings[*kind as usize]
+                    .iter()
+                    .map(move |entry| (*kind, entry))
+            })
+            .map(|(kind, entry)| {
+                let timing = entry.value();
+                let buckets: Vec<u64> = timing
+                    .buckets
+                    .iter()
+                    .map(|b| b.load(Ordering::Relaxed))
+                    .collect();
+                let count = timing.count.load(Ordering::Relaxed);
+                let max_ms = timing.max_ms.load(Ordering::Relaxed);
+                TimingRow {
+                    kind,
+                    name: entry.key().clone(),
+                    count,
+                    errors: timing.errors.load(Ordering::Relaxed),
+                    avg_ms: timing.total_ms.load(Ordering::Relaxed) / count.max(1),
+                    p95_ms: percentile(&buckets, 0.95).unwrap_or(max_ms).min(max_ms),
+                    max_ms,
+                }
+            })
+            .collect();
+        timings.sort_by(|a, b| {
+            a.kind
+                .cmp(&b.kind)
+                .then(b.count.cmp(&a.count))
+                .then(a.name.cmp(&b.name))
+        });
+
+        let mut tokens: Vec<TokenRow> = self
+            .tokens
+            .iter()
+            .map(|entry| TokenRow {
+                model: entry.key().clone(),
+                prompt: entry.prompt.load(Ordering::Relaxed),
+                completion: entry.completion.load(Ordering::Relaxed),
+            })
+            .collect();
+        tokens.sort_by(|a, b| a.model.cmp(&b.model));
+
+        Snapshot { timings, tokens }
+    }
+}
+
+/// Upper bound of the bucket holding the `quantile` call, `None` when that's the unbounded one
+fn percentile(buckets: &[u64], quantile: f64) -> Option<u64> {
+    let total: u64 = buckets.iter().sum();
+    if total == 0 {
+        return Some(0);
+    }
+    let target = (total as f64 * quantile).ceil() as u64;
+    let mut seen = 0;
+    for (i, count) in buckets.iter().enumerate() {
+        seen += count;
+        if seen >= target {
+            return BUCKETS_MS.get(i).copied();
+        }
+    }
+    None
+}
+
+static REGISTRY: Lazy<Registry> = Lazy::new(Registry::default);
+
+/// One timed name, totals since startup
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimingRow {
+    pub kind: Kind,
+    pub name: String,
+    pub count: u64,
+    pub errors: u64,
+    pub avg_ms: u64,
+    /// Estimated from the histogram buckets
+    pub p95_ms: u64,
+    pub max_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenRow {
+    pub model: String,
+    pub prompt: u64,
+    pub completion: u64,
+}
+
+/// Everything recorded since startup
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Snapshot {
+    pub timings: Vec<TimingRow>,
+    pub tokens: Vec<TokenRow>,
+}
+
+impl Snapshot {
+    pub fn of(&self, kind: Kind) -> impl Iterator<Item = &TimingRow> {
+        self.timings.iter().filter(move |row| row.kind == kind)
+    }
+}
+
+pub fn record(kind: Kind, name: &str, elapsed: Duration, ok: bool) {
+    REGISTRY.record(kind, name, elapsed, ok);
+}
+
+pub fn record_tokens(model: &str, prompt: u64, completion: u64) {
+    REGISTRY.record_tokens(model, prompt, completion);
+}
+
+/// Run `call`, recording how long it took and whether it failed
+pub async fn timed<T, E>(
+    kind: Kind,
+    name: &str,
+    call: impl Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+    let started = Instant::now();
+    let result = call.await;
+    record(kind, name, started.elapsed(), result.is_ok());
+    result
+}
+
+/// Time a finished command from the start `pre_command` stored on it
+pub async fn record_command(ctx: Context<'_>, ok: bool) {
+    let started = ctx.invocation_data::<Instant>().await.map(|s| *s);
+    if let Some(started) = started {
+        record(
+            Kind::Command,
+            &ctx.command().qualified_name,
+            started.elapsed(),
+            ok,
+        );
+    }
+}
+
+pub fn snapshot() -> Snapshot {
+    REGISTRY.snapshot()
+}
+
+/// Log the snapshot every 10 minutes, one line per name
+pub fn spawn_logger() {
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(LOG_INTERVAL);
+        // The first tick completes immediately
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let snapshot = snapshot();
+            for row in &snapshot.timings {
+                info!(
+                    kind = row.kind.label(),
+                    name = %row.name,
+                    count = row.count,
+                    errors = row.errors,
+                    avg_ms = row.avg_ms,
+                    p95_ms = row.p95_ms,
+                    max_ms = row.max_ms,
+                    "metrics"
+                );
+            }
+            for row in &snapshot.tokens {
+                info!(
+                    model = %row.model,
+                    prompt_tokens = row.prompt,
+                    completion_tokens = row.completion,
+                    "llm tokens"
+                );
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timings_and_percentiles() {
+        let registry = Registry::default();
+        for ms in [10, 20, 30, 40, 60, 70, 80, 90, 120, 3000] {
+            registry.record(
+                Kind::Firestore,
+                "get_document 200",
+                Duration::from_millis(ms),
+                true,
+            );
+        }
+        registry.record(Kind::Command, "stat", Duration::from_millis(400), false);
+        registry.record_tokens("gemini-flash-latest", 120, 30);
+        registry.record_tokens("gemini-flash-latest", 80, 20);
+
+        let snapshot = registry.snapshot();
+        let firestore: Vec<_> = snapshot.of(Kind::Firestore).collect();
+        assert_eq!(firestore.len(), 1);
+        let row = firestore[0];
+        assert_eq!((row.count, row.errors), (10, 0));
+        assert_eq!(row.avg_ms, 352);
+        assert_eq!(row.max_ms, 3000);
+        // The 10th of 10 calls is in the 2500-5000ms bucket, capped at the slowest call
+        assert_eq!(row.p95_ms, 3000);
+
+        let command = snapshot.of(Kind::Command).next().unwrap();
+        assert_eq!((command.count, command.errors), (1, 1));
+        assert_eq!(command.p95_ms, 400);
+
+        assert_eq!(
+            snapshot.tokens,
+            vec![TokenRow {
+                model: "gemini-flash-latest".to_string(),
+                prompt: 200,
+                completion: 50,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_percentile_of_buckets() {
+        assert_eq!(percentile(&[0; 8], 0.95), Some(0));
+        assert_eq!(percentile(&[19, 1, 0, 0, 0, 0, 0, 0], 0.95), Some(50));
+        assert_eq!(percentile(&[18, 1, 1, 0, 0, 0, 0, 0], 0.95), Some(100));
+        assert_eq!(percentile(&[0, 0, 0, 0, 0, 0, 0, 5], 0.95), None);
+    }
+}
@@ -1,8 +1,27 @@
 // Utility functions module
 pub mod ayumi_prompt;
+pub mod clock;
 pub mod config;
+pub mod daily;
+pub mod discord;
 pub mod emojis;
+pub mod error_reply;
 pub mod formatters;
+pub mod health;
+pub mod i18n;
+pub mod images;
+pub mod kana;
+pub mod message_verdicts;
+pub mod metrics;
 pub mod points;
+pub mod privacy;
+pub mod quarantine;
+pub mod reading_speed;
+pub mod shutdown;
+pub mod stats_rebuild;
 pub mod streak;
+#[cfg(test)]
+pub mod test_support;
+pub mod time_of_day;
+pub mod validation;
 pub mod visualizations;
@@ -1,8 +1,11 @@
 // Points calculation system
 // Ported from utils/points.js
 
+use serde_json::Value;
 use std::collections::HashMap;
 
+use crate::utils::privacy::public_logs;
+
 /// Points multipliers for each media type
 /// These values determine how much 1 unit of activity is worth in points
 pub fn points_multipliers() -> HashMap<&'static str, f64> {
@@ -19,15 +22,70 @@ pub fn points_multipliers() -> HashMap<&'static str, f64> {
 
 /// Calculate points for a given media type and amount
 pub fn calculate_points(media_type: &str, amount: f64) -> i64 {
-    let multiplier = points_multipliers().get(media_type).copied().unwrap_or(1.0);
+    calculate_points_with(media_type, amount, None)
+}
 
-    (amount * multiplier).round() as i64
+/// Calculate points using a guild's multiplier overrides, falling back to the defaults
+pub fn calculate_points_with(
+    media_type: &str,
+    amount: f64,
+    overrides: Option<&HashMap<String, f64>>,
+) -> i64 {
+    (amount * effective_multiplier(media_type, overrides)).round() as i64
 }
 
 /// Get the multiplier for a media type
 #[allow(dead_code)]
 pub fn get_multiplier(media_type: &str) -> f64 {
-    points_multipliers().get(media_type).copied().unwrap_or(1.0)
+    effective_multiplier(media_type, None)
+}
+
+/// Multiplier for a media type, preferring a guild override when one is set
+pub fn effective_multiplier(media_type: &str, overrides: Option<&HashMap<String, f64>>) -> f64 {
+    overrides
+        .and_then(|o| o.get(media_type))
+        .copied()
+        .or_else(|| points_multipliers().get(media_type).copied())
+        .unwrap_or(1.0)
+}
+
+/// Points for one immersion log, 0 for logs of another media type or without an amount
+pub fn log_points(
+    log: &Value,
+    media_type_filter: Option<&str>,
+    overrides: Option<&HashMap<String, f64>>,
+) -> f64 {
+    let Some(activity) = log.get("activity").filter(|a| a.is_object()) else {
+        return 0.0;
+    };
+    let Some(media_type) = activity.get("type").and_then(|v| v.as_str()) else {
+        return 0.0;
+    };
+    if media_type_filter.is_some_and(|filter| filter != media_type) {
+        return 0.0;
+    }
+
+    let amount = activity
+        .get("amount")
+        .and_then(|v| v.as_f64())
+        .unwrap_or(0.0);
+    if amount > 0.0 {
+        calculate_points_with(media_type, amount, overrides) as f64
+    } else {
+        0.0
+    }
+}
+
+/// Total points of a user's logs for the leaderboard and recap, private logs left out (each
+/// log is rounded on its own, like the leaderboard always did)
+pub fn sum_log_points<'a>(
+    logs: impl IntoIterator<Item = &'a Value>,
+    media_type_filter: Option<&str>,
+    overrides: Option<&HashMap<String, f64>>,
+) -> f64 {
+    public_logs(logs)
+        .map(|log| log_points(log, media_type_filter, overrides))
+        .sum()
 }
 
 #[cfg(test)]
@@ -57,4 +115,42 @@ mod tests {
         // Unknown types get multiplier of 1.0
         assert_eq!(calculate_points("unknown", 100.0), 100);
     }
+
+    #[test]
+    fn test_guild_overrides() {
+        let overrides = HashMap::from([("anime".to_string(), 20.0)]);
+
+        assert_eq!(calculate_points_with("anime", 2.0, Some(&overrides)), 40);
+        // Types without an override keep the default
+        assert_eq!(calculate_points_with("manga", 100.0, Some(&overrides)), 25);
+        assert_eq!(calculate_points_with("anime", 2.0, None), 26);
+    }
+
+    #[test]
+    fn test_sum_log_points() {
+        let logs = [
+            serde_json::json!({ "activity": { "type": "anime", "amount": 2 } }),
+            serde_json::json!({ "activity": { "type": "manga", "amount": 10.0 } }),
+            serde_json::json!({ "activity": { "type": "manga", "amount": -4.0 } }),
+            serde_json::json!({ "activity": "broken" }),
+        ];
+
+        // 10 manga pages round to 3 points (2.5 rounds away from zero)
+        assert_eq!(sum_log_points(&logs, None, None), 29.0);
+        assert_eq!(sum_log_points(&logs, Some("anime"), None), 26.0);
+        let overrides = HashMap::from([("anime".to_string(), 20.0)]);
+        assert_eq!(sum_log_points(&logs, Some("anime"), Some(&overrides)), 40.0);
+    }
+
+    #[test]
+    fn test_sum_log_points_leaves_out_private_logs() {
+        let public = serde_json::json!({ "activity": { "type": "anime", "amount": 2 } });
+        let private = serde_json::json!({
+            "activity": { "type": "anime", "amount": 5 },
+            "metadata": { "private": true }
+        });
+        assert_eq!(sum_log_points([&public, &private], None, None), 26.0);
+        // Still worth its points on its own, for the owner's stats
+        assert_eq!(log_points(&private, None, None), 65.0);
+    }
 }
@@ -0,0 +1,156 @@
+// Leaderboard privacy
+// Members can opt out of public rankings, either left out entirely or shown as "Anonymous",
+// and keep single logs to themselves. Every aggregate display goes through here so the
+// settings are honoured everywhere.
+
+use serde_json::Value;
+use std::borrow::Cow;
+
+use crate::models::user::UserProfile;
+
+pub const ANONYMOUS_NAME: &str = "Anonymous";
+
+/// Reply when someone looks up the stats of a member who opted out
+pub const LOOKUP_OPTED_OUT: &str =
+    "Member ini memilih untuk merahasiakan statistiknya, jadi tidak bisa dilihat oleh orang lain.";
+
+/// How a member appears on leaderboards and recaps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LeaderboardPrivacy {
+    #[default]
+    Public,
+    Anonymous,
+    Hidden,
+}
+
+impl LeaderboardPrivacy {
+    fn parse(opt_out: bool, mode: Option<&str>) -> Self {
+        match (opt_out, mode) {
+            (false, _) => Self::Public,
+            (true, Some("anonymize")) => Self::Anonymous,
+            (true, _) => Self::Hidden,
+        }
+    }
+
+    pub fn of(profile: &UserProfile) -> Self {
+        Self::parse(
+            profile.leaderboard_opt_out,
+            profile.leaderboard_opt_out_mode.as_deref(),
+        )
+    }
+
+    /// From a raw user document, as listed by `get_all_users`
+    pub fn of_doc(user_doc: &Value) -> Self {
+        let profile = user_doc.get("profile");
+        Self::parse(
+            profile
+                .and_then(|p| p.get("leaderboardOptOut"))
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+            profile
+                .and_then(|p| p.get("leaderboardOptOutMode"))
+                .and_then(Value::as_str),
+        )
+    }
+
+    pub fn is_public(&self) -> bool {
+        *self == Self::Public
+    }
+
+    /// The name to show in place of `name`, `None` when the member is left out
+    pub fn display_name<'a>(&self, name: &'a str) -> Option<Cow<'a, str>> {
+        match self {
+            Self::Public => Some(Cow::Borrowed(name)),
+            Self::Anonymous => Some(Cow::Borrowed(ANONYMOUS_NAME)),
+            Self::Hidden => None,
+        }
+    }
+
+    /// Value stored in `profile.leaderboardOptOutMode`
+    pub fn mode(&self) -> Option<&'static str> {
+        match self {
+            Self::Public => None,
+            Self::Anonymous => Some("anonymize"),
+            Self::Hidden => Some("hide"),
+        }
+    }
+}
+
+/// Whether a log is kept to its owner (`metadata.private`). It counts for their own stats
+/// and streaks, never for anything other members see.
+pub fn is_private_log(log: &Value) -> bool {
+    log.pointer("/metadata/private").and_then(Value::as_bool) == Some(true)
+}
+
+/// Logs that may show up in public aggregates
+pub fn public_logs<'a>(
+    logs: impl IntoIterator<Item = &'a Value>,
+) -> impl Iterator<Item = &'a Value> {
+    logs.into_iter().filter(|log| !is_private_log(log))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_privacy_from_user_doc() {
+        let doc = |profile: Value| json!({ "profile": profile });
+        assert_eq!(
+            LeaderboardPrivacy::of_doc(&json!({})),
+            LeaderboardPrivacy::Public
+        );
+        assert_eq!(
+            LeaderboardPrivacy::of_doc(&doc(json!({ "leaderboardOptOutMode": "anonymize" }))),
+            LeaderboardPrivacy::Public
+        );
+        assert_eq!(
+            LeaderboardPrivacy::of_doc(&doc(json!({ "leaderboardOptOut": true }))),
+            LeaderboardPrivacy::Hidden
+        );
+        assert_eq!(
+            LeaderboardPrivacy::of_doc(&doc(
+                json!({ "leaderboardOptOut": true, "leaderboardOptOutMode": "anonymize" })
+            )),
+            LeaderboardPrivacy::Anonymous
+        );
+
+        // Round trip through the stored mode
+        for privacy in [LeaderboardPrivacy::Anonymous, LeaderboardPrivacy::Hidden] {
+            let profile = UserProfile {
+                leaderboard_opt_out: true,
+                leaderboard_opt_out_mode: privacy.mode().map(String::from),
+                ..Default::default()
+            };
+            assert_eq!(LeaderboardPrivacy::of(&profile), privacy);
+        }
+    }
+
+    #[test]
+    fn test_display_name() {
+        assert_eq!(
+            LeaderboardPrivacy::Public.display_name("Yuki").as_deref(),
+            Some("Yuki")
+        );
+        assert_eq!(
+            LeaderboardPrivacy::Anonymous
+                .display_name("Yuki")
+                .as_deref(),
+            Some(ANONYMOUS_NAME)
+        );
+        assert_eq!(LeaderboardPrivacy::Hidden.display_name("Yuki"), None);
+    }
+
+    #[test]
+    fn test_public_logs() {
+        let logs = [
+            json!({ "metadata": { "source": "manual" } }),
+            json!({ "metadata": { "private": true } }),
+            json!({ "metadata": { "private": false } }),
+            json!({}),
+        ];
+        assert!(is_private_log(&logs[1]));
+        assert_eq!(public_logs(&logs).count(), 3);
+    }
+}
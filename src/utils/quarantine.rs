@@ -0,0 +1,266 @@
+// Failure quarantine for background tasks
+// Skips a guild for a while after it keeps failing, so one broken config doesn't flood the logs
+
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use std::future::Future;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use super::clock::Clock;
+
+/// Consecutive failures before a guild is quarantined
+pub const FAILURE_THRESHOLD: u32 = 3;
+
+/// Quarantine lengths in hours, escalating on each repeat offence (last one is the cap)
+const BACKOFF_HOURS: [i64; 3] = [1, 6, 24];
+
+#[derive(Debug, Clone, Default)]
+struct Entry {
+    consecutive_failures: u32,
+    /// How many times this guild has been quarantined since its last success
+    strikes: usize,
+    until: Option<DateTime<Utc>>,
+}
+
+/// Snapshot of a quarantined (guild, task) pair
+#[derive(Debug, Clone)]
+pub struct QuarantineStatus {
+    pub guild_id: String,
+    pub task: &'static str,
+    pub consecutive_failures: u32,
+    pub until: DateTime<Utc>,
+}
+
+/// Per-(guild, task) failure tracker shared by the background loops
+pub struct Quarantine {
+    clock: Arc<dyn Clock>,
+    entries: DashMap<(String, &'static str), Entry>,
+}
+
+impl Quarantine {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            clock,
+            entries: DashMap::new(),
+        }
+    }
+
+    /// Guard a single run of `task` for `guild_id`
+    pub fn guard(&self, guild_id: impl Into<String>, task: &'static str) -> Guard<'_> {
+        Guard {
+            quarantine: self,
+            guild_id: guild_id.into(),
+            task,
+        }
+    }
+
+    /// Whether the task should currently skip this guild
+    pub fn is_quarantined(&self, guild_id: &str, task: &'static str) -> bool {
+        let now = self.clock.now_utc();
+        self.entries
+            .get(&(guild_id.to_string(), task))
+            .and_then(|e| e.until)
+            .is_some_and(|until| now < until)
+    }
+
+    /// Reset the failure count after a successful run
+    pub fn record_success(&self, guild_id: &str, task: &'static str) {
+        if let Some((_, entry)) = self.entries.remove(&(guild_id.to_string(), task)) {
+            if entry.strikes > 0 {
+                info!(
+                    "Guild {} left quarantine for task '{}' after a successful run",
+                    guild_id, task
+                );
+            }
+        }
+    }
+
+    /// Count a failed run, quarantining the guild once it crosses the threshold
+    pub fn record_failure(&self, guild_id: &str, task: &'static str) {
+        let now = self.clock.now_utc();
+        let mut entry = self
+            .entries
+            .entry((guild_id.to_string(), task))
+            .or_default();
+
+        entry.consecutive_failures += 1;
+
+        // Once past the threshold, any failure after a quarantine expires escalates straight away
+        if entry.consecutive_failures >= FAILURE_THRESHOLD {
+            let hours = BACKOFF_HOURS[entry.strikes.min(BACKOFF_HOURS.len() - 1)];
+            entry.strikes += 1;
+            entry.until = Some(now + Duration::hours(hours));
+            warn!(
+                "Guild {} quarantined from task '{}' for {}h after {} consecutive failures",
+                guild_id, task, hours, entry.consecutive_failures
+            );
+        }
+    }
+
+    /// All pairs that are currently being skipped
+    pub fn list(&self) -> Vec<QuarantineStatus> {
+        let now = self.clock.now_utc();
+        let mut statuses: Vec<QuarantineStatus> = self
+            .entries
+            .iter()
+            .filter_map(|e| {
+                let until = e.until.filter(|until| now < *until)?;
+                Some(QuarantineStatus {
+                    guild_id: e.key().0.clone(),
+                    task: e.key().1,
+                    consecutive_failures: e.consecutive_failures,
+                    until,
+                })
+            })
+            .collect();
+        statuses.sort_by(|a, b| a.guild_id.cmp(&b.guild_id).then(a.task.cmp(b.task)));
+        statuses
+    }
+
+    /// Forget all failures for a guild, returns how many tasks were reset
+    pub fn clear(&self, guild_id: &str) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|(gid, _), _| gid != guild_id);
+        let cleared = before - self.entries.len();
+        if cleared > 0 {
+            info!(
+                "Guild {} manually cleared from quarantine ({} tasks)",
+                guild_id, cleared
+            );
+        }
+        cleared
+    }
+}
+
+/// One guarded run of a background task for a guild
+pub struct Guard<'a> {
+    quarantine: &'a Quarantine,
+    guild_id: String,
+    task: &'static str,
+}
+
+impl Guard<'_> {
+    /// Run `f` unless the guild is quarantined, returns `None` when skipped
+    pub async fn run<F, Fut, T, E>(self, f: F) -> Option<Result<T, E>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        if self.quarantine.is_quarantined(&self.guild_id, self.task) {
+            return None;
+        }
+
+        let result = f().await;
+        match &result {
+            Ok(_) => self.quarantine.record_success(&self.guild_id, self.task),
+            Err(_) => self.quarantine.record_failure(&self.guild_id, self.task),
+        }
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::clock::MockClock;
+
+    const TASK: &str = "test_task";
+
+    fn setup() -> (Arc<MockClock>, Quarantine) {
+        let clock = Arc::new(MockClock::at("2025-01-15T10:00:00Z"));
+        let quarantine = Quarantine::new(clock.clone());
+        (clock, quarantine)
+    }
+
+    fn fail_times(q: &Quarantine, n: u32) {
+        for _ in 0..n {
+            q.record_failure("g1", TASK);
+        }
+    }
+
+    #[test]
+    fn test_backoff_schedule() {
+        let (clock, q) = setup();
+
+        fail_times(&q, 2);
+        assert!(!q.is_quarantined("g1", TASK));
+
+        // Third failure: 1h
+        fail_times(&q, 1);
+        assert!(q.is_quarantined("g1", TASK));
+        clock.advance(Duration::minutes(59));
+        assert!(q.is_quarantined("g1", TASK));
+        clock.advance(Duration::minutes(1));
+        assert!(!q.is_quarantined("g1", TASK));
+
+        // Fails again on release: 6h
+        fail_times(&q, 1);
+        clock.advance(Duration::hours(5));
+        assert!(q.is_quarantined("g1", TASK));
+        clock.advance(Duration::hours(1));
+        assert!(!q.is_quarantined("g1", TASK));
+
+        // Then 24h, and it stays capped there
+        for _ in 0..2 {
+            fail_times(&q, 1);
+            clock.advance(Duration::hours(23));
+            assert!(q.is_quarantined("g1", TASK));
+            clock.advance(Duration::hours(1));
+            assert!(!q.is_quarantined("g1", TASK));
+        }
+
+        // Other guilds and tasks are unaffected
+        fail_times(&q, 1);
+        assert!(!q.is_quarantined("g2", TASK));
+        assert!(!q.is_quarantined("g1", "other_task"));
+    }
+
+    #[test]
+    fn test_success_resets_schedule() {
+        let (clock, q) = setup();
+
+        fail_times(&q, 3);
+        clock.advance(Duration::hours(1));
+        q.record_success("g1", TASK);
+
+        // Needs three fresh failures and starts again at 1h
+        fail_times(&q, 2);
+        assert!(!q.is_quarantined("g1", TASK));
+        fail_times(&q, 1);
+        clock.advance(Duration::hours(1));
+        assert!(!q.is_quarantined("g1", TASK));
+    }
+
+    #[test]
+    fn test_list_and_clear() {
+        let (_clock, q) = setup();
+
+        fail_times(&q, 3);
+        q.record_failure("g2", TASK);
+
+        let listed = q.list();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].guild_id, "g1");
+
+        assert_eq!(q.clear("g1"), 1);
+        assert!(q.list().is_empty());
+        assert!(!q.is_quarantined("g1", TASK));
+    }
+
+    #[tokio::test]
+    async fn test_guard_skips_quarantined_guild() {
+        let (_clock, q) = setup();
+
+        for _ in 0..3 {
+            let ran = q
+                .guard("g1", TASK)
+                .run(|| async { Err::<(), _>("boom") })
+                .await;
+            assert!(ran.is_some());
+        }
+
+        let skipped = q.guard("g1", TASK).run(|| async { Ok::<_, ()>(()) }).await;
+        assert!(skipped.is_none());
+    }
+}
@@ -0,0 +1,131 @@
+// Reading speed insight
+// Characters per minute on days with both a characters log (visual novel, reading)
+// and a reading time log, for `/stat visual_type:ReadingSpeed`
+
+use chrono::{Duration, NaiveDate};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use super::streak::log_date;
+
+/// Days covered by the trend
+pub const TREND_DAYS: i64 = 28;
+
+/// Anything faster is a logging mistake, not reading
+const MAX_CHARS_PER_MINUTE: f64 = 100_000.0;
+
+const CHARACTER_TYPES: [&str; 2] = ["visual_novel", "reading"];
+const TIME_TYPE: &str = "reading_time";
+
+/// Characters per minute on each day in `start..=end` that has both kinds of log
+pub fn daily_speeds(logs: &[Value], start: NaiveDate, end: NaiveDate) -> Vec<(NaiveDate, f64)> {
+    // date -> (characters, minutes)
+    let mut days: BTreeMap<NaiveDate, (f64, f64)> = BTreeMap::new();
+    for log in logs {
+        let Some(date) = log_date(log).and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok())
+        else {
+            continue;
+        };
+        if date < start || date > end {
+            continue;
+        }
+        let activity = log.get("activity");
+        let media_type = activity
+            .and_then(|a| a.get("type"))
+            .and_then(|t| t.as_str())
+            .unwrap_or_default();
+        let amount = activity
+            .and_then(|a| a.get("amount"))
+            .and_then(|a| a.as_f64())
+            .unwrap_or(0.0);
+        if amount <= 0.0 {
+            continue;
+        }
+
+        let day = days.entry(date).or_default();
+        if CHARACTER_TYPES.contains(&media_type) {
+            day.0 += amount;
+        } else if media_type == TIME_TYPE {
+            day.1 += amount;
+        }
+    }
+
+    days.into_iter()
+        .filter(|(_, (chars, minutes))| *chars > 0.0 && *minutes > 0.0)
+        .map(|(date, (chars, minutes))| (date, chars / minutes))
+        .filter(|(_, speed)| *speed <= MAX_CHARS_PER_MINUTE)
+        .collect()
+}
+
+/// Average speed of the last 7 days up to `today` against the 7 before, `None` for a
+/// week without paired days
+pub fn weekly_trend(speeds: &[(NaiveDate, f64)], today: NaiveDate) -> (Option<f64>, Option<f64>) {
+    let average = |from: NaiveDate, to: NaiveDate| {
+        let week: Vec<f64> = speeds
+            .iter()
+            .filter(|(date, _)| *date >= from && *date <= to)
+            .map(|(_, speed)| *speed)
+            .collect();
+        (!week.is_empty()).then(|| week.iter().sum::<f64>() / week.len() as f64)
+    };
+    let this_week_start = today - Duration::days(6);
+    (
+        average(this_week_start, today),
+        average(
+            this_week_start - Duration::days(7),
+            this_week_start - Duration::days(1),
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_support::log;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_daily_speeds_pairs_characters_with_time() {
+        let logs = vec![
+            // Two character logs and a time log on one day
+            log("visual_novel", 6000.0).date("2025-01-10").build(),
+            log("reading", 3000.0).date("2025-01-10").build(),
+            log("reading_time", 30.0).date("2025-01-10").build(),
+            // Only characters
+            log("visual_novel", 5000.0).date("2025-01-11").build(),
+            // Only time
+            log("reading_time", 60.0).date("2025-01-12").build(),
+            // Zero minutes
+            log("visual_novel", 5000.0).date("2025-01-13").build(),
+            log("reading_time", 0.0).date("2025-01-13").build(),
+            // Absurd
+            log("visual_novel", 200_000.0).date("2025-01-14").build(),
+            log("reading_time", 1.0).date("2025-01-14").build(),
+            // Outside the range
+            log("visual_novel", 1000.0).date("2024-12-01").build(),
+            log("reading_time", 10.0).date("2024-12-01").build(),
+        ];
+        assert_eq!(
+            daily_speeds(&logs, date("2025-01-01"), date("2025-01-15")),
+            vec![(date("2025-01-10"), 300.0)]
+        );
+    }
+
+    #[test]
+    fn test_weekly_trend() {
+        let speeds = vec![
+            (date("2025-01-02"), 100.0),
+            (date("2025-01-08"), 200.0),
+            (date("2025-01-09"), 300.0),
+            (date("2025-01-15"), 400.0),
+        ];
+        assert_eq!(
+            weekly_trend(&speeds, date("2025-01-15")),
+            (Some(350.0), Some(150.0))
+        );
+        assert_eq!(weekly_trend(&speeds, date("2025-01-30")), (None, None));
+    }
+}
@@ -0,0 +1,103 @@
+// Graceful shutdown
+// Background loops and delayed cleanups watch one cancellation token. On SIGTERM or Ctrl+C
+// it is cancelled and main gives the tracked tasks a bounded grace period to wind down
+// before the shards are stopped.
+
+use std::future::Future;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+
+/// Shared by everything that should stop with the bot
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    token: CancellationToken,
+    tasks: TaskTracker,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spawn a task shutdown waits for
+    pub fn spawn<F>(&self, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.tasks.spawn(task);
+    }
+
+    /// Completes once shutdown started
+    pub async fn cancelled(&self) {
+        self.token.cancelled().await
+    }
+
+    /// Sleep for `duration`, `false` when shutdown started first
+    pub async fn sleep(&self, duration: Duration) -> bool {
+        tokio::select! {
+            _ = tokio::time::sleep(duration) => true,
+            _ = self.token.cancelled() => false,
+        }
+    }
+
+    /// Tell every task to stop and wait up to `grace` for them, `false` when some didn't
+    pub async fn shutdown(&self, grace: Duration) -> bool {
+        self.token.cancel();
+        self.tasks.close();
+        tokio::time::timeout(grace, self.tasks.wait()).await.is_ok()
+    }
+}
+
+/// Completes on Ctrl+C, or on SIGTERM where there is one (systemd sends it on deploy)
+pub async fn signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to register Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to register SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_shutdown_cancels_sleeps_and_waits_for_tasks() {
+        let shutdown = Shutdown::new();
+        let (done_tx, done_rx) = tokio::sync::oneshot::channel();
+        let task = shutdown.clone();
+        shutdown.spawn(async move {
+            let slept = task.sleep(Duration::from_secs(3600)).await;
+            let _ = done_tx.send(slept);
+        });
+
+        assert!(shutdown.shutdown(Duration::from_secs(5)).await);
+        assert_eq!(done_rx.await, Ok(false));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_gives_up_after_grace() {
+        let shutdown = Shutdown::new();
+        // Ignores the token
+        shutdown.spawn(async {
+            tokio::time::sleep(Duration::from_secs(3600)).await;
+        });
+        assert!(!shutdown.shutdown(Duration::from_millis(20)).await);
+    }
+}
@@ -0,0 +1,366 @@
+// Stats rebuild
+// Recomputes the stats and summary of a user document from their immersion logs, for
+// `/recalculate`. Totals drift when an increment is lost (crashes, the Node.js bot,
+// deletions made by hand), the logs are the source of truth.
+
+use chrono::DateTime;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use super::clock::Clock;
+use super::config::{get_media_label, get_unit};
+use super::streak;
+use crate::api::firebase::{field_path, is_soft_deleted};
+use crate::models::user::UserDoc;
+
+/// Stats of one media type as the logs add up
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RebuiltType {
+    pub total: f64,
+    pub sessions: i64,
+    /// Latest `timestamps.created` of the type's logs
+    pub last_activity: Option<String>,
+    pub current_streak: i64,
+    pub best_streak: i64,
+}
+
+/// Stats of every media type with logs
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RebuiltStats {
+    pub types: BTreeMap<String, RebuiltType>,
+    /// Earliest `timestamps.created` of all logs
+    pub first_activity: Option<String>,
+    /// Latest `timestamps.created` of all logs
+    pub last_activity: Option<String>,
+}
+
+/// Later of two creation timestamps. Unparseable ones lose, so a stray value never
+/// hides a real date.
+fn later(a: Option<String>, b: &str) -> Option<String> {
+    let parse = |s: &str| DateTime::parse_from_rfc3339(s).ok();
+    match a {
+        Some(a) if parse(&a) >= parse(b) => Some(a),
+        _ => Some(b.to_string()),
+    }
+}
+
+fn earlier(a: Option<String>, b: &str) -> Option<String> {
+    let parse = |s: &str| DateTime::parse_from_rfc3339(s).ok();
+    match (a, parse(b)) {
+        (Some(a), Some(b_time)) if parse(&a).is_some_and(|a_time| a_time > b_time) => {
+            Some(b.to_string())
+        }
+        (Some(a), _) => Some(a),
+        (None, _) => Some(b.to_string()),
+    }
+}
+
+/// Add up the logs. Soft-deleted logs and logs without a media type are skipped; legacy
+/// logs without `timestamps.date` count on the WIB day they were created.
+pub fn rebuild_stats(logs: &[Value], clock: &dyn Clock) -> RebuiltStats {
+    let mut rebuilt = RebuiltStats::default();
+    let mut dates: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for log in logs.iter().filter(|log| !is_soft_deleted(log)) {
+        let Some(media_type) = log
+            .get("activity")
+            .and_then(|a| a.get("type"))
+            .and_then(Value::as_str)
+        else {
+            continue;
+        };
+        let amount = log["activity"]["amount"].as_f64().unwrap_or(0.0);
+
+        let stats = rebuilt.types.entry(media_type.to_string()).or_default();
+        stats.total += amount;
+        stats.sessions += 1;
+        if let Some(created) = log["timestamps"]["created"].as_str() {
+            stats.last_activity = later(stats.last_activity.take(), created);
+            rebuilt.last_activity = later(rebuilt.last_activity.take(), created);
+            rebuilt.first_activity = earlier(rebuilt.first_activity.take(), created);
+        }
+        if let Some(date) = streak::log_date(log) {
+            dates.entry(media_type.to_string()).or_default().push(date);
+        }
+    }
+
+    for (media_type, stats) in rebuilt.types.iter_mut() {
+        let mut type_dates = dates.remove(media_type).unwrap_or_default();
+        type_dates.sort();
+        let streak = streak::calculate_streak_with_clock(&type_dates, clock);
+        stats.current_streak = streak.current as i64;
+        stats.best_streak = streak.longest as i64;
+    }
+
+    rebuilt
+}
+
+/// Put the rebuilt numbers into the document. Media types without logs drop to zero,
+/// fields the rebuild doesn't cover are kept.
+pub fn apply(user: &mut UserDoc, rebuilt: &RebuiltStats) {
+    let mut media_types = user.stats.media_types();
+    media_types.extend(rebuilt.types.keys().cloned());
+    media_types.sort();
+    media_types.dedup();
+
+    for media_type in &media_types {
+        let fresh = rebuilt.types.get(media_type).cloned().unwrap_or_default();
+        let stats = user.stats.entry(media_type);
+        stats.total = fresh.total;
+        stats.sessions = fresh.sessions;
+        stats.last_activity = fresh.last_activity;
+        stats.current_streak = fresh.current_streak;
+        stats.best_streak = fresh.best_streak;
+        stats.unit = Some(get_unit(media_type).to_string());
+        stats.label = Some(get_media_label(media_type).to_string());
+    }
+
+    let summary = &mut user.summary;
+    summary.total_sessions = user.stats.total_sessions();
+    summary.last_activity = rebuilt.last_activity.clone();
+    summary.active_types = user.stats.media_types();
+    if let Some(first) = &rebuilt.first_activity {
+        summary.join_date = earlier(summary.join_date.take(), first);
+    }
+}
+
+/// Field paths `apply` may change, for a field-mask write of the rebuilt document
+pub fn field_paths(user: &UserDoc) -> Vec<String> {
+    let mut paths: Vec<String> = user
+        .stats
+        .media_types()
+        .iter()
+        .flat_map(|media_type| {
+            [
+                "total",
+                "sessions",
+                "lastActivity",
+                "currentStreak",
+                "bestStreak",
+                "unit",
+                "label",
+            ]
+            .map(|field| field_path(&["stats", media_type, field]))
+        })
+        .collect();
+    paths.extend(
+        ["totalSessions", "lastActivity", "activeTypes", "joinDate"]
+            .map(|field| field_path(&["summary", field])),
+    );
+    paths
+}
+
+/// A number that differs between the stored and the rebuilt document
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatChange {
+    /// e.g. "Anime total"
+    pub name: String,
+    pub before: f64,
+    pub after: f64,
+}
+
+impl StatChange {
+    /// e.g. "Anime total: 523 → 498 (-25)"
+    pub fn line(&self) -> String {
+        let delta = self.after - self.before;
+        let sign = if delta > 0.0 { "+" } else { "" };
+        format!(
+            "{}: {} → {} ({}{})",
+            self.name,
+            format_value(self.before),
+            format_value(self.after),
+            sign,
+            format_value(delta)
+        )
+    }
+}
+
+fn format_value(n: f64) -> String {
+    if n == n.trunc() {
+        (n as i64).to_string()
+    } else {
+        format!("{:.1}", n)
+    }
+}
+
+/// Totals, sessions and streaks that the rebuild changes, media types in name order
+pub fn diff(before: &UserDoc, after: &UserDoc) -> Vec<StatChange> {
+    let mut changes = Vec::new();
+    for (media_type, new) in after.stats.iter() {
+        let old = before.stats.get(media_type).cloned().unwrap_or_default();
+        let label = get_media_label(media_type);
+        let fields = [
+            ("total", old.total, new.total),
+            ("sessions", old.sessions as f64, new.sessions as f64),
+            (
+                "current streak",
+                old.current_streak as f64,
+                new.current_streak as f64,
+            ),
+            (
+                "best streak",
+                old.best_streak as f64,
+                new.best_streak as f64,
+            ),
+        ];
+        for (field, before, after) in fields {
+            if (before - after).abs() > 1e-9 {
+                changes.push(StatChange {
+                    name: format!("{} {}", label, field),
+                    before,
+                    after,
+                });
+            }
+        }
+    }
+    if before.summary.total_sessions != after.summary.total_sessions {
+        changes.push(StatChange {
+            name: "Total sessions".to_string(),
+            before: before.summary.total_sessions as f64,
+            after: after.summary.total_sessions as f64,
+        });
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::user::{MediaStats, UserStats};
+    use crate::utils::clock::MockClock;
+    use crate::utils::test_support::log;
+    use serde_json::json;
+
+    fn logs() -> Vec<Value> {
+        vec![
+            log("anime", 3.0)
+                .created("2025-01-13T10:00:00+00:00")
+                .date("2025-01-13")
+                .build(),
+            log("anime", 2.0)
+                .created("2025-01-14T12:00:00+00:00")
+                .date("2025-01-14")
+                .build(),
+            // Legacy log: 2025-01-14T20:00Z is 2025-01-15 in WIB
+            log("anime", 1.0)
+                .created("2025-01-14T20:00:00+00:00")
+                .build(),
+            log("reading", 5000.0)
+                .created("2025-01-10T10:00:00+00:00")
+                .date("2025-01-10")
+                .build(),
+            log("anime", 100.0)
+                .created("2025-01-14T10:00:00+00:00")
+                .date("2025-01-14")
+                .deleted(true)
+                .build(),
+            json!({ "activity": { "amount": 5.0 } }),
+        ]
+    }
+
+    #[test]
+    fn test_rebuild_stats_from_logs() {
+        let clock = MockClock::at("2025-01-15T12:00:00Z");
+        let rebuilt = rebuild_stats(&logs(), &clock);
+
+        assert_eq!(
+            rebuilt.types["anime"],
+            RebuiltType {
+                total: 6.0,
+                sessions: 3,
+                last_activity: Some("2025-01-14T20:00:00+00:00".to_string()),
+                current_streak: 3,
+                best_streak: 3,
+            }
+        );
+        assert_eq!(
+            rebuilt.types["reading"],
+            RebuiltType {
+                total: 5000.0,
+                sessions: 1,
+                last_activity: Some("2025-01-10T10:00:00+00:00".to_string()),
+                current_streak: 0,
+                best_streak: 1,
+            }
+        );
+        assert_eq!(rebuilt.types.len(), 2);
+        assert_eq!(
+            rebuilt.first_activity.as_deref(),
+            Some("2025-01-10T10:00:00+00:00")
+        );
+        assert_eq!(
+            rebuilt.last_activity.as_deref(),
+            Some("2025-01-14T20:00:00+00:00")
+        );
+    }
+
+    #[test]
+    fn test_apply_and_diff() {
+        let clock = MockClock::at("2025-01-15T12:00:00Z");
+        let mut stats = BTreeMap::new();
+        stats.insert(
+            "anime".to_string(),
+            MediaStats {
+                total: 31.0,
+                sessions: 4,
+                current_streak: 3,
+                best_streak: 3,
+                ..Default::default()
+            },
+        );
+        // Only ever logged through the old bot, every log is gone
+        stats.insert(
+            "manga".to_string(),
+            MediaStats {
+                total: 40.0,
+                sessions: 2,
+                best_streak: 1,
+                ..Default::default()
+            },
+        );
+        let mut before = UserDoc {
+            stats: UserStats(stats),
+            ..Default::default()
+        };
+        before.summary.total_sessions = 6;
+        before.summary.join_date = Some("2024-06-01T00:00:00+00:00".to_string());
+
+        let mut after = before.clone();
+        apply(&mut after, &rebuild_stats(&logs(), &clock));
+
+        assert_eq!(after.stats.total("manga"), 0.0);
+        assert_eq!(after.stats.get("reading").unwrap().sessions, 1);
+        assert_eq!(after.summary.total_sessions, 4);
+        assert_eq!(
+            after.summary.active_types,
+            vec!["anime", "manga", "reading"]
+        );
+        // An earlier join date than any surviving log is kept
+        assert_eq!(
+            after.summary.join_date.as_deref(),
+            Some("2024-06-01T00:00:00+00:00")
+        );
+
+        let changes: Vec<String> = diff(&before, &after).iter().map(StatChange::line).collect();
+        assert_eq!(
+            changes,
+            vec![
+                "Anime total: 31 → 6 (-25)",
+                "Anime sessions: 4 → 3 (-1)",
+                "Manga total: 40 → 0 (-40)",
+                "Manga sessions: 2 → 0 (-2)",
+                "Manga best streak: 1 → 0 (-1)",
+                "Reading total: 0 → 5000 (+5000)",
+                "Reading sessions: 0 → 1 (+1)",
+                "Reading best streak: 0 → 1 (+1)",
+                "Total sessions: 6 → 4 (-2)",
+            ]
+        );
+
+        // Once applied there is nothing left to fix
+        let mut again = after.clone();
+        apply(&mut again, &rebuild_stats(&logs(), &clock));
+        assert!(diff(&after, &again).is_empty());
+        assert!(field_paths(&after).contains(&"stats.manga.total".to_string()));
+    }
+}
@@ -1,10 +1,12 @@
 // Streak calculation system
 // Ported from utils/streak.js
 
-use chrono::{Duration, NaiveDate};
+use chrono::{DateTime, Duration, NaiveDate};
+use serde_json::Value;
 use std::collections::HashSet;
 
-use super::config::get_effective_date;
+use super::clock::{Clock, SystemClock};
+use super::config::{effective_date_at, local_date_in};
 
 /// Result of streak calculation
 #[derive(Debug, Clone, Default)]
@@ -15,7 +17,18 @@ pub struct StreakResult {
 
 /// Calculate streak from a list of activity dates
 /// Dates should be in YYYY-MM-DD format and sorted ascending
+#[allow(dead_code)]
 pub fn calculate_streak(dates: &[String]) -> StreakResult {
+    calculate_streak_with_clock(dates, &SystemClock)
+}
+
+/// Calculate streak relative to the effective date of the given clock
+pub fn calculate_streak_with_clock(dates: &[String], clock: &dyn Clock) -> StreakResult {
+    calculate_streak_on(dates, effective_date_at(clock.now_utc()))
+}
+
+/// Calculate streak as of `today`, the effective date in the member's timezone
+pub fn calculate_streak_on(dates: &[String], today: NaiveDate) -> StreakResult {
     if dates.is_empty() {
         return StreakResult::default();
     }
@@ -34,8 +47,6 @@ pub fn calculate_streak(dates: &[String]) -> StreakResult {
 
     let date_set: HashSet<NaiveDate> = parsed_dates.iter().cloned().collect();
 
-    // Get today and yesterday with day offset
-    let today = get_effective_date();
     let yesterday = today - Duration::days(1);
 
     // Calculate current streak from today backwards
@@ -69,6 +80,133 @@ pub fn calculate_streak(dates: &[String]) -> StreakResult {
     }
 }
 
+/// Consecutive days of logging that earn a streak freeze
+pub const FREEZE_EARN_DAYS: i32 = 30;
+
+/// Most streak freezes a user can hold
+pub const MAX_STREAK_FREEZES: u32 = 2;
+
+/// Current streak where single missed days may be bridged by freezes
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FreezeStreak {
+    /// Days logged in the streak, bridged days don't count
+    pub current: i32,
+    /// Missed days bridged with a freeze that wasn't spent on them before, oldest first
+    pub newly_frozen: Vec<String>,
+    /// Freezes left after bridging
+    pub freezes_left: u32,
+}
+
+/// Current streak as of `today`, letting a freeze bridge a single missed day.
+/// `frozen` are days a freeze was already spent on; they bridge for free.
+/// Only a day missed since the last log can take a new freeze, yesterday or the day before
+/// when yesterday was logged late; an older one that wasn't bridged back then ended the
+/// streak there. Two missed days in a row always break the streak.
+pub fn calculate_streak_with_freezes(
+    dates: &[String],
+    frozen: &[String],
+    freezes_available: u32,
+    today: NaiveDate,
+) -> FreezeStreak {
+    let parse = |dates: &[String]| -> HashSet<NaiveDate> {
+        dates
+            .iter()
+            .filter_map(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+            .collect()
+    };
+    let active = parse(dates);
+    let frozen = parse(frozen);
+    let covered = |day: NaiveDate| active.contains(&day) || frozen.contains(&day);
+
+    let mut result = FreezeStreak {
+        freezes_left: freezes_available,
+        ..Default::default()
+    };
+    let mut newly_frozen = Vec::new();
+
+    // Not having logged yet today never breaks the streak
+    let mut day = if active.contains(&today) {
+        today
+    } else {
+        today - Duration::days(1)
+    };
+    let oldest_freezable = today - Duration::days(2);
+    loop {
+        let previous = day - Duration::days(1);
+        if active.contains(&day) {
+            result.current += 1;
+        } else if frozen.contains(&day) && covered(previous) {
+            // Paid for already
+        } else if day >= oldest_freezable && result.freezes_left > 0 && covered(previous) {
+            result.freezes_left -= 1;
+            newly_frozen.push(day);
+        } else {
+            break;
+        }
+        day = previous;
+    }
+
+    newly_frozen.sort();
+    result.newly_frozen = newly_frozen
+        .iter()
+        .map(|d| d.format("%Y-%m-%d").to_string())
+        .collect();
+    result
+}
+
+/// Freezes earned when the streak went from `before` to `after` days
+pub fn freezes_earned(before: i32, after: i32) -> u32 {
+    if after <= before || before < 0 {
+        return 0;
+    }
+    (after / FREEZE_EARN_DAYS - before / FREEZE_EARN_DAYS) as u32
+}
+
+/// Activity date (YYYY-MM-DD) of an immersion log document, for members in WIB
+pub fn log_date(log: &Value) -> Option<String> {
+    log_date_in(log, None)
+}
+
+/// Activity date (YYYY-MM-DD) of an immersion log document for a member in `tz`.
+/// The stored `timestamps.date` wins; legacy logs without one fall back to the calendar
+/// date of `created` in `tz`, or in WIB (UTC+7) without one, which is what the Node.js
+/// bot stored as the raw date.
+pub fn log_date_in(log: &Value, tz: Option<&str>) -> Option<String> {
+    let timestamps = log.get("timestamps")?;
+
+    if let Some(date) = timestamps.get("date").and_then(|v| v.as_str()) {
+        return Some(date.to_string());
+    }
+
+    let created = timestamps.get("created").and_then(|v| v.as_str())?;
+    let created_utc = DateTime::parse_from_rfc3339(created).ok()?;
+    Some(
+        local_date_in(tz, created_utc.with_timezone(&chrono::Utc))
+            .format("%Y-%m-%d")
+            .to_string(),
+    )
+}
+
+/// Activity dates of a set of logs, optionally only those of one media type
+pub fn log_dates(logs: &[Value], media_type: Option<&str>) -> Vec<String> {
+    log_dates_in(logs, media_type, None)
+}
+
+/// `log_dates` for a member in `tz`, see `log_date_in`
+pub fn log_dates_in(logs: &[Value], media_type: Option<&str>, tz: Option<&str>) -> Vec<String> {
+    logs.iter()
+        .filter(|log| {
+            media_type.is_none_or(|mt| {
+                log.get("activity")
+                    .and_then(|a| a.get("type"))
+                    .and_then(|t| t.as_str())
+                    == Some(mt)
+            })
+        })
+        .filter_map(|log| log_date_in(log, tz))
+        .collect()
+}
+
 /// Calculate only the longest streak (when current is 0)
 fn calculate_longest_only(dates: &[NaiveDate]) -> StreakResult {
     StreakResult {
@@ -114,6 +252,8 @@ fn calculate_longest_streak(dates: &[NaiveDate]) -> i32 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::clock::MockClock;
+    use crate::utils::config::get_effective_date;
 
     fn today_str() -> String {
         get_effective_date().format("%Y-%m-%d").to_string()
@@ -176,4 +316,206 @@ mod tests {
         assert_eq!(result.current, 2);
         assert_eq!(result.longest, 3);
     }
+
+    #[test]
+    fn test_log_date_legacy_fallback() {
+        let log = serde_json::json!({
+            "timestamps": { "created": "2025-01-15T18:30:00Z" }
+        });
+        // 01:30 WIB on the 16th
+        assert_eq!(log_date(&log).as_deref(), Some("2025-01-16"));
+
+        let log = serde_json::json!({
+            "timestamps": { "date": "2025-01-15", "created": "2025-01-15T18:30:00Z" }
+        });
+        assert_eq!(log_date(&log).as_deref(), Some("2025-01-15"));
+    }
+
+    #[test]
+    fn test_log_date_in_timezone() {
+        let created = |at: &str| serde_json::json!({ "timestamps": { "created": at } });
+        let new_york = Some("America/New_York");
+
+        // 23:30 EST on Mar 8 is already Mar 9 in WIB
+        let log = created("2025-03-09T04:30:00Z");
+        assert_eq!(log_date_in(&log, new_york).as_deref(), Some("2025-03-08"));
+        assert_eq!(log_date_in(&log, None).as_deref(), Some("2025-03-09"));
+        // Clocks went forward at 07:00 UTC, 04:30 UTC is now 00:30 EDT
+        let log = created("2025-03-10T04:30:00Z");
+        assert_eq!(log_date_in(&log, new_york).as_deref(), Some("2025-03-10"));
+        // And back on Nov 2, 04:30 UTC is 23:30 EST the day before again
+        let log = created("2025-11-03T04:30:00Z");
+        assert_eq!(log_date_in(&log, new_york).as_deref(), Some("2025-11-02"));
+        let log = created("2025-11-01T04:30:00Z");
+        assert_eq!(log_date_in(&log, new_york).as_deref(), Some("2025-11-01"));
+
+        // A stored date is never moved, whatever the timezone
+        let log = serde_json::json!({
+            "timestamps": { "date": "2025-03-09", "created": "2025-03-09T04:30:00Z" }
+        });
+        assert_eq!(log_date_in(&log, new_york).as_deref(), Some("2025-03-09"));
+        assert_eq!(
+            log_dates_in(&[log, created("2025-03-09T04:30:00Z")], None, new_york),
+            vec!["2025-03-09", "2025-03-08"]
+        );
+    }
+
+    #[test]
+    fn test_streak_on_date() {
+        let today = NaiveDate::from_ymd_opt(2025, 1, 15).unwrap();
+        let dates = vec!["2025-01-13".to_string(), "2025-01-14".to_string()];
+        assert_eq!(calculate_streak_on(&dates, today).current, 2);
+        // Two days later it's broken
+        assert_eq!(
+            calculate_streak_on(&dates, today + Duration::days(2)).current,
+            0
+        );
+    }
+
+    #[test]
+    fn test_log_dates_by_media_type() {
+        let logs = vec![
+            serde_json::json!({ "activity": { "type": "anime" }, "timestamps": { "date": "2025-01-14" } }),
+            serde_json::json!({ "activity": { "type": "manga" }, "timestamps": { "date": "2025-01-15" } }),
+            serde_json::json!({ "activity": { "type": "anime" }, "timestamps": { "created": "2025-01-15T03:00:00Z" } }),
+        ];
+
+        assert_eq!(
+            log_dates(&logs, Some("anime")),
+            vec!["2025-01-14", "2025-01-15"]
+        );
+        assert_eq!(log_dates(&logs, None).len(), 3);
+    }
+
+    #[test]
+    fn test_streak_across_day_boundary() {
+        // 23:00 WIB on Jan 15, logged on Jan 14 and Jan 15
+        let clock = MockClock::at("2025-01-15T16:00:00Z");
+        let dates = vec!["2025-01-14".to_string(), "2025-01-15".to_string()];
+        assert_eq!(calculate_streak_with_clock(&dates, &clock).current, 2);
+
+        // 01:30 WIB on Jan 16 is still Jan 15 because of the day offset
+        clock.advance(Duration::minutes(150));
+        assert_eq!(calculate_streak_with_clock(&dates, &clock).current, 2);
+
+        // Jan 16 is today now, Jan 15 counts as yesterday so the streak survives
+        clock.advance(Duration::hours(1));
+        assert_eq!(calculate_streak_with_clock(&dates, &clock).current, 2);
+
+        // A full day without logging breaks it
+        clock.advance(Duration::days(1));
+        let result = calculate_streak_with_clock(&dates, &clock);
+        assert_eq!(result.current, 0);
+        assert_eq!(result.longest, 2);
+    }
+
+    fn days_back(today: NaiveDate, offsets: impl IntoIterator<Item = i64>) -> Vec<String> {
+        offsets
+            .into_iter()
+            .map(|i| (today - Duration::days(i)).format("%Y-%m-%d").to_string())
+            .collect()
+    }
+
+    fn jan(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 1, day).unwrap()
+    }
+
+    #[test]
+    fn test_freeze_bridges_single_missed_day() {
+        let today = jan(20);
+        // Missed the 18th
+        let dates = days_back(today, [0, 1, 3, 4, 5]);
+
+        let without = calculate_streak_with_freezes(&dates, &[], 0, today);
+        assert_eq!(without.current, 2);
+
+        let with = calculate_streak_with_freezes(&dates, &[], 1, today);
+        assert_eq!(with.current, 5);
+        assert_eq!(with.newly_frozen, vec!["2025-01-18".to_string()]);
+        assert_eq!(with.freezes_left, 0);
+    }
+
+    #[test]
+    fn test_two_missed_days_break_the_streak() {
+        let today = jan(20);
+        let dates = days_back(today, [0, 3, 4]);
+
+        let result = calculate_streak_with_freezes(&dates, &[], 1, today);
+        assert_eq!(result.current, 1);
+        assert!(result.newly_frozen.is_empty());
+        assert_eq!(result.freezes_left, 1);
+
+        // Two freezes can't cover two days in a row either
+        let result = calculate_streak_with_freezes(&dates, &[], 2, today);
+        assert_eq!(result.current, 1);
+        assert_eq!(result.freezes_left, 2);
+    }
+
+    #[test]
+    fn test_frozen_gaps_bridge_without_costing() {
+        let today = jan(20);
+        // Missed the 19th, 17th and 15th
+        let dates = days_back(today, [0, 2, 4, 6, 7]);
+
+        // Only the 19th can take a new freeze, the 17th was logged past unbridged
+        let result = calculate_streak_with_freezes(&dates, &[], 2, today);
+        assert_eq!(result.current, 2);
+        assert_eq!(result.newly_frozen, vec!["2025-01-19".to_string()]);
+        assert_eq!(result.freezes_left, 1);
+
+        // Freezes spent earlier bridge again without costing one
+        let frozen = vec!["2025-01-15".to_string(), "2025-01-17".to_string()];
+        let result = calculate_streak_with_freezes(&dates, &frozen, 2, today);
+        assert_eq!(result.current, 5);
+        assert_eq!(result.newly_frozen, vec!["2025-01-19".to_string()]);
+        assert_eq!(result.freezes_left, 1);
+    }
+
+    #[test]
+    fn test_old_gap_ends_the_streak() {
+        let today = jan(20);
+        // Missed the 15th, and logged every day since
+        let dates = days_back(today, [0, 1, 2, 3, 4, 6, 7, 8, 9]);
+
+        let result = calculate_streak_with_freezes(&dates, &[], 2, today);
+        assert_eq!(result.current, 5);
+        assert!(result.newly_frozen.is_empty());
+        assert_eq!(result.freezes_left, 2);
+    }
+
+    #[test]
+    fn test_freezes_never_go_negative() {
+        let today = jan(20);
+        let dates = days_back(today, [0, 2, 3]);
+        for available in 0..=MAX_STREAK_FREEZES {
+            let result = calculate_streak_with_freezes(&dates, &[], available, today);
+            let spent = available.min(1);
+            assert_eq!(result.newly_frozen.len() as u32, spent);
+            assert_eq!(result.freezes_left, available - spent);
+            assert_eq!(result.current, 1 + 2 * spent as i32);
+        }
+    }
+
+    #[test]
+    fn test_freeze_keeps_streak_until_today_is_logged() {
+        let today = jan(20);
+        // Missed yesterday, nothing logged today yet
+        let dates = days_back(today, [2, 3]);
+        let result = calculate_streak_with_freezes(&dates, &[], 1, today);
+        assert_eq!(result.current, 2);
+        assert_eq!(result.newly_frozen, vec!["2025-01-19".to_string()]);
+
+        assert_eq!(calculate_streak_with_freezes(&[], &[], 2, today).current, 0);
+    }
+
+    #[test]
+    fn test_freezes_earned_every_30_days() {
+        assert_eq!(freezes_earned(29, 30), 1);
+        assert_eq!(freezes_earned(30, 30), 0);
+        assert_eq!(freezes_earned(30, 31), 0);
+        assert_eq!(freezes_earned(59, 60), 1);
+        // A backdated log filling a gap can jump over a multiple
+        assert_eq!(freezes_earned(28, 31), 1);
+        assert_eq!(freezes_earned(40, 1), 0);
+    }
 }
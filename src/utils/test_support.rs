@@ -0,0 +1,61 @@
+// Test helpers
+// Immersion log documents shaped like the ones `/immersion` saves, for the unit tests
+
+use serde_json::{json, Value};
+
+use super::config::get_unit;
+use crate::commands::log::ImmersionLog;
+
+/// An immersion log document with only the fields a test sets
+#[derive(Debug, Clone)]
+pub struct LogBuilder(Value);
+
+/// A log of `amount` in `media_type`, without timestamps until they're set
+pub fn log(media_type: &str, amount: f64) -> LogBuilder {
+    LogBuilder(json!({
+        "activity": { "type": media_type, "amount": amount, "unit": get_unit(media_type) },
+        "timestamps": {}
+    }))
+}
+
+impl LogBuilder {
+    pub fn title(mut self, title: &str) -> Self {
+        self.0["activity"]["title"] = json!(title);
+        self
+    }
+
+    /// `timestamps.created`, RFC3339
+    pub fn created(mut self, created: &str) -> Self {
+        self.0["timestamps"]["created"] = json!(created);
+        self
+    }
+
+    /// `timestamps.date`, the activity date (YYYY-MM-DD)
+    pub fn date(mut self, date: &str) -> Self {
+        self.0["timestamps"]["date"] = json!(date);
+        self
+    }
+
+    /// `timestamps.month`, taken from the date like `/immersion` does
+    pub fn month_of_date(mut self) -> Self {
+        let month = self.0["timestamps"]["date"]
+            .as_str()
+            .map(|d| d[..7].to_string());
+        self.0["timestamps"]["month"] = json!(month);
+        self
+    }
+
+    pub fn deleted(mut self, deleted: bool) -> Self {
+        self.0["deleted"] = json!(deleted);
+        self
+    }
+
+    pub fn build(self) -> Value {
+        self.0
+    }
+
+    /// The typed log, which needs a creation time
+    pub fn into_log(self) -> ImmersionLog {
+        serde_json::from_value(self.0).expect("test log is missing a field")
+    }
+}
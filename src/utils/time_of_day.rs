@@ -0,0 +1,225 @@
+// Time of day insight
+// Points per hour of the day, in the member's timezone, from when each log was saved,
+// for `/stat visual_type:TimeOfDay`
+
+use chrono::DateTime;
+use serde_json::Value;
+use std::collections::HashMap;
+
+use super::config::{effective_date_in, local_hour_in};
+use super::points::calculate_points_with;
+
+/// Hours in the busiest-window summary
+const WINDOW_HOURS: usize = 2;
+
+/// Width of the longest histogram bar
+const BAR_WIDTH: f64 = 12.0;
+
+/// Points per hour and how many logs had no usable time
+#[derive(Debug, Default, PartialEq)]
+pub struct HourlyPoints {
+    pub hours: [f64; 24],
+    pub excluded: usize,
+}
+
+impl HourlyPoints {
+    pub fn total(&self) -> f64 {
+        self.hours.iter().sum()
+    }
+}
+
+/// Hour a log was saved at in `tz` (WIB without one). `None` when the log has no creation time, or when it was
+/// logged for another day (a custom date or an imported log), since then the time says
+/// when it was typed in rather than when the immersion happened.
+fn log_hour(log: &Value, tz: Option<&str>) -> Option<u32> {
+    let timestamps = log.get("timestamps")?;
+    let created = DateTime::parse_from_rfc3339(timestamps.get("created")?.as_str()?)
+        .ok()?
+        .with_timezone(&chrono::Utc);
+    if let Some(date) = timestamps.get("date").and_then(|d| d.as_str()) {
+        if effective_date_in(tz, created)
+            .format("%Y-%m-%d")
+            .to_string()
+            != date
+        {
+            return None;
+        }
+    }
+    Some(local_hour_in(tz, created))
+}
+
+/// Bucket the logs' points into the 24 hours of the day
+pub fn hourly_points(
+    logs: &[Value],
+    overrides: Option<&HashMap<String, f64>>,
+    tz: Option<&str>,
+) -> HourlyPoints {
+    let mut result = HourlyPoints::default();
+    for log in logs {
+        let activity = log.get("activity");
+        let media_type = activity
+            .and_then(|a| a.get("type"))
+            .and_then(|t| t.as_str());
+        let amount = activity
+            .and_then(|a| a.get("amount"))
+            .and_then(|a| a.as_f64());
+        let (Some(media_type), Some(amount)) = (media_type, amount) else {
+            continue;
+        };
+        match log_hour(log, tz) {
+            Some(hour) => {
+                result.hours[hour as usize] +=
+                    calculate_points_with(media_type, amount, overrides) as f64;
+            }
+            None => result.excluded += 1,
+        }
+    }
+    result
+}
+
+/// Start hour and share of points of the busiest window, which may wrap past midnight
+pub fn busiest_window(hourly: &HourlyPoints) -> Option<(usize, f64)> {
+    let total = hourly.total();
+    if total <= 0.0 {
+        return None;
+    }
+    (0..24)
+        .map(|start| {
+            let points: f64 = (0..WINDOW_HOURS)
+                .map(|i| hourly.hours[(start + i) % 24])
+                .sum();
+            (start, points)
+        })
+        // The earliest start wins a tie
+        .fold(
+            None,
+            |best: Option<(usize, f64)>, (start, points)| match best {
+                Some((_, best_points)) if best_points >= points => best,
+                _ => Some((start, points)),
+            },
+        )
+        .map(|(start, points)| (start, points / total))
+}
+
+/// "Most active: 21:00–23:00 (34% of points)"
+pub fn summary(hourly: &HourlyPoints) -> Option<String> {
+    let (start, share) = busiest_window(hourly)?;
+    Some(format!(
+        "Most active: {:02}:00–{:02}:00 ({:.0}% of points)",
+        start,
+        (start + WINDOW_HOURS) % 24,
+        share * 100.0
+    ))
+}
+
+/// One line per hour, "21 ████████ 1234", scaled to the busiest hour
+pub fn histogram(hourly: &HourlyPoints) -> String {
+    let max = hourly.hours.iter().cloned().fold(0.0, f64::max);
+    hourly
+        .hours
+        .iter()
+        .enumerate()
+        .map(|(hour, &points)| {
+            let width = if max > 0.0 {
+                (points / max * BAR_WIDTH).round() as usize
+            } else {
+                0
+            };
+            // Any activity gets at least a sliver
+            let width = if points > 0.0 { width.max(1) } else { 0 };
+            format!("{:02} {:<12} {}", hour, "█".repeat(width), points.round())
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_support::log;
+
+    #[test]
+    fn test_hourly_points() {
+        let logs = vec![
+            // 14:30 UTC is 21:30 WIB
+            log("anime", 1.0)
+                .created("2025-01-15T14:30:00+00:00")
+                .date("2025-01-15")
+                .build(),
+            // 18:10 UTC is 01:10 WIB on the 16th, still the 15th before the day ends at 2:00
+            log("anime", 2.0)
+                .created("2025-01-15T18:10:00+00:00")
+                .date("2025-01-15")
+                .build(),
+            // Logged for an earlier day
+            log("anime", 3.0)
+                .created("2025-01-15T14:30:00+00:00")
+                .date("2025-01-10")
+                .build(),
+            // No creation time
+            log("anime", 4.0).date("2025-01-15").build(),
+        ];
+        let hourly = hourly_points(&logs, None, None);
+        assert_eq!(hourly.hours[21], 13.0);
+        assert_eq!(hourly.hours[1], 26.0);
+        assert_eq!(hourly.total(), 39.0);
+        assert_eq!(hourly.excluded, 2);
+    }
+
+    #[test]
+    fn test_hourly_points_in_member_timezone() {
+        let logs = vec![
+            // 14:30 UTC is 15:30 in Berlin (CET)
+            log("anime", 1.0)
+                .created("2025-01-15T14:30:00+00:00")
+                .date("2025-01-15")
+                .build(),
+            // 00:30 UTC on the 16th is 01:30 in Berlin, still the 15th there
+            log("anime", 2.0)
+                .created("2025-01-16T00:30:00+00:00")
+                .date("2025-01-15")
+                .build(),
+            // 20:10 UTC is 21:10 on the 15th in Berlin but the 16th in WIB, so it counts here only
+            log("anime", 3.0)
+                .created("2025-01-15T20:10:00+00:00")
+                .date("2025-01-15")
+                .build(),
+        ];
+        let hourly = hourly_points(&logs, None, Some("Europe/Berlin"));
+        assert_eq!(hourly.hours[15], 13.0);
+        assert_eq!(hourly.hours[1], 26.0);
+        assert_eq!(hourly.hours[21], 39.0);
+        assert_eq!(hourly.excluded, 0);
+
+        // The same logs in WIB
+        let wib = hourly_points(&logs, None, None);
+        assert_eq!(wib.hours[21], 13.0);
+        assert_eq!(wib.excluded, 2);
+    }
+
+    #[test]
+    fn test_busiest_window_wraps_midnight() {
+        let mut hourly = HourlyPoints::default();
+        hourly.hours[23] = 30.0;
+        hourly.hours[0] = 30.0;
+        hourly.hours[12] = 40.0;
+        assert_eq!(busiest_window(&hourly), Some((23, 0.6)));
+        assert_eq!(
+            summary(&hourly).as_deref(),
+            Some("Most active: 23:00–01:00 (60% of points)")
+        );
+        assert_eq!(busiest_window(&HourlyPoints::default()), None);
+    }
+
+    #[test]
+    fn test_histogram() {
+        let mut hourly = HourlyPoints::default();
+        hourly.hours[21] = 100.0;
+        hourly.hours[22] = 1.0;
+        let lines: Vec<_> = histogram(&hourly).lines().map(str::to_string).collect();
+        assert_eq!(lines.len(), 24);
+        assert_eq!(lines[21], format!("21 {} 100", "█".repeat(12)));
+        assert_eq!(lines[22], format!("22 {:<12} 1", "█"));
+        assert_eq!(lines[0], format!("00 {:<12} 0", ""));
+    }
+}
@@ -0,0 +1,145 @@
+// Immersion amount validation
+// Caps on a single log per media type, so an amount typed under the wrong type
+// (20000 "episodes" meant as characters) doesn't land on the leaderboard
+
+use super::config::{get_media_label, get_unit};
+use super::formatters::format_number;
+
+/// Most one log of each media type may hold
+const AMOUNT_LIMITS: [(&str, f64); 7] = [
+    ("anime", 50.0),
+    ("manga", 1000.0),
+    ("book", 2000.0),
+    ("listening", 1440.0),
+    ("reading_time", 1440.0),
+    ("visual_novel", 500_000.0),
+    ("reading", 500_000.0),
+];
+
+/// Types an over-limit amount was probably meant for, most likely first
+fn likely_types(media_type: &str) -> &'static [&'static str] {
+    match media_type {
+        // Minutes watched, or characters read
+        "anime" => &["listening", "reading"],
+        "manga" => &["book", "reading"],
+        "book" | "listening" | "reading_time" => &["reading"],
+        _ => &[],
+    }
+}
+
+pub fn amount_limit(media_type: &str) -> Option<f64> {
+    AMOUNT_LIMITS
+        .iter()
+        .find(|(t, _)| *t == media_type)
+        .map(|(_, limit)| *limit)
+}
+
+/// Why an amount can't be logged
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum AmountError {
+    #[error("Amount must be a number greater than 0.")]
+    NotPositive,
+    #[error("{}", over_limit_message(media_type, *limit, *suggestion))]
+    OverLimit {
+        media_type: &'static str,
+        limit: f64,
+        /// The type the amount fits, when it looks like it was meant for another one
+        suggestion: Option<&'static str>,
+    },
+}
+
+fn over_limit_message(media_type: &str, limit: f64, suggestion: Option<&str>) -> String {
+    let mut message = format!(
+        "{} logs are limited to **{} {}** each.",
+        get_media_label(media_type),
+        format_number(limit as i64),
+        get_unit(media_type)
+    );
+    if let Some(suggested) = suggestion {
+        message.push_str(&format!(
+            " Did you mean {} ({})?",
+            get_media_label(suggested),
+            get_unit(suggested)
+        ));
+    }
+    message
+}
+
+/// Check an amount against its media type's cap
+pub fn validate_amount(media_type: &str, amount: f64) -> Result<(), AmountError> {
+    if !amount.is_finite() || amount <= 0.0 {
+        return Err(AmountError::NotPositive);
+    }
+    let Some((media_type, limit)) = AMOUNT_LIMITS.iter().find(|(t, _)| *t == media_type) else {
+        return Ok(());
+    };
+    if amount <= *limit {
+        return Ok(());
+    }
+    let suggestion = likely_types(media_type)
+        .iter()
+        .copied()
+        .find(|t| amount_limit(t).is_some_and(|l| amount <= l));
+    Err(AmountError::OverLimit {
+        media_type,
+        limit: *limit,
+        suggestion,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_amount_within_limit() {
+        assert_eq!(validate_amount("anime", 12.0), Ok(()));
+        assert_eq!(validate_amount("anime", 50.0), Ok(()));
+        assert_eq!(validate_amount("visual_novel", 500_000.0), Ok(()));
+        assert_eq!(validate_amount("reading_time", 0.5), Ok(()));
+    }
+
+    #[test]
+    fn test_rejects_non_positive() {
+        for amount in [0.0, -3.0, f64::NAN, f64::INFINITY] {
+            assert_eq!(
+                validate_amount("manga", amount),
+                Err(AmountError::NotPositive),
+                "{}",
+                amount
+            );
+        }
+    }
+
+    #[test]
+    fn test_over_limit_suggests_a_type() {
+        let err = validate_amount("anime", 20_000.0).unwrap_err();
+        assert_eq!(
+            err,
+            AmountError::OverLimit {
+                media_type: "anime",
+                limit: 50.0,
+                suggestion: Some("reading"),
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Anime logs are limited to **50 episodes** each. Did you mean Reading (characters)?"
+        );
+        // 120 episodes are more likely 120 minutes
+        assert!(matches!(
+            validate_amount("anime", 120.0),
+            Err(AmountError::OverLimit {
+                suggestion: Some("listening"),
+                ..
+            })
+        ));
+        assert!(matches!(
+            validate_amount("reading", 600_000.0),
+            Err(AmountError::OverLimit {
+                suggestion: None,
+                ..
+            })
+        ));
+    }
+}
@@ -2,22 +2,22 @@
 // Uses charts-rs library for professional quality charts
 
 use ab_glyph::{FontRef, PxScale};
-use charts_rs::{svg_to_png, BarChart, Box as ChartBox, THEME_DARK};
-use chrono::{Datelike, Duration, NaiveDate, Utc};
+use charts_rs::{
+    svg_to_png, BarChart, Box as ChartBox, Canvas, LineChart, Rect, Text, DEFAULT_FONT_FAMILY,
+    THEME_DARK,
+};
+use chrono::{Datelike, Duration, NaiveDate};
 use image::{ImageBuffer, ImageEncoder, Rgba, RgbaImage};
 use imageproc::drawing::draw_text_mut;
 use std::collections::HashMap;
 
+use crate::models::user::UserDoc;
+
 // Embed BOLD font at compile time for heatmap (charts-rs handles its own fonts)
 const FONT_DATA: &[u8] = include_bytes!("../assets/NotoSansJP-Bold.ttf");
 
-// Colors for heatmap (keeping manual implementation for now since charts-rs heatmap is different format)
+// Fixed heatmap colors (keeping manual implementation for now since charts-rs heatmap is different format)
 const BG_COLOR: Rgba<u8> = Rgba([30, 30, 32, 255]);
-const CELL_EMPTY: Rgba<u8> = Rgba([45, 45, 48, 255]);
-const CELL_L1: Rgba<u8> = Rgba([0, 100, 50, 255]);
-const CELL_L2: Rgba<u8> = Rgba([0, 150, 70, 255]);
-const CELL_L3: Rgba<u8> = Rgba([50, 200, 100, 255]);
-const CELL_L4: Rgba<u8> = Rgba([100, 255, 130, 255]);
 const LABEL_COLOR: Rgba<u8> = Rgba([255, 255, 255, 255]);
 const GRAY_COLOR: Rgba<u8> = Rgba([150, 150, 150, 255]);
 const TODAY_BORDER: Rgba<u8> = Rgba([255, 255, 255, 255]);
@@ -30,46 +30,279 @@ const MONTHS: [&str; 12] = [
 // Day labels in Japanese kanji
 const DAYS: [&str; 7] = ["日", "月", "火", "水", "木", "金", "土"];
 
-// Legend colors (from less to more)
-const LEGEND_COLORS: [Rgba<u8>; 6] = [
-    CELL_EMPTY,
-    CELL_L1,
-    CELL_L2,
-    CELL_L3,
-    CELL_L4,
-    Rgba([130, 255, 160, 255]),
-];
+/// Heatmap cell colors: empty cell plus a 5-step ramp from least to most activity
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeatmapPalette {
+    pub empty: Rgba<u8>,
+    pub ramp: [Rgba<u8>; 5],
+}
+
+/// Default green ramp
+pub const GREEN_RAMP: HeatmapPalette = HeatmapPalette {
+    empty: Rgba([45, 45, 48, 255]),
+    ramp: [
+        Rgba([0, 100, 50, 255]),
+        Rgba([0, 150, 70, 255]),
+        Rgba([50, 200, 100, 255]),
+        Rgba([100, 255, 130, 255]),
+        Rgba([130, 255, 160, 255]),
+    ],
+};
+
+/// Blue to orange ramp, distinguishable with red-green color blindness
+pub const COLORBLIND_RAMP: HeatmapPalette = HeatmapPalette {
+    empty: Rgba([45, 45, 48, 255]),
+    ramp: [
+        Rgba([33, 102, 172, 255]),
+        Rgba([103, 169, 207, 255]),
+        Rgba([253, 219, 199, 255]),
+        Rgba([244, 165, 130, 255]),
+        Rgba([230, 97, 1, 255]),
+    ],
+};
+
+/// Heatmap palette for the given color preference
+pub fn heatmap_palette(colorblind: bool) -> HeatmapPalette {
+    if colorblind {
+        COLORBLIND_RAMP
+    } else {
+        GREEN_RAMP
+    }
+}
+
+/// Colors used by the generated charts
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartTheme {
+    pub background: (u8, u8, u8),
+    pub text: (u8, u8, u8),
+    pub grid: (u8, u8, u8),
+    pub series: Vec<(u8, u8, u8)>,
+    pub heatmap: HeatmapPalette,
+}
+
+impl Default for ChartTheme {
+    fn default() -> Self {
+        Self {
+            background: (30, 30, 32),
+            text: (238, 238, 238),
+            grid: (72, 71, 83),
+            series: vec![
+                (0, 191, 255),
+                (46, 204, 113),
+                (255, 165, 0),
+                (231, 76, 60),
+                (155, 89, 182),
+                (241, 196, 15),
+                (26, 188, 156),
+            ],
+            heatmap: GREEN_RAMP,
+        }
+    }
+}
+
+impl ChartTheme {
+    /// Theme for a user document, honoring `preferences.colorblindMode`
+    pub fn for_user(user: &UserDoc) -> Self {
+        let colorblind = user.preference("colorblindMode");
+
+        let mut theme = Self {
+            heatmap: heatmap_palette(colorblind),
+            ..Self::default()
+        };
+        if colorblind {
+            // Okabe-Ito palette for the bar series as well
+            theme.series = vec![
+                (0, 114, 178),
+                (230, 159, 0),
+                (86, 180, 233),
+                (213, 94, 0),
+                (204, 121, 167),
+                (0, 158, 115),
+                (240, 228, 66),
+            ];
+        }
+        theme
+    }
+}
 
 /// Activity level thresholds (points)
-fn get_activity_color(points: i64, max_points: i64) -> Rgba<u8> {
+fn get_activity_color(points: i64, max_points: i64, palette: &HeatmapPalette) -> Rgba<u8> {
     if points == 0 {
-        return CELL_EMPTY;
+        return palette.empty;
     }
     let ratio = points as f64 / max_points.max(1) as f64;
     if ratio > 0.8 {
-        Rgba([130, 255, 160, 255])
+        palette.ramp[4]
     } else if ratio > 0.6 {
-        CELL_L4
+        palette.ramp[3]
     } else if ratio > 0.4 {
-        CELL_L3
+        palette.ramp[2]
     } else if ratio > 0.2 {
-        CELL_L2
+        palette.ramp[1]
     } else {
-        CELL_L1
+        palette.ramp[0]
+    }
+}
+
+/// Days a heatmap covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeatmapRange {
+    /// January to December of one year
+    Year(i32),
+    /// The 365 days ending today
+    Trailing365,
+}
+
+const HEATMAP_COLS: u32 = 53;
+
+/// Where the heatmap's dates land on the grid
+#[derive(Debug, Clone, PartialEq)]
+struct HeatmapLayout {
+    title: String,
+    /// First and last day of the range
+    start: NaiveDate,
+    end: NaiveDate,
+    /// Sunday of the first column
+    grid_start: NaiveDate,
+    /// (column, month index) of each month label
+    month_labels: Vec<(u32, usize)>,
+}
+
+impl HeatmapLayout {
+    fn new(range: HeatmapRange, today: NaiveDate) -> Result<Self, String> {
+        let (title, start, end) = match range {
+            HeatmapRange::Year(year) => (
+                format!("Immersion Heatmap - {}", year),
+                NaiveDate::from_ymd_opt(year, 1, 1).ok_or("Invalid year")?,
+                NaiveDate::from_ymd_opt(year, 12, 31).ok_or("Invalid year")?,
+            ),
+            HeatmapRange::Trailing365 => (
+                "Immersion Heatmap - Last 365 Days".to_string(),
+                today - Duration::days(364),
+                today,
+            ),
+        };
+        let days_since_sunday = start.weekday().num_days_from_sunday();
+        let grid_start = start - Duration::days(days_since_sunday as i64);
+
+        let mut layout = Self {
+            title,
+            start,
+            end,
+            grid_start,
+            month_labels: Vec::new(),
+        };
+        // Each month is labelled above the column holding its 1st
+        let mut first = NaiveDate::from_ymd_opt(start.year(), start.month(), 1).unwrap_or(start);
+        while first <= end {
+            if first >= start {
+                if let Some(col) = layout.column(first) {
+                    layout.month_labels.push((col, first.month0() as usize));
+                }
+            }
+            first = first
+                .checked_add_months(chrono::Months::new(1))
+                .ok_or("Date out of range")?;
+        }
+        // A month cut off at the start gets a label too when there is room for it
+        if start.day() != 1 && layout.month_labels.first().is_none_or(|(col, _)| *col >= 3) {
+            layout.month_labels.insert(0, (0, start.month0() as usize));
+        }
+        Ok(layout)
+    }
+
+    /// Column of a date, `None` when it falls off the grid
+    fn column(&self, date: NaiveDate) -> Option<u32> {
+        let col = (date - self.grid_start).num_days().div_euclid(7);
+        (0..HEATMAP_COLS as i64)
+            .contains(&col)
+            .then_some(col as u32)
+    }
+
+    fn contains(&self, date: NaiveDate) -> bool {
+        (self.start..=self.end).contains(&date)
+    }
+}
+
+/// Numbers under the heatmap, only counting days within the range
+#[derive(Debug, Clone, PartialEq)]
+struct HeatmapStats {
+    days_active: usize,
+    total_points: i64,
+    /// Average points per active day
+    avg_points: f64,
+    /// Best day, what the color ramp is scaled to
+    max_points: i64,
+}
+
+impl HeatmapStats {
+    fn new(daily_points: &HashMap<String, i64>, layout: &HeatmapLayout) -> Self {
+        let in_range: Vec<i64> = daily_points
+            .iter()
+            .filter(|(date, _)| {
+                NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok_and(|d| layout.contains(d))
+            })
+            .map(|(_, &points)| points)
+            .collect();
+        let days_active = in_range.iter().filter(|&&p| p > 0).count();
+        let total_points: i64 = in_range.iter().sum();
+        let avg_points = if days_active > 0 {
+            total_points as f64 / days_active as f64
+        } else {
+            0.0
+        };
+        Self {
+            days_active,
+            total_points,
+            avg_points,
+            max_points: in_range.iter().copied().max().unwrap_or(1),
+        }
+    }
+
+    fn lines(&self) -> [String; 3] {
+        [
+            format!("{} days active", self.days_active),
+            format!("{} total points", self.total_points),
+            format!("{:.1} avg points/day", self.avg_points),
+        ]
     }
 }
 
-/// Generate a GitHub-style heatmap image for user activity
+/// Days with points within the heatmap's range
+pub fn heatmap_active_days(
+    daily_points: &HashMap<String, i64>,
+    range: HeatmapRange,
+    today: NaiveDate,
+) -> Result<usize, String> {
+    let layout = HeatmapLayout::new(range, today)?;
+    Ok(HeatmapStats::new(daily_points, &layout).days_active)
+}
+
+/// Title of a heatmap of one media type, e.g. "Reading Heatmap 2025 - yuki"
+fn media_heatmap_title(media_label: &str, range: HeatmapRange, username: &str) -> String {
+    match range {
+        HeatmapRange::Year(year) => format!("{} Heatmap {} - {}", media_label, year, username),
+        HeatmapRange::Trailing365 => {
+            format!("{} Heatmap (last 365 days) - {}", media_label, username)
+        }
+    }
+}
+
+/// Generate a GitHub-style heatmap image for user activity, highlighting `today`.
+/// `media_label` names the media type the points are limited to, if any.
 /// Returns PNG bytes
 pub fn generate_heatmap(
     daily_points: &HashMap<String, i64>,
-    year: i32,
-    _username: &str,
+    range: HeatmapRange,
+    today: NaiveDate,
+    username: &str,
+    media_label: Option<&str>,
+    theme: &ChartTheme,
 ) -> Result<Vec<u8>, String> {
     // Keep manual implementation for GitHub-style heatmap (charts-rs heatmap is matrix-style)
     const CELL_SIZE: u32 = 14;
     const GAP: u32 = 3;
-    const COLS: u32 = 53;
+    const COLS: u32 = HEATMAP_COLS;
     const ROWS: u32 = 7;
     const PADDING_LEFT: u32 = 40;
     const PADDING_TOP: u32 = 65;
@@ -84,93 +317,69 @@ pub fn generate_heatmap(
     let font =
         FontRef::try_from_slice(FONT_DATA).map_err(|e| format!("Failed to load font: {:?}", e))?;
 
-    let max_points = daily_points.values().copied().max().unwrap_or(1);
-    let days_active = daily_points.values().filter(|&&p| p > 0).count();
-    let total_points: i64 = daily_points.values().sum();
-    let avg_points = if days_active > 0 {
-        total_points as f64 / days_active as f64
-    } else {
-        0.0
+    let layout = HeatmapLayout::new(range, today)?;
+    let stats = HeatmapStats::new(daily_points, &layout);
+    let title = match media_label {
+        Some(label) => media_heatmap_title(label, range, username),
+        None => layout.title.clone(),
     };
 
-    let today = Utc::now().format("%Y-%m-%d").to_string();
-
     // Draw title
-    let title = format!("Immersion Heatmap - {}", year);
     let title_scale = PxScale::from(18.0);
     draw_text_mut(&mut img, LABEL_COLOR, 15, 12, title_scale, &font, &title);
 
-    let start_date = NaiveDate::from_ymd_opt(year, 1, 1).ok_or("Invalid year")?;
-    let end_date = NaiveDate::from_ymd_opt(year, 12, 31).ok_or("Invalid year")?;
-    let days_since_sunday = start_date.weekday().num_days_from_sunday();
-    let grid_start = start_date - Duration::days(days_since_sunday as i64);
-
-    let mut month_cols: [Option<u32>; 12] = [None; 12];
-    let mut current_date = grid_start;
-    let mut col = 0;
-    let mut row;
-
-    while current_date <= end_date && col < COLS {
-        row = current_date.weekday().num_days_from_sunday() as u32;
-
-        if current_date.year() == year {
-            let month_idx = (current_date.month() - 1) as usize;
-            if month_cols[month_idx].is_none() {
-                month_cols[month_idx] = Some(col);
-            }
-        }
-
-        if current_date.year() == year || (current_date < start_date && col == 0) {
-            let date_str = current_date.format("%Y-%m-%d").to_string();
-            let points = daily_points.get(&date_str).copied().unwrap_or(0);
-            let color = get_activity_color(points, max_points);
-
-            let x = PADDING_LEFT + col * (CELL_SIZE + GAP);
-            let y = PADDING_TOP + row * (CELL_SIZE + GAP);
-            let is_today = date_str == today;
-
-            if is_today {
-                for dx in 0..CELL_SIZE + 2 {
-                    for dy in 0..CELL_SIZE + 2 {
-                        let px = x.saturating_sub(1) + dx;
-                        let py = y.saturating_sub(1) + dy;
-                        if px < width && py < height {
-                            img.put_pixel(px, py, TODAY_BORDER);
-                        }
+    // The days before the range in the first column are drawn too, so it isn't ragged
+    let mut current_date = layout.grid_start;
+    while current_date <= layout.end {
+        let Some(col) = layout.column(current_date) else {
+            break;
+        };
+        let row = current_date.weekday().num_days_from_sunday();
+
+        let date_str = current_date.format("%Y-%m-%d").to_string();
+        let points = daily_points.get(&date_str).copied().unwrap_or(0);
+        let color = get_activity_color(points, stats.max_points, &theme.heatmap);
+
+        let x = PADDING_LEFT + col * (CELL_SIZE + GAP);
+        let y = PADDING_TOP + row * (CELL_SIZE + GAP);
+        let is_today = current_date == today;
+
+        if is_today {
+            for dx in 0..CELL_SIZE + 2 {
+                for dy in 0..CELL_SIZE + 2 {
+                    let px = x.saturating_sub(1) + dx;
+                    let py = y.saturating_sub(1) + dy;
+                    if px < width && py < height {
+                        img.put_pixel(px, py, TODAY_BORDER);
                     }
                 }
             }
+        }
 
-            for dx in 0..CELL_SIZE {
-                for dy in 0..CELL_SIZE {
-                    if x + dx < width && y + dy < height {
-                        img.put_pixel(x + dx, y + dy, color);
-                    }
+        for dx in 0..CELL_SIZE {
+            for dy in 0..CELL_SIZE {
+                if x + dx < width && y + dy < height {
+                    img.put_pixel(x + dx, y + dy, color);
                 }
             }
         }
 
-        current_date = current_date + Duration::days(1);
-        if current_date.weekday().num_days_from_sunday() == 0 {
-            col += 1;
-        }
+        current_date += Duration::days(1);
     }
 
     // Draw month labels
     let month_scale = PxScale::from(13.0);
-    for (month_idx, maybe_col) in month_cols.iter().enumerate() {
-        if let Some(col) = maybe_col {
-            let x = PADDING_LEFT + col * (CELL_SIZE + GAP);
-            draw_text_mut(
-                &mut img,
-                LABEL_COLOR,
-                x as i32,
-                42,
-                month_scale,
-                &font,
-                MONTHS[month_idx],
-            );
-        }
+    for &(col, month_idx) in &layout.month_labels {
+        let x = PADDING_LEFT + col * (CELL_SIZE + GAP);
+        draw_text_mut(
+            &mut img,
+            LABEL_COLOR,
+            x as i32,
+            42,
+            month_scale,
+            &font,
+            MONTHS[month_idx],
+        );
     }
 
     // Draw day labels
@@ -195,11 +404,12 @@ pub fn generate_heatmap(
         &font,
         "Less",
     );
-    for (i, color) in LEGEND_COLORS.iter().enumerate() {
+    let legend_colors = std::iter::once(theme.heatmap.empty).chain(theme.heatmap.ramp);
+    for (i, color) in legend_colors.enumerate() {
         let box_x = legend_x + 35 + (i as u32) * 18;
         for dx in 0..14 {
             for dy in 0..14 {
-                img.put_pixel(box_x + dx, legend_y + dy, *color);
+                img.put_pixel(box_x + dx, legend_y + dy, color);
             }
         }
     }
@@ -218,33 +428,17 @@ pub fn generate_heatmap(
     let stats_x = heatmap_right_edge - 150;
     let stats_y_base = height - 30;
     let stats_scale = PxScale::from(12.0);
-    draw_text_mut(
-        &mut img,
-        GRAY_COLOR,
-        stats_x as i32,
-        (stats_y_base - 30) as i32,
-        stats_scale,
-        &font,
-        &format!("{} days active", days_active),
-    );
-    draw_text_mut(
-        &mut img,
-        GRAY_COLOR,
-        stats_x as i32,
-        (stats_y_base - 15) as i32,
-        stats_scale,
-        &font,
-        &format!("{} total points", total_points),
-    );
-    draw_text_mut(
-        &mut img,
-        GRAY_COLOR,
-        stats_x as i32,
-        stats_y_base as i32,
-        stats_scale,
-        &font,
-        &format!("{:.1} avg points/day", avg_points),
-    );
+    for (i, line) in stats.lines().iter().enumerate() {
+        draw_text_mut(
+            &mut img,
+            GRAY_COLOR,
+            stats_x as i32,
+            (stats_y_base - 30 + 15 * i as u32) as i32,
+            stats_scale,
+            &font,
+            line,
+        );
+    }
 
     // Encode to PNG
     let mut png_bytes: Vec<u8> = Vec::new();
@@ -272,6 +466,7 @@ pub fn generate_bar_chart(
     data: &[BarData],
     title: &str,
     _value_label: &str,
+    theme: &ChartTheme,
 ) -> Result<Vec<u8>, String> {
     if data.is_empty() {
         return Err("No data to chart".to_string());
@@ -310,6 +505,16 @@ pub fn generate_bar_chart(
         right: 10.0,
     });
 
+    // Apply theme colors on top of the dark base theme
+    bar_chart.background_color = theme.background.into();
+    bar_chart.title_font_color = theme.text.into();
+    bar_chart.legend_font_color = theme.text.into();
+    bar_chart.x_axis_font_color = theme.text.into();
+    bar_chart.series_label_font_color = theme.text.into();
+    bar_chart.grid_stroke_color = theme.grid.into();
+    bar_chart.x_axis_stroke_color = theme.grid.into();
+    bar_chart.series_colors = theme.series.iter().map(|&c| c.into()).collect();
+
     // Generate SVG then convert to PNG
     let svg = bar_chart
         .svg()
@@ -319,3 +524,411 @@ pub fn generate_bar_chart(
 
     Ok(png_data)
 }
+
+/// Line chart of one value per date, using charts-rs
+/// Returns PNG bytes
+pub fn generate_line_chart(
+    points: &[(NaiveDate, f64)],
+    title: &str,
+    series_name: &str,
+    theme: &ChartTheme,
+) -> Result<Vec<u8>, String> {
+    if points.is_empty() {
+        return Err("No data to chart".to_string());
+    }
+
+    let values: Vec<f32> = points.iter().map(|(_, v)| *v as f32).collect();
+    let dates: Vec<String> = points
+        .iter()
+        .map(|(date, _)| date.format("%m-%d").to_string())
+        .collect();
+
+    let mut line_chart =
+        LineChart::new_with_theme(vec![(series_name, values).into()], dates, THEME_DARK);
+
+    line_chart.width = 800.0;
+    line_chart.height = 450.0;
+    line_chart.title_text = title.to_string();
+    line_chart.title_font_size = 24.0;
+    line_chart.legend_show = Some(false);
+    line_chart.series_smooth = true;
+
+    line_chart.background_color = theme.background.into();
+    line_chart.title_font_color = theme.text.into();
+    line_chart.x_axis_font_color = theme.text.into();
+    line_chart.series_label_font_color = theme.text.into();
+    line_chart.grid_stroke_color = theme.grid.into();
+    line_chart.x_axis_stroke_color = theme.grid.into();
+    for y_axis in &mut line_chart.y_axis_configs {
+        y_axis.axis_font_color = theme.text.into();
+    }
+    line_chart.series_colors = theme.series.iter().map(|&c| c.into()).collect();
+
+    let svg = line_chart
+        .svg()
+        .map_err(|e| format!("SVG generation failed: {:?}", e))?;
+
+    svg_to_png(&svg).map_err(|e| format!("PNG conversion failed: {:?}", e))
+}
+
+/// Round a chart's top value up to 1, 2 or 5 times a power of ten per grid step
+fn nice_axis_max(max: f64, steps: u32) -> f64 {
+    if max <= 0.0 {
+        return steps as f64;
+    }
+    let raw_step = max / steps as f64;
+    let magnitude = 10f64.powf(raw_step.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|m| m * magnitude)
+        .find(|step| *step >= raw_step)
+        .unwrap_or(10.0 * magnitude);
+    step * steps as f64
+}
+
+/// Stacked bar chart with one bar per day and one segment per series, drawn on a
+/// charts-rs canvas (its bar chart can't stack).
+///
+/// Each series needs one value per day. A series keeps the color of its position even
+/// when it is left out of the chart for having no points, so callers passing the same
+/// series order get the same colors every time. Days without points stay as gaps.
+/// Returns PNG bytes
+pub fn generate_stacked_bar_chart(
+    days: &[NaiveDate],
+    series: &[(String, Vec<f64>)],
+    title: &str,
+    theme: &ChartTheme,
+) -> Result<Vec<u8>, String> {
+    const WIDTH: f32 = 800.0;
+    const HEIGHT: f32 = 450.0;
+    const PLOT_LEFT: f32 = 60.0;
+    const PLOT_RIGHT: f32 = 780.0;
+    const PLOT_TOP: f32 = 60.0;
+    const PLOT_BOTTOM: f32 = 370.0;
+    const GRID_STEPS: u32 = 4;
+
+    if days.is_empty() || series.iter().any(|(_, values)| values.len() != days.len()) {
+        return Err("Every series needs one value per day".to_string());
+    }
+    let day_totals: Vec<f64> = (0..days.len())
+        .map(|i| series.iter().map(|(_, values)| values[i]).sum())
+        .collect();
+    let max = day_totals.iter().cloned().fold(0.0, f64::max);
+    if max <= 0.0 {
+        return Err("No data to chart".to_string());
+    }
+    let axis_max = nice_axis_max(max, GRID_STEPS);
+    let scale = (PLOT_BOTTOM - PLOT_TOP) / axis_max as f32;
+    let color = |i: usize| theme.series[i % theme.series.len()];
+    let text = |text: String, x: f32, y: f32, size: f32, anchor: &str| Text {
+        text,
+        font_family: Some(DEFAULT_FONT_FAMILY.to_string()),
+        font_size: Some(size),
+        font_color: Some(theme.text.into()),
+        x: Some(x),
+        y: Some(y),
+        text_anchor: Some(anchor.to_string()),
+        ..Default::default()
+    };
+
+    let mut canvas = Canvas::new(WIDTH, HEIGHT);
+    canvas.rect(Rect {
+        fill: Some(theme.background.into()),
+        width: WIDTH,
+        height: HEIGHT,
+        ..Default::default()
+    });
+    canvas.text(text(title.to_string(), WIDTH / 2.0, 35.0, 22.0, "middle"));
+
+    // Horizontal grid with the value of each line
+    for step in 0..=GRID_STEPS {
+        let value = axis_max / GRID_STEPS as f64 * step as f64;
+        let y = PLOT_BOTTOM - value as f32 * scale;
+        canvas.line(charts_rs::Line {
+            color: Some(theme.grid.into()),
+            stroke_width: 1.0,
+            left: PLOT_LEFT,
+            top: y,
+            right: PLOT_RIGHT,
+            bottom: y,
+            ..Default::default()
+        });
+        canvas.text(text(
+            format!("{}", value.round() as i64),
+            PLOT_LEFT - 8.0,
+            y + 4.0,
+            12.0,
+            "end",
+        ));
+    }
+
+    // Bars, first series at the bottom
+    let slot = (PLOT_RIGHT - PLOT_LEFT) / days.len() as f32;
+    let bar_width = slot * 0.6;
+    // Roughly one date label every 50px
+    let label_every = (50.0 / slot).ceil().max(1.0) as usize;
+    for (i, day) in days.iter().enumerate() {
+        let left = PLOT_LEFT + slot * i as f32 + (slot - bar_width) / 2.0;
+        let mut top = PLOT_BOTTOM;
+        for (s, (_, values)) in series.iter().enumerate() {
+            let height = values[i] as f32 * scale;
+            if height <= 0.0 {
+                continue;
+            }
+            top -= height;
+            canvas.rect(Rect {
+                fill: Some(color(s).into()),
+                left,
+                top,
+                width: bar_width,
+                height,
+                ..Default::default()
+            });
+        }
+        let last = i + 1 == days.len();
+        if (days.len() - 1 - i).is_multiple_of(label_every) || last {
+            canvas.text(text(
+                day.format("%m-%d").to_string(),
+                left + bar_width / 2.0,
+                PLOT_BOTTOM + 18.0,
+                12.0,
+                "middle",
+            ));
+        }
+    }
+
+    // Legend of the series that made it into the chart
+    let mut x = PLOT_LEFT;
+    for (s, (name, values)) in series.iter().enumerate() {
+        if values.iter().all(|v| *v <= 0.0) {
+            continue;
+        }
+        canvas.rect(Rect {
+            fill: Some(color(s).into()),
+            left: x,
+            top: 405.0,
+            width: 14.0,
+            height: 14.0,
+            rx: Some(2.0),
+            ry: Some(2.0),
+            ..Default::default()
+        });
+        canvas.text(text(name.clone(), x + 20.0, 417.0, 13.0, "start"));
+        x += 20.0 + name.chars().count() as f32 * 8.0 + 18.0;
+    }
+
+    let svg = canvas
+        .svg()
+        .map_err(|e| format!("SVG generation failed: {:?}", e))?;
+
+    svg_to_png(&svg).map_err(|e| format!("PNG conversion failed: {:?}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_colorblind_preference_uses_colorblind_ramp() {
+        let user: UserDoc =
+            serde_json::from_value(json!({ "preferences": { "colorblindMode": true } })).unwrap();
+        let theme = ChartTheme::for_user(&user);
+        assert_eq!(theme.heatmap, COLORBLIND_RAMP);
+
+        // Busiest day maps to the top of the orange end of the ramp
+        assert_eq!(
+            get_activity_color(100, 100, &theme.heatmap),
+            COLORBLIND_RAMP.ramp[4]
+        );
+        assert_eq!(
+            get_activity_color(10, 100, &theme.heatmap),
+            COLORBLIND_RAMP.ramp[0]
+        );
+    }
+
+    #[test]
+    fn test_default_theme_uses_green_ramp() {
+        let user: UserDoc = serde_json::from_value(json!({ "preferences": {} })).unwrap();
+        let theme = ChartTheme::for_user(&user);
+        assert_eq!(theme.heatmap, GREEN_RAMP);
+        assert_eq!(get_activity_color(0, 100, &theme.heatmap), GREEN_RAMP.empty);
+        assert_eq!(
+            get_activity_color(50, 100, &theme.heatmap),
+            GREEN_RAMP.ramp[2]
+        );
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_trailing_layout_ends_on_today() {
+        let today = date(2025, 1, 15);
+        let layout = HeatmapLayout::new(HeatmapRange::Trailing365, today).unwrap();
+
+        assert_eq!(layout.start, date(2024, 1, 17));
+        assert_eq!(layout.end, today);
+        // Sunday on or before the start
+        assert_eq!(layout.grid_start, date(2024, 1, 14));
+        assert_eq!(layout.column(today), Some(HEATMAP_COLS - 1));
+
+        // January 2024 is cut off and too close to February for its own label
+        assert_eq!(layout.month_labels.len(), 12);
+        assert_eq!(layout.month_labels[0], (2, 1));
+        assert_eq!(layout.month_labels[11], (50, 0));
+    }
+
+    #[test]
+    fn test_year_layout_is_unchanged() {
+        let layout = HeatmapLayout::new(HeatmapRange::Year(2025), date(2025, 1, 15)).unwrap();
+        assert_eq!(layout.title, "Immersion Heatmap - 2025");
+        assert_eq!(layout.grid_start, date(2024, 12, 29));
+        assert_eq!(layout.column(date(2025, 12, 31)), Some(52));
+        let months: Vec<usize> = layout.month_labels.iter().map(|(_, m)| *m).collect();
+        assert_eq!(months, (0..12).collect::<Vec<_>>());
+        assert_eq!(layout.month_labels[0], (0, 0));
+    }
+
+    #[test]
+    fn test_stats_only_count_the_range() {
+        let layout = HeatmapLayout::new(HeatmapRange::Trailing365, date(2025, 1, 15)).unwrap();
+        let points: HashMap<String, i64> = [
+            ("2025-01-15", 30),
+            ("2024-06-01", 10),
+            ("2024-01-16", 500),
+            ("2025-01-16", 500),
+        ]
+        .into_iter()
+        .map(|(d, p)| (d.to_string(), p))
+        .collect();
+
+        let stats = HeatmapStats::new(&points, &layout);
+        assert_eq!(stats.max_points, 30);
+        assert_eq!(
+            stats.lines(),
+            [
+                "2 days active".to_string(),
+                "40 total points".to_string(),
+                "20.0 avg points/day".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_heatmap_dimensions_and_today_highlight() {
+        let today = date(2025, 1, 15);
+        let png = generate_heatmap(
+            &HashMap::new(),
+            HeatmapRange::Trailing365,
+            today,
+            "yuki",
+            None,
+            &ChartTheme::default(),
+        )
+        .unwrap();
+        let img = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(img.dimensions(), (53 * 17 + 70, 7 * 17 + 140));
+
+        // Last column, Wednesday row: border around, empty cell inside
+        let (x, y) = (40 + 52 * 17, 65 + 3 * 17);
+        assert_eq!(*img.get_pixel(x - 1, y - 1), TODAY_BORDER);
+        assert_eq!(*img.get_pixel(x + 5, y + 5), GREEN_RAMP.empty);
+        // No cells after today
+        assert_eq!(*img.get_pixel(x + 5, y + 17 + 5), BG_COLOR);
+    }
+
+    #[test]
+    fn test_media_heatmap() {
+        let today = date(2025, 1, 15);
+        assert_eq!(
+            media_heatmap_title("Reading", HeatmapRange::Year(2025), "yuki"),
+            "Reading Heatmap 2025 - yuki"
+        );
+        let points: HashMap<String, i64> = [("2024-12-31", 10), ("2025-01-02", 0)]
+            .into_iter()
+            .map(|(d, p)| (d.to_string(), p))
+            .collect();
+        assert_eq!(
+            heatmap_active_days(&points, HeatmapRange::Year(2025), today),
+            Ok(0)
+        );
+        assert_eq!(
+            heatmap_active_days(&points, HeatmapRange::Trailing365, today),
+            Ok(1)
+        );
+
+        // Only the title differs from the unfiltered image
+        let render = |label| {
+            generate_heatmap(
+                &points,
+                HeatmapRange::Trailing365,
+                today,
+                "yuki",
+                label,
+                &ChartTheme::default(),
+            )
+            .map(|png| image::load_from_memory(&png).unwrap().to_rgba8())
+            .unwrap()
+        };
+        let (all, reading) = (render(None), render(Some("Reading")));
+        assert_ne!(all, reading);
+        let below_title = |img: &RgbaImage| {
+            img.enumerate_pixels()
+                .filter(|(_, y, _)| *y >= 40)
+                .map(|(_, _, p)| *p)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(below_title(&all), below_title(&reading));
+    }
+
+    #[test]
+    fn test_line_chart_renders() {
+        let points = vec![(date(2025, 1, 10), 300.0), (date(2025, 1, 12), 320.5)];
+        let png = generate_line_chart(
+            &points,
+            "Reading Speed",
+            "chars/min",
+            &ChartTheme::default(),
+        )
+        .unwrap();
+        let img = image::load_from_memory(&png).unwrap();
+        assert_eq!((img.width(), img.height()), (800, 450));
+        assert!(
+            generate_line_chart(&[], "Reading Speed", "chars/min", &ChartTheme::default()).is_err()
+        );
+    }
+
+    #[test]
+    fn test_nice_axis_max() {
+        assert_eq!(nice_axis_max(37.0, 4), 40.0);
+        assert_eq!(nice_axis_max(130.0, 4), 200.0);
+        assert_eq!(nice_axis_max(4.0, 4), 4.0);
+        assert_eq!(nice_axis_max(0.0, 4), 4.0);
+    }
+
+    #[test]
+    fn test_stacked_bar_chart_renders_with_gaps() {
+        let days: Vec<NaiveDate> = (10..=16).map(|d| date(2025, 1, d)).collect();
+        let series = vec![
+            (
+                "Anime".to_string(),
+                vec![10.0, 0.0, 0.0, 20.0, 5.0, 0.0, 0.0],
+            ),
+            ("Manga".to_string(), vec![0.0; 7]),
+            (
+                "Reading".to_string(),
+                vec![3.0, 0.0, 0.0, 0.0, 40.0, 0.0, 1.0],
+            ),
+        ];
+        let theme = ChartTheme::default();
+        let png = generate_stacked_bar_chart(&days, &series, "Daily Mix", &theme).unwrap();
+        let img = image::load_from_memory(&png).unwrap();
+        assert_eq!((img.width(), img.height()), (800, 450));
+
+        assert!(generate_stacked_bar_chart(&days, &series[1..2], "Daily Mix", &theme).is_err());
+        let short = vec![("Anime".to_string(), vec![1.0])];
+        assert!(generate_stacked_bar_chart(&days, &short, "Daily Mix", &theme).is_err());
+    }
+}